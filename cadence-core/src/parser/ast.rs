@@ -1,7 +1,7 @@
 use crate::types::{
     chord::Chord,
     note::Note,
-    pattern::{EveryPattern, Pattern},
+    pattern::{EveryPattern, Pattern, ProbabilityPattern},
 };
 use std::fmt;
 
@@ -153,6 +153,39 @@ impl Default for SpannedProgram {
     }
 }
 
+/// A MIDI input binding: what to do when a controller sends a message
+#[derive(Debug, Clone, PartialEq)]
+pub enum MidiBinding {
+    /// bind note 36 { ... } - run statements when the note is pressed
+    Note { note: u8, body: Vec<Statement> },
+    /// bind cc 1 to tempo range 60 180 - map controller values onto a parameter
+    Control {
+        controller: u8,
+        /// "tempo" or "volume"
+        parameter: String,
+        min: i32,
+        max: i32,
+    },
+}
+
+impl fmt::Display for MidiBinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MidiBinding::Note { note, .. } => write!(f, "bind note {} {{ ... }}", note),
+            MidiBinding::Control {
+                controller,
+                parameter,
+                min,
+                max,
+            } => write!(
+                f,
+                "bind cc {} to {} range {} {}",
+                controller, parameter, min, max
+            ),
+        }
+    }
+}
+
 /// Statement types for scripting
 #[derive(Debug, Clone, PartialEq)]
 pub enum Statement {
@@ -162,6 +195,13 @@ pub enum Statement {
     /// Variable re-assignment: prog = other_expr
     Assign { name: String, value: Expression },
 
+    /// Indexed re-assignment: p[1] = D
+    IndexAssign {
+        name: String,
+        index: Expression,
+        value: Expression,
+    },
+
     /// Expression statement (evaluates and optionally prints): [C, E, G]
     Expression(Expression),
 
@@ -183,9 +223,28 @@ pub enum Statement {
     /// Set volume: volume 0.5 or volume x
     Volume(Expression),
 
+    /// Global capo: capo 2 (semitones added to all sounding notes; notation is unchanged)
+    Capo(Expression),
+
     /// Set waveform: waveform "sine"
     Waveform(String),
 
+    /// MIDI CC automation: cc 74 "0 32 64 127" (add `smooth` to interpolate)
+    ControlChange {
+        controller: u8,
+        target: Expression,
+        smooth: bool,
+    },
+
+    /// MIDI program change on the current track: midi program 12
+    MidiProgram(u8),
+
+    /// MIDI bank select (CC 0 / CC 32) on the current track: midi bank 1 0
+    MidiBank { msb: u8, lsb: u8 },
+
+    /// MIDI velocity curve on the current track: midi vel_curve soft
+    MidiVelocityCurve(String),
+
     /// Infinite loop: loop { ... }
     Loop { body: Vec<Statement> },
 
@@ -200,6 +259,12 @@ pub enum Statement {
         body: Vec<Statement>,
     },
 
+    /// Conditional loop: while condition { ... }
+    While {
+        condition: Expression,
+        body: Vec<Statement>,
+    },
+
     /// Conditional: if condition { ... } else { ... }
     If {
         condition: Expression,
@@ -235,11 +300,19 @@ pub enum Statement {
         params: Vec<String>,
         body: Vec<Statement>,
         return_type: Option<String>,
+        /// Doc comment from the `///` lines above a top-level definition
+        doc: Option<String>,
     },
 
     /// Wait statement: wait <beats> (advances virtual time)
     Wait { beats: Expression },
 
+    /// MIDI input binding: bind note 36 { ... } or bind cc 1 to tempo range 60 180
+    Bind(MidiBinding),
+
+    /// REPL command alias: alias "drop" = "stop 1; on 3 play kick loop"
+    Alias { name: String, source: String },
+
     /// Use/import module: use "path" or use { a, b } from "path" as ns
     Use {
         /// Path to the module file
@@ -249,6 +322,16 @@ pub enum Statement {
         /// Optional namespace alias
         alias: Option<String>,
     },
+
+    /// Strict mode: `use strict` makes builtin calls need the `std.` prefix
+    UseStrict,
+}
+
+/// Escape a string so it reads back as the same string literal
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
 }
 
 impl fmt::Display for Statement {
@@ -256,6 +339,9 @@ impl fmt::Display for Statement {
         match self {
             Statement::Let { name, value } => write!(f, "let {} = {}", name, value),
             Statement::Assign { name, value } => write!(f, "{} = {}", name, value),
+            Statement::IndexAssign { name, index, value } => {
+                write!(f, "{}[{}] = {}", name, index, value)
+            }
             Statement::Expression(expr) => write!(f, "{}", expr),
             Statement::Play {
                 target,
@@ -278,12 +364,28 @@ impl fmt::Display for Statement {
             Statement::Stop => write!(f, "stop"),
             Statement::Tempo(bpm) => write!(f, "tempo {}", bpm),
             Statement::Volume(vol) => write!(f, "volume {}", vol),
+            Statement::Capo(semitones) => write!(f, "capo {}", semitones),
             Statement::Waveform(name) => write!(f, "waveform \"{}\"", name),
+            Statement::ControlChange {
+                controller,
+                target,
+                smooth,
+            } => {
+                write!(f, "cc {} {}", controller, target)?;
+                if *smooth {
+                    write!(f, " smooth")?;
+                }
+                Ok(())
+            }
+            Statement::MidiProgram(program) => write!(f, "midi program {}", program),
+            Statement::MidiBank { msb, lsb } => write!(f, "midi bank {} {}", msb, lsb),
+            Statement::MidiVelocityCurve(curve) => write!(f, "midi vel_curve {}", curve),
             Statement::Loop { .. } => write!(f, "loop {{ ... }}"),
             Statement::Repeat { count, .. } => write!(f, "repeat {} {{ ... }}", count),
             Statement::For {
                 var, start, end, ..
             } => write!(f, "for {} in {}..{} {{ ... }}", var, start, end),
+            Statement::While { condition, .. } => write!(f, "while {} {{ ... }}", condition),
             Statement::If { .. } => write!(f, "if ... {{ ... }}"),
             Statement::Break => write!(f, "break"),
             Statement::Continue => write!(f, "continue"),
@@ -297,6 +399,11 @@ impl fmt::Display for Statement {
                 write!(f, "fn {}({}) {{ ... }}", name, params.join(", "))
             }
             Statement::Wait { beats } => write!(f, "wait {}", beats),
+            Statement::Bind(binding) => write!(f, "{}", binding),
+            Statement::Alias { name, source } => {
+                write!(f, "alias \"{}\" = \"{}\"", escape(name), escape(source))
+            }
+            Statement::UseStrict => write!(f, "use strict"),
             Statement::Use {
                 path,
                 imports,
@@ -391,6 +498,9 @@ pub enum Expression {
     /// Numeric literal: 20, 100, etc.
     Number(i32),
 
+    /// Decimal literal: 1.5, 120.5
+    Float(f32),
+
     /// Pre-evaluated value (for dynamic function dispatch)
     /// Used when we need to pass an already-evaluated Value back through as an Expression
     Value(Box<Value>),
@@ -420,6 +530,13 @@ pub enum Expression {
         index: Box<Expression>,
     },
 
+    /// Range inside index brackets: the `1..3` in pattern[1..3]
+    /// End is exclusive; negative bounds count from the end
+    Range {
+        start: Box<Expression>,
+        end: Box<Expression>,
+    },
+
     /// Binary arithmetic operation: expr + expr, expr * expr, etc.
     BinaryOp {
         left: Box<Expression>,
@@ -451,18 +568,28 @@ pub enum ArithmeticOp {
 
 /// Represents the result of evaluating an expression
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(tag = "type", content = "value", rename_all = "snake_case")
+)]
 pub enum Value {
     Note(Note),
     Chord(Chord),
     Boolean(bool),
     Pattern(Pattern),
     Number(i32),
+    /// Decimal number, e.g. the factor in `fast(p, 1.5)`
+    Float(f32),
     String(String),
     /// User-defined function
+    #[cfg_attr(feature = "serde", serde(skip))]
     Function {
         name: String,
         params: Vec<String>,
         body: Vec<Statement>,
+        /// The `-> Type` annotation, checked against the returned value
+        return_type: Option<String>,
     },
     /// Unit value (void) - for functions that don't return anything
     Unit,
@@ -471,8 +598,12 @@ pub enum Value {
     /// Pattern combinator that applies a transformation every N cycles
     /// Used for TidalCycles-style `every(2, rev, pattern)` alternation
     EveryPattern(Box<EveryPattern>),
+    /// Pattern combinator that applies a transformation on a random share of cycles
+    /// Used for TidalCycles-style `sometimes(rev, pattern)`
+    ProbabilityPattern(Box<ProbabilityPattern>),
     /// Lazy/thunked expression - evaluated on each access
     /// Used for TidalCycles-style reactive variables
+    #[cfg_attr(feature = "serde", serde(skip))]
     Thunk {
         expression: Box<Expression>,
         /// Environment captured at definition time (for closures)
@@ -489,22 +620,26 @@ impl PartialEq for Value {
             (Value::Boolean(a), Value::Boolean(b)) => a == b,
             (Value::Pattern(a), Value::Pattern(b)) => a == b,
             (Value::Number(a), Value::Number(b)) => a == b,
+            (Value::Float(a), Value::Float(b)) => a == b,
             (Value::String(a), Value::String(b)) => a == b,
             (
                 Value::Function {
                     name: n1,
                     params: p1,
                     body: b1,
+                    return_type: r1,
                 },
                 Value::Function {
                     name: n2,
                     params: p2,
                     body: b2,
+                    return_type: r2,
                 },
-            ) => n1 == n2 && p1 == p2 && b1 == b2,
+            ) => n1 == n2 && p1 == p2 && b1 == b2 && r1 == r2,
             (Value::Unit, Value::Unit) => true,
             (Value::Array(a), Value::Array(b)) => a == b,
             (Value::EveryPattern(a), Value::EveryPattern(b)) => a == b,
+            (Value::ProbabilityPattern(a), Value::ProbabilityPattern(b)) => a == b,
             // For thunks, compare only the expression (env identity doesn't matter for equality)
             (Value::Thunk { expression: e1, .. }, Value::Thunk { expression: e2, .. }) => e1 == e2,
             _ => false,
@@ -561,9 +696,10 @@ impl fmt::Display for Expression {
                 };
                 write!(f, "{} {} {}", left, op_str, right)
             }
-            Expression::Pattern(pattern) => write!(f, "{}", pattern),
+            Expression::Pattern(pattern) => fmt::Display::fmt(pattern, f),
             Expression::String(s) => write!(f, "\"{}\"", s),
             Expression::Number(n) => write!(f, "{}", n),
+            Expression::Float(n) => write!(f, "{}", n),
             Expression::Value(v) => write!(f, "{}", v),
             Expression::Array(elements) => {
                 write!(f, "[")?;
@@ -587,6 +723,9 @@ impl fmt::Display for Expression {
             Expression::Index { target, index } => {
                 write!(f, "{}[{}]", target, index)
             }
+            Expression::Range { start, end } => {
+                write!(f, "{}..{}", start, end)
+            }
             Expression::BinaryOp {
                 left,
                 right,
@@ -605,7 +744,7 @@ impl fmt::Display for Expression {
     }
 }
 
-use crate::types::DrumSound;
+use crate::types::{ControlPattern, DrumSound, PatternStep, DEFAULT_VELOCITY};
 
 /// Playback info extracted from a Value - frequencies, duration, and optional drums
 #[derive(Debug, Clone)]
@@ -616,6 +755,8 @@ pub struct PlaybackInfo {
     pub duration_beats: f32,
     /// Drum sounds to trigger
     pub drums: Vec<DrumSound>,
+    /// MIDI velocity (0-127) for each frequency
+    pub velocities: Vec<u8>,
 }
 
 impl Value {
@@ -630,25 +771,31 @@ impl Value {
                 frequencies: vec![note.frequency()],
                 duration_beats: 1.0,
                 drums: vec![],
+                velocities: vec![DEFAULT_VELOCITY],
             }]),
             Value::Chord(chord) => Ok(vec![PlaybackInfo {
                 frequencies: chord.notes_vec().iter().map(|n| n.frequency()).collect(),
                 duration_beats: 1.0,
                 drums: vec![],
+                velocities: vec![DEFAULT_VELOCITY; chord.len()],
             }]),
             Value::Pattern(pattern) => {
                 // Convert pattern to playback info with per-event durations
                 Ok(pattern
                     .to_rich_events()
                     .into_iter()
-                    .map(|event| PlaybackInfo {
-                        frequencies: if event.is_rest {
-                            vec![]
+                    .map(|event| {
+                        let notes = if event.is_rest {
+                            &[][..]
                         } else {
-                            event.notes.iter().map(|n| n.frequency).collect()
-                        },
-                        duration_beats: event.duration_f32(),
-                        drums: event.drums,
+                            &event.notes[..]
+                        };
+                        PlaybackInfo {
+                            frequencies: notes.iter().map(|n| n.frequency).collect(),
+                            duration_beats: event.duration_f32(),
+                            velocities: notes.iter().map(|n| n.velocity).collect(),
+                            drums: event.drums,
+                        }
                     })
                     .collect())
             }
@@ -661,7 +808,7 @@ impl Value {
                 }
             }
             Value::Boolean(_) => Err("Cannot play a boolean value".to_string()),
-            Value::Number(_) => Err("Cannot play a raw number".to_string()),
+            Value::Number(_) | Value::Float(_) => Err("Cannot play a raw number".to_string()),
             Value::Function { name, .. } => {
                 Err(format!("Cannot play a function '{}' - call it first", name))
             }
@@ -672,6 +819,9 @@ impl Value {
                 // The real cycle selection happens in the playback engine
                 Value::Pattern(every.base.clone()).to_playback_info()
             }
+            Value::ProbabilityPattern(sometimes) => {
+                Value::Pattern(sometimes.base.clone()).to_playback_info()
+            }
             Value::Thunk { .. } => {
                 Err("Cannot play a thunk directly - it should have been evaluated".to_string())
             }
@@ -679,14 +829,174 @@ impl Value {
     }
 }
 
+#[cfg(feature = "serde")]
+impl Value {
+    /// Serialize this value as JSON, e.g. `{"type":"note","value":"C4"}`.
+    /// Functions and thunks have no JSON form.
+    pub fn to_json(&self) -> Result<String, String> {
+        serde_json::to_string(self).map_err(|e| e.to_string())
+    }
+
+    /// Parse a value from the JSON produced by `to_json`. Unknown fields are
+    /// ignored so that older readers accept newer output.
+    pub fn from_json(json: &str) -> Result<Value, String> {
+        serde_json::from_str(json).map_err(|e| e.to_string())
+    }
+}
+
+impl Value {
+    /// Convert this value to a numeric control pattern (for CC automation)
+    ///
+    /// Accepts control strings ("0 64 127"), single numbers, and arrays of
+    /// numbers. Note patterns are rejected.
+    pub fn to_control_pattern(&self) -> Result<ControlPattern, String> {
+        match self {
+            Value::String(s) => ControlPattern::parse(s).map_err(|e| e.to_string()),
+            Value::Number(n) => Ok(ControlPattern::new(vec![*n])),
+            Value::Array(values) => {
+                let numbers = values
+                    .iter()
+                    .map(|v| match v {
+                        Value::Number(n) => Ok(*n),
+                        other => Err(format!(
+                            "Control arrays must contain numbers, found {}",
+                            other
+                        )),
+                    })
+                    .collect::<Result<Vec<i32>, String>>()?;
+                if numbers.is_empty() {
+                    return Err("Control pattern is empty".to_string());
+                }
+                Ok(ControlPattern::new(numbers))
+            }
+            Value::Pattern(_)
+            | Value::EveryPattern(_)
+            | Value::ProbabilityPattern(_)
+            | Value::Note(_)
+            | Value::Chord(_) => {
+                Err("CC automation expects numeric values 0-127, not notes".to_string())
+            }
+            other => Err(format!("Cannot use {} as a control pattern", other)),
+        }
+    }
+
+    /// The pattern this value plays during a given cycle (for offline rendering)
+    ///
+    /// Notes and chords become one-beat patterns, matching how looping playback
+    /// retriggers them every beat. `every` and `sometimes` patterns pick their variant for the cycle.
+    pub fn pattern_for_cycle(&self, cycle: usize) -> Result<Pattern, String> {
+        match self {
+            Value::Pattern(pattern) => Ok(pattern.clone()),
+            Value::String(s) => Pattern::parse(s).map_err(|e| e.to_string()),
+            Value::EveryPattern(every) => Ok(every.get_pattern_for_cycle(cycle).clone()),
+            Value::ProbabilityPattern(sometimes) => {
+                Ok(sometimes.get_pattern_for_cycle(cycle).clone())
+            }
+            Value::Note(note) => {
+                Ok(Pattern::with_steps(vec![PatternStep::Note(*note)]).with_cycle_length(1))
+            }
+            Value::Chord(chord) => {
+                Ok(Pattern::with_steps(vec![PatternStep::Chord(chord.clone())])
+                    .with_cycle_length(1))
+            }
+            other => Err(format!("Cannot render {} as a pattern", other)),
+        }
+    }
+
+    /// The frequencies (Hz) sounding in each event of the first cycle, one
+    /// list per event. Rests and drum hits give empty lists.
+    pub fn event_frequencies(&self) -> Result<Vec<Vec<f32>>, String> {
+        let pattern = self
+            .pattern_for_cycle(0)
+            .map_err(|_| format!("Cannot get frequencies of {}", self.type_name()))?;
+        Ok(pattern
+            .to_rich_events()
+            .iter()
+            .map(|event| event.notes.iter().map(|n| n.frequency).collect())
+            .collect())
+    }
+
+    /// This value as a single pattern step (for editing a pattern in place)
+    ///
+    /// A pattern of one step becomes that step; longer patterns are grouped
+    /// so they fit in one slot.
+    pub fn to_pattern_step(&self) -> Result<PatternStep, String> {
+        let pattern = match self {
+            Value::Note(note) => return Ok(PatternStep::Note(*note)),
+            Value::Chord(chord) => return Ok(PatternStep::Chord(chord.clone())),
+            Value::Pattern(pattern) => pattern.clone(),
+            Value::String(s) => Pattern::parse(s).map_err(|e| e.to_string())?,
+            other => return Err(format!("Cannot use {} as a pattern step", other)),
+        };
+        match <[PatternStep; 1]>::try_from(pattern.steps) {
+            Ok([step]) => Ok(step),
+            Err(steps) if steps.is_empty() => {
+                Err("Cannot use an empty pattern as a step".to_string())
+            }
+            Err(steps) => Ok(PatternStep::Group(steps)),
+        }
+    }
+
+    /// The value of a whole number or float, for arithmetic and comparisons
+    pub fn as_f32(&self) -> Option<f32> {
+        match self {
+            Value::Number(n) => Some(*n as f32),
+            Value::Float(f) => Some(*f),
+            _ => None,
+        }
+    }
+
+    /// Short lowercase name of this value's kind ("note", "chord", ...)
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Note(_) => "note",
+            Value::Chord(_) => "chord",
+            Value::Boolean(_) => "boolean",
+            Value::Pattern(_) => "pattern",
+            Value::Number(_) | Value::Float(_) => "number",
+            Value::String(_) => "string",
+            Value::Function { .. } => "function",
+            Value::Unit => "unit",
+            Value::Array(_) => "array",
+            Value::EveryPattern(_) => "every",
+            Value::ProbabilityPattern(_) => "sometimes",
+            Value::Thunk { .. } => "expression",
+        }
+    }
+
+    /// Whether this value satisfies a `-> Type` return annotation. Chords and
+    /// arrays stand in for each other; type names not listed here aren't checked.
+    pub fn matches_type(&self, type_name: &str) -> bool {
+        match type_name {
+            "Note" => matches!(self, Value::Note(_)),
+            "Chord" | "Array" => matches!(self, Value::Chord(_) | Value::Array(_)),
+            "Pattern" => matches!(
+                self,
+                Value::Pattern(_) | Value::EveryPattern(_) | Value::ProbabilityPattern(_)
+            ),
+            "Number" => matches!(self, Value::Number(_) | Value::Float(_)),
+            "String" => matches!(self, Value::String(_)),
+            "Boolean" => matches!(self, Value::Boolean(_)),
+            _ => true,
+        }
+    }
+}
+
+/// Chords in patterns are shown by symbol, e.g. `"Cmaj7 Dm7 Gdom7"`. The
+/// alternate form (`{:#}`) shows the notes of every chord instead.
 impl fmt::Display for Value {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Value::Note(note) => write!(f, "{}", note),
+            Value::Chord(chord) if f.alternate() => {
+                let notes: Vec<String> = chord.notes_vec().iter().map(|n| n.to_string()).collect();
+                write!(f, "[{}]", notes.join(", "))
+            }
             Value::Chord(chord) => write!(f, "{}", chord),
             Value::Boolean(b) => write!(f, "{}", b),
-            Value::Pattern(pattern) => write!(f, "{}", pattern),
+            Value::Pattern(pattern) => fmt::Display::fmt(pattern, f),
             Value::Number(n) => write!(f, "{}", n),
+            Value::Float(n) => write!(f, "{}", n),
             Value::String(s) => write!(f, "\"{}\"", s),
             Value::Function { name, params, .. } => {
                 write!(f, "<fn {}({})>", name, params.join(", "))
@@ -698,11 +1008,12 @@ impl fmt::Display for Value {
                     if i > 0 {
                         write!(f, ", ")?;
                     }
-                    write!(f, "{}", val)?;
+                    fmt::Display::fmt(val, f)?;
                 }
                 write!(f, "]")
             }
             Value::EveryPattern(every) => write!(f, "{}", every),
+            Value::ProbabilityPattern(sometimes) => write!(f, "{}", sometimes),
             Value::Thunk { expression, .. } => write!(f, "<thunk: {}>", expression),
         }
     }
@@ -814,6 +1125,32 @@ mod tests {
         assert!(display.contains("F Major") || display.contains("F"));
     }
 
+    #[test]
+    fn test_pattern_display_chord_symbols() {
+        let pattern = Pattern::parse("[C,E,G,B] [D,F,A,C] [G,B,D,F]*2 _").unwrap();
+        let value = Value::Pattern(pattern);
+        assert_eq!(value.to_string(), "\"Cmaj7 Dm7 Gdom7*2 _\"");
+        assert_eq!(
+            format!("{:#}", value),
+            "\"[C,E,G,B] [D,F,A,C5] [G,B,D5,F5]*2 _\""
+        );
+
+        // A triad isn't shown as a note name
+        let triads = Value::Pattern(Pattern::parse("[C,E,G] C").unwrap());
+        assert_eq!(triads.to_string(), "\"Cmaj C\"");
+
+        // No known quality, or a voicing the symbol doesn't build: the notes
+        let cluster = Value::Pattern(Pattern::parse("[C,D,E] [E,G,C]").unwrap());
+        assert_eq!(cluster.to_string(), "\"[C,D,E] [E,G,C5]\"");
+
+        let chord = Value::Chord(Chord::from_note_strings(vec!["E", "G", "C"]).unwrap());
+        assert_eq!(format!("{:#}", chord), "[E, G, C5]");
+        assert_eq!(
+            format!("{:#}", Value::Array(vec![chord.clone(), chord])),
+            "[[E, G, C5], [E, G, C5]]"
+        );
+    }
+
     #[test]
     fn test_expression_constructors() {
         let c_note = Expression::Note(Note::from_str("C").unwrap());
@@ -13,7 +13,7 @@ fn infer_type_from_expr(expr: &Expression, table: Option<&SymbolTable>) -> Optio
         Expression::Note(_) => Some("Note".to_string()),
         Expression::Chord(_) => Some("Chord".to_string()),
         Expression::Pattern(_) => Some("Pattern".to_string()),
-        Expression::Number(_) => Some("Number".to_string()),
+        Expression::Number(_) | Expression::Float(_) => Some("Number".to_string()),
         Expression::Boolean(_) => Some("Boolean".to_string()),
         Expression::String(_) => Some("String".to_string()),
         Expression::Array(_) => Some("Chord".to_string()), // Arrays often become chords
@@ -31,7 +31,9 @@ fn infer_type_from_expr(expr: &Expression, table: Option<&SymbolTable>) -> Optio
                 "major" | "minor" | "dim" | "aug" | "sus2" | "sus4" | "invert" | "bass" => {
                     Some("Chord".to_string())
                 }
-                "fast" | "slow" | "rev" | "every" => Some("Pattern".to_string()),
+                "fast" | "slow" | "rev" | "every" | "sometimes" | "sometimes_by" => {
+                    Some("Pattern".to_string())
+                }
                 "root" | "fifth" => Some("Note".to_string()),
                 _ => None, // Unknown function, can't infer
             }
@@ -80,9 +82,10 @@ impl Binder {
                 params,
                 body,
                 return_type,
+                doc,
             } => {
                 let mut func = FunctionSymbol::new(name.clone(), params.clone(), span);
-                func.doc_comment = spanned.doc_comment.clone();
+                func.doc_comment = doc.clone();
                 func.return_type = return_type.clone();
                 self.table.add_function(func);
 
@@ -136,7 +139,7 @@ impl Binder {
                 }
             }
 
-            Statement::For { body, .. } => {
+            Statement::For { body, .. } | Statement::While { body, .. } => {
                 for stmt in body {
                     self.bind_inner_statement(stmt);
                 }
@@ -222,7 +225,7 @@ impl Binder {
                 }
             }
 
-            Statement::For { body, .. } => {
+            Statement::For { body, .. } | Statement::While { body, .. } => {
                 for s in body {
                     self.bind_inner_statement(s);
                 }
@@ -1,8 +1,9 @@
 use crate::parser::ast::{Expression, Value};
 
-use crate::parser::evaluator::{Evaluator, EnvironmentRef};
+use crate::parser::evaluator::{EnvironmentRef, Evaluator};
 use crate::types::{
-    analyze_progression, Chord, CommonProgressions, Note, RomanNumeral, VoiceLeading,
+    analyze_progression, probability_seed, Chord, CommonProgressions, Delay, DelayTime, Note,
+    RomanNumeral, ScaleMode, SeededRng, VoiceLeading, DEFAULT_SEED,
 };
 use anyhow::{anyhow, Result};
 use std::collections::HashMap;
@@ -13,10 +14,80 @@ pub type BuiltinHandler =
 
 static REGISTRY: OnceLock<FunctionRegistry> = OnceLock::new();
 
+/// Namespace that always reaches the builtins: `std.fast(...)`
+pub const STD_NAMESPACE: &str = "std";
+
+/// Variable set by `use strict`, after which builtins must be called as
+/// `std.name(...)`
+pub const STRICT_VARIABLE: &str = "_strict";
+
 pub fn get_registry() -> &'static FunctionRegistry {
     REGISTRY.get_or_init(FunctionRegistry::new)
 }
 
+/// The function a transform argument names, as in `every(2, rev, p)`: a bare
+/// name, a string or a call without arguments. A transform that takes
+/// arguments, like `fast(p, 2)`, has to be wrapped in a function of its own.
+fn transform_name(arg: &Expression, env: &Option<EnvironmentRef>) -> Option<String> {
+    let name = match arg {
+        Expression::Variable(name) | Expression::String(name) => name.clone(),
+        Expression::Pattern(p) => p.to_string(),
+        Expression::FunctionCall { name, args } if args.is_empty() => name.clone(),
+        _ => return None,
+    };
+    Some(function_target(name, env))
+}
+
+/// The name to call for a function passed by name. Builtins are called
+/// through `std.`, since naming one isn't a bare call and works after
+/// `use strict`; user functions keep their name and win, as in bare calls.
+fn function_target(name: String, env: &Option<EnvironmentRef>) -> String {
+    let user_function = matches!(
+        env.as_ref().and_then(|e| e.lookup(&name)),
+        Some(Value::Function { .. })
+    );
+    let builtin = get_registry().get(&name).is_some_and(|b| b.name == name);
+    if builtin && !user_function {
+        format!("{}.{}", STD_NAMESPACE, name)
+    } else {
+        name
+    }
+}
+
+/// Like [`transform_name`], but only if the name is a builtin or a user
+/// function, to tell the transform from the pattern in method calls
+fn transform_function(arg: &Expression, env: &Option<EnvironmentRef>) -> Option<String> {
+    transform_name(arg, env).filter(|name| {
+        get_registry().get(name).is_some()
+            || matches!(
+                env.as_ref().and_then(|e| e.lookup(name)),
+                Some(Value::Function { .. })
+            )
+    })
+}
+
+/// Error for a transform argument that isn't a function name
+/// Part of a cycle for `linger`/`trunc`: a fraction in (0, 1], or a whole
+/// number of percent from 1 to 100
+fn cycle_fraction(function: &str, value: Value) -> Result<crate::types::Time> {
+    match value {
+        Value::Float(f) if f > 0.0 && f <= 1.0 => Ok(crate::types::from_f64(f as f64)),
+        Value::Number(n) if (1..=100).contains(&n) => Ok(crate::types::time::time(n as i64, 100)),
+        other => Err(anyhow!(
+            "{}() fraction must be between 0 and 1 (like 0.5), or a percent from 1 to 100, got {}",
+            function,
+            other
+        )),
+    }
+}
+
+fn transform_error(function: &str) -> anyhow::Error {
+    anyhow!(
+        "{}() expects a function name as transform argument; wrap a transform with arguments in a function, like fn faster(p) {{ return fast(p, 2) }}",
+        function
+    )
+}
+
 pub struct BuiltinFunction {
     pub name: String,
     pub category: String, // e.g., "Core", "Math", "Pattern", "Audio"
@@ -58,7 +129,7 @@ impl BuiltinFunction {
                 }
             }
         }
-        
+
         if arities.is_empty() {
             vec![0]
         } else {
@@ -67,6 +138,29 @@ impl BuiltinFunction {
             arities
         }
     }
+
+    /// Whether argument `index` of a call with `arity` arguments takes a
+    /// pattern, going by the overload of that arity in the signature
+    pub fn takes_pattern(&self, index: usize, arity: usize) -> bool {
+        self.signature.split(" or ").any(|part| {
+            let (Some(start), Some(end)) = (part.find('('), part.find(')')) else {
+                return false;
+            };
+            let mut params: Vec<&str> = part[start + 1..end]
+                .split(',')
+                .map(str::trim)
+                .filter(|param| !param.is_empty())
+                .collect();
+            // Method-style signatures take the pattern as an implicit first argument
+            if part.trim().contains('.') && !part.trim().starts_with("fn") {
+                params.insert(0, "pattern");
+            }
+            params.len() == arity
+                && params
+                    .get(index)
+                    .is_some_and(|param| *param == "pattern" || param.contains("Pattern"))
+        })
+    }
 }
 
 pub struct DocItem {
@@ -133,7 +227,12 @@ impl FunctionRegistry {
         docs
     }
 
+    /// Look up a builtin by name; `std.fast` is the same builtin as `fast`
     pub fn get(&self, name: &str) -> Option<&BuiltinFunction> {
+        let name = name
+            .strip_prefix(STD_NAMESPACE)
+            .and_then(|rest| rest.strip_prefix('.'))
+            .unwrap_or(name);
         self.functions.get(name)
     }
 
@@ -143,8 +242,8 @@ impl FunctionRegistry {
         self.register(
             "fast",
             "Pattern",
-            "Speeds up a pattern by a given factor.",
-            "fast(pattern: Pattern, factor: Number | Note) -> Pattern",
+            "Speeds up a pattern by a given factor, or with a note value like \"8th\" plays each step as that note.",
+            "fast(pattern: Pattern, factor: Number | Note | String) -> Pattern",
             Arc::new(|evaluator, args, env| {
                 if args.len() != 2 {
                     return Err(anyhow!("fast() expects 2 arguments: pattern, factor"));
@@ -153,25 +252,43 @@ impl FunctionRegistry {
                 let pattern_value = evaluator.eval_with_env(args[0].clone(), env.clone())?;
                 let factor_value = evaluator.eval_with_env(args[1].clone(), env.clone())?;
 
+                // A note value name like "8th" sets the step length instead
+                let note_value = match &factor_value {
+                    Value::String(name) => Some(crate::types::note_value(name.trim()).ok_or_else(|| {
+                        let names: Vec<&str> = crate::types::NOTE_VALUES.iter().map(|(n, ..)| *n).collect();
+                        anyhow!("fast() unknown note value '{}', expected one of: {}", name, names.join(", "))
+                    })?),
+                    _ => None,
+                };
                 let factor = match factor_value {
-                    Value::Note(note) => (note.pitch_class() as usize).max(1),
-                    Value::Number(n) => (n as usize).max(1),
-                    _ => return Err(anyhow!("fast() factor must be a note or number")),
+                    Value::Note(note) => crate::types::beats((note.pitch_class() as i64).max(1)),
+                    Value::Number(n) => crate::types::beats(n as i64),
+                    Value::Float(n) => crate::types::from_f64(n as f64),
+                    // Unused: the note value sets the step length
+                    Value::String(_) => crate::types::beats(1),
+                    _ => return Err(anyhow!("fast() factor must be a note, number or note value")),
+                };
+                if factor <= crate::types::beats(0) {
+                    return Err(anyhow!("fast() factor must be greater than 0, got {}", factor_value));
+                }
+                let fast = |pattern: crate::types::Pattern| match note_value {
+                    Some(step) => pattern.with_step_beats(step),
+                    None => pattern.fast(factor),
                 };
 
                 match pattern_value {
-                    Value::Pattern(p) => Ok(Value::Pattern(p.fast(factor))),
+                    Value::Pattern(p) => Ok(Value::Pattern(fast(p))),
                     Value::String(s) => {
                         let pattern = crate::types::Pattern::parse(&s)
                             .map_err(|e| anyhow!("fast(): invalid pattern string: {}", e))?;
-                        Ok(Value::Pattern(pattern.fast(factor)))
+                        Ok(Value::Pattern(fast(pattern)))
                     }
                     Value::EveryPattern(every) => {
                         // Apply fast to both base and transformed patterns
                         let fast_every = crate::types::EveryPattern::new(
                             every.interval,
-                            every.base.clone().fast(factor),
-                            every.transformed.clone().fast(factor),
+                            fast(every.base.clone()),
+                            fast(every.transformed.clone()),
                         );
                         Ok(Value::EveryPattern(Box::new(fast_every)))
                     }
@@ -180,13 +297,13 @@ impl FunctionRegistry {
                         let pattern = crate::types::Pattern::with_steps(vec![
                             crate::types::PatternStep::Note(n)
                         ]);
-                        Ok(Value::Pattern(pattern.fast(factor)))
+                        Ok(Value::Pattern(fast(pattern)))
                     }
                     Value::Chord(c) => {
                         let pattern = crate::types::Pattern::with_steps(vec![
                             crate::types::PatternStep::Chord(c)
                         ]);
-                        Ok(Value::Pattern(pattern.fast(factor)))
+                        Ok(Value::Pattern(fast(pattern)))
                     }
                     _ => Err(anyhow!("fast() first argument must be a pattern, note, chord, or pattern string")),
                 }
@@ -196,8 +313,8 @@ impl FunctionRegistry {
         self.register(
             "slow",
             "Pattern",
-            "Slows down a pattern by a given factor.",
-            "slow(pattern: Pattern, factor: Number | Note) -> Pattern",
+            "Slows down a pattern by a given factor, or with a note value like \"half\" plays each step as that note.",
+            "slow(pattern: Pattern, factor: Number | Note | String) -> Pattern",
             Arc::new(|evaluator, args, env| {
                 if args.len() != 2 {
                     return Err(anyhow!("slow() expects 2 arguments: pattern, factor"));
@@ -206,25 +323,43 @@ impl FunctionRegistry {
                 let pattern_value = evaluator.eval_with_env(args[0].clone(), env.clone())?;
                 let factor_value = evaluator.eval_with_env(args[1].clone(), env.clone())?;
 
+                // A note value name like "8th" sets the step length instead
+                let note_value = match &factor_value {
+                    Value::String(name) => Some(crate::types::note_value(name.trim()).ok_or_else(|| {
+                        let names: Vec<&str> = crate::types::NOTE_VALUES.iter().map(|(n, ..)| *n).collect();
+                        anyhow!("slow() unknown note value '{}', expected one of: {}", name, names.join(", "))
+                    })?),
+                    _ => None,
+                };
                 let factor = match factor_value {
-                    Value::Note(note) => (note.pitch_class() as usize).max(1),
-                    Value::Number(n) => (n as usize).max(1),
-                    _ => return Err(anyhow!("slow() factor must be a note or number")),
+                    Value::Note(note) => crate::types::beats((note.pitch_class() as i64).max(1)),
+                    Value::Number(n) => crate::types::beats(n as i64),
+                    Value::Float(n) => crate::types::from_f64(n as f64),
+                    // Unused: the note value sets the step length
+                    Value::String(_) => crate::types::beats(1),
+                    _ => return Err(anyhow!("slow() factor must be a note, number or note value")),
+                };
+                if factor <= crate::types::beats(0) {
+                    return Err(anyhow!("slow() factor must be greater than 0, got {}", factor_value));
+                }
+                let slow = |pattern: crate::types::Pattern| match note_value {
+                    Some(step) => pattern.with_step_beats(step),
+                    None => pattern.slow(factor),
                 };
 
                 match pattern_value {
-                    Value::Pattern(p) => Ok(Value::Pattern(p.slow(factor))),
+                    Value::Pattern(p) => Ok(Value::Pattern(slow(p))),
                     Value::String(s) => {
                         let pattern = crate::types::Pattern::parse(&s)
                             .map_err(|e| anyhow!("slow(): invalid pattern string: {}", e))?;
-                        Ok(Value::Pattern(pattern.slow(factor)))
+                        Ok(Value::Pattern(slow(pattern)))
                     }
                     Value::EveryPattern(every) => {
                         // Apply slow to both base and transformed patterns
                         let slow_every = crate::types::EveryPattern::new(
                             every.interval,
-                            every.base.clone().slow(factor),
-                            every.transformed.clone().slow(factor),
+                            slow(every.base.clone()),
+                            slow(every.transformed.clone()),
                         );
                         Ok(Value::EveryPattern(Box::new(slow_every)))
                     }
@@ -233,13 +368,13 @@ impl FunctionRegistry {
                         let pattern = crate::types::Pattern::with_steps(vec![
                             crate::types::PatternStep::Note(n)
                         ]);
-                        Ok(Value::Pattern(pattern.slow(factor)))
+                        Ok(Value::Pattern(slow(pattern)))
                     }
                     Value::Chord(c) => {
                         let pattern = crate::types::Pattern::with_steps(vec![
                             crate::types::PatternStep::Chord(c)
                         ]);
-                        Ok(Value::Pattern(pattern.slow(factor)))
+                        Ok(Value::Pattern(slow(pattern)))
                     }
                     _ => Err(anyhow!("slow() first argument must be a pattern, note, chord, or pattern string")),
                 }
@@ -291,9 +426,11 @@ impl FunctionRegistry {
                                 PatternStep::Variable(_) => {
                                     Err(anyhow!("Cannot index unresolved variable"))
                                 }
-                                PatternStep::Group(steps) => Ok(Value::Pattern(
-                                    crate::types::Pattern::with_steps(steps.clone()),
-                                )),
+                                PatternStep::Group(steps) | PatternStep::Tuplet(steps) => {
+                                    Ok(Value::Pattern(crate::types::Pattern::with_steps(
+                                        steps.clone(),
+                                    )))
+                                }
                                 PatternStep::Repeat(inner, count) => Ok(Value::Pattern(
                                     crate::types::Pattern::with_steps(vec![PatternStep::Repeat(
                                         inner.clone(),
@@ -304,19 +441,17 @@ impl FunctionRegistry {
                                 PatternStep::Alternation(steps) => Ok(Value::Pattern(
                                     crate::types::Pattern::with_steps(steps.clone()),
                                 )),
-                                PatternStep::Euclidean(inner, pulses, steps) => Ok(Value::Pattern(
-                                    crate::types::Pattern::with_steps(vec![PatternStep::Euclidean(
-                                        inner.clone(),
-                                        *pulses,
-                                        *steps,
-                                    )]),
+                                PatternStep::Euclidean(..) | PatternStep::Polymeter(..) | PatternStep::Choice(..) | PatternStep::Degraded(..) => Ok(Value::Pattern(
+                                    crate::types::Pattern::with_steps(vec![step.clone()]),
                                 )),
                                 PatternStep::Polyrhythm(sub_patterns) => Ok(Value::Pattern(
                                     crate::types::Pattern::with_steps(vec![PatternStep::Polyrhythm(
                                         sub_patterns.clone(),
                                     )]),
                                 )),
-                                PatternStep::Velocity(inner, _) => step_to_value(inner),
+                                PatternStep::Velocity(inner, _)
+                                | PatternStep::Speed(inner, _)
+                                | PatternStep::Pan(inner, _) => step_to_value(inner),
                             }
                         }
                         step_to_value(&pattern.steps[actual_idx as usize])
@@ -389,7 +524,8 @@ impl FunctionRegistry {
                     return Err(anyhow!("rev() expects 1 argument: pattern"));
                 }
 
-                let arg_value = evaluator.eval_with_env(args.into_iter().next().unwrap(), env.clone())?;
+                let arg_value =
+                    evaluator.eval_with_env(args.into_iter().next().unwrap(), env.clone())?;
                 match arg_value {
                     Value::Pattern(p) => Ok(Value::Pattern(p.rev())),
                     Value::String(s) => {
@@ -411,6 +547,519 @@ impl FunctionRegistry {
             }),
         );
 
+        self.register(
+            "scan",
+            "Pattern",
+            "Reveals one more step each cycle (cycle k plays the first k+1 steps), then loops the full pattern.",
+            "scan(pattern: Pattern) -> Pattern",
+            Arc::new(|evaluator, args, env| {
+                if args.len() != 1 {
+                    return Err(anyhow!("scan() expects 1 argument: pattern"));
+                }
+
+                // Read _cycle from environment (set by the playback loop)
+                let cycle = env
+                    .as_ref()
+                    .and_then(|e| match e.lookup("_cycle") {
+                        Some(Value::Number(n)) => Some(n.max(0) as usize),
+                        _ => None,
+                    })
+                    .unwrap_or(0);
+
+                let arg_value = evaluator.eval_with_env(args[0].clone(), env.clone())?;
+                match arg_value {
+                    Value::Pattern(p) => Ok(Value::Pattern(p.scan(cycle))),
+                    Value::String(s) => {
+                        let pattern = crate::types::Pattern::parse(&s)
+                            .map_err(|e| anyhow!("scan(): invalid pattern string: {}", e))?;
+                        Ok(Value::Pattern(pattern.scan(cycle)))
+                    }
+                    _ => Err(anyhow!("scan() only works on patterns")),
+                }
+            }),
+        );
+
+        self.register(
+            "iter",
+            "Pattern",
+            "Splits the cycle into n sections and starts one section later each cycle (cycle k starts at section k mod n), coming back round after n cycles.",
+            "iter(pattern: Pattern, n: Number) -> Pattern",
+            Arc::new(|evaluator, args, env| {
+                if args.len() != 2 {
+                    return Err(anyhow!("iter() expects 2 arguments: pattern, n"));
+                }
+
+                // Read _cycle from environment (set by the playback loop)
+                let cycle = env
+                    .as_ref()
+                    .and_then(|e| match e.lookup("_cycle") {
+                        Some(Value::Number(n)) => Some(n.max(0) as usize),
+                        _ => None,
+                    })
+                    .unwrap_or(0);
+
+                let arg_value = evaluator.eval_with_env(args[0].clone(), env.clone())?;
+                let sections = match evaluator.eval_with_env(args[1].clone(), env.clone())? {
+                    Value::Number(n) if n > 0 => n as usize,
+                    _ => return Err(anyhow!("iter() n must be a positive number")),
+                };
+                match arg_value {
+                    Value::Pattern(p) => Ok(Value::Pattern(p.iter(sections, cycle))),
+                    Value::String(s) => {
+                        let pattern = crate::types::Pattern::parse(&s)
+                            .map_err(|e| anyhow!("iter(): invalid pattern string: {}", e))?;
+                        Ok(Value::Pattern(pattern.iter(sections, cycle)))
+                    }
+                    _ => Err(anyhow!("iter() only works on patterns")),
+                }
+            }),
+        );
+
+        // degrade(), undegrade() and degrade_by() share a handler; undegrade()
+        // keeps the steps degrade() would drop. The steps are only wrapped
+        // here: which ones drop is decided per cycle when events are generated.
+        fn degrade_handler(
+            name: &'static str,
+            arities: std::ops::RangeInclusive<usize>,
+            usage: &'static str,
+            invert: bool,
+        ) -> BuiltinHandler {
+            Arc::new(move |evaluator, args, env| {
+                if !arities.contains(&args.len()) {
+                    return Err(anyhow!("{}() expects {}", name, usage));
+                }
+
+                // Mix in _track (set by the playback loop) so two tracks
+                // playing the same expression don't drop the same steps
+                let track_id = env
+                    .as_ref()
+                    .and_then(|e| match e.lookup("_track") {
+                        Some(Value::Number(n)) => Some(n.max(0) as usize),
+                        _ => None,
+                    })
+                    .unwrap_or(0);
+
+                let values = args
+                    .into_iter()
+                    .map(|arg| evaluator.eval_with_env(arg, env.clone()))
+                    .collect::<Result<Vec<_>>>()?;
+
+                let percent = match values.get(1) {
+                    Some(Value::Number(n)) if (0..=100).contains(n) => *n as u8,
+                    Some(_) => {
+                        return Err(anyhow!("{}() percent must be a number from 0 to 100", name))
+                    }
+                    None => 50,
+                };
+                let seed = match values.get(2) {
+                    Some(Value::Number(n)) => Some(*n as u64),
+                    Some(_) => return Err(anyhow!("{}() seed must be a number", name)),
+                    None => None,
+                };
+
+                let degrade = |pattern: crate::types::Pattern| {
+                    let base_seed = seed.or(pattern.seed).unwrap_or(DEFAULT_SEED);
+                    let pattern = pattern.with_seed(probability_seed(base_seed, track_id, 0));
+                    if invert {
+                        pattern.undegrade(percent)
+                    } else {
+                        pattern.degrade(percent)
+                    }
+                };
+
+                match &values[0] {
+                    Value::Pattern(p) => Ok(Value::Pattern(degrade(p.clone()))),
+                    Value::String(s) => {
+                        let pattern = crate::types::Pattern::parse(s)
+                            .map_err(|e| anyhow!("{}(): invalid pattern: {}", name, e))?;
+                        Ok(Value::Pattern(degrade(pattern)))
+                    }
+                    Value::EveryPattern(every) => {
+                        let degraded_every = crate::types::EveryPattern::new(
+                            every.interval,
+                            degrade(every.base.clone()),
+                            degrade(every.transformed.clone()),
+                        );
+                        Ok(Value::EveryPattern(Box::new(degraded_every)))
+                    }
+                    _ => Err(anyhow!("{}() first argument must be a pattern", name)),
+                }
+            })
+        }
+
+        self.register(
+            "degrade",
+            "Pattern",
+            "Randomly drops steps, each with the given chance in percent (0-100, default 50). Choices change every cycle and differ between tracks; pass a seed for a different set of choices.",
+            "degrade(pattern: Pattern) -> Pattern or degrade(pattern: Pattern, percent: Number) -> Pattern or degrade(pattern: Pattern, percent: Number, seed: Number) -> Pattern",
+            degrade_handler("degrade", 1..=3, "1 to 3 arguments: pattern, [percent], [seed]", false),
+        );
+
+        self.register(
+            "undegrade",
+            "Pattern",
+            "Randomly keeps steps, each with the given chance in percent (0-100, default 50), and drops the rest. With the same percent and seed it plays exactly the steps degrade() drops.",
+            "undegrade(pattern: Pattern) -> Pattern or undegrade(pattern: Pattern, percent: Number) -> Pattern or undegrade(pattern: Pattern, percent: Number, seed: Number) -> Pattern",
+            degrade_handler("undegrade", 1..=3, "1 to 3 arguments: pattern, [percent], [seed]", true),
+        );
+
+        self.register(
+            "degrade_by",
+            "Pattern",
+            "Randomly drops steps, each with the given chance in percent (0-100). Same as degrade() with a percent.",
+            "degrade_by(pattern: Pattern, percent: Number) -> Pattern",
+            degrade_handler("degrade_by", 2..=2, "2 arguments: pattern, percent", false),
+        );
+
+        self.register(
+            "rand_transpose",
+            "Pattern",
+            "Transposes a pattern or chord by a random number of semitones between -max and max. The amount is limited so no note leaves MIDI 0-127. It changes every cycle and differs between tracks; pass a seed for a different set of choices.",
+            "rand_transpose(pattern: Pattern, max: Number) -> Pattern or rand_transpose(pattern: Pattern, max: Number, seed: Number) -> Pattern",
+            Arc::new(|evaluator, args, env| {
+                if args.len() != 2 && args.len() != 3 {
+                    return Err(anyhow!(
+                        "rand_transpose() expects 2 or 3 arguments: pattern, max, [seed]"
+                    ));
+                }
+
+                // Read _track and _cycle from environment (set by the playback loop)
+                let lookup = |name: &str| {
+                    env.as_ref()
+                        .and_then(|e| match e.lookup(name) {
+                            Some(Value::Number(n)) => Some(n.max(0) as usize),
+                            _ => None,
+                        })
+                        .unwrap_or(0)
+                };
+                let (track_id, cycle) = (lookup("_track"), lookup("_cycle"));
+
+                let values = args
+                    .into_iter()
+                    .map(|arg| evaluator.eval_with_env(arg, env.clone()))
+                    .collect::<Result<Vec<_>>>()?;
+
+                let max = match &values[1] {
+                    Value::Number(n) if (0..=127).contains(n) => *n as u8,
+                    _ => return Err(anyhow!("rand_transpose() max must be a number from 0 to 127")),
+                };
+                let seed = match values.get(2) {
+                    Some(Value::Number(n)) => *n as u64,
+                    Some(_) => return Err(anyhow!("rand_transpose() seed must be a number")),
+                    None => DEFAULT_SEED,
+                };
+                let seed = probability_seed(seed, track_id, cycle);
+
+                match &values[0] {
+                    Value::Pattern(p) => Ok(Value::Pattern(p.clone().rand_transpose(max, seed))),
+                    Value::String(s) => {
+                        let pattern = crate::types::Pattern::parse(s)
+                            .map_err(|e| anyhow!("rand_transpose(): invalid pattern: {}", e))?;
+                        Ok(Value::Pattern(pattern.rand_transpose(max, seed)))
+                    }
+                    Value::Chord(c) => {
+                        let semitones = SeededRng::new(seed).next_offset(max);
+                        let semitones = Note::clamp_transpose(&c.notes_vec(), semitones);
+                        Ok(Value::Chord(c.clone() + semitones))
+                    }
+                    _ => Err(anyhow!("rand_transpose() first argument must be a pattern or chord")),
+                }
+            }),
+        );
+
+        self.register(
+            "scatter",
+            "Pattern",
+            "Nudges every note's start randomly, early or late, by up to amount percent of its length (0 to 50). The nudges change every cycle and differ between tracks; pass a seed for a different set.",
+            "scatter(pattern: Pattern, amount: Number) -> Pattern or scatter(pattern: Pattern, amount: Number, seed: Number) -> Pattern",
+            Arc::new(|evaluator, args, env| {
+                if args.len() != 2 && args.len() != 3 {
+                    return Err(anyhow!(
+                        "scatter() expects 2 or 3 arguments: pattern, amount, [seed]"
+                    ));
+                }
+
+                // Read _track and _cycle from environment (set by the playback loop)
+                let lookup = |name: &str| {
+                    env.as_ref()
+                        .and_then(|e| match e.lookup(name) {
+                            Some(Value::Number(n)) => Some(n.max(0) as usize),
+                            _ => None,
+                        })
+                        .unwrap_or(0)
+                };
+                let (track_id, cycle) = (lookup("_track"), lookup("_cycle"));
+
+                let values = args
+                    .into_iter()
+                    .map(|arg| evaluator.eval_with_env(arg, env.clone()))
+                    .collect::<Result<Vec<_>>>()?;
+
+                let pattern = match &values[0] {
+                    Value::Pattern(p) => p.clone(),
+                    Value::String(s) => crate::types::Pattern::parse(s)
+                        .map_err(|e| anyhow!("scatter(): invalid pattern: {}", e))?,
+                    _ => return Err(anyhow!("scatter() first argument must be a pattern")),
+                };
+                let amount = match &values[1] {
+                    Value::Number(n) if (0..=50).contains(n) => *n as u32,
+                    _ => return Err(anyhow!("scatter() amount must be a percentage from 0 to 50")),
+                };
+                let seed = match values.get(2) {
+                    Some(Value::Number(n)) => *n as u64,
+                    Some(_) => return Err(anyhow!("scatter() seed must be a number")),
+                    None => DEFAULT_SEED,
+                };
+
+                Ok(Value::Pattern(
+                    pattern.scatter(amount, probability_seed(seed, track_id, cycle)),
+                ))
+            }),
+        );
+
+        self.register(
+            "humanize",
+            "Pattern",
+            "Loosens a pattern: each step's velocity moves randomly by up to velocity percent, and each note starts early or late by up to timing percent of the most scatter() allows (both 0 to 100). The same pattern always gets the same jitter, so it survives reloads; pass a seed for a different set.",
+            "humanize(pattern: Pattern, timing: Number, velocity: Number) -> Pattern or humanize(pattern: Pattern, timing: Number, velocity: Number, seed: Number) -> Pattern",
+            Arc::new(|evaluator, args, env| {
+                if args.len() != 3 && args.len() != 4 {
+                    return Err(anyhow!(
+                        "humanize() expects 3 or 4 arguments: pattern, timing, velocity, [seed]"
+                    ));
+                }
+
+                let values = args
+                    .into_iter()
+                    .map(|arg| evaluator.eval_with_env(arg, env.clone()))
+                    .collect::<Result<Vec<_>>>()?;
+
+                let pattern = match &values[0] {
+                    Value::Pattern(p) => p.clone(),
+                    Value::String(s) => crate::types::Pattern::parse(s)
+                        .map_err(|e| anyhow!("humanize(): invalid pattern: {}", e))?,
+                    _ => return Err(anyhow!("humanize() first argument must be a pattern")),
+                };
+                let percent = |value: &Value, name: &str| match value {
+                    Value::Number(n) if (0..=100).contains(n) => Ok(*n as u32),
+                    // Small numbers (0-11) are parsed as notes, extract pitch class
+                    Value::Note(n) => Ok(n.pitch_class() as u32),
+                    _ => Err(anyhow!("humanize() {} must be a percentage from 0 to 100", name)),
+                };
+                let timing = percent(&values[1], "timing")?;
+                let velocity = percent(&values[2], "velocity")?;
+                let seed = match values.get(3) {
+                    Some(Value::Number(n)) => *n as u64,
+                    Some(_) => return Err(anyhow!("humanize() seed must be a number")),
+                    None => DEFAULT_SEED,
+                };
+
+                Ok(Value::Pattern(pattern.humanize(timing, velocity, seed)))
+            }),
+        );
+
+        self.register(
+            "seed",
+            "Pattern",
+            "Sets the seed for a pattern's random choices like [C|E|G]. The same seed always makes the same choices.",
+            "seed(pattern: Pattern, seed: Number) -> Pattern",
+            Arc::new(|evaluator, args, env| {
+                if args.len() != 2 {
+                    return Err(anyhow!("seed() expects 2 arguments: pattern, seed"));
+                }
+
+                let values = args
+                    .into_iter()
+                    .map(|arg| evaluator.eval_with_env(arg, env.clone()))
+                    .collect::<Result<Vec<_>>>()?;
+
+                let pattern = match &values[0] {
+                    Value::Pattern(p) => p.clone(),
+                    Value::String(s) => crate::types::Pattern::parse(s)
+                        .map_err(|e| anyhow!("seed(): invalid pattern: {}", e))?,
+                    _ => return Err(anyhow!("seed() first argument must be a pattern")),
+                };
+                let seed = match &values[1] {
+                    Value::Number(n) => *n as u64,
+                    _ => return Err(anyhow!("seed() seed must be a number")),
+                };
+
+                Ok(Value::Pattern(pattern.with_seed(seed)))
+            }),
+        );
+
+        self.register(
+            "shuffle",
+            "Pattern",
+            "Plays a pattern's steps in a new random order every cycle, each step once. Pass a seed for a different set of orders.",
+            "shuffle(pattern: Pattern) -> Pattern or shuffle(pattern: Pattern, seed: Number) -> Pattern",
+            Arc::new(|evaluator, args, env| {
+                if args.len() != 1 && args.len() != 2 {
+                    return Err(anyhow!("shuffle() expects 1 or 2 arguments: pattern, [seed]"));
+                }
+
+                let values = args
+                    .into_iter()
+                    .map(|arg| evaluator.eval_with_env(arg, env.clone()))
+                    .collect::<Result<Vec<_>>>()?;
+
+                let seed = match values.get(1) {
+                    Some(Value::Number(n)) => Some(*n as u64),
+                    Some(_) => return Err(anyhow!("shuffle() seed must be a number")),
+                    None => None,
+                };
+                let shuffle = |pattern: crate::types::Pattern| {
+                    let pattern = pattern.shuffle();
+                    match seed {
+                        Some(seed) => pattern.with_seed(seed),
+                        None => pattern,
+                    }
+                };
+
+                match values.into_iter().next() {
+                    Some(Value::Pattern(p)) => Ok(Value::Pattern(shuffle(p))),
+                    Some(Value::String(s)) => {
+                        let pattern = crate::types::Pattern::parse(&s)
+                            .map_err(|e| anyhow!("shuffle(): invalid pattern: {}", e))?;
+                        Ok(Value::Pattern(shuffle(pattern)))
+                    }
+                    Some(Value::EveryPattern(every)) => {
+                        Ok(Value::EveryPattern(Box::new(crate::types::EveryPattern::new(
+                            every.interval,
+                            shuffle(every.base.clone()),
+                            shuffle(every.transformed.clone()),
+                        ))))
+                    }
+                    Some(Value::ProbabilityPattern(sometimes)) => {
+                        Ok(Value::ProbabilityPattern(Box::new(sometimes.with_patterns(
+                            shuffle(sometimes.base.clone()),
+                            shuffle(sometimes.transformed.clone()),
+                        ))))
+                    }
+                    _ => Err(anyhow!("shuffle() first argument must be a pattern")),
+                }
+            }),
+        );
+
+        self.register(
+            "scramble",
+            "Pattern",
+            "Fills every step of a pattern with a random one of its steps, picked again every cycle, so steps can repeat or go missing. Pass a seed for a different set of picks.",
+            "scramble(pattern: Pattern) -> Pattern or scramble(pattern: Pattern, seed: Number) -> Pattern",
+            Arc::new(|evaluator, args, env| {
+                if args.len() != 1 && args.len() != 2 {
+                    return Err(anyhow!("scramble() expects 1 or 2 arguments: pattern, [seed]"));
+                }
+
+                let values = args
+                    .into_iter()
+                    .map(|arg| evaluator.eval_with_env(arg, env.clone()))
+                    .collect::<Result<Vec<_>>>()?;
+
+                let seed = match values.get(1) {
+                    Some(Value::Number(n)) => Some(*n as u64),
+                    Some(_) => return Err(anyhow!("scramble() seed must be a number")),
+                    None => None,
+                };
+                let scramble = |pattern: crate::types::Pattern| {
+                    let pattern = pattern.scramble();
+                    match seed {
+                        Some(seed) => pattern.with_seed(seed),
+                        None => pattern,
+                    }
+                };
+
+                match values.into_iter().next() {
+                    Some(Value::Pattern(p)) => Ok(Value::Pattern(scramble(p))),
+                    Some(Value::String(s)) => {
+                        let pattern = crate::types::Pattern::parse(&s)
+                            .map_err(|e| anyhow!("scramble(): invalid pattern: {}", e))?;
+                        Ok(Value::Pattern(scramble(pattern)))
+                    }
+                    Some(Value::EveryPattern(every)) => {
+                        Ok(Value::EveryPattern(Box::new(crate::types::EveryPattern::new(
+                            every.interval,
+                            scramble(every.base.clone()),
+                            scramble(every.transformed.clone()),
+                        ))))
+                    }
+                    Some(Value::ProbabilityPattern(sometimes)) => {
+                        Ok(Value::ProbabilityPattern(Box::new(sometimes.with_patterns(
+                            scramble(sometimes.base.clone()),
+                            scramble(sometimes.transformed.clone()),
+                        ))))
+                    }
+                    _ => Err(anyhow!("scramble() first argument must be a pattern")),
+                }
+            }),
+        );
+
+        self.register(
+            "some_steps",
+            "Pattern",
+            "Applies a transform to single steps, each with the given chance in percent. Each chosen step is passed to the transform as a one-step pattern, and a result of several steps plays inside that step. Choices change every cycle and differ between tracks; pass a seed for a different set of choices.",
+            "some_steps(pattern: Pattern, percent: Number, transform: String | Function) -> Pattern or some_steps(pattern: Pattern, percent: Number, transform: String | Function, seed: Number) -> Pattern",
+            Arc::new(|evaluator, args, env| {
+                if args.len() != 3 && args.len() != 4 {
+                    return Err(anyhow!(
+                        "some_steps() expects 3 or 4 arguments: pattern, percent, transform, [seed]"
+                    ));
+                }
+
+                // Read _track and _cycle from environment (set by the playback loop)
+                let lookup = |name: &str| {
+                    env.as_ref()
+                        .and_then(|e| match e.lookup(name) {
+                            Some(Value::Number(n)) => Some(n.max(0) as usize),
+                            _ => None,
+                        })
+                        .unwrap_or(0)
+                };
+                let (track_id, cycle) = (lookup("_track"), lookup("_cycle"));
+
+                let transform_name =
+                    transform_name(&args[2], &env).ok_or_else(|| transform_error("some_steps"))?;
+
+                let pattern = match evaluator.eval_with_env(args[0].clone(), env.clone())? {
+                    Value::Pattern(p) => p,
+                    Value::String(s) => crate::types::Pattern::parse(&s)
+                        .map_err(|e| anyhow!("some_steps(): invalid pattern: {}", e))?,
+                    _ => return Err(anyhow!("some_steps() first argument must be a pattern")),
+                };
+                let percent = match evaluator.eval_with_env(args[1].clone(), env.clone())? {
+                    Value::Number(n) => n.clamp(0, 100),
+                    _ => return Err(anyhow!("some_steps() percent must be a number")),
+                };
+                let seed = match args.get(3) {
+                    Some(arg) => match evaluator.eval_with_env(arg.clone(), env.clone())? {
+                        Value::Number(n) => n as u64,
+                        _ => return Err(anyhow!("some_steps() seed must be a number")),
+                    },
+                    None => DEFAULT_SEED,
+                };
+
+                let transform = |step: &crate::types::PatternStep| {
+                    let call_expr = Expression::FunctionCall {
+                        name: transform_name.clone(),
+                        args: vec![Expression::Pattern(crate::types::Pattern::with_steps(
+                            vec![step.clone()],
+                        ))],
+                    };
+                    evaluator
+                        .eval_with_env(call_expr, env.clone())?
+                        .to_pattern_step()
+                        .map_err(|e| anyhow!("some_steps(): '{}' {}", transform_name, e))
+                };
+                pattern
+                    .some_steps(
+                        percent as f64 / 100.0,
+                        probability_seed(seed, track_id, cycle),
+                        transform,
+                    )
+                    .map(Value::Pattern)
+            }),
+        );
+
         self.register(
             "rotate",
             "Pattern",
@@ -441,6 +1090,71 @@ impl FunctionRegistry {
             }),
         );
 
+        self.register(
+            "erhythm",
+            "Pattern",
+            "Euclidean rhythm: spreads pulses evenly over steps, starting rotation steps later. Onsets play the note or chord given, or take each step of a pattern in turn. invert swaps onsets and rests.",
+            "erhythm(pulses: Number, steps: Number, rotation: Number) -> Pattern or erhythm(pulses: Number, steps: Number, rotation: Number, onset: Note/Chord/Pattern) -> Pattern or erhythm(pulses: Number, steps: Number, rotation: Number, onset: Note/Chord/Pattern, invert: Boolean) -> Pattern",
+            Arc::new(|evaluator, args, env| {
+                if args.len() < 3 || args.len() > 5 {
+                    return Err(anyhow!(
+                        "erhythm() expects 3 to 5 arguments: pulses, steps, rotation, [onset], [invert]"
+                    ));
+                }
+
+                let mut numbers = Vec::with_capacity(3);
+                for (arg, name) in args.iter().zip(["pulses", "steps", "rotation"]) {
+                    match evaluator.eval_with_env(arg.clone(), env.clone())? {
+                        Value::Number(n) => numbers.push(n),
+                        _ => return Err(anyhow!("erhythm() {} must be a number", name)),
+                    }
+                }
+                let (pulses, steps, rotation) = (numbers[0], numbers[1], numbers[2]);
+                if pulses < 0 || steps <= 0 {
+                    return Err(anyhow!(
+                        "erhythm() needs pulses of 0 or more and at least one step"
+                    ));
+                }
+
+                let onsets = match args.get(3) {
+                    None => vec![crate::types::PatternStep::Note(Note::new(0)?)],
+                    Some(arg) => match evaluator.eval_with_env(arg.clone(), env.clone())? {
+                        Value::Note(note) => vec![crate::types::PatternStep::Note(note)],
+                        Value::Chord(chord) => vec![crate::types::PatternStep::Chord(chord)],
+                        Value::Pattern(p) => p.steps,
+                        Value::String(s) => {
+                            crate::types::Pattern::parse(&s)
+                                .map_err(|e| anyhow!("erhythm(): invalid pattern: {}", e))?
+                                .steps
+                        }
+                        _ => {
+                            return Err(anyhow!(
+                                "erhythm() onset must be a note, chord or pattern"
+                            ))
+                        }
+                    },
+                };
+
+                let invert = match args.get(4) {
+                    None => false,
+                    Some(arg) => match evaluator.eval_with_env(arg.clone(), env.clone())? {
+                        Value::Boolean(b) => b,
+                        _ => return Err(anyhow!("erhythm() invert must be true or false")),
+                    },
+                };
+
+                crate::types::euclidean(
+                    pulses as usize,
+                    steps as usize,
+                    rotation,
+                    &onsets,
+                    invert,
+                )
+                .map(Value::Pattern)
+                .map_err(|e| anyhow!("erhythm(): {}", e))
+            }),
+        );
+
         self.register(
             "take",
             "Pattern",
@@ -532,23 +1246,121 @@ impl FunctionRegistry {
         );
 
         self.register(
-            "palindrome",
+            "replace",
             "Pattern",
-            "Creates a palindrome: pattern followed by its reverse.",
-            "palindrome(pattern: Pattern) -> Pattern",
+            "Replaces the step at an index with a note, chord or pattern. Negative indices count from the end.",
+            "replace(pattern: Pattern, index: Number, value: Note/Chord/Pattern) -> Pattern",
             Arc::new(|evaluator, args, env| {
-                if args.len() != 1 {
-                    return Err(anyhow!("palindrome() expects 1 argument: pattern"));
+                if args.len() != 3 {
+                    return Err(anyhow!("replace() expects 3 arguments: pattern, index, value"));
                 }
 
                 let pattern_value = evaluator.eval_with_env(args[0].clone(), env.clone())?;
+                let index_value = evaluator.eval_with_env(args[1].clone(), env.clone())?;
+                let step_value = evaluator.eval_with_env(args[2].clone(), env.clone())?;
 
-                match pattern_value {
-                    Value::Pattern(p) => Ok(Value::Pattern(p.palindrome())),
-                    Value::String(s) => {
-                        let pattern = crate::types::Pattern::parse(&s)
-                            .map_err(|e| anyhow!("palindrome(): invalid pattern: {}", e))?;
-                        Ok(Value::Pattern(pattern.palindrome()))
+                let pattern = match pattern_value {
+                    Value::Pattern(p) => p,
+                    Value::String(s) => crate::types::Pattern::parse(&s)
+                        .map_err(|e| anyhow!("replace(): invalid pattern: {}", e))?,
+                    _ => return Err(anyhow!("replace() first argument must be a pattern")),
+                };
+                let Value::Number(index) = index_value else {
+                    return Err(anyhow!("replace() index must be a number"));
+                };
+                let step = step_value
+                    .to_pattern_step()
+                    .map_err(|e| anyhow!("replace(): {}", e))?;
+
+                pattern
+                    .replace_step(index, step)
+                    .map(Value::Pattern)
+                    .map_err(|e| anyhow!("replace(): {}", e))
+            }),
+        );
+
+        self.register(
+            "insert",
+            "Pattern",
+            "Inserts a note, chord or pattern as a new step at an index. Negative indices count from the end, so -1 appends.",
+            "insert(pattern: Pattern, index: Number, value: Note/Chord/Pattern) -> Pattern",
+            Arc::new(|evaluator, args, env| {
+                if args.len() != 3 {
+                    return Err(anyhow!("insert() expects 3 arguments: pattern, index, value"));
+                }
+
+                let pattern_value = evaluator.eval_with_env(args[0].clone(), env.clone())?;
+                let index_value = evaluator.eval_with_env(args[1].clone(), env.clone())?;
+                let step_value = evaluator.eval_with_env(args[2].clone(), env.clone())?;
+
+                let pattern = match pattern_value {
+                    Value::Pattern(p) => p,
+                    Value::String(s) => crate::types::Pattern::parse(&s)
+                        .map_err(|e| anyhow!("insert(): invalid pattern: {}", e))?,
+                    _ => return Err(anyhow!("insert() first argument must be a pattern")),
+                };
+                let Value::Number(index) = index_value else {
+                    return Err(anyhow!("insert() index must be a number"));
+                };
+                let step = step_value
+                    .to_pattern_step()
+                    .map_err(|e| anyhow!("insert(): {}", e))?;
+
+                pattern
+                    .insert_step(index, step)
+                    .map(Value::Pattern)
+                    .map_err(|e| anyhow!("insert(): {}", e))
+            }),
+        );
+
+        self.register(
+            "remove",
+            "Pattern",
+            "Removes the step at an index. Negative indices count from the end.",
+            "remove(pattern: Pattern, index: Number) -> Pattern",
+            Arc::new(|evaluator, args, env| {
+                if args.len() != 2 {
+                    return Err(anyhow!("remove() expects 2 arguments: pattern, index"));
+                }
+
+                let pattern_value = evaluator.eval_with_env(args[0].clone(), env.clone())?;
+                let index_value = evaluator.eval_with_env(args[1].clone(), env.clone())?;
+
+                let pattern = match pattern_value {
+                    Value::Pattern(p) => p,
+                    Value::String(s) => crate::types::Pattern::parse(&s)
+                        .map_err(|e| anyhow!("remove(): invalid pattern: {}", e))?,
+                    _ => return Err(anyhow!("remove() first argument must be a pattern")),
+                };
+                let Value::Number(index) = index_value else {
+                    return Err(anyhow!("remove() index must be a number"));
+                };
+
+                pattern
+                    .remove_step(index)
+                    .map(Value::Pattern)
+                    .map_err(|e| anyhow!("remove(): {}", e))
+            }),
+        );
+
+        self.register(
+            "palindrome",
+            "Pattern",
+            "Creates a palindrome: pattern followed by its reverse.",
+            "palindrome(pattern: Pattern) -> Pattern",
+            Arc::new(|evaluator, args, env| {
+                if args.len() != 1 {
+                    return Err(anyhow!("palindrome() expects 1 argument: pattern"));
+                }
+
+                let pattern_value = evaluator.eval_with_env(args[0].clone(), env.clone())?;
+
+                match pattern_value {
+                    Value::Pattern(p) => Ok(Value::Pattern(p.palindrome())),
+                    Value::String(s) => {
+                        let pattern = crate::types::Pattern::parse(&s)
+                            .map_err(|e| anyhow!("palindrome(): invalid pattern: {}", e))?;
+                        Ok(Value::Pattern(pattern.palindrome()))
                     }
                     Value::EveryPattern(every) => {
                         // Apply palindrome to both base and transformed patterns
@@ -564,6 +1376,327 @@ impl FunctionRegistry {
             }),
         );
 
+        self.register(
+            "offbeat",
+            "Pattern",
+            "Moves every step onto the off-beat by inserting a rest before it.",
+            "offbeat(pattern: Pattern) -> Pattern",
+            Arc::new(|evaluator, args, env| {
+                if args.len() != 1 {
+                    return Err(anyhow!("offbeat() expects 1 argument: pattern"));
+                }
+
+                let pattern_value = evaluator.eval_with_env(args[0].clone(), env.clone())?;
+
+                match pattern_value {
+                    Value::Pattern(p) => Ok(Value::Pattern(p.offbeat())),
+                    Value::String(s) => {
+                        let pattern = crate::types::Pattern::parse(&s)
+                            .map_err(|e| anyhow!("offbeat(): invalid pattern: {}", e))?;
+                        Ok(Value::Pattern(pattern.offbeat()))
+                    }
+                    Value::EveryPattern(every) => {
+                        let offbeat_every = crate::types::EveryPattern::new(
+                            every.interval,
+                            every.base.clone().offbeat(),
+                            every.transformed.clone().offbeat(),
+                        );
+                        Ok(Value::EveryPattern(Box::new(offbeat_every)))
+                    }
+                    _ => Err(anyhow!("offbeat() argument must be a pattern")),
+                }
+            }),
+        );
+
+        self.register(
+            "linger",
+            "Pattern",
+            "Repeats the first part of the cycle, given as a fraction (0.5 = first half) or a whole percent (50), until it fills the whole cycle.",
+            "linger(pattern: Pattern, fraction: Float | Number) -> Pattern",
+            Arc::new(|evaluator, args, env| {
+                if args.len() != 2 {
+                    return Err(anyhow!("linger() expects 2 arguments: pattern, fraction"));
+                }
+
+                let pattern_value = evaluator.eval_with_env(args[0].clone(), env.clone())?;
+                let fraction = cycle_fraction(
+                    "linger",
+                    evaluator.eval_with_env(args[1].clone(), env.clone())?,
+                )?;
+
+                match pattern_value {
+                    Value::Pattern(p) => Ok(Value::Pattern(p.linger(fraction))),
+                    Value::String(s) => {
+                        let pattern = crate::types::Pattern::parse(&s)
+                            .map_err(|e| anyhow!("linger(): invalid pattern: {}", e))?;
+                        Ok(Value::Pattern(pattern.linger(fraction)))
+                    }
+                    Value::EveryPattern(every) => {
+                        let lingered = crate::types::EveryPattern::new(
+                            every.interval,
+                            every.base.clone().linger(fraction),
+                            every.transformed.clone().linger(fraction),
+                        );
+                        Ok(Value::EveryPattern(Box::new(lingered)))
+                    }
+                    _ => Err(anyhow!("linger() first argument must be a pattern")),
+                }
+            }),
+        );
+
+        self.register(
+            "trunc",
+            "Pattern",
+            "Plays only the first part of the cycle, given as a fraction (0.75 = first three quarters) or a whole percent (75), and rests for the remainder.",
+            "trunc(pattern: Pattern, fraction: Float | Number) -> Pattern",
+            Arc::new(|evaluator, args, env| {
+                if args.len() != 2 {
+                    return Err(anyhow!("trunc() expects 2 arguments: pattern, fraction"));
+                }
+
+                let pattern_value = evaluator.eval_with_env(args[0].clone(), env.clone())?;
+                let fraction = cycle_fraction(
+                    "trunc",
+                    evaluator.eval_with_env(args[1].clone(), env.clone())?,
+                )?;
+
+                match pattern_value {
+                    Value::Pattern(p) => Ok(Value::Pattern(p.trunc(fraction))),
+                    Value::String(s) => {
+                        let pattern = crate::types::Pattern::parse(&s)
+                            .map_err(|e| anyhow!("trunc(): invalid pattern: {}", e))?;
+                        Ok(Value::Pattern(pattern.trunc(fraction)))
+                    }
+                    Value::EveryPattern(every) => {
+                        let truncated = crate::types::EveryPattern::new(
+                            every.interval,
+                            every.base.clone().trunc(fraction),
+                            every.transformed.clone().trunc(fraction),
+                        );
+                        Ok(Value::EveryPattern(Box::new(truncated)))
+                    }
+                    _ => Err(anyhow!("trunc() first argument must be a pattern")),
+                }
+            }),
+        );
+
+        self.register(
+            "diff",
+            "Pattern",
+            "Describes what changed from one pattern to another, one change per line: a reversal or transposition of the whole pattern, or steps added, removed, replaced or transposed, and changes of length.",
+            "diff(a: Pattern, b: Pattern) -> String",
+            Arc::new(|evaluator, args, env| {
+                if args.len() != 2 {
+                    return Err(anyhow!("diff() expects 2 arguments: a, b"));
+                }
+
+                let mut patterns = Vec::with_capacity(2);
+                for arg in args {
+                    patterns.push(match evaluator.eval_with_env(arg, env.clone())? {
+                        Value::Pattern(p) => p,
+                        Value::String(s) => crate::types::Pattern::parse(&s)
+                            .map_err(|e| anyhow!("diff(): invalid pattern: {}", e))?,
+                        _ => return Err(anyhow!("diff() arguments must be patterns")),
+                    });
+                }
+
+                let changes = patterns[0].diff(&patterns[1]);
+                if changes.is_empty() {
+                    return Ok(Value::String("no changes".to_string()));
+                }
+                let lines: Vec<String> = changes.iter().map(|change| change.to_string()).collect();
+                Ok(Value::String(lines.join("\n")))
+            }),
+        );
+
+        self.register(
+            "zoom",
+            "Pattern",
+            "Plays the part of the cycle between start and end, given in percent (0 50 = first half), stretched to fill the whole cycle.",
+            "zoom(pattern: Pattern, start: Number, end: Number) -> Pattern",
+            Arc::new(|evaluator, args, env| {
+                if args.len() != 3 {
+                    return Err(anyhow!("zoom() expects 3 arguments: pattern, start, end"));
+                }
+
+                let pattern_value = evaluator.eval_with_env(args[0].clone(), env.clone())?;
+                let mut bounds = [0; 2];
+                for (bound, arg) in bounds.iter_mut().zip(&args[1..]) {
+                    *bound = match evaluator.eval_with_env(arg.clone(), env.clone())? {
+                        Value::Number(n) if (0..=100).contains(&n) => n,
+                        Value::Number(n) => {
+                            return Err(anyhow!("zoom() bounds must be between 0 and 100, got {}", n))
+                        }
+                        _ => return Err(anyhow!("zoom() start and end must be numbers")),
+                    };
+                }
+                let [start, end] = bounds;
+                if start >= end {
+                    return Err(anyhow!("zoom() start must be before end, got {} and {}", start, end));
+                }
+                let (start, end) = (
+                    crate::types::time::time(start as i64, 100),
+                    crate::types::time::time(end as i64, 100),
+                );
+
+                match pattern_value {
+                    Value::Pattern(p) => Ok(Value::Pattern(p.zoom(start, end))),
+                    Value::String(s) => {
+                        let pattern = crate::types::Pattern::parse(&s)
+                            .map_err(|e| anyhow!("zoom(): invalid pattern: {}", e))?;
+                        Ok(Value::Pattern(pattern.zoom(start, end)))
+                    }
+                    Value::EveryPattern(every) => {
+                        let zoomed = crate::types::EveryPattern::new(
+                            every.interval,
+                            every.base.clone().zoom(start, end),
+                            every.transformed.clone().zoom(start, end),
+                        );
+                        Ok(Value::EveryPattern(Box::new(zoomed)))
+                    }
+                    _ => Err(anyhow!("zoom() first argument must be a pattern")),
+                }
+            }),
+        );
+
+        self.register(
+            "swing_n",
+            "Pattern",
+            "Swings a pattern at a grid of 8ths, 16ths, etc., delaying every other grid position by a percentage of a grid step.",
+            "swing_n(pattern: Pattern, subdivision: Number, amount: Number) -> Pattern",
+            Arc::new(|evaluator, args, env| {
+                if args.len() != 3 {
+                    return Err(anyhow!(
+                        "swing_n() expects 3 arguments: pattern, subdivision, amount"
+                    ));
+                }
+
+                let pattern_value = evaluator.eval_with_env(args[0].clone(), env.clone())?;
+                let subdivision = match evaluator.eval_with_env(args[1].clone(), env.clone())? {
+                    Value::Number(n) if n > 0 => n as u32,
+                    _ => return Err(anyhow!("swing_n() subdivision must be a positive number")),
+                };
+                let amount = match evaluator.eval_with_env(args[2].clone(), env.clone())? {
+                    Value::Number(n) if (0..100).contains(&n) => n as u32,
+                    _ => return Err(anyhow!("swing_n() amount must be a percentage from 0 to 99")),
+                };
+
+                match pattern_value {
+                    Value::Pattern(p) => Ok(Value::Pattern(p.swing_n(subdivision, amount))),
+                    Value::String(s) => {
+                        let pattern = crate::types::Pattern::parse(&s)
+                            .map_err(|e| anyhow!("swing_n(): invalid pattern: {}", e))?;
+                        Ok(Value::Pattern(pattern.swing_n(subdivision, amount)))
+                    }
+                    Value::EveryPattern(every) => {
+                        let swung_every = crate::types::EveryPattern::new(
+                            every.interval,
+                            every.base.clone().swing_n(subdivision, amount),
+                            every.transformed.clone().swing_n(subdivision, amount),
+                        );
+                        Ok(Value::EveryPattern(Box::new(swung_every)))
+                    }
+                    _ => Err(anyhow!("swing_n() first argument must be a pattern")),
+                }
+            }),
+        );
+
+        self.register(
+            "swing",
+            "Pattern",
+            "Swings a pattern's own steps, delaying every odd-indexed step: 0 is straight, 100 is triplet swing.",
+            "swing(pattern: Pattern, amount: Number) -> Pattern",
+            Arc::new(|evaluator, args, env| {
+                if args.len() != 2 {
+                    return Err(anyhow!("swing() expects 2 arguments: pattern, amount"));
+                }
+
+                let pattern_value = evaluator.eval_with_env(args[0].clone(), env.clone())?;
+                let amount = match evaluator.eval_with_env(args[1].clone(), env.clone())? {
+                    Value::Number(n) if (0..=100).contains(&n) => n as u32,
+                    // Small numbers (0-11) are parsed as notes, extract pitch class
+                    Value::Note(n) => n.pitch_class() as u32,
+                    _ => return Err(anyhow!("swing() amount must be a percentage from 0 to 100")),
+                };
+
+                match pattern_value {
+                    Value::Pattern(p) => Ok(Value::Pattern(p.swing(amount))),
+                    Value::String(s) => {
+                        let pattern = crate::types::Pattern::parse(&s)
+                            .map_err(|e| anyhow!("swing(): invalid pattern: {}", e))?;
+                        Ok(Value::Pattern(pattern.swing(amount)))
+                    }
+                    Value::EveryPattern(every) => {
+                        let swung_every = crate::types::EveryPattern::new(
+                            every.interval,
+                            every.base.clone().swing(amount),
+                            every.transformed.clone().swing(amount),
+                        );
+                        Ok(Value::EveryPattern(Box::new(swung_every)))
+                    }
+                    Value::ProbabilityPattern(sometimes) => Ok(Value::ProbabilityPattern(Box::new(
+                        sometimes.with_patterns(
+                            sometimes.base.clone().swing(amount),
+                            sometimes.transformed.clone().swing(amount),
+                        ),
+                    ))),
+                    _ => Err(anyhow!("swing() first argument must be a pattern")),
+                }
+            }),
+        );
+
+        self.register(
+            "strum",
+            "Pattern",
+            "Strums chords: their notes start one after another, spread percent of a beat apart, from the lowest note up or with \"down\" from the highest down.",
+            "strum(pattern: Pattern, spread: Number) -> Pattern or strum(pattern: Pattern, spread: Number, direction: String) -> Pattern",
+            Arc::new(|evaluator, args, env| {
+                if args.len() != 2 && args.len() != 3 {
+                    return Err(anyhow!(
+                        "strum() expects 2 or 3 arguments: pattern, spread, [direction]"
+                    ));
+                }
+
+                let pattern_value = evaluator.eval_with_env(args[0].clone(), env.clone())?;
+                let spread = match evaluator.eval_with_env(args[1].clone(), env.clone())? {
+                    Value::Number(n) if (0..=100).contains(&n) => n as u32,
+                    _ => return Err(anyhow!("strum() spread must be a percentage of a beat from 0 to 100")),
+                };
+                let down = match args.get(2) {
+                    None => false,
+                    Some(arg) => match evaluator.eval_with_env(arg.clone(), env.clone())? {
+                        Value::String(s) if s == "up" => false,
+                        Value::String(s) if s == "down" => true,
+                        _ => return Err(anyhow!("strum() direction must be \"up\" or \"down\"")),
+                    },
+                };
+
+                match pattern_value {
+                    Value::Pattern(p) => Ok(Value::Pattern(p.strum(spread, down))),
+                    Value::String(s) => {
+                        let pattern = crate::types::Pattern::parse(&s)
+                            .map_err(|e| anyhow!("strum(): invalid pattern: {}", e))?;
+                        Ok(Value::Pattern(pattern.strum(spread, down)))
+                    }
+                    Value::Chord(c) => {
+                        let pattern = crate::types::Pattern::with_steps(vec![
+                            crate::types::PatternStep::Chord(c)
+                        ]);
+                        Ok(Value::Pattern(pattern.strum(spread, down)))
+                    }
+                    Value::EveryPattern(every) => {
+                        let strummed_every = crate::types::EveryPattern::new(
+                            every.interval,
+                            every.base.clone().strum(spread, down),
+                            every.transformed.clone().strum(spread, down),
+                        );
+                        Ok(Value::EveryPattern(Box::new(strummed_every)))
+                    }
+                    _ => Err(anyhow!("strum() first argument must be a pattern or chord")),
+                }
+            }),
+        );
+
         self.register(
             "stutter",
             "Pattern",
@@ -604,6 +1737,431 @@ impl FunctionRegistry {
             }),
         );
 
+        self.register(
+            "run",
+            "Pattern",
+            "Generates the numbers 0 to n-1 as steps. Steps are notes, so each number k is MIDI note k, as with note(k); n can be at most 128.",
+            "run(n: Number) -> Pattern",
+            Arc::new(|evaluator, args, env| {
+                if args.len() != 1 {
+                    return Err(anyhow!("run() expects 1 argument: n"));
+                }
+
+                let n = match evaluator.eval_with_env(args[0].clone(), env.clone())? {
+                    Value::Number(n) if (0..=128).contains(&n) => n as usize,
+                    Value::Number(n) => {
+                        return Err(anyhow!("run() length must be between 0 and 128, got {}", n))
+                    }
+                    _ => return Err(anyhow!("run() argument must be a number")),
+                };
+
+                let pattern = crate::types::Pattern::run(Note::from_midi(0)?, n)
+                    .map_err(|e| anyhow!("run(): {}", e))?;
+                Ok(Value::Pattern(pattern))
+            }),
+        );
+
+        self.register(
+            "run_notes",
+            "Pattern",
+            "Generates n ascending chromatic notes starting at root, up to MIDI note 127.",
+            "run_notes(root: Note, n: Number) -> Pattern",
+            Arc::new(|evaluator, args, env| {
+                if args.len() != 2 {
+                    return Err(anyhow!("run_notes() expects 2 arguments: root, n"));
+                }
+
+                let root_value = evaluator.eval_with_env(args[0].clone(), env.clone())?;
+                let n_value = evaluator.eval_with_env(args[1].clone(), env.clone())?;
+
+                let root = match root_value {
+                    Value::Note(note) => note,
+                    _ => return Err(anyhow!("run_notes() first argument must be a note")),
+                };
+
+                let n = match n_value {
+                    Value::Number(n) if n >= 0 => n as usize,
+                    Value::Number(n) => {
+                        return Err(anyhow!("run_notes() length can't be negative, got {}", n))
+                    }
+                    _ => return Err(anyhow!("run_notes() second argument must be a number")),
+                };
+
+                let pattern = crate::types::Pattern::run(root, n)
+                    .map_err(|e| anyhow!("run_notes(): {}", e))?;
+                Ok(Value::Pattern(pattern))
+            }),
+        );
+
+        self.register(
+            "melody",
+            "Pattern",
+            "Generates length notes by sampling scale degrees with the given weights (uniform if the weights don't match the scale size), at most 1024 of them. The same seed always gives the same melody.",
+            "melody(key: Note, mode: String, length: Number, weights: Array) -> Pattern or melody(key: Note, mode: String, length: Number, weights: Array, seed: Number) -> Pattern",
+            Arc::new(|evaluator, args, env| {
+                if args.len() != 4 && args.len() != 5 {
+                    return Err(anyhow!(
+                        "melody() expects 4 or 5 arguments: key, mode, length, weights, [seed]"
+                    ));
+                }
+
+                let values = args
+                    .into_iter()
+                    .map(|arg| evaluator.eval_with_env(arg, env.clone()))
+                    .collect::<Result<Vec<_>>>()?;
+
+                let key = match &values[0] {
+                    Value::Note(note) => *note,
+                    _ => return Err(anyhow!("melody() first argument must be a note")),
+                };
+                let mode = match &values[1] {
+                    Value::String(name) => ScaleMode::from_name(name)
+                        .ok_or_else(|| anyhow!("melody(): unknown mode '{}'", name))?,
+                    _ => return Err(anyhow!("melody() second argument must be a mode name")),
+                };
+                let length = match &values[2] {
+                    Value::Number(n) if (0..=1024).contains(n) => *n as usize,
+                    _ => {
+                        return Err(anyhow!(
+                            "melody() length must be a number from 0 to 1024"
+                        ))
+                    }
+                };
+                let weights: Vec<f64> = match &values[3] {
+                    Value::Array(items) => items
+                        .iter()
+                        .map(|item| match item {
+                            Value::Number(n) => Ok(*n as f64),
+                            _ => Err(anyhow!("melody() weights must be numbers")),
+                        })
+                        .collect::<Result<_>>()?,
+                    // `[]` evaluates to an empty chord
+                    Value::Chord(chord) if chord.is_empty() => Vec::new(),
+                    _ => return Err(anyhow!("melody() fourth argument must be an array of weights")),
+                };
+                let seed = match values.get(4) {
+                    Some(Value::Number(n)) => *n as u64,
+                    Some(_) => return Err(anyhow!("melody() seed must be a number")),
+                    None => DEFAULT_SEED,
+                };
+
+                let scale = mode.notes(key);
+                let weights = if weights.len() == scale.len() {
+                    weights
+                } else {
+                    vec![1.0; scale.len()]
+                };
+
+                let mut rng = SeededRng::new(seed);
+                let steps = (0..length)
+                    .map(|_| {
+                        let degree = rng.weighted_index(&weights).ok_or_else(|| {
+                            anyhow!("melody() weights must include a positive value")
+                        })?;
+                        Ok(crate::types::PatternStep::Note(scale[degree]))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+
+                Ok(Value::Pattern(crate::types::Pattern::with_steps(steps)))
+            }),
+        );
+
+        self.register(
+            "in_scale",
+            "Pattern",
+            "Moves every note of a pattern, chord or note to the nearest tone of a scale. A note halfway between two scale tones goes down. Rests and drums are left alone.",
+            "in_scale(target: Pattern | Chord | Note, name: String, root: Note) -> Pattern | Chord | Note",
+            Arc::new(|evaluator, args, env| {
+                if args.len() != 3 {
+                    return Err(anyhow!("in_scale() expects 3 arguments: target, name, root"));
+                }
+
+                let values = args
+                    .into_iter()
+                    .map(|arg| evaluator.eval_with_env(arg, env.clone()))
+                    .collect::<Result<Vec<_>>>()?;
+
+                let mode = match &values[1] {
+                    Value::String(name) => ScaleMode::from_name(name).ok_or_else(|| {
+                        anyhow!(
+                            "in_scale(): unknown scale '{}'. Known scales: {}",
+                            name,
+                            ScaleMode::NAMES.join(", ")
+                        )
+                    })?,
+                    _ => return Err(anyhow!("in_scale() second argument must be a scale name")),
+                };
+                let root = match &values[2] {
+                    Value::Note(note) => *note,
+                    _ => return Err(anyhow!("in_scale() third argument must be a note")),
+                };
+                let snap = |note| mode.snap(root, note);
+
+                match &values[0] {
+                    Value::Note(note) => Ok(Value::Note(snap(*note))),
+                    Value::Chord(chord) => Ok(Value::Chord(chord.clone().map_notes(snap))),
+                    Value::Pattern(p) => Ok(Value::Pattern(p.clone().map_notes(snap))),
+                    Value::String(s) => crate::types::Pattern::parse(s)
+                        .map(|p| Value::Pattern(p.map_notes(snap)))
+                        .map_err(|e| anyhow!("in_scale(): invalid pattern: {}", e)),
+                    _ => Err(anyhow!("in_scale() first argument must be a pattern, chord or note")),
+                }
+            }),
+        );
+
+        self.register(
+            "toscale",
+            "Pattern",
+            "Maps each number to that degree of the scale, counting from 0 at the key. Numbers past the top of the scale wrap into higher octaves; negative ones go below the key.",
+            "toscale(numbers: Array, key: Note, mode: String) -> Pattern",
+            Arc::new(|evaluator, args, env| {
+                if args.len() != 3 {
+                    return Err(anyhow!("toscale() expects 3 arguments: numbers, key, mode"));
+                }
+
+                let values = args
+                    .into_iter()
+                    .map(|arg| evaluator.eval_with_env(arg, env.clone()))
+                    .collect::<Result<Vec<_>>>()?;
+
+                let degrees: Vec<i32> = match &values[0] {
+                    Value::Array(items) => items
+                        .iter()
+                        .map(|item| match item {
+                            Value::Number(n) => Ok(*n),
+                            _ => Err(anyhow!("toscale() numbers must be numbers")),
+                        })
+                        .collect::<Result<_>>()?,
+                    // `[]` evaluates to an empty chord
+                    Value::Chord(chord) if chord.is_empty() => Vec::new(),
+                    _ => return Err(anyhow!("toscale() first argument must be an array of numbers")),
+                };
+                let key = match &values[1] {
+                    Value::Note(note) => *note,
+                    _ => return Err(anyhow!("toscale() second argument must be a note")),
+                };
+                let mode = match &values[2] {
+                    Value::String(name) => ScaleMode::from_name(name)
+                        .ok_or_else(|| anyhow!("toscale(): unknown mode '{}'", name))?,
+                    _ => return Err(anyhow!("toscale() third argument must be a mode name")),
+                };
+
+                let steps = degrees
+                    .into_iter()
+                    .map(|degree| {
+                        mode.degree(key, degree)
+                            .map(crate::types::PatternStep::Note)
+                            .ok_or_else(|| anyhow!("toscale(): degree {} is out of range", degree))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                Ok(Value::Pattern(crate::types::Pattern::with_steps(steps)))
+            }),
+        );
+
+        self.register(
+            "scale",
+            "Pattern",
+            "Returns one octave of a scale as a pattern of notes, starting at the root. Scale names are case-insensitive.",
+            "scale(name: String, root: Note) -> Pattern",
+            Arc::new(|evaluator, args, env| {
+                if args.len() != 2 {
+                    return Err(anyhow!("scale() expects 2 arguments: name, root"));
+                }
+
+                let values = args
+                    .into_iter()
+                    .map(|arg| evaluator.eval_with_env(arg, env.clone()))
+                    .collect::<Result<Vec<_>>>()?;
+
+                let mode = match &values[0] {
+                    Value::String(name) => ScaleMode::from_name(name).ok_or_else(|| {
+                        anyhow!(
+                            "scale(): unknown scale '{}'. Known scales: {}",
+                            name,
+                            ScaleMode::NAMES.join(", ")
+                        )
+                    })?,
+                    _ => return Err(anyhow!("scale() first argument must be a scale name")),
+                };
+                let root = match &values[1] {
+                    Value::Note(note) => *note,
+                    _ => return Err(anyhow!("scale() second argument must be a note")),
+                };
+
+                let steps = (0..mode.intervals().len() as i32)
+                    .map(|degree| {
+                        mode.degree(root, degree)
+                            .map(crate::types::PatternStep::Note)
+                            .ok_or_else(|| anyhow!("scale(): {} is out of range", root))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                Ok(Value::Pattern(crate::types::Pattern::with_steps(steps)))
+            }),
+        );
+
+        self.register(
+            "enclose",
+            "Pattern",
+            "Approaches target with an enclosure: the next scale tone above it, the note a semitone below it, then the target.",
+            "enclose(target: Note, key: Note, mode: String) -> Pattern",
+            Arc::new(|evaluator, args, env| {
+                if args.len() != 3 {
+                    return Err(anyhow!("enclose() expects 3 arguments: target, key, mode"));
+                }
+
+                let values = args
+                    .into_iter()
+                    .map(|arg| evaluator.eval_with_env(arg, env.clone()))
+                    .collect::<Result<Vec<_>>>()?;
+
+                let target = match &values[0] {
+                    Value::Note(note) => *note,
+                    _ => return Err(anyhow!("enclose() first argument must be a note")),
+                };
+                let key = match &values[1] {
+                    Value::Note(note) => *note,
+                    _ => return Err(anyhow!("enclose() second argument must be a note")),
+                };
+                let mode = match &values[2] {
+                    Value::String(name) => ScaleMode::from_name(name)
+                        .ok_or_else(|| anyhow!("enclose(): unknown mode '{}'", name))?,
+                    _ => return Err(anyhow!("enclose() third argument must be a mode name")),
+                };
+
+                let steps = mode
+                    .enclose(key, target)
+                    .into_iter()
+                    .map(crate::types::PatternStep::Note)
+                    .collect();
+                Ok(Value::Pattern(crate::types::Pattern::with_steps(steps)))
+            }),
+        );
+
+        self.register(
+            "tensions",
+            "Chord",
+            "The tensions (9, 11, 13 and alterations) that fit over a chord in a key, leaving out avoid notes like the 11 over a major chord. Dominant chords also get the altered tensions.",
+            "tensions(chord: Chord, key: Note, mode: String) -> Pattern",
+            Arc::new(|evaluator, args, env| {
+                if args.len() != 3 {
+                    return Err(anyhow!("tensions() expects 3 arguments: chord, key, mode"));
+                }
+
+                let values = args
+                    .into_iter()
+                    .map(|arg| evaluator.eval_with_env(arg, env.clone()))
+                    .collect::<Result<Vec<_>>>()?;
+
+                let chord = match &values[0] {
+                    Value::Chord(chord) => chord,
+                    _ => return Err(anyhow!("tensions() first argument must be a chord")),
+                };
+                let key = match &values[1] {
+                    Value::Note(note) => *note,
+                    _ => return Err(anyhow!("tensions() second argument must be a note")),
+                };
+                let mode = match &values[2] {
+                    Value::String(name) => ScaleMode::from_name(name)
+                        .ok_or_else(|| anyhow!("tensions(): unknown mode '{}'", name))?,
+                    _ => return Err(anyhow!("tensions() third argument must be a mode name")),
+                };
+
+                let steps = chord
+                    .tensions(key, mode)
+                    .into_iter()
+                    .filter(|tension| !tension.avoid)
+                    .map(|tension| crate::types::PatternStep::Note(tension.note))
+                    .collect();
+                Ok(Value::Pattern(crate::types::Pattern::with_steps(steps)))
+            }),
+        );
+
+        self.register(
+            "chord_scale",
+            "Analysis",
+            "Names the scale to play over a chord in a key: the key's own scale read from the chord root when the chord fits the key (dorian over Dm7 in C major), otherwise the most common mode holding every chord tone.",
+            "chord_scale(chord: Chord, key: Note, mode: String) -> String",
+            Arc::new(|evaluator, args, env| {
+                if args.len() != 3 {
+                    return Err(anyhow!("chord_scale() expects 3 arguments: chord, key, mode"));
+                }
+
+                let values = args
+                    .into_iter()
+                    .map(|arg| evaluator.eval_with_env(arg, env.clone()))
+                    .collect::<Result<Vec<_>>>()?;
+
+                let chord = match &values[0] {
+                    Value::Chord(chord) => chord,
+                    _ => return Err(anyhow!("chord_scale() first argument must be a chord")),
+                };
+                let key = match &values[1] {
+                    Value::Note(note) => *note,
+                    _ => return Err(anyhow!("chord_scale() second argument must be a note")),
+                };
+                let mode = match &values[2] {
+                    Value::String(name) => ScaleMode::from_name(name)
+                        .ok_or_else(|| anyhow!("chord_scale(): unknown mode '{}'", name))?,
+                    _ => return Err(anyhow!("chord_scale() third argument must be a mode name")),
+                };
+
+                // Ambiguous chords take the first suggestion
+                let scale = ScaleMode::for_chord(chord, key, mode)[0];
+                Ok(Value::String(scale.name().to_string()))
+            }),
+        );
+
+        self.register(
+            "comp",
+            "Pattern",
+            "Practice helper: returns [chord, run], the chord held for a cycle and a run up its chord_scale() from the root to the octave, to loop on two tracks.",
+            "comp(chord: Chord, key: Note, mode: String) -> Array",
+            Arc::new(|evaluator, args, env| {
+                if args.len() != 3 {
+                    return Err(anyhow!("comp() expects 3 arguments: chord, key, mode"));
+                }
+
+                let values = args
+                    .into_iter()
+                    .map(|arg| evaluator.eval_with_env(arg, env.clone()))
+                    .collect::<Result<Vec<_>>>()?;
+
+                let chord = match &values[0] {
+                    Value::Chord(chord) => chord,
+                    _ => return Err(anyhow!("comp() first argument must be a chord")),
+                };
+                let key = match &values[1] {
+                    Value::Note(note) => *note,
+                    _ => return Err(anyhow!("comp() second argument must be a note")),
+                };
+                let mode = match &values[2] {
+                    Value::String(name) => ScaleMode::from_name(name)
+                        .ok_or_else(|| anyhow!("comp(): unknown mode '{}'", name))?,
+                    _ => return Err(anyhow!("comp() third argument must be a mode name")),
+                };
+                let root = chord
+                    .root()
+                    .ok_or_else(|| anyhow!("comp() needs a chord with notes"))?;
+
+                let scale = ScaleMode::for_chord(chord, key, mode)[0];
+                let run = (0..=scale.intervals().len() as i32)
+                    .map(|degree| {
+                        scale
+                            .degree(root, degree)
+                            .map(crate::types::PatternStep::Note)
+                            .ok_or_else(|| anyhow!("comp(): {} is out of range", root))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+
+                Ok(Value::Array(vec![
+                    Value::Pattern(crate::types::Pattern::with_steps(vec![
+                        crate::types::PatternStep::Chord(chord.clone()),
+                    ])),
+                    Value::Pattern(crate::types::Pattern::with_steps(run)),
+                ]))
+            }),
+        );
+
         self.register(
             "len",
             "Core",
@@ -632,6 +2190,131 @@ impl FunctionRegistry {
             }),
         );
 
+        self.register(
+            "num",
+            "Core",
+            "Converts a value to a number: a note gives its MIDI number, so num(note(60)) is 60, or its pitch class (C is 0, B is 11) with \"pitch_class\"; a float is rounded and a string is parsed.",
+            "num(value: Note | Number | Float | String, mode?: \"midi\" | \"pitch_class\") -> Number",
+            Arc::new(|evaluator, args, env| {
+                if args.is_empty() || args.len() > 2 {
+                    return Err(anyhow!(
+                        "num() expects 1 or 2 arguments: value, [\"midi\" | \"pitch_class\"]"
+                    ));
+                }
+
+                let value = evaluator.eval_with_env(args[0].clone(), env.clone())?;
+                let midi = match args.get(1) {
+                    None => true,
+                    Some(arg) => match evaluator.eval_with_env(arg.clone(), env.clone())? {
+                        Value::String(mode) if mode == "midi" => true,
+                        Value::String(mode) if mode == "pitch_class" => false,
+                        other => {
+                            return Err(anyhow!(
+                                "num() mode must be \"midi\" or \"pitch_class\", got {}",
+                                other
+                            ))
+                        }
+                    },
+                };
+
+                match value {
+                    Value::Note(note) if midi => Ok(Value::Number(note.midi_note() as i32)),
+                    Value::Note(note) => Ok(Value::Number(note.pitch_class() as i32)),
+                    Value::Number(n) => Ok(Value::Number(n)),
+                    Value::Float(f) if f.is_finite() && f.abs() <= i32::MAX as f32 => {
+                        Ok(Value::Number(f.round() as i32))
+                    }
+                    Value::String(s) => s
+                        .trim()
+                        .parse::<i32>()
+                        .map(Value::Number)
+                        .map_err(|_| anyhow!("num(): \"{}\" is not a whole number", s)),
+                    other => Err(anyhow!("num() can't convert {} to a number", other)),
+                }
+            }),
+        );
+
+        self.register(
+            "note",
+            "Core",
+            "Converts a MIDI note number (0-127) to a note, so note(60) is C4; a note is returned as is and a string is parsed as a note name.",
+            "note(value: Number | Note | String) -> Note",
+            Arc::new(|evaluator, args, env| {
+                if args.len() != 1 {
+                    return Err(anyhow!("note() expects 1 argument"));
+                }
+
+                match evaluator.eval_with_env(args[0].clone(), env.clone())? {
+                    Value::Number(n) => Note::from_midi(n)
+                        .map(Value::Note)
+                        .map_err(|e| anyhow!("note(): {}", e)),
+                    Value::Note(note) => Ok(Value::Note(note)),
+                    Value::String(s) => s
+                        .trim()
+                        .parse::<Note>()
+                        .map(Value::Note)
+                        .map_err(|e| anyhow!("note(): invalid note \"{}\": {}", s, e)),
+                    other => Err(anyhow!("note() can't convert {} to a note", other)),
+                }
+            }),
+        );
+
+        #[cfg(feature = "serde")]
+        self.register(
+            "to_json",
+            "Core",
+            "Serializes a value as JSON, tagged by type.",
+            "to_json(value: Any) -> String",
+            Arc::new(|evaluator, args, env| {
+                if args.len() != 1 {
+                    return Err(anyhow!("to_json() expects 1 argument"));
+                }
+
+                let value = evaluator.eval_with_env(args[0].clone(), env.clone())?;
+                let json = value.to_json().map_err(|e| anyhow!("to_json(): {}", e))?;
+                Ok(Value::String(json))
+            }),
+        );
+
+        #[cfg(feature = "serde")]
+        self.register(
+            "from_json",
+            "Core",
+            "Rebuilds a value from the JSON produced by to_json.",
+            "from_json(json: String) -> Any",
+            Arc::new(|evaluator, args, env| {
+                if args.len() != 1 {
+                    return Err(anyhow!("from_json() expects 1 argument"));
+                }
+
+                match evaluator.eval_with_env(args[0].clone(), env.clone())? {
+                    Value::String(json) => {
+                        Value::from_json(&json).map_err(|e| anyhow!("from_json(): {}", e))
+                    }
+                    _ => Err(anyhow!("from_json() argument must be a string")),
+                }
+            }),
+        );
+
+        self.register(
+            "frequencies",
+            "Core",
+            "Returns the frequencies in Hz of a note, a chord or the first event of a pattern. A rest gives an empty array.",
+            "frequencies(value: Note | Chord | Pattern) -> Array",
+            Arc::new(|evaluator, args, env| {
+                if args.len() != 1 {
+                    return Err(anyhow!("frequencies() expects 1 argument"));
+                }
+
+                let value = evaluator.eval_with_env(args[0].clone(), env)?;
+                let events = value
+                    .event_frequencies()
+                    .map_err(|e| anyhow!("frequencies(): {}", e))?;
+                let first = events.into_iter().next().unwrap_or_default();
+                Ok(Value::Array(first.into_iter().map(Value::Float).collect()))
+            }),
+        );
+
         // cat - variadic pattern concatenation (replaces concat)
         self.register(
             "cat",
@@ -649,22 +2332,27 @@ impl FunctionRegistry {
                     let val = evaluator.eval_with_env(arg, env.clone())?;
                     let pattern = match val {
                         Value::Pattern(p) => p,
-                        Value::String(s) => crate::types::Pattern::parse(&s)
-                            .map_err(|e| anyhow!("cat(): invalid pattern at position {}: {}", i + 1, e))?,
+                        Value::String(s) => crate::types::Pattern::parse(&s).map_err(|e| {
+                            anyhow!("cat(): invalid pattern at position {}: {}", i + 1, e)
+                        })?,
                         Value::Note(n) => crate::types::Pattern::with_steps(vec![
                             crate::types::PatternStep::Note(n),
                         ]),
                         Value::Chord(c) => crate::types::Pattern::with_steps(vec![
                             crate::types::PatternStep::Chord(c),
                         ]),
-                        _ => return Err(anyhow!("cat(): argument {} must be a pattern, note, or chord", i + 1)),
+                        _ => {
+                            return Err(anyhow!(
+                                "cat(): argument {} must be a pattern, note, or chord",
+                                i + 1
+                            ))
+                        }
                     };
                     patterns.push(pattern);
                 }
 
                 // Fold all patterns together
-                let result = patterns.into_iter().reduce(|acc, p| acc.concat(p))
-                    .unwrap(); // Safe: we checked len >= 2
+                let result = patterns.into_iter().reduce(|acc, p| acc.concat(p)).unwrap(); // Safe: we checked len >= 2
                 Ok(Value::Pattern(result))
             }),
         );
@@ -714,15 +2402,21 @@ impl FunctionRegistry {
                     let val = evaluator.eval_with_env(arg, env.clone())?;
                     let pattern = match val {
                         Value::Pattern(p) => p,
-                        Value::String(s) => crate::types::Pattern::parse(&s)
-                            .map_err(|e| anyhow!("stack(): invalid pattern at position {}: {}", i + 1, e))?,
+                        Value::String(s) => crate::types::Pattern::parse(&s).map_err(|e| {
+                            anyhow!("stack(): invalid pattern at position {}: {}", i + 1, e)
+                        })?,
                         Value::Note(n) => crate::types::Pattern::with_steps(vec![
                             crate::types::PatternStep::Note(n),
                         ]),
                         Value::Chord(c) => crate::types::Pattern::with_steps(vec![
                             crate::types::PatternStep::Chord(c),
                         ]),
-                        _ => return Err(anyhow!("stack(): argument {} must be a pattern, note, or chord", i + 1)),
+                        _ => {
+                            return Err(anyhow!(
+                                "stack(): argument {} must be a pattern, note, or chord",
+                                i + 1
+                            ))
+                        }
                     };
                     patterns.push(pattern);
                 }
@@ -734,34 +2428,65 @@ impl FunctionRegistry {
         );
 
         self.register(
-            "transpose",
+            "transpose",
+            "Pattern",
+            "Transposes all notes in a pattern by n semitones.",
+            "transpose(pattern: Pattern, semitones: Number) -> Pattern",
+            Arc::new(|evaluator, args, env| {
+                if args.len() != 2 {
+                    return Err(anyhow!(
+                        "transpose() expects 2 arguments: pattern, semitones"
+                    ));
+                }
+
+                let pattern_value = evaluator.eval_with_env(args[0].clone(), env.clone())?;
+                let semitones_value = evaluator.eval_with_env(args[1].clone(), env.clone())?;
+
+                let pattern = match pattern_value {
+                    Value::Pattern(p) => p,
+                    Value::String(s) => crate::types::Pattern::parse(&s)
+                        .map_err(|e| anyhow!("transpose(): invalid pattern: {}", e))?,
+                    _ => return Err(anyhow!("transpose() first argument must be a pattern")),
+                };
+
+                let semitones = match semitones_value {
+                    Value::Number(n) => n as i8,
+                    Value::Note(note) => note.pitch_class() as i8,
+                    _ => return Err(anyhow!("transpose() second argument must be a number")),
+                };
+
+                Ok(Value::Pattern(pattern.transpose(semitones)))
+            }),
+        );
+
+        self.register(
+            "to_register",
             "Pattern",
-            "Transposes all notes in a pattern by n semitones.",
-            "transpose(pattern: Pattern, semitones: Number) -> Pattern",
+            "Moves a pattern by whole octaves so its average pitch is as close as possible to center, keeping pitch classes. Useful for dropping a line into an instrument's range.",
+            "to_register(pattern: Pattern, center: Note) -> Pattern",
             Arc::new(|evaluator, args, env| {
                 if args.len() != 2 {
                     return Err(anyhow!(
-                        "transpose() expects 2 arguments: pattern, semitones"
+                        "to_register() expects 2 arguments: pattern, center"
                     ));
                 }
 
                 let pattern_value = evaluator.eval_with_env(args[0].clone(), env.clone())?;
-                let semitones_value = evaluator.eval_with_env(args[1].clone(), env.clone())?;
+                let center_value = evaluator.eval_with_env(args[1].clone(), env.clone())?;
 
                 let pattern = match pattern_value {
                     Value::Pattern(p) => p,
                     Value::String(s) => crate::types::Pattern::parse(&s)
-                        .map_err(|e| anyhow!("transpose(): invalid pattern: {}", e))?,
-                    _ => return Err(anyhow!("transpose() first argument must be a pattern")),
+                        .map_err(|e| anyhow!("to_register(): invalid pattern: {}", e))?,
+                    _ => return Err(anyhow!("to_register() first argument must be a pattern")),
                 };
 
-                let semitones = match semitones_value {
-                    Value::Number(n) => n as i8,
-                    Value::Note(note) => note.pitch_class() as i8,
-                    _ => return Err(anyhow!("transpose() second argument must be a number")),
+                let center = match center_value {
+                    Value::Note(note) => note,
+                    _ => return Err(anyhow!("to_register() second argument must be a note")),
                 };
 
-                Ok(Value::Pattern(pattern.transpose(semitones)))
+                Ok(Value::Pattern(pattern.to_register(center)))
             }),
         );
 
@@ -781,7 +2506,7 @@ impl FunctionRegistry {
                 // - Function style: every(n, transform, pattern)
                 // - Method style:   every(pattern, n, transform) (desugared from pattern.every(n, transform))
                 let first_val = evaluator.eval_with_env(args[0].clone(), env.clone())?;
-                
+
                 let (n, transform_arg_idx, pattern_val) = match first_val {
                     // Function call style: every(n, transform, pattern)
                     Value::Number(num) => {
@@ -808,20 +2533,8 @@ impl FunctionRegistry {
                 };
 
                 // Extract the transform function name from the appropriate argument
-                let transform_name = match &args[transform_arg_idx] {
-                    Expression::Variable(name) => name.clone(),
-                    Expression::String(s) => s.clone(),
-                    Expression::Pattern(p) => p.to_string(),
-                    Expression::FunctionCall {
-                        name,
-                        args: internal_args,
-                    } if internal_args.is_empty() => name.clone(),
-                    _ => {
-                        return Err(anyhow!(
-                            "every() expects a function name as transform argument"
-                        ));
-                    }
-                };
+                let transform_name = transform_name(&args[transform_arg_idx], &env)
+                    .ok_or_else(|| transform_error("every"))?;
 
                 // Parse the base pattern
                 let base_pattern = match pattern_val {
@@ -864,75 +2577,470 @@ impl FunctionRegistry {
                     }
                 };
 
-                // 5. Return the EveryPattern combinator
-                use crate::types::EveryPattern;
-                Ok(Value::EveryPattern(Box::new(EveryPattern::new(
-                    n,
-                    base_pattern,
-                    transformed_pattern,
-                ))))
+                // 5. Return the EveryPattern combinator
+                use crate::types::EveryPattern;
+                Ok(Value::EveryPattern(Box::new(EveryPattern::new(
+                    n,
+                    base_pattern,
+                    transformed_pattern,
+                ))))
+            }),
+        );
+
+        self.register(
+            "sometimes_by",
+            "Pattern",
+            "Applies a transformation on a random share of cycles during playback, each cycle with the given chance in percent (0-100). The choice changes every cycle and differs between tracks.",
+            "sometimes_by(percent: Number, transform: String | Function, pattern: Pattern) -> ProbabilityPattern",
+            Arc::new(|evaluator, args, env| {
+                if args.len() != 3 {
+                    return Err(anyhow!(
+                        "sometimes_by() expects 3 arguments: percent, function_name, pattern"
+                    ));
+                }
+
+                // Same calling conventions as every():
+                // - Function style: sometimes_by(percent, transform, pattern)
+                // - Method style:   sometimes_by(pattern, percent, transform)
+                let first_val = evaluator.eval_with_env(args[0].clone(), env.clone())?;
+                let (percent_val, transform_arg_idx, pattern_val) = match first_val {
+                    Value::Pattern(_) | Value::String(_) => {
+                        let percent_val = evaluator.eval_with_env(args[1].clone(), env.clone())?;
+                        (percent_val, 2usize, first_val)
+                    }
+                    _ => {
+                        let pattern_val = evaluator.eval_with_env(args[2].clone(), env.clone())?;
+                        (first_val, 1usize, pattern_val)
+                    }
+                };
+                let percent = match percent_val {
+                    Value::Number(n) if (0..=100).contains(&n) => n as u8,
+                    _ => {
+                        return Err(anyhow!(
+                            "sometimes_by() percent must be a number from 0 to 100"
+                        ))
+                    }
+                };
+
+                let transform_name = transform_name(&args[transform_arg_idx], &env)
+                    .ok_or_else(|| transform_error("sometimes_by"))?;
+
+                let base_pattern = match pattern_val {
+                    Value::Pattern(p) => p,
+                    Value::String(s) => crate::types::Pattern::parse(&s)
+                        .map_err(|e| anyhow!("sometimes_by(): invalid pattern: {}", e))?,
+                    _ => return Err(anyhow!("sometimes_by() expects a pattern")),
+                };
+
+                // Pre-compute the transformed pattern, as every() does
+                let call_expr = Expression::FunctionCall {
+                    name: transform_name.clone(),
+                    args: vec![Expression::Pattern(base_pattern.clone())],
+                };
+                let transformed_pattern = match evaluator.eval_with_env(call_expr, env.clone())? {
+                    Value::Pattern(p) => p,
+                    _ => {
+                        return Err(anyhow!(
+                            "Transform function '{}' must return a pattern",
+                            transform_name
+                        ));
+                    }
+                };
+
+                // Mix in _track (set by the playback loop) so two tracks
+                // playing the same expression don't pick the same cycles
+                let track_id = env
+                    .as_ref()
+                    .and_then(|e| match e.lookup("_track") {
+                        Some(Value::Number(n)) => Some(n.max(0) as usize),
+                        _ => None,
+                    })
+                    .unwrap_or(0);
+
+                Ok(Value::ProbabilityPattern(Box::new(
+                    crate::types::ProbabilityPattern::new(
+                        percent,
+                        probability_seed(DEFAULT_SEED, track_id, 0),
+                        base_pattern,
+                        transformed_pattern,
+                    ),
+                )))
+            }),
+        );
+
+        self.register(
+            "sometimesBy",
+            "Pattern",
+            "TidalCycles name for sometimes_by().",
+            "sometimesBy(percent: Number, transform: String | Function, pattern: Pattern) -> ProbabilityPattern",
+            Arc::new(|evaluator, args, env| {
+                let sometimes_by = get_registry()
+                    .get("sometimes_by")
+                    .ok_or_else(|| anyhow!("sometimes_by() is not registered"))?;
+                (sometimes_by.handler)(evaluator, args, env)
+            }),
+        );
+
+        self.register(
+            "sometimes",
+            "Pattern",
+            "With the transform first, applies it on about half of the cycles during playback, like sometimes_by() with 50 percent. With the pattern first, as in pattern.sometimes(rev), applies it to about half of the steps instead, like some_steps() with 50 percent.",
+            "sometimes(transform: String | Function, pattern: Pattern) -> ProbabilityPattern or sometimes(pattern: Pattern, transform: String | Function) -> Pattern",
+            Arc::new(|evaluator, args, env| {
+                if args.len() != 2 {
+                    return Err(anyhow!(
+                        "sometimes() expects 2 arguments: function_name, pattern"
+                    ));
+                }
+
+                // The argument order picks per-cycle or per-step transforms
+                let per_cycle = transform_function(&args[0], &env).is_some();
+                let (forward_to, forwarded) = if per_cycle {
+                    let mut forwarded = vec![Expression::Number(50)];
+                    forwarded.extend(args);
+                    ("sometimes_by", forwarded)
+                } else {
+                    let mut forwarded = args;
+                    forwarded.insert(1, Expression::Number(50));
+                    ("some_steps", forwarded)
+                };
+
+                let handler = get_registry()
+                    .get(forward_to)
+                    .ok_or_else(|| anyhow!("{}() is not registered", forward_to))?;
+                (handler.handler)(evaluator, forwarded, env)
+            }),
+        );
+
+        self.register(
+            "jux",
+            "Pattern",
+            "Plays a pattern panned left against a transformed copy panned right, like TidalCycles' jux: \"C E G _\".jux(rev).",
+            "jux(transform: String | Function, pattern: Pattern) -> Pattern",
+            Arc::new(|evaluator, args, env| {
+                if args.len() != 2 {
+                    return Err(anyhow!("jux() expects 2 arguments: function_name, pattern"));
+                }
+
+                // Method style `pattern.jux(rev)` arrives as (pattern, transform),
+                // so the first argument is the transform only if it names a function
+                let (transform_name, pattern_arg) = match transform_function(&args[0], &env) {
+                    Some(name) => (name, &args[1]),
+                    None => match transform_function(&args[1], &env) {
+                        Some(name) => (name, &args[0]),
+                        None => return Err(transform_error("jux")),
+                    },
+                };
+
+                let base_pattern = match evaluator.eval_with_env(pattern_arg.clone(), env.clone())? {
+                    Value::Pattern(p) => p,
+                    Value::String(s) => crate::types::Pattern::parse(&s)
+                        .map_err(|e| anyhow!("jux(): invalid pattern: {}", e))?,
+                    _ => return Err(anyhow!("jux() expects a pattern")),
+                };
+
+                let call_expr = Expression::FunctionCall {
+                    name: transform_name.clone(),
+                    args: vec![Expression::Pattern(base_pattern.clone())],
+                };
+                let transformed_pattern = match evaluator.eval_with_env(call_expr, env.clone())? {
+                    Value::Pattern(p) => p,
+                    _ => {
+                        return Err(anyhow!(
+                            "Transform function '{}' must return a pattern",
+                            transform_name
+                        ));
+                    }
+                };
+
+                Ok(Value::Pattern(base_pattern.jux(transformed_pattern)))
+            }),
+        );
+
+        self.register(
+            "fill",
+            "Pattern",
+            "Plays a fill in place of a looping pattern on the last cycle of every phrase of n cycles (4 by default). The fill is stretched or squeezed to the pattern's cycle length.",
+            "fill(pattern: Pattern, fill_pattern: Pattern) -> EveryPattern or fill(pattern: Pattern, fill_pattern: Pattern, n: Number) -> EveryPattern",
+            Arc::new(|evaluator, args, env| {
+                if args.len() != 2 && args.len() != 3 {
+                    return Err(anyhow!(
+                        "fill() expects 2 or 3 arguments: pattern, fill_pattern, [n]"
+                    ));
+                }
+
+                let values = args
+                    .into_iter()
+                    .map(|arg| evaluator.eval_with_env(arg, env.clone()))
+                    .collect::<Result<Vec<_>>>()?;
+
+                let as_pattern = |value: &Value, what: &str| match value {
+                    Value::Pattern(p) => Ok(p.clone()),
+                    Value::String(s) => crate::types::Pattern::parse(s)
+                        .map_err(|e| anyhow!("fill(): invalid {}: {}", what, e)),
+                    _ => Err(anyhow!("fill() {} must be a pattern", what)),
+                };
+                let base = as_pattern(&values[0], "pattern")?;
+                let mut fill = as_pattern(&values[1], "fill pattern")?;
+                let cycles = match values.get(2) {
+                    Some(Value::Number(n)) if *n >= 1 => *n as usize,
+                    Some(_) => return Err(anyhow!("fill() n must be a whole number of cycles, at least 1")),
+                    None => 4,
+                };
+
+                // The dispatcher times every cycle by the base pattern's length
+                fill.beats_per_cycle = base.beats_per_cycle;
+                Ok(Value::EveryPattern(Box::new(crate::types::EveryPattern::new(
+                    cycles, base, fill,
+                ))))
+            }),
+        );
+
+        // --- Chord/Note Functions ---
+
+        self.register(
+            "invert",
+            "Chord",
+            "Inverts a chord or all chords in a pattern.",
+            "invert(target: Chord | Pattern) -> Chord | Pattern",
+            Arc::new(|evaluator, args, env| {
+                if args.len() != 1 {
+                    return Err(anyhow!("invert() expects 1 argument, got {}", args.len()));
+                }
+
+                let arg_value = evaluator.eval_with_env(args.into_iter().next().unwrap(), env)?;
+                match arg_value {
+                    Value::Chord(chord) => {
+                        let inverted = chord.invert();
+                        Ok(Value::Chord(inverted))
+                    }
+                    Value::Pattern(pattern) => {
+                        let inverted = pattern.map_chords(|chord| chord.invert());
+                        Ok(Value::Pattern(inverted))
+                    }
+                    _ => Err(anyhow!("invert() only works on chords or progressions")),
+                }
+            }),
+        );
+
+        self.register(
+            "invert_down",
+            "Chord",
+            "Inverts a chord or all chords in a pattern downwards (top note drops an octave).",
+            "invert_down(target: Chord | Pattern) -> Chord | Pattern",
+            Arc::new(|evaluator, args, env| {
+                if args.len() != 1 {
+                    return Err(anyhow!(
+                        "invert_down() expects 1 argument, got {}",
+                        args.len()
+                    ));
+                }
+
+                let arg_value = evaluator.eval_with_env(args.into_iter().next().unwrap(), env)?;
+                match arg_value {
+                    Value::Chord(chord) => Ok(Value::Chord(chord.invert_down())),
+                    Value::Pattern(pattern) => Ok(Value::Pattern(
+                        pattern.map_chords(|chord| chord.invert_down()),
+                    )),
+                    _ => Err(anyhow!(
+                        "invert_down() only works on chords or progressions"
+                    )),
+                }
+            }),
+        );
+
+        self.register(
+            "invert_n",
+            "Chord",
+            "Inverts a chord n times.",
+            "invert_n(chord: Chord, n: Note | Number) -> Chord",
+            Arc::new(|evaluator, args, env| {
+                if args.len() != 2 {
+                    return Err(anyhow!(
+                        "invert_n() expects 2 arguments, got {}",
+                        args.len()
+                    ));
+                }
+
+                let mut arg_iter = args.into_iter();
+                let chord_expr = arg_iter.next().unwrap();
+                let n_expr = arg_iter.next().unwrap();
+
+                let chord_value = evaluator.eval_with_env(chord_expr, env.clone())?;
+                let n_value = evaluator.eval_with_env(n_expr, env.clone())?;
+
+                match (chord_value, n_value) {
+                    (Value::Chord(chord), Value::Note(note)) => {
+                        let n = note.pitch_class() as usize;
+                        let inverted = chord.invert_n(n);
+                        Ok(Value::Chord(inverted))
+                    }
+                    (Value::Chord(chord), Value::Number(n)) => {
+                        let n = n as usize;
+                        let inverted = chord.invert_n(n);
+                        Ok(Value::Chord(inverted))
+                    }
+                    _ => Err(anyhow!("invert_n() expects (chord, note/number) arguments")),
+                }
+            }),
+        );
+
+        self.register(
+            "contrary",
+            "Voice Leading",
+            "Moves the top voice up and the bottom voice down by n semitones (contrary motion).",
+            "contrary(chord: Chord, outer: Number) -> Chord",
+            Arc::new(|evaluator, args, env| {
+                if args.len() != 2 {
+                    return Err(anyhow!(
+                        "contrary() expects 2 arguments, got {}",
+                        args.len()
+                    ));
+                }
+
+                let chord_value = evaluator.eval_with_env(args[0].clone(), env.clone())?;
+                let outer_value = evaluator.eval_with_env(args[1].clone(), env)?;
+
+                let outer = match outer_value {
+                    Value::Number(n) => n.clamp(-127, 127) as i8,
+                    Value::Note(note) => note.pitch_class() as i8,
+                    _ => return Err(anyhow!("contrary() second argument must be a number")),
+                };
+
+                match chord_value {
+                    Value::Chord(chord) => Ok(Value::Chord(chord.contrary(outer))),
+                    _ => Err(anyhow!("contrary() expects (chord, number) arguments")),
+                }
+            }),
+        );
+
+        self.register(
+            "alter",
+            "Chord",
+            "Applies a dominant alteration (\"b5\", \"#5\", \"b9\", \"#9\", \"#11\" or \"b13\"), replacing the natural tone it conflicts with or adding it.",
+            "alter(chord: Chord, alteration: String) -> Chord",
+            Arc::new(|evaluator, args, env| {
+                if args.len() != 2 {
+                    return Err(anyhow!("alter() expects 2 arguments, got {}", args.len()));
+                }
+
+                let chord_value = evaluator.eval_with_env(args[0].clone(), env.clone())?;
+                let alteration_value = evaluator.eval_with_env(args[1].clone(), env)?;
+
+                let alteration = match alteration_value {
+                    Value::String(s) => s,
+                    // "b5", "b9" and "b13" are read as the notes B5, B9 and B13
+                    Value::Pattern(p) => match p.steps.as_slice() {
+                        [crate::types::PatternStep::Note(n)] if n.pitch_class() == 11 => {
+                            format!("b{}", n.octave())
+                        }
+                        _ => return Err(anyhow!("alter() second argument must be an alteration")),
+                    },
+                    _ => return Err(anyhow!("alter() second argument must be an alteration")),
+                };
+
+                match chord_value {
+                    Value::Chord(chord) => chord
+                        .alter(&alteration)
+                        .map(Value::Chord)
+                        .map_err(|e| anyhow!("alter(): {}", e)),
+                    _ => Err(anyhow!("alter() expects (chord, string) arguments")),
+                }
+            }),
+        );
+
+        self.register(
+            "voices",
+            "Chord",
+            "Doubles chord tones (root, then fifth, then third, an octave up each time) until the chord has n notes. Fewer than the chord has drops doublings, then the fifth.",
+            "voices(chord: Chord, n: Number) -> Chord",
+            Arc::new(|evaluator, args, env| {
+                if args.len() != 2 {
+                    return Err(anyhow!("voices() expects 2 arguments, got {}", args.len()));
+                }
+
+                let chord_value = evaluator.eval_with_env(args[0].clone(), env.clone())?;
+                let n = match evaluator.eval_with_env(args[1].clone(), env)? {
+                    Value::Number(n) if n > 0 => n as usize,
+                    _ => return Err(anyhow!("voices() count must be a positive number")),
+                };
+
+                match chord_value {
+                    Value::Chord(chord) => Ok(Value::Chord(chord.voices(n))),
+                    _ => Err(anyhow!("voices() first argument must be a chord")),
+                }
             }),
         );
 
-        // --- Chord/Note Functions ---
-
         self.register(
-            "invert",
+            "arpeggiate",
             "Chord",
-            "Inverts a chord or all chords in a pattern.",
-            "invert(target: Chord | Pattern) -> Chord | Pattern",
+            "Plays a chord's notes one after another: \"up\" (default), \"down\", \"updown\", \"downup\" or \"converge\". Given a pattern, each chord is arpeggiated within its own step.",
+            "arpeggiate(chord: Chord) -> Pattern or arpeggiate(chord: Chord | Pattern, mode: String) -> Pattern",
             Arc::new(|evaluator, args, env| {
-                if args.len() != 1 {
-                    return Err(anyhow!("invert() expects 1 argument, got {}", args.len()));
+                if args.is_empty() || args.len() > 2 {
+                    return Err(anyhow!(
+                        "arpeggiate() expects 1 or 2 arguments: chord, [mode]"
+                    ));
                 }
 
-                let arg_value = evaluator.eval_with_env(args.into_iter().next().unwrap(), env)?;
-                match arg_value {
+                let values = args
+                    .into_iter()
+                    .map(|arg| evaluator.eval_with_env(arg, env.clone()))
+                    .collect::<Result<Vec<_>>>()?;
+                let mode = match values.get(1) {
+                    Some(Value::String(mode)) => mode.as_str(),
+                    Some(_) => return Err(anyhow!("arpeggiate() mode must be a string")),
+                    None => "up",
+                };
+                let arpeggiate = |pattern: crate::types::Pattern| {
+                    pattern
+                        .arpeggiate(mode)
+                        .map(Value::Pattern)
+                        .map_err(|e| anyhow!("arpeggiate(): {}", e))
+                };
+
+                match &values[0] {
                     Value::Chord(chord) => {
-                        let inverted = chord.invert();
-                        Ok(Value::Chord(inverted))
+                        let notes = chord
+                            .arpeggio(mode)
+                            .map_err(|e| anyhow!("arpeggiate(): {}", e))?;
+                        Ok(Value::Pattern(crate::types::Pattern::with_steps(
+                            notes.into_iter().map(crate::types::PatternStep::Note).collect(),
+                        )))
                     }
-                    Value::Pattern(pattern) => {
-                        let inverted = pattern.map_chords(|chord| chord.invert());
-                        Ok(Value::Pattern(inverted))
-                    }
-                    _ => Err(anyhow!("invert() only works on chords or progressions")),
+                    Value::Pattern(p) => arpeggiate(p.clone()),
+                    Value::String(s) => arpeggiate(
+                        crate::types::Pattern::parse(s)
+                            .map_err(|e| anyhow!("arpeggiate(): invalid pattern: {}", e))?,
+                    ),
+                    _ => Err(anyhow!(
+                        "arpeggiate() first argument must be a chord or a pattern of chords"
+                    )),
                 }
             }),
         );
 
         self.register(
-            "invert_n",
+            "voicing",
             "Chord",
-            "Inverts a chord n times.",
-            "invert_n(chord: Chord, n: Note | Number) -> Chord",
+            "Revoices a chord in close position or as a drop-2, drop-3 or drop-2-4 voicing.",
+            "voicing(chord: Chord, kind: String) -> Chord",
             Arc::new(|evaluator, args, env| {
                 if args.len() != 2 {
-                    return Err(anyhow!(
-                        "invert_n() expects 2 arguments, got {}",
-                        args.len()
-                    ));
+                    return Err(anyhow!("voicing() expects 2 arguments, got {}", args.len()));
                 }
 
-                let mut arg_iter = args.into_iter();
-                let chord_expr = arg_iter.next().unwrap();
-                let n_expr = arg_iter.next().unwrap();
-
-                let chord_value = evaluator.eval_with_env(chord_expr, env.clone())?;
-                let n_value = evaluator.eval_with_env(n_expr, env.clone())?;
-
-                match (chord_value, n_value) {
-                    (Value::Chord(chord), Value::Note(note)) => {
-                        let n = note.pitch_class() as usize;
-                        let inverted = chord.invert_n(n);
-                        Ok(Value::Chord(inverted))
-                    }
-                    (Value::Chord(chord), Value::Number(n)) => {
-                        let n = n as usize;
-                        let inverted = chord.invert_n(n);
-                        Ok(Value::Chord(inverted))
-                    }
-                    _ => Err(anyhow!("invert_n() expects (chord, note/number) arguments")),
+                let mut args_iter = args.into_iter();
+                let chord_val = evaluator.eval_with_env(args_iter.next().unwrap(), env.clone())?;
+                let kind_val = evaluator.eval_with_env(args_iter.next().unwrap(), env)?;
+                match (chord_val, kind_val) {
+                    (Value::Chord(chord), Value::String(kind)) => chord
+                        .voicing(&kind)
+                        .map(Value::Chord)
+                        .map_err(|e| anyhow!("voicing(): {}", e)),
+                    (Value::Chord(_), _) => Err(anyhow!("voicing() kind must be a string")),
+                    _ => Err(anyhow!("voicing() first argument must be a chord")),
                 }
             }),
         );
@@ -985,6 +3093,113 @@ impl FunctionRegistry {
             }),
         );
 
+        self.register(
+            "upper_structure",
+            "Chord",
+            "Names an upper-structure triad over the lower chord (e.g. \"D triad over C7 (9 #11 13)\"), or the plain chord name if there is none.",
+            "upper_structure(chord: Chord) -> String",
+            Arc::new(|evaluator, args, env| {
+                if args.len() != 1 {
+                    return Err(anyhow!(
+                        "upper_structure() expects 1 argument, got {}",
+                        args.len()
+                    ));
+                }
+
+                let arg_value = evaluator.eval_with_env(args.into_iter().next().unwrap(), env)?;
+                match arg_value {
+                    Value::Chord(chord) => Ok(Value::String(chord.upper_structure())),
+                    _ => Err(anyhow!("upper_structure() only works on chords")),
+                }
+            }),
+        );
+
+        self.register(
+            "full_symbol",
+            "Chord",
+            "The most complete chord symbol, with extensions, alterations and a slash bass (e.g. \"Cmaj7#11/E\", \"G7b9#9\").",
+            "full_symbol(chord: Chord) -> String",
+            Arc::new(|evaluator, args, env| {
+                if args.len() != 1 {
+                    return Err(anyhow!(
+                        "full_symbol() expects 1 argument, got {}",
+                        args.len()
+                    ));
+                }
+
+                let arg_value = evaluator.eval_with_env(args.into_iter().next().unwrap(), env)?;
+                match arg_value {
+                    Value::Chord(chord) => Ok(Value::String(chord.full_symbol())),
+                    _ => Err(anyhow!("full_symbol() only works on chords")),
+                }
+            }),
+        );
+
+        self.register(
+            "from_intervals",
+            "Chord",
+            "Builds a chord by stacking semitone intervals above a root (the inverse of reading a chord's intervals). Repeated intervals collapse, and every note must stay within MIDI note 127.",
+            "from_intervals(root: Note, intervals: Array) -> Chord",
+            Arc::new(|evaluator, args, env| {
+                if args.len() != 2 {
+                    return Err(anyhow!(
+                        "from_intervals() expects 2 arguments (root, intervals), got {}",
+                        args.len()
+                    ));
+                }
+
+                let root = match evaluator.eval_with_env(args[0].clone(), env.clone())? {
+                    Value::Note(note) => note,
+                    _ => return Err(anyhow!("from_intervals() root must be a note")),
+                };
+                // Every note has to stay inside MIDI 0-127
+                let room = 127 - root.midi_note() as i32;
+                let intervals = match evaluator.eval_with_env(args[1].clone(), env)? {
+                    Value::Array(values) => values
+                        .into_iter()
+                        .map(|value| match value {
+                            Value::Number(n) if (0..=room).contains(&n) => Ok(n as u8),
+                            other => Err(anyhow!(
+                                "from_intervals() intervals above {} must be numbers from 0 to {} to stay within MIDI note 127, got {}",
+                                root,
+                                room,
+                                other
+                            )),
+                        })
+                        .collect::<Result<Vec<u8>>>()?,
+                    _ => return Err(anyhow!("from_intervals() intervals must be an array")),
+                };
+
+                Ok(Value::Chord(Chord::from_intervals(root, &intervals)))
+            }),
+        );
+
+        self.register(
+            "chord",
+            "Chord",
+            "Builds a chord on a root from a quality name (\"major\", \"half_diminished\", \"dominant\", ...) or a symbol suffix (\"m7b5\", \"sus4\", \"add9\").",
+            "chord(root: Note, quality: String) -> Chord",
+            Arc::new(|evaluator, args, env| {
+                if args.len() != 2 {
+                    return Err(anyhow!(
+                        "chord() expects 2 arguments (root, quality), got {}",
+                        args.len()
+                    ));
+                }
+
+                let root = match evaluator.eval_with_env(args[0].clone(), env.clone())? {
+                    Value::Note(note) => note,
+                    _ => return Err(anyhow!("chord() root must be a note")),
+                };
+                let quality = match evaluator.eval_with_env(args[1].clone(), env)? {
+                    Value::String(s) => s,
+                    other => return Err(anyhow!("chord() quality must be a string, got {}", other)),
+                };
+
+                Ok(Value::Chord(Chord::build(root, &quality)?))
+            }),
+        );
+
         // --- Transformation/Analysis Functions ---
 
         self.register(
@@ -1036,6 +3251,7 @@ impl FunctionRegistry {
                         return Err(anyhow!("map() first argument must be a function name"));
                     }
                 };
+                let func_name = function_target(func_name, &env);
 
                 let progression_value = evaluator.eval_with_env(progression_expr, env.clone())?;
                 if let Value::Pattern(pattern) = progression_value {
@@ -1049,7 +3265,7 @@ impl FunctionRegistry {
                                 vec![Value::Chord(chord.clone())],
                                 env.clone(),
                             )?;
-                            
+
                             // Extract the chord from the result
                             match result {
                                 Value::Chord(c) => mapped_chords.push(c),
@@ -1077,7 +3293,7 @@ impl FunctionRegistry {
                                 }
                             }
                         }
-                        
+
                         // Rebuild pattern from mapped chords
                         let mut result = crate::types::Pattern::from_chords(mapped_chords);
                         result.beats_per_cycle = pattern.beats_per_cycle;
@@ -1101,7 +3317,6 @@ impl FunctionRegistry {
             }),
         );
 
-
         // Voice Leading
 
         self.register(
@@ -1267,6 +3482,54 @@ impl FunctionRegistry {
             }),
         );
 
+        self.register(
+            "best_voicing",
+            "Voice Leading",
+            "Picks the candidate voicing that moves most smoothly from the previous chord.",
+            "best_voicing(prev: Chord, candidates: Array) -> Chord",
+            Arc::new(|evaluator, args, env| {
+                if args.len() != 2 {
+                    return Err(anyhow!(
+                        "best_voicing() expects 2 arguments, got {}",
+                        args.len()
+                    ));
+                }
+
+                let previous = match evaluator.eval_with_env(args[0].clone(), env.clone())? {
+                    Value::Chord(chord) => chord,
+                    _ => return Err(anyhow!("best_voicing() first argument must be a chord")),
+                };
+                let candidates = match evaluator.eval_with_env(args[1].clone(), env.clone())? {
+                    Value::Array(values) => values
+                        .into_iter()
+                        .map(|value| match value {
+                            Value::Chord(chord) => Ok(chord),
+                            other => Err(anyhow!(
+                                "best_voicing() candidates must be chords, found {}",
+                                other
+                            )),
+                        })
+                        .collect::<Result<Vec<Chord>>>()?,
+                    // A progression literal like [[C, E, G], [E, G, C5]]
+                    Value::Pattern(pattern) => pattern
+                        .as_chords()
+                        .ok_or_else(|| anyhow!("best_voicing() candidates must be chords"))?,
+                    // `[]` evaluates to an empty chord
+                    Value::Chord(chord) if chord.is_empty() => Vec::new(),
+                    _ => {
+                        return Err(anyhow!(
+                            "best_voicing() second argument must be an array of chords"
+                        ))
+                    }
+                };
+
+                previous
+                    .best_voicing(&candidates)
+                    .map(|chord| Value::Chord(chord.clone()))
+                    .ok_or_else(|| anyhow!("best_voicing() needs at least one candidate"))
+            }),
+        );
+
         self.register(
             "analyze_voice_leading",
             "Voice Leading",
@@ -1483,6 +3746,93 @@ impl FunctionRegistry {
             }),
         );
 
+        self.register(
+            "approach",
+            "Progression",
+            "Generates the ii-V that leads into a scale degree (1-7) of a major key, ending on that degree's chord. Degree 1 gives the key's own ii-V-I.",
+            "approach(target_degree: Number, key: Note) -> Pattern",
+            Arc::new(|evaluator, args, env| {
+                if args.len() != 2 {
+                    return Err(anyhow!("approach() expects 2 arguments: target_degree, key"));
+                }
+
+                let degree = evaluator.eval_with_env(args[0].clone(), env.clone())?;
+                let key = evaluator.eval_with_env(args[1].clone(), env)?;
+                let (degree, key) = match (degree, key) {
+                    (Value::Number(n), Value::Note(key)) if (1..=7).contains(&n) => (n as u8, key),
+                    (Value::Note(key), Value::Number(n)) => {
+                        return Err(anyhow!(
+                            "approach() takes the degree first, then the key: approach({}, {})",
+                            n,
+                            key
+                        ))
+                    }
+                    (Value::Number(_), _) => {
+                        return Err(anyhow!(
+                            "approach() target degree must be a number from 1 to 7, then a key note: approach(5, C)"
+                        ))
+                    }
+                    _ => {
+                        return Err(anyhow!(
+                            "approach() expects a target degree (1-7) and a key note: approach(5, C)"
+                        ))
+                    }
+                };
+
+                Ok(Value::Pattern(CommonProgressions::approach(degree, key)?))
+            }),
+        );
+
+        self.register(
+            "chordpro",
+            "Progression",
+            "Writes a pattern's chords as a ChordPro chart in a key, four beats to a bar. Rests become N.C.",
+            "chordpro(pattern: Pattern, key: Note) -> String",
+            Arc::new(|evaluator, args, env| {
+                if args.len() != 2 {
+                    return Err(anyhow!("chordpro() expects 2 arguments: pattern, key"));
+                }
+
+                let pattern = match evaluator.eval_with_env(args[0].clone(), env.clone())? {
+                    Value::Pattern(p) => p,
+                    Value::String(s) => crate::types::Pattern::parse(&s)
+                        .map_err(|e| anyhow!("chordpro(): invalid pattern: {}", e))?,
+                    _ => return Err(anyhow!("chordpro() first argument must be a pattern")),
+                };
+                let key = match evaluator.eval_with_env(args[1].clone(), env)? {
+                    Value::Note(key) => key,
+                    _ => return Err(anyhow!("chordpro() key must be a note")),
+                };
+
+                Ok(Value::String(crate::types::chordpro(&pattern, key)))
+            }),
+        );
+
+        self.register(
+            "musicxml",
+            "Progression",
+            "Writes one cycle of a pattern as a MusicXML score in a key, for notation software like MuseScore. Timing is rounded to 32nd notes.",
+            "musicxml(pattern: Pattern, key: Note) -> String",
+            Arc::new(|evaluator, args, env| {
+                if args.len() != 2 {
+                    return Err(anyhow!("musicxml() expects 2 arguments: pattern, key"));
+                }
+
+                let pattern = match evaluator.eval_with_env(args[0].clone(), env.clone())? {
+                    Value::Pattern(p) => p,
+                    Value::String(s) => crate::types::Pattern::parse(&s)
+                        .map_err(|e| anyhow!("musicxml(): invalid pattern: {}", e))?,
+                    _ => return Err(anyhow!("musicxml() first argument must be a pattern")),
+                };
+                let key = match evaluator.eval_with_env(args[1].clone(), env)? {
+                    Value::Note(key) => key,
+                    _ => return Err(anyhow!("musicxml() key must be a note")),
+                };
+
+                Ok(Value::String(crate::types::musicxml(&pattern, key)))
+            }),
+        );
+
         self.register(
             "analyze_progression",
             "Analysis",
@@ -1527,6 +3877,39 @@ impl FunctionRegistry {
             }),
         );
 
+        self.register(
+            "identify_progression",
+            "Analysis",
+            "Names the common progression a chord pattern plays in a key, or the closest one.",
+            "identify_progression(pattern: Pattern, key: Note) -> String",
+            Arc::new(|evaluator, args, env| {
+                if args.len() != 2 {
+                    return Err(anyhow!(
+                        "identify_progression() expects 2 arguments: pattern, key"
+                    ));
+                }
+
+                let pattern = match evaluator.eval_with_env(args[0].clone(), env.clone())? {
+                    Value::Pattern(p) => p,
+                    Value::String(s) => crate::types::Pattern::parse(&s)
+                        .map_err(|e| anyhow!("identify_progression(): invalid pattern: {}", e))?,
+                    _ => {
+                        return Err(anyhow!(
+                            "identify_progression() first argument must be a pattern"
+                        ))
+                    }
+                };
+                let key = match evaluator.eval_with_env(args[1].clone(), env)? {
+                    Value::Note(key) => key,
+                    _ => return Err(anyhow!("identify_progression() key must be a note")),
+                };
+
+                CommonProgressions::identify(&pattern, key)
+                    .map(Value::String)
+                    .map_err(|e| anyhow!("identify_progression(): {}", e))
+            }),
+        );
+
         // --- Keywords (Documentation Only) ---
 
         let dummy_handler: BuiltinHandler =
@@ -1745,6 +4128,59 @@ impl FunctionRegistry {
             }),
         );
 
+        self.register(
+            "delay",
+            "Audio",
+            "Echoes a pattern through a feedback delay. Time is in beats, or in milliseconds as a string like \"250ms\"; feedback (0-95) sets how much of each echo comes back, mix (0-100) the balance of echoes against the dry sound.",
+            "delay(pattern: Pattern, time: Number, feedback: Number, mix: Number) -> Pattern",
+            Arc::new(|evaluator, args, env| {
+                if args.len() != 4 {
+                    return Err(anyhow!(
+                        "delay() expects 4 arguments: pattern, time, feedback, mix"
+                    ));
+                }
+
+                let values = args
+                    .into_iter()
+                    .map(|arg| evaluator.eval_with_env(arg, env.clone()))
+                    .collect::<Result<Vec<_>>>()?;
+
+                let pattern = match &values[0] {
+                    Value::Pattern(p) => p.clone(),
+                    Value::String(s) => crate::types::Pattern::parse(s)
+                        .map_err(|e| anyhow!("delay(): invalid pattern: {}", e))?,
+                    _ => return Err(anyhow!("delay() first argument must be a pattern")),
+                };
+
+                // Small numbers (0-11) are parsed as notes, so take their pitch class
+                let time = match &values[1] {
+                    Value::Number(n) if *n > 0 => DelayTime::Beats(*n as f32),
+                    Value::Float(n) if *n > 0.0 => DelayTime::Beats(*n),
+                    Value::Note(n) if n.pitch_class() > 0 => {
+                        DelayTime::Beats(n.pitch_class() as f32)
+                    }
+                    Value::String(s) => match s.trim().strip_suffix("ms").map(|ms| ms.trim().parse::<u32>()) {
+                        Some(Ok(ms)) if ms > 0 => DelayTime::Millis(ms),
+                        _ => return Err(anyhow!("delay() time must be beats or milliseconds like \"250ms\"")),
+                    },
+                    _ => return Err(anyhow!("delay() time must be a positive number of beats")),
+                };
+                let percent = |value: &Value, what: &str| match value {
+                    Value::Number(n) if *n >= 0 => Ok(*n as u32),
+                    Value::Note(n) => Ok(n.pitch_class() as u32),
+                    _ => Err(anyhow!("delay() {} must be a percentage", what)),
+                };
+                // Feedback of 100% or more would never fade, so Delay::new clamps it
+                let feedback = percent(&values[2], "feedback")?;
+                let mix = match percent(&values[3], "mix")? {
+                    mix if mix <= 100 => mix,
+                    _ => return Err(anyhow!("delay() mix must be from 0 to 100")),
+                };
+
+                Ok(Value::Pattern(pattern.delay(Delay::new(time, feedback, mix))))
+            }),
+        );
+
         self.register(
             "env",
             "Audio",
@@ -1830,5 +4266,72 @@ impl FunctionRegistry {
                 }
             }),
         );
+
+        self.register(
+            "export_midi",
+            "Audio",
+            "Writes a pattern, or an array of up to 15 patterns (one per track and channel, skipping drum channel 10), to a Standard MIDI File.",
+            "export_midi(pattern, path: String, bars: Number) or export_midi(pattern, path: String, bars: Number, bpm: Number)",
+            Arc::new(|evaluator, args, env| {
+                if args.len() != 3 && args.len() != 4 {
+                    return Err(anyhow!(
+                        "export_midi() expects 3-4 arguments: pattern, path, bars, [bpm]"
+                    ));
+                }
+
+                let source = evaluator.eval_with_env(args[0].clone(), env.clone())?;
+                let path = match evaluator.eval_with_env(args[1].clone(), env.clone())? {
+                    Value::String(s) => s,
+                    _ => return Err(anyhow!("export_midi() path must be a string")),
+                };
+                let bars = match evaluator.eval_with_env(args[2].clone(), env.clone())? {
+                    Value::Number(n) if n > 0 => n as usize,
+                    _ => return Err(anyhow!("export_midi() bars must be a positive number")),
+                };
+                let bpm = match args.get(3) {
+                    Some(arg) => match evaluator.eval_with_env(arg.clone(), env.clone())? {
+                        Value::Number(n) if n > 0 => n as f32,
+                        _ => return Err(anyhow!("export_midi() bpm must be a positive number")),
+                    },
+                    None => 120.0,
+                };
+
+                // An array exports one track per element, anything else a single track
+                let sources = match source {
+                    Value::Array(values) => values,
+                    other => vec![other],
+                };
+                if sources.len() > crate::types::midi_file::MELODIC_CHANNELS {
+                    return Err(anyhow!(
+                        "export_midi() can write at most {} patterns, one per MIDI channel (channel 10 is kept for drums), got {}",
+                        crate::types::midi_file::MELODIC_CHANNELS,
+                        sources.len()
+                    ));
+                }
+
+                let mut file = crate::types::MidiFile::new(bpm);
+                let beats = file.bars_to_beats(bars);
+                for (i, value) in sources.iter().enumerate() {
+                    // Checked against MELODIC_CHANNELS above
+                    let channel = crate::types::midi_file::melodic_channel(i).unwrap_or_default();
+                    let mut track =
+                        crate::types::MidiFileTrack::new(format!("Track {}", i + 1), channel);
+                    track.render(beats, |cycle, _| {
+                        value.pattern_for_cycle(cycle).map_err(|e| anyhow!("{}", e))
+                    })?;
+                    file.tracks.push(track);
+                }
+
+                #[cfg(not(target_arch = "wasm32"))]
+                {
+                    file.write(&path)?;
+                    Ok(Value::String(path))
+                }
+                #[cfg(target_arch = "wasm32")]
+                {
+                    Err(anyhow!("File export not supported in WASM: {}", path))
+                }
+            }),
+        );
     }
 }
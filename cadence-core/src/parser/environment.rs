@@ -12,11 +12,18 @@ use std::sync::{Arc, RwLock};
 /// by the main thread during playback.
 pub type SharedEnvironment = Arc<RwLock<Environment>>;
 
+/// Variables the playback loop sets before evaluating a looping pattern.
+/// Changing them doesn't count towards [`Environment::generation`], since
+/// they are re-set on every tick and the loop tracks their values itself.
+pub const PLAYBACK_VARIABLES: [&str; 3] = ["_beat", "_cycle", "_track"];
+
 /// Scoped environment for variable storage
 #[derive(Debug)]
 pub struct Environment {
     /// Stack of scopes (inner scopes shadow outer ones)
     scopes: Vec<HashMap<String, Value>>,
+    /// Count of changes to anything but the playback variables
+    generation: u64,
 }
 
 impl Environment {
@@ -24,6 +31,21 @@ impl Environment {
     pub fn new() -> Self {
         Environment {
             scopes: vec![HashMap::new()],
+            generation: 0,
+        }
+    }
+
+    /// Changes since creation, not counting the playback variables. Two
+    /// equal generations mean a pattern expression evaluates the same way,
+    /// given the same playback variables.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Record a change to `name`, unless it's a playback variable
+    fn changed(&mut self, name: &str) {
+        if !PLAYBACK_VARIABLES.contains(&name) {
+            self.generation += 1;
         }
     }
 
@@ -32,28 +54,41 @@ impl Environment {
     pub fn clear(&mut self) {
         self.scopes.clear();
         self.scopes.push(HashMap::new());
+        self.generation += 1;
     }
 
     /// Push a new scope (e.g., when entering a block)
     pub fn push_scope(&mut self) {
         self.scopes.push(HashMap::new());
+        self.generation += 1;
     }
 
     /// Pop the current scope (e.g., when exiting a block)
     pub fn pop_scope(&mut self) {
         if self.scopes.len() > 1 {
             self.scopes.pop();
+            self.generation += 1;
         }
         // Never pop the global scope
     }
 
     /// Define a new variable in the current scope
     pub fn define(&mut self, name: String, value: Value) {
+        self.changed(&name);
         if let Some(scope) = self.scopes.last_mut() {
             scope.insert(name, value);
         }
     }
 
+    /// Remove a variable from the global scope. Returns false if it wasn't there.
+    pub fn undefine(&mut self, name: &str) -> bool {
+        let removed = self.scopes[0].remove(name).is_some();
+        if removed {
+            self.changed(name);
+        }
+        removed
+    }
+
     /// Get a variable's value (searches from inner to outer scopes)
     pub fn get(&self, name: &str) -> Option<&Value> {
         // Search from innermost to outermost scope
@@ -68,11 +103,15 @@ impl Environment {
     /// Set a variable's value (updates in the scope where it's defined)
     pub fn set(&mut self, name: &str, value: Value) -> Result<(), String> {
         // Search from innermost to outermost scope
-        for scope in self.scopes.iter_mut().rev() {
-            if scope.contains_key(name) {
-                scope.insert(name.to_string(), value);
-                return Ok(());
-            }
+        if let Some(scope) = self
+            .scopes
+            .iter_mut()
+            .rev()
+            .find(|scope| scope.contains_key(name))
+        {
+            scope.insert(name.to_string(), value);
+            self.changed(name);
+            return Ok(());
         }
         Err(format!("Variable '{}' is not defined", name))
     }
@@ -218,6 +257,27 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_generation_ignores_playback_variables() {
+        let mut env = Environment::new();
+        env.define("_beat".to_string(), Value::Number(1));
+        env.define("_cycle".to_string(), Value::Number(2));
+        env.set("_beat", Value::Number(3)).unwrap();
+        assert_eq!(env.generation(), 0);
+
+        env.define("x".to_string(), make_note_value("C"));
+        let defined = env.generation();
+        assert!(defined > 0);
+        env.set("x", make_note_value("D")).unwrap();
+        assert!(env.generation() > defined);
+
+        let set = env.generation();
+        assert!(!env.undefine("missing"));
+        assert_eq!(env.generation(), set);
+        assert!(env.undefine("x"));
+        assert!(env.generation() > set);
+    }
+
     #[test]
     fn test_depth() {
         let mut env = Environment::new();
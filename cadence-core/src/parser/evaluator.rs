@@ -3,6 +3,7 @@ use crate::{
     types::{Chord, CommonProgressions, Note},
 };
 // use crate::types::{chord::Chord, note::Note};
+use crate::parser::builtins::{STD_NAMESPACE, STRICT_VARIABLE};
 use crate::parser::environment::{Environment, SharedEnvironment};
 use anyhow::{anyhow, Result};
 use std::cell::RefCell;
@@ -66,7 +67,10 @@ impl Evaluator {
                             if let Some(value) = environment.lookup(name) {
                                 value_to_pattern_steps(&value)
                             } else {
-                                None
+                                // An undefined name like Cmaj7 is that chord
+                                crate::types::Chord::from_symbol(name)
+                                    .ok()
+                                    .map(|chord| vec![crate::types::PatternStep::Chord(chord)])
                             }
                         })?;
                         Ok(Value::Pattern(resolved))
@@ -81,6 +85,7 @@ impl Evaluator {
             }
             Expression::String(s) => Ok(Value::String(s)),
             Expression::Number(n) => Ok(Value::Number(n)),
+            Expression::Float(n) => Ok(Value::Float(n)),
             Expression::Transpose { target, semitones } => {
                 let target_value = self.eval_with_env(*target, env.clone())?;
                 match target_value {
@@ -101,6 +106,7 @@ impl Evaluator {
                         // Numeric addition: n + semitones
                         Ok(Value::Number(n + semitones as i32))
                     }
+                    Value::Float(n) => Ok(Value::Float(n + semitones as f32)),
                     Value::String(_) => Err(anyhow!("Cannot transpose a string")),
                     Value::Function { .. } => Err(anyhow!("Cannot transpose a function")),
                     Value::Unit => Err(anyhow!("Cannot transpose unit")),
@@ -115,6 +121,13 @@ impl Evaluator {
                         );
                         Ok(Value::EveryPattern(Box::new(transposed)))
                     }
+                    Value::ProbabilityPattern(sometimes) => {
+                        let transposed = sometimes.with_patterns(
+                            sometimes.base.clone() + semitones,
+                            sometimes.transformed.clone() + semitones,
+                        );
+                        Ok(Value::ProbabilityPattern(Box::new(transposed)))
+                    }
                     Value::Thunk {
                         expression,
                         env: thunk_env,
@@ -213,7 +226,16 @@ impl Evaluator {
                             result
                         }
                         Some(v) => Ok(v),
-                        None => Err(anyhow!("Variable '{}' is not defined", name)),
+                        // A builtin named without calling it, like `let f = invert`,
+                        // stands for itself so it can be called through the variable
+                        None if crate::parser::builtins::get_registry().get(&name).is_some() => {
+                            Ok(Value::String(name))
+                        }
+                        // So does a chord symbol like `Cmaj7` that isn't a variable;
+                        // names the user defines always win
+                        None => crate::types::Chord::from_symbol(&name)
+                            .map(Value::Chord)
+                            .map_err(|_| anyhow!("Variable '{}' is not defined", name)),
                     }
                 }
                 None => Err(anyhow!(
@@ -230,27 +252,41 @@ impl Evaluator {
                 let left_val = self.eval_with_env(*left, env.clone())?;
                 let right_val = self.eval_with_env(*right, env)?;
 
+                // Whole numbers and floats compare by value, so 1 == 1.0
+                let numbers = match (&left_val, &right_val) {
+                    (Value::Number(_) | Value::Float(_), Value::Number(_) | Value::Float(_)) => {
+                        left_val.as_f32().zip(right_val.as_f32())
+                    }
+                    _ => None,
+                };
+
                 // For numeric comparisons, extract numbers
                 let result = match operator {
-                    crate::parser::ast::ComparisonOp::Equal => left_val == right_val,
-                    crate::parser::ast::ComparisonOp::NotEqual => left_val != right_val,
+                    crate::parser::ast::ComparisonOp::Equal => match numbers {
+                        Some((l, r)) => l == r,
+                        None => left_val == right_val,
+                    },
+                    crate::parser::ast::ComparisonOp::NotEqual => match numbers {
+                        Some((l, r)) => l != r,
+                        None => left_val != right_val,
+                    },
                     crate::parser::ast::ComparisonOp::Less
                     | crate::parser::ast::ComparisonOp::Greater
                     | crate::parser::ast::ComparisonOp::LessEqual
                     | crate::parser::ast::ComparisonOp::GreaterEqual => {
                         // Extract numeric values
-                        let left_num = match &left_val {
-                            Value::Number(n) => *n as f32,
-                            _ => {
+                        let left_num = match left_val.as_f32() {
+                            Some(n) => n,
+                            None => {
                                 return Err(anyhow!(
                                     "Comparison requires numeric values, got {:?}",
                                     left_val
                                 ))
                             }
                         };
-                        let right_num = match &right_val {
-                            Value::Number(n) => *n as f32,
-                            _ => {
+                        let right_num = match right_val.as_f32() {
+                            Some(n) => n,
+                            None => {
                                 return Err(anyhow!(
                                     "Comparison requires numeric values, got {:?}",
                                     right_val
@@ -326,7 +362,23 @@ impl Evaluator {
             // Index operation: pattern[0], chord[1], array[-1]
             Expression::Index { target, index } => {
                 let target_val = self.eval_with_env(*target, env.clone())?;
-                let index_val = self.eval_with_env(*index, env)?;
+
+                // Slice: pattern[1..3]
+                let index = match *index {
+                    Expression::Range { start, end } => {
+                        let bound = |expr: Expression| -> Result<i32> {
+                            match self.eval_with_env(expr, env.clone())? {
+                                Value::Number(n) => Ok(n),
+                                other => {
+                                    Err(anyhow!("Slice bounds must be numbers, got {:?}", other))
+                                }
+                            }
+                        };
+                        return slice_value(target_val, bound(*start)?, bound(*end)?);
+                    }
+                    index => index,
+                };
+                let index_val = self.eval_with_env(index, env)?;
 
                 let idx = match index_val {
                     Value::Number(n) => n,
@@ -366,7 +418,7 @@ impl Evaluator {
                                 PatternStep::Variable(_) => {
                                     Err(anyhow!("Cannot index unresolved variable"))
                                 }
-                                PatternStep::Group(steps) => {
+                                PatternStep::Group(steps) | PatternStep::Tuplet(steps) => {
                                     // Return as pattern containing the group
                                     Ok(Value::Pattern(crate::types::Pattern::with_steps(
                                         steps.clone(),
@@ -388,10 +440,13 @@ impl Evaluator {
                                         steps.clone(),
                                     )))
                                 }
-                                PatternStep::Euclidean(inner, pulses, steps) => {
-                                    // Return as pattern containing the euclidean step
+                                PatternStep::Euclidean(..)
+                                | PatternStep::Polymeter(..)
+                                | PatternStep::Choice(..)
+                                | PatternStep::Degraded(..) => {
+                                    // Return as pattern containing the euclidean, polymeter, choice or degraded step
                                     Ok(Value::Pattern(crate::types::Pattern::with_steps(vec![
-                                        PatternStep::Euclidean(inner.clone(), *pulses, *steps),
+                                        step.clone(),
                                     ])))
                                 }
                                 PatternStep::Polyrhythm(sub_patterns) => {
@@ -400,8 +455,10 @@ impl Evaluator {
                                         PatternStep::Polyrhythm(sub_patterns.clone()),
                                     ])))
                                 }
-                                PatternStep::Velocity(inner, _) => {
-                                    // Unwrap velocity step and return its value
+                                PatternStep::Velocity(inner, _)
+                                | PatternStep::Speed(inner, _)
+                                | PatternStep::Pan(inner, _) => {
+                                    // Unwrap velocity or speed step and return its value
                                     step_to_value(inner)
                                 }
                             }
@@ -463,6 +520,10 @@ impl Evaluator {
                 }
             }
 
+            Expression::Range { .. } => Err(anyhow!(
+                "A range like 1..3 can only be used to slice, as in pattern[1..3]"
+            )),
+
             // Binary arithmetic operations: +, -, *, /, %
             Expression::BinaryOp {
                 left,
@@ -496,6 +557,34 @@ impl Evaluator {
                         };
                         Ok(Value::Number(result))
                     }
+                    // A float on either side makes the result a float
+                    (
+                        l @ (Value::Number(_) | Value::Float(_)),
+                        r @ (Value::Number(_) | Value::Float(_)),
+                    ) => {
+                        let (l, r) = (
+                            l.as_f32().unwrap_or_default(),
+                            r.as_f32().unwrap_or_default(),
+                        );
+                        let result = match operator {
+                            ArithmeticOp::Add => l + r,
+                            ArithmeticOp::Subtract => l - r,
+                            ArithmeticOp::Multiply => l * r,
+                            ArithmeticOp::Divide => {
+                                if r == 0.0 {
+                                    return Err(anyhow!("Division by zero"));
+                                }
+                                l / r
+                            }
+                            ArithmeticOp::Modulo => {
+                                if r == 0.0 {
+                                    return Err(anyhow!("Modulo by zero"));
+                                }
+                                l % r
+                            }
+                        };
+                        Ok(Value::Float(result))
+                    }
                     // Runtime transposition: Note +/- Number
                     (Value::Note(note), Value::Number(n)) => {
                         let semitones = match operator {
@@ -569,74 +658,110 @@ impl Evaluator {
         args: Vec<Expression>,
         env: Option<EnvironmentRef>,
     ) -> Result<Value> {
-        // First, check for user-defined functions in the environment
-        if let Some(environment) = &env {
-            if let Some(func_value) = environment.lookup(name) {
-                if let Value::Function {
-                    params,
-                    body,
-                    name: func_name,
-                } = func_value.clone()
-                {
-                    // Check argument count
-                    if args.len() != params.len() {
-                        return Err(anyhow!(
-                            "{}() expects {} arguments, got {}",
-                            func_name,
-                            params.len(),
-                            args.len()
-                        ));
-                    }
-
-                    // Evaluate arguments
-                    let mut arg_values = Vec::new();
-                    for arg in args {
-                        arg_values.push(self.eval_with_env(arg, env.clone())?);
-                    }
+        // First, check for user-defined functions in the environment. A
+        // variable can hold one too (`let g = my_fn`), or a function's name
+        // (`let f = invert`)
+        let bound = match &env {
+            Some(environment) => match environment.lookup(name) {
+                Some(Value::Thunk { .. }) => {
+                    Some(self.eval_with_env(Expression::Variable(name.to_string()), env.clone())?)
+                }
+                value => value,
+            },
+            None => None,
+        };
+        if let Some(Value::String(target)) = &bound {
+            if target != name && self.is_function_name(target, &env) {
+                return self.eval_function_with_env(target, args, env);
+            }
+        }
+        if let Some(func_value) = &bound {
+            if let Value::Function {
+                params,
+                body,
+                name: func_name,
+                return_type,
+            } = func_value.clone()
+            {
+                // Check argument count
+                if args.len() != params.len() {
+                    return Err(anyhow!(
+                        "{}() expects {} arguments, got {}",
+                        func_name,
+                        params.len(),
+                        args.len()
+                    ));
+                }
 
-                    // Create a new environment with parameters bound to argument values
-                    let mut local_env = crate::parser::environment::Environment::new();
+                // Evaluate arguments
+                let mut arg_values = Vec::new();
+                for arg in args {
+                    arg_values.push(self.eval_with_env(arg, env.clone())?);
+                }
 
-                    // Copy outer environment bindings (if we have access)
-                    if let Some(environment) = &env {
-                        // EnvironmentRef doesn't expose all_names() directly easily without locking
-                        // But for function calls we need to capture the scope
-                        match environment {
-                            EnvironmentRef::Borrowed(e) => {
+                // Create a new environment with parameters bound to argument values
+                let mut local_env = crate::parser::environment::Environment::new();
+
+                // Copy outer environment bindings (if we have access)
+                if let Some(environment) = &env {
+                    // EnvironmentRef doesn't expose all_names() directly easily without locking
+                    // But for function calls we need to capture the scope
+                    match environment {
+                        EnvironmentRef::Borrowed(e) => {
+                            for var_name in e.all_names() {
+                                if let Some(val) = e.get(var_name) {
+                                    local_env.define(var_name.clone(), val.clone());
+                                }
+                            }
+                        }
+                        EnvironmentRef::Shared(e_lock) => {
+                            if let Ok(e) = e_lock.read() {
                                 for var_name in e.all_names() {
                                     if let Some(val) = e.get(var_name) {
                                         local_env.define(var_name.clone(), val.clone());
                                     }
                                 }
                             }
-                            EnvironmentRef::Shared(e_lock) => {
-                                if let Ok(e) = e_lock.read() {
-                                    for var_name in e.all_names() {
-                                        if let Some(val) = e.get(var_name) {
-                                            local_env.define(var_name.clone(), val.clone());
-                                        }
-                                    }
-                                }
-                            }
                         }
                     }
+                }
 
-                    // Push new scope for locals
-                    local_env.push_scope();
+                // Push new scope for locals
+                local_env.push_scope();
 
-                    // Bind parameters to arguments
-                    for (param, value) in params.iter().zip(arg_values.into_iter()) {
-                        local_env.define(param.clone(), value);
-                    }
+                // Bind parameters to arguments
+                for (param, value) in params.iter().zip(arg_values) {
+                    local_env.define(param.clone(), value);
+                }
+
+                // Execute body statements
+                let result = self.run_statements_in_local_env(&body, &mut local_env)?;
 
-                    // Execute body statements
-                    return self.run_statements_in_local_env(&body, &mut local_env);
+                // Enforce the `-> Type` annotation, if there is one
+                if let Some(return_type) = return_type {
+                    if !result.matches_type(&return_type) {
+                        return Err(anyhow!(
+                            "{}() should return {}, got {}",
+                            func_name,
+                            return_type,
+                            result.type_name()
+                        ));
+                    }
                 }
+                return Ok(result);
             }
         }
 
         // Check built-in function registry
         if let Some(builtin) = crate::parser::builtins::get_registry().get(name) {
+            if builtin.name == name && Self::is_strict(&env) {
+                return Err(anyhow!(
+                    "{}() is a builtin; after use strict, call it as {}.{}()",
+                    name,
+                    STD_NAMESPACE,
+                    name
+                ));
+            }
             return (builtin.handler)(self, args, env);
         }
 
@@ -658,7 +783,36 @@ impl Evaluator {
             }
         }
 
-        Err(anyhow!("Unknown function: {}", name))
+        match bound {
+            Some(value) => Err(anyhow!(
+                "'{}' is a {}, not a function",
+                name,
+                value.type_name()
+            )),
+            None => Err(anyhow!("Unknown function: {}", name)),
+        }
+    }
+
+    /// Whether `use strict` is on, so builtins need the `std.` prefix
+    fn is_strict(env: &Option<EnvironmentRef>) -> bool {
+        env.as_ref().is_some_and(|environment| {
+            matches!(
+                environment.lookup(STRICT_VARIABLE),
+                Some(Value::Boolean(true))
+            )
+        })
+    }
+
+    /// Whether `name` can be called: a builtin, a progression like `I-V-vi-IV`,
+    /// or a user-defined function in the environment
+    fn is_function_name(&self, name: &str, env: &Option<EnvironmentRef>) -> bool {
+        crate::parser::builtins::get_registry().get(name).is_some()
+            || CommonProgressions::is_valid_progression(name)
+            || CommonProgressions::is_numeric_progression(name)
+            || CommonProgressions::is_roman_numeral_progression(name)
+            || env.as_ref().is_some_and(|environment| {
+                matches!(environment.lookup(name), Some(Value::Function { .. }))
+            })
     }
 
     /// Call a function by name with already-evaluated Value arguments.
@@ -684,10 +838,70 @@ impl Evaluator {
         self.eval_function_with_env(name, args, env)
     }
 
+    /// The new value of `name` after `name[index] = value`: a copy of the
+    /// variable's pattern, chord or array with one element replaced.
+    /// Pattern steps are replaced through `replace()`. Negative indices count
+    /// from the end.
+    pub fn eval_index_assign(
+        &self,
+        name: &str,
+        index: &Expression,
+        value: &Expression,
+        env: Option<EnvironmentRef>,
+    ) -> Result<Value> {
+        let target = self.eval_with_env(Expression::Variable(name.to_string()), env.clone())?;
+        let index_val = self.eval_with_env(index.clone(), env.clone())?;
+        let new_val = self.eval_with_env(value.clone(), env)?;
+
+        let idx = match index_val {
+            Value::Number(n) => n,
+            _ => return Err(anyhow!("Index must be a number, got {:?}", index_val)),
+        };
+        let resolve = |len: usize, what: &str| -> Result<usize> {
+            let actual_idx = if idx < 0 { len as i32 + idx } else { idx };
+            if actual_idx < 0 || actual_idx >= len as i32 {
+                return Err(anyhow!(
+                    "Index {} out of bounds for {} with {} elements",
+                    idx,
+                    what,
+                    len
+                ));
+            }
+            Ok(actual_idx as usize)
+        };
+
+        match target {
+            Value::Pattern(_) | Value::String(_) => {
+                self.call_function_by_name("replace", vec![target, index_val, new_val], None)
+            }
+            Value::Chord(chord) => {
+                let Value::Note(note) = new_val else {
+                    return Err(anyhow!(
+                        "Only a note can replace a note of a chord, got a {}",
+                        new_val.type_name()
+                    ));
+                };
+                let mut notes = chord.notes_vec();
+                let i = resolve(notes.len(), "chord")?;
+                notes[i] = note;
+                Ok(Value::Chord(Chord::from_notes(notes)))
+            }
+            Value::Array(mut items) => {
+                let i = resolve(items.len(), "array")?;
+                items[i] = new_val;
+                Ok(Value::Array(items))
+            }
+            _ => Err(anyhow!(
+                "Cannot assign to an index of '{}' - only Pattern, Chord, or Array supported",
+                name
+            )),
+        }
+    }
+
     /// Execute a list of statements in a local environment and return the result.
     /// Used for user-defined function body execution.
     ///
-    /// Supports: let, if/else, repeat, loop, return, expressions
+    /// Supports: let, if/else, repeat, loop, while, return, expressions
     /// Returns: Value::Unit for void functions, or the returned/last expression value
     fn run_statements_in_local_env(
         &self,
@@ -716,6 +930,16 @@ impl Evaluator {
                     }
                 }
 
+                Statement::IndexAssign { name, index, value } => {
+                    let val = self.eval_index_assign(
+                        name,
+                        index,
+                        value,
+                        Some(EnvironmentRef::Borrowed(local_env)),
+                    )?;
+                    local_env.set(name, val).map_err(|e| anyhow!("{}", e))?;
+                }
+
                 Statement::Expression(expr) => {
                     last_value = self
                         .eval_with_env(expr.clone(), Some(EnvironmentRef::Borrowed(local_env)))?;
@@ -804,6 +1028,27 @@ impl Evaluator {
                     }
                 }
 
+                Statement::While { condition, body } => loop {
+                    let cond_val = self.eval_with_env(
+                        condition.clone(),
+                        Some(EnvironmentRef::Borrowed(local_env)),
+                    )?;
+                    match cond_val {
+                        Value::Boolean(true) => {}
+                        Value::Boolean(false) => break,
+                        _ => return Err(anyhow!("Condition must be a boolean")),
+                    }
+
+                    local_env.push_scope();
+                    let result = self.run_statements_in_local_env(body, local_env);
+                    local_env.pop_scope();
+                    match result {
+                        Ok(Value::Unit) => continue,
+                        Ok(v) => return Ok(v), // Return from function
+                        Err(e) => return Err(e),
+                    }
+                },
+
                 Statement::For {
                     var,
                     start,
@@ -841,13 +1086,18 @@ impl Evaluator {
                 }
 
                 Statement::FunctionDef {
-                    name, params, body, ..
+                    name,
+                    params,
+                    body,
+                    return_type,
+                    ..
                 } => {
                     // Define nested function in local scope
                     let func = Value::Function {
                         name: name.clone(),
                         params: params.clone(),
                         body: body.clone(),
+                        return_type: return_type.clone(),
                     };
                     local_env.define(name.clone(), func);
                 }
@@ -863,9 +1113,26 @@ impl Evaluator {
                 Statement::Volume(_) => {
                     return Err(anyhow!("volume is not supported inside pure functions"));
                 }
+                Statement::Capo(_) => {
+                    return Err(anyhow!("capo is not supported inside pure functions"));
+                }
                 Statement::Waveform(_) => {
                     return Err(anyhow!("waveform is not supported inside pure functions"));
                 }
+                Statement::ControlChange { .. } => {
+                    return Err(anyhow!("cc is not supported inside pure functions"));
+                }
+                Statement::MidiProgram(_)
+                | Statement::MidiBank { .. }
+                | Statement::MidiVelocityCurve(_) => {
+                    return Err(anyhow!("midi is not supported inside pure functions"));
+                }
+                Statement::Bind(_) => {
+                    return Err(anyhow!("bind is not supported inside pure functions"));
+                }
+                Statement::Alias { .. } => {
+                    return Err(anyhow!("alias is not supported inside functions"));
+                }
                 Statement::Stop => {
                     return Err(anyhow!("stop is not supported inside pure functions"));
                 }
@@ -875,7 +1142,7 @@ impl Evaluator {
                 Statement::Track { .. } => {
                     return Err(anyhow!("track is not supported inside pure functions"));
                 }
-                Statement::Use { .. } => {
+                Statement::Use { .. } | Statement::UseStrict => {
                     return Err(anyhow!("use/import is not supported inside functions"));
                 }
 
@@ -942,6 +1209,55 @@ pub fn eval(input: &str) -> Result<Value> {
     evaluator.eval(expr)
 }
 
+/// Resolve slice bounds against a length: negative bounds count from the
+/// end, and bounds past either end are clamped
+fn slice_bounds(start: i32, end: i32, len: usize) -> Result<std::ops::Range<usize>> {
+    let resolve = |bound: i32| {
+        if bound < 0 {
+            len as i64 + bound as i64
+        } else {
+            bound as i64
+        }
+    };
+    let (first, last) = (resolve(start), resolve(end));
+    if first > last {
+        return Err(anyhow!("Slice {}..{} is reversed", start, end));
+    }
+    let clamp = |bound: i64| bound.clamp(0, len as i64) as usize;
+    Ok(clamp(first)..clamp(last))
+}
+
+/// Slice a pattern, chord, array or string: steps `start` up to (not including) `end`
+fn slice_value(target: Value, start: i32, end: i32) -> Result<Value> {
+    match target {
+        Value::Pattern(mut pattern) => {
+            let range = slice_bounds(start, end, pattern.steps.len())?;
+            pattern.steps = pattern.steps[range].to_vec();
+            Ok(Value::Pattern(pattern))
+        }
+        Value::Chord(chord) => {
+            let notes = chord.notes_vec();
+            let range = slice_bounds(start, end, notes.len())?;
+            Ok(Value::Chord(crate::types::Chord::from_notes(
+                notes[range].to_vec(),
+            )))
+        }
+        Value::Array(arr) => {
+            let range = slice_bounds(start, end, arr.len())?;
+            Ok(Value::Array(arr[range].to_vec()))
+        }
+        Value::String(s) => {
+            let chars: Vec<char> = s.chars().collect();
+            let range = slice_bounds(start, end, chars.len())?;
+            Ok(Value::String(chars[range].iter().collect()))
+        }
+        _ => Err(anyhow!(
+            "Cannot slice {:?} - only Pattern, Chord, Array, or String supported",
+            target
+        )),
+    }
+}
+
 /// Convert a Value to PatternStep(s) for variable resolution in patterns
 fn value_to_pattern_steps(value: &Value) -> Option<Vec<crate::types::PatternStep>> {
     use crate::types::PatternStep;
@@ -29,6 +29,79 @@ mod pattern_operator_tests {
         }
     }
 
+    #[test]
+    fn test_eval_fractional_fast() {
+        use crate::types::time::{beats, time};
+        let events = |source: &str| match Evaluator::new().eval(parse(source).unwrap()).unwrap() {
+            Value::Pattern(p) => p
+                .to_rich_events()
+                .iter()
+                .map(|e| (e.start_beat, e.duration))
+                .collect::<Vec<_>>(),
+            _ => panic!("Expected pattern value"),
+        };
+
+        // Whole factors play as before
+        assert_eq!(
+            events("fast(\"C E\", 2)"),
+            vec![(beats(0), beats(1)), (beats(1), beats(1))]
+        );
+        // 1.5 squeezes the 4-beat cycle into 8/3 beats
+        assert_eq!(
+            events("fast(\"C E\", 1.5)"),
+            vec![(beats(0), time(4, 3)), (time(4, 3), time(4, 3))]
+        );
+        assert_eq!(
+            events("slow(\"C E\", 2.5)"),
+            vec![(beats(0), beats(5)), (beats(5), beats(5))]
+        );
+
+        for source in [
+            "fast(\"C E\", 0)",
+            "slow(\"C E\", -2)",
+            "fast(\"C E\", 0.0)",
+        ] {
+            let err = Evaluator::new().eval(parse(source).unwrap()).unwrap_err();
+            assert!(err.to_string().contains("greater than 0"), "{}", err);
+        }
+    }
+
+    #[test]
+    fn test_eval_fast_triplet_feel() {
+        use crate::types::time::{beats, time};
+        let eval = |source: &str| match Evaluator::new().eval(parse(source).unwrap()).unwrap() {
+            Value::Pattern(p) => p,
+            _ => panic!("Expected pattern value"),
+        };
+
+        // Four steps at 1.5x: each step lasts 2/3 of a beat, rests included
+        let p = eval("fast(\"C E G _\", 1.5)");
+        assert_eq!(p.beats_per_cycle, time(8, 3));
+        let events = p.to_rich_events();
+        assert_eq!(events.len(), 4);
+        for (i, event) in events.iter().enumerate() {
+            assert_eq!(event.start_beat, time(2 * i as i64, 3));
+            assert_eq!(event.duration, time(2, 3));
+        }
+        assert!(events[3].is_rest);
+
+        // The method form matches, and slowing by the same factor undoes it
+        assert_eq!(eval("\"C E G _\".fast(1.5)"), p);
+        assert_eq!(
+            eval("slow(fast(\"C E G _\", 1.5), 1.5)").beats_per_cycle,
+            beats(4)
+        );
+        assert_eq!(
+            eval("slow(\"C E G _\", 2.5)").to_rich_events()[1].duration,
+            time(5, 2)
+        );
+
+        let err = Evaluator::new()
+            .eval(parse("fast(\"C E\", -1.5)").unwrap())
+            .unwrap_err();
+        assert!(err.to_string().contains("greater than 0"), "{}", err);
+    }
+
     #[test]
     fn test_eval_rev() {
         // rev("C D E") -> E D C
@@ -111,4 +184,1246 @@ mod pattern_operator_tests {
             _ => panic!("Expected EveryPattern"),
         }
     }
+
+    #[test]
+    fn test_eval_fill() {
+        let eval = |source: &str| Evaluator::new().eval(parse(source).unwrap());
+        let Value::EveryPattern(every) = eval("fill(\"kick snare\", \"snare*4\")").unwrap() else {
+            panic!("fill() should return an EveryPattern");
+        };
+        let Value::Pattern(fill) = eval("\"snare*4\"").unwrap() else {
+            panic!("Expected pattern value");
+        };
+
+        // In a 4-cycle phrase only the fourth cycle plays the fill
+        for cycle in 0..3 {
+            assert_eq!(every.get_pattern_for_cycle(cycle), &every.base);
+        }
+        assert_eq!(
+            every.get_pattern_for_cycle(3).to_rich_events(),
+            fill.to_rich_events()
+        );
+        assert_eq!(every.get_pattern_for_cycle(7), &every.transformed);
+
+        let Value::EveryPattern(every) = eval("fill(\"C E\", slow(\"D\", 2), 2)").unwrap() else {
+            panic!("fill() should return an EveryPattern");
+        };
+        assert_eq!(every.interval(), 2);
+        assert_eq!(
+            every.transformed.beats_per_cycle,
+            every.base.beats_per_cycle
+        );
+        assert!(eval("fill(\"C E\", \"D\", 0)").is_err());
+    }
+
+    #[test]
+    fn test_eval_sometimes() {
+        let eval = |source: &str| Evaluator::new().eval(parse(source).unwrap());
+        let Value::ProbabilityPattern(sometimes) =
+            eval("sometimes_by(30, rev, \"C D E\")").unwrap()
+        else {
+            panic!("sometimes_by() should return a ProbabilityPattern");
+        };
+        assert_eq!(sometimes.probability, 30);
+        assert_eq!(sometimes.transformed, sometimes.base.clone().rev());
+
+        // The same forms of transform as every(), and method style
+        for source in [
+            "sometimes(\"rev\", \"C D E\")",
+            "sometimes(rev(), \"C D E\")",
+            "sometimesBy(50, rev, \"C D E\")",
+            "\"C D E\".sometimes_by(50, \"rev\")",
+        ] {
+            let Value::ProbabilityPattern(p) = eval(source).unwrap() else {
+                panic!("{} should return a ProbabilityPattern", source);
+            };
+            assert_eq!(p.probability, 50, "{}", source);
+            assert_eq!(p.transformed, p.base.clone().rev(), "{}", source);
+        }
+
+        // Some cycles are transformed and some aren't
+        let Value::ProbabilityPattern(p) = eval("sometimes(rev, \"C D E\")").unwrap() else {
+            panic!("sometimes() should return a ProbabilityPattern");
+        };
+        let transformed = (0..64).filter(|&cycle| p.is_transformed(cycle)).count();
+        assert!(
+            transformed > 10 && transformed < 54,
+            "transformed: {}",
+            transformed
+        );
+
+        assert!(eval("sometimes_by(101, rev, \"C D E\")").is_err());
+        assert!(eval("sometimes_by(50, \"C\" + 2, \"C D E\")").is_err());
+    }
+
+    #[test]
+    fn test_eval_sometimes_per_step() {
+        use crate::parser::{Environment, EnvironmentRef};
+
+        let eval_in_cycle = |source: &str, cycle: i32| {
+            let mut env = Environment::new();
+            env.define("_cycle".to_string(), Value::Number(cycle));
+            Evaluator::new()
+                .eval_with_env(parse(source).unwrap(), Some(EnvironmentRef::Borrowed(&env)))
+                .unwrap()
+        };
+        let source = "\"C D E F G A B C5\".sometimes(palindrome)";
+
+        // With the pattern first, single steps are transformed, like some_steps() at 50%
+        let Value::Pattern(p) = eval_in_cycle(source, 3) else {
+            panic!("sometimes(pattern, transform) should return a pattern");
+        };
+        let doubled = p
+            .steps
+            .iter()
+            .filter(|s| matches!(s, crate::types::PatternStep::Group(_)))
+            .count();
+        assert!(doubled > 0 && doubled < 8, "doubled: {}", doubled);
+        assert_eq!(
+            Value::Pattern(p),
+            eval_in_cycle("some_steps(\"C D E F G A B C5\", 50, palindrome)", 3)
+        );
+        assert_eq!(
+            eval_in_cycle("sometimes(\"C D E F G A B C5\", palindrome)", 3),
+            eval_in_cycle(source, 3)
+        );
+
+        // Two evaluations of a cycle agree; other cycles pick other steps
+        assert_eq!(eval_in_cycle(source, 3), eval_in_cycle(source, 3));
+        assert!((0..8).any(|cycle| eval_in_cycle(source, cycle) != eval_in_cycle(source, 3)));
+    }
+
+    #[test]
+    fn test_eval_frequencies() {
+        use crate::types::Note;
+        let eval = |source: &str| Evaluator::new().eval(parse(source).unwrap());
+        let hz = |name: &str| Value::Float(name.parse::<Note>().unwrap().frequency());
+
+        assert_eq!(
+            eval("frequencies([C, E, G])").unwrap(),
+            Value::Array(vec![hz("C"), hz("E"), hz("G")])
+        );
+        assert_eq!(
+            eval("frequencies(A4)").unwrap(),
+            Value::Array(vec![Value::Float(440.0)])
+        );
+        assert_eq!(eval("frequencies(\"_ C\")").unwrap(), Value::Array(vec![]));
+        assert_eq!(
+            Value::Pattern(crate::types::Pattern::parse("C _ [E, G]").unwrap())
+                .event_frequencies()
+                .unwrap()
+                .iter()
+                .map(Vec::len)
+                .collect::<Vec<_>>(),
+            vec![1, 0, 2]
+        );
+        assert!(eval("frequencies(3)").is_err());
+    }
+
+    #[test]
+    fn test_eval_scale() {
+        let eval = |source: &str| Evaluator::new().eval(parse(source).unwrap());
+        let pattern = |source: &str| match eval(source).unwrap() {
+            Value::Pattern(p) => p.to_string(),
+            other => panic!("expected a pattern, got {:?}", other),
+        };
+
+        assert_eq!(pattern("scale(\"dorian\", D)"), "\"D E F G A B C5\"");
+        assert_eq!(
+            pattern("scale(\"Minor_Pentatonic\", A)"),
+            "\"A C5 D5 E5 G5\""
+        );
+        assert_eq!(pattern("scale(\"major\", F)"), "\"F G A Bb C5 D5 E5\"");
+        // A normal pattern, so the pattern functions apply
+        assert_eq!(
+            pattern("transpose(scale(\"major\", C), 2)"),
+            pattern("scale(\"major\", D)")
+        );
+        assert_eq!(pattern("rev(scale(\"lydian\", C))"), "\"B A G F# E D C\"");
+
+        let err = eval("scale(\"bebop\", C)").unwrap_err().to_string();
+        assert!(err.contains("unknown scale 'bebop'"), "{}", err);
+        assert!(err.contains("harmonic_minor"), "{}", err);
+    }
+
+    #[test]
+    fn test_eval_arpeggiate() {
+        let eval = |source: &str| Evaluator::new().eval(parse(source).unwrap());
+
+        assert_eq!(
+            eval("arpeggiate([C, E, G])").unwrap(),
+            eval("\"C E G\"").unwrap()
+        );
+        assert_eq!(
+            eval("arpeggiate([C, E, G], \"updown\")").unwrap(),
+            eval("\"C E G E\"").unwrap()
+        );
+        // Each chord of a progression is arpeggiated inside its own step
+        assert_eq!(
+            eval("arpeggiate(\"[C,E,G] [B3,D,G]@2 _\", \"down\")").unwrap(),
+            eval("\"[G E C] [G D B3]@2 _\"").unwrap()
+        );
+        let Value::Pattern(progression) = eval("ii_V_I(C)").unwrap() else {
+            panic!("expected a pattern");
+        };
+        let Value::Pattern(arpeggios) = eval("arpeggiate(ii_V_I(C), \"up\")").unwrap() else {
+            panic!("expected a pattern");
+        };
+        assert_eq!(arpeggios.beats_per_cycle, progression.beats_per_cycle);
+        assert_eq!(arpeggios.to_rich_events().len(), 9);
+
+        let err = eval("arpeggiate([C, E, G], \"sideways\")")
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.contains("arpeggiate(): unknown arpeggio mode"),
+            "{}",
+            err
+        );
+        assert!(eval("arpeggiate(C)").is_err());
+    }
+
+    #[test]
+    fn test_eval_voicing() {
+        let eval = |source: &str| Evaluator::new().eval(parse(source).unwrap());
+
+        assert_eq!(
+            eval("voicing([C, E, G, B], \"drop2\")").unwrap(),
+            eval("[G3, C, E, B]").unwrap()
+        );
+        assert_eq!(
+            eval("root(voicing([C, E, G, B], \"drop2\"))").unwrap(),
+            eval("C").unwrap()
+        );
+        assert_eq!(
+            eval("voicing([G3, C, E, B], \"close\")").unwrap(),
+            eval("[G3, B3, C, E]").unwrap()
+        );
+        let err = eval("voicing([C, E, G], \"drop3\")")
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.contains("voicing(): drop3 voicing needs at least 4 notes"),
+            "{}",
+            err
+        );
+        assert!(eval("voicing(C, \"drop2\")").is_err());
+    }
+
+    #[test]
+    fn test_eval_identify_progression() {
+        let eval = |source: &str| Evaluator::new().eval(parse(source).unwrap());
+
+        assert_eq!(
+            eval("identify_progression(I_V_vi_IV(D), D)").unwrap(),
+            Value::String("I_V_vi_IV".to_string())
+        );
+        assert_eq!(
+            eval("identify_progression(\"[D,F,A] [G3,B3,D] [C,E,G]\", C)").unwrap(),
+            Value::String("ii_V_I".to_string())
+        );
+        assert_eq!(
+            eval("identify_progression(1456(C), C)").unwrap(),
+            Value::String("I_IV_V_I (closest match, 3 of 4 chords)".to_string())
+        );
+        assert!(eval("identify_progression(\"C D\", 5)").is_err());
+    }
+
+    #[test]
+    fn test_eval_chord() {
+        let eval = |source: &str| Evaluator::new().eval(parse(source).unwrap());
+
+        assert_eq!(
+            eval("chord(C, \"m7b5\")").unwrap(),
+            eval("[C, Eb, Gb, Bb]").unwrap()
+        );
+        assert_eq!(
+            eval("chord(G3, \"dominant\")").unwrap(),
+            eval("[G3, B3, D4, F4]").unwrap()
+        );
+        assert_eq!(
+            eval("invert(chord(A, \"minor\"))").unwrap(),
+            eval("[C5, E5, A5]").unwrap()
+        );
+
+        let err = eval("chord(C, \"blue\")").unwrap_err().to_string();
+        assert!(err.contains("unknown chord quality 'blue'"), "{}", err);
+        assert!(eval("chord(\"C E G\", \"major\")").is_err());
+    }
+
+    #[test]
+    fn test_eval_in_scale() {
+        let eval = |source: &str| Evaluator::new().eval(parse(source).unwrap()).unwrap();
+
+        // Every chromatic note lands on C major; halfway notes go down
+        assert_eq!(
+            eval("in_scale(run_notes(C, 12), \"major\", C)"),
+            eval("\"C C D D E F F G G A A B\"")
+        );
+        assert_eq!(
+            eval("in_scale(\"C# _ kick [D#, F#, A#]\", \"major\", C)"),
+            eval("\"C _ kick [D, F, A]\"")
+        );
+        assert_eq!(eval("in_scale(F#, \"Lydian\", C)"), eval("F#"));
+        assert_eq!(eval("in_scale([C, C#, E], \"major\", C)"), eval("[C, E]"));
+        assert!(Evaluator::new()
+            .eval(parse("in_scale(C, \"bebop\", C)").unwrap())
+            .is_err());
+    }
+
+    #[test]
+    fn test_eval_run() {
+        use crate::types::PatternStep;
+
+        let expr = parse("run(4)").unwrap();
+        let result = Evaluator::new().eval(expr).unwrap();
+
+        match result {
+            Value::Pattern(p) => {
+                let numbers: Vec<u8> = p
+                    .steps
+                    .iter()
+                    .map(|s| match s {
+                        PatternStep::Note(n) => n.midi_note(),
+                        _ => panic!("Expected note steps"),
+                    })
+                    .collect();
+                assert_eq!(numbers, vec![0, 1, 2, 3]);
+            }
+            _ => panic!("Expected pattern value"),
+        }
+
+        // n = 0 yields an empty pattern
+        let expr = parse("run(0)").unwrap();
+        match Evaluator::new().eval(expr).unwrap() {
+            Value::Pattern(p) => assert!(p.is_empty()),
+            _ => panic!("Expected pattern value"),
+        }
+    }
+
+    #[test]
+    fn test_eval_run_limits() {
+        let eval = |source: &str| Evaluator::new().eval(parse(source).unwrap());
+
+        // Every MIDI note fits in one run, one more does not
+        match eval("run(128)").unwrap() {
+            Value::Pattern(p) => assert_eq!(p.steps.len(), 128),
+            _ => panic!("Expected pattern value"),
+        }
+        assert!(eval("run(129)").is_err());
+        assert!(eval("run(2000000000)").is_err());
+        assert!(eval("run(-1)").is_err());
+        assert!(eval("run_notes(G9, 2)").is_err());
+        assert!(eval("run_notes(C, 2000000000)").is_err());
+
+        // A note is not a count
+        assert!(eval("run(E)").is_err());
+        assert!(eval("run_notes(C, E)").is_err());
+    }
+
+    #[test]
+    fn test_eval_run_notes() {
+        use crate::types::PatternStep;
+
+        let expr = parse("run_notes(C, 3)").unwrap();
+        let result = Evaluator::new().eval(expr).unwrap();
+
+        match result {
+            Value::Pattern(p) => {
+                let names: Vec<String> = p
+                    .steps
+                    .iter()
+                    .map(|s| match s {
+                        PatternStep::Note(n) => n.to_string(),
+                        _ => panic!("Expected note steps"),
+                    })
+                    .collect();
+                assert_eq!(names, vec!["C", "C#", "D"]);
+            }
+            _ => panic!("Expected pattern value"),
+        }
+    }
+
+    #[test]
+    fn test_eval_melody_length_limits() {
+        let eval = |source: &str| Evaluator::new().eval(parse(source).unwrap());
+        assert!(eval("melody(C, \"major\", 1024, [1])").is_ok());
+        let err = eval("melody(C, \"major\", 1000000000, [1])").unwrap_err();
+        assert!(err.to_string().contains("0 to 1024"), "{}", err);
+        assert!(eval("melody(C, \"major\", -1, [1])").is_err());
+    }
+
+    #[test]
+    fn test_eval_melody_reproducible() {
+        use crate::types::{Note, PatternStep};
+
+        let eval_melody = |source: &str| -> Vec<Note> {
+            match Evaluator::new().eval(parse(source).unwrap()).unwrap() {
+                Value::Pattern(p) => p
+                    .steps
+                    .iter()
+                    .map(|s| match s {
+                        PatternStep::Note(n) => *n,
+                        _ => panic!("Expected note steps"),
+                    })
+                    .collect(),
+                _ => panic!("Expected pattern value"),
+            }
+        };
+
+        let source = "melody(A, \"minor_pentatonic\", 16, [4, 1, 1, 2, 1], 7)";
+        let first = eval_melody(source);
+        assert_eq!(first.len(), 16);
+        assert_eq!(first, eval_melody(source));
+
+        // A minor pentatonic: A C D E G
+        let scale_tones = [9, 0, 2, 4, 7];
+        assert!(first.iter().all(|n| scale_tones.contains(&n.pitch_class())));
+
+        // Zero weights exclude degrees; mismatched weights fall back to uniform
+        let roots = eval_melody("melody(C, \"major\", 8, [1, 0, 0, 0, 0, 0, 0])");
+        assert!(roots.iter().all(|n| n.pitch_class() == 0));
+        let uniform = eval_melody("melody(C, \"major\", 8, [1, 0])");
+        assert_eq!(uniform.len(), 8);
+        assert_ne!(
+            eval_melody(source),
+            eval_melody(&source.replace(", 7)", ", 8)"))
+        );
+    }
+
+    #[test]
+    fn test_eval_export_midi() {
+        let path = std::env::temp_dir().join("cadence_export_test.mid");
+        let path_str = path.to_string_lossy().replace('\\', "/");
+
+        let source = format!("export_midi([\"C E\", \"kick\"], \"{}\", 2, 100)", path_str);
+        let expr = parse(&source).unwrap();
+        let result = Evaluator::new().eval(expr).unwrap();
+        assert_eq!(result, Value::String(path_str));
+
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+        assert_eq!(&bytes[0..4], b"MThd");
+        // Conductor track plus one track per array element
+        assert_eq!(&bytes[10..12], &[0, 3]);
+    }
+
+    #[test]
+    fn test_eval_export_midi_rejects_bad_bars() {
+        let expr = parse("export_midi(\"C E\", \"out.mid\", \"two\")").unwrap();
+        assert!(Evaluator::new().eval(expr).is_err());
+    }
+
+    #[test]
+    fn test_eval_export_midi_rejects_note_bars_and_too_many_tracks() {
+        let eval = |source: &str| Evaluator::new().eval(parse(source).unwrap());
+        assert!(eval("export_midi(\"C E\", \"out.mid\", D)").is_err());
+
+        // Sixteen melodic tracks don't fit around drum channel 10
+        let tracks = vec!["\"C\""; 16].join(", ");
+        let err = eval(&format!("export_midi([{}], \"out.mid\", 1)", tracks)).unwrap_err();
+        assert!(err.to_string().contains("at most 15"), "{}", err);
+    }
+
+    #[test]
+    fn test_eval_approach() {
+        let eval = |source: &str| Evaluator::new().eval(parse(source).unwrap());
+        let Value::Pattern(p) = eval("approach(6, C)").unwrap() else {
+            panic!("approach() should return a pattern");
+        };
+        assert_eq!(p.steps.len(), 3);
+
+        // Swapped arguments say which order to use
+        let err = eval("approach(C, 5)").unwrap_err().to_string();
+        assert!(err.contains("degree first"), "{}", err);
+        assert!(err.contains("approach(5, C"), "{}", err);
+        assert!(eval("approach(8, C)").is_err());
+    }
+
+    #[test]
+    fn test_eval_from_intervals() {
+        let eval = |source: &str| Evaluator::new().eval(parse(source).unwrap());
+        assert_eq!(
+            eval("from_intervals(C, [4, 7])").unwrap(),
+            eval("[C, E, G]").unwrap()
+        );
+        assert!(eval("from_intervals(C, [4, -1])").is_err());
+        // C4 is MIDI 60, so 67 semitones up is G9 (127) and 68 is past it
+        assert!(eval("from_intervals(C, [67])").is_ok());
+        let err = eval("from_intervals(C, [4, 100])").unwrap_err().to_string();
+        assert!(err.contains("0 to 67"), "{}", err);
+    }
+
+    #[test]
+    fn test_eval_chordpro_progression() {
+        let eval = |source: &str| Evaluator::new().eval(parse(source).unwrap());
+        let Value::String(chart) = eval("chordpro(I_V_vi_IV(C), C)").unwrap() else {
+            panic!("chordpro() should return a string");
+        };
+        assert!(chart.starts_with("{key: C}\n"));
+
+        // The four chord symbols appear in order
+        let positions: Vec<usize> = ["[C]", "[G]", "[Am]", "[F]"]
+            .iter()
+            .map(|symbol| chart.find(symbol).expect(symbol))
+            .collect();
+        assert!(positions.windows(2).all(|w| w[0] < w[1]), "{}", chart);
+
+        assert!(eval("chordpro(\"C E G\", 5)").is_err());
+    }
+
+    #[test]
+    fn test_eval_erhythm() {
+        let eval = |source: &str| Evaluator::new().eval(parse(source).unwrap());
+        let Value::Pattern(tresillo) = eval("erhythm(3, 8, 0, [C, E, G])").unwrap() else {
+            panic!("erhythm() should return a pattern");
+        };
+        assert_eq!(tresillo.steps.len(), 8);
+        assert!(matches!(
+            tresillo.steps[3],
+            crate::types::PatternStep::Chord(_)
+        ));
+        assert_eq!(tresillo.steps[1], crate::types::PatternStep::Rest);
+
+        // Onsets from a pattern take its steps in turn
+        let Value::Pattern(p) = eval("erhythm(2, 4, 1, \"kick snare\", true)").unwrap() else {
+            panic!("erhythm() should return a pattern");
+        };
+        assert_eq!(p.to_string(), "\"kick _ snare _\"");
+
+        assert!(eval("erhythm(9, 8, 0)").is_err());
+        assert!(eval("erhythm(3, 8)").is_err());
+    }
+
+    #[test]
+    fn test_eval_to_register() {
+        let eval = |source: &str| Evaluator::new().eval(parse(source).unwrap());
+        let Value::Pattern(p) = eval("\"C6 E6 G6\".to_register(C3)").unwrap() else {
+            panic!("to_register() should return a pattern");
+        };
+        assert_eq!(p.to_string(), "\"C3 E3 G3\"");
+        assert!(eval("to_register(\"C6\", 3)").is_err());
+    }
+
+    #[test]
+    fn test_eval_enclose() {
+        let eval = |source: &str| Evaluator::new().eval(parse(source).unwrap());
+        let Value::Pattern(p) = eval("enclose(G, C, \"major\")").unwrap() else {
+            panic!("enclose() should return a pattern");
+        };
+        assert_eq!(p.to_string(), "\"A F# G\"");
+        assert!(eval("enclose(G, C, \"bebop\")").is_err());
+        assert!(eval("enclose(G, C)").is_err());
+    }
+
+    #[test]
+    fn test_eval_voices() {
+        let eval = |source: &str| Evaluator::new().eval(parse(source).unwrap());
+        let Value::Chord(chord) = eval("[C, E, G].voices(5)").unwrap() else {
+            panic!("voices() should return a chord");
+        };
+        assert_eq!(chord.len(), 5);
+        assert!(chord.analyze().contains("C Major"));
+        assert!(eval("voices([C, E, G], 0)").is_err());
+    }
+
+    #[test]
+    fn test_eval_tensions() {
+        let eval = |source: &str| Evaluator::new().eval(parse(source).unwrap());
+        // The natural 11 is an avoid note over a major triad
+        let Value::Pattern(p) = eval("tensions([C, E, G], C, \"major\")").unwrap() else {
+            panic!("tensions() should return a pattern");
+        };
+        assert_eq!(p.to_string(), "\"D5 A5\"");
+        assert!(eval("tensions([C, E, G], C, \"bebop\")").is_err());
+        assert!(eval("tensions(C, C, \"major\")").is_err());
+    }
+
+    #[test]
+    fn test_eval_toscale() {
+        let eval = |source: &str| Evaluator::new().eval(parse(source).unwrap());
+        let Value::Pattern(p) = eval("toscale([0, 2, 4], C, \"major\")").unwrap() else {
+            panic!("toscale() should return a pattern");
+        };
+        assert_eq!(p.to_string(), "\"C E G\"");
+        let Value::Pattern(p) = eval("toscale([-1, 7, 8], C, \"major\")").unwrap() else {
+            panic!("toscale() should return a pattern");
+        };
+        assert_eq!(p.to_string(), "\"B3 C5 D5\"");
+        assert!(eval("toscale([0, 200], C, \"major\")").is_err());
+        assert!(eval("toscale([0], C, \"bebop\")").is_err());
+    }
+
+    #[test]
+    fn test_eval_swing_n() {
+        let eval = |source: &str| Evaluator::new().eval(parse(source).unwrap());
+        let Value::Pattern(p) = eval("swing_n(\"C D\".fast(4), 8, 50)").unwrap() else {
+            panic!("swing_n() should return a pattern");
+        };
+        let starts: Vec<f32> = p
+            .to_rich_events()
+            .iter()
+            .map(|e| e.start_beat_f32())
+            .collect();
+        assert_eq!(starts, vec![0.0, 0.75]);
+        assert!(eval("swing_n(\"C D\", 0, 50)").is_err());
+        assert!(eval("swing_n(\"C D\", 8, 100)").is_err());
+    }
+
+    #[test]
+    fn test_eval_fast_slow_note_values() {
+        let eval = |source: &str| Evaluator::new().eval(parse(source).unwrap());
+        let starts = |source: &str| -> Vec<f32> {
+            let Value::Pattern(p) = eval(source).unwrap() else {
+                panic!("expected a pattern");
+            };
+            p.to_rich_events()
+                .iter()
+                .map(|e| e.start_beat_f32())
+                .collect()
+        };
+
+        // Sixteenths: four steps fill one beat of the 4-beat cycle
+        assert_eq!(
+            starts("fast(\"C D E F\", \"16th\")"),
+            vec![0.0, 0.25, 0.5, 0.75]
+        );
+        assert_eq!(starts("\"C E\".fast(\"8th\")"), vec![0.0, 0.5]);
+        assert_eq!(starts("\"C E G\".slow(\"whole\")"), vec![0.0, 4.0, 8.0]);
+        let triplets = starts("\"C E G\".fast(\"triplet8th\")");
+        assert!((triplets[2] - 2.0 / 3.0).abs() < 1e-6);
+
+        let err = eval("fast(\"C E\", \"eighth\")").unwrap_err().to_string();
+        assert!(err.contains("unknown note value 'eighth'"));
+        assert!(err.contains("whole, half, quarter, 8th, 16th, triplet8th"));
+    }
+
+    #[test]
+    fn test_eval_swing() {
+        let eval = |source: &str| Evaluator::new().eval(parse(source).unwrap());
+        let starts = |source: &str| -> Vec<f32> {
+            let Value::Pattern(p) = eval(source).unwrap() else {
+                panic!("swing() should return a pattern");
+            };
+            p.to_rich_events()
+                .iter()
+                .map(|e| e.start_beat_f32())
+                .collect()
+        };
+
+        assert_eq!(starts("swing(\"C D E F\", 0)"), vec![0.0, 1.0, 2.0, 3.0]);
+        // Half of triplet swing delays odd steps by a sixth of a step
+        let swung = starts("swing(\"C D E F\", 50)");
+        assert_eq!(swung[0], 0.0);
+        assert!((swung[1] - (1.0 + 1.0 / 6.0)).abs() < 1e-6);
+        assert_eq!(swung[2], 2.0);
+
+        // Triplet swing, kept through fast() and slow()
+        let fast = starts("\"C D E F\".swing(100).fast(2)");
+        assert!((fast[1] - (0.5 + 0.5 / 3.0)).abs() < 1e-6);
+        let slow = starts("\"C D E F\".slow(4).swing(100)");
+        assert!((slow[1] - (4.0 + 4.0 / 3.0)).abs() < 1e-6);
+
+        assert!(eval("swing(\"C D\", 101)").is_err());
+    }
+
+    #[test]
+    fn test_eval_jux() {
+        let eval = |source: &str| Evaluator::new().eval(parse(source).unwrap());
+        let pans = |source: &str| -> Vec<Vec<Option<f32>>> {
+            let Value::Pattern(p) = eval(source).unwrap() else {
+                panic!("jux() should return a pattern");
+            };
+            p.to_rich_events()
+                .iter()
+                .map(|e| e.notes.iter().map(|n| n.pan).collect())
+                .collect()
+        };
+
+        let method = pans("\"C E G _\".jux(rev)");
+        assert_eq!(method[1], vec![Some(0.0), Some(1.0)]);
+        assert_eq!(pans("\"C E G _\".jux(\"rev\")"), method);
+        assert_eq!(pans("jux(rev, \"C E G _\")"), method);
+        assert!(eval("jux(\"C E\", \"D F\")").is_err());
+    }
+
+    #[test]
+    fn test_eval_strum() {
+        let eval = |source: &str| Evaluator::new().eval(parse(source).unwrap());
+        let Value::Pattern(p) = eval("[C, E, G].strum(25, \"down\")").unwrap() else {
+            panic!("strum() should return a pattern");
+        };
+        let notes: Vec<(String, f32)> = p
+            .to_rich_events()
+            .iter()
+            .map(|e| (e.notes[0].name.clone(), e.start_beat_f32()))
+            .collect();
+        assert_eq!(
+            notes,
+            vec![
+                ("G4".to_string(), 0.0),
+                ("E4".to_string(), 0.25),
+                ("C4".to_string(), 0.5)
+            ]
+        );
+        assert!(eval("strum(\"[C, E, G]\", 101)").is_err());
+        assert!(eval("strum(\"[C, E, G]\", 10, \"sideways\")").is_err());
+    }
+
+    #[test]
+    fn test_eval_delay() {
+        use crate::types::{Delay, DelayTime};
+        let eval = |source: &str| Evaluator::new().eval(parse(source).unwrap());
+        let delay_of = |source: &str| match eval(source).unwrap() {
+            Value::Pattern(p) => p.delay,
+            other => panic!("delay() should return a pattern, got {}", other),
+        };
+
+        assert_eq!(
+            delay_of("\"C E G\".delay(0.75, 40, 30)"),
+            Some(Delay::new(DelayTime::Beats(0.75), 40, 30))
+        );
+        assert_eq!(
+            delay_of("delay(\"C E G\", \"250ms\", 20, 50)"),
+            Some(Delay::new(DelayTime::Millis(250), 20, 50))
+        );
+        // Runaway feedback is clamped
+        assert_eq!(
+            delay_of("delay(\"C E G\", 1, 120, 0)").map(|d| d.feedback),
+            Some(Delay::MAX_FEEDBACK)
+        );
+        // The delay survives other transforms
+        assert!(delay_of("fast(delay(\"C E G\", 0.5, 40, 30), 2)").is_some());
+
+        assert!(eval("delay(\"C E G\", 0, 40, 30)").is_err());
+        assert!(eval("delay(\"C E G\", \"fast\", 40, 30)").is_err());
+        assert!(eval("delay(\"C E G\", 0.5, 40, 101)").is_err());
+    }
+
+    #[test]
+    fn test_eval_rand_transpose() {
+        let eval = |source: &str| Evaluator::new().eval(parse(source).unwrap());
+        assert_eq!(
+            eval("rand_transpose(\"C E G\", 0)").unwrap(),
+            eval("\"C E G\"").unwrap()
+        );
+        // The same seed always picks the same amount
+        assert_eq!(
+            eval("rand_transpose(\"C E G\", 7, 3)").unwrap(),
+            eval("rand_transpose(\"C E G\", 7, 3)").unwrap()
+        );
+        let Value::Chord(chord) = eval("rand_transpose([C, E, G], 2, 3)").unwrap() else {
+            panic!("rand_transpose() of a chord should return a chord");
+        };
+        let root = chord.notes_vec()[0].midi_note() as i32;
+        assert!((58..=62).contains(&root), "root {}", root);
+        // Chords stay inside MIDI 0-127 too (G9 is 127)
+        for seed in 0..20 {
+            let Value::Chord(chord) =
+                eval(&format!("rand_transpose([C, E, G], 127, {})", seed)).unwrap()
+            else {
+                panic!("rand_transpose() of a chord should return a chord");
+            };
+            for note in chord.notes_vec() {
+                let midi = (note.octave() as i32 + 1) * 12 + note.pitch_class() as i32;
+                assert!((0..=127).contains(&midi), "{} is outside MIDI", note);
+            }
+        }
+        assert!(eval("rand_transpose(\"C E G\", -1)").is_err());
+    }
+
+    #[test]
+    fn test_eval_degrade_by() {
+        let eval = |source: &str| Evaluator::new().eval(parse(source).unwrap());
+        let sounding = |source: &str, cycle: usize| -> Vec<bool> {
+            let Value::Pattern(p) = eval(source).unwrap() else {
+                panic!("expected a pattern");
+            };
+            p.to_rich_events_for_cycle(cycle)
+                .iter()
+                .map(|e| !e.is_rest)
+                .collect()
+        };
+
+        assert_eq!(
+            eval("degrade_by(\"C D E F\", 30)").unwrap(),
+            eval("degrade(\"C D E F\", 30)").unwrap()
+        );
+        assert_eq!(sounding("degrade_by(\"C D E F\", 0)", 0), vec![true; 4]);
+        assert_eq!(sounding("degrade_by(\"C D E F\", 100)", 0), vec![false; 4]);
+
+        // Out of range percents are errors for both
+        assert!(eval("degrade_by(\"C D E F\", 101)").is_err());
+        assert!(eval("degrade(\"C D E F\", 101)").is_err());
+        assert!(eval("degrade(\"C D E F\", -1)").is_err());
+        assert!(eval("degrade_by(\"C D E F\")").is_err());
+        assert!(eval("degrade_by(\"C D E F\", 50, 1)").is_err());
+        assert!(eval("degrade_by(C, 50)").is_err());
+
+        // Both halves of an every() are degraded
+        match eval("degrade_by(every(2, \"rev\", \"C D E F\"), 100)").unwrap() {
+            Value::EveryPattern(every) => {
+                for cycle in 0..2 {
+                    let pattern = every.get_pattern_for_cycle(cycle);
+                    let events = pattern.to_rich_events_for_cycle(cycle);
+                    assert!(events.iter().all(|e| e.is_rest));
+                }
+            }
+            other => panic!("expected an every pattern, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_eval_degrade_decides_per_cycle() {
+        let eval = |source: &str| Evaluator::new().eval(parse(source).unwrap()).unwrap();
+        let Value::Pattern(p) = eval("degrade(\"C D E F G A B C5\", 50)") else {
+            panic!("expected a pattern");
+        };
+        let sounding = |pattern: &crate::types::Pattern, cycle: usize| -> Vec<bool> {
+            pattern
+                .to_rich_events_for_cycle(cycle)
+                .iter()
+                .map(|e| !e.is_rest)
+                .collect()
+        };
+
+        // One evaluation drops different steps in different cycles, and
+        // re-evaluating drops the same ones in the same cycle
+        assert!((1..8).any(|cycle| sounding(&p, cycle) != sounding(&p, 0)));
+        assert_eq!(
+            eval("degrade(\"C D E F G A B C5\", 50)"),
+            Value::Pattern(p.clone())
+        );
+
+        // Together with undegrade() each step plays exactly once
+        let Value::Pattern(kept) = eval("undegrade(\"C D E F G A B C5\", 50)") else {
+            panic!("expected a pattern");
+        };
+        for cycle in 0..4 {
+            let (a, b) = (sounding(&p, cycle), sounding(&kept, cycle));
+            assert!(a.iter().zip(&b).all(|(a, b)| a != b));
+        }
+
+        let eval = |source: &str| Evaluator::new().eval(parse(source).unwrap());
+        assert!(eval("undegrade(\"C D\", \"x\")").is_err());
+        assert!(eval("undegrade(C, 50)").is_err());
+    }
+
+    #[test]
+    fn test_eval_scatter() {
+        let eval = |source: &str| Evaluator::new().eval(parse(source).unwrap());
+        let events = |source: &str| match eval(source).unwrap() {
+            Value::Pattern(p) => p.to_rich_events(),
+            other => panic!("expected a pattern, got {:?}", other),
+        };
+        assert_eq!(events("scatter(\"C D E F\", 0)"), events("\"C D E F\""));
+        // The same seed always gives the same nudges
+        assert_eq!(
+            events("scatter(\"C D E F\", 30, 5)"),
+            events("scatter(\"C D E F\", 30, 5)")
+        );
+        assert!(eval("scatter(\"C D E F\", 51)").is_err());
+        assert!(eval("scatter(\"C D E F\", -1)").is_err());
+    }
+
+    #[test]
+    fn test_eval_humanize() {
+        let eval = |source: &str| Evaluator::new().eval(parse(source).unwrap());
+        let events = |source: &str| match eval(source).unwrap() {
+            Value::Pattern(p) => p.to_rich_events(),
+            other => panic!("expected a pattern, got {:?}", other),
+        };
+        assert_eq!(events("humanize(\"C D E F\", 0, 0)"), events("\"C D E F\""));
+
+        // Running it twice gives identical events
+        let humanized = events("\"C D E F G A B C5\".humanize(40, 20)");
+        assert_eq!(humanized, events("\"C D E F G A B C5\".humanize(40, 20)"));
+        let velocities: Vec<u8> = humanized.iter().map(|e| e.notes[0].velocity).collect();
+        assert!(velocities.iter().all(|v| (80..=120).contains(v)));
+        assert!(velocities.iter().any(|v| *v != 100));
+        assert!(humanized
+            .iter()
+            .enumerate()
+            .any(|(i, e)| e.start_beat_f32() != i as f32 * 0.5));
+
+        assert_ne!(
+            events("humanize(\"C D E F\", 40, 20, 7)"),
+            events("humanize(\"C D E F\", 40, 20)")
+        );
+        assert!(eval("humanize(\"C D E F\", 101, 0)").is_err());
+        assert!(eval("humanize(\"C D E F\", 10)").is_err());
+    }
+
+    #[test]
+    fn test_eval_shuffle_and_scramble() {
+        let eval = |source: &str| Evaluator::new().eval(parse(source).unwrap());
+        let Value::Pattern(p) = eval("\"C D E F\".slow(2).pan(20).shuffle(9)").unwrap() else {
+            panic!("shuffle() should return a pattern");
+        };
+        assert!(p.shuffle);
+        assert_eq!(p.seed, Some(9));
+        assert_eq!(p.beats_per_cycle_f32(), 8.0);
+        assert_eq!(p.pan, Some(0.2));
+        assert!(crate::types::CompiledPattern::compile(&p).is_none());
+
+        let Value::Pattern(p) = eval("scramble(\"C D E F\")").unwrap() else {
+            panic!("scramble() should return a pattern");
+        };
+        assert_eq!(p.steps.len(), 4);
+
+        assert!(matches!(
+            eval("shuffle(every(2, rev, \"C D E\"))").unwrap(),
+            Value::EveryPattern(every) if every.base.shuffle && every.transformed.shuffle
+        ));
+        assert!(eval("scramble(4)").is_err());
+    }
+
+    #[test]
+    fn test_eval_seed() {
+        let eval = |source: &str| Evaluator::new().eval(parse(source).unwrap());
+        let picks = |source: &str| match eval(source).unwrap() {
+            Value::Pattern(p) => (0..16)
+                .map(|cycle| p.to_rich_events_for_cycle(cycle)[0].notes[0].midi)
+                .collect::<Vec<_>>(),
+            other => panic!("expected a pattern, got {:?}", other),
+        };
+        assert_eq!(picks("seed(\"[C|E|G]\", 3)"), picks("seed(\"[C|E|G]\", 3)"));
+        assert_ne!(picks("seed(\"[C|E|G]\", 3)"), picks("seed(\"[C|E|G]\", 4)"));
+        assert!(eval("seed(\"[C|E|G]\")").is_err());
+        assert!(eval("seed(\"[C|E|G]\", \"x\")").is_err());
+    }
+
+    #[test]
+    fn test_eval_some_steps() {
+        let eval = |source: &str| Evaluator::new().eval(parse(source).unwrap());
+        assert_eq!(
+            eval("some_steps(\"C D E F\", 0, \"palindrome\")").unwrap(),
+            eval("\"C D E F\"").unwrap()
+        );
+        // A transform that adds steps plays them inside the chosen step
+        assert_eq!(
+            eval("some_steps(\"C _ E\", 100, palindrome)").unwrap(),
+            eval("\"[C C] _ [E E]\"").unwrap()
+        );
+        assert_eq!(
+            eval("some_steps(\"C D E F\", 50, rev, 9)").unwrap(),
+            eval("some_steps(\"C D E F\", 50, rev, 9)").unwrap()
+        );
+        assert!(eval("some_steps(\"C D\", 100, transpose)").is_err());
+    }
+
+    #[test]
+    fn test_eval_replace_insert_remove() {
+        let eval = |source: &str| Evaluator::new().eval(parse(source).unwrap());
+        let pattern = |source: &str| match eval(source).unwrap() {
+            Value::Pattern(p) => p.to_string(),
+            other => panic!("expected a pattern, got {:?}", other),
+        };
+        assert_eq!(pattern("replace(\"C E G\", 1, D)"), "\"C D G\"");
+        assert_eq!(pattern("\"C E G\".replace(0, [F, A, C5])"), "\"Fmaj E G\"");
+        assert_eq!(pattern("replace(\"C E G\", -1, \"A B\")"), "\"C E [A B]\"");
+        assert_eq!(pattern("\"C E G\".insert(-1, D).remove(0)"), "\"E G D\"");
+        assert!(eval("replace(\"C E G\", 3, D)").is_err());
+        assert!(eval("remove(\"C E G\", -4)").is_err());
+        assert!(eval("insert(\"C E G\", 0, 5)").is_err());
+    }
+
+    #[test]
+    fn test_eval_alter() {
+        let eval = |source: &str| Evaluator::new().eval(parse(source).unwrap());
+        let Value::Chord(chord) = eval("[G, B, D, F].alter(\"b9\").alter(\"#5\")").unwrap() else {
+            panic!("alter() should return a chord");
+        };
+        let names: Vec<String> = chord.notes_vec().iter().map(|n| n.full_name()).collect();
+        assert_eq!(names, vec!["G4", "B4", "D#4", "F4", "Ab5"]);
+        let Value::Chord(chord) = eval("alter([G, B, D, F], \"b13\")").unwrap() else {
+            panic!("alter() should return a chord");
+        };
+        assert_eq!(chord.notes_vec().last().unwrap().full_name(), "Eb6");
+        assert!(eval("alter([G, B, D, F], \"b7\")").is_err());
+        assert!(eval("alter(G, \"b9\")").is_err());
+    }
+
+    #[test]
+    fn test_eval_linger_trunc() {
+        let eval = |source: &str| Evaluator::new().eval(parse(source).unwrap());
+        let Value::Pattern(p) = eval("linger(\"C E G D\", 0.5)").unwrap() else {
+            panic!("linger() should return a pattern");
+        };
+        assert_eq!(p.to_string(), "\"C E C E\"");
+        let Value::Pattern(p) = eval("trunc(\"C E G D\", 0.75)").unwrap() else {
+            panic!("trunc() should return a pattern");
+        };
+        assert_eq!(p.to_string(), "\"C E G _\"");
+        let Value::Pattern(p) = eval("\"C E G D\".trunc(0.25)").unwrap() else {
+            panic!("trunc() should return a pattern");
+        };
+        assert_eq!(p.to_string(), "\"C _@3\"");
+        // The whole cycle leaves the pattern as it is
+        let Value::Pattern(p) = eval("linger(\"C E G D\", 1.0)").unwrap() else {
+            panic!("linger() should return a pattern");
+        };
+        assert_eq!(p.to_string(), "\"C E G D\"");
+
+        // A whole number is a percent
+        assert_eq!(
+            eval("linger(\"C E G D\", 50)").unwrap(),
+            eval("linger(\"C E G D\", 0.5)").unwrap()
+        );
+        assert_eq!(
+            eval("trunc(\"C E G D\", 25)").unwrap(),
+            eval("trunc(\"C E G D\", 0.25)").unwrap()
+        );
+
+        assert!(eval("linger(\"C E\", 0)").is_err());
+        assert!(eval("linger(\"C E\", 0.0)").is_err());
+        assert!(eval("trunc(\"C E\", 1.5)").is_err());
+        assert!(eval("trunc(\"C E\", 150)").is_err());
+    }
+
+    #[test]
+    fn test_eval_diff() {
+        let eval = |source: &str| Evaluator::new().eval(parse(source).unwrap());
+        assert_eq!(
+            eval("diff(\"C E G\", rev(\"C E G\"))").unwrap(),
+            Value::String("steps reversed".to_string())
+        );
+        assert_eq!(
+            eval("diff(\"C E G\", \"C E G\")").unwrap(),
+            Value::String("no changes".to_string())
+        );
+        assert_eq!(
+            eval("diff(\"C E G\", \"C E A B\")").unwrap(),
+            Value::String(
+                "step 2: G -> A (up 2 semitones)\nstep 3: added B\nlength: 3 -> 4 steps"
+                    .to_string()
+            )
+        );
+        assert!(eval("diff(\"C E G\", 4)").is_err());
+    }
+
+    #[test]
+    fn test_eval_float_arithmetic_and_comparison() {
+        let eval = |source: &str| Evaluator::new().eval(parse(source).unwrap()).unwrap();
+        assert_eq!(eval("1.5 + 1"), Value::Float(2.5));
+        assert_eq!(eval("1 + 1.5"), Value::Float(2.5));
+        assert_eq!(eval("0.5 * 2"), Value::Float(1.0));
+        assert_eq!(eval("3 - 0.5"), Value::Float(2.5));
+        assert_eq!(eval("1.5 / 0.5"), Value::Float(3.0));
+        assert_eq!(eval("5.5 % 2"), Value::Float(1.5));
+        // Two whole numbers stay whole
+        assert_eq!(eval("7 / 2"), Value::Number(3));
+
+        assert_eq!(eval("1.5 < 2"), Value::Boolean(true));
+        assert_eq!(eval("2 >= 2.5"), Value::Boolean(false));
+        assert_eq!(eval("0.25 <= 0.25"), Value::Boolean(true));
+        assert_eq!(eval("1 == 1.0"), Value::Boolean(true));
+        assert_eq!(eval("1.5 != 1.5"), Value::Boolean(false));
+
+        assert!(Evaluator::new().eval(parse("1.5 / 0").unwrap()).is_err());
+        assert!(Evaluator::new().eval(parse("1 % 0.0").unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_eval_num_and_note() {
+        let eval = |source: &str| Evaluator::new().eval(parse(source).unwrap());
+        assert_eq!(eval("num(C)").unwrap(), Value::Number(60));
+        assert_eq!(eval("num(Bb3)").unwrap(), Value::Number(58));
+        assert_eq!(eval("num(C, \"midi\")").unwrap(), Value::Number(60));
+        assert_eq!(eval("num(C, \"pitch_class\")").unwrap(), Value::Number(0));
+        assert_eq!(
+            eval("num(Bb3, \"pitch_class\")").unwrap(),
+            Value::Number(10)
+        );
+        assert_eq!(eval("num(2.6)").unwrap(), Value::Number(3));
+        assert_eq!(eval("num(\"12\")").unwrap(), Value::Number(12));
+        assert_eq!(eval("num(E) + 2").unwrap(), Value::Number(66));
+
+        let Value::Note(e4) = eval("note(64)").unwrap() else {
+            panic!("note() should return a note");
+        };
+        assert_eq!(e4.full_name(), "E4");
+        let Value::Note(up) = eval("note(num(E) + 2)").unwrap() else {
+            panic!("note() should return a note");
+        };
+        assert_eq!(up.full_name(), "F#4");
+
+        // num and note undo each other across the whole MIDI range
+        for midi in [0, 60, 127] {
+            assert_eq!(
+                eval(&format!("num(note({}))", midi)).unwrap(),
+                Value::Number(midi)
+            );
+        }
+        assert_eq!(
+            eval("note(num(G2))").unwrap(),
+            eval("G2").unwrap(),
+            "note(num(n)) should give back the note"
+        );
+
+        assert!(eval("note(128)").is_err());
+        assert!(eval("note(-1)").is_err());
+        assert!(eval("num(\"C E\")").is_err());
+        assert!(eval("num(C, \"hz\")").is_err());
+    }
+
+    #[test]
+    fn test_eval_zoom() {
+        let eval = |source: &str| Evaluator::new().eval(parse(source).unwrap());
+        let Value::Pattern(p) = eval("zoom(\"C E G D\", 0, 50)").unwrap() else {
+            panic!("zoom() should return a pattern");
+        };
+        assert_eq!(p.to_string(), "\"C E\"");
+        let Value::Pattern(p) = eval("\"C E G D\".zoom(25, 100)").unwrap() else {
+            panic!("zoom() should return a pattern");
+        };
+        assert_eq!(p.to_string(), "\"E G D\"");
+        assert!(eval("zoom(\"C E\", 50, 50)").is_err());
+        assert!(eval("zoom(\"C E\", 0, 150)").is_err());
+        assert!(eval("zoom(C, 0, 50)").is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_eval_to_json_from_json() {
+        let eval = |source: &str| Evaluator::new().eval(parse(source).unwrap());
+        assert_eq!(
+            eval("to_json([C, E])").unwrap(),
+            Value::String(
+                r#"{"type":"chord","value":{"notes":["C4","E4"],"bass":"C4"}}"#.to_string()
+            )
+        );
+        assert_eq!(
+            eval("from_json(to_json(\"C [E G]\"))").unwrap(),
+            eval("\"C [E G]\"").unwrap()
+        );
+        assert!(eval("from_json(3)").is_err());
+        assert!(eval("from_json(\"not json\")").is_err());
+    }
+
+    #[test]
+    fn test_eval_best_voicing() {
+        let eval = |source: &str| Evaluator::new().eval(parse(source).unwrap());
+        let Value::Chord(chord) =
+            eval("best_voicing([C4, E4, G4], [[F5, A5, C6], [C4, F4, A4]])").unwrap()
+        else {
+            panic!("best_voicing() should return a chord");
+        };
+        let octaves: Vec<i8> = chord.notes().map(|note| note.octave()).collect();
+        assert_eq!(octaves, vec![4, 4, 4]);
+        assert!(eval("best_voicing([C4, E4, G4], [])").is_err());
+        assert!(eval("best_voicing([C4, E4, G4], [C4, 2])").is_err());
+    }
+
+    #[test]
+    fn test_eval_slice() {
+        let eval = |source: &str| Evaluator::new().eval(parse(source).unwrap());
+        assert_eq!(
+            eval("\"C D E F\"[1..3]").unwrap(),
+            eval("take(drop(\"C D E F\", 1), 2)").unwrap()
+        );
+        // Negative bounds count from the end, out-of-bounds ones are clamped
+        assert_eq!(
+            eval("\"C D E F\"[-2..10]").unwrap(),
+            eval("drop(\"C D E F\", 2)").unwrap()
+        );
+        assert_eq!(
+            eval("[C, E, G, B][0..-1]").unwrap(),
+            eval("[C, E, G]").unwrap()
+        );
+        assert_eq!(
+            eval("\"abcd\"[1..2]").unwrap(),
+            Value::String("b".to_string())
+        );
+        match eval("[1, 2, 3][5..9]").unwrap() {
+            Value::Array(values) => assert!(values.is_empty()),
+            other => panic!("expected an array, got {:?}", other),
+        }
+
+        assert!(eval("\"C D E F\"[3..1]").is_err());
+        assert!(eval("\"C D E F\"[0..\"x\"]").is_err());
+    }
+
+    #[test]
+    fn test_eval_chord_scale_and_comp() {
+        use crate::types::PatternStep;
+
+        let eval = |source: &str| Evaluator::new().eval(parse(source).unwrap());
+        assert_eq!(
+            eval("chord_scale([D, F, A, C5], C, \"major\")").unwrap(),
+            Value::String("dorian".to_string())
+        );
+        assert!(eval("chord_scale([D, F, A], C, \"bebop\")").is_err());
+
+        let Value::Array(parts) = eval("comp([D, F, A, C5], C, \"major\")").unwrap() else {
+            panic!("comp() should return an array");
+        };
+        let [Value::Pattern(chordal), Value::Pattern(run)] = parts.as_slice() else {
+            panic!("comp() should return two patterns, got {:?}", parts);
+        };
+        let Value::Chord(chord) = eval("[D, F, A, C5]").unwrap() else {
+            panic!("expected a chord");
+        };
+        assert_eq!(chordal.steps, vec![PatternStep::Chord(chord.clone())]);
+
+        // D dorian from the root up to the octave, holding every chord tone
+        assert_eq!(run.to_string(), "\"D E F G A B C5 D5\"");
+        let run_classes: Vec<u8> = run
+            .steps
+            .iter()
+            .map(|step| match step {
+                PatternStep::Note(note) => note.pitch_class(),
+                other => panic!("expected notes in the run, got {:?}", other),
+            })
+            .collect();
+        assert!(chord
+            .notes()
+            .all(|note| run_classes.contains(&note.pitch_class())));
+
+        assert!(eval("comp(D, C, \"major\")").is_err());
+    }
+
+    #[test]
+    fn test_eval_iter_uses_cycle() {
+        use crate::parser::{Environment, EnvironmentRef};
+
+        let iterated = |cycle: i32| {
+            let mut env = Environment::new();
+            env.define("_cycle".to_string(), Value::Number(cycle));
+            let expr = parse("iter(\"C D E F\", 4)").unwrap();
+            Evaluator::new()
+                .eval_with_env(expr, Some(EnvironmentRef::Borrowed(&env)))
+                .unwrap()
+                .to_string()
+        };
+
+        assert_eq!(iterated(0), "\"C D E F\"");
+        assert_eq!(iterated(1), "\"D E F C\"");
+        assert_eq!(iterated(6), "\"E F C D\"");
+        assert!(Evaluator::new()
+            .eval(parse("iter(\"C D\", 0)").unwrap())
+            .is_err());
+    }
+
+    #[test]
+    fn test_eval_scan_uses_cycle() {
+        use crate::parser::{Environment, EnvironmentRef};
+
+        let sounding_steps = |cycle: i32| {
+            let mut env = Environment::new();
+            env.define("_cycle".to_string(), Value::Number(cycle));
+            let expr = parse("scan(\"C D E F\")").unwrap();
+            match Evaluator::new()
+                .eval_with_env(expr, Some(EnvironmentRef::Borrowed(&env)))
+                .unwrap()
+            {
+                Value::Pattern(p) => p.to_rich_events().iter().filter(|e| !e.is_rest).count(),
+                _ => panic!("Expected pattern value"),
+            }
+        };
+
+        assert_eq!(sounding_steps(0), 1);
+        assert_eq!(sounding_steps(2), 3);
+        assert_eq!(sounding_steps(7), 4);
+    }
 }
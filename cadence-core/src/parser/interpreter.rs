@@ -2,15 +2,26 @@
 //!
 //! Executes statements with side effects (audio, variable binding, control flow).
 
-use crate::parser::ast::{Expression, Program, Statement, Value};
+use crate::parser::ast::{Expression, MidiBinding, Program, SpannedProgram, Statement, Value};
+use crate::parser::builtins::{get_registry, STD_NAMESPACE, STRICT_VARIABLE};
 use crate::parser::environment::{Environment, SharedEnvironment};
 use crate::parser::evaluator::{EnvironmentRef, Evaluator};
 use crate::parser::module_resolver::ModuleResolver;
-use crate::parser::statement_parser::parse_statements;
+use crate::parser::reload::{
+    declared_name, plan_reload, statement_hash, LoadedStatement, NewStatement, ReloadSummary,
+};
+use crate::parser::statement_parser::{parse_spanned_statements, parse_statements};
+use crate::parser::symbols::{FunctionSymbol, Span};
 use crate::types::{QueueMode, ScheduledAction, ScheduledEvent};
 use anyhow::{anyhow, Result};
+use std::collections::{HashMap, HashSet};
 use std::sync::{Arc, RwLock};
 
+/// In WASM, `loop` and `while` stop after this many passes to prevent a
+/// browser freeze
+#[cfg(target_arch = "wasm32")]
+const MAX_LOOP_ITERATIONS: u32 = 10_000;
+
 /// Control flow signals for break/continue/return
 #[derive(Debug)]
 pub enum ControlFlow {
@@ -33,18 +44,53 @@ pub enum InterpreterAction {
         track_id: usize,
         /// Pre-evaluated display value (so we don't re-evaluate after scope is gone)
         display_value: Value,
+        /// Source text as entered (the play statement, or the expression itself)
+        source: String,
         /// Scheduled beat offset from script start (for virtual time via `wait`)
         /// None = immediate playback, Some(beat) = play at this beat offset
         scheduled_beat: Option<f64>,
     },
     /// Set the tempo (global)
     SetTempo(f32),
+    /// Set the capo in semitones (global, applied to sounding notes at dispatch)
+    SetCapo(i32),
     /// Set the volume for a specific track (0.0-1.0)
     SetVolume { volume: f32, track_id: usize },
     /// Set the waveform for a specific track
     SetWaveform { waveform: String, track_id: usize },
+    /// Automate a MIDI CC from a numeric control pattern (re-evaluated live)
+    ControlChange {
+        controller: u8,
+        expression: Expression,
+        smooth: bool,
+        track_id: usize,
+    },
+    /// Send a MIDI program change on a track's channel
+    MidiProgram { program: u8, track_id: usize },
+    /// Send a MIDI bank select (CC 0 MSB, CC 32 LSB) on a track's channel
+    MidiBank { msb: u8, lsb: u8, track_id: usize },
+    /// Set the velocity curve ("linear", "soft" or "hard") for a track's MIDI notes
+    MidiVelocityCurve { curve: String, track_id: usize },
     /// Stop playback (specific track or all)
     Stop { track_id: Option<usize> },
+    /// Register a MIDI input binding with the host; `bind cc ... to volume`
+    /// sets the volume of the track it was bound on
+    Bind {
+        binding: MidiBinding,
+        track_id: usize,
+    },
+    /// Register a REPL command that runs `source` when `name` is typed
+    DefineAlias { name: String, source: String },
+}
+
+/// A top-level definition, kept so a session can be written back out as source
+#[derive(Debug, Clone)]
+struct Definition {
+    /// Name bound or assigned (`None` for `use`, which can bind several)
+    name: Option<String>,
+    statement: Statement,
+    /// Source text of the top-level statement, when it was run from source
+    source: Option<String>,
 }
 
 /// Interpreter for executing Cadence statements
@@ -55,6 +101,8 @@ pub struct Interpreter {
     pub environment: SharedEnvironment,
     /// Current tempo (BPM)
     pub tempo: f32,
+    /// Capo offset in semitones applied to playback (0 = none)
+    pub capo: i32,
     /// Current volume (0.0-1.0)
     pub volume: f32,
     /// Current track ID (default 1)
@@ -69,6 +117,12 @@ pub struct Interpreter {
     pub virtual_time: f64,
     /// Scheduled events for future execution (Sonic Pi style)
     scheduled_events: Vec<ScheduledEvent>,
+    /// Source text of the top-level statement being run
+    statement_source: Option<String>,
+    /// Top-level lets, assignments, functions and uses in the order they ran
+    definitions: Vec<Definition>,
+    /// Statements of each file last run with `reload_spanned_program`, by key
+    loaded: HashMap<String, Vec<LoadedStatement>>,
     /// Module resolver for `use` statements (optional, created on first use)
     #[cfg(not(target_arch = "wasm32"))]
     module_resolver: Option<ModuleResolver>,
@@ -81,6 +135,7 @@ impl Interpreter {
             evaluator: Evaluator::new(),
             environment: Arc::new(RwLock::new(Environment::new())),
             tempo: 120.0,
+            capo: 0,
             volume: 0.5,
             current_track: 1,
             in_track_block: false,
@@ -88,11 +143,22 @@ impl Interpreter {
             actions: Vec::new(),
             virtual_time: 0.0,
             scheduled_events: Vec::new(),
+            statement_source: None,
+            definitions: Vec::new(),
+            loaded: HashMap::new(),
             #[cfg(not(target_arch = "wasm32"))]
             module_resolver: None,
         }
     }
 
+    /// Source text for a play of `target`: the statement as written when it
+    /// was run from source, otherwise the expression itself
+    fn play_source(&self, target: &Expression) -> String {
+        self.statement_source
+            .clone()
+            .unwrap_or_else(|| target.to_string())
+    }
+
     /// Get a clone of the shared environment for passing to playback threads
     pub fn shared_environment(&self) -> SharedEnvironment {
         self.environment.clone()
@@ -128,10 +194,36 @@ impl Interpreter {
 
     /// Run a complete program
     pub fn run_program(&mut self, program: &Program) -> Result<Option<Value>> {
+        self.run_statements(program.statements.iter().map(|stmt| (stmt, None)))
+    }
+
+    /// Run a program parsed from `source`, keeping the text of each top-level
+    /// statement for play actions and for saving the session as it was written
+    pub fn run_spanned_program(
+        &mut self,
+        program: &SpannedProgram,
+        source: &str,
+    ) -> Result<Option<Value>> {
+        self.run_statements(
+            program
+                .statements
+                .iter()
+                .map(|spanned| (&spanned.statement, source.get(spanned.start..spanned.end))),
+        )
+    }
+
+    fn run_statements<'a>(
+        &mut self,
+        statements: impl Iterator<Item = (&'a Statement, Option<&'a str>)>,
+    ) -> Result<Option<Value>> {
         let mut last_value = None;
 
-        for stmt in &program.statements {
-            match self.run_statement(stmt)? {
+        for (stmt, source) in statements {
+            self.statement_source = source.map(str::to_string);
+            let flow = self.run_statement(stmt);
+            self.statement_source = None;
+
+            match flow? {
                 ControlFlow::Normal => {}
                 ControlFlow::Return(val) => return Ok(val),
                 ControlFlow::Break => return Err(anyhow!("Break outside of loop")),
@@ -147,17 +239,136 @@ impl Interpreter {
         Ok(last_value)
     }
 
+    /// Run only what changed in a program since it was last run under `key`
+    /// (usually the file's path).
+    ///
+    /// Statements whose text is unchanged are skipped, so their bindings and
+    /// any tracks they started are left alone. Bindings made by statements
+    /// that are gone are removed. New and edited statements run in file order;
+    /// if one fails, it and any after it run again on the next reload.
+    pub fn reload_spanned_program(
+        &mut self,
+        key: &str,
+        program: &SpannedProgram,
+        source: &str,
+    ) -> Result<ReloadSummary> {
+        let texts: Vec<&str> = program
+            .statements
+            .iter()
+            .map(|spanned| source.get(spanned.start..spanned.end).unwrap_or(""))
+            .collect();
+        let new: Vec<NewStatement> = program
+            .statements
+            .iter()
+            .zip(&texts)
+            .map(|(spanned, text)| NewStatement {
+                hash: statement_hash(text),
+                name: declared_name(&spanned.statement),
+            })
+            .collect();
+        let old = self.loaded.remove(key).unwrap_or_default();
+        let plan = plan_reload(&old, &new);
+
+        // Names still bound by the file are replaced, not removed, so tracks
+        // reading them keep playing
+        let still_bound: HashSet<&str> = plan
+            .run
+            .iter()
+            .filter_map(|&i| new[i].name)
+            .chain(
+                plan.kept
+                    .iter()
+                    .flat_map(|&(_, i)| old[i].names.iter().map(String::as_str)),
+            )
+            .collect();
+        for &i in &plan.removed {
+            for name in &old[i].names {
+                if still_bound.contains(name.as_str()) {
+                    continue;
+                }
+                self.environment.write().unwrap().undefine(name);
+                self.definitions
+                    .retain(|def| def.name.as_ref() != Some(name));
+            }
+        }
+
+        let mut loaded: Vec<Option<LoadedStatement>> = vec![None; new.len()];
+        for &(new_index, old_index) in &plan.kept {
+            loaded[new_index] = Some(old[old_index].clone());
+        }
+
+        let mut result = Ok(());
+        for &i in &plan.run {
+            let statement = &program.statements[i].statement;
+            let before = self.global_names();
+            self.statement_source = Some(texts[i].to_string());
+            let flow = self.run_statement(statement);
+            self.statement_source = None;
+
+            let flow = match flow {
+                Ok(ControlFlow::Normal) | Ok(ControlFlow::Return(_)) => Ok(()),
+                Ok(ControlFlow::Break) => Err(anyhow!("Break outside of loop")),
+                Ok(ControlFlow::Continue) => Err(anyhow!("Continue outside of loop")),
+                Err(e) => Err(e),
+            };
+            if flow.is_err() {
+                result = flow;
+                break;
+            }
+
+            let mut names: Vec<String> = new[i].name.map(str::to_string).into_iter().collect();
+            for name in self.global_names() {
+                if !before.contains(&name) && !names.contains(&name) {
+                    names.push(name);
+                }
+            }
+            loaded[i] = Some(LoadedStatement {
+                hash: new[i].hash,
+                names,
+            });
+        }
+
+        // Statements that didn't run successfully are left out, so they're
+        // seen as new next time
+        self.loaded
+            .insert(key.to_string(), loaded.into_iter().flatten().collect());
+        result.map(|()| plan.summary)
+    }
+
+    /// Forget what was loaded under `key`, so the next reload runs everything
+    pub fn forget_loaded(&mut self, key: &str) {
+        self.loaded.remove(key);
+    }
+
+    /// Names bound in the global scope, skipping host-injected ones like `_beat`
+    fn global_names(&self) -> Vec<String> {
+        let env = self.environment.read().unwrap();
+        env.current_scope_names()
+            .into_iter()
+            .filter(|name| !name.starts_with('_'))
+            .cloned()
+            .collect()
+    }
+
     /// Run a single statement
     pub fn run_statement(&mut self, stmt: &Statement) -> Result<ControlFlow> {
         match stmt {
             Statement::Let { name, value } => {
-                // Store as thunk for lazy/reactive evaluation (TidalCycles-style)
-                // The expression will be re-evaluated each time the variable is accessed
-                let val = Value::Thunk {
-                    expression: Box::new(value.clone()),
-                    env: self.environment.clone(),
+                let in_block = self.environment.read().unwrap().depth() > 1;
+                let val = if in_block {
+                    // Block-local bindings are discarded on block exit, so evaluate
+                    // now; this also lets `let x = x + 2` read the outer `x`
+                    self.eval_expression(value)?
+                } else {
+                    // Store as thunk for lazy/reactive evaluation (TidalCycles-style)
+                    // The expression will be re-evaluated each time the variable is accessed
+                    Value::Thunk {
+                        expression: Box::new(value.clone()),
+                        env: self.environment.clone(),
+                    }
                 };
                 self.environment.write().unwrap().define(name.clone(), val);
+                self.record_definition(Some(name), stmt);
                 Ok(ControlFlow::Normal)
             }
 
@@ -172,6 +383,19 @@ impl Interpreter {
                 } else {
                     return Err(anyhow!("Cannot assign to undefined variable '{}'", name));
                 }
+                self.record_definition(Some(name), stmt);
+                Ok(ControlFlow::Normal)
+            }
+
+            Statement::IndexAssign { name, index, value } => {
+                let env = Some(EnvironmentRef::Shared(self.environment.clone()));
+                let val = self.evaluator.eval_index_assign(name, index, value, env)?;
+                self.environment
+                    .write()
+                    .unwrap()
+                    .set(name, val)
+                    .map_err(|e| anyhow!("{}", e))?;
+                self.record_definition(Some(name), stmt);
                 Ok(ControlFlow::Normal)
             }
 
@@ -185,6 +409,7 @@ impl Interpreter {
                 let val = self.eval_expression(expr)?;
                 let bpm = match val {
                     Value::Number(n) => n as f32,
+                    Value::Float(n) => n,
                     _ => return Err(anyhow!("Tempo requires a numeric value")),
                 };
                 self.tempo = bpm;
@@ -193,6 +418,18 @@ impl Interpreter {
                 Ok(ControlFlow::Normal)
             }
 
+            Statement::Capo(expr) => {
+                let val = self.eval_expression(expr)?;
+                let semitones = match val {
+                    Value::Number(n) => n,
+                    _ => return Err(anyhow!("Capo requires a number of semitones")),
+                };
+                self.capo = semitones;
+                self.actions.push(InterpreterAction::SetCapo(semitones));
+                println!("Capo set to {} semitones", semitones);
+                Ok(ControlFlow::Normal)
+            }
+
             Statement::Volume(expr) => {
                 let val = self.eval_expression(expr)?;
                 let vol = match val {
@@ -223,6 +460,61 @@ impl Interpreter {
                 Ok(ControlFlow::Normal)
             }
 
+            Statement::ControlChange {
+                controller,
+                target,
+                smooth,
+            } => {
+                // Validate the control pattern up front (rejects note patterns)
+                let val = self.eval_expression(target)?;
+                let control = val.to_control_pattern().map_err(|e| anyhow!("cc: {}", e))?;
+                self.actions.push(InterpreterAction::ControlChange {
+                    controller: *controller,
+                    expression: target.clone(),
+                    smooth: *smooth,
+                    track_id: self.current_track,
+                });
+                println!(
+                    "CC {} automation {}{} (Track {})",
+                    controller,
+                    control,
+                    if *smooth { " smooth" } else { "" },
+                    self.current_track
+                );
+                Ok(ControlFlow::Normal)
+            }
+
+            Statement::MidiProgram(program) => {
+                self.actions.push(InterpreterAction::MidiProgram {
+                    program: *program,
+                    track_id: self.current_track,
+                });
+                println!("MIDI program {} (Track {})", program, self.current_track);
+                Ok(ControlFlow::Normal)
+            }
+
+            Statement::MidiBank { msb, lsb } => {
+                self.actions.push(InterpreterAction::MidiBank {
+                    msb: *msb,
+                    lsb: *lsb,
+                    track_id: self.current_track,
+                });
+                println!("MIDI bank {} {} (Track {})", msb, lsb, self.current_track);
+                Ok(ControlFlow::Normal)
+            }
+
+            Statement::MidiVelocityCurve(curve) => {
+                self.actions.push(InterpreterAction::MidiVelocityCurve {
+                    curve: curve.clone(),
+                    track_id: self.current_track,
+                });
+                println!(
+                    "MIDI velocity curve {} (Track {})",
+                    curve, self.current_track
+                );
+                Ok(ControlFlow::Normal)
+            }
+
             Statement::Stop => {
                 // At top-level, stop ALL tracks.
                 // Inside a `track N { stop }` block, stop only that track.
@@ -272,6 +564,7 @@ impl Interpreter {
                                         frequencies: event_info.frequencies,
                                         duration_beats: event_info.duration_beats,
                                         drums: event_info.drums,
+                                        velocities: event_info.velocities,
                                     },
                                     self.current_track,
                                 ));
@@ -314,7 +607,13 @@ impl Interpreter {
                         queue_mode,
                         track_id: self.current_track,
                         display_value: val.clone(),
-                        scheduled_beat: None,
+                        source: self.play_source(target),
+                        // Capture virtual time so a loop after `wait` starts later
+                        scheduled_beat: if self.virtual_time > 0.0 {
+                            Some(self.virtual_time)
+                        } else {
+                            None
+                        },
                     });
                     println!("Playing {} (looping, Track {})", val, self.current_track);
                 }
@@ -337,16 +636,33 @@ impl Interpreter {
                 result
             }
 
+            Statement::Bind(binding) => {
+                self.actions.push(InterpreterAction::Bind {
+                    binding: binding.clone(),
+                    track_id: self.current_track,
+                });
+                println!("MIDI input: {} (Track {})", binding, self.current_track);
+                Ok(ControlFlow::Normal)
+            }
+
+            Statement::Alias { name, source } => {
+                self.actions.push(InterpreterAction::DefineAlias {
+                    name: name.clone(),
+                    source: source.clone(),
+                });
+                Ok(ControlFlow::Normal)
+            }
+
             Statement::Load(path) => {
                 #[cfg(not(target_arch = "wasm32"))]
                 {
                     let contents = std::fs::read_to_string(path)
                         .map_err(|e| anyhow!("Failed to load '{}': {}", path, e))?;
-                    let program = parse_statements(&contents)
+                    let program = parse_spanned_statements(&contents)
                         .map_err(|e| anyhow!("Parse error in '{}': {}", path, e))?;
 
                     println!("Loaded: {}", path);
-                    self.run_program(&program)?;
+                    self.run_spanned_program(&program, &contents)?;
                     Ok(ControlFlow::Normal)
                 }
                 #[cfg(target_arch = "wasm32")]
@@ -358,51 +674,69 @@ impl Interpreter {
             Statement::Loop { body } => {
                 // In WASM, limit loop iterations to prevent browser freeze
                 #[cfg(target_arch = "wasm32")]
-                const MAX_ITERATIONS: u32 = 10_000;
-                #[cfg(target_arch = "wasm32")]
                 let mut iterations = 0u32;
 
                 loop {
                     #[cfg(target_arch = "wasm32")]
                     {
                         iterations += 1;
-                        if iterations > MAX_ITERATIONS {
+                        if iterations > MAX_LOOP_ITERATIONS {
                             return Err(anyhow!(
                                 "Loop exceeded maximum iterations ({}) - possible infinite loop",
-                                MAX_ITERATIONS
+                                MAX_LOOP_ITERATIONS
                             ));
                         }
                     }
 
-                    for stmt in body {
-                        match self.run_statement(stmt)? {
-                            ControlFlow::Normal => {}
-                            ControlFlow::Break => return Ok(ControlFlow::Normal),
-                            ControlFlow::Continue => break,
-                            ControlFlow::Return(val) => return Ok(ControlFlow::Return(val)),
+                    match self.run_block(body, None)? {
+                        ControlFlow::Normal | ControlFlow::Continue => {}
+                        ControlFlow::Break => return Ok(ControlFlow::Normal),
+                        ControlFlow::Return(val) => return Ok(ControlFlow::Return(val)),
+                    }
+                }
+            }
+
+            Statement::While { condition, body } => {
+                // Same runaway guard as `loop`: a condition that never turns
+                // false is only stopped by it in WASM
+                #[cfg(target_arch = "wasm32")]
+                let mut iterations = 0u32;
+
+                loop {
+                    let is_true = match self.eval_expression(condition)? {
+                        Value::Boolean(b) => b,
+                        _ => return Err(anyhow!("Condition must be a boolean")),
+                    };
+                    if !is_true {
+                        return Ok(ControlFlow::Normal);
+                    }
+
+                    #[cfg(target_arch = "wasm32")]
+                    {
+                        iterations += 1;
+                        if iterations > MAX_LOOP_ITERATIONS {
+                            return Err(anyhow!(
+                                "While loop exceeded maximum iterations ({}) - possible infinite loop",
+                                MAX_LOOP_ITERATIONS
+                            ));
                         }
                     }
+
+                    match self.run_block(body, None)? {
+                        ControlFlow::Normal | ControlFlow::Continue => {}
+                        ControlFlow::Break => return Ok(ControlFlow::Normal),
+                        ControlFlow::Return(val) => return Ok(ControlFlow::Return(val)),
+                    }
                 }
             }
 
             Statement::Repeat { count, body } => {
                 for _ in 0..*count {
-                    self.environment.write().unwrap().push_scope();
-                    for stmt in body {
-                        match self.run_statement(stmt)? {
-                            ControlFlow::Normal => {}
-                            ControlFlow::Break => {
-                                self.environment.write().unwrap().pop_scope();
-                                return Ok(ControlFlow::Normal);
-                            }
-                            ControlFlow::Continue => break,
-                            ControlFlow::Return(val) => {
-                                self.environment.write().unwrap().pop_scope();
-                                return Ok(ControlFlow::Return(val));
-                            }
-                        }
+                    match self.run_block(body, None)? {
+                        ControlFlow::Normal | ControlFlow::Continue => {}
+                        ControlFlow::Break => return Ok(ControlFlow::Normal),
+                        ControlFlow::Return(val) => return Ok(ControlFlow::Return(val)),
                     }
-                    self.environment.write().unwrap().pop_scope();
                 }
                 Ok(ControlFlow::Normal)
             }
@@ -426,26 +760,11 @@ impl Interpreter {
                 };
 
                 for i in start_num..end_num {
-                    self.environment.write().unwrap().push_scope();
-                    self.environment
-                        .write()
-                        .unwrap()
-                        .define(var.clone(), Value::Number(i));
-                    for stmt in body {
-                        match self.run_statement(stmt)? {
-                            ControlFlow::Normal => {}
-                            ControlFlow::Break => {
-                                self.environment.write().unwrap().pop_scope();
-                                return Ok(ControlFlow::Normal);
-                            }
-                            ControlFlow::Continue => break,
-                            ControlFlow::Return(val) => {
-                                self.environment.write().unwrap().pop_scope();
-                                return Ok(ControlFlow::Return(val));
-                            }
-                        }
+                    match self.run_block(body, Some((var, Value::Number(i))))? {
+                        ControlFlow::Normal | ControlFlow::Continue => {}
+                        ControlFlow::Break => return Ok(ControlFlow::Normal),
+                        ControlFlow::Return(val) => return Ok(ControlFlow::Return(val)),
                     }
-                    self.environment.write().unwrap().pop_scope();
                 }
                 Ok(ControlFlow::Normal)
             }
@@ -470,18 +789,7 @@ impl Interpreter {
                     }
                 };
 
-                self.environment.write().unwrap().push_scope();
-                for stmt in body {
-                    match self.run_statement(stmt)? {
-                        ControlFlow::Normal => {}
-                        cf => {
-                            self.environment.write().unwrap().pop_scope();
-                            return Ok(cf);
-                        }
-                    }
-                }
-                self.environment.write().unwrap().pop_scope();
-                Ok(ControlFlow::Normal)
+                self.run_block(body, None)
             }
 
             Statement::Break => Ok(ControlFlow::Break),
@@ -496,35 +804,47 @@ impl Interpreter {
 
             Statement::Comment(_) => Ok(ControlFlow::Normal),
 
-            Statement::Block(stmts) => {
-                self.environment.write().unwrap().push_scope();
-                for stmt in stmts {
-                    match self.run_statement(stmt)? {
-                        ControlFlow::Normal => {}
-                        cf => {
-                            self.environment.write().unwrap().pop_scope();
-                            return Ok(cf);
-                        }
-                    }
-                }
-                self.environment.write().unwrap().pop_scope();
-                Ok(ControlFlow::Normal)
-            }
+            Statement::Block(stmts) => self.run_block(stmts, None),
 
             Statement::FunctionDef {
-                name, params, body, ..
+                name,
+                params,
+                body,
+                return_type,
+                ..
             } => {
                 // Store the function as a Value::Function in the environment
                 let func_value = Value::Function {
                     name: name.clone(),
                     params: params.clone(),
                     body: body.clone(),
+                    return_type: return_type.clone(),
                 };
                 self.environment
                     .write()
                     .unwrap()
                     .define(name.clone(), func_value);
+                self.record_definition(Some(name), stmt);
                 println!("Defined function: {}({})", name, params.join(", "));
+                let strict = matches!(
+                    self.environment.read().unwrap().get(STRICT_VARIABLE),
+                    Some(Value::Boolean(true))
+                );
+                if !strict && get_registry().get(name).is_some() {
+                    eprintln!(
+                        "Warning: {}() shadows the builtin of the same name; call {}.{}() for the builtin",
+                        name, STD_NAMESPACE, name
+                    );
+                }
+                Ok(ControlFlow::Normal)
+            }
+
+            Statement::UseStrict => {
+                self.environment
+                    .write()
+                    .unwrap()
+                    .define(STRICT_VARIABLE.to_string(), Value::Boolean(true));
+                self.record_definition(None, stmt);
                 Ok(ControlFlow::Normal)
             }
 
@@ -563,13 +883,14 @@ impl Interpreter {
                             for (name, val) in &exports.values {
                                 env.define(name.clone(), val.clone());
                             }
-                            for (name, (params, body)) in &exports.functions {
+                            for (name, (params, body, return_type)) in &exports.functions {
                                 env.define(
                                     name.clone(),
                                     Value::Function {
                                         name: name.clone(),
                                         params: params.clone(),
                                         body: body.clone(),
+                                        return_type: return_type.clone(),
                                     },
                                 );
                             }
@@ -586,13 +907,14 @@ impl Interpreter {
                             for (name, val) in &exports.values {
                                 env.define(format!("{}_{}", ns, name), val.clone());
                             }
-                            for (name, (params, body)) in &exports.functions {
+                            for (name, (params, body, return_type)) in &exports.functions {
                                 env.define(
                                     format!("{}_{}", ns, name),
                                     Value::Function {
                                         name: name.clone(),
                                         params: params.clone(),
                                         body: body.clone(),
+                                        return_type: return_type.clone(),
                                     },
                                 );
                             }
@@ -631,6 +953,8 @@ impl Interpreter {
                         }
                     }
 
+                    drop(env);
+                    self.record_definition(None, stmt);
                     Ok(ControlFlow::Normal)
                 }
                 #[cfg(target_arch = "wasm32")]
@@ -644,6 +968,168 @@ impl Interpreter {
         }
     }
 
+    /// Remember a top-level definition for `definitions_source`.
+    /// A new `let` or `fn` replaces everything recorded earlier for that name.
+    fn record_definition(&mut self, name: Option<&String>, stmt: &Statement) {
+        if self.environment.read().unwrap().depth() > 1 {
+            return;
+        }
+        let reassigned = matches!(
+            stmt,
+            Statement::Assign { .. } | Statement::IndexAssign { .. }
+        );
+        if let (Some(name), false) = (name, reassigned) {
+            self.definitions
+                .retain(|def| def.name.as_ref() != Some(name));
+        }
+        self.definitions.push(Definition {
+            name: name.cloned(),
+            statement: stmt.clone(),
+            source: self.statement_source.clone(),
+        });
+    }
+
+    /// A snapshot of the global bindings, sorted by name. `let` bindings
+    /// are resolved to their current value; host variables like `_beat` are
+    /// left out.
+    pub fn bindings(&self) -> Vec<(String, Value)> {
+        let mut bindings: Vec<(String, Value)> = {
+            let env = self.environment.read().unwrap();
+            env.current_scope_names()
+                .into_iter()
+                .filter(|name| !name.starts_with('_'))
+                .filter_map(|name| env.get(name).map(|value| (name.clone(), value.clone())))
+                .collect()
+        };
+        for (_, value) in &mut bindings {
+            if let Value::Thunk { expression, .. } = value {
+                // A binding whose expression fails now is shown unevaluated
+                if let Ok(resolved) = self.eval_expression(expression) {
+                    *value = resolved;
+                }
+            }
+        }
+        bindings.sort_by(|(a, _), (b, _)| a.cmp(b));
+        bindings
+    }
+
+    /// Top-level user functions, sorted by name, with their doc comments and
+    /// return annotations
+    pub fn user_functions(&self) -> Vec<FunctionSymbol> {
+        let mut functions: Vec<FunctionSymbol> = self
+            .definitions
+            .iter()
+            .filter_map(|def| match &def.statement {
+                Statement::FunctionDef {
+                    name,
+                    params,
+                    return_type,
+                    doc,
+                    ..
+                } => {
+                    let mut func =
+                        FunctionSymbol::new(name.clone(), params.clone(), Span::new(0, 0));
+                    func.doc_comment = doc.clone();
+                    func.return_type = return_type.clone();
+                    Some(func)
+                }
+                _ => None,
+            })
+            .collect();
+        functions.sort_by(|a, b| a.name.cmp(&b.name));
+        functions
+    }
+
+    /// Remove a global binding, and forget its definition so a saved session
+    /// doesn't bring it back. Returns false if nothing was bound to `name`.
+    pub fn unset(&mut self, name: &str) -> bool {
+        if !self.environment.write().unwrap().undefine(name) {
+            return false;
+        }
+        self.definitions
+            .retain(|def| def.name.as_deref() != Some(name));
+        true
+    }
+
+    /// Remove every global binding and definition, as in a fresh session.
+    /// The shared environment is cleared in place, so anything holding it
+    /// sees the change.
+    pub fn clear_environment(&mut self) {
+        self.environment.write().unwrap().clear();
+        self.definitions.clear();
+        self.loaded.clear();
+    }
+
+    /// Top-level definitions as runnable source, in the order they ran.
+    ///
+    /// Definitions run without source text are printed from the syntax tree;
+    /// any that don't parse back to the same statement (such as function
+    /// bodies) are written as comments, with a warning for each.
+    pub fn definitions_source(&self) -> (String, Vec<String>) {
+        let mut output = String::new();
+        let mut warnings = Vec::new();
+
+        for def in &self.definitions {
+            if let Some(source) = &def.source {
+                output.push_str(source);
+                output.push('\n');
+                continue;
+            }
+
+            let text = def.statement.to_string();
+            let round_trips = parse_statements(&text)
+                .is_ok_and(|program| program.statements == [def.statement.clone()]);
+            if round_trips {
+                output.push_str(&text);
+                output.push('\n');
+            } else {
+                for line in text.lines() {
+                    output.push_str(&format!("// {}\n", line));
+                }
+                warnings.push(format!(
+                    "'{}' can't be written as source; saved as a comment",
+                    def.name.as_deref().unwrap_or(&text)
+                ));
+            }
+        }
+
+        (output, warnings)
+    }
+
+    /// Run a block body in its own scope, optionally binding a loop variable.
+    ///
+    /// `let` inside the block shadows outer bindings until the block exits;
+    /// assignments still update the nearest enclosing binding. The scope is
+    /// popped however the block exits, including on error. Control flow other
+    /// than `Normal` stops the block and is returned for the caller to handle.
+    fn run_block(
+        &mut self,
+        body: &[Statement],
+        binding: Option<(&String, Value)>,
+    ) -> Result<ControlFlow> {
+        {
+            let mut env = self.environment.write().unwrap();
+            env.push_scope();
+            if let Some((name, value)) = binding {
+                env.define(name.clone(), value);
+            }
+        }
+
+        let mut result = Ok(ControlFlow::Normal);
+        for stmt in body {
+            match self.run_statement(stmt) {
+                Ok(ControlFlow::Normal) => {}
+                other => {
+                    result = other;
+                    break;
+                }
+            }
+        }
+
+        self.environment.write().unwrap().pop_scope();
+        result
+    }
+
     /// Evaluate an expression using the environment
     fn eval_expression(&self, expr: &crate::parser::ast::Expression) -> Result<Value> {
         // Use eval_with_env to enable variable resolution
@@ -689,6 +1175,19 @@ impl Interpreter {
                 Ok(ControlFlow::Normal)
             }
 
+            Statement::IndexAssign { name, index, value } => {
+                let val = self.evaluator.eval_index_assign(
+                    name,
+                    index,
+                    value,
+                    Some(EnvironmentRef::Borrowed(local_env)),
+                )?;
+                local_env
+                    .set(name, val)
+                    .map_err(|e| anyhow::anyhow!("{}", e))?;
+                Ok(ControlFlow::Normal)
+            }
+
             Statement::Expression(expr) => {
                 let _val = self
                     .evaluator
@@ -825,6 +1324,42 @@ impl Interpreter {
                 Ok(ControlFlow::Normal)
             }
 
+            Statement::While { condition, body } => {
+                loop {
+                    let cond_val = self.evaluator.eval_with_env(
+                        condition.clone(),
+                        Some(EnvironmentRef::Borrowed(local_env)),
+                    )?;
+                    match cond_val {
+                        Value::Boolean(true) => {}
+                        Value::Boolean(false) => break,
+                        _ => return Err(anyhow::anyhow!("Condition must be a boolean")),
+                    }
+
+                    local_env.push_scope();
+                    let mut should_break = false;
+                    for stmt in body {
+                        match self.run_statement_with_local_env(stmt, local_env)? {
+                            ControlFlow::Normal => {}
+                            ControlFlow::Break => {
+                                should_break = true;
+                                break;
+                            }
+                            ControlFlow::Continue => break,
+                            cf @ ControlFlow::Return(_) => {
+                                local_env.pop_scope();
+                                return Ok(cf);
+                            }
+                        }
+                    }
+                    local_env.pop_scope();
+                    if should_break {
+                        break;
+                    }
+                }
+                Ok(ControlFlow::Normal)
+            }
+
             Statement::Loop { body } => {
                 loop {
                     local_env.push_scope();
@@ -861,6 +1396,7 @@ impl Interpreter {
                     .eval_with_env(expr.clone(), Some(EnvironmentRef::Borrowed(local_env)))?;
                 let bpm = match val {
                     Value::Number(n) => n as f32,
+                    Value::Float(n) => n,
                     _ => return Err(anyhow!("Tempo requires a numeric value")),
                 };
                 self.tempo = bpm;
@@ -868,6 +1404,19 @@ impl Interpreter {
                 Ok(ControlFlow::Normal)
             }
 
+            Statement::Capo(expr) => {
+                let val = self
+                    .evaluator
+                    .eval_with_env(expr.clone(), Some(EnvironmentRef::Borrowed(local_env)))?;
+                let semitones = match val {
+                    Value::Number(n) => n,
+                    _ => return Err(anyhow!("Capo requires a number of semitones")),
+                };
+                self.capo = semitones;
+                self.actions.push(InterpreterAction::SetCapo(semitones));
+                Ok(ControlFlow::Normal)
+            }
+
             Statement::Volume(expr) => {
                 let val = self
                     .evaluator
@@ -893,6 +1442,49 @@ impl Interpreter {
                 Ok(ControlFlow::Normal)
             }
 
+            Statement::ControlChange {
+                controller,
+                target,
+                smooth,
+            } => {
+                let val = self
+                    .evaluator
+                    .eval_with_env(target.clone(), Some(EnvironmentRef::Borrowed(local_env)))?;
+                val.to_control_pattern().map_err(|e| anyhow!("cc: {}", e))?;
+                self.actions.push(InterpreterAction::ControlChange {
+                    controller: *controller,
+                    expression: target.clone(),
+                    smooth: *smooth,
+                    track_id: self.current_track,
+                });
+                Ok(ControlFlow::Normal)
+            }
+
+            Statement::MidiProgram(program) => {
+                self.actions.push(InterpreterAction::MidiProgram {
+                    program: *program,
+                    track_id: self.current_track,
+                });
+                Ok(ControlFlow::Normal)
+            }
+
+            Statement::MidiBank { msb, lsb } => {
+                self.actions.push(InterpreterAction::MidiBank {
+                    msb: *msb,
+                    lsb: *lsb,
+                    track_id: self.current_track,
+                });
+                Ok(ControlFlow::Normal)
+            }
+
+            Statement::MidiVelocityCurve(curve) => {
+                self.actions.push(InterpreterAction::MidiVelocityCurve {
+                    curve: curve.clone(),
+                    track_id: self.current_track,
+                });
+                Ok(ControlFlow::Normal)
+            }
+
             Statement::Play {
                 target,
                 looping,
@@ -909,6 +1501,7 @@ impl Interpreter {
                     queue_mode: None,
                     track_id: self.current_track,
                     display_value: val,
+                    source: self.play_source(target),
                     // Capture virtual time for scheduled playback
                     scheduled_beat: if self.virtual_time > 0.0 {
                         Some(self.virtual_time)
@@ -928,12 +1521,17 @@ impl Interpreter {
 
             // Function definitions inside functions - define in local scope
             Statement::FunctionDef {
-                name, params, body, ..
+                name,
+                params,
+                body,
+                return_type,
+                ..
             } => {
                 let func_value = Value::Function {
                     name: name.clone(),
                     params: params.clone(),
                     body: body.clone(),
+                    return_type: return_type.clone(),
                 };
                 local_env.define(name.clone(), func_value);
                 Ok(ControlFlow::Normal)
@@ -967,9 +1565,21 @@ impl Interpreter {
                 Ok(ControlFlow::Normal)
             }
 
-            Statement::Use { .. } => Err(anyhow::anyhow!(
+            Statement::Use { .. } | Statement::UseStrict => Err(anyhow::anyhow!(
                 "use/import is not allowed inside functions"
             )),
+
+            Statement::Bind(binding) => {
+                self.actions.push(InterpreterAction::Bind {
+                    binding: binding.clone(),
+                    track_id: self.current_track,
+                });
+                Ok(ControlFlow::Normal)
+            }
+
+            Statement::Alias { .. } => {
+                Err(anyhow::anyhow!("alias is not allowed inside functions"))
+            }
         }
     }
 }
@@ -983,6 +1593,7 @@ impl Default for Interpreter {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::parser::parse;
     use crate::parser::statement_parser::parse_statements;
 
     #[test]
@@ -1296,6 +1907,105 @@ mod tests {
         assert_eq!(actions.len(), 6);
     }
 
+    #[test]
+    fn test_while_runs_until_condition_is_false() {
+        let mut interpreter = Interpreter::new();
+
+        let program = parse_statements("let n = 0\nwhile n < 3 { tempo 100; n = n + 1 }").unwrap();
+        interpreter.run_program(&program).unwrap();
+
+        // The body ran exactly three times
+        assert_eq!(interpreter.take_actions().len(), 3);
+        let n = interpreter.eval_expression(&parse("n").unwrap()).unwrap();
+        assert_eq!(n, Value::Number(3));
+
+        // A false condition never runs the body
+        let program = parse_statements("while false { tempo 100 }").unwrap();
+        interpreter.run_program(&program).unwrap();
+        assert!(interpreter.take_actions().is_empty());
+    }
+
+    #[test]
+    fn test_while_with_break_and_continue() {
+        let mut interpreter = Interpreter::new();
+
+        let program = parse_statements(
+            "let n = 0\nwhile true {\n  n = n + 1\n  if n == 2 { continue }\n  if n > 4 { break }\n  tempo 100\n}",
+        )
+        .unwrap();
+        interpreter.run_program(&program).unwrap();
+
+        // Passes 1, 3 and 4 set the tempo; pass 2 skipped it, pass 5 broke out
+        assert_eq!(interpreter.take_actions().len(), 3);
+
+        let program = parse_statements(
+            "fn first_above(limit) {\n  let n = 1\n  while true {\n    n = n * 2\n    if n > limit { return n }\n  }\n}\nlet big = first_above(20)",
+        )
+        .unwrap();
+        interpreter.run_program(&program).unwrap();
+        let big = interpreter.eval_expression(&parse("big").unwrap()).unwrap();
+        assert_eq!(big, Value::Number(32));
+
+        assert!(parse_statements("while 1 { tempo 100 }")
+            .map(|program| interpreter.run_program(&program).is_err())
+            .unwrap());
+    }
+
+    #[test]
+    fn test_let_in_if_block_does_not_persist() {
+        let mut interpreter = Interpreter::new();
+
+        let program = parse_statements("if true { let inner = C }").unwrap();
+        interpreter.run_program(&program).unwrap();
+
+        let env = interpreter.environment.read().unwrap();
+        assert!(!env.is_defined("inner"));
+        assert_eq!(env.depth(), 1);
+    }
+
+    #[test]
+    fn test_let_in_block_shadows_outer() {
+        let mut interpreter = Interpreter::new();
+
+        // The inner `x` reads the outer one, and the outer is untouched after the block
+        let program =
+            parse_statements("let x = 1\nlet y = 0\nif true { let x = x + 2; y = x }").unwrap();
+        interpreter.run_program(&program).unwrap();
+
+        let x = interpreter.eval_expression(&parse("x").unwrap()).unwrap();
+        let y = interpreter.eval_expression(&parse("y").unwrap()).unwrap();
+        assert_eq!(x, Value::Number(1));
+        assert_eq!(y, Value::Number(3));
+    }
+
+    #[test]
+    fn test_assign_in_block_updates_outer() {
+        let mut interpreter = Interpreter::new();
+
+        let program = parse_statements(
+            "let count = 0\nrepeat 3 { count = count + 1 }\nloop { count = count + 1; break }",
+        )
+        .unwrap();
+        interpreter.run_program(&program).unwrap();
+
+        let count = interpreter
+            .eval_expression(&parse("count").unwrap())
+            .unwrap();
+        assert_eq!(count, Value::Number(4));
+    }
+
+    #[test]
+    fn test_block_scope_popped_on_error() {
+        let mut interpreter = Interpreter::new();
+
+        let program = parse_statements("if true { let inner = C; missing = D }").unwrap();
+        assert!(interpreter.run_program(&program).is_err());
+
+        let env = interpreter.environment.read().unwrap();
+        assert!(!env.is_defined("inner"));
+        assert_eq!(env.depth(), 1);
+    }
+
     #[test]
     fn test_break_outside_loop_error() {
         let mut interpreter = Interpreter::new();
@@ -1438,4 +2148,570 @@ mod tests {
         interpreter.reset_virtual_time();
         assert_eq!(interpreter.virtual_time, 0.0);
     }
+
+    #[test]
+    fn test_wait_spaces_out_song_sections() {
+        let mut interpreter = Interpreter::new();
+        let program = parse_statements(
+            r#"
+            let intro = "C E G C5"
+            let verse = "A C5 E5 A5"
+            play intro
+            wait 16
+            play verse
+            wait 32
+            play verse loop
+        "#,
+        )
+        .unwrap();
+        interpreter.run_program(&program).unwrap();
+
+        let starts: Vec<f64> = interpreter
+            .take_scheduled_events()
+            .iter()
+            .map(|event| event.scheduled_beat)
+            .collect();
+        assert_eq!(starts, vec![0.0, 1.0, 2.0, 3.0, 16.0, 17.0, 18.0, 19.0]);
+
+        // A loop after the waits starts at its place in the script too
+        let loops: Vec<Option<f64>> = interpreter
+            .take_actions()
+            .into_iter()
+            .filter_map(|action| match action {
+                InterpreterAction::PlayExpression { scheduled_beat, .. } => Some(scheduled_beat),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(loops, vec![Some(48.0)]);
+
+        // Once the host has taken the events, the next run starts at beat 0
+        interpreter.reset_virtual_time();
+        interpreter
+            .run_program(&parse_statements("play intro loop").unwrap())
+            .unwrap();
+        assert!(matches!(
+            interpreter.take_actions().as_slice(),
+            [InterpreterAction::PlayExpression {
+                scheduled_beat: None,
+                ..
+            }]
+        ));
+    }
+
+    // =========================================================================
+    // MIDI CC Automation Tests
+    // =========================================================================
+
+    #[test]
+    fn test_cc_statement_emits_action() {
+        let mut interpreter = Interpreter::new();
+
+        let program = parse_statements(r#"on 2 cc 74 "0 32 64 96 127" smooth"#).unwrap();
+        interpreter.run_program(&program).unwrap();
+
+        let actions = interpreter.take_actions();
+        assert_eq!(actions.len(), 1);
+        match &actions[0] {
+            InterpreterAction::ControlChange {
+                controller,
+                smooth,
+                track_id,
+                ..
+            } => {
+                assert_eq!(*controller, 74);
+                assert!(*smooth);
+                assert_eq!(*track_id, 2);
+            }
+            other => panic!("Expected ControlChange action, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_cc_rejects_note_patterns() {
+        let mut interpreter = Interpreter::new();
+
+        let program = parse_statements(r#"cc 1 "C E G""#).unwrap();
+        let err = interpreter.run_program(&program).unwrap_err();
+        assert!(err.to_string().contains("not notes"));
+        assert!(interpreter.take_actions().is_empty());
+    }
+
+    #[test]
+    fn test_midi_program_and_bank_emit_actions() {
+        let mut interpreter = Interpreter::new();
+
+        let program = parse_statements("on 2 midi bank 1 0\non 2 midi program 12").unwrap();
+        interpreter.run_program(&program).unwrap();
+
+        let actions = interpreter.take_actions();
+        assert_eq!(actions.len(), 2);
+        assert!(matches!(
+            actions[0],
+            InterpreterAction::MidiBank {
+                msb: 1,
+                lsb: 0,
+                track_id: 2
+            }
+        ));
+        assert!(matches!(
+            actions[1],
+            InterpreterAction::MidiProgram {
+                program: 12,
+                track_id: 2
+            }
+        ));
+    }
+
+    #[test]
+    fn test_midi_values_out_of_range_fail_to_parse() {
+        for source in ["midi program 128", "midi bank 1 200", "midi bank 300 0"] {
+            let err = parse_statements(source).unwrap_err();
+            assert!(err.to_string().contains("0-127"), "{}: {}", source, err);
+        }
+    }
+
+    #[test]
+    fn test_midi_vel_curve_statement() {
+        let mut interpreter = Interpreter::new();
+
+        let program = parse_statements("on 3 midi vel_curve soft").unwrap();
+        interpreter.run_program(&program).unwrap();
+        match &interpreter.take_actions()[0] {
+            InterpreterAction::MidiVelocityCurve { curve, track_id } => {
+                assert_eq!(curve, "soft");
+                assert_eq!(*track_id, 3);
+            }
+            other => panic!("Expected MidiVelocityCurve action, got {:?}", other),
+        }
+
+        assert!(parse_statements("midi vel_curve loud").is_err());
+    }
+
+    #[test]
+    fn test_scheduled_notes_carry_pattern_velocities() {
+        let mut interpreter = Interpreter::new();
+
+        let program = parse_statements(r#"play "C(127) C [C, E](30)""#).unwrap();
+        interpreter.run_program(&program).unwrap();
+
+        let velocities: Vec<Vec<u8>> = interpreter
+            .take_scheduled_events()
+            .into_iter()
+            .map(|event| match event.action {
+                ScheduledAction::PlayNotes { velocities, .. } => velocities,
+                other => panic!("Expected PlayNotes, got {:?}", other),
+            })
+            .collect();
+        assert_eq!(velocities, vec![vec![127], vec![100], vec![30, 30]]);
+    }
+    #[test]
+    fn test_capo_statement() {
+        let mut interpreter = Interpreter::new();
+
+        let program = parse_statements("capo 2\ncapo -1").unwrap();
+        interpreter.run_program(&program).unwrap();
+
+        let actions = interpreter.take_actions();
+        assert!(matches!(actions[0], InterpreterAction::SetCapo(2)));
+        assert!(matches!(actions[1], InterpreterAction::SetCapo(-1)));
+        assert_eq!(interpreter.capo, -1);
+
+        // `capo` is still usable as a variable name
+        let program = parse_statements("let capo = 3\ncapo = 4").unwrap();
+        interpreter.run_program(&program).unwrap();
+        assert!(interpreter.take_actions().is_empty());
+    }
+
+    #[test]
+    fn test_return_type_is_checked() {
+        let mut interpreter = Interpreter::new();
+        let program = parse_statements(
+            "fn triad() -> Chord { return [C, E, G] }\n\
+             fn count() -> Chord { return 3 }\n\
+             fn notes() -> Chord { return [1, C] }\n\
+             fn any() { return 3 }",
+        )
+        .unwrap();
+        interpreter.run_program(&program).unwrap();
+
+        let run = |interpreter: &mut Interpreter, source: &str| {
+            interpreter.run_program(&parse_statements(source).unwrap())
+        };
+        assert!(run(&mut interpreter, "triad()").is_ok());
+        let err = run(&mut interpreter, "count()").unwrap_err();
+        assert_eq!(err.to_string(), "count() should return Chord, got number");
+        // Arrays are accepted where a chord is declared
+        assert!(run(&mut interpreter, "notes()").is_ok());
+        // No annotation, no check
+        assert!(run(&mut interpreter, "any()").is_ok());
+    }
+
+    #[test]
+    fn test_call_function_through_variable() {
+        let mut interpreter = Interpreter::new();
+        let program = parse_statements(
+            "fn up(c) { return c + 12 }\n\
+             let f = invert\n\
+             let g = up\n\
+             let n = 3",
+        )
+        .unwrap();
+        interpreter.run_program(&program).unwrap();
+
+        let run = |interpreter: &mut Interpreter, source: &str| {
+            interpreter.run_program(&parse_statements(source).unwrap())
+        };
+        let inverted = run(&mut interpreter, "invert([C, E, G])").unwrap();
+        assert_eq!(run(&mut interpreter, "f([C, E, G])").unwrap(), inverted);
+        let raised = run(&mut interpreter, "up([C, E, G])").unwrap();
+        assert_eq!(run(&mut interpreter, "g([C, E, G])").unwrap(), raised);
+
+        // Inside a function body the binding is evaluated straight away
+        run(
+            &mut interpreter,
+            "fn inverted(c) { let h = invert\n return h(c) }",
+        )
+        .unwrap();
+        assert_eq!(
+            run(&mut interpreter, "inverted([C, E, G])").unwrap(),
+            inverted
+        );
+
+        let err = run(&mut interpreter, "n([C, E, G])").unwrap_err();
+        assert_eq!(err.to_string(), "'n' is a number, not a function");
+    }
+
+    #[test]
+    fn test_transform_with_arguments_is_wrapped_in_function() {
+        let mut interpreter = Interpreter::new();
+        let mut run = |source: &str| interpreter.run_program(&parse_statements(source).unwrap());
+
+        run("fn faster(p) { return fast(p, 2) }").unwrap();
+        let Some(Value::Pattern(p)) = run("\"C E G _\".jux(faster)").unwrap() else {
+            panic!("jux() should return a pattern");
+        };
+        // The sped-up copy plays twice against the original once
+        assert_eq!(p.to_rich_events().len(), 8);
+        assert!(run("jux(faster, \"C E G _\")").is_ok());
+        assert!(run("every(2, faster, \"C E G _\")").is_ok());
+        assert!(run("\"C E G _\".sometimes(faster)").is_ok());
+        assert!(run("sometimes_by(30, faster, \"C E G _\")").is_ok());
+        assert!(run("some_steps(\"C E G _\", 50, faster)").is_ok());
+
+        for source in [
+            "\"C E G _\".jux(fast(2))",
+            "every(2, fast(2), \"C E G _\")",
+            "sometimes_by(30, fast(2), \"C E G _\")",
+            "some_steps(\"C E G _\", 50, fast(2))",
+        ] {
+            let err = run(source).unwrap_err().to_string();
+            assert!(err.contains("wrap a transform with arguments"), "{}", err);
+        }
+    }
+
+    #[test]
+    fn test_index_assign() {
+        let mut interpreter = Interpreter::new();
+        let mut run = |source: &str| interpreter.run_program(&parse_statements(source).unwrap());
+
+        run("let p = \"C E G\"\np[1] = D").unwrap();
+        assert_eq!(run("p").unwrap(), run("\"C D G\"").unwrap());
+        run("p[-1] = [F, A, C5]").unwrap();
+        assert_eq!(run("p").unwrap(), run("\"C D [F, A, C5]\"").unwrap());
+
+        run("let c = [C, E, G]\nc[0] = D").unwrap();
+        assert_eq!(run("c").unwrap(), run("[D, E, G]").unwrap());
+
+        // Inside a function body the local copy changes
+        run("fn lift(p) { p[0] = B\n return p }").unwrap();
+        assert_eq!(run("lift(\"C E\")").unwrap(), run("\"B E\"").unwrap());
+
+        let err = run("p[3] = E").unwrap_err();
+        assert!(err.to_string().contains("out of bounds"), "{}", err);
+        assert!(run("q[0] = E").is_err());
+    }
+
+    #[test]
+    fn test_chord_symbols() {
+        let mut interpreter = Interpreter::new();
+        let mut run = |source: &str| interpreter.run_program(&parse_statements(source).unwrap());
+
+        assert_eq!(run("Cmaj7").unwrap(), run("[C, E, G, B]").unwrap());
+        assert_eq!(run("F#m7b5").unwrap(), run("[F#, A, C5, E5]").unwrap());
+        assert_eq!(run("Fsus4").unwrap(), run("[F, Bb, C5]").unwrap());
+        // A chord like any other, so the chord functions apply
+        assert_eq!(run("Am.invert()").unwrap(), run("[C5, E5, A5]").unwrap());
+        assert_eq!(run("bass(Dm7)").unwrap(), run("D").unwrap());
+        // In pattern strings too
+        assert_eq!(
+            run("\"Dm7 Cmaj7\"").unwrap(),
+            run("\"[D, F, A, C5] [C, E, G, B]\"").unwrap()
+        );
+
+        // Plain and octave names stay notes, and variables win
+        assert_eq!(run("C").unwrap(), Some(Value::Note("C".parse().unwrap())));
+        assert_eq!(run("G7").unwrap(), Some(Value::Note("G7".parse().unwrap())));
+        // so dominant chords with a bare number are spelled with `dom`
+        assert_eq!(run("Gdom7").unwrap(), run("[G, B, D5, F5]").unwrap());
+        assert_eq!(run("Bbdom7").unwrap(), run("[Bb, D5, F5, Ab5]").unwrap());
+        assert_eq!(run("Cdom9").unwrap(), run("[C, E, G, Bb, D5]").unwrap());
+        assert_eq!(
+            run("Gdom7.invert()").unwrap(),
+            run("[B, D5, F5, G5]").unwrap()
+        );
+        assert_eq!(
+            run("\"Gdom7 Cmaj6\"").unwrap(),
+            run("\"[G, B, D5, F5] [C, E, G, A]\"").unwrap()
+        );
+        run("let Am = E").unwrap();
+        assert_eq!(run("Am").unwrap(), run("E").unwrap());
+        assert!(run("Cwhatever").is_err());
+    }
+
+    #[test]
+    fn test_std_namespace() {
+        let mut interpreter = Interpreter::new();
+        let mut run = |source: &str| interpreter.run_program(&parse_statements(source).unwrap());
+
+        assert_eq!(run("std.rev(\"C E\")").unwrap(), run("\"E C\"").unwrap());
+        assert_eq!(
+            run("std.fast(\"C E\", 2)").unwrap(),
+            run("fast(\"C E\", 2)").unwrap()
+        );
+        // Chains like any other call
+        assert_eq!(
+            run("std.rev(\"C E\").transpose(2)").unwrap(),
+            run("\"F# D\"").unwrap()
+        );
+        assert!(run("std.glissando(\"C E\")").is_err());
+
+        // A user function shadows the builtin; std. still reaches it
+        run("fn fast(p, n) { return p }").unwrap();
+        assert_eq!(run("fast(\"C E\", 2)").unwrap(), run("\"C E\"").unwrap());
+        assert_eq!(run("\"C E\".fast(2)").unwrap(), run("\"C E\"").unwrap());
+        assert_ne!(
+            run("std.fast(\"C E\", 2)").unwrap(),
+            run("\"C E\"").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_use_strict() {
+        let mut interpreter = Interpreter::new();
+        let mut run = |source: &str| interpreter.run_program(&parse_statements(source).unwrap());
+
+        assert_eq!(
+            parse_statements("use strict").unwrap().statements[0].to_string(),
+            "use strict"
+        );
+        run("use strict").unwrap();
+        let err = run("rev(\"C E\")").unwrap_err().to_string();
+        assert!(err.contains("std.rev()"), "{}", err);
+        assert!(run("\"C E\".rev()").is_err());
+        assert_eq!(run("std.rev(\"C E\")").unwrap(), run("\"E C\"").unwrap());
+        // Method calls go through std. too
+        assert_eq!(run("\"C E\".std.rev()").unwrap(), run("\"E C\"").unwrap());
+        assert_eq!(
+            run("\"C E\".std.fast(2).std.rev()").unwrap(),
+            run("std.rev(std.fast(\"C E\", 2))").unwrap()
+        );
+
+        // Naming a transform isn't a bare call, so strings and names work
+        assert_eq!(
+            run("std.every(2, \"rev\", \"C E\")").unwrap(),
+            run("std.every(2, \"std.rev\", \"C E\")").unwrap()
+        );
+        assert!(run("std.every(2, rev, \"C E\")").is_ok());
+        assert_eq!(
+            run("std.jux(\"C E\", \"rev\")").unwrap(),
+            run("std.jux(\"C E\", \"std.rev\")").unwrap()
+        );
+        assert!(run("\"C E\".std.jux(rev)").is_ok());
+        assert_eq!(
+            run("std.sometimes(\"C E\", \"rev\")").unwrap(),
+            run("std.sometimes(\"C E\", \"std.rev\")").unwrap()
+        );
+        assert!(run("std.sometimes(\"rev\", \"C E\")").is_ok());
+
+        // User functions are called by their bare names
+        run("fn twice(p) { return std.fast(p, 2) }").unwrap();
+        assert_eq!(
+            run("twice(\"C E\")").unwrap(),
+            run("std.fast(\"C E\", 2)").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_pattern_display_reads_back() {
+        let mut interpreter = Interpreter::new();
+        let mut run = |source: &str| interpreter.run_program(&parse_statements(source).unwrap());
+
+        for source in [
+            "\"[C,E,G] C\"",
+            "\"[G,B,D,F] G7 [C,E,G,B]*2 _\"",
+            "\"[C,E,G,Bb,D5] [A,C#5,E5,F#5] [D,F,A,C5]@2\"",
+            "\"[E,G,C5] [C,D,E] <[F,A,C5] [F,Ab,C5]>\"",
+        ] {
+            let value = run(source).unwrap().unwrap();
+            assert_eq!(run(&value.to_string()).unwrap(), Some(value.clone()));
+            assert_eq!(run(&format!("{:#}", value)).unwrap(), Some(value));
+        }
+    }
+
+    #[test]
+    fn test_alias_statement() {
+        let mut interpreter = Interpreter::new();
+
+        let source = r#"alias "drop" = "stop 1; play \"C E\" loop""#;
+        let program = parse_statements(source).unwrap();
+        assert_eq!(program.statements[0].to_string(), source);
+        interpreter.run_program(&program).unwrap();
+
+        match &interpreter.take_actions()[0] {
+            InterpreterAction::DefineAlias { name, source } => {
+                assert_eq!(name, "drop");
+                assert_eq!(source, "stop 1; play \"C E\" loop");
+            }
+            other => panic!("Expected an alias, got {:?}", other),
+        }
+
+        // Aliases are REPL-level, so functions can't define them
+        let program = parse_statements("fn f() { alias \"x\" = \"stop\" }\nf()").unwrap();
+        assert!(interpreter.run_program(&program).is_err());
+
+        // `alias` is still usable as a variable name
+        let program = parse_statements("let alias = 1\nalias = 2").unwrap();
+        interpreter.run_program(&program).unwrap();
+        assert!(interpreter.take_actions().is_empty());
+    }
+
+    /// Run source the way the REPL does, keeping statement text
+    fn run_source(interpreter: &mut Interpreter, source: &str) {
+        let program = parse_spanned_statements(source).unwrap();
+        interpreter.run_spanned_program(&program, source).unwrap();
+    }
+
+    /// Current value of each named binding
+    fn snapshot(interpreter: &Interpreter, names: &[&str]) -> Vec<Value> {
+        names
+            .iter()
+            .map(|name| {
+                interpreter
+                    .eval_expression(&Expression::Variable(name.to_string()))
+                    .unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_definitions_source_restores_state() {
+        let mut session = Interpreter::new();
+        run_source(
+            &mut session,
+            "let bass = \"C2 G1\"\nlet lead = fast(bass, 2)\nfn up(p) {\n  return p + 12\n}\nlet n = 3; n = 5\nlet bass = \"C2 _ G1\"",
+        );
+
+        let (source, warnings) = session.definitions_source();
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        // Redefining `bass` replaces the earlier definition
+        assert_eq!(
+            source,
+            "let lead = fast(bass, 2)\nfn up(p) {\n  return p + 12\n}\nlet n = 3\nn = 5\nlet bass = \"C2 _ G1\"\n"
+        );
+
+        let mut restored = Interpreter::new();
+        run_source(&mut restored, &source);
+        let names = ["bass", "lead", "up", "n"];
+        assert_eq!(snapshot(&restored, &names), snapshot(&session, &names));
+    }
+
+    #[test]
+    fn test_definitions_source_comments_unprintable_values() {
+        let mut interpreter = Interpreter::new();
+        let program = parse_statements("let x = 3\nfn f(a) {\n  return a\n}").unwrap();
+        interpreter.run_program(&program).unwrap();
+        // Block-local bindings aren't part of the session
+        run_source(&mut interpreter, "repeat 1 { let tmp = 1 }");
+
+        let (source, warnings) = interpreter.definitions_source();
+        assert_eq!(source, "let x = 3\n// fn f(a) { ... }\n");
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("'f'"));
+    }
+
+    fn reload(interpreter: &mut Interpreter, source: &str) -> ReloadSummary {
+        let program = parse_spanned_statements(source).unwrap();
+        interpreter
+            .reload_spanned_program("live.cadence", &program, source)
+            .unwrap()
+    }
+
+    /// Source text of each play the interpreter was asked to start
+    fn played(interpreter: &mut Interpreter) -> Vec<String> {
+        interpreter
+            .take_actions()
+            .into_iter()
+            .filter_map(|action| match action {
+                InterpreterAction::PlayExpression { source, .. } => Some(source),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_reload_runs_only_changed_statements() {
+        let mut interpreter = Interpreter::new();
+        let v1 = "let bass = \"C2 G1\"\nlet lead = \"E G\"\nlet pad = [C, E, G]\nplay bass loop\nplay lead loop";
+        let summary = reload(&mut interpreter, v1);
+        assert_eq!(summary.added, 5);
+        assert_eq!(played(&mut interpreter).len(), 2);
+
+        // Edit `bass`, delete `pad` and add a new play; the tracks already
+        // playing `bass` and `lead` aren't restarted
+        let v2 = "let bass = \"C2 _ C2 G1\"\nlet lead = \"E G\"\nplay bass loop\nplay lead loop\ntrack 3 play lead loop";
+        let summary = reload(&mut interpreter, v2);
+        assert_eq!(played(&mut interpreter), vec!["track 3 play lead loop"]);
+        assert_eq!(
+            summary.to_string(),
+            "1 statement changed, 1 added, 1 removed"
+        );
+
+        let env = interpreter.environment.read().unwrap();
+        assert!(!env.is_defined("pad"));
+        assert!(env.is_defined("lead"));
+        drop(env);
+        assert_eq!(
+            interpreter
+                .eval_expression(&parse("bass").unwrap())
+                .unwrap(),
+            interpreter
+                .eval_expression(&parse("\"C2 _ C2 G1\"").unwrap())
+                .unwrap()
+        );
+        let (source, _) = interpreter.definitions_source();
+        assert!(!source.contains("pad"));
+
+        // Saving without changes runs nothing
+        let summary = reload(&mut interpreter, v2);
+        assert!(summary.is_empty());
+        assert!(played(&mut interpreter).is_empty());
+    }
+
+    #[test]
+    fn test_reload_retries_failed_statements() {
+        let mut interpreter = Interpreter::new();
+        let program = parse_spanned_statements("play missing loop").unwrap();
+        assert!(interpreter
+            .reload_spanned_program("live.cadence", &program, "play missing loop")
+            .is_err());
+
+        let summary = reload(&mut interpreter, "let missing = \"C E\"\nplay missing loop");
+        assert_eq!(summary.added, 2);
+        assert_eq!(played(&mut interpreter), vec!["play missing loop"]);
+
+        // Forgetting the file runs it all again
+        interpreter.forget_loaded("live.cadence");
+        reload(&mut interpreter, "let missing = \"C E\"\nplay missing loop");
+        assert_eq!(played(&mut interpreter).len(), 1);
+    }
 }
@@ -69,6 +69,7 @@ pub enum Token {
     Track,    // track
     On,       // on (alias for track)
     For,      // for
+    While,    // while
     In,       // in
     DotDot,   // ..
     Wait,     // wait (for virtual time scheduling)
@@ -144,6 +145,7 @@ impl fmt::Display for Token {
             Token::Track => write!(f, "track"),
             Token::On => write!(f, "on"),
             Token::For => write!(f, "for"),
+            Token::While => write!(f, "while"),
             Token::In => write!(f, "in"),
             Token::DotDot => write!(f, ".."),
             Token::Wait => write!(f, "wait"),
@@ -385,6 +387,22 @@ impl Lexer {
     }
 
     /// Read an identifier or note name (now supports digits, dashes, underscores)
+    /// Read the `.5` of `1.5`. A dot not followed by a digit is left alone,
+    /// so `2.rev()`-style method calls still lex as a dot.
+    fn read_fraction(&mut self) -> Option<String> {
+        if self.current_char != Some('.') || !self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            return None;
+        }
+
+        let mut result = String::from(".");
+        self.advance();
+        while let Some(ch) = self.current_char.filter(|c| c.is_ascii_digit()) {
+            result.push(ch);
+            self.advance();
+        }
+        Some(result)
+    }
+
     fn read_identifier(&mut self) -> String {
         let mut result = String::new();
 
@@ -598,7 +616,12 @@ impl Lexer {
 
                         // Check if it's a negative number
                         if next_ch.is_ascii_digit() {
-                            return Ok(Token::Number(self.read_number()?));
+                            let number = self.read_number()?;
+                            if let Some(fraction) = self.read_fraction() {
+                                let magnitude = format!("{}{}", number.unsigned_abs(), fraction);
+                                return Ok(Token::Float(-magnitude.parse::<f32>()?));
+                            }
+                            return Ok(Token::Number(number));
                         }
                     }
 
@@ -717,10 +740,13 @@ impl Lexer {
 
                 // Handle any alphanumeric character (including digits)
                 Some(ch) if ch.is_alphanumeric() || ch == '_' => {
-                    let identifier = self.read_identifier();
+                    let mut identifier = self.read_identifier();
 
                     // Check if it's a pure number (possibly float)
                     if identifier.chars().all(|c| c.is_ascii_digit() || c == '.') {
+                        if let Some(fraction) = self.read_fraction() {
+                            identifier.push_str(&fraction);
+                        }
                         if identifier.contains('.') {
                             if let Ok(num) = identifier.parse::<f32>() {
                                 return Ok(Token::Float(num));
@@ -757,6 +783,7 @@ impl Lexer {
                         "track" => Token::Track,
                         "on" => Token::On,
                         "for" => Token::For,
+                        "while" => Token::While,
                         "in" => Token::In,
                         "wait" => Token::Wait,
                         "true" => Token::Boolean(true),
@@ -956,6 +983,27 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_floats() {
+        let mut lexer = Lexer::new("1.5 -0.25 0..4 x.fast");
+        let tokens = lexer.tokenize().unwrap();
+
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Float(1.5),
+                Token::Float(-0.25),
+                Token::Number(0),
+                Token::DotDot,
+                Token::Number(4),
+                Token::Identifier("x".to_string()),
+                Token::Dot,
+                Token::Identifier("fast".to_string()),
+                Token::Eof,
+            ]
+        );
+    }
+
     #[test]
     fn test_chord_literal() {
         let mut lexer = Lexer::new("[C, E, G]");
@@ -9,6 +9,7 @@ pub mod evaluator;
 pub mod interpreter;
 pub mod lexer;
 pub mod module_resolver;
+pub mod reload;
 pub mod statement_parser;
 pub mod symbols;
 pub mod validator;
@@ -16,10 +17,12 @@ pub mod validator;
 #[cfg(test)]
 mod evaluator_tests;
 
-pub use ast::{Expression, Program, Statement, Value};
-pub use environment::{Environment, SharedEnvironment};
+pub use ast::{Expression, MidiBinding, Program, Statement, Value};
+pub use environment::{Environment, SharedEnvironment, PLAYBACK_VARIABLES};
 pub use error::CadenceError;
 pub use evaluator::{eval, EnvironmentRef, Evaluator};
 pub use interpreter::{ControlFlow, Interpreter, InterpreterAction};
 pub use lexer::{Lexer, Token};
-pub use statement_parser::{parse_expression as parse, parse_statements, StatementParser};
+pub use statement_parser::{
+    parse_expression as parse, parse_spanned_statements, parse_statements, StatementParser,
+};
@@ -74,13 +74,16 @@ impl FileProvider for NativeFileProvider {
     }
 }
 
+/// An exported function: (params, body, return type annotation)
+pub type ExportedFunction = (Vec<String>, Vec<Statement>, Option<String>);
+
 /// Exported definitions from a module
 #[derive(Debug, Clone, Default)]
 pub struct ModuleExports {
     /// Exported variable values (from `let` statements)
     pub values: HashMap<String, Value>,
-    /// Exported function definitions (name -> (params, body))
-    pub functions: HashMap<String, (Vec<String>, Vec<Statement>)>,
+    /// Exported function definitions by name
+    pub functions: HashMap<String, ExportedFunction>,
 }
 
 impl ModuleExports {
@@ -93,11 +96,12 @@ impl ModuleExports {
         if let Some(val) = self.values.get(name) {
             return Some(val.clone());
         }
-        if let Some((params, body)) = self.functions.get(name) {
+        if let Some((params, body, return_type)) = self.functions.get(name) {
             return Some(Value::Function {
                 name: name.to_string(),
                 params: params.clone(),
                 body: body.clone(),
+                return_type: return_type.clone(),
             });
         }
         None
@@ -224,7 +228,12 @@ impl ModuleResolver {
             match stmt {
                 Statement::Let { name, value } => {
                     // Evaluate the expression to get the value
-                    match evaluator.eval_with_env(value.clone(), Some(crate::parser::evaluator::EnvironmentRef::Borrowed(&temp_env))) {
+                    match evaluator.eval_with_env(
+                        value.clone(),
+                        Some(crate::parser::evaluator::EnvironmentRef::Borrowed(
+                            &temp_env,
+                        )),
+                    ) {
                         Ok(val) => {
                             temp_env.define(name.clone(), val.clone());
                             exports.values.insert(name.clone(), val);
@@ -237,12 +246,17 @@ impl ModuleResolver {
                     }
                 }
                 Statement::FunctionDef {
-                    name, params, body, ..
+                    name,
+                    params,
+                    body,
+                    return_type,
+                    ..
                 } => {
                     // Store function definition
-                    exports
-                        .functions
-                        .insert(name.clone(), (params.clone(), body.clone()));
+                    exports.functions.insert(
+                        name.clone(),
+                        (params.clone(), body.clone(), return_type.clone()),
+                    );
                     // Also add to temp env for use by other definitions
                     temp_env.define(
                         name.clone(),
@@ -250,6 +264,7 @@ impl ModuleResolver {
                             name: name.clone(),
                             params: params.clone(),
                             body: body.clone(),
+                            return_type: return_type.clone(),
                         },
                     );
                 }
@@ -267,10 +282,8 @@ impl ModuleResolver {
                             for (name, val) in &nested_exports.values {
                                 exports.values.insert(name.clone(), val.clone());
                             }
-                            for (name, (params, body)) in &nested_exports.functions {
-                                exports
-                                    .functions
-                                    .insert(name.clone(), (params.clone(), body.clone()));
+                            for (name, function) in &nested_exports.functions {
+                                exports.functions.insert(name.clone(), function.clone());
                             }
                         }
                         // use { a, b } from "path" - import specific items
@@ -278,8 +291,15 @@ impl ModuleResolver {
                             for name in names {
                                 if let Some(val) = nested_exports.get(name) {
                                     match val {
-                                        Value::Function { params, body, .. } => {
-                                            exports.functions.insert(name.clone(), (params, body));
+                                        Value::Function {
+                                            params,
+                                            body,
+                                            return_type,
+                                            ..
+                                        } => {
+                                            exports
+                                                .functions
+                                                .insert(name.clone(), (params, body, return_type));
                                         }
                                         other => {
                                             exports.values.insert(name.clone(), other);
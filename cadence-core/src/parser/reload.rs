@@ -0,0 +1,229 @@
+//! Incremental reloading of a changed file
+//!
+//! When a watched file is saved, only the statements whose text changed are
+//! run again. Each top-level statement is identified by a hash of its source
+//! text, so unchanged `let` and `fn` bindings, one-shot plays and running
+//! tracks are left alone. Statements that were deleted have their bindings
+//! removed.
+
+use crate::parser::ast::Statement;
+use std::collections::hash_map::DefaultHasher;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+/// A top-level statement from the last version of a file that was run
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoadedStatement {
+    /// Hash of the statement's source text
+    pub hash: u64,
+    /// Global names the statement bound
+    pub names: Vec<String>,
+}
+
+/// Hash of a statement's source text, ignoring surrounding whitespace
+pub fn statement_hash(text: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    text.trim().hash(&mut hasher);
+    hasher.finish()
+}
+
+/// The name a statement binds, known before running it
+pub fn declared_name(statement: &Statement) -> Option<&str> {
+    match statement {
+        Statement::Let { name, .. } | Statement::FunctionDef { name, .. } => Some(name),
+        _ => None,
+    }
+}
+
+/// A new statement of the file, as seen by [`plan_reload`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewStatement<'a> {
+    pub hash: u64,
+    pub name: Option<&'a str>,
+}
+
+/// What a reload has to do to bring the interpreter up to date
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReloadPlan {
+    /// New statements to run, by index, in file order
+    pub run: Vec<usize>,
+    /// Unchanged statements: (index in the new file, index in the old one)
+    pub kept: Vec<(usize, usize)>,
+    /// Old statements that are gone, by index
+    pub removed: Vec<usize>,
+    pub summary: ReloadSummary,
+}
+
+/// Counts of what changed between two versions of a file
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReloadSummary {
+    /// Statements edited in place (same binding, or replacing a removed statement)
+    pub changed: usize,
+    pub added: usize,
+    pub removed: usize,
+}
+
+impl ReloadSummary {
+    pub fn is_empty(&self) -> bool {
+        self.changed == 0 && self.added == 0 && self.removed == 0
+    }
+}
+
+impl fmt::Display for ReloadSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return write!(f, "no changes");
+        }
+        // "2 statements changed, 1 removed": only the first count names the noun
+        let counts = [
+            (self.changed, "changed"),
+            (self.added, "added"),
+            (self.removed, "removed"),
+        ];
+        let mut first = true;
+        for (count, what) in counts.into_iter().filter(|&(count, _)| count > 0) {
+            if first {
+                let noun = if count == 1 {
+                    "statement"
+                } else {
+                    "statements"
+                };
+                write!(f, "{} {} {}", count, noun, what)?;
+                first = false;
+            } else {
+                write!(f, ", {} {}", count, what)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Work out which statements of `new` need to run, given the `old` version.
+///
+/// A new statement whose text matches an old one is unchanged. The rest run.
+/// For the summary, a statement that runs counts as changed when it binds the
+/// same name as a statement that is gone, or when both bind nothing; it
+/// counts as added otherwise.
+pub fn plan_reload(old: &[LoadedStatement], new: &[NewStatement]) -> ReloadPlan {
+    let mut plan = ReloadPlan::default();
+    let mut unmatched_old: Vec<bool> = vec![true; old.len()];
+
+    for (index, statement) in new.iter().enumerate() {
+        let matched = old
+            .iter()
+            .enumerate()
+            .position(|(i, loaded)| unmatched_old[i] && loaded.hash == statement.hash);
+        match matched {
+            Some(i) => {
+                unmatched_old[i] = false;
+                plan.kept.push((index, i));
+            }
+            None => plan.run.push(index),
+        }
+    }
+
+    // Pair statements that run with ones that are gone
+    for &index in &plan.run {
+        let paired = old.iter().enumerate().position(|(i, loaded)| {
+            unmatched_old[i]
+                && match new[index].name {
+                    Some(name) => loaded.names.iter().any(|n| n == name),
+                    None => loaded.names.is_empty(),
+                }
+        });
+        match paired {
+            Some(i) => {
+                unmatched_old[i] = false;
+                plan.summary.changed += 1;
+            }
+            None => plan.summary.added += 1,
+        }
+    }
+
+    // Anything left over, including statements paired above only for the
+    // summary, is gone from the file
+    plan.removed = old
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| !plan.kept.iter().any(|&(_, kept)| kept == *i))
+        .map(|(i, _)| i)
+        .collect();
+    plan.summary.removed = plan.removed.len() - plan.summary.changed;
+    plan
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn loaded(text: &str, names: &[&str]) -> LoadedStatement {
+        LoadedStatement {
+            hash: statement_hash(text),
+            names: names.iter().map(|n| n.to_string()).collect(),
+        }
+    }
+
+    fn new<'a>(text: &str, name: Option<&'a str>) -> NewStatement<'a> {
+        NewStatement {
+            hash: statement_hash(text),
+            name,
+        }
+    }
+
+    #[test]
+    fn test_plan_reload() {
+        let old = [
+            loaded("let bass = \"C2 G1\"", &["bass"]),
+            loaded("let lead = \"E G\"", &["lead"]),
+            loaded("play bass loop", &[]),
+            loaded("let pad = [C, E, G]", &["pad"]),
+        ];
+        let new_file = [
+            new("let bass = \"C2 _ C2 G1\"", Some("bass")),
+            new("let lead = \"E G\"", Some("lead")),
+            new("  play bass loop", None),
+            new("play lead loop", None),
+        ];
+
+        let plan = plan_reload(&old, &new_file);
+        assert_eq!(plan.run, vec![0, 3]);
+        assert_eq!(plan.kept, vec![(1, 1), (2, 2)]);
+        assert_eq!(plan.removed, vec![0, 3]);
+        assert_eq!(
+            plan.summary,
+            ReloadSummary {
+                changed: 1,
+                added: 1,
+                removed: 1
+            }
+        );
+        assert_eq!(
+            plan.summary.to_string(),
+            "1 statement changed, 1 added, 1 removed"
+        );
+    }
+
+    #[test]
+    fn test_plan_reload_duplicates_and_first_load() {
+        // Two identical statements only match two old ones
+        let old = [loaded("play C", &[])];
+        let plan = plan_reload(&old, &[new("play C", None), new("play C", None)]);
+        assert_eq!(plan.run, vec![1]);
+        assert_eq!(plan.summary.added, 1);
+
+        let plan = plan_reload(&[], &[new("let x = C", Some("x"))]);
+        assert_eq!(plan.run, vec![0]);
+        assert_eq!(plan.summary.to_string(), "1 statement added");
+
+        let plan = plan_reload(&old, &[new("play C", None)]);
+        assert!(plan.run.is_empty() && plan.summary.is_empty());
+        assert_eq!(plan.summary.to_string(), "no changes");
+
+        let two_changed = ReloadSummary {
+            changed: 2,
+            added: 0,
+            removed: 1,
+        };
+        assert_eq!(two_changed.to_string(), "2 statements changed, 1 removed");
+    }
+}
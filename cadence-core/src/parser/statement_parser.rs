@@ -9,8 +9,9 @@
 //! - `repeat 4 { ... }`
 
 use crate::parser::ast::{
-    ComparisonOp, Expression, Program, SpannedProgram, SpannedStatement, Statement,
+    ComparisonOp, Expression, MidiBinding, Program, SpannedProgram, SpannedStatement, Statement,
 };
+use crate::parser::builtins::STD_NAMESPACE;
 use crate::parser::error::CadenceError;
 use crate::parser::lexer::{Lexer, Span, SpannedToken, Token};
 // use anyhow::Result; // Removed anyhow dependency
@@ -156,6 +157,7 @@ impl StatementParser {
             | Token::Arrow
             | Token::In => 2, // <=, >=, &&, ||, .., ->, in
             Token::For => 3,
+            Token::While => 5,
             Token::Wait => 4,
             Token::Use => 3,
             Token::From => 4,
@@ -189,15 +191,13 @@ impl StatementParser {
 
         while !self.check(&Token::Eof) {
             // Skip semicolons, newlines, and comments between statements
-            while self.is_skippable() {
-                self.advance();
-            }
+            let doc_comment = self.skip_to_statement();
 
             if self.check(&Token::Eof) {
                 break;
             }
 
-            let stmt = self.parse_statement()?;
+            let stmt = self.parse_documented_statement(doc_comment)?;
             program.push(stmt);
         }
 
@@ -209,59 +209,113 @@ impl StatementParser {
         let mut program = SpannedProgram::new();
 
         while !self.check(&Token::Eof) {
-            // Collect doc comments (/// lines) before the statement
-            let mut doc_lines: Vec<String> = Vec::new();
-
             // Skip semicolons, newlines, and regular comments; collect doc comments
-            loop {
-                match self.current() {
-                    Token::Semicolon | Token::Newline => {
-                        self.advance();
-                    }
-                    Token::Comment(text) => {
-                        // Doc comments start with / (making ///)
-                        if let Some(stripped) = text.strip_prefix('/') {
-                            // Strip the leading / and optional space
-                            let doc_text = stripped.trim_start();
-                            doc_lines.push(doc_text.to_string());
-                        }
-                        // Skip all comments (doc or regular)
-                        self.advance();
-                    }
-                    _ => break,
-                }
+            let doc_comment = self.skip_to_statement();
+
+            if self.check(&Token::Eof) {
+                break;
             }
 
+            program.push(self.parse_spanned_statement(doc_comment)?);
+        }
+
+        Ok(program)
+    }
+
+    /// Like `parse_spanned_program`, but on an error skip to the next
+    /// statement and keep going, returning every error along with the
+    /// statements that did parse
+    pub fn parse_spanned_program_recovering(&mut self) -> (SpannedProgram, Vec<CadenceError>) {
+        let mut program = SpannedProgram::new();
+        let mut errors = Vec::new();
+
+        while !self.check(&Token::Eof) {
+            let doc_comment = self.skip_to_statement();
+
             if self.check(&Token::Eof) {
                 break;
             }
 
-            // Record start position (both char and UTF-16)
-            let start_span = self.current_span();
-            let start = start_span.offset;
-            let utf16_start = start_span.utf16_offset;
+            let start = self.position;
+            match self.parse_spanned_statement(doc_comment) {
+                Ok(stmt) => program.push(stmt),
+                Err(e) => {
+                    errors.push(e);
+                    self.skip_past_statement(start);
+                }
+            }
+        }
 
-            let stmt = self.parse_statement()?;
+        (program, errors)
+    }
 
-            // Record end position as the end of the last consumed token
-            // This ensures the span covers all characters of the statement
-            let end = self.previous_token_end();
-            let utf16_end = self.previous_token_utf16_end();
+    /// Parse the statement at the current token, recording where it starts and ends
+    fn parse_spanned_statement(
+        &mut self,
+        doc_comment: Option<String>,
+    ) -> Result<SpannedStatement, CadenceError> {
+        // Record start position (both char and UTF-16)
+        let start_span = self.current_span();
+        let start = start_span.offset;
+        let utf16_start = start_span.utf16_offset;
+
+        let stmt = self.parse_documented_statement(doc_comment.clone())?;
+
+        // Record end position as the end of the last consumed token
+        // This ensures the span covers all characters of the statement
+        let end = self.previous_token_end();
+        let utf16_end = self.previous_token_utf16_end();
+
+        Ok(
+            SpannedStatement::with_utf16(stmt, start, end, utf16_start, utf16_end)
+                .with_doc_comment(doc_comment),
+        )
+    }
 
-            // Build doc comment from collected lines (join with newlines)
-            let doc_comment = if doc_lines.is_empty() {
-                None
-            } else {
-                Some(doc_lines.join("\n"))
-            };
+    /// Move from the token at `start` to the end of its statement: the next
+    /// newline or `;` outside any block, or the end of input
+    fn skip_past_statement(&mut self, start: usize) {
+        self.position = start;
+        let mut depth = 0usize;
+        loop {
+            match self.current() {
+                Token::Eof => break,
+                Token::Newline | Token::Semicolon if depth == 0 && self.position > start => break,
+                Token::LeftBrace => depth += 1,
+                Token::RightBrace => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+            self.advance();
+        }
+    }
 
-            program.push(
-                SpannedStatement::with_utf16(stmt, start, end, utf16_start, utf16_end)
-                    .with_doc_comment(doc_comment),
-            );
+    /// Skip semicolons, newlines and comments before a top-level statement,
+    /// returning the `///` doc comment lines among them joined with newlines
+    fn skip_to_statement(&mut self) -> Option<String> {
+        let mut doc_lines: Vec<String> = Vec::new();
+        while self.is_skippable() {
+            if let Token::Comment(text) = self.current() {
+                // Doc comments start with / (making ///)
+                if let Some(stripped) = text.strip_prefix('/') {
+                    // Strip the leading / and optional space
+                    doc_lines.push(stripped.trim_start().to_string());
+                }
+            }
+            self.advance();
         }
+        (!doc_lines.is_empty()).then(|| doc_lines.join("\n"))
+    }
 
-        Ok(program)
+    /// Parse a top-level statement, giving a function definition its doc comment
+    fn parse_documented_statement(
+        &mut self,
+        doc_comment: Option<String>,
+    ) -> Result<Statement, CadenceError> {
+        let mut stmt = self.parse_statement()?;
+        if let Statement::FunctionDef { doc, .. } = &mut stmt {
+            *doc = doc_comment;
+        }
+        Ok(stmt)
     }
 
     /// Parse a single statement
@@ -284,6 +338,7 @@ impl StatementParser {
             Token::Loop => self.parse_loop_statement(),
             Token::Repeat => self.parse_repeat_statement(),
             Token::For => self.parse_for_statement(),
+            Token::While => self.parse_while_statement(),
             Token::Wait => self.parse_wait_statement(),
             Token::If => self.parse_if_statement(),
             Token::Break => {
@@ -296,6 +351,33 @@ impl StatementParser {
             }
             Token::Return => self.parse_return_statement(),
             Token::LeftBrace => self.parse_block_statement(),
+            Token::Identifier(name) if name == "cc" && matches!(self.peek(), Token::Number(_)) => {
+                self.parse_cc_statement()
+            }
+            Token::Identifier(name)
+                if name == "capo"
+                    && matches!(self.peek(), Token::Number(_) | Token::Identifier(_)) =>
+            {
+                self.advance(); // consume 'capo'
+                Ok(Statement::Capo(self.parse_expression()?))
+            }
+            Token::Identifier(name)
+                if name == "midi"
+                    && matches!(self.peek(), Token::Identifier(kind) if kind == "program" || kind == "bank" || kind == "vel_curve") =>
+            {
+                self.parse_midi_statement()
+            }
+            Token::Identifier(name)
+                if name == "alias" && matches!(self.peek(), Token::StringLiteral(_)) =>
+            {
+                self.parse_alias_statement()
+            }
+            Token::Identifier(name)
+                if name == "bind"
+                    && matches!(self.peek(), Token::Identifier(kind) if kind == "note" || kind == "cc") =>
+            {
+                self.parse_bind_statement()
+            }
             Token::Identifier(name) => {
                 // Check if this is an assignment (identifier = expr)
                 // Use peek to see if next token is Equals
@@ -305,8 +387,18 @@ impl StatementParser {
                     let value = self.parse_expression()?;
                     Ok(Statement::Assign { name, value })
                 } else {
-                    // Expression statement
+                    // Expression statement, or an indexed assignment (name[index] = expr)
                     let expr = self.parse_expression()?;
+                    if let (Expression::Index { target, index }, Token::Equals) =
+                        (&expr, self.current())
+                    {
+                        if let Expression::Variable(name) = target.as_ref() {
+                            let (name, index) = (name.clone(), index.as_ref().clone());
+                            self.advance(); // consume =
+                            let value = self.parse_expression()?;
+                            return Ok(Statement::IndexAssign { name, index, value });
+                        }
+                    }
                     Ok(Statement::Expression(expr))
                 }
             }
@@ -448,6 +540,192 @@ impl StatementParser {
         Ok(Statement::Waveform(name))
     }
 
+    /// Parse: cc <controller> <expression> [smooth]
+    fn parse_cc_statement(&mut self) -> Result<Statement, CadenceError> {
+        self.advance(); // consume 'cc'
+
+        let controller = match self.current() {
+            Token::Number(n) if (0..=127).contains(n) => *n as u8,
+            _ => {
+                return Err(CadenceError::new(
+                    "CC controller number must be 0-127".to_string(),
+                    self.current_span(),
+                ))
+            }
+        };
+        self.advance();
+
+        let target = self.parse_expression()?;
+
+        let smooth = matches!(self.current(), Token::Identifier(s) if s == "smooth");
+        if smooth {
+            self.advance();
+        }
+
+        Ok(Statement::ControlChange {
+            controller,
+            target,
+            smooth,
+        })
+    }
+
+    /// Parse: midi program <n>, midi bank <msb> <lsb> or midi vel_curve <linear|soft|hard>
+    fn parse_midi_statement(&mut self) -> Result<Statement, CadenceError> {
+        self.advance(); // consume 'midi'
+
+        let kind = match self.current() {
+            Token::Identifier(kind) => kind.clone(),
+            _ => unreachable!("parse_midi_statement called without program/bank/vel_curve"),
+        };
+        self.advance();
+
+        if kind == "vel_curve" {
+            let curve = match self.current() {
+                Token::Identifier(curve)
+                    if ["linear", "soft", "hard"].contains(&curve.as_str()) =>
+                {
+                    curve.clone()
+                }
+                _ => {
+                    return Err(CadenceError::new(
+                        "Velocity curve must be linear, soft or hard".to_string(),
+                        self.current_span(),
+                    ))
+                }
+            };
+            self.advance();
+            return Ok(Statement::MidiVelocityCurve(curve));
+        }
+
+        if kind == "program" {
+            let program = self.parse_midi_data_byte("program number")?;
+            return Ok(Statement::MidiProgram(program));
+        }
+
+        let msb = self.parse_midi_data_byte("bank MSB")?;
+        let lsb = self.parse_midi_data_byte("bank LSB")?;
+        Ok(Statement::MidiBank { msb, lsb })
+    }
+
+    /// Parse a literal MIDI data byte (0-127)
+    fn parse_midi_data_byte(&mut self, what: &str) -> Result<u8, CadenceError> {
+        let value = match self.current() {
+            Token::Number(n) if (0..=127).contains(n) => *n as u8,
+            _ => {
+                return Err(CadenceError::new(
+                    format!("MIDI {} must be 0-127", what),
+                    self.current_span(),
+                ))
+            }
+        };
+        self.advance();
+        Ok(value)
+    }
+
+    /// Parse: bind note <n> { statements } or bind cc <n> to <tempo|volume> range <min> <max>
+    fn parse_bind_statement(&mut self) -> Result<Statement, CadenceError> {
+        self.advance(); // consume 'bind'
+
+        let kind = match self.current() {
+            Token::Identifier(kind) => kind.clone(),
+            _ => unreachable!("parse_bind_statement called without note/cc"),
+        };
+        self.advance();
+
+        let number = match self.current() {
+            Token::Number(n) if (0..=127).contains(n) => *n as u8,
+            _ => {
+                return Err(CadenceError::new(
+                    format!(
+                        "Expected MIDI {} number (0-127) after 'bind {}'",
+                        kind, kind
+                    ),
+                    self.current_span(),
+                ))
+            }
+        };
+        self.advance();
+
+        if kind == "note" {
+            let body = self.parse_block()?;
+            return Ok(Statement::Bind(MidiBinding::Note { note: number, body }));
+        }
+
+        if !matches!(self.current(), Token::Identifier(s) if s == "to") {
+            return Err(CadenceError::new(
+                "Expected 'to' after 'bind cc <n>'".to_string(),
+                self.current_span(),
+            ));
+        }
+        self.advance();
+
+        let parameter = match self.current() {
+            Token::Tempo => "tempo".to_string(),
+            Token::Volume => "volume".to_string(),
+            _ => {
+                return Err(CadenceError::new(
+                    "CC bindings can target 'tempo' or 'volume'".to_string(),
+                    self.current_span(),
+                ))
+            }
+        };
+        self.advance();
+
+        if !matches!(self.current(), Token::Identifier(s) if s == "range") {
+            return Err(CadenceError::new(
+                format!("Expected 'range <min> <max>' after '{}'", parameter),
+                self.current_span(),
+            ));
+        }
+        self.advance();
+
+        let mut bounds = [0i32; 2];
+        for bound in bounds.iter_mut() {
+            *bound = match self.current() {
+                Token::Number(n) => *n,
+                _ => {
+                    return Err(CadenceError::new(
+                        "Expected number in range".to_string(),
+                        self.current_span(),
+                    ))
+                }
+            };
+            self.advance();
+        }
+
+        Ok(Statement::Bind(MidiBinding::Control {
+            controller: number,
+            parameter,
+            min: bounds[0],
+            max: bounds[1],
+        }))
+    }
+
+    /// Parse: alias "name" = "source"
+    fn parse_alias_statement(&mut self) -> Result<Statement, CadenceError> {
+        self.advance(); // consume 'alias'
+
+        let name = match self.current().clone() {
+            Token::StringLiteral(s) => s,
+            _ => unreachable!("parse_alias_statement called without a name"),
+        };
+        self.advance();
+        self.expect(&Token::Equals)?;
+
+        let source = match self.current().clone() {
+            Token::StringLiteral(s) => s,
+            _ => {
+                return Err(CadenceError::new(
+                    "Expected the aliased commands as a string, e.g. alias \"drop\" = \"stop 1; stop 2\"".to_string(),
+                    self.current_span(),
+                ))
+            }
+        };
+        self.advance();
+
+        Ok(Statement::Alias { name, source })
+    }
+
     /// Parse: load "path/to/file.cadence"
     fn parse_load_statement(&mut self) -> Result<Statement, CadenceError> {
         self.expect(&Token::Load)?;
@@ -471,9 +749,15 @@ impl StatementParser {
     /// - use "path/to/file.cadence" as alias
     /// - use { name1, name2 } from "path/to/file.cadence"
     /// - use { name1, name2 } from "path/to/file.cadence" as alias
+    /// - use strict
     fn parse_use_statement(&mut self) -> Result<Statement, CadenceError> {
         self.expect(&Token::Use)?;
 
+        if matches!(self.current(), Token::Identifier(name) if name == "strict") {
+            self.advance();
+            return Ok(Statement::UseStrict);
+        }
+
         // Check if it starts with { (selective imports)
         if self.check(&Token::LeftBrace) {
             // use { name1, name2 } from "path"
@@ -676,6 +960,8 @@ impl StatementParser {
             params,
             body,
             return_type,
+            // Filled in by `parse_documented_statement` at the top level
+            doc: None,
         })
     }
 
@@ -780,6 +1066,14 @@ impl StatementParser {
         })
     }
 
+    /// Parse: while <condition> { statements }
+    fn parse_while_statement(&mut self) -> Result<Statement, CadenceError> {
+        self.expect(&Token::While)?;
+        let condition = self.parse_expression()?;
+        let body = self.parse_block()?;
+        Ok(Statement::While { condition, body })
+    }
+
     /// Parse: wait <expression>
     /// Advances virtual time by the specified number of beats
     fn parse_wait_statement(&mut self) -> Result<Statement, CadenceError> {
@@ -1065,8 +1359,9 @@ impl StatementParser {
     }
 
     /// Parse postfix operations (method calls and indexing)
-    /// Grammar: postfix_expr = primary_expr ('.' identifier '(' args ')') | ('[' expr ']'))*
-    /// Desugars method calls to function calls: expr.method(a, b) → method(expr, a, b)
+    /// Grammar: postfix_expr = primary_expr ('.' ['std' '.'] identifier '(' args ')') | ('[' expr ']'))*
+    /// Desugars method calls to function calls: expr.method(a, b) → method(expr, a, b),
+    /// and expr.std.method(a) → std.method(expr, a)
     fn parse_postfix_expression(&mut self) -> Result<Expression, CadenceError> {
         let mut expr = self.parse_primary_expression()?;
 
@@ -1075,7 +1370,7 @@ impl StatementParser {
             if matches!(self.current(), Token::Dot) {
                 self.advance(); // consume '.'
 
-                let method_name = match self.current().clone() {
+                let mut method_name = match self.current().clone() {
                     Token::Identifier(name) => name,
                     _ => {
                         let span = self.current_span();
@@ -1087,9 +1382,36 @@ impl StatementParser {
                 };
                 self.advance();
 
+                // p.std.fast(2) is the method form of std.fast(p, 2)
+                if method_name == STD_NAMESPACE && matches!(self.current(), Token::Dot) {
+                    self.advance();
+                    method_name = match self.current().clone() {
+                        Token::Identifier(name) => format!("{}.{}", STD_NAMESPACE, name),
+                        _ => {
+                            let span = self.current_span();
+                            return Err(CadenceError::new(
+                                format!(
+                                    "Expected method name after '.std.', found {:?}",
+                                    self.current()
+                                ),
+                                span,
+                            ));
+                        }
+                    };
+                    self.advance();
+                }
+
+                // std.fast(a, b) calls the builtin, even where a user function
+                // named fast shadows it
+                let namespaced = matches!(&expr, Expression::Variable(ns) if ns == STD_NAMESPACE);
+
                 // Parse method arguments (must have parentheses)
                 self.expect(&Token::LeftParen)?;
-                let mut args = vec![expr]; // receiver is first argument
+                let mut args = if namespaced {
+                    Vec::new()
+                } else {
+                    vec![expr] // receiver is first argument
+                };
 
                 if !matches!(self.current(), Token::RightParen) {
                     args.push(self.parse_expression()?);
@@ -1101,11 +1423,24 @@ impl StatementParser {
                 self.expect(&Token::RightParen)?;
 
                 // Desugar to function call: receiver.method(a, b) → method(receiver, a, b)
-                expr = Expression::function_call(method_name, args);
+                expr = if namespaced {
+                    Expression::function_call(format!("{}.{}", STD_NAMESPACE, method_name), args)
+                } else {
+                    Expression::function_call(method_name, args)
+                };
             } else if matches!(self.current(), Token::LeftBracket) {
                 self.advance(); // consume '['
 
-                let index = self.parse_expression()?;
+                let mut index = self.parse_expression()?;
+
+                // Slice: pattern[1..3]
+                if matches!(self.current(), Token::DotDot) {
+                    self.advance();
+                    index = Expression::Range {
+                        start: Box::new(index),
+                        end: Box::new(self.parse_expression()?),
+                    };
+                }
 
                 self.expect(&Token::RightBracket)?;
 
@@ -1177,6 +1512,11 @@ impl StatementParser {
                 }
             }
 
+            Token::Float(num) => {
+                self.advance();
+                Ok(Expression::Float(num))
+            }
+
             Token::Identifier(name) => {
                 self.advance();
                 // Check if this is a function call (has parentheses) or variable
@@ -1352,6 +1692,14 @@ pub fn parse_spanned_statements(input: &str) -> std::result::Result<SpannedProgr
     parser.parse_spanned_program()
 }
 
+/// Parse a program, collecting every syntax error instead of stopping at the first
+pub fn parse_spanned_statements_recovering(input: &str) -> (SpannedProgram, Vec<CadenceError>) {
+    match StatementParser::new(input) {
+        Ok(mut parser) => parser.parse_spanned_program_recovering(),
+        Err(e) => (SpannedProgram::new(), vec![e]),
+    }
+}
+
 /// Convenience function to parse a string into a single expression
 pub fn parse_expression(input: &str) -> std::result::Result<Expression, CadenceError> {
     let mut parser = StatementParser::new(input)?;
@@ -1540,6 +1888,21 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_while() {
+        let program = parse_statements("while n < 3 {\n  n = n + 1\n}").unwrap();
+        assert_eq!(program.statements.len(), 1);
+
+        match &program.statements[0] {
+            Statement::While { condition, body } => {
+                assert!(matches!(condition, Expression::Comparison { .. }));
+                assert_eq!(body.len(), 1);
+            }
+            _ => panic!("Expected While statement"),
+        }
+        assert!(parse_statements("while n < 3").is_err());
+    }
+
     #[test]
     fn test_parse_else_if() {
         let program = parse_statements("if true { tempo 120 } else if false { tempo 60 }").unwrap();
@@ -2087,6 +2450,31 @@ mod expression_tests {
         }
     }
 
+    #[test]
+    fn test_recovering_parse_collects_every_error() {
+        let source = "let = 1\ntempo 120\nfn f( { play C }\nplay \"C E\" loop";
+        let (program, errors) = parse_spanned_statements_recovering(source);
+
+        // Both broken statements are reported, the rest still parse
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].span.line, 1);
+        assert_eq!(errors[1].span.line, 3);
+        assert_eq!(program.statements.len(), 2);
+        assert!(matches!(
+            program.statements[0].statement,
+            Statement::Tempo(_)
+        ));
+        assert!(matches!(
+            program.statements[1].statement,
+            Statement::Play { .. }
+        ));
+
+        // A clean program matches the regular parse
+        let (program, errors) = parse_spanned_statements_recovering("tempo 90\nstop");
+        assert!(errors.is_empty());
+        assert_eq!(program, parse_spanned_statements("tempo 90\nstop").unwrap());
+    }
+
     #[test]
     fn test_spanned_statement_boundaries() {
         // Test that statement spans cover the entire statement text
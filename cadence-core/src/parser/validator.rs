@@ -1,12 +1,22 @@
-use crate::parser::ast::{Expression, SpannedProgram, SpannedStatement, Statement};
+use crate::parser::ast::{Expression, MidiBinding, SpannedProgram, SpannedStatement, Statement};
 use crate::parser::binder::Binder;
 use crate::parser::error::CadenceError;
 use crate::parser::lexer::Span;
+use crate::parser::statement_parser::parse_spanned_statements_recovering;
 use crate::types::{CommonProgressions, Pattern};
+use std::collections::HashSet;
 
 pub struct Validator<'a> {
     errors: Vec<CadenceError>,
     binder: &'a Binder,
+    /// Variables, parameters and loop variables, any of which may hold a function
+    bound_names: HashSet<String>,
+    /// Namespaces from `use ... as ns`, whose functions are called as `ns_name`
+    namespaces: Vec<String>,
+    /// A `load` or whole-module `use` may define functions we can't see
+    open_imports: bool,
+    /// Set once `use strict` is seen; builtins then need the `std.` prefix
+    strict: bool,
 }
 
 impl<'a> Validator<'a> {
@@ -14,97 +24,89 @@ impl<'a> Validator<'a> {
         Validator {
             errors: Vec::new(),
             binder,
+            bound_names: HashSet::new(),
+            namespaces: Vec::new(),
+            open_imports: false,
+            strict: false,
         }
     }
 
     pub fn validate(program: &SpannedProgram, binder: &'a Binder) -> Vec<CadenceError> {
         let mut validator = Validator::new(binder);
-        validator.visit_program(program);
-        validator.errors
-    }
-
-    fn visit_program(&mut self, program: &SpannedProgram) {
         for stmt in &program.statements {
-            self.visit_statement(stmt);
+            validator.collect_names(&stmt.statement);
         }
+        validator.visit_program(program);
+        validator.errors
     }
 
-    fn visit_statement(&mut self, stmt: &SpannedStatement) {
-        let span = stmt.to_span();
-        match &stmt.statement {
-            Statement::Expression(expr) => self.visit_expression(expr, span),
-            Statement::Let { name, value } => {
-                // Check for direct self-reference (e.g., let x = x)
-                if self.expression_references_var(name, value) {
-                    self.errors.push(CadenceError::new(
-                        format!(
-                            "Variable '{}' cannot reference itself in its definition",
-                            name
-                        ),
-                        span,
-                    ));
-                }
-                self.visit_expression(value, span);
+    /// Record every name a function call could resolve to besides
+    /// builtins and top-level functions
+    fn collect_names(&mut self, stmt: &Statement) {
+        match stmt {
+            Statement::Let { name, .. } | Statement::Assign { name, .. } => {
+                self.bound_names.insert(name.clone());
             }
-            Statement::Assign { value, .. } => self.visit_expression(value, span),
-            Statement::FunctionDef { body, .. } => {
-                // Validate function body
-                for inner_stmt in body {
-                    self.visit_unspanned_statement(inner_stmt, span);
-                }
+            Statement::FunctionDef { params, body, .. } => {
+                self.bound_names.extend(params.iter().cloned());
+                body.iter().for_each(|inner| self.collect_names(inner));
+            }
+            Statement::For { var, body, .. } => {
+                self.bound_names.insert(var.clone());
+                body.iter().for_each(|inner| self.collect_names(inner));
             }
             Statement::If {
-                condition,
                 then_body,
                 else_body,
+                ..
             } => {
-                self.visit_expression(condition, span);
-                // Validate then branch
-                for inner_stmt in then_body {
-                    self.visit_unspanned_statement(inner_stmt, span);
-                }
-                // Validate else branch
-                if let Some(else_stmts) = else_body {
-                    for inner_stmt in else_stmts {
-                        self.visit_unspanned_statement(inner_stmt, span);
-                    }
-                }
-            }
-            Statement::Repeat { body, .. } | Statement::Loop { body } => {
-                // Validate loop body
-                for inner_stmt in body {
-                    self.visit_unspanned_statement(inner_stmt, span);
-                }
-            }
-            Statement::For {
-                body, start, end, ..
-            } => {
-                self.visit_expression(start, span);
-                self.visit_expression(end, span);
-                // Validate for loop body
-                for inner_stmt in body {
-                    self.visit_unspanned_statement(inner_stmt, span);
-                }
+                then_body.iter().for_each(|inner| self.collect_names(inner));
+                else_body
+                    .iter()
+                    .flatten()
+                    .for_each(|inner| self.collect_names(inner));
             }
-            Statement::Block(body) => {
-                // Validate block body
-                for inner_stmt in body {
-                    self.visit_unspanned_statement(inner_stmt, span);
-                }
+            Statement::Repeat { body, .. }
+            | Statement::Loop { body }
+            | Statement::While { body, .. }
+            | Statement::Block(body)
+            | Statement::Bind(MidiBinding::Note { body, .. }) => {
+                body.iter().for_each(|inner| self.collect_names(inner));
             }
-            Statement::Track { body, .. } => {
-                // Validate track body (boxed statement)
-                self.visit_unspanned_statement(body, span);
-            }
-            Statement::Play { target, .. } => self.visit_expression(target, span),
-            Statement::Tempo(expr) | Statement::Volume(expr) | Statement::Wait { beats: expr } => {
-                self.visit_expression(expr, span);
-            }
-            Statement::Return(Some(expr)) => self.visit_expression(expr, span),
+            Statement::Track { body, .. } => self.collect_names(body),
+            Statement::Use { imports, alias, .. } => match (imports, alias) {
+                (_, Some(ns)) => self.namespaces.push(ns.clone()),
+                (Some(names), None) => self.bound_names.extend(names.iter().cloned()),
+                (None, None) => self.open_imports = true,
+            },
+            Statement::Load(_) => self.open_imports = true,
             _ => {}
         }
     }
 
+    fn visit_program(&mut self, program: &SpannedProgram) {
+        for stmt in &program.statements {
+            self.visit_statement(stmt);
+        }
+    }
+
+    fn visit_statement(&mut self, stmt: &SpannedStatement) {
+        let span = stmt.to_span();
+        if let Statement::Let { name, value } = &stmt.statement {
+            // Check for direct self-reference (e.g., let x = x)
+            if self.expression_references_var(name, value) {
+                self.errors.push(CadenceError::new(
+                    format!(
+                        "Variable '{}' cannot reference itself in its definition",
+                        name
+                    ),
+                    span,
+                ));
+            }
+        }
+        self.visit_unspanned_statement(&stmt.statement, span);
+    }
+
     /// Visit an unspanned statement (used for nested bodies)
     /// Uses the parent span for error reporting
     fn visit_unspanned_statement(&mut self, stmt: &Statement, parent_span: Span) {
@@ -112,6 +114,10 @@ impl<'a> Validator<'a> {
             Statement::Expression(expr) => self.visit_expression(expr, parent_span),
             Statement::Let { value, .. } => self.visit_expression(value, parent_span),
             Statement::Assign { value, .. } => self.visit_expression(value, parent_span),
+            Statement::IndexAssign { index, value, .. } => {
+                self.visit_expression(index, parent_span);
+                self.visit_expression(value, parent_span);
+            }
             Statement::FunctionDef { body, .. } => {
                 for inner_stmt in body {
                     self.visit_unspanned_statement(inner_stmt, parent_span);
@@ -132,7 +138,9 @@ impl<'a> Validator<'a> {
                     }
                 }
             }
-            Statement::Repeat { body, .. } | Statement::Loop { body } => {
+            Statement::Repeat { body, .. }
+            | Statement::Loop { body }
+            | Statement::Bind(MidiBinding::Note { body, .. }) => {
                 for inner_stmt in body {
                     self.visit_unspanned_statement(inner_stmt, parent_span);
                 }
@@ -140,12 +148,20 @@ impl<'a> Validator<'a> {
             Statement::For {
                 body, start, end, ..
             } => {
+                self.check_number("For loop bounds", start, parent_span);
+                self.check_number("For loop bounds", end, parent_span);
                 self.visit_expression(start, parent_span);
                 self.visit_expression(end, parent_span);
                 for inner_stmt in body {
                     self.visit_unspanned_statement(inner_stmt, parent_span);
                 }
             }
+            Statement::While { condition, body } => {
+                self.visit_expression(condition, parent_span);
+                for inner_stmt in body {
+                    self.visit_unspanned_statement(inner_stmt, parent_span);
+                }
+            }
             Statement::Block(body) => {
                 for inner_stmt in body {
                     self.visit_unspanned_statement(inner_stmt, parent_span);
@@ -154,49 +170,90 @@ impl<'a> Validator<'a> {
             Statement::Track { body, .. } => {
                 self.visit_unspanned_statement(body, parent_span);
             }
-            Statement::Play { target, .. } => self.visit_expression(target, parent_span),
-            Statement::Tempo(expr) | Statement::Volume(expr) | Statement::Wait { beats: expr } => {
-                self.visit_expression(expr, parent_span);
+            Statement::Play { target, .. } | Statement::ControlChange { target, .. } => {
+                self.visit_pattern(target, parent_span)
             }
+            Statement::Tempo(expr) => self.check_number_statement("Tempo", expr, parent_span),
+            Statement::Volume(expr) => self.check_number_statement("Volume", expr, parent_span),
+            Statement::Capo(expr) => self.check_number_statement("Capo", expr, parent_span),
+            Statement::Wait { beats } => self.check_number_statement("wait", beats, parent_span),
             Statement::Return(Some(expr)) => self.visit_expression(expr, parent_span),
+            Statement::UseStrict => self.strict = true,
             _ => {}
         }
     }
 
+    fn check_number_statement(&mut self, what: &str, expr: &Expression, span: Span) {
+        self.check_number(what, expr, span);
+        self.visit_expression(expr, span);
+    }
+
+    /// Report a literal that can never be the number `what` needs
+    fn check_number(&mut self, what: &str, expr: &Expression, span: Span) {
+        let found = match expr {
+            Expression::Note(_) => "a note",
+            Expression::Chord(_) | Expression::Array(_) => "a chord",
+            Expression::Pattern(_) => "a pattern",
+            Expression::String(_) => "a string",
+            Expression::Boolean(_) => "a boolean",
+            _ => return,
+        };
+        self.errors.push(CadenceError::new(
+            format!("{} requires a number, found {}", what, found),
+            span,
+        ));
+    }
+
+    /// Visit an expression in a position that is played as a pattern, where
+    /// a string literal has to be valid mini-notation
+    fn visit_pattern(&mut self, expr: &Expression, span: Span) {
+        if let Expression::String(s) = expr {
+            self.check_pattern_string(s, span);
+        }
+        self.visit_expression(expr, span);
+    }
+
     fn visit_expression(&mut self, expr: &Expression, span: Span) {
         match expr {
             Expression::FunctionCall { name, args } => {
                 self.check_function_call(name, args, span);
-                for arg in args {
-                    self.visit_expression(arg, span);
+                let builtin = crate::parser::builtins::get_registry().get(name);
+                for (i, arg) in args.iter().enumerate() {
+                    if builtin.is_some_and(|b| b.takes_pattern(i, args.len())) {
+                        self.visit_pattern(arg, span);
+                    } else {
+                        self.visit_expression(arg, span);
+                    }
                 }
             }
-            Expression::BinaryOp { left, right, .. }
-            | Expression::LogicalAnd { left, right }
+            Expression::LogicalAnd { left, right }
             | Expression::LogicalOr { left, right }
-            | Expression::Intersection { left, right }
-            | Expression::Union { left, right }
-            | Expression::Difference { left, right }
             | Expression::Comparison { left, right, .. } => {
                 self.visit_expression(left, span);
                 self.visit_expression(right, span);
             }
-            Expression::LogicalNot(expr) | Expression::Transpose { target: expr, .. } => {
-                self.visit_expression(expr, span);
+            Expression::BinaryOp { left, right, .. }
+            | Expression::Intersection { left, right }
+            | Expression::Union { left, right }
+            | Expression::Difference { left, right } => {
+                self.visit_pattern(left, span);
+                self.visit_pattern(right, span);
             }
+            Expression::LogicalNot(expr) => self.visit_expression(expr, span),
+            Expression::Transpose { target, .. } => self.visit_pattern(target, span),
             Expression::Index { target, index } => {
                 self.visit_expression(target, span);
                 self.visit_expression(index, span);
             }
+            Expression::Range { start, end } => {
+                self.visit_expression(start, span);
+                self.visit_expression(end, span);
+            }
             Expression::Array(elements) => {
                 for elem in elements {
                     self.visit_expression(elem, span);
                 }
             }
-            // Pre-validate pattern strings for syntax errors
-            Expression::String(s) => {
-                self.check_pattern_string(s, span);
-            }
             _ => {}
         }
     }
@@ -220,6 +277,15 @@ impl<'a> Validator<'a> {
 
         // 2. Check built-in functions
         if let Some(builtin) = crate::parser::builtins::get_registry().get(name) {
+            if self.strict && builtin.name == name {
+                self.errors.push(CadenceError::new(
+                    format!(
+                        "Builtin '{}' needs the std prefix after use strict: std.{}()",
+                        name, name
+                    ),
+                    span,
+                ));
+            }
             let valid_arities = builtin.valid_arities();
             let got = args.len();
             if !valid_arities.contains(&got) {
@@ -241,17 +307,34 @@ impl<'a> Validator<'a> {
             return;
         }
 
-        // 3. Check common progressions (I-IV-V etc)
-        if (CommonProgressions::is_valid_progression(name)
+        // 3. Variables and imports can hold functions; their arity isn't known
+        let imported = self.open_imports
+            || self
+                .namespaces
+                .iter()
+                .any(|ns| name.starts_with(&format!("{}_", ns)));
+        if imported || self.bound_names.contains(name) {
+            return;
+        }
+
+        // 4. Check common progressions (I-IV-V etc)
+        if CommonProgressions::is_valid_progression(name)
             || CommonProgressions::is_numeric_progression(name)
-            || CommonProgressions::is_roman_numeral_progression(name))
-            && args.len() != 1
+            || CommonProgressions::is_roman_numeral_progression(name)
         {
-            self.errors.push(CadenceError::new(
-                format!("Progression '{}' expects 1 key argument", name),
-                span,
-            ));
+            if args.len() != 1 {
+                self.errors.push(CadenceError::new(
+                    format!("Progression '{}' expects 1 key argument", name),
+                    span,
+                ));
+            }
+            return;
         }
+
+        self.errors.push(CadenceError::new(
+            format!("Unknown function: {}", name),
+            span,
+        ));
     }
 
     /// Pre-validate pattern strings for syntax errors
@@ -295,6 +378,10 @@ impl<'a> Validator<'a> {
                 self.expression_references_var(var_name, target)
                     || self.expression_references_var(var_name, index)
             }
+            Expression::Range { start, end } => {
+                self.expression_references_var(var_name, start)
+                    || self.expression_references_var(var_name, end)
+            }
             Expression::Array(elements) => elements
                 .iter()
                 .any(|el| self.expression_references_var(var_name, el)),
@@ -304,6 +391,34 @@ impl<'a> Validator<'a> {
     }
 }
 
+/// Check a program without running it: arity and name errors in calls,
+/// literals of the wrong type, and malformed patterns
+pub fn validate_program(program: &SpannedProgram) -> Vec<CadenceError> {
+    let binder = Binder {
+        table: Binder::bind(program),
+    };
+    Validator::validate(program, &binder)
+}
+
+/// Parse and validate source, collecting every parse error instead of
+/// stopping at the first. Errors come back in source order with their line
+/// and column filled in.
+pub fn check_source(source: &str) -> Vec<CadenceError> {
+    let (program, mut errors) = parse_spanned_statements_recovering(source);
+    errors.extend(validate_program(&program));
+
+    // Validation errors only know their offset into the source
+    for error in &mut errors {
+        if error.span.line == 0 {
+            let before: Vec<char> = source.chars().take(error.span.offset).collect();
+            error.span.line = before.iter().filter(|&&c| c == '\n').count() + 1;
+            error.span.column = before.iter().rev().take_while(|&&c| c != '\n').count() + 1;
+        }
+    }
+    errors.sort_by_key(|error| error.span.offset);
+    errors
+}
+
 trait ToSpan {
     fn to_span(&self) -> Span;
 }
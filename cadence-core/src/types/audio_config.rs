@@ -6,7 +6,9 @@
 //! web-based editors.
 
 /// Available waveform types
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
 pub enum Waveform {
     #[default]
     Sine,
@@ -44,7 +46,7 @@ impl Waveform {
 /// - `decay`: Time in seconds to fall from peak to sustain level
 /// - `sustain`: Level to hold while note is held (0.0-1.0, NOT time!)
 /// - `release`: Time in seconds to fall from sustain to 0 after note-off
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct AdsrParams {
     pub attack: f32,
     pub decay: f32,
@@ -92,6 +94,22 @@ impl AdsrParams {
     pub fn organ() -> Self {
         Self::new(0.005, 0.0, 1.0, 0.01)
     }
+
+    /// Name of the preset an `(attack, decay, sustain, release)` tuple came from, if any
+    pub fn preset_name(envelope: (f32, f32, f32, f32)) -> Option<&'static str> {
+        let (attack, decay, sustain, release) = envelope;
+        let params = Self::new(attack, decay, sustain, release);
+        [
+            ("default", Self::default_envelope()),
+            ("pluck", Self::pluck()),
+            ("pad", Self::pad()),
+            ("perc", Self::perc()),
+            ("organ", Self::organ()),
+        ]
+        .into_iter()
+        .find(|(_, preset)| *preset == params)
+        .map(|(name, _)| name)
+    }
 }
 
 impl Default for AdsrParams {
@@ -140,6 +158,16 @@ mod tests {
         assert!(params.sustain <= 1.0);
     }
 
+    #[test]
+    fn test_adsr_preset_name() {
+        assert_eq!(AdsrParams::preset_name((0.3, 0.2, 0.8, 0.5)), Some("pad"));
+        assert_eq!(
+            AdsrParams::preset_name((0.01, 0.1, 0.7, 0.2)),
+            Some("default")
+        );
+        assert_eq!(AdsrParams::preset_name((0.5, 0.1, 0.5, 0.5)), None);
+    }
+
     #[test]
     fn test_queue_mode_default() {
         assert_eq!(QueueMode::default(), QueueMode::Beat);
@@ -1,18 +1,62 @@
 use crate::types::note::Note;
-use anyhow::Result;
+use crate::types::scale::{uses_sharps, ScaleMode};
+use crate::types::voice_leading::VoiceLeading;
+use anyhow::{anyhow, Result};
 #[cfg(feature = "colored")]
 use colored::*;
 use std::collections::BTreeSet;
 use std::fmt;
 use std::ops::{Add, BitAnd, BitOr, BitXor, Sub};
 
+/// A tension over a chord, as listed by [`Chord::tensions`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tension {
+    /// `9`, `#11`, `b13`, ...
+    pub name: &'static str,
+    /// The tension an octave above the chord root
+    pub note: Note,
+    /// A semitone above a chord tone, so it clashes when held against it
+    pub avoid: bool,
+}
+
 /// Represents a musical chord as a collection of notes with bass note tracking for inversions
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(from = "ChordJson", into = "ChordJson"))]
 pub struct Chord {
     notes: BTreeSet<Note>,
     bass_note: Option<Note>, // The note that should be in the bass (for inversions)
     input_order: Vec<Note>,  // Preserve original input order for display
 }
+
+/// JSON shape of a chord: notes in input order plus the bass note
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct ChordJson {
+    notes: Vec<Note>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    bass: Option<Note>,
+}
+
+#[cfg(feature = "serde")]
+impl From<Chord> for ChordJson {
+    fn from(chord: Chord) -> Self {
+        ChordJson {
+            notes: chord.input_order,
+            bass: chord.bass_note,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<ChordJson> for Chord {
+    fn from(json: ChordJson) -> Self {
+        match json.bass {
+            Some(bass) => Chord::with_bass(json.notes, bass),
+            None => Chord::from_notes(json.notes),
+        }
+    }
+}
 impl Chord {
     /// Create a new empty chord
     pub fn new() -> Self {
@@ -79,6 +123,102 @@ impl Chord {
         Ok(Self::from_notes(notes))
     }
 
+    /// Build a chord by stacking semitone intervals above a root.
+    /// The root is always included; repeated intervals collapse into one note.
+    pub fn from_intervals(root: Note, intervals: &[u8]) -> Self {
+        let mut notes = vec![root];
+        for &interval in intervals {
+            let note = root.transpose(interval.min(i8::MAX as u8) as i8);
+            if !notes.contains(&note) {
+                notes.push(note);
+            }
+        }
+        Self::from_notes(notes)
+    }
+
+    /// Build a chord from a lead-sheet symbol like "Cmaj7", "F#m7b5", "Bbsus4"
+    /// or "C/E". The root is voiced from octave 4 with the chord stacked
+    /// above it; a slash bass goes below the root. "Gdom7", "Cdom9" and
+    /// "Amaj6" are the same as "G7", "C9" and "A6", which scripts read as notes.
+    pub fn from_symbol(symbol: &str) -> Result<Self> {
+        let (name, bass) = match symbol.split_once('/') {
+            Some((name, bass)) => (name, Some(bass)),
+            None => (symbol, None),
+        };
+        let root_len = match name.as_bytes() {
+            [b'A'..=b'G', b'#' | b'b', ..] => 2,
+            [b'A'..=b'G', ..] => 1,
+            _ => return Err(anyhow!("'{}' doesn't start with a note name", symbol)),
+        };
+        let root: Note = name[..root_len].parse()?;
+        let suffix = &name[root_len..];
+        let chord = Self::from_suffix(root, suffix)
+            .ok_or_else(|| anyhow!("unknown chord quality '{}' in '{}'", suffix, symbol))?;
+
+        let Some(bass) = bass else {
+            return Ok(chord);
+        };
+        let bass: Note = bass
+            .parse()
+            .map_err(|_| anyhow!("invalid bass note '{}' in '{}'", bass, symbol))?;
+        let bass = if bass.midi_note() >= root.midi_note() {
+            bass - 12
+        } else {
+            bass
+        };
+        let mut notes = vec![bass];
+        notes.extend(chord.input_order);
+        Ok(Self::from_notes(notes))
+    }
+
+    /// Build a chord on `root` from a quality name, either one of the
+    /// qualities Roman numeral analysis uses ("Major", "Minor",
+    /// "Diminished", "Augmented", "HalfDiminished", "MajorMinor" or
+    /// "dominant", plus sevenths like "major7" and "minor7") or a symbol
+    /// suffix such as "m7b5", "sus4" or "add9". Names ignore case, spaces,
+    /// dashes and underscores; suffixes are case-sensitive ("M7" is not "m7").
+    pub fn build(root: Note, quality: &str) -> Result<Self> {
+        let name: String = quality
+            .chars()
+            .filter(|c| !matches!(c, ' ' | '-' | '_'))
+            .collect::<String>()
+            .to_lowercase();
+        let suffix = match name.as_str() {
+            "major" => "",
+            "minor" => "m",
+            "diminished" => "dim",
+            "augmented" => "aug",
+            "halfdiminished" => "m7b5",
+            "dominant" | "dominant7" | "majorminor" => "7",
+            "major7" | "majorseventh" => "maj7",
+            "minor7" | "minorseventh" => "m7",
+            "diminished7" | "diminishedseventh" => "dim7",
+            "augmented7" => "aug7",
+            "minormajor7" => "mMaj7",
+            _ => quality,
+        };
+        Self::from_suffix(root, suffix).ok_or_else(|| {
+            anyhow!(
+                "unknown chord quality '{}' (expected major, minor, diminished, augmented, \
+                 half_diminished, dominant, major7, minor7, diminished7, or a chord symbol \
+                 suffix like m7b5, sus4 or add9)",
+                quality
+            )
+        })
+    }
+
+    /// The chord a symbol suffix names on `root`, with each tone spelled
+    /// from the root's letter
+    fn from_suffix(root: Note, suffix: &str) -> Option<Self> {
+        let intervals = symbol_intervals(suffix)?;
+        let dim7 = intervals.contains(&6) && intervals.contains(&9);
+        let notes = std::iter::once(0)
+            .chain(intervals.iter().copied())
+            .map(|interval| chord_tone(root, interval, dim7))
+            .collect();
+        Some(Self::from_notes(notes))
+    }
+
     /// Create a chord with a specific bass note
     pub fn with_bass(notes: Vec<Note>, bass: Note) -> Self {
         let mut chord = Self::from_notes(notes);
@@ -171,10 +311,37 @@ impl Chord {
             }
         }
 
+        // Seventh chords in open voicings can have a fifth or third in the bass
+        if self.notes.len() == 4 {
+            if let Some(analyzed_root) = self.find_seventh_root() {
+                return Some(analyzed_root);
+            }
+        }
+
         // Fall back to bass note or lowest note
         self.bass()
     }
 
+    /// Find the root of a four-note chord: the bass if the chord is a known
+    /// quality over it, otherwise the first note that is
+    fn find_seventh_root(&self) -> Option<Note> {
+        let candidates = self.bass().into_iter().chain(self.notes_vec());
+        for potential_root in candidates {
+            let mut intervals: Vec<u8> = self
+                .notes
+                .iter()
+                .map(|note| (note.pitch_class() + 12 - potential_root.pitch_class()) % 12)
+                .filter(|&interval| interval != 0)
+                .collect();
+            intervals.sort();
+            intervals.dedup();
+            if intervals.len() == 3 && symbol_suffix(&intervals).is_some() {
+                return Some(potential_root);
+            }
+        }
+        None
+    }
+
     /// Find the root of a triad by analyzing intervals
     fn find_triad_root(&self) -> Option<Note> {
         let notes_vec = self.notes_vec();
@@ -235,6 +402,322 @@ impl Chord {
         }
     }
 
+    /// Replace every note with `f(note)`, keeping the voicing order and the
+    /// bass. Notes that map onto the same note merge into one.
+    pub fn map_notes(self, f: impl Fn(Note) -> Note) -> Self {
+        let mut voices: Vec<Note> = Vec::new();
+        for note in self.input_order.into_iter().map(&f) {
+            if !voices.contains(&note) {
+                voices.push(note);
+            }
+        }
+        let mut chord = Chord::from_notes(voices);
+        chord.bass_note = self.bass_note.map(f);
+        chord
+    }
+
+    /// Move the outer voices in contrary motion: the top voice rises by `outer`
+    /// semitones and the bottom voice falls by `outer`. Inner voices stay put.
+    /// Chords with fewer than two voices are returned unchanged.
+    pub fn contrary(self, outer: i8) -> Self {
+        if self.input_order.len() < 2 {
+            return self;
+        }
+
+        let pitch = |n: &Note| n.octave() as i16 * 12 + n.pitch_class() as i16;
+        let (bottom_idx, _) = self
+            .input_order
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, n)| pitch(n))
+            .unwrap();
+        let (top_idx, _) = self
+            .input_order
+            .iter()
+            .enumerate()
+            .max_by_key(|(_, n)| pitch(n))
+            .unwrap();
+
+        let bottom = self.input_order[bottom_idx];
+        let top = self.input_order[top_idx];
+        let mut voices = self.input_order.clone();
+        voices[bottom_idx] = bottom - outer;
+        voices[top_idx] = top + outer;
+
+        let bass_note = self.bass_note.map(|b| {
+            if b == bottom {
+                bottom - outer
+            } else if b == top {
+                top + outer
+            } else {
+                b
+            }
+        });
+
+        let mut chord = Chord::from_notes(voices);
+        chord.bass_note = bass_note;
+        chord
+    }
+
+    /// Apply a dominant alteration: `b5`, `#5`, `b9`, `#9`, `#11` or `b13`,
+    /// measured from the root. A natural tone the alteration conflicts with
+    /// (the fifth, ninth, eleventh or thirteenth) is raised or lowered in place;
+    /// otherwise the altered tone is added. Alterations chain, so
+    /// `alter("b9")` then `alter("#5")` gives a 7#5b9.
+    pub fn alter(mut self, alteration: &str) -> Result<Self> {
+        let (natural, shift): (u8, i8) = match alteration {
+            "b5" => (7, -1),
+            "#5" => (7, 1),
+            "b9" => (2, -1),
+            "#9" => (2, 1),
+            "#11" => (5, 1),
+            "b13" => (9, -1),
+            _ => {
+                return Err(anyhow!(
+                    "unknown alteration '{}' (expected b5, #5, b9, #9, #11 or b13)",
+                    alteration
+                ))
+            }
+        };
+        let Some(root) = self.root() else {
+            return Ok(self);
+        };
+        let degree = |n: &Note| (n.pitch_class() + 12 - root.pitch_class()) % 12;
+        let altered = (natural as i8 + shift).rem_euclid(12) as u8;
+        if self.input_order.iter().any(|n| degree(n) == altered) {
+            return Ok(self);
+        }
+
+        let conflicting: Vec<Note> = self
+            .input_order
+            .iter()
+            .filter(|n| degree(n) == natural)
+            .copied()
+            .collect();
+        if conflicting.is_empty() {
+            // Fifths sit inside the octave, tensions an octave above the root
+            let octave = if natural == 7 { 0 } else { 12 };
+            self.add_note((root + (natural as i8 + shift + octave)).respell(shift > 0));
+            return Ok(self);
+        }
+        for old in conflicting {
+            let new = (old + shift).respell(shift > 0);
+            for note in self.input_order.iter_mut().filter(|n| **n == old) {
+                *note = new;
+            }
+            self.notes.remove(&old);
+            self.notes.insert(new);
+            if self.bass_note == Some(old) {
+                self.bass_note = Some(new);
+            }
+        }
+        Ok(self)
+    }
+
+    /// The tensions (9, 11 and 13 with their alterations) that fit over this
+    /// chord in a key, lowest first.
+    ///
+    /// A tension is listed when it is in the scale and isn't already a chord
+    /// tone. Dominant chords (a major third and a minor seventh) also get the
+    /// altered tensions, in the scale or not. A tension a semitone above a
+    /// chord tone is flagged as an avoid note, like the 11 over a major
+    /// chord, except for the b9 and b13 of a dominant.
+    pub fn tensions(&self, key: Note, mode: ScaleMode) -> Vec<Tension> {
+        const TENSIONS: [(&str, u8); 7] = [
+            ("b9", 1),
+            ("9", 2),
+            ("#9", 3),
+            ("11", 5),
+            ("#11", 6),
+            ("b13", 8),
+            ("13", 9),
+        ];
+
+        let Some(root) = self.root() else {
+            return Vec::new();
+        };
+        let degrees: Vec<u8> = self
+            .notes
+            .iter()
+            .map(|n| (n.pitch_class() + 12 - root.pitch_class()) % 12)
+            .collect();
+        let dominant = degrees.contains(&4) && degrees.contains(&10);
+        let scale = mode.intervals();
+
+        TENSIONS
+            .iter()
+            .filter(|(_, interval)| !degrees.contains(interval))
+            .filter_map(|&(name, interval)| {
+                let altered = name.len() > 1;
+                let in_scale = scale
+                    .contains(&((root.pitch_class() + interval + 12 - key.pitch_class()) % 12));
+                if !(in_scale || dominant && altered) {
+                    return None;
+                }
+
+                let sharp = match name.as_bytes()[0] {
+                    b'b' => false,
+                    b'#' => true,
+                    _ => uses_sharps(key),
+                };
+                let clashes = degrees.contains(&((interval + 11) % 12));
+                Some(Tension {
+                    name,
+                    note: (root + (interval as i8 + 12)).respell(sharp),
+                    avoid: clashes && !(dominant && matches!(name, "b9" | "b13")),
+                })
+            })
+            .collect()
+    }
+
+    /// Double chord tones until the chord has `n` notes, for section voicings.
+    ///
+    /// The root is doubled first, then the fifth, then the third, each copy an
+    /// octave above the highest one already in the chord. Asking for fewer
+    /// notes than the chord has drops doublings instead (thirds first, then
+    /// fifths, then roots, highest copy first) and then the fifth itself;
+    /// other tones are kept, so the chord can end up with more than `n` notes.
+    pub fn voices(mut self, n: usize) -> Self {
+        let Some(root) = self.root() else {
+            return self;
+        };
+        let degree = |note: &Note| (note.pitch_class() + 12 - root.pitch_class()) % 12;
+        let has = |chord: &Chord, d: u8| chord.notes.iter().any(|note| degree(note) == d);
+        let fifth = [7, 6, 8].into_iter().find(|&d| has(&self, d));
+        let third = [4, 3].into_iter().find(|&d| has(&self, d));
+        // Tones that can be doubled, most essential first
+        let doublable: Vec<u8> = [Some(0), fifth, third].into_iter().flatten().collect();
+        let copies = |chord: &Chord, d: u8| -> Vec<Note> {
+            let mut copies: Vec<Note> = chord
+                .notes
+                .iter()
+                .filter(|note| degree(note) == d)
+                .copied()
+                .collect();
+            copies.sort_by_key(|note| note.midi_note());
+            copies
+        };
+
+        for &d in doublable.iter().cycle() {
+            if self.len() >= n {
+                break;
+            }
+            let highest = *copies(&self, d).last().unwrap();
+            if highest.midi_note() + 12 > 127 {
+                break;
+            }
+            self.add_note(highest + 12);
+        }
+
+        for &d in doublable.iter().rev() {
+            while self.len() > n {
+                match copies(&self, d).as_slice() {
+                    [_, .., highest] => self.remove_note(highest),
+                    _ => break,
+                };
+            }
+        }
+        if let Some(fifth) = fifth.filter(|_| self.len() > n) {
+            if let [only] = copies(&self, fifth).as_slice() {
+                if self.bass() != Some(*only) {
+                    self.remove_note(only);
+                }
+            }
+        }
+        self
+    }
+
+    /// The chord's notes in arpeggio order. `mode` is "up" (lowest to
+    /// highest), "down", "updown" and "downup" (there and back, without
+    /// playing the turning note twice so the figure loops smoothly), or
+    /// "converge" (lowest, highest, then working inwards).
+    pub fn arpeggio(&self, mode: &str) -> Result<Vec<Note>> {
+        let mut up = self.notes_vec();
+        up.sort_by_key(|note| note.midi_note());
+        let down: Vec<Note> = up.iter().rev().copied().collect();
+        // One way and most of the way back: C E G E
+        let there_and_back = |there: &[Note], back: &[Note]| -> Vec<Note> {
+            let mut notes = there.to_vec();
+            if there.len() > 2 {
+                notes.extend(&back[1..back.len() - 1]);
+            }
+            notes
+        };
+
+        match mode {
+            "up" => Ok(up),
+            "down" => Ok(down),
+            "updown" => Ok(there_and_back(&up, &down)),
+            "downup" => Ok(there_and_back(&down, &up)),
+            "converge" => {
+                let (mut low, mut high) = (0, up.len());
+                let mut notes = Vec::with_capacity(up.len());
+                while low < high {
+                    notes.push(up[low]);
+                    low += 1;
+                    if low < high {
+                        high -= 1;
+                        notes.push(up[high]);
+                    }
+                }
+                Ok(notes)
+            }
+            _ => Err(anyhow!(
+                "unknown arpeggio mode '{}' (expected up, down, updown, downup or converge)",
+                mode
+            )),
+        }
+    }
+
+    /// Revoice the chord. "close" packs every note into the octave above
+    /// the lowest one; "drop2", "drop3" and "drop24" start from that close
+    /// voicing and move the second, third, or second and fourth voices from
+    /// the top down an octave. Drop-2 needs at least three notes, drop-3 and
+    /// drop-2-4 at least four.
+    pub fn voicing(&self, kind: &str) -> Result<Self> {
+        let mut close = self.notes_vec();
+        close.sort_by_key(|note| note.midi_note());
+        let Some(&lowest) = close.first() else {
+            return Ok(self.clone());
+        };
+        for note in close.iter_mut().skip(1) {
+            while note.midi_note() >= lowest.midi_note() + 12 {
+                *note = *note - 12;
+            }
+        }
+        close.sort_by_key(|note| note.midi_note());
+        close.dedup_by_key(|note| note.pitch_class());
+
+        // Voices counted down from the top, 1 being the highest, and the
+        // fewest notes the voicing makes sense for
+        let (dropped, needed): (&[usize], usize) = match kind {
+            "close" => (&[], 0),
+            "drop2" => (&[2], 3),
+            "drop3" => (&[3], 4),
+            "drop24" => (&[2, 4], 4),
+            _ => {
+                return Err(anyhow!(
+                    "unknown voicing '{}' (expected close, drop2, drop3 or drop24)",
+                    kind
+                ))
+            }
+        };
+        if close.len() < needed {
+            return Err(anyhow!(
+                "{} voicing needs at least {} notes, the chord has {}",
+                kind,
+                needed,
+                close.len()
+            ));
+        }
+        let len = close.len();
+        for &voice in dropped {
+            close[len - voice] = close[len - voice] - 12;
+        }
+        close.sort_by_key(|note| note.midi_note());
+        Ok(Chord::from_notes(close))
+    }
+
     /// Normalize the chord to a target octave (default: 4)
     ///
     /// This shifts all notes so the bass note is in the target octave,
@@ -291,6 +774,40 @@ impl Chord {
         self
     }
 
+    /// Invert downwards: move the top voice down an octave into the bass
+    pub fn invert_down(self) -> Self {
+        self.invert_down_n(1)
+    }
+
+    /// Invert downwards n times. Cycles through the same voicings as
+    /// `invert_n`, in the opposite order.
+    pub fn invert_down_n(mut self, n: usize) -> Self {
+        if self.notes.len() < 2 {
+            return self;
+        }
+
+        let steps = n % self.notes.len();
+
+        if steps == 0 {
+            return self;
+        }
+
+        for _ in 0..steps {
+            if let Some(note_to_move) = self.input_order.pop() {
+                self.notes.remove(&note_to_move);
+
+                let new_note = note_to_move - 12; // Transpose down 1 octave
+
+                self.input_order.insert(0, new_note);
+                self.notes.insert(new_note);
+            }
+        }
+
+        self.bass_note = self.input_order.first().copied();
+
+        self
+    }
+
     /// Get the inversion number (0 = root position, 1 = first inversion, etc.)
     pub fn inversion(&self) -> usize {
         if let (Some(root), Some(bass)) = (self.root(), self.bass()) {
@@ -308,12 +825,60 @@ impl Chord {
         0
     }
 
+    /// Pick the candidate that moves most smoothly from this chord, scored with
+    /// `VoiceLeading::smoothness_score`. That score only sees pitch classes, so
+    /// voicings of the same chord tie; the one whose notes lie closest in pitch
+    /// wins, then the earlier candidate.
+    pub fn best_voicing<'a>(&self, candidates: &'a [Chord]) -> Option<&'a Chord> {
+        candidates
+            .iter()
+            .map(|candidate| {
+                let score = VoiceLeading::analyze(self, candidate).smoothness_score();
+                (candidate, score, self.pitch_distance(candidate))
+            })
+            .min_by(|(_, a, a_distance), (_, b, b_distance)| {
+                a.total_cmp(b).then(a_distance.cmp(b_distance))
+            })
+            .map(|(candidate, _, _)| candidate)
+    }
+
+    /// Semitones between each note of `other` and the nearest note of this chord
+    fn pitch_distance(&self, other: &Chord) -> u32 {
+        other
+            .notes()
+            .filter_map(|to| {
+                self.notes()
+                    .map(|from| (to.midi_note() as i32 - from.midi_note() as i32).unsigned_abs())
+                    .min()
+            })
+            .sum()
+    }
+
     /// Analyze the chord and try to identify it
     pub fn analyze(&self) -> String {
         if self.is_empty() {
             return "Empty".to_string();
         }
 
+        // Octave doublings don't change the chord: analyze its lowest copies
+        let mut lowest: Vec<Note> = Vec::new();
+        let mut by_pitch = self.notes_vec();
+        by_pitch.sort_by_key(|note| note.midi_note());
+        for note in by_pitch {
+            if !lowest.iter().any(|n| n.pitch_class() == note.pitch_class()) {
+                lowest.push(note);
+            }
+        }
+        if lowest.len() < self.len() {
+            let bass = self.bass().unwrap();
+            let bass = lowest
+                .iter()
+                .copied()
+                .find(|n| n.pitch_class() == bass.pitch_class())
+                .unwrap();
+            return Chord::with_bass(lowest, bass).analyze();
+        }
+
         let notes_vec = self.notes_vec();
 
         match notes_vec.len() {
@@ -486,6 +1051,242 @@ impl Chord {
             "Unknown 4-note chord".to_string()
         }
     }
+
+    /// Lead-sheet chord symbol, e.g. "Cmaj7", "F#m7b5", or "C/E" when a note
+    /// other than the root is in the bass.
+    ///
+    /// Voicings with no known quality are written as the bass note followed
+    /// by the other note names in parentheses, e.g. "C(Db F#)".
+    pub fn to_symbol(&self) -> String {
+        if let Some(symbol) = self.known_symbol() {
+            return symbol;
+        }
+        let mut voicing = self.notes_vec();
+        voicing.sort_by_key(|n| n.midi_note());
+        let Some(bass) = self.bass_note.or_else(|| voicing.first().copied()) else {
+            return "N.C.".to_string();
+        };
+        let others: Vec<String> = voicing
+            .iter()
+            .filter(|n| n.pitch_class() != bass.pitch_class())
+            .map(|n| n.name())
+            .collect();
+        format!("{}({})", bass.name(), others.join(" "))
+    }
+
+    /// The chord symbol, or None when the voicing has no known quality
+    pub fn known_symbol(&self) -> Option<String> {
+        let mut voicing = self.notes_vec();
+        voicing.sort_by_key(|n| n.midi_note());
+        let bass = self.bass_note.or_else(|| voicing.first().copied())?;
+
+        let mut pitch_classes: Vec<u8> = voicing.iter().map(|n| n.pitch_class()).collect();
+        pitch_classes.sort();
+        pitch_classes.dedup();
+
+        // Prefer the analyzed root, then the bass, before trying the rest
+        let candidates = self
+            .root()
+            .into_iter()
+            .chain(std::iter::once(bass))
+            .chain(voicing.iter().copied());
+        for root in candidates {
+            let mut intervals: Vec<u8> = pitch_classes
+                .iter()
+                .map(|pc| (pc + 12 - root.pitch_class()) % 12)
+                .filter(|&interval| interval != 0)
+                .collect();
+            intervals.sort();
+            if let Some(suffix) = symbol_suffix(&intervals) {
+                let slash = if bass.pitch_class() != root.pitch_class() {
+                    format!("/{}", bass.name())
+                } else {
+                    String::new()
+                };
+                return Some(format!("{}{}{}", root.name(), suffix, slash));
+            }
+        }
+        None
+    }
+
+    /// The chord symbol a script reads back as this same chord, or None
+    /// when there isn't one (no known quality, or a voicing other than the
+    /// one the symbol builds). Symbols that would read as a note with an
+    /// octave are respelled, so G major is "Gmaj" and G7 is "Gdom7".
+    pub fn script_symbol(&self) -> Option<String> {
+        let symbol = self.known_symbol()?;
+        let symbol = if symbol.parse::<Note>().is_ok() {
+            let root_len = if symbol[1..].starts_with(['#', 'b']) {
+                2
+            } else {
+                1
+            };
+            let suffix = match &symbol[root_len..] {
+                "" => "maj",
+                "6" => "maj6",
+                "7" => "dom7",
+                "9" => "dom9",
+                _ => return None,
+            };
+            format!("{}{}", &symbol[..root_len], suffix)
+        } else {
+            symbol
+        };
+        (Self::from_symbol(&symbol).ok()? == *self).then_some(symbol)
+    }
+
+    /// The most complete chord symbol for the voicing, with extensions,
+    /// alterations and a slash bass, e.g. "Cmaj7#11/E", "C13" or "G7b9#9".
+    ///
+    /// A voicing `known_symbol()` can name keeps that simpler name. Otherwise
+    /// the chord is split into a base quality plus tensions, choosing the
+    /// root and base that leave the fewest tensions (a root in the bass wins
+    /// a tie). The highest natural
+    /// tension over a seventh chord replaces the 7 ("C9", "C13"); altered ones
+    /// are appended, and tensions over a triad are written as "add" tones.
+    /// Falls back to `to_symbol()` when no reading fits.
+    pub fn full_symbol(&self) -> String {
+        if let Some(symbol) = self.known_symbol() {
+            return symbol;
+        }
+        let mut voicing = self.notes_vec();
+        voicing.sort_by_key(|n| n.midi_note());
+        let Some(bass) = self.bass_note.or_else(|| voicing.first().copied()) else {
+            return "N.C.".to_string();
+        };
+
+        let mut pitch_classes: Vec<u8> = voicing.iter().map(|n| n.pitch_class()).collect();
+        pitch_classes.sort();
+        pitch_classes.dedup();
+
+        let candidates = self
+            .root()
+            .into_iter()
+            .chain(std::iter::once(bass))
+            .chain(voicing.iter().copied());
+        let mut best: Option<((usize, bool), String)> = None;
+        for root in candidates {
+            let mut intervals: Vec<u8> = pitch_classes
+                .iter()
+                .map(|pc| (pc + 12 - root.pitch_class()) % 12)
+                .filter(|&interval| interval != 0)
+                .collect();
+            intervals.sort();
+            for mask in 0u32..(1 << intervals.len()) {
+                let (base, rest): (Vec<_>, Vec<_>) = intervals
+                    .iter()
+                    .enumerate()
+                    .partition(|(i, _)| mask & (1 << i) != 0);
+                let base: Vec<u8> = base.into_iter().map(|(_, &interval)| interval).collect();
+                let rest: Vec<u8> = rest.into_iter().map(|(_, &interval)| interval).collect();
+                let inverted = bass.pitch_class() != root.pitch_class();
+                let score = (rest.len(), inverted);
+                if best
+                    .as_ref()
+                    .is_some_and(|(best_score, _)| score >= *best_score)
+                {
+                    continue;
+                }
+                if let Some(suffix) = extended_suffix(&base, &rest) {
+                    let slash = if inverted {
+                        format!("/{}", bass.name())
+                    } else {
+                        String::new()
+                    };
+                    best = Some((score, format!("{}{}{}", root.name(), suffix, slash)));
+                }
+            }
+        }
+        best.map(|(_, symbol)| symbol)
+            .unwrap_or_else(|| self.to_symbol())
+    }
+
+    /// Describe the voicing as an upper-structure triad over a lower chord,
+    /// e.g. C E Bb | D F# A is "D triad over C7 (9 #11 13)".
+    ///
+    /// The top three notes must form a major or minor triad that adds at least
+    /// one tension over the lower notes (a bass with an optional 3rd and 7th).
+    /// Anything else falls back to the plain chord name from `analyze()`.
+    pub fn upper_structure(&self) -> String {
+        self.find_upper_structure()
+            .unwrap_or_else(|| self.analyze())
+    }
+
+    fn find_upper_structure(&self) -> Option<String> {
+        let mut voicing = self.notes_vec();
+        voicing.sort_by_key(|n| n.midi_note());
+        if voicing.len() < 4 {
+            return None;
+        }
+
+        let (lower, upper) = voicing.split_at(voicing.len() - 3);
+        let bass = lower[0];
+        let interval_from_bass = |note: &Note| (note.pitch_class() + 12 - bass.pitch_class()) % 12;
+
+        // Lower structure: bass plus optional 3rd, 5th and 7th
+        let mut lower_intervals: Vec<u8> = lower.iter().map(interval_from_bass).collect();
+        lower_intervals.retain(|&i| i != 0 && i != 7);
+        lower_intervals.sort_unstable();
+        lower_intervals.dedup();
+        let lower_suffix = match lower_intervals.as_slice() {
+            [] | [4] => "",
+            [3] => "m",
+            [4, 10] | [10] => "7",
+            [4, 11] => "maj7",
+            [3, 10] => "m7",
+            _ => return None,
+        };
+        let major_third = lower_intervals.contains(&4);
+
+        // Upper structure: a major or minor triad on a root other than the bass
+        let (upper_root, minor) = upper.iter().find_map(|&root| {
+            let mut intervals: Vec<u8> = upper
+                .iter()
+                .filter(|&&n| n != root)
+                .map(|n| (n.pitch_class() + 12 - root.pitch_class()) % 12)
+                .collect();
+            intervals.sort_unstable();
+            match intervals.as_slice() {
+                [4, 7] => Some((root, false)),
+                [3, 7] => Some((root, true)),
+                _ => None,
+            }
+        })?;
+        if upper_root.pitch_class() == bass.pitch_class() {
+            return None;
+        }
+
+        let mut tensions: Vec<u8> = upper
+            .iter()
+            .map(interval_from_bass)
+            .filter(|&i| matches!(i, 1 | 2 | 5 | 6 | 8 | 9) || (i == 3 && major_third))
+            .collect();
+        if tensions.is_empty() {
+            return None;
+        }
+        tensions.sort_unstable();
+        let tension_names: Vec<&str> = tensions
+            .iter()
+            .map(|i| match i {
+                1 => "b9",
+                2 => "9",
+                3 => "#9",
+                5 => "11",
+                6 => "#11",
+                8 => "b13",
+                _ => "13",
+            })
+            .collect();
+
+        Some(format!(
+            "{}{} triad over {}{} ({})",
+            upper_root.name(),
+            if minor { "m" } else { "" },
+            bass.name(),
+            lower_suffix,
+            tension_names.join(" ")
+        ))
+    }
 }
 
 impl Default for Chord {
@@ -733,6 +1534,152 @@ impl BitXor for Chord {
     }
 }
 
+/// Chord symbol suffix for sorted intervals above the root
+fn symbol_suffix(intervals: &[u8]) -> Option<&'static str> {
+    let suffix = match intervals {
+        [] => "",
+        [7] => "5",
+        [4, 7] => "",
+        [3, 7] => "m",
+        [3, 6] => "dim",
+        [4, 8] => "aug",
+        [2, 7] => "sus2",
+        [5, 7] => "sus4",
+        [4, 7, 9] => "6",
+        [3, 7, 9] => "m6",
+        [4, 7, 10] => "7",
+        [4, 7, 11] => "maj7",
+        [3, 7, 10] => "m7",
+        [3, 7, 11] => "mMaj7",
+        [3, 6, 10] => "m7b5",
+        [3, 6, 9] => "dim7",
+        [4, 8, 10] => "aug7",
+        [4, 8, 11] => "maj7#5",
+        [2, 7, 10] => "7sus2",
+        [5, 7, 10] => "7sus4",
+        [2, 4, 7] => "add9",
+        [2, 3, 7] => "madd9",
+        [2, 4, 7, 9] => "6/9",
+        [2, 4, 7, 10] => "9",
+        [2, 4, 7, 11] => "maj9",
+        [2, 3, 7, 10] => "m9",
+        _ => return None,
+    };
+    Some(suffix)
+}
+
+/// The note `interval` semitones above `root`, spelled on the letter the
+/// chord tone belongs to, so a minor third above C is Eb rather than D#.
+/// In a diminished seventh chord the 9-semitone tone is the seventh.
+fn chord_tone(root: Note, interval: u8, dim7: bool) -> Note {
+    const LETTER_STEPS: [usize; 12] = [0, 1, 1, 2, 2, 3, 4, 4, 4, 5, 6, 6];
+    const NATURALS: [u8; 7] = [0, 2, 4, 5, 7, 9, 11];
+
+    let note = root.transpose(interval as i8);
+    let root_letter = "CDEFGAB"
+        .find(&root.name()[..1])
+        .expect("note names start with a letter");
+    let steps = if dim7 && interval == 9 {
+        6
+    } else {
+        LETTER_STEPS[interval as usize % 12]
+    };
+    let letter = NATURALS[(root_letter + steps) % 7];
+    match (note.pitch_class() + 12 - letter) % 12 {
+        1 => note.respell(true),
+        11 => note.respell(false),
+        _ => note,
+    }
+}
+
+/// Intervals above the root for a chord symbol suffix, the inverse of
+/// `symbol_suffix` plus a few common spellings
+fn symbol_intervals(suffix: &str) -> Option<&'static [u8]> {
+    let intervals: &[u8] = match suffix {
+        "" | "maj" | "M" => &[4, 7],
+        "5" => &[7],
+        "m" | "min" => &[3, 7],
+        "dim" => &[3, 6],
+        "aug" => &[4, 8],
+        "sus2" => &[2, 7],
+        "sus4" | "sus" => &[5, 7],
+        // `maj6`, `dom7` and `dom9` spell the chords whose usual symbols
+        // (C6, G7, C9) read as a note and an octave in a script
+        "6" | "maj6" => &[4, 7, 9],
+        "m6" => &[3, 7, 9],
+        "7" | "dom7" => &[4, 7, 10],
+        "maj7" | "M7" => &[4, 7, 11],
+        "m7" | "min7" => &[3, 7, 10],
+        "mMaj7" => &[3, 7, 11],
+        "m7b5" => &[3, 6, 10],
+        "dim7" => &[3, 6, 9],
+        "aug7" => &[4, 8, 10],
+        "maj7#5" => &[4, 8, 11],
+        "7sus2" => &[2, 7, 10],
+        "7sus4" => &[5, 7, 10],
+        "add9" => &[4, 7, 14],
+        "madd9" => &[3, 7, 14],
+        "9" | "dom9" => &[4, 7, 10, 14],
+        "maj9" => &[4, 7, 11, 14],
+        "m9" => &[3, 7, 10, 14],
+        _ => return None,
+    };
+    Some(intervals)
+}
+
+/// Chord symbol suffix for a base quality plus tensions (intervals above
+/// the root), or None when the tensions don't fit the base
+fn extended_suffix(base: &[u8], tensions: &[u8]) -> Option<String> {
+    let suffix = match base {
+        // Sevenths with the fifth left out, so it can be altered
+        [3, 10] => "m7",
+        [4, 10] => "7",
+        [4, 11] => "maj7",
+        // Written as an altered dominant once there are tensions
+        [4, 8, 10] => "7#5",
+        // Extended qualities are rebuilt from their seventh or triad
+        [2, 3, 7] | [2, 4, 7] | [2, 3, 7, 10] | [2, 4, 7, 9] | [2, 4, 7, 10] | [2, 4, 7, 11] => {
+            return None
+        }
+        _ => symbol_suffix(base)?,
+    };
+    let seventh = base.contains(&10) || base.contains(&11);
+    let fifth = base.iter().any(|i| matches!(i, 6..=8));
+    let major_third = base.contains(&4);
+
+    let mut natural = None;
+    let mut added: Vec<(u8, u8, &str)> = Vec::new();
+    for &interval in tensions {
+        match interval {
+            2 if seventh => natural = natural.max(Some(9)),
+            5 if seventh => natural = natural.max(Some(11)),
+            9 if seventh => natural = natural.max(Some(13)),
+            1 => added.push((9, interval, "b9")),
+            2 => added.push((9, interval, "9")),
+            3 if major_third => added.push((9, interval, "#9")),
+            5 => added.push((11, interval, "11")),
+            6 if fifth => added.push((11, interval, "#11")),
+            6 => added.push((5, interval, "b5")),
+            8 if fifth => added.push((13, interval, "b13")),
+            8 => added.push((5, interval, "#5")),
+            _ => return None,
+        }
+    }
+    added.sort();
+
+    let mut symbol = match natural {
+        Some(degree) => suffix.replacen('7', &degree.to_string(), 1),
+        None => suffix.to_string(),
+    };
+    if !seventh && !added.is_empty() {
+        symbol.push_str("add");
+    }
+    for (_, _, name) in added {
+        symbol.push_str(name);
+    }
+    Some(symbol)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -759,6 +1706,23 @@ mod tests {
         assert_eq!(chord.bass(), Some("C".parse().unwrap()));
     }
 
+    #[test]
+    fn test_chord_from_intervals() {
+        let c: Note = "C".parse().unwrap();
+        assert_eq!(Chord::from_intervals(c, &[4, 7]), c_major());
+
+        // C Eb G Bb
+        let c_minor_seventh = Chord::from_intervals(c, &[3, 7, 10]);
+        let pitch_classes: Vec<u8> = c_minor_seventh.notes().map(|n| n.pitch_class()).collect();
+        assert_eq!(pitch_classes, vec![0, 3, 7, 10]);
+        assert!(c_minor_seventh.notes().all(|n| n.octave() == 4));
+
+        // Duplicates (and the root itself) collapse
+        let collapsed = Chord::from_intervals(c, &[0, 4, 4, 7]);
+        assert_eq!(collapsed.len(), 3);
+        assert_eq!(collapsed.bass(), Some(c));
+    }
+
     #[test]
     fn test_chord_from_invalid_notes() {
         let result = Chord::from_note_strings(vec!["C", "X", "G"]);
@@ -933,4 +1897,393 @@ mod tests {
         assert_eq!(c_maj_over_e.root(), Some("C".parse().unwrap()));
         assert_eq!(c_maj_over_e.inversion(), 1);
     }
+
+    #[test]
+    fn test_contrary_motion_widens_outer_interval() {
+        let pitch = |n: &Note| n.octave() as i16 * 12 + n.pitch_class() as i16;
+        let outer_interval = |c: &Chord| {
+            let pitches: Vec<i16> = c.notes_vec().iter().map(pitch).collect();
+            pitches.iter().max().unwrap() - pitches.iter().min().unwrap()
+        };
+
+        let chord = c_major();
+        let moved = chord.clone().contrary(2);
+        assert_eq!(outer_interval(&moved), outer_interval(&chord) + 4);
+
+        // Inner voice (E) is unchanged; outer voices move apart
+        let notes = moved.notes_vec();
+        assert_eq!(notes[0].pitch_class(), 10); // Bb3
+        assert_eq!(notes[0].octave(), 3);
+        assert_eq!(notes[1].pitch_class(), 4); // E4
+        assert_eq!(notes[2].pitch_class(), 9); // A4
+
+        // Single notes are unchanged
+        let single = Chord::from_note_strings(vec!["C"]).unwrap();
+        assert_eq!(single.clone().contrary(3), single);
+    }
+
+    #[test]
+    fn test_alter_dominant() {
+        let g7 = || Chord::from_note_strings(vec!["G", "B", "D", "F"]).unwrap();
+        let names =
+            |c: &Chord| -> Vec<String> { c.notes_vec().iter().map(|n| n.full_name()).collect() };
+
+        // No ninth to replace, so b9 is added an octave above the root
+        assert_eq!(
+            names(&g7().alter("b9").unwrap()),
+            vec!["G4", "B4", "D5", "F5", "Ab5"]
+        );
+        // The natural fifth is raised in place
+        assert_eq!(
+            names(&g7().alter("#5").unwrap()),
+            vec!["G4", "B4", "D#5", "F5"]
+        );
+        // Alterations chain
+        assert_eq!(
+            names(&g7().alter("b9").unwrap().alter("#5").unwrap()),
+            vec!["G4", "B4", "D#5", "F5", "Ab5"]
+        );
+
+        // A natural ninth conflicts with #9 and is replaced
+        let g9 = Chord::from_note_strings(vec!["G", "B", "D", "F", "A"]).unwrap();
+        assert_eq!(
+            names(&g9.alter("#9").unwrap()),
+            vec!["G4", "B4", "D5", "F5", "A#5"]
+        );
+        // Already altered: nothing to do
+        let altered = g7().alter("b5").unwrap();
+        assert_eq!(altered.clone().alter("b5").unwrap(), altered);
+
+        assert!(g7().alter("b7").is_err());
+    }
+
+    #[test]
+    fn test_voices() {
+        let names = |c: &Chord| -> Vec<String> {
+            let mut notes = c.notes_vec();
+            notes.sort_by_key(|n| n.midi_note());
+            notes.iter().map(|n| n.full_name()).collect()
+        };
+
+        // Root, then fifth, each an octave above
+        let five = c_major().voices(5);
+        assert_eq!(names(&five), vec!["C4", "E4", "G4", "C5", "G5"]);
+        assert!(five.analyze().contains("C Major"));
+        assert_eq!(
+            names(&c_major().voices(7)),
+            vec!["C4", "E4", "G4", "C5", "E5", "G5", "C6"]
+        );
+
+        // Fewer voices drop the doublings, then the fifth
+        assert_eq!(names(&five.clone().voices(4)), vec!["C4", "E4", "G4", "C5"]);
+        assert_eq!(names(&five.voices(2)), vec!["C4", "E4"]);
+        assert_eq!(c_major().voices(3), c_major());
+    }
+
+    #[test]
+    fn test_tensions() {
+        let c: Note = "C".parse().unwrap();
+        let summary = |chord: &Chord| -> Vec<String> {
+            chord
+                .tensions(c, ScaleMode::Major)
+                .iter()
+                .map(|t| {
+                    let avoid = if t.avoid { " (avoid)" } else { "" };
+                    format!("{} {}{}", t.name, t.note.full_name(), avoid)
+                })
+                .collect()
+        };
+
+        // The 11 sits a semitone above the major third
+        assert_eq!(summary(&c_major()), vec!["9 D5", "11 F5 (avoid)", "13 A5"]);
+
+        // Dominants take the altered tensions too, whether or not they're in the key
+        let g7 = Chord::from_note_strings(vec!["G", "B", "D", "F"]).unwrap();
+        assert_eq!(
+            summary(&g7),
+            vec![
+                "b9 Ab5",
+                "9 A5",
+                "#9 A#5",
+                "11 C6 (avoid)",
+                "#11 C#6",
+                "b13 Eb6",
+                "13 E6"
+            ]
+        );
+
+        // A minor seventh already has the #9 as its third
+        let dm7 = Chord::from_note_strings(vec!["D", "F", "A", "C"]).unwrap();
+        assert_eq!(summary(&dm7), vec!["9 E5", "11 G5", "13 B5"]);
+    }
+    #[test]
+    fn test_invert_down_lowers_top_voice() {
+        let inverted = c_major().invert_down();
+        let notes = inverted.notes_vec();
+
+        // G4 drops to G3 and becomes the bass: G3 C4 E4
+        assert_eq!(notes[0].pitch_class(), 7);
+        assert_eq!(notes[0].octave(), 3);
+        assert_eq!(notes[1].pitch_class(), 0);
+        assert_eq!(notes[2].pitch_class(), 4);
+        assert_eq!(inverted.bass().unwrap().pitch_class(), 7);
+        assert_eq!(inverted.inversion(), 2);
+
+        // Down-inversions visit the up-inversion voicings in reverse order
+        let bass_classes = |invert: fn(Chord) -> Chord| {
+            let mut chord = c_major();
+            (0..3)
+                .map(|_| {
+                    chord = invert(chord.clone());
+                    chord.bass().unwrap().pitch_class()
+                })
+                .collect::<Vec<u8>>()
+        };
+        assert_eq!(bass_classes(Chord::invert), vec![4, 7, 0]);
+        assert_eq!(bass_classes(Chord::invert_down), vec![7, 4, 0]);
+    }
+
+    #[test]
+    fn test_best_voicing_prefers_close_voicing() {
+        let voicing = |notes: Vec<&str>| Chord::from_note_strings(notes).unwrap();
+        let previous = voicing(vec!["C4", "E4", "G4"]);
+
+        // F major kept around the previous chord vs. clustered an octave higher
+        let close = voicing(vec!["C4", "F4", "A4"]);
+        let clustered = voicing(vec!["F5", "A5", "C6"]);
+        let candidates = vec![clustered.clone(), close.clone()];
+        assert_eq!(previous.best_voicing(&candidates), Some(&close));
+
+        // Smoother pitch classes win over register: C F A moves less than Db F Ab
+        let far_but_smooth = voicing(vec!["C5", "F5", "A5"]);
+        let near_but_rough = voicing(vec!["Db4", "F4", "Ab4"]);
+        let candidates = vec![near_but_rough, far_but_smooth.clone()];
+        assert_eq!(previous.best_voicing(&candidates), Some(&far_but_smooth));
+
+        assert_eq!(previous.best_voicing(&[]), None);
+    }
+
+    #[test]
+    fn test_upper_structure_triads() {
+        let voicing = |notes: Vec<&str>| Chord::from_note_strings(notes).unwrap();
+
+        // Lydian dominant: D major over C7
+        assert_eq!(
+            voicing(vec!["C3", "E3", "Bb3", "D4", "F#4", "A4"]).upper_structure(),
+            "D triad over C7 (9 #11 13)"
+        );
+        // Altered dominant: Ab major over C7 gives #9 and b13
+        assert_eq!(
+            voicing(vec!["C3", "E3", "Bb3", "Ab4", "C5", "Eb5"]).upper_structure(),
+            "Ab triad over C7 (#9 b13)"
+        );
+        // A plain Cmaj7 has no upper structure
+        assert_eq!(
+            voicing(vec!["C4", "E4", "G4", "B4"]).upper_structure(),
+            voicing(vec!["C4", "E4", "G4", "B4"]).analyze()
+        );
+    }
+
+    #[test]
+    fn test_to_symbol() {
+        let symbol = |notes: Vec<&str>| Chord::from_note_strings(notes).unwrap().to_symbol();
+
+        assert_eq!(symbol(vec!["C", "E", "G"]), "C");
+        assert_eq!(symbol(vec!["A", "C", "E"]), "Am");
+        assert_eq!(symbol(vec!["C", "E", "G", "B"]), "Cmaj7");
+        assert_eq!(symbol(vec!["G", "B", "D", "F"]), "G7");
+        assert_eq!(symbol(vec!["F#", "A", "C", "E"]), "F#m7b5");
+        assert_eq!(symbol(vec!["Bb3", "D4", "F4"]), "Bb");
+        // Inversions become slash chords
+        assert_eq!(symbol(vec!["E3", "G3", "C4"]), "C/E");
+        assert_eq!(symbol(vec!["E3", "G3", "Bb3", "C4"]), "C7/E");
+        // Unknown qualities list their notes
+        assert_eq!(symbol(vec!["C", "C#", "F#"]), "C(C# F#)");
+        assert_eq!(Chord::new().to_symbol(), "N.C.");
+    }
+
+    #[test]
+    fn test_from_symbol() {
+        let chord = |symbol: &str| Chord::from_symbol(symbol).unwrap();
+        let spelled = |symbol: &str| {
+            let mut notes = chord(symbol).notes_vec();
+            notes.sort_by_key(|n| n.midi_note());
+            let names: Vec<String> = notes.iter().map(|n| n.to_string()).collect();
+            names.join(" ")
+        };
+
+        assert_eq!(spelled("C"), "C E G");
+        assert_eq!(spelled("Cm"), "C Eb G");
+        assert_eq!(spelled("Bdim"), "B D5 F5");
+        assert_eq!(spelled("Ebaug"), "Eb G B");
+        assert_eq!(spelled("A6"), "A C#5 E5 F#5");
+        assert_eq!(spelled("G7"), "G B D5 F5");
+        assert_eq!(spelled("Fmaj7"), "F A C5 E5");
+        assert_eq!(spelled("Dm7"), "D F A C5");
+        assert_eq!(spelled("Bdim7"), "B D5 F5 Ab5");
+        assert_eq!(spelled("Dsus2"), "D E A");
+        assert_eq!(spelled("Fsus4"), "F Bb C5");
+        assert_eq!(spelled("C9"), "C E G Bb D5");
+        assert_eq!(spelled("Cadd9"), "C E G D5");
+        assert_eq!(spelled("F#m7b5"), "F# A C5 E5");
+        assert_eq!(chord("Am9").to_symbol(), "Am9");
+        // Spellings that can't be read as a note with an octave
+        assert_eq!(chord("Gdom7"), chord("G7"));
+        assert_eq!(chord("Bbdom7"), chord("Bb7"));
+        assert_eq!(chord("Cdom9"), chord("C9"));
+        assert_eq!(chord("Amaj6"), chord("A6"));
+
+        // Slash chords put the bass below the root
+        let slash = chord("C/E");
+        assert_eq!(slash.bass(), Some("E3".parse().unwrap()));
+        assert_eq!(slash.to_symbol(), "C/E");
+
+        // Every symbol reads back the same
+        for symbol in [
+            "C", "F#m", "Bbmaj7", "Ebm7b5", "Gdim7", "A7sus4", "Dmaj9", "E5",
+        ] {
+            assert_eq!(chord(symbol).to_symbol(), symbol);
+        }
+
+        assert!(Chord::from_symbol("Cfoo").is_err());
+        assert!(Chord::from_symbol("H7").is_err());
+        assert!(Chord::from_symbol("C/X").is_err());
+    }
+
+    #[test]
+    fn test_arpeggio() {
+        let names = |chord: &Chord, mode: &str| -> Vec<String> {
+            chord
+                .arpeggio(mode)
+                .unwrap()
+                .iter()
+                .map(|n| n.to_string())
+                .collect()
+        };
+        // Given as a first inversion, still arpeggiated from the lowest note
+        let c7 = Chord::from_notes(
+            ["E", "G", "Bb", "C5"]
+                .iter()
+                .map(|n| n.parse().unwrap())
+                .collect(),
+        );
+        assert_eq!(names(&c7, "up"), ["E", "G", "Bb", "C5"]);
+        assert_eq!(names(&c7, "down"), ["C5", "Bb", "G", "E"]);
+        assert_eq!(names(&c7, "updown"), ["E", "G", "Bb", "C5", "Bb", "G"]);
+        assert_eq!(names(&c7, "downup"), ["C5", "Bb", "G", "E", "G", "Bb"]);
+        assert_eq!(names(&c7, "converge"), ["E", "C5", "G", "Bb"]);
+
+        let c = Chord::from_note_strings(vec!["C", "E", "G"]).unwrap();
+        assert_eq!(names(&c, "updown"), ["C", "E", "G", "E"]);
+        assert_eq!(names(&c, "converge"), ["C", "G", "E"]);
+        let single = Chord::from_note_strings(vec!["C"]).unwrap();
+        assert_eq!(names(&single, "updown"), ["C"]);
+
+        let err = c.arpeggio("sideways").unwrap_err().to_string();
+        assert!(err.contains("unknown arpeggio mode 'sideways'"), "{}", err);
+    }
+
+    #[test]
+    fn test_voicing() {
+        let cmaj7 = Chord::from_note_strings(vec!["C", "E", "G", "B"]).unwrap();
+        let intervals = |chord: &Chord| -> Vec<u8> {
+            let notes = chord.notes_vec();
+            notes
+                .iter()
+                .map(|n| n.midi_note() - notes[0].midi_note())
+                .collect()
+        };
+
+        // Drop-2 Cmaj7: G3 C E B, a fourth, a sixth and a tenth over the bass
+        let drop2 = cmaj7.voicing("drop2").unwrap();
+        assert_eq!(drop2.bass(), Some("G3".parse().unwrap()));
+        assert_eq!(intervals(&drop2), [0, 5, 9, 16]);
+        assert_eq!(drop2.root(), Some("C".parse().unwrap()));
+        assert_eq!(drop2.to_symbol(), "Cmaj7/G");
+
+        let drop3 = cmaj7.voicing("drop3").unwrap();
+        assert_eq!(intervals(&drop3), [0, 8, 15, 19]);
+        assert_eq!(drop3.root(), Some("C".parse().unwrap()));
+        let drop24 = cmaj7.voicing("drop24").unwrap();
+        assert_eq!(intervals(&drop24), [0, 7, 16, 23]);
+        assert_eq!(drop24.root(), Some("C3".parse().unwrap()));
+
+        // Close position packs a spread chord back into one octave
+        assert_eq!(intervals(&drop2.voicing("close").unwrap()), [0, 4, 5, 9]);
+        assert_eq!(intervals(&drop3.voicing("close").unwrap()), [0, 3, 7, 8]);
+
+        let triad = Chord::from_note_strings(vec!["C", "E", "G"]).unwrap();
+        assert_eq!(intervals(&triad.voicing("drop2").unwrap()), [0, 8, 15]);
+        let err = triad.voicing("drop3").unwrap_err().to_string();
+        assert!(err.contains("at least 4 notes"), "{}", err);
+        let err = cmaj7.voicing("spread").unwrap_err().to_string();
+        assert!(err.contains("unknown voicing 'spread'"), "{}", err);
+    }
+
+    #[test]
+    fn test_build() {
+        let c: Note = "C".parse().unwrap();
+        let symbol = |quality: &str| Chord::build(c, quality).unwrap().to_symbol();
+
+        assert_eq!(symbol("Major"), "C");
+        assert_eq!(symbol("minor"), "Cm");
+        assert_eq!(symbol("Diminished"), "Cdim");
+        assert_eq!(symbol("augmented"), "Caug");
+        assert_eq!(symbol("HalfDiminished"), "Cm7b5");
+        assert_eq!(symbol("half-diminished"), "Cm7b5");
+        assert_eq!(symbol("MajorMinor"), "C7");
+        assert_eq!(symbol("dominant"), "C7");
+        assert_eq!(symbol("major_7"), "Cmaj7");
+        assert_eq!(symbol("minor7"), "Cm7");
+        assert_eq!(symbol("diminished7"), "Cdim7");
+        // Symbol suffixes are accepted as they are
+        assert_eq!(symbol("m7b5"), "Cm7b5");
+        assert_eq!(symbol("M7"), "Cmaj7");
+        assert_eq!(symbol("sus4"), "Csus4");
+
+        // The root keeps its octave
+        let low = Chord::build("A2".parse().unwrap(), "m7").unwrap();
+        assert_eq!(low.bass(), Some("A2".parse().unwrap()));
+
+        let err = Chord::build(c, "lydian").unwrap_err().to_string();
+        assert!(err.contains("unknown chord quality 'lydian'"), "{}", err);
+        assert!(err.contains("half_diminished"), "{}", err);
+    }
+
+    #[test]
+    fn test_full_symbol() {
+        let symbol = |notes: Vec<&str>| Chord::from_note_strings(notes).unwrap().full_symbol();
+
+        // Names to_symbol() already knows are kept
+        assert_eq!(symbol(vec!["C", "E", "G"]), "C");
+        assert_eq!(symbol(vec!["E3", "G3", "C4"]), "C/E");
+        assert_eq!(symbol(vec!["C", "E", "G", "Bb", "D"]), "C9");
+
+        // Tensions over a seventh chord
+        assert_eq!(symbol(vec!["C", "E", "G", "B", "F#5"]), "Cmaj7#11");
+        assert_eq!(symbol(vec!["E3", "G3", "B3", "C4", "F#4"]), "Cmaj7#11/E");
+        assert_eq!(symbol(vec!["C", "E", "G", "Bb", "D5", "A5"]), "C13");
+        assert_eq!(symbol(vec!["C", "E", "G", "Bb", "D5", "F#5"]), "C9#11");
+
+        // Altered dominants, including an altered fifth
+        let g7 = Chord::from_note_strings(vec!["G3", "B3", "D4", "F4"]).unwrap();
+        assert_eq!(
+            g7.clone()
+                .alter("b9")
+                .unwrap()
+                .alter("#9")
+                .unwrap()
+                .full_symbol(),
+            "G7b9#9"
+        );
+        assert_eq!(g7.clone().alter("b5").unwrap().full_symbol(), "G7b5");
+        assert_eq!(
+            g7.alter("#5").unwrap().alter("b9").unwrap().full_symbol(),
+            "G7#5b9"
+        );
+
+        // Tensions over a triad are added tones
+        assert_eq!(symbol(vec!["C", "E", "G", "F#5"]), "Cadd#11");
+        assert_eq!(Chord::new().full_symbol(), "N.C.");
+    }
 }
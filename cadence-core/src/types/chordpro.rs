@@ -0,0 +1,133 @@
+//! ChordPro lead-sheet export
+//!
+//! Writes the chords of a pattern as a ChordPro chart: a `{key: ...}`
+//! directive followed by bar lines with a `[chord]` marker for each change.
+
+use crate::types::{Chord, Note, Pattern, PlaybackEvent, Time};
+
+/// Beats in one bar of the chart. Cadence has no time signature, so bars are 4/4.
+pub const BEATS_PER_BAR: i64 = 4;
+
+/// Bars written on each line of the chart
+const BARS_PER_LINE: usize = 4;
+
+/// Render one cycle of `pattern` as a ChordPro chart in `key`.
+///
+/// The cycle is split into bars of [`BEATS_PER_BAR`] beats, and each event is
+/// written as a chord symbol in the bar it starts in. Roots are spelled with
+/// flats in flat keys. Rests become `[N.C.]`, and a bar where nothing new
+/// starts is written as `%`.
+pub fn chordpro(pattern: &Pattern, key: Note) -> String {
+    let flats = uses_flats(key);
+    let bar_length = Time::from_integer(BEATS_PER_BAR);
+    let bar_count = (pattern.beats_per_cycle / bar_length)
+        .ceil()
+        .to_integer()
+        .max(1) as usize;
+
+    let mut bars: Vec<Vec<String>> = vec![Vec::new(); bar_count];
+    let mut previous_was_rest = false;
+    for event in pattern.to_rich_events() {
+        let bar = (event.start_beat / bar_length).floor().to_integer() as usize;
+        let Some(bar) = bars.get_mut(bar) else {
+            continue;
+        };
+        match event_symbol(&event, flats) {
+            Some(symbol) => {
+                bar.push(format!("[{}]", symbol));
+                previous_was_rest = false;
+            }
+            // A run of rests is a single no-chord marker
+            None if previous_was_rest => {}
+            None => {
+                bar.push("[N.C.]".to_string());
+                previous_was_rest = true;
+            }
+        }
+    }
+
+    let bars: Vec<String> = bars
+        .into_iter()
+        .map(|chords| {
+            if chords.is_empty() {
+                "%".to_string()
+            } else {
+                chords.join(" ")
+            }
+        })
+        .collect();
+
+    let mut chart = format!("{{key: {}}}\n", key.respell(!flats).name());
+    for line in bars.chunks(BARS_PER_LINE) {
+        chart.push_str(&format!("| {} |\n", line.join(" | ")));
+    }
+    chart
+}
+
+/// Chord symbol for an event, or `None` for rests and drum hits
+fn event_symbol(event: &PlaybackEvent, flats: bool) -> Option<String> {
+    if event.is_rest || event.notes.is_empty() {
+        return None;
+    }
+    let notes: Vec<Note> = event
+        .notes
+        .iter()
+        .filter_map(|n| n.name.parse::<Note>().ok())
+        .map(|note| note.respell(!flats))
+        .collect();
+    Some(Chord::from_notes(notes).to_symbol())
+}
+
+/// Major keys written with flats: F and every key whose tonic is a flat
+pub(crate) fn uses_flats(key: Note) -> bool {
+    key.pitch_class() == 5 || key.name().ends_with('b')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::time::beats;
+
+    fn chart(notation: &str, key: &str) -> String {
+        chordpro(&Pattern::parse(notation).unwrap(), key.parse().unwrap())
+    }
+
+    #[test]
+    fn test_chordpro_bars() {
+        // Slowed down, each chord fills a bar
+        let pattern = Pattern::parse("[C, E, G] [A, C, E] [C4, F4, A4] [G, B, D, F]")
+            .unwrap()
+            .slow(beats(4));
+        assert_eq!(
+            chordpro(&pattern, "C".parse().unwrap()),
+            "{key: C}\n| [C] | [Am] | [F/C] | [G7] |\n"
+        );
+    }
+
+    #[test]
+    fn test_chordpro_rests_and_held_bars() {
+        let pattern = Pattern::parse("[C, E, G]@2 _ _").unwrap().slow(beats(4));
+        assert_eq!(
+            chordpro(&pattern, "C".parse().unwrap()),
+            "{key: C}\n| [C] | % | [N.C.] | % |\n"
+        );
+        // Consecutive rests collapse
+        assert_eq!(
+            chart("[C, E, G] _ _ [G, B, D]", "G"),
+            "{key: G}\n| [C] [N.C.] [G] |\n"
+        );
+    }
+
+    #[test]
+    fn test_chordpro_spells_for_key() {
+        assert_eq!(chart("[A#, D, F]", "F"), "{key: F}\n| [Bb] |\n");
+        assert_eq!(chart("[Bb, D, F]", "E"), "{key: E}\n| [A#] |\n");
+        assert_eq!(chart("[C, E, G]", "Eb"), "{key: Eb}\n| [C] |\n");
+    }
+
+    #[test]
+    fn test_chordpro_wraps_lines() {
+        let pattern = Pattern::parse("C D E F G").unwrap().slow(beats(5));
+        assert_eq!(chordpro(&pattern, "C".parse().unwrap()).lines().count(), 3);
+    }
+}
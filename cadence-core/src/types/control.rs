@@ -0,0 +1,154 @@
+//! Numeric control patterns for automation lanes (e.g. MIDI CC)
+//!
+//! A control pattern is a sequence of 0-127 values spread evenly over a
+//! cycle, written with the same space-separated style as note patterns:
+//! `"0 32 64 96 127"`. `~` and `_` hold the previous value.
+
+use crate::types::Note;
+use anyhow::{anyhow, Result};
+use std::fmt;
+
+/// Highest value a control step can take (7-bit MIDI data byte)
+pub const CONTROL_MAX: u8 = 127;
+
+/// A cycle of numeric control values (`None` = hold previous value)
+#[derive(Debug, Clone, PartialEq)]
+pub struct ControlPattern {
+    pub values: Vec<Option<u8>>,
+    /// Length of one cycle in beats (matches the default pattern cycle)
+    pub beats_per_cycle: f64,
+}
+
+impl ControlPattern {
+    /// Create a control pattern from raw values, clamping each to 0-127
+    pub fn new(values: Vec<i32>) -> Self {
+        ControlPattern {
+            values: values
+                .into_iter()
+                .map(|v| Some(v.clamp(0, CONTROL_MAX as i32) as u8))
+                .collect(),
+            beats_per_cycle: 4.0,
+        }
+    }
+
+    /// Parse a control pattern string like `"0 32 64 ~ 127"`
+    pub fn parse(notation: &str) -> Result<Self> {
+        let mut values = Vec::new();
+
+        for token in notation.split_whitespace() {
+            if token == "~" || token == "_" {
+                values.push(None);
+                continue;
+            }
+
+            if let Ok(n) = token.parse::<i32>() {
+                values.push(Some(n.clamp(0, CONTROL_MAX as i32) as u8));
+            } else if token.parse::<Note>().is_ok() {
+                return Err(anyhow!(
+                    "Control patterns expect numbers 0-127, found note '{}'",
+                    token
+                ));
+            } else {
+                return Err(anyhow!("Invalid control value '{}'", token));
+            }
+        }
+
+        if values.is_empty() {
+            return Err(anyhow!("Control pattern is empty"));
+        }
+
+        Ok(ControlPattern {
+            values,
+            beats_per_cycle: 4.0,
+        })
+    }
+
+    /// Duration of each step in beats
+    pub fn step_beats(&self) -> f64 {
+        self.beats_per_cycle / self.values.len().max(1) as f64
+    }
+
+    /// Resolved value for a step, walking back through held steps
+    fn resolved(&self, index: usize) -> Option<u8> {
+        let len = self.values.len();
+        (0..len)
+            .map(|back| self.values[(index + len - back) % len])
+            .find_map(|v| v)
+    }
+
+    /// Sample the pattern at a position (in beats from the start of playback).
+    ///
+    /// Returns the step index and the value at that position. With `smooth`,
+    /// the value is linearly interpolated towards the next step's value.
+    pub fn sample(&self, position: f64, smooth: bool) -> Option<(usize, u8)> {
+        if self.values.is_empty() || self.beats_per_cycle <= 0.0 {
+            return None;
+        }
+
+        let cycle_position = position.rem_euclid(self.beats_per_cycle);
+        let step_beats = self.step_beats();
+        let index = ((cycle_position / step_beats).floor() as usize).min(self.values.len() - 1);
+        let current = self.resolved(index)?;
+
+        if !smooth {
+            return Some((index, current));
+        }
+
+        let next = self
+            .resolved((index + 1) % self.values.len())
+            .unwrap_or(current);
+        let fraction = (cycle_position - index as f64 * step_beats) / step_beats;
+        let value = current as f64 + (next as f64 - current as f64) * fraction;
+        Some((index, value.round().clamp(0.0, CONTROL_MAX as f64) as u8))
+    }
+}
+
+impl fmt::Display for ControlPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let parts: Vec<String> = self
+            .values
+            .iter()
+            .map(|v| match v {
+                Some(n) => n.to_string(),
+                None => "~".to_string(),
+            })
+            .collect();
+        write!(f, "\"{}\"", parts.join(" "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_clamps_values() {
+        let p = ControlPattern::parse("0 64 200 -5").unwrap();
+        assert_eq!(p.values, vec![Some(0), Some(64), Some(127), Some(0)]);
+    }
+
+    #[test]
+    fn test_parse_rejects_notes() {
+        let err = ControlPattern::parse("C E G").unwrap_err();
+        assert!(err.to_string().contains("found note 'C'"));
+    }
+
+    #[test]
+    fn test_sample_steps() {
+        let p = ControlPattern::parse("0 64 ~ 127").unwrap();
+        assert_eq!(p.sample(0.0, false), Some((0, 0)));
+        assert_eq!(p.sample(1.5, false), Some((1, 64)));
+        // Held step keeps the previous value
+        assert_eq!(p.sample(2.0, false), Some((2, 64)));
+        // Wraps around the cycle
+        assert_eq!(p.sample(4.0, false), Some((0, 0)));
+    }
+
+    #[test]
+    fn test_sample_smooth_interpolates() {
+        let p = ControlPattern::parse("0 100").unwrap();
+        assert_eq!(p.sample(1.0, true), Some((0, 50)));
+        // Last step ramps back towards the first
+        assert_eq!(p.sample(3.0, true), Some((1, 50)));
+    }
+}
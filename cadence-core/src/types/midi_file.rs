@@ -0,0 +1,368 @@
+//! Standard MIDI File (SMF) export
+//!
+//! Renders patterns into note events and writes them as a type-1 MIDI file:
+//! a conductor track carrying tempo and time signature, followed by one
+//! track per Cadence track. Drums are written on GM channel 10.
+
+use crate::types::pattern::{Pattern, PlaybackEvent};
+use crate::types::time::{to_f64, Time};
+use anyhow::Result;
+use num_rational::Ratio;
+
+/// Resolution of exported files (ticks per quarter-note beat)
+pub const TICKS_PER_BEAT: u16 = 480;
+
+/// General MIDI percussion channel (channel 10, 0-indexed)
+pub const DRUM_CHANNEL: u8 = 9;
+
+/// Channels left for melodic tracks once `DRUM_CHANNEL` is set aside
+pub const MELODIC_CHANNELS: usize = 15;
+
+/// Channel for the `index`-th melodic track (from 0), skipping `DRUM_CHANNEL`,
+/// or `None` past the last of the `MELODIC_CHANNELS`
+pub fn melodic_channel(index: usize) -> Option<u8> {
+    match index {
+        i if i < DRUM_CHANNEL as usize => Some(i as u8),
+        i if i < MELODIC_CHANNELS => Some(i as u8 + 1),
+        _ => None,
+    }
+}
+
+/// A single note in an exported track
+#[derive(Debug, Clone, PartialEq)]
+pub struct MidiFileNote {
+    /// Start time in beats from the beginning of the file
+    pub start_beat: Time,
+    /// Duration in beats
+    pub duration: Time,
+    /// MIDI channel (0-15)
+    pub channel: u8,
+    /// MIDI note number (0-127)
+    pub key: u8,
+    /// Note-on velocity (1-127)
+    pub velocity: u8,
+}
+
+/// One exported track: a name, a channel for melodic notes, and its notes
+#[derive(Debug, Clone, PartialEq)]
+pub struct MidiFileTrack {
+    pub name: String,
+    /// Channel used for melodic notes (drums always use `DRUM_CHANNEL`)
+    pub channel: u8,
+    pub notes: Vec<MidiFileNote>,
+}
+
+impl MidiFileTrack {
+    /// Create an empty track
+    pub fn new(name: impl Into<String>, channel: u8) -> Self {
+        MidiFileTrack {
+            name: name.into(),
+            channel: channel.min(15),
+            notes: Vec::new(),
+        }
+    }
+
+    /// Add one cycle's playback events starting at `offset`.
+    /// Events starting at or after `end` are dropped and durations are clipped to `end`.
+    pub fn add_events(&mut self, offset: Time, events: &[PlaybackEvent], end: Time) {
+        for event in events {
+            let start = offset + event.start_beat;
+            if event.is_rest || start >= end {
+                continue;
+            }
+            let duration = event.duration.min(end - start);
+
+            for note in &event.notes {
+                self.notes.push(MidiFileNote {
+                    start_beat: start,
+                    duration,
+                    channel: self.channel,
+                    key: note.midi,
+                    velocity: note.velocity.clamp(1, 127),
+                });
+            }
+            for drum in &event.drums {
+                self.notes.push(MidiFileNote {
+                    start_beat: start,
+                    duration,
+                    channel: DRUM_CHANNEL,
+                    key: drum.midi_note(),
+                    velocity: event.drum_velocity,
+                });
+            }
+        }
+    }
+
+    /// Render `beats` beats of playback, one cycle at a time.
+    ///
+    /// `pattern_for_cycle` is called with each cycle number, so reactive or
+    /// `every` patterns can produce a different pattern per cycle.
+    pub fn render<F>(&mut self, beats: Time, mut pattern_for_cycle: F) -> Result<()>
+    where
+        F: FnMut(usize, Time) -> Result<Pattern>,
+    {
+        let mut offset: Time = Ratio::from_integer(0);
+        let mut cycle = 0;
+
+        while offset < beats {
+            let pattern = pattern_for_cycle(cycle, offset)?;
+            if pattern.beats_per_cycle <= Ratio::from_integer(0) {
+                break;
+            }
+            self.add_events(offset, &pattern.to_rich_events_for_cycle(cycle), beats);
+            offset += pattern.beats_per_cycle;
+            cycle += 1;
+        }
+
+        Ok(())
+    }
+}
+
+/// A type-1 Standard MIDI File
+#[derive(Debug, Clone, PartialEq)]
+pub struct MidiFile {
+    /// Tempo in BPM
+    pub tempo: f32,
+    /// Beats per bar (time signature numerator, over a quarter-note beat)
+    pub beats_per_bar: u8,
+    pub tracks: Vec<MidiFileTrack>,
+}
+
+impl MidiFile {
+    /// Create an empty file in 4/4 at the given tempo
+    pub fn new(tempo: f32) -> Self {
+        MidiFile {
+            tempo,
+            beats_per_bar: 4,
+            tracks: Vec::new(),
+        }
+    }
+
+    /// Length of `bars` bars in beats
+    pub fn bars_to_beats(&self, bars: usize) -> Time {
+        Ratio::from_integer(bars as i64 * self.beats_per_bar as i64)
+    }
+
+    /// Encode the file as SMF bytes
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+
+        // Header: format 1, conductor track + one per track
+        bytes.extend_from_slice(b"MThd");
+        bytes.extend_from_slice(&6u32.to_be_bytes());
+        bytes.extend_from_slice(&1u16.to_be_bytes());
+        bytes.extend_from_slice(&(self.tracks.len() as u16 + 1).to_be_bytes());
+        bytes.extend_from_slice(&TICKS_PER_BEAT.to_be_bytes());
+
+        write_chunk(&mut bytes, &self.conductor_track());
+        for track in &self.tracks {
+            write_chunk(&mut bytes, &encode_track(track));
+        }
+
+        bytes
+    }
+
+    /// Write the file to disk
+    pub fn write(&self, path: &str) -> Result<()> {
+        std::fs::write(path, self.to_bytes())
+            .map_err(|e| anyhow::anyhow!("Failed to write '{}': {}", path, e))
+    }
+
+    /// Tempo and time signature meta events
+    fn conductor_track(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+        let micros_per_beat = (60_000_000.0 / self.tempo.max(1.0) as f64).round() as u32;
+
+        // Set Tempo: FF 51 03 tttttt
+        write_var_len(&mut data, 0);
+        data.extend_from_slice(&[0xFF, 0x51, 0x03]);
+        data.extend_from_slice(&micros_per_beat.to_be_bytes()[1..]);
+
+        // Time Signature: FF 58 04 nn dd cc bb (denominator as power of 2)
+        write_var_len(&mut data, 0);
+        data.extend_from_slice(&[0xFF, 0x58, 0x04, self.beats_per_bar, 2, 24, 8]);
+
+        end_of_track(&mut data);
+        data
+    }
+}
+
+/// Convert beats to ticks, rounding to the nearest tick
+fn beats_to_ticks(beats: Time) -> u32 {
+    (to_f64(beats) * TICKS_PER_BEAT as f64).round().max(0.0) as u32
+}
+
+fn encode_track(track: &MidiFileTrack) -> Vec<u8> {
+    let mut data = Vec::new();
+
+    // Track Name: FF 03 len text
+    write_var_len(&mut data, 0);
+    data.extend_from_slice(&[0xFF, 0x03]);
+    write_var_len(&mut data, track.name.len() as u32);
+    data.extend_from_slice(track.name.as_bytes());
+
+    // (tick, is_note_on, status, key, velocity); note-offs sort before note-ons
+    let mut messages: Vec<(u32, bool, u8, u8, u8)> = Vec::new();
+    for note in &track.notes {
+        let start = beats_to_ticks(note.start_beat);
+        let end = beats_to_ticks(note.start_beat + note.duration).max(start + 1);
+        let channel = note.channel & 0x0F;
+        messages.push((start, true, 0x90 | channel, note.key & 0x7F, note.velocity));
+        messages.push((end, false, 0x80 | channel, note.key & 0x7F, 0));
+    }
+    messages.sort_by_key(|&(tick, is_on, ..)| (tick, is_on));
+
+    let mut last_tick = 0;
+    for (tick, _, status, key, velocity) in messages {
+        write_var_len(&mut data, tick - last_tick);
+        data.extend_from_slice(&[status, key, velocity]);
+        last_tick = tick;
+    }
+
+    end_of_track(&mut data);
+    data
+}
+
+fn end_of_track(data: &mut Vec<u8>) {
+    write_var_len(data, 0);
+    data.extend_from_slice(&[0xFF, 0x2F, 0x00]);
+}
+
+fn write_chunk(bytes: &mut Vec<u8>, data: &[u8]) {
+    bytes.extend_from_slice(b"MTrk");
+    bytes.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    bytes.extend_from_slice(data);
+}
+
+/// Write a variable-length quantity (7 bits per byte, high bit = continue)
+fn write_var_len(data: &mut Vec<u8>, mut value: u32) {
+    let mut buffer = [0u8; 5];
+    let mut len = 0;
+    loop {
+        buffer[len] = (value & 0x7F) as u8;
+        len += 1;
+        value >>= 7;
+        if value == 0 {
+            break;
+        }
+    }
+    for i in (0..len).rev() {
+        let continue_bit = if i > 0 { 0x80 } else { 0 };
+        data.push(buffer[i] | continue_bit);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::pattern::DEFAULT_VELOCITY;
+
+    fn render(notation: &str, bars: usize) -> MidiFileTrack {
+        let pattern = Pattern::parse(notation).unwrap();
+        let file = MidiFile::new(120.0);
+        let mut track = MidiFileTrack::new("test", 0);
+        track
+            .render(file.bars_to_beats(bars), |_, _| Ok(pattern.clone()))
+            .unwrap();
+        track
+    }
+
+    #[test]
+    fn test_var_len_encoding() {
+        let cases: [(u32, &[u8]); 4] = [
+            (0, &[0x00]),
+            (0x7F, &[0x7F]),
+            (0x80, &[0x81, 0x00]),
+            (0x3FFF, &[0xFF, 0x7F]),
+        ];
+        for (value, expected) in cases {
+            let mut data = Vec::new();
+            write_var_len(&mut data, value);
+            assert_eq!(data, expected, "encoding {}", value);
+        }
+    }
+
+    #[test]
+    fn test_render_pattern_onsets() {
+        let track = render("C E _ G", 2);
+        let onsets: Vec<(f64, u8)> = track
+            .notes
+            .iter()
+            .map(|n| (to_f64(n.start_beat), n.key))
+            .collect();
+        assert_eq!(
+            onsets,
+            vec![
+                (0.0, 60),
+                (1.0, 64),
+                (3.0, 67),
+                (4.0, 60),
+                (5.0, 64),
+                (7.0, 67)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_render_alternation_and_clipping() {
+        let pattern = Pattern::parse("<C D> E").unwrap();
+        let mut track = MidiFileTrack::new("alt", 0);
+        track
+            .render(Ratio::from_integer(7), |_, _| Ok(pattern.clone()))
+            .unwrap();
+        let keys: Vec<u8> = track.notes.iter().map(|n| n.key).collect();
+        assert_eq!(keys, vec![60, 64, 62, 64]);
+        // Last note is clipped to the export length
+        assert_eq!(track.notes[3].duration, Ratio::from_integer(1));
+    }
+
+    #[test]
+    fn test_drums_use_channel_ten() {
+        let track = render("kick snare", 1);
+        assert!(track.notes.iter().all(|n| n.channel == DRUM_CHANNEL));
+        assert_eq!(track.notes[0].key, 36);
+        assert_eq!(track.notes[1].key, 38);
+    }
+
+    #[test]
+    fn test_drums_keep_their_velocity() {
+        let track = render("kick(60) snare", 1);
+        assert_eq!(track.notes[0].velocity, 60);
+        assert_eq!(track.notes[1].velocity, DEFAULT_VELOCITY);
+    }
+
+    #[test]
+    fn test_melodic_channels_skip_drums() {
+        let channels: Vec<u8> = (0..MELODIC_CHANNELS)
+            .map(|i| melodic_channel(i).unwrap())
+            .collect();
+        assert_eq!(
+            channels,
+            vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 10, 11, 12, 13, 14, 15]
+        );
+        assert_eq!(melodic_channel(MELODIC_CHANNELS), None);
+    }
+
+    #[test]
+    fn test_file_layout() {
+        let mut file = MidiFile::new(120.0);
+        file.tracks.push(render("C", 1));
+        let bytes = file.to_bytes();
+
+        assert_eq!(&bytes[0..4], b"MThd");
+        // Format 1, two tracks (conductor + pattern), 480 ticks per beat
+        assert_eq!(&bytes[8..14], &[0, 1, 0, 2, 0x01, 0xE0]);
+
+        // Conductor: tempo 500000us (120 BPM) then 4/4 time signature
+        assert_eq!(&bytes[14..18], b"MTrk");
+        assert_eq!(&bytes[22..29], &[0x00, 0xFF, 0x51, 0x03, 0x07, 0xA1, 0x20]);
+        assert_eq!(&bytes[29..37], &[0x00, 0xFF, 0x58, 0x04, 4, 2, 24, 8]);
+
+        // Pattern track ends with a 4-beat C4 note
+        let tail = &bytes[bytes.len() - 13..];
+        assert_eq!(
+            tail,
+            &[0x00, 0x90, 60, 100, 0x8F, 0x00, 0x80, 60, 0, 0x00, 0xFF, 0x2F, 0x00]
+        );
+    }
+}
@@ -2,20 +2,36 @@
 
 pub mod audio_config;
 pub mod chord;
+pub mod chordpro;
+pub mod control;
 pub mod drum;
+pub mod midi_file;
+pub mod musicxml;
 pub mod note;
 pub mod pattern;
+pub mod rng;
 pub mod roman_numeral;
+pub mod scale;
 pub mod scheduled_event;
 pub mod time;
 pub mod voice_leading;
 
 pub use audio_config::{AdsrParams, QueueMode, Waveform};
-pub use chord::Chord;
+pub use chord::{Chord, Tension};
+pub use chordpro::chordpro;
+pub use control::ControlPattern;
 pub use drum::DrumSound;
+pub use midi_file::{MidiFile, MidiFileTrack};
+pub use musicxml::{musicxml, musicxml_score};
 pub use note::Note;
-pub use pattern::{EveryPattern, NoteInfo, Pattern, PatternStep, PlaybackEvent};
+pub use pattern::{
+    euclidean, morph_amount, morph_events, CompiledPattern, Delay, DelayTime, EveryPattern,
+    NoteInfo, Pattern, PatternChange, PatternStep, PlaybackEvent, ProbabilityPattern,
+    DEFAULT_VELOCITY,
+};
+pub use rng::{probability_seed, SeededRng, DEFAULT_SEED};
 pub use roman_numeral::*;
+pub use scale::ScaleMode;
 pub use scheduled_event::{ScheduledAction, ScheduledEvent};
-pub use time::{beats, from_f64, time, to_f32, to_f64, Arc, Time};
+pub use time::{beats, from_f64, note_value, time, to_f32, to_f64, Arc, Time, NOTE_VALUES};
 pub use voice_leading::VoiceLeading;
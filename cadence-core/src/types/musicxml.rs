@@ -0,0 +1,401 @@
+//! MusicXML export
+//!
+//! Writes patterns as a partwise MusicXML score that notation software such
+//! as MuseScore or Sibelius can open: one part per pattern, pitches spelled
+//! for the key, chords as stacked notes, and rests.
+
+use crate::types::chordpro::uses_flats;
+use crate::types::{Note, Pattern, PlaybackEvent, Time};
+
+/// Divisions of a quarter note: the finest duration written is a 32nd
+pub const DIVISIONS: i64 = 8;
+
+/// Durations that can be written as a single note (in divisions), longest
+/// first, with their note type and whether they're dotted
+const NOTE_VALUES: [(i64, &str, bool); 10] = [
+    (32, "whole", false),
+    (24, "half", true),
+    (16, "half", false),
+    (12, "quarter", true),
+    (8, "quarter", false),
+    (6, "eighth", true),
+    (4, "eighth", false),
+    (3, "16th", true),
+    (2, "16th", false),
+    (1, "32nd", false),
+];
+
+/// Render one cycle of `pattern` as a single-part MusicXML score in `key`.
+pub fn musicxml(pattern: &Pattern, key: Note) -> String {
+    musicxml_score(&[("Music".to_string(), pattern.clone())], key)
+}
+
+/// Render one cycle of each named pattern as a part of a MusicXML score.
+///
+/// Cycles that are a whole number of 4/4 bars are written in 4/4; any other
+/// cycle is one measure with a time signature of its own (3/4, 6/8, ...).
+/// Timing is rounded to the nearest 32nd, so tuplets (such as three notes
+/// in a 4-beat cycle) come out as the nearest notatable durations. Notes
+/// longer than one note value, or crossing a bar line, are tied. Drum hits
+/// are written as rests.
+pub fn musicxml_score(parts: &[(String, Pattern)], key: Note) -> String {
+    let flats = uses_flats(key);
+    let fifths = {
+        let fifths = (key.pitch_class() as i32 * 7) % 12;
+        if flats && fifths > 0 {
+            fifths - 12
+        } else {
+            fifths
+        }
+    };
+
+    let mut xml = String::from(concat!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"no\"?>\n",
+        "<!DOCTYPE score-partwise PUBLIC \"-//Recordare//DTD MusicXML 4.0 Partwise//EN\" ",
+        "\"http://www.musicxml.org/dtds/partwise.dtd\">\n",
+        "<score-partwise version=\"4.0\">\n",
+        "  <part-list>\n",
+    ));
+    for (i, (name, _)) in parts.iter().enumerate() {
+        xml.push_str(&format!(
+            "    <score-part id=\"P{}\"><part-name>{}</part-name></score-part>\n",
+            i + 1,
+            escape(name)
+        ));
+    }
+    xml.push_str("  </part-list>\n");
+
+    for (i, (_, pattern)) in parts.iter().enumerate() {
+        xml.push_str(&format!("  <part id=\"P{}\">\n", i + 1));
+        write_part(&mut xml, pattern, fifths, flats);
+        xml.push_str("  </part>\n");
+    }
+    xml.push_str("</score-partwise>\n");
+    xml
+}
+
+/// A stretch of the cycle (in divisions) holding one event or a rest
+struct Segment {
+    start: i64,
+    end: i64,
+    /// Spelled pitches, empty for a rest
+    pitches: Vec<Note>,
+}
+
+/// Write the measures of one part
+fn write_part(xml: &mut String, pattern: &Pattern, fifths: i32, flats: bool) {
+    let total = to_divisions(pattern.beats_per_cycle).max(1);
+    let measure = if total % (4 * DIVISIONS) == 0 {
+        4 * DIVISIONS
+    } else {
+        total
+    };
+    let segments = segments(&pattern.to_rich_events(), total, flats);
+
+    // Bass clef when most of the part sits below middle C
+    let pitches: Vec<i32> = segments
+        .iter()
+        .flat_map(|s| s.pitches.iter().map(|n| n.midi_note() as i32))
+        .collect();
+    let low = !pitches.is_empty() && pitches.iter().sum::<i32>() < 60 * pitches.len() as i32;
+    let clef = if low { ("F", 4) } else { ("G", 2) };
+
+    let (beats, beat_type) = time_signature(measure);
+    for (number, measure_start) in (0..total).step_by(measure as usize).enumerate() {
+        let measure_end = (measure_start + measure).min(total);
+        xml.push_str(&format!("    <measure number=\"{}\">\n", number + 1));
+        if number == 0 {
+            xml.push_str(&format!(
+                concat!(
+                    "      <attributes>\n",
+                    "        <divisions>{}</divisions>\n",
+                    "        <key><fifths>{}</fifths></key>\n",
+                    "        <time><beats>{}</beats><beat-type>{}</beat-type></time>\n",
+                    "        <clef><sign>{}</sign><line>{}</line></clef>\n",
+                    "      </attributes>\n",
+                ),
+                DIVISIONS, fifths, beats, beat_type, clef.0, clef.1
+            ));
+        }
+        for segment in &segments {
+            let start = segment.start.max(measure_start);
+            let end = segment.end.min(measure_end);
+            if start >= end {
+                continue;
+            }
+            let pieces = split_duration(end - start);
+            for (i, &(duration, note_type, dotted)) in pieces.iter().enumerate() {
+                // Tied into from an earlier piece, or on into a later one
+                let tie_stop = start > segment.start || i > 0;
+                let tie_start = end < segment.end || i + 1 < pieces.len();
+                write_note(
+                    xml,
+                    &segment.pitches,
+                    (duration, note_type, dotted),
+                    (tie_stop, tie_start),
+                );
+            }
+        }
+        xml.push_str("    </measure>\n");
+    }
+}
+
+/// Lay the cycle's events out on the division grid, filling gaps with rests
+fn segments(events: &[PlaybackEvent], total: i64, flats: bool) -> Vec<Segment> {
+    let mut segments: Vec<Segment> = Vec::new();
+    let mut position = 0;
+    for (i, event) in events.iter().enumerate() {
+        let start = to_divisions(event.start_beat).clamp(0, total);
+        let next_start = events
+            .get(i + 1)
+            .map_or(total, |next| to_divisions(next.start_beat));
+        let end = to_divisions(event.start_beat + event.duration)
+            .min(next_start)
+            .min(total);
+        // Rounded onto a note that's already there
+        if start < position || start >= end {
+            continue;
+        }
+        if start > position {
+            segments.push(Segment {
+                start: position,
+                end: start,
+                pitches: Vec::new(),
+            });
+        }
+        let pitches = if event.is_rest {
+            Vec::new()
+        } else {
+            event
+                .notes
+                .iter()
+                .filter_map(|n| n.name.parse::<Note>().ok())
+                .map(|note| note.respell(!flats))
+                .collect()
+        };
+        segments.push(Segment {
+            start,
+            end,
+            pitches,
+        });
+        position = end;
+    }
+    if position < total {
+        segments.push(Segment {
+            start: position,
+            end: total,
+            pitches: Vec::new(),
+        });
+    }
+    segments
+}
+
+/// Write one `<note>` per pitch (a rest when there are none), stacking
+/// chord tones with `<chord/>`
+fn write_note(
+    xml: &mut String,
+    pitches: &[Note],
+    (duration, note_type, dotted): (i64, &str, bool),
+    (tie_stop, tie_start): (bool, bool),
+) {
+    let dot = if dotted { "<dot/>" } else { "" };
+    if pitches.is_empty() {
+        xml.push_str(&format!(
+            "      <note><rest/><duration>{}</duration><type>{}</type>{}</note>\n",
+            duration, note_type, dot
+        ));
+        return;
+    }
+
+    let mut ties = String::new();
+    let mut tied = String::new();
+    if tie_stop {
+        ties.push_str("<tie type=\"stop\"/>");
+        tied.push_str("<tied type=\"stop\"/>");
+    }
+    if tie_start {
+        ties.push_str("<tie type=\"start\"/>");
+        tied.push_str("<tied type=\"start\"/>");
+    }
+    let notations = if tied.is_empty() {
+        String::new()
+    } else {
+        format!("<notations>{}</notations>", tied)
+    };
+
+    for (i, pitch) in pitches.iter().enumerate() {
+        let chord = if i > 0 { "<chord/>" } else { "" };
+        xml.push_str(&format!(
+            "      <note>{}{}<duration>{}</duration>{}<type>{}</type>{}{}</note>\n",
+            chord,
+            pitch_element(*pitch),
+            duration,
+            ties,
+            note_type,
+            dot,
+            notations
+        ));
+    }
+}
+
+/// `<pitch>` with step, alter and octave, from the note's spelling
+fn pitch_element(note: Note) -> String {
+    let name = note.name();
+    let step = &name[..1];
+    let alter = match name.get(1..) {
+        Some("#") => "<alter>1</alter>",
+        Some("b") => "<alter>-1</alter>",
+        _ => "",
+    };
+    format!(
+        "<pitch><step>{}</step>{}<octave>{}</octave></pitch>",
+        step,
+        alter,
+        note.octave()
+    )
+}
+
+/// Break a duration into writable note values, longest first
+fn split_duration(mut duration: i64) -> Vec<(i64, &'static str, bool)> {
+    let mut pieces = Vec::new();
+    while duration > 0 {
+        let value = *NOTE_VALUES
+            .iter()
+            .find(|(length, _, _)| *length <= duration)
+            .expect("a 32nd fits any remaining duration");
+        pieces.push(value);
+        duration -= value.0;
+    }
+    pieces
+}
+
+/// Time signature for a measure of `length` divisions, using the largest
+/// beat unit (quarter, eighth, 16th or 32nd) that divides it
+fn time_signature(length: i64) -> (i64, i64) {
+    [4, 8, 16, 32]
+        .into_iter()
+        .map(|beat_type| (length * beat_type, beat_type))
+        .find(|(units, _)| units % (4 * DIVISIONS) == 0)
+        .map(|(units, beat_type)| (units / (4 * DIVISIONS), beat_type))
+        .unwrap_or((length, 32))
+}
+
+/// A time in beats, rounded to the nearest division
+fn to_divisions(beats: Time) -> i64 {
+    (beats * DIVISIONS).round().to_integer()
+}
+
+/// Escape text for use inside an XML element
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::time::beats;
+
+    fn score(notation: &str, key: &str) -> String {
+        musicxml(&Pattern::parse(notation).unwrap(), key.parse().unwrap())
+    }
+
+    /// The `<note>` lines of a score
+    fn notes(xml: &str) -> Vec<&str> {
+        xml.lines()
+            .map(str::trim)
+            .filter(|line| line.starts_with("<note>"))
+            .collect()
+    }
+
+    #[test]
+    fn test_musicxml_single_measure() {
+        let xml = score("C D E F", "C");
+        assert!(xml.starts_with("<?xml"));
+        assert!(xml.ends_with("</score-partwise>\n"));
+        assert_eq!(xml.matches("<measure ").count(), 1);
+        assert!(xml.contains("<time><beats>4</beats><beat-type>4</beat-type></time>"));
+        assert_eq!(
+            notes(&xml),
+            vec![
+                "<note><pitch><step>C</step><octave>4</octave></pitch><duration>8</duration><type>quarter</type></note>",
+                "<note><pitch><step>D</step><octave>4</octave></pitch><duration>8</duration><type>quarter</type></note>",
+                "<note><pitch><step>E</step><octave>4</octave></pitch><duration>8</duration><type>quarter</type></note>",
+                "<note><pitch><step>F</step><octave>4</octave></pitch><duration>8</duration><type>quarter</type></note>",
+            ]
+        );
+        // Every element that opens is closed
+        for tag in ["score-partwise", "part", "measure", "note", "pitch"] {
+            assert_eq!(
+                xml.matches(&format!("<{}>", tag)).count()
+                    + xml.matches(&format!("<{} ", tag)).count(),
+                xml.matches(&format!("</{}>", tag)).count(),
+                "{}",
+                tag
+            );
+        }
+    }
+
+    #[test]
+    fn test_musicxml_chords_rests_and_spelling() {
+        let xml = score("[C, Eb, G] _ C# _", "F");
+        assert!(xml.contains("<key><fifths>-1</fifths></key>"));
+        let notes = notes(&xml);
+        assert_eq!(notes.len(), 6);
+        assert!(notes[0].starts_with("<note><pitch><step>C</step>"));
+        assert!(notes[1].starts_with("<note><chord/><pitch><step>E</step><alter>-1</alter>"));
+        assert!(notes[2].starts_with("<note><chord/><pitch><step>G</step>"));
+        assert_eq!(
+            notes[3],
+            "<note><rest/><duration>8</duration><type>quarter</type></note>"
+        );
+        // C# is spelled Db in F
+        assert!(notes[4].contains("<step>D</step><alter>-1</alter>"));
+
+        assert!(score("C", "E").contains("<key><fifths>4</fifths></key>"));
+        assert!(score("Bb", "E").contains("<step>A</step><alter>1</alter>"));
+        // Low parts get a bass clef
+        assert!(score("C2 G2", "C").contains("<clef><sign>F</sign><line>4</line></clef>"));
+    }
+
+    #[test]
+    fn test_musicxml_measures_and_ties() {
+        // Two bars of 4/4, with the half note E held across the bar line
+        let pattern = Pattern::parse("C E@2 G").unwrap().slow(beats(2));
+        let xml = musicxml(&pattern, "C".parse().unwrap());
+        assert_eq!(xml.matches("<measure ").count(), 2);
+        let notes = notes(&xml);
+        assert_eq!(notes.len(), 4);
+        assert!(notes[1].contains("<tie type=\"start\"/><type>half</type>"));
+        assert!(notes[2].contains("<tie type=\"stop\"/><type>half</type>"));
+
+        // A three-beat cycle is a bar of 3/4
+        let waltz = Pattern::parse("C E G").unwrap().with_cycle_length(3);
+        let xml = musicxml(&waltz, "C".parse().unwrap());
+        assert!(xml.contains("<time><beats>3</beats><beat-type>4</beat-type></time>"));
+        assert_eq!(xml.matches("<measure ").count(), 1);
+    }
+
+    #[test]
+    fn test_musicxml_tuplets_are_approximated() {
+        // Thirds of a bar round to the 32nd grid: 11 + 10 + 11 divisions,
+        // each split into tied notes that add up to the full bar
+        let xml = score("C E G", "C");
+        let total: i64 = notes(&xml)
+            .iter()
+            .map(|n| {
+                let start = n.find("<duration>").unwrap() + "<duration>".len();
+                let end = n.find("</duration>").unwrap();
+                n[start..end].parse::<i64>().unwrap()
+            })
+            .sum();
+        assert_eq!(total, 32);
+        assert!(xml.contains("<tie type=\"start\"/>"));
+
+        // Euclidean rhythms on an eighth-note grid need no approximation
+        let xml = score("C(3,8)", "C");
+        assert!(!xml.contains("<tie"));
+        assert_eq!(xml.matches("<type>eighth</type>").count(), 8);
+    }
+}
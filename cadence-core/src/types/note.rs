@@ -91,6 +91,14 @@ impl Note {
         })
     }
 
+    /// Create the note for a MIDI note number (0-127), so 60 is C4
+    pub fn from_midi(midi: i32) -> Result<Self> {
+        if !(0..=127).contains(&midi) {
+            return Err(anyhow!("MIDI note must be 0-127, got {}", midi));
+        }
+        Self::new_with_octave((midi % 12) as u8, (midi / 12) as i8 - 1)
+    }
+
     /// Create a note with specific accidental preference
     pub fn with_accidental_preference(pitch_class: u8, sharp: bool) -> Result<Self> {
         if pitch_class > 11 {
@@ -112,6 +120,21 @@ impl Note {
         })
     }
 
+    /// The same pitch written with sharps or flats (naturals are unchanged)
+    pub fn respell(self, sharp: bool) -> Note {
+        let accidental_preference = if Self::is_natural_note(self.pitch_class) {
+            AccidentalPreference::Natural
+        } else if sharp {
+            AccidentalPreference::Sharp
+        } else {
+            AccidentalPreference::Flat
+        };
+        Note {
+            accidental_preference,
+            ..self
+        }
+    }
+
     /// Get the chromatic pitch class (0-11)
     pub fn pitch_class(&self) -> u8 {
         self.pitch_class
@@ -122,6 +145,11 @@ impl Note {
         self.octave
     }
 
+    /// Whether this note is a black key on a piano keyboard (C#, D#, F#, G#, A#)
+    pub fn is_black_key(&self) -> bool {
+        matches!(self.pitch_class, 1 | 3 | 6 | 8 | 10)
+    }
+
     /// Get the note name (without octave)
     pub fn name(&self) -> String {
         match self.pitch_class {
@@ -214,6 +242,15 @@ impl Note {
         }
     }
 
+    /// Limit a transposition by `semitones` so none of `notes` leaves MIDI 0-127
+    pub fn clamp_transpose(notes: &[Note], semitones: i8) -> i8 {
+        let midi = notes.iter().map(|n| n.midi_note() as i16);
+        match (midi.clone().min(), midi.max()) {
+            (Some(lowest), Some(highest)) => (semitones as i16).clamp(-lowest, 127 - highest) as i8,
+            _ => semitones,
+        }
+    }
+
     /// Transpose the note by a number of semitones
     pub fn transpose(self, semitones: i8) -> Note {
         let current_semitone_in_octave = self.pitch_class as i32;
@@ -356,6 +393,27 @@ impl fmt::Display for Note {
         }
     }
 }
+/// Notes serialize as their spelled name with octave ("C#4", "Bb3")
+#[cfg(feature = "serde")]
+impl serde::Serialize for Note {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.full_name())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Note {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        name.parse().map_err(serde::de::Error::custom)
+    }
+}
+
 // Arithmetic operations for transposition
 impl Add<i8> for Note {
     type Output = Note;
@@ -400,6 +458,19 @@ mod tests {
         assert!(invalid.is_err());
     }
 
+    #[test]
+    fn test_is_black_key() {
+        let black: Vec<u8> = (0..12)
+            .filter(|&pc| Note::new(pc).unwrap().is_black_key())
+            .collect();
+        assert_eq!(black, vec![1, 3, 6, 8, 10]);
+
+        assert!("C#".parse::<Note>().unwrap().is_black_key());
+        assert!("Bb".parse::<Note>().unwrap().is_black_key());
+        assert!(!"C".parse::<Note>().unwrap().is_black_key());
+        assert!(!"E".parse::<Note>().unwrap().is_black_key());
+    }
+
     #[test]
     fn test_note_parsing() {
         let c: Note = "C".parse().unwrap();
@@ -503,6 +574,17 @@ mod tests {
         assert_eq!(c4_back.octave, 4);
     }
 
+    #[test]
+    fn test_from_midi() {
+        let middle_c = Note::from_midi(60).unwrap();
+        assert_eq!(middle_c.full_name(), "C4");
+        assert_eq!(Note::from_midi(64).unwrap().full_name(), "E4");
+        assert_eq!(Note::from_midi(0).unwrap().full_name(), "C-1");
+        assert_eq!(Note::from_midi(127).unwrap().midi_note(), 127);
+        assert!(Note::from_midi(128).is_err());
+        assert!(Note::from_midi(-1).is_err());
+    }
+
     #[test]
     fn test_octave_frequencies() {
         let a4: Note = "A4".parse().unwrap();
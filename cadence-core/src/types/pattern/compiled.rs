@@ -0,0 +1,238 @@
+//! Precompiled pattern form for cheap per-beat stepping.
+//!
+//! The dispatcher re-evaluates looping expressions on every tick so that
+//! variable changes are heard immediately. Rebuilding rich events from the
+//! step tree each time is wasteful when nothing changed, so a resolved
+//! pattern is flattened once into timed events for every cycle of its
+//! variation period and only recompiled when its fingerprint changes.
+
+use super::core::Pattern;
+use super::event::PlaybackEvent;
+use super::step::PatternStep;
+use crate::types::time::{to_f64, Time};
+
+/// Longest variation period (in cycles) that is flattened ahead of time.
+/// Patterns whose alternations repeat less often than this are not compiled.
+pub const MAX_COMPILED_CYCLES: usize = 1024;
+
+/// A resolved pattern flattened into timed events
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompiledPattern {
+    /// Events of every cycle in the variation period, back to back
+    events: Vec<PlaybackEvent>,
+    /// Index into `events` where each cycle starts (one extra entry marks the end)
+    cycle_starts: Vec<usize>,
+    /// Length of one cycle in beats
+    pub beats_per_cycle: Time,
+    /// Fingerprint of the pattern this was compiled from
+    pub fingerprint: u64,
+}
+
+impl CompiledPattern {
+    /// Compile a pattern, expanding alternations for each cycle of its period.
+    ///
+    /// Returns `None` for patterns with unresolved variables, random
+    /// choices or shuffled steps, or whose variation period exceeds
+    /// [`MAX_COMPILED_CYCLES`].
+    pub fn compile(pattern: &Pattern) -> Option<Self> {
+        if pattern.has_variables() || pattern.shuffle {
+            return None;
+        }
+
+        let period = variation_period(&pattern.steps)?;
+        let mut events = Vec::new();
+        let mut cycle_starts = Vec::with_capacity(period + 1);
+
+        for cycle in 0..period {
+            cycle_starts.push(events.len());
+            events.extend(pattern.to_rich_events_for_cycle(cycle));
+        }
+        cycle_starts.push(events.len());
+
+        Some(CompiledPattern {
+            events,
+            cycle_starts,
+            beats_per_cycle: pattern.beats_per_cycle,
+            fingerprint: pattern.fingerprint(),
+        })
+    }
+
+    /// Number of cycles before the pattern repeats exactly
+    pub fn period(&self) -> usize {
+        self.cycle_starts.len() - 1
+    }
+
+    /// Events for a given cycle (same as `Pattern::to_rich_events_for_cycle`)
+    pub fn events_for_cycle(&self, cycle: usize) -> &[PlaybackEvent] {
+        let cycle = cycle % self.period();
+        &self.events[self.cycle_starts[cycle]..self.cycle_starts[cycle + 1]]
+    }
+
+    /// Find the event sounding at a position (in beats from pattern start).
+    ///
+    /// Returns `(cycle, event_index, event)`, where `event_index` is relative
+    /// to the start of that cycle.
+    pub fn event_at(&self, position: f64) -> Option<(usize, usize, &PlaybackEvent)> {
+        let beats_per_cycle = to_f64(self.beats_per_cycle);
+        if beats_per_cycle <= 0.0 || position < 0.0 {
+            return None;
+        }
+
+        let cycle = (position / beats_per_cycle).floor() as usize;
+        let cycle_position = position - cycle as f64 * beats_per_cycle;
+        let events = self.events_for_cycle(cycle);
+
+        // Last event that has started by this position
+        let index = events
+            .partition_point(|e| to_f64(e.start_beat) <= cycle_position)
+            .checked_sub(1)?;
+        Some((cycle, index, &events[index]))
+    }
+}
+
+/// Least common multiple of all alternation lengths in the step tree
+fn variation_period(steps: &[PatternStep]) -> Option<usize> {
+    steps
+        .iter()
+        .try_fold(1, |acc, step| lcm(acc, step_period(step)?))
+}
+
+fn step_period(step: &PatternStep) -> Option<usize> {
+    match step {
+        PatternStep::Alternation(choices) => {
+            let inner = variation_period(choices)?;
+            lcm(choices.len().max(1), inner)
+        }
+        PatternStep::Group(steps) | PatternStep::Tuplet(steps) => variation_period(steps),
+        PatternStep::Polyrhythm(subs) => subs
+            .iter()
+            .try_fold(1, |acc, sub| lcm(acc, variation_period(sub)?)),
+        // Lines back up once a whole number of passes fits in whole cycles
+        PatternStep::Polymeter(steps, per_cycle) => {
+            let per_cycle = (*per_cycle).max(1);
+            let drift = lcm(steps.len().max(1), per_cycle)? / per_cycle;
+            lcm(drift, variation_period(steps)?)
+        }
+        // Random choices and drops never settle into a repeating period
+        PatternStep::Choice(_) | PatternStep::Degraded(..) => None,
+        PatternStep::Repeat(inner, _)
+        | PatternStep::Weighted(inner, _)
+        | PatternStep::Euclidean(inner, ..)
+        | PatternStep::Velocity(inner, _)
+        | PatternStep::Speed(inner, _)
+        | PatternStep::Pan(inner, _) => step_period(inner),
+        PatternStep::Note(_)
+        | PatternStep::Chord(_)
+        | PatternStep::Rest
+        | PatternStep::Variable(_)
+        | PatternStep::Drum(_) => Some(1),
+    }
+}
+
+fn lcm(a: usize, b: usize) -> Option<usize> {
+    let gcd = |mut x: usize, mut y: usize| {
+        while y != 0 {
+            (x, y) = (y, x % y);
+        }
+        x
+    };
+    let result = a / gcd(a, b) * b;
+    (result <= MAX_COMPILED_CYCLES).then_some(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::time::beats;
+
+    fn assert_matches_rich_events(notation: &str, cycles: usize) {
+        let pattern = Pattern::parse(notation).unwrap();
+        let compiled = CompiledPattern::compile(&pattern).unwrap();
+        for cycle in 0..cycles {
+            assert_eq!(
+                compiled.events_for_cycle(cycle),
+                pattern.to_rich_events_for_cycle(cycle).as_slice(),
+                "{} differs at cycle {}",
+                notation,
+                cycle
+            );
+        }
+    }
+
+    #[test]
+    fn test_compiled_matches_rich_events() {
+        assert_matches_rich_events("C E G _", 4);
+        assert_matches_rich_events("C@2 [D E] F*2", 4);
+        assert_matches_rich_events("{C D E, F G}", 4);
+        assert_matches_rich_events("C(3,8)", 4);
+        assert_matches_rich_events("kick snare hh hh", 4);
+    }
+
+    #[test]
+    fn test_compiled_alternation_period() {
+        let pattern = Pattern::parse("<C D> <E F G>").unwrap();
+        let compiled = CompiledPattern::compile(&pattern).unwrap();
+        assert_eq!(compiled.period(), 6);
+        assert_matches_rich_events("<C D> <E F G>", 13);
+        assert_matches_rich_events("<C <D E>> G", 9);
+    }
+
+    #[test]
+    fn test_compiled_polymeter_period() {
+        let pattern = Pattern::parse("{C E G}%4 <D F>").unwrap();
+        let compiled = CompiledPattern::compile(&pattern).unwrap();
+        assert_eq!(compiled.period(), 6);
+        assert_matches_rich_events("{C E G}%4 <D F>", 13);
+        assert_matches_rich_events("{C E G D}%2", 5);
+    }
+
+    #[test]
+    fn test_compiled_event_at() {
+        let pattern = Pattern::parse("<C D> E").unwrap();
+        let compiled = CompiledPattern::compile(&pattern).unwrap();
+
+        let (cycle, index, event) = compiled.event_at(0.5).unwrap();
+        assert_eq!((cycle, index), (0, 0));
+        assert_eq!(event.notes[0].name, "C4");
+
+        let (_, index, event) = compiled.event_at(2.0).unwrap();
+        assert_eq!(index, 1);
+        assert_eq!(event.notes[0].name, "E4");
+
+        // Second cycle picks the next alternation element
+        let (cycle, index, event) = compiled.event_at(4.0).unwrap();
+        assert_eq!((cycle, index), (1, 0));
+        assert_eq!(event.notes[0].name, "D4");
+    }
+
+    #[test]
+    fn test_fingerprint_tracks_changes() {
+        let a = Pattern::parse("C E G").unwrap();
+        let b = Pattern::parse("C E G").unwrap();
+        let c = Pattern::parse("C E A").unwrap();
+        assert_eq!(a.fingerprint(), b.fingerprint());
+        assert_ne!(a.fingerprint(), c.fingerprint());
+        assert_ne!(a.fingerprint(), a.clone().fast(beats(2)).fingerprint());
+
+        // Nesting, velocities and audio properties all count
+        let fingerprint = |notation: &str| Pattern::parse(notation).unwrap().fingerprint();
+        assert_ne!(fingerprint("[C E] G"), fingerprint("C [E G]"));
+        assert_ne!(fingerprint("C(100) E"), fingerprint("C(90) E"));
+        assert_ne!(fingerprint("<C E>"), fingerprint("[C E]"));
+        let mut panned = a.clone();
+        panned.pan = Some(0.25);
+        assert_ne!(a.fingerprint(), panned.fingerprint());
+    }
+
+    #[test]
+    fn test_compile_rejects_variables() {
+        let pattern = Pattern::parse("C lead E").unwrap();
+        assert!(CompiledPattern::compile(&pattern).is_none());
+    }
+
+    #[test]
+    fn test_compile_rejects_choices() {
+        let pattern = Pattern::parse("C <D [E|G]>").unwrap();
+        assert!(CompiledPattern::compile(&pattern).is_none());
+    }
+}
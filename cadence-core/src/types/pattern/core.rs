@@ -1,11 +1,11 @@
 //! Core Pattern struct and implementation.
 
-use super::event::PlaybackEvent;
+use super::event::{PlaybackEvent, DEFAULT_VELOCITY};
 use super::parser::{has_non_variable_content, parse_steps};
 use super::step::PatternStep;
 use crate::types::audio_config::Waveform;
 use crate::types::time::{beats, to_f32, Time};
-use crate::types::{Chord, Note};
+use crate::types::{probability_seed, Chord, Note, SeededRng, DEFAULT_SEED};
 use anyhow::{anyhow, Result};
 use num_rational::Ratio;
 use std::fmt;
@@ -29,6 +29,9 @@ fn merge_concurrent_events(events: Vec<PlaybackEvent>) -> Vec<PlaybackEvent> {
             if last.start_beat == event.start_beat {
                 // Merge notes and drums into the existing event
                 last.notes.extend(event.notes);
+                if !event.drums.is_empty() {
+                    last.drum_velocity = event.drum_velocity;
+                }
                 last.drums.extend(event.drums);
                 // If either is not a rest, the merged event is not a rest
                 last.is_rest = last.is_rest && event.is_rest;
@@ -57,11 +60,100 @@ fn merge_concurrent_events(events: Vec<PlaybackEvent>) -> Vec<PlaybackEvent> {
     merged
 }
 
+/// `step` (unwrapped from any existing weight) carrying the given weight
+fn with_weight(step: PatternStep, weight: usize) -> PatternStep {
+    let step = match step {
+        PatternStep::Weighted(inner, _) => *inner,
+        step => step,
+    };
+    match weight {
+        1 => step,
+        w => PatternStep::Weighted(Box::new(step), w),
+    }
+}
+
+/// The steps `step` plays one after another in equal shares of its time,
+/// opening up groups, repeats and weights the way playback does. Other steps
+/// play whole.
+fn timeline_leaves(step: &PatternStep) -> Vec<PatternStep> {
+    match step {
+        PatternStep::Group(steps) | PatternStep::Tuplet(steps) => {
+            steps.iter().flat_map(timeline_leaves).collect()
+        }
+        PatternStep::Repeat(inner, count) => {
+            let leaves = timeline_leaves(inner);
+            (0..*count).flat_map(|_| leaves.clone()).collect()
+        }
+        PatternStep::Weighted(inner, _) => timeline_leaves(inner),
+        step => vec![step.clone()],
+    }
+}
+
+/// The part of a timeline from `start` to `end`, shortening the steps the
+/// cuts fall inside
+fn cut_timeline(
+    timeline: &[(PatternStep, Time)],
+    start: Time,
+    end: Time,
+) -> Vec<(PatternStep, Time)> {
+    let mut window = Vec::new();
+    let mut position = Time::from_integer(0);
+    for (step, length) in timeline {
+        let (from, to) = (position.max(start), (position + length).min(end));
+        if from < to {
+            window.push((step.clone(), to - from));
+        }
+        position += length;
+    }
+    window
+}
+
+/// Steps playing a timeline, with whole-number weights on the finest grid
+/// its lengths need
+fn timeline_steps(timeline: Vec<(PatternStep, Time)>) -> Vec<PatternStep> {
+    let gcd = |mut a: i64, mut b: i64| {
+        while b != 0 {
+            (a, b) = (b, a % b);
+        }
+        a
+    };
+    let grid = timeline.iter().fold(1, |grid, (_, length)| {
+        grid / gcd(grid, *length.denom()) * length.denom()
+    });
+    let weights: Vec<i64> = timeline
+        .iter()
+        .map(|(_, length)| (*length * grid).to_integer())
+        .collect();
+    let common = weights.iter().fold(0, |acc, &w| gcd(acc, w)).max(1);
+    timeline
+        .into_iter()
+        .zip(weights)
+        .map(|((step, _), weight)| with_weight(step, (weight / common) as usize))
+        .collect()
+}
+
+/// The leading steps adding up to `amount` of weight, shortening the last one
+/// if the cut falls inside it
+fn take_weight(steps: &[PatternStep], amount: usize) -> Vec<PatternStep> {
+    let mut taken = Vec::new();
+    let mut remaining = amount;
+    for step in steps {
+        if remaining == 0 {
+            break;
+        }
+        let weight = step.weight().min(remaining);
+        taken.push(with_weight(step.clone(), weight));
+        remaining -= weight;
+    }
+    taken
+}
+
 /// A cycle-based pattern
 ///
 /// All steps in a pattern fit into one cycle (default 4 beats).
 /// More steps = faster per-step, fewer steps = slower per-step.
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Pattern {
     /// Steps in the pattern
     pub steps: Vec<PatternStep>,
@@ -73,6 +165,302 @@ pub struct Pattern {
     pub waveform: Option<Waveform>,
     /// Optional stereo pan (0.0 = left, 0.5 = center, 1.0 = right)
     pub pan: Option<f32>,
+    /// Optional swing, applied when events are generated
+    pub swing: Option<Swing>,
+    /// Optional random nudges to event starts, applied after swing
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub scatter: Option<Scatter>,
+    /// Optional strum for chords, applied after scatter
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub strum: Option<Strum>,
+    /// Optional echo, applied by the synth to everything the track plays
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub delay: Option<Delay>,
+    /// Optional seed for random choices (`[C|E|G]`); a fixed default is
+    /// used when unset, so the same pattern makes the same choices
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub seed: Option<u64>,
+    /// Play the steps in a new random order every cycle, drawn from `seed`
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "std::ops::Not::not")
+    )]
+    pub shuffle: bool,
+}
+
+/// Swing at a note-value grid, or at the pattern's own steps: events on
+/// every other grid position are pushed back towards the next one
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Swing {
+    /// Grid in notes per whole note (4 beats): 8 swings eighths, 16 sixteenths.
+    /// 0 swings the pattern's steps, so the swing follows `fast` and `slow`.
+    pub subdivision: u32,
+    /// How far an off position moves towards the next grid position, in
+    /// percent. When swinging steps it is in percent of triplet swing, which
+    /// moves an off step a third of the way.
+    pub amount: u32,
+}
+
+impl Swing {
+    /// Delay events that start on an off position of the grid. An event that
+    /// ends on one is lengthened to meet the delayed note, so legato stays legato.
+    /// `step` is the length of one of the pattern's steps.
+    fn apply(&self, events: &mut [PlaybackEvent], step: Time) {
+        let (unit, delay) = match self.subdivision {
+            0 if step == Time::from_integer(0) => return,
+            0 => (step, step * self.amount as i64 / 300),
+            subdivision => {
+                let unit = beats(4) / subdivision as i64;
+                (unit, unit * self.amount as i64 / 100)
+            }
+        };
+        let is_off = |time: Time| {
+            let position = time / unit;
+            position.is_integer() && position.to_integer() % 2 == 1
+        };
+        for event in events {
+            let end = event.start_beat + event.duration;
+            if is_off(event.start_beat) {
+                event.start_beat += delay;
+                event.duration -= delay;
+            }
+            if is_off(end) {
+                event.duration += delay;
+            }
+        }
+    }
+}
+
+/// Random timing: each event starts a little early or late
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Scatter {
+    /// Largest nudge, in percent of the event's length
+    pub amount: u32,
+    /// Seed for the nudges
+    pub seed: u64,
+}
+
+impl Scatter {
+    /// Move each event's start by up to `amount` percent of its length either
+    /// way, keeping its end in place. Nothing moves before the cycle starts.
+    fn apply(&self, events: &mut [PlaybackEvent]) {
+        let mut rng = SeededRng::new(self.seed);
+        for event in events {
+            // Draw for every event so one event's nudge doesn't depend on the rests before it
+            let percent = rng.next_offset(self.amount as u8);
+            if event.is_rest {
+                continue;
+            }
+            let end = event.start_beat + event.duration;
+            let start = event.start_beat + event.duration * percent as i64 / 100;
+            event.start_beat = start.max(Time::from_integer(0));
+            event.duration = end - event.start_beat;
+        }
+    }
+}
+
+/// Strummed chords: the notes of a chord start one after another instead
+/// of all at once
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Strum {
+    /// Delay between successive notes, in percent of a beat
+    pub spread: u32,
+    /// Strum from the highest note down instead of from the lowest up
+    pub down: bool,
+}
+
+impl Strum {
+    /// Split every event with more than one note into one event per note,
+    /// each starting `spread` after the one before and all ending together.
+    /// The delay shrinks when needed so the last note starts inside the step.
+    fn apply(&self, events: Vec<PlaybackEvent>) -> Vec<PlaybackEvent> {
+        let spread = Ratio::new(self.spread as i64, 100);
+        let mut strummed = Vec::with_capacity(events.len());
+        for event in events {
+            if event.is_rest || event.notes.len() < 2 || self.spread == 0 {
+                strummed.push(event);
+                continue;
+            }
+
+            let mut notes = event.notes;
+            notes.sort_by_key(|note| note.midi);
+            if self.down {
+                notes.reverse();
+            }
+            let delay = spread.min(event.duration / notes.len() as i64);
+            let mut drums = event.drums;
+            for (i, note) in notes.into_iter().enumerate() {
+                let offset = delay * i as i64;
+                strummed.push(PlaybackEvent {
+                    notes: vec![note],
+                    // Drums hit with the first note
+                    drums: std::mem::take(&mut drums),
+                    drum_velocity: event.drum_velocity,
+                    start_beat: event.start_beat + offset,
+                    duration: event.duration - offset,
+                    is_rest: false,
+                });
+            }
+        }
+        strummed.sort_by_key(|event| event.start_beat);
+        strummed
+    }
+}
+
+/// A feedback delay: the track's sound repeats as echoes that fade by
+/// `feedback` each time
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Delay {
+    /// Time between echoes
+    pub time: DelayTime,
+    /// Level of each echo relative to the one before, in percent (0-95)
+    pub feedback: u32,
+    /// Balance of echoes against the dry sound, in percent: 0 is dry only
+    pub mix: u32,
+}
+
+/// Time between delay echoes: tempo-synced beats or fixed milliseconds
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum DelayTime {
+    Beats(f32),
+    Millis(u32),
+}
+
+impl Delay {
+    /// Highest feedback; at 100% echoes would never fade
+    pub const MAX_FEEDBACK: u32 = 95;
+
+    /// Create a delay, clamping feedback to `MAX_FEEDBACK` and mix to 100
+    pub fn new(time: DelayTime, feedback: u32, mix: u32) -> Self {
+        Delay {
+            time,
+            feedback: feedback.min(Self::MAX_FEEDBACK),
+            mix: mix.min(100),
+        }
+    }
+
+    /// Time between echoes in seconds at the given tempo
+    pub fn seconds(&self, bpm: f32) -> f32 {
+        match self.time {
+            DelayTime::Beats(beats) => beats * 60.0 / bpm.max(1.0),
+            DelayTime::Millis(ms) => ms as f32 / 1000.0,
+        }
+    }
+}
+
+/// One difference between two patterns, found by [`Pattern::diff`]. Step
+/// indices count from 0, like `p[0]`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PatternChange {
+    /// The second pattern plays the first one's steps backwards
+    Reversed,
+    /// Every step moved by the same number of semitones
+    Transposed(i32),
+    /// A step of the second pattern, at its index there, that the first lacks
+    Added(usize, PatternStep),
+    /// A step of the first pattern, at its index there, that the second lacks
+    Removed(usize, PatternStep),
+    /// The step at this index moved by a number of semitones
+    StepTransposed(usize, PatternStep, PatternStep, i32),
+    /// The step at this index became a different one
+    Replaced(usize, PatternStep, PatternStep),
+    /// The number of steps changed
+    Length(usize, usize),
+    /// The cycle length in beats changed
+    CycleLength(Time, Time),
+    /// Steps and timing match, but a sound setting like the envelope differs
+    Settings,
+}
+
+impl fmt::Display for PatternChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let interval = |semitones: i32| {
+            let direction = if semitones > 0 { "up" } else { "down" };
+            match semitones.abs() {
+                1 => format!("{} 1 semitone", direction),
+                n => format!("{} {} semitones", direction, n),
+            }
+        };
+        match self {
+            PatternChange::Reversed => write!(f, "steps reversed"),
+            PatternChange::Transposed(semitones) => {
+                write!(f, "transposed {}", interval(*semitones))
+            }
+            PatternChange::Added(index, step) => write!(f, "step {}: added {}", index, step),
+            PatternChange::Removed(index, step) => write!(f, "step {}: removed {}", index, step),
+            PatternChange::StepTransposed(index, from, to, semitones) => write!(
+                f,
+                "step {}: {} -> {} ({})",
+                index,
+                from,
+                to,
+                interval(*semitones)
+            ),
+            PatternChange::Replaced(index, from, to) => {
+                write!(f, "step {}: {} -> {}", index, from, to)
+            }
+            PatternChange::Length(from, to) => write!(f, "length: {} -> {} steps", from, to),
+            PatternChange::CycleLength(from, to) => write!(f, "cycle: {} -> {} beats", from, to),
+            PatternChange::Settings => write!(f, "sound settings changed"),
+        }
+    }
+}
+
+/// Semitones from `from` to `to` when `to` is `from` moved bodily: a note to a
+/// note, or a chord to a chord with every note moved the same distance
+fn semitones_between(from: &PatternStep, to: &PatternStep) -> Option<i32> {
+    let pitches = |step: &PatternStep| -> Option<Vec<i32>> {
+        match step {
+            PatternStep::Note(note) => Some(vec![note.midi_note() as i32]),
+            PatternStep::Chord(chord) => {
+                let mut pitches: Vec<i32> =
+                    chord.notes().map(|note| note.midi_note() as i32).collect();
+                pitches.sort();
+                Some(pitches)
+            }
+            _ => None,
+        }
+    };
+    let (from, to) = (pitches(from)?, pitches(to)?);
+    if from.is_empty() || from.len() != to.len() {
+        return None;
+    }
+    let semitones = to[0] - from[0];
+    from.iter()
+        .zip(&to)
+        .all(|(a, b)| b - a == semitones)
+        .then_some(semitones)
+}
+
+/// A step-by-step change between `from` at `index` and `to`, if they differ
+fn step_change(index: usize, from: &PatternStep, to: &PatternStep) -> Option<PatternChange> {
+    if from == to {
+        return None;
+    }
+    Some(match semitones_between(from, to) {
+        Some(0) | None => PatternChange::Replaced(index, from.clone(), to.clone()),
+        Some(semitones) => {
+            PatternChange::StepTransposed(index, from.clone(), to.clone(), semitones)
+        }
+    })
 }
 
 impl Pattern {
@@ -84,6 +472,12 @@ impl Pattern {
             envelope: None,
             waveform: None,
             pan: None,
+            swing: None,
+            scatter: None,
+            strum: None,
+            delay: None,
+            seed: None,
+            shuffle: false,
         }
     }
 
@@ -95,6 +489,12 @@ impl Pattern {
             envelope: None,
             waveform: None,
             pan: None,
+            swing: None,
+            scatter: None,
+            strum: None,
+            delay: None,
+            seed: None,
+            shuffle: false,
         }
     }
 
@@ -110,6 +510,29 @@ impl Pattern {
         self
     }
 
+    /// Set the seed for random choices
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Play the steps in a new random order every cycle. The order is drawn
+    /// when events are generated, from the pattern's seed and the cycle.
+    pub fn shuffle(mut self) -> Self {
+        self.shuffle = true;
+        self
+    }
+
+    /// Let every step position play a random step of the pattern, picked
+    /// again every cycle, so steps can repeat or go missing
+    pub fn scramble(mut self) -> Self {
+        if !self.steps.is_empty() {
+            let choice = PatternStep::Choice(self.steps.clone());
+            self.steps = vec![choice; self.steps.len()];
+        }
+        self
+    }
+
     /// Get the duration of each step in beats (exact rational)
     pub fn step_beats(&self) -> Time {
         if self.steps.is_empty() {
@@ -199,10 +622,11 @@ impl Pattern {
                             sub_event_duration
                         };
 
-                        for (notes, drums, is_rest) in step_info_list {
+                        for (notes, drums, drum_velocity, is_rest) in step_info_list {
                             events.push(PlaybackEvent {
                                 notes,
                                 drums,
+                                drum_velocity,
                                 start_beat: sub_current_beat,
                                 duration: event_duration,
                                 is_rest,
@@ -223,10 +647,11 @@ impl Pattern {
                     step_duration
                 };
 
-                for (notes, drums, is_rest) in step_info_list {
+                for (notes, drums, drum_velocity, is_rest) in step_info_list {
                     events.push(PlaybackEvent {
                         notes,
                         drums,
+                        drum_velocity,
                         start_beat: current_beat,
                         duration: event_duration,
                         is_rest,
@@ -237,22 +662,53 @@ impl Pattern {
         }
 
         // Sort events by start_beat to interleave polyrhythm events properly
-        events.sort_by(|a, b| a.start_beat.cmp(&b.start_beat));
+        events.sort_by_key(|a| a.start_beat);
 
         // Merge events at the same start_beat into combined events
-        merge_concurrent_events(events)
+        let mut events = merge_concurrent_events(events);
+        if let Some(swing) = &self.swing {
+            swing.apply(&mut events, self.step_beats());
+        }
+        if let Some(scatter) = &self.scatter {
+            scatter.apply(&mut events);
+            events.sort_by_key(|event| event.start_beat);
+        }
+        match &self.strum {
+            Some(strum) => strum.apply(events),
+            None => events,
+        }
     }
 
     /// Get rich playback events with cycle-aware alternation selection.
     /// This is the method to use for actual playback, where Alternation steps
     /// need to select the correct element based on the current cycle.
     ///
+    /// Random choices (`[C|E|G]`) are picked again for every cycle, from the
+    /// pattern's seed.
+    ///
     /// # Arguments
     /// * `cycle` - The current cycle number (0-indexed), used to select alternation elements
     pub fn to_rich_events_for_cycle(&self, cycle: usize) -> Vec<PlaybackEvent> {
         let mut events = Vec::new();
 
-        let total_weight: i64 = self.steps.iter().map(|s| s.weight() as i64).sum();
+        let mut chosen: Vec<PatternStep>;
+        let steps = if self.shuffle || self.steps.iter().any(|s| s.has_choices()) {
+            let seed = self.seed.unwrap_or(DEFAULT_SEED);
+            let mut rng = SeededRng::new(probability_seed(seed, 0, cycle));
+            chosen = self.steps.iter().map(|s| s.choose(&mut rng)).collect();
+            if self.shuffle {
+                // Fisher-Yates, after the choices so shuffling doesn't change them
+                for i in (1..chosen.len()).rev() {
+                    let j = (rng.next_u64() % (i as u64 + 1)) as usize;
+                    chosen.swap(i, j);
+                }
+            }
+            &chosen
+        } else {
+            &self.steps
+        };
+
+        let total_weight: i64 = steps.iter().map(|s| s.weight() as i64).sum();
         if total_weight == 0 {
             return events;
         }
@@ -260,7 +716,7 @@ impl Pattern {
 
         let mut current_beat: Time = Ratio::from_integer(0);
 
-        for step in &self.steps {
+        for step in steps {
             let step_weight = step.weight() as i64;
             let step_duration = unit_duration * step_weight;
 
@@ -285,10 +741,11 @@ impl Pattern {
                             sub_event_duration
                         };
 
-                        for (notes, drums, is_rest) in step_info_list {
+                        for (notes, drums, drum_velocity, is_rest) in step_info_list {
                             events.push(PlaybackEvent {
                                 notes,
                                 drums,
+                                drum_velocity,
                                 start_beat: sub_current_beat,
                                 duration: event_duration,
                                 is_rest,
@@ -309,10 +766,11 @@ impl Pattern {
                     step_duration
                 };
 
-                for (notes, drums, is_rest) in step_info_list {
+                for (notes, drums, drum_velocity, is_rest) in step_info_list {
                     events.push(PlaybackEvent {
                         notes,
                         drums,
+                        drum_velocity,
                         start_beat: current_beat,
                         duration: event_duration,
                         is_rest,
@@ -323,21 +781,44 @@ impl Pattern {
         }
 
         // Sort events by start_beat to interleave polyrhythm events properly
-        events.sort_by(|a, b| a.start_beat.cmp(&b.start_beat));
+        events.sort_by_key(|a| a.start_beat);
 
         // Merge events at the same start_beat into combined events
-        merge_concurrent_events(events)
+        let mut events = merge_concurrent_events(events);
+        if let Some(swing) = &self.swing {
+            swing.apply(&mut events, self.step_beats());
+        }
+        if let Some(scatter) = &self.scatter {
+            scatter.apply(&mut events);
+            events.sort_by_key(|event| event.start_beat);
+        }
+        match &self.strum {
+            Some(strum) => strum.apply(events),
+            None => events,
+        }
     }
 
-    /// Transform: speed up by factor (plays N times per cycle)
-    pub fn fast(mut self, factor: usize) -> Self {
-        self.beats_per_cycle /= factor as i64;
+    /// Transform: speed up by factor (plays N times per cycle). Fractional
+    /// factors like 3/2 stretch every step by the same ratio. Panics if the
+    /// factor is zero.
+    pub fn fast(mut self, factor: Time) -> Self {
+        self.beats_per_cycle /= factor;
         self
     }
 
     /// Transform: slow down by factor (takes N cycles to complete)
-    pub fn slow(mut self, factor: usize) -> Self {
-        self.beats_per_cycle *= factor as i64;
+    pub fn slow(mut self, factor: Time) -> Self {
+        self.beats_per_cycle *= factor;
+        self
+    }
+
+    /// Transform: make every step last `step` beats, so the cycle becomes
+    /// `step` times the number of steps. A zero-length step panics.
+    pub fn with_step_beats(mut self, step: Time) -> Self {
+        assert!(step > Time::from_integer(0), "step length must be positive");
+        if !self.steps.is_empty() {
+            self.beats_per_cycle = step * self.steps.len() as i64;
+        }
         self
     }
 
@@ -388,6 +869,28 @@ impl Pattern {
         self
     }
 
+    /// Replace every note in the pattern, including chord notes, with
+    /// `f(note)`. Rests and drums are untouched.
+    pub fn map_notes(mut self, f: impl Fn(Note) -> Note) -> Self {
+        self.steps = self.steps.iter().map(|s| s.map_notes(&f)).collect();
+        self
+    }
+
+    /// Move the pattern by whole octaves so its average pitch lands as close
+    /// as possible to `center`. Pitch classes are kept. The average is taken
+    /// over the distinct notes, so a wide melody is centered on its mean
+    /// rather than on its lowest or highest note.
+    pub fn to_register(self, center: Note) -> Self {
+        let notes = self.get_all_notes();
+        if notes.is_empty() {
+            return self;
+        }
+        let pitch = |note: &Note| (note.octave() as i32 + 1) * 12 + note.pitch_class() as i32;
+        let mean = notes.iter().map(pitch).sum::<i32>() as f32 / notes.len() as f32;
+        let octaves = ((pitch(&center) as f32 - mean) / 12.0).round() as i8;
+        self.transpose(octaves.saturating_mul(12))
+    }
+
     // ========================================================================
     // Variable Resolution
     // ========================================================================
@@ -397,10 +900,16 @@ impl Pattern {
         fn step_has_variables(step: &PatternStep) -> bool {
             match step {
                 PatternStep::Variable(_) => true,
-                PatternStep::Group(steps) => steps.iter().any(step_has_variables),
+                PatternStep::Group(steps) | PatternStep::Tuplet(steps) => {
+                    steps.iter().any(step_has_variables)
+                }
                 PatternStep::Repeat(inner, _) => step_has_variables(inner),
-                PatternStep::Weighted(inner, _) => step_has_variables(inner),
-                PatternStep::Alternation(steps) => steps.iter().any(step_has_variables),
+                PatternStep::Weighted(inner, _) | PatternStep::Degraded(inner, ..) => {
+                    step_has_variables(inner)
+                }
+                PatternStep::Alternation(steps)
+                | PatternStep::Polymeter(steps, _)
+                | PatternStep::Choice(steps) => steps.iter().any(step_has_variables),
                 _ => false,
             }
         }
@@ -412,14 +921,18 @@ impl Pattern {
         fn collect_vars(step: &PatternStep, vars: &mut Vec<String>) {
             match step {
                 PatternStep::Variable(name) => vars.push(name.clone()),
-                PatternStep::Group(steps) => {
+                PatternStep::Group(steps) | PatternStep::Tuplet(steps) => {
                     for s in steps {
                         collect_vars(s, vars);
                     }
                 }
                 PatternStep::Repeat(inner, _) => collect_vars(inner, vars),
-                PatternStep::Weighted(inner, _) => collect_vars(inner, vars),
-                PatternStep::Alternation(steps) => {
+                PatternStep::Weighted(inner, _) | PatternStep::Degraded(inner, ..) => {
+                    collect_vars(inner, vars)
+                }
+                PatternStep::Alternation(steps)
+                | PatternStep::Polymeter(steps, _)
+                | PatternStep::Choice(steps) => {
                     for s in steps {
                         collect_vars(s, vars);
                     }
@@ -456,6 +969,13 @@ impl Pattern {
                     }
                     Ok(vec![PatternStep::Group(resolved)])
                 }
+                PatternStep::Tuplet(steps) => {
+                    let mut resolved = Vec::new();
+                    for s in steps {
+                        resolved.extend(resolve_step(s, lookup)?);
+                    }
+                    Ok(vec![PatternStep::Tuplet(resolved)])
+                }
                 PatternStep::Repeat(inner, count) => {
                     let resolved_inner = resolve_step(inner, lookup)?;
                     if resolved_inner.len() == 1 {
@@ -486,6 +1006,19 @@ impl Pattern {
                         )])
                     }
                 }
+                // A variable of several steps is dropped or kept as a whole
+                PatternStep::Degraded(inner, percent, keep) => {
+                    let mut resolved_inner = resolve_step(inner, lookup)?;
+                    let inner = match resolved_inner.len() {
+                        1 => resolved_inner.remove(0),
+                        _ => PatternStep::Group(resolved_inner),
+                    };
+                    Ok(vec![PatternStep::Degraded(
+                        Box::new(inner),
+                        *percent,
+                        *keep,
+                    )])
+                }
                 PatternStep::Alternation(steps) => {
                     let mut resolved = Vec::new();
                     for s in steps {
@@ -493,6 +1026,25 @@ impl Pattern {
                     }
                     Ok(vec![PatternStep::Alternation(resolved)])
                 }
+                PatternStep::Polymeter(steps, per_cycle) => {
+                    let mut resolved = Vec::new();
+                    for s in steps {
+                        resolved.extend(resolve_step(s, lookup)?);
+                    }
+                    Ok(vec![PatternStep::Polymeter(resolved, *per_cycle)])
+                }
+                // Each option stays one option, even if its variable holds several steps
+                PatternStep::Choice(options) => {
+                    let mut resolved = Vec::new();
+                    for option in options {
+                        let mut steps = resolve_step(option, lookup)?;
+                        resolved.push(match steps.len() {
+                            1 => steps.remove(0),
+                            _ => PatternStep::Group(steps),
+                        });
+                    }
+                    Ok(vec![PatternStep::Choice(resolved)])
+                }
                 // Non-variable steps pass through unchanged
                 other => Ok(vec![other.clone()]),
             }
@@ -509,6 +1061,12 @@ impl Pattern {
             envelope: self.envelope,
             waveform: self.waveform,
             pan: self.pan,
+            swing: self.swing,
+            scatter: self.scatter,
+            strum: self.strum,
+            delay: self.delay,
+            seed: self.seed,
+            shuffle: self.shuffle,
         })
     }
 
@@ -574,6 +1132,12 @@ impl Pattern {
             envelope: Some((0.01, 0.1, 0.7, 0.3)),
             waveform: None,
             pan: None,
+            swing: None,
+            scatter: None,
+            strum: None,
+            delay: None,
+            seed: None,
+            shuffle: false,
         }
     }
 
@@ -608,6 +1172,63 @@ impl Pattern {
         self
     }
 
+    /// Replace every chord with its notes played one after another in the
+    /// chord's step (see [`Chord::arpeggio`] for the modes). Other steps are
+    /// left as they are.
+    pub fn arpeggiate(mut self, mode: &str) -> Result<Self> {
+        fn arpeggiate_step(step: PatternStep, mode: &str) -> Result<PatternStep> {
+            let all = |steps: Vec<PatternStep>| -> Result<Vec<PatternStep>> {
+                steps
+                    .into_iter()
+                    .map(|s| arpeggiate_step(s, mode))
+                    .collect()
+            };
+            Ok(match step {
+                PatternStep::Chord(chord) => PatternStep::Group(
+                    chord
+                        .arpeggio(mode)?
+                        .into_iter()
+                        .map(PatternStep::Note)
+                        .collect(),
+                ),
+                PatternStep::Group(steps) => PatternStep::Group(all(steps)?),
+                PatternStep::Tuplet(steps) => PatternStep::Tuplet(all(steps)?),
+                PatternStep::Alternation(steps) => PatternStep::Alternation(all(steps)?),
+                PatternStep::Choice(options) => PatternStep::Choice(all(options)?),
+                PatternStep::Polymeter(steps, per_cycle) => {
+                    PatternStep::Polymeter(all(steps)?, per_cycle)
+                }
+                PatternStep::Repeat(inner, count) => {
+                    PatternStep::Repeat(Box::new(arpeggiate_step(*inner, mode)?), count)
+                }
+                PatternStep::Weighted(inner, weight) => {
+                    PatternStep::Weighted(Box::new(arpeggiate_step(*inner, mode)?), weight)
+                }
+                PatternStep::Velocity(inner, vel) => {
+                    PatternStep::Velocity(Box::new(arpeggiate_step(*inner, mode)?), vel)
+                }
+                PatternStep::Speed(inner, speed) => {
+                    PatternStep::Speed(Box::new(arpeggiate_step(*inner, mode)?), speed)
+                }
+                PatternStep::Pan(inner, pan) => {
+                    PatternStep::Pan(Box::new(arpeggiate_step(*inner, mode)?), pan)
+                }
+                PatternStep::Degraded(inner, percent, keep) => {
+                    PatternStep::Degraded(Box::new(arpeggiate_step(*inner, mode)?), percent, keep)
+                }
+                // Euclidean pulses and polyrhythm layers play one event per slot
+                other => other,
+            })
+        }
+
+        self.steps = self
+            .steps
+            .into_iter()
+            .map(|step| arpeggiate_step(step, mode))
+            .collect::<Result<_>>()?;
+        Ok(self)
+    }
+
     /// Optimize voice leading for this pattern.
     /// Only works on chord-only patterns.
     pub fn optimize_voice_leading(self) -> Self {
@@ -776,7 +1397,7 @@ impl Pattern {
                         notes.insert(*note);
                     }
                 }
-                PatternStep::Group(steps) => {
+                PatternStep::Group(steps) | PatternStep::Tuplet(steps) => {
                     for s in steps {
                         collect_notes(s, notes);
                     }
@@ -788,12 +1409,14 @@ impl Pattern {
                 PatternStep::Variable(_) => {} // Variables don't contribute notes until resolved
                 PatternStep::Drum(_) => {}     // Drums don't contribute melodic notes
                 PatternStep::Weighted(inner, _) => collect_notes(inner, notes), // Delegate to inner
-                PatternStep::Alternation(steps) => {
+                PatternStep::Alternation(steps)
+                | PatternStep::Polymeter(steps, _)
+                | PatternStep::Choice(steps) => {
                     for s in steps {
                         collect_notes(s, notes);
                     }
                 }
-                PatternStep::Euclidean(inner, _, _) => collect_notes(inner, notes),
+                PatternStep::Euclidean(inner, ..) => collect_notes(inner, notes),
                 PatternStep::Polyrhythm(sub_patterns) => {
                     for sub in sub_patterns {
                         for s in sub {
@@ -801,7 +1424,10 @@ impl Pattern {
                         }
                     }
                 }
-                PatternStep::Velocity(inner, _) => collect_notes(inner, notes),
+                PatternStep::Velocity(inner, _)
+                | PatternStep::Speed(inner, _)
+                | PatternStep::Pan(inner, _)
+                | PatternStep::Degraded(inner, ..) => collect_notes(inner, notes),
             }
         }
 
@@ -822,6 +1448,43 @@ impl Pattern {
         self.steps.is_empty()
     }
 
+    /// Hash of the pattern's steps, timing and audio properties, walked
+    /// field by field. Used to detect when a reactive pattern actually
+    /// changed and needs recompiling. Only comparable within one run: the
+    /// hasher isn't stable across builds.
+    pub fn fingerprint(&self) -> u64 {
+        use std::collections::hash_map::DefaultHasher;
+        use std::hash::{Hash, Hasher};
+
+        let mut hasher = DefaultHasher::new();
+        self.steps.len().hash(&mut hasher);
+        for step in &self.steps {
+            step.hash_structure(&mut hasher);
+        }
+        self.beats_per_cycle.hash(&mut hasher);
+        // Floats are hashed by their bits
+        self.envelope
+            .map(|(a, d, s, r)| [a, d, s, r].map(f32::to_bits))
+            .hash(&mut hasher);
+        self.waveform.hash(&mut hasher);
+        self.pan.map(f32::to_bits).hash(&mut hasher);
+        self.swing.hash(&mut hasher);
+        self.scatter.hash(&mut hasher);
+        self.strum.hash(&mut hasher);
+        self.delay
+            .map(|delay| {
+                let time = match delay.time {
+                    DelayTime::Beats(beats) => (0, beats.to_bits()),
+                    DelayTime::Millis(ms) => (1, ms),
+                };
+                (time, delay.feedback, delay.mix)
+            })
+            .hash(&mut hasher);
+        self.seed.hash(&mut hasher);
+        self.shuffle.hash(&mut hasher);
+        hasher.finish()
+    }
+
     /// Reverse the order of steps in the pattern (retrograde)
     pub fn retrograde(mut self) -> Self {
         self.steps.reverse();
@@ -865,6 +1528,122 @@ impl Pattern {
         self
     }
 
+    /// Resolve a step index, counting from the end when negative.
+    /// `len` is the number of valid positions.
+    fn step_index(index: i32, len: usize) -> Result<usize> {
+        let resolved = if index < 0 {
+            len as i64 + index as i64
+        } else {
+            index as i64
+        };
+        if resolved < 0 || resolved >= len as i64 {
+            return Err(anyhow!(
+                "Index {} out of bounds for pattern with {} steps",
+                index,
+                len
+            ));
+        }
+        Ok(resolved as usize)
+    }
+
+    /// Replace the step at `index` (negative counts from the end)
+    pub fn replace_step(mut self, index: i32, step: PatternStep) -> Result<Self> {
+        let i = Self::step_index(index, self.steps.len())?;
+        self.steps[i] = step;
+        Ok(self)
+    }
+
+    /// Insert a step so it ends up at `index`. Negative indices count from the
+    /// end, so -1 appends.
+    pub fn insert_step(mut self, index: i32, step: PatternStep) -> Result<Self> {
+        let i = Self::step_index(index, self.steps.len() + 1)?;
+        self.steps.insert(i, step);
+        Ok(self)
+    }
+
+    /// Remove the step at `index` (negative counts from the end)
+    pub fn remove_step(mut self, index: i32) -> Result<Self> {
+        let i = Self::step_index(index, self.steps.len())?;
+        self.steps.remove(i);
+        Ok(self)
+    }
+
+    /// Randomly replace steps with rests. Each sounding step is dropped with
+    /// a chance of `percent` (0-100), decided again for every cycle when
+    /// events are generated, from the pattern's seed. Dropped steps keep
+    /// their weight so the rhythm doesn't shift.
+    pub fn degrade(self, percent: u8) -> Self {
+        self.degrade_steps(percent, false)
+    }
+
+    /// The complement of `degrade`: each sounding step is kept with a chance
+    /// of `percent`. With the same percent and seed it drops exactly the
+    /// steps `degrade` keeps.
+    pub fn undegrade(self, percent: u8) -> Self {
+        self.degrade_steps(percent, true)
+    }
+
+    /// Shared by `degrade` and `undegrade`: wraps every sounding step in a
+    /// [`PatternStep::Degraded`], inside its weight
+    fn degrade_steps(mut self, percent: u8, keep: bool) -> Self {
+        let percent = percent.min(100);
+        let degraded = |inner: PatternStep| PatternStep::Degraded(Box::new(inner), percent, keep);
+        for step in self.steps.iter_mut() {
+            *step = match std::mem::replace(step, PatternStep::Rest) {
+                PatternStep::Rest => PatternStep::Rest,
+                PatternStep::Weighted(inner, w) => {
+                    PatternStep::Weighted(Box::new(degraded(*inner)), w)
+                }
+                other => degraded(other),
+            };
+        }
+        self
+    }
+
+    /// Replace steps with `transform(step)`, each with probability `amount`
+    /// (0.0 to 1.0); the choices are fixed by `seed`. Rests are left alone.
+    /// A transform can return a group, which then plays inside the step.
+    pub fn some_steps(
+        mut self,
+        amount: f64,
+        seed: u64,
+        mut transform: impl FnMut(&PatternStep) -> Result<PatternStep>,
+    ) -> Result<Self> {
+        let mut rng = SeededRng::new(seed);
+        for step in self.steps.iter_mut() {
+            // Draw for every step so a step's fate doesn't depend on the ones before it
+            if rng.next_f64() >= amount || *step == PatternStep::Rest {
+                continue;
+            }
+            *step = transform(step)?;
+        }
+        Ok(self)
+    }
+
+    /// Transpose the whole pattern by a random number of semitones between
+    /// `-max` and `max`, fixed by `seed`. A `max` of 0 leaves it unchanged.
+    /// The amount is limited so no note leaves MIDI 0-127.
+    pub fn rand_transpose(self, max: u8, seed: u64) -> Self {
+        let semitones = SeededRng::new(seed).next_offset(max);
+        let semitones = Note::clamp_transpose(&self.get_all_notes(), semitones);
+        self.transpose(semitones)
+    }
+
+    /// Progressive reveal for a given cycle: only the first `cycle + 1` steps sound,
+    /// the rest become rests (keeping their weight so the rhythm doesn't shift).
+    /// Once every step is revealed the full pattern plays.
+    pub fn scan(mut self, cycle: usize) -> Self {
+        for step in self.steps.iter_mut().skip(cycle.saturating_add(1)) {
+            *step = match step {
+                PatternStep::Weighted(_, w) => {
+                    PatternStep::Weighted(Box::new(PatternStep::Rest), *w)
+                }
+                _ => PatternStep::Rest,
+            };
+        }
+        self
+    }
+
     /// Create a palindrome: pattern followed by its reverse
     pub fn palindrome(mut self) -> Self {
         let reversed: Vec<PatternStep> = self.steps.iter().rev().cloned().collect();
@@ -889,6 +1668,220 @@ impl Pattern {
         self
     }
 
+    /// Shift every step onto the off-beat by putting a rest of the same weight
+    /// before it. The cycle length is unchanged, so each step gets half its slot:
+    /// "C E" becomes "_ C _ E".
+    pub fn offbeat(mut self) -> Self {
+        let mut new_steps = Vec::with_capacity(self.steps.len() * 2);
+        for step in self.steps {
+            let rest = match step.weight() {
+                1 => PatternStep::Rest,
+                w => PatternStep::Weighted(Box::new(PatternStep::Rest), w),
+            };
+            new_steps.push(rest);
+            new_steps.push(step);
+        }
+        self.steps = new_steps;
+        self
+    }
+
+    /// Repeat the first `fraction` of the cycle until it fills the whole cycle:
+    /// "C E G D" lingered at 1/2 becomes "C E C E". A step the cut falls inside
+    /// is shortened. `fraction` must be in (0, 1]; anything else is a no-op.
+    pub fn linger(mut self, fraction: Time) -> Self {
+        let Some((head, total)) = self.head(fraction) else {
+            return self;
+        };
+        let head_weight: usize = head.iter().map(PatternStep::weight).sum();
+        let mut new_steps = Vec::new();
+        let mut remaining = total;
+        while remaining > 0 {
+            let part = remaining.min(head_weight);
+            new_steps.extend(take_weight(&head, part));
+            remaining -= part;
+        }
+        self.steps = new_steps;
+        self
+    }
+
+    /// Play only the first `fraction` of the cycle and rest for the remainder:
+    /// "C E G D" truncated at 1/2 becomes "C E _@2". A step the cut falls inside
+    /// is shortened. `fraction` must be in (0, 1]; anything else is a no-op.
+    pub fn trunc(mut self, fraction: Time) -> Self {
+        let Some((mut head, total)) = self.head(fraction) else {
+            return self;
+        };
+        let head_weight: usize = head.iter().map(PatternStep::weight).sum();
+        head.push(with_weight(PatternStep::Rest, total - head_weight));
+        self.steps = head;
+        self
+    }
+
+    /// Play the part of the cycle from `start` to `end` (fractions of the cycle),
+    /// stretched to fill the whole cycle: "C D E F" zoomed to 0..1/2 becomes
+    /// "C D". Groups and repeats are opened up so the window can cut between
+    /// their notes; a step the window cuts through is shortened. Anything but
+    /// 0 <= start < end <= 1 is a no-op.
+    pub fn zoom(mut self, start: Time, end: Time) -> Self {
+        if self.steps.is_empty() || start < Time::from_integer(0) || end > beats(1) || start >= end
+        {
+            return self;
+        }
+        self.steps = timeline_steps(cut_timeline(&self.timeline(), start, end));
+        self
+    }
+
+    /// TidalCycles' `iter`: split the cycle into `n` equal sections and start
+    /// `cycle % n` sections later, so each cycle begins one section on from
+    /// the last and the pattern comes back round after `n` cycles. A section
+    /// boundary inside a group or step cuts it as in `zoom`.
+    pub fn iter(mut self, n: usize, cycle: usize) -> Self {
+        if self.steps.is_empty() || n == 0 || cycle.is_multiple_of(n) {
+            return self;
+        }
+        let shift = Time::new((cycle % n) as i64, n as i64);
+
+        // On a step boundary the steps just rotate, keeping groups whole
+        let total: usize = self.steps.iter().map(PatternStep::weight).sum();
+        let mut position = 0;
+        for (index, step) in self.steps.iter().enumerate() {
+            if Time::new(position as i64, total as i64) == shift {
+                self.steps.rotate_left(index);
+                return self;
+            }
+            position += step.weight();
+        }
+
+        let timeline = self.timeline();
+        let mut rotated = cut_timeline(&timeline, shift, beats(1));
+        rotated.extend(cut_timeline(&timeline, Time::from_integer(0), shift));
+        self.steps = timeline_steps(rotated);
+        self
+    }
+
+    /// Every playable step with its share of the cycle, in order
+    fn timeline(&self) -> Vec<(PatternStep, Time)> {
+        let total: usize = self.steps.iter().map(PatternStep::weight).sum();
+        let mut timeline = Vec::new();
+        for step in &self.steps {
+            let share = Time::new(step.weight() as i64, total as i64);
+            let leaves = match timeline_leaves(step) {
+                // A step that plays nothing, like C*0, is silent for its share
+                leaves if leaves.is_empty() => vec![PatternStep::Rest],
+                leaves => leaves,
+            };
+            let length = share / leaves.len() as i64;
+            timeline.extend(leaves.into_iter().map(|leaf| (leaf, length)));
+        }
+        timeline
+    }
+
+    /// The steps covering the first `fraction` of the cycle, along with the total
+    /// weight they're measured against. Weights are scaled up so the cut lands on
+    /// a whole weight. `None` when there is nothing to cut.
+    fn head(&self, fraction: Time) -> Option<(Vec<PatternStep>, usize)> {
+        if self.steps.is_empty() || fraction <= Time::from_integer(0) || fraction >= beats(1) {
+            return None;
+        }
+        let total: usize = self.steps.iter().map(PatternStep::weight).sum();
+        let cut = fraction * Time::from_integer(total as i64);
+        let scale = *cut.denom() as usize;
+        let scaled: Vec<PatternStep> = self
+            .steps
+            .iter()
+            .map(|step| with_weight(step.clone(), step.weight() * scale))
+            .collect();
+        Some((take_weight(&scaled, *cut.numer() as usize), total * scale))
+    }
+
+    /// Swing the pattern at a grid of `subdivision` notes per whole note (8 for
+    /// eighths, 16 for sixteenths), delaying every other grid position by
+    /// `amount` percent of a grid step. Events off the grid are left alone.
+    pub fn swing_n(mut self, subdivision: u32, amount: u32) -> Self {
+        self.swing = Some(Swing {
+            subdivision,
+            amount,
+        });
+        self
+    }
+
+    /// Swing the pattern's own steps: every odd-indexed step is delayed by
+    /// `amount` percent of triplet swing (0 is straight, 100 plays the pair
+    /// of steps as a triplet quarter and eighth). Since it is measured in
+    /// steps, the swing is kept through `fast` and `slow`.
+    pub fn swing(self, amount: u32) -> Self {
+        self.swing_n(0, amount)
+    }
+
+    /// Nudge every event's start randomly by up to `amount` percent of its
+    /// length, early or late, with the nudges fixed by `seed`. An amount of 0
+    /// keeps everything on the grid.
+    pub fn scatter(mut self, amount: u32, seed: u64) -> Self {
+        self.scatter = Some(Scatter { amount, seed });
+        self
+    }
+
+    /// Humanize: every step's velocity moves by up to `velocity` percent
+    /// either way, and every event starts early or late by up to `timing`
+    /// percent of the largest `scatter` nudge (half its length). All of it is
+    /// drawn from `seed` in step order, so the same pattern and seed always
+    /// play the same. Rests and variables are left alone.
+    pub fn humanize(mut self, timing: u32, velocity: u32, seed: u64) -> Self {
+        let mut rng = SeededRng::new(seed);
+        self.steps = self
+            .steps
+            .into_iter()
+            .map(|step| {
+                // Draw for every step so one step's accent doesn't depend on the rests before it
+                let percent = rng.next_offset(velocity.min(100) as u8) as i32;
+                let (inner, base) = match step {
+                    PatternStep::Rest | PatternStep::Variable(_) => return step,
+                    PatternStep::Velocity(inner, base) => (*inner, base),
+                    other => (other, DEFAULT_VELOCITY),
+                };
+                let jittered = base as i32 + base as i32 * percent / 100;
+                PatternStep::Velocity(Box::new(inner), jittered.clamp(1, 127) as u8)
+            })
+            .collect();
+        self.scatter(timing.min(100) / 2, seed)
+    }
+
+    /// Strum chords: their notes start `spread` percent of a beat apart, from
+    /// the lowest note up, or from the highest down when `down` is set.
+    /// Single notes and rests are unaffected.
+    pub fn strum(mut self, spread: u32, down: bool) -> Self {
+        self.strum = Some(Strum { spread, down });
+        self
+    }
+
+    /// Echo everything the pattern plays through a feedback delay
+    pub fn delay(mut self, delay: Delay) -> Self {
+        self.delay = Some(delay);
+        self
+    }
+
+    /// Build an ascending chromatic run of `n` notes starting at `root`.
+    /// A run of length 0 yields an empty pattern; a run that would climb
+    /// past MIDI note 127 is an error.
+    pub fn run(root: Note, n: usize) -> Result<Self> {
+        let room = 128 - root.midi_note() as usize;
+        if n > room {
+            return Err(anyhow!(
+                "A run from {} can have at most {} notes, got {}",
+                root.full_name(),
+                room,
+                n
+            ));
+        }
+        let mut steps = Vec::with_capacity(n);
+        let mut note = root;
+        for _ in 0..n {
+            steps.push(PatternStep::Note(note));
+            note = note + 1;
+        }
+        Ok(Pattern::with_steps(steps))
+    }
+
     /// Concatenate another pattern onto this one
     pub fn concat(mut self, other: Pattern) -> Self {
         self.steps.extend(other.steps);
@@ -942,7 +1935,7 @@ impl Pattern {
                     }
                     // For complex steps, flatten to notes
                     other => {
-                        for (notes_info, _, is_rest) in other.to_step_info() {
+                        for (notes_info, _, _, is_rest) in other.to_step_info() {
                             if !is_rest {
                                 has_rest = false;
                                 for note_info in notes_info {
@@ -970,6 +1963,12 @@ impl Pattern {
         let envelope = patterns[0].envelope;
         let waveform = patterns[0].waveform;
         let pan = patterns[0].pan;
+        let swing = patterns[0].swing;
+        let scatter = patterns[0].scatter;
+        let strum = patterns[0].strum;
+        let delay = patterns[0].delay;
+        let seed = patterns[0].seed;
+        let shuffle = patterns[0].shuffle;
 
         Pattern {
             steps: merged_steps,
@@ -977,7 +1976,170 @@ impl Pattern {
             envelope,
             waveform,
             pan,
+            swing,
+            scatter,
+            strum,
+            delay,
+            seed,
+            shuffle,
+        }
+    }
+
+    /// Play this pattern panned hard left against `transformed` panned hard
+    /// right, like TidalCycles' `jux`. Both layers run side by side over a
+    /// cycle long enough for each to play whole, so a layer sped up with
+    /// `fast(2)` plays twice against one pass of the other. The pan is set
+    /// on each layer's notes; drums keep the track's pan.
+    pub fn jux(self, transformed: Pattern) -> Self {
+        /// Passes allowed for the shorter layer before both are squeezed into one cycle
+        const MAX_PASSES: i64 = 16;
+
+        let layer = |pattern: &Pattern, pan: f32, passes: i64| -> Vec<PatternStep> {
+            let steps = pattern
+                .steps
+                .iter()
+                .map(|step| PatternStep::Pan(Box::new(step.clone()), pan));
+            (0..passes).flat_map(|_| steps.clone()).collect()
+        };
+
+        let (left, right) = (self.beats_per_cycle, transformed.beats_per_cycle);
+        let zero = Time::from_integer(0);
+        let (beats_per_cycle, left_passes, right_passes) = if left > zero && right > zero {
+            // Least common multiple of the two cycle lengths
+            let gcd = |mut a: i64, mut b: i64| {
+                while b != 0 {
+                    (a, b) = (b, a % b);
+                }
+                a
+            };
+            let (a, b) = (left.numer() * right.denom(), right.numer() * left.denom());
+            let cycle = Time::new(a / gcd(a, b) * b, left.denom() * right.denom());
+            match ((cycle / left).to_integer(), (cycle / right).to_integer()) {
+                (l, r) if l <= MAX_PASSES && r <= MAX_PASSES => (cycle, l, r),
+                _ => (left, 1, 1),
+            }
+        } else {
+            (left, 1, 1)
+        };
+
+        let layers: Vec<Vec<PatternStep>> = [
+            layer(&self, 0.0, left_passes),
+            layer(&transformed, 1.0, right_passes),
+        ]
+        .into_iter()
+        .filter(|steps| !steps.is_empty())
+        .collect();
+
+        Pattern {
+            steps: if layers.is_empty() {
+                Vec::new()
+            } else {
+                vec![PatternStep::Polyrhythm(layers)]
+            },
+            beats_per_cycle,
+            ..self
+        }
+    }
+
+    /// What changed from this pattern to `other`, for showing what a transform
+    /// did. Empty when they are equal.
+    ///
+    /// A reversal or a transposition of the whole pattern is reported as one
+    /// change. Otherwise steps are matched up in order, so a step inserted or
+    /// removed in the middle shows as one added or removed step; unmatched
+    /// steps facing each other show as replaced or transposed.
+    pub fn diff(&self, other: &Pattern) -> Vec<PatternChange> {
+        let (from, to) = (&self.steps, &other.steps);
+        let mut changes = Vec::new();
+
+        if from.len() > 1 && from != to && from.iter().rev().eq(to.iter()) {
+            changes.push(PatternChange::Reversed);
+        } else if from.len() == to.len() && from != to {
+            // A whole-pattern transposition moves every pitched step the same way
+            let first = from
+                .iter()
+                .zip(to)
+                .find_map(|(a, b)| semitones_between(a, b).filter(|&semitones| semitones != 0));
+            let moved_by = |semitones: i32| {
+                from.iter()
+                    .zip(to)
+                    .all(|(a, b)| match semitones_between(a, b) {
+                        Some(moved) => moved == semitones,
+                        None => a == b,
+                    })
+            };
+            match first {
+                Some(semitones) if moved_by(semitones) => {
+                    changes.push(PatternChange::Transposed(semitones))
+                }
+                _ => changes.extend(Self::step_changes(from, to)),
+            }
+        } else {
+            changes.extend(Self::step_changes(from, to));
+        }
+
+        if from.len() != to.len() {
+            changes.push(PatternChange::Length(from.len(), to.len()));
+        }
+        if self.beats_per_cycle != other.beats_per_cycle {
+            changes.push(PatternChange::CycleLength(
+                self.beats_per_cycle,
+                other.beats_per_cycle,
+            ));
+        }
+        if changes.is_empty() && self != other {
+            changes.push(PatternChange::Settings);
+        }
+        changes
+    }
+
+    /// Step changes from `from` to `to`, keeping the longest run of steps the
+    /// two share in order and pairing up the rest
+    fn step_changes(from: &[PatternStep], to: &[PatternStep]) -> Vec<PatternChange> {
+        // Longest common subsequence, filled from the ends
+        let mut common = vec![vec![0usize; to.len() + 1]; from.len() + 1];
+        for i in (0..from.len()).rev() {
+            for j in (0..to.len()).rev() {
+                common[i][j] = if from[i] == to[j] {
+                    common[i + 1][j + 1] + 1
+                } else {
+                    common[i + 1][j].max(common[i][j + 1])
+                };
+            }
+        }
+
+        let mut changes = Vec::new();
+        let (mut removed, mut added) = (Vec::new(), Vec::new());
+        // Steps dropped and inserted between two shared steps face each other
+        let mut flush = |removed: &mut Vec<usize>, added: &mut Vec<usize>| {
+            for pair in 0..removed.len().max(added.len()) {
+                changes.extend(match (removed.get(pair), added.get(pair)) {
+                    (Some(&i), Some(&j)) => step_change(i, &from[i], &to[j]),
+                    (Some(&i), None) => Some(PatternChange::Removed(i, from[i].clone())),
+                    (None, Some(&j)) => Some(PatternChange::Added(j, to[j].clone())),
+                    (None, None) => None,
+                });
+            }
+            removed.clear();
+            added.clear();
+        };
+
+        let (mut i, mut j) = (0, 0);
+        while i < from.len() || j < to.len() {
+            if i < from.len() && j < to.len() && from[i] == to[j] {
+                flush(&mut removed, &mut added);
+                i += 1;
+                j += 1;
+            } else if j < to.len() && (i == from.len() || common[i][j + 1] >= common[i + 1][j]) {
+                added.push(j);
+                j += 1;
+            } else {
+                removed.push(i);
+                i += 1;
+            }
         }
+        flush(&mut removed, &mut added);
+        changes
     }
 
     /// Parse from mini-notation string
@@ -1062,7 +2224,8 @@ impl fmt::Display for Pattern {
             if i > 0 {
                 write!(f, " ")?;
             }
-            write!(f, "{}", step)?;
+            // Passes `{:#}` on, to show chord steps as notes
+            fmt::Display::fmt(step, f)?;
         }
         write!(f, "\"")
     }
@@ -1,5 +1,9 @@
 //! Bjorklund algorithm for Euclidean rhythm generation.
 
+use super::core::Pattern;
+use super::step::PatternStep;
+use anyhow::{anyhow, Result};
+
 /// Generate a Euclidean rhythm pattern using Bjorklund's algorithm.
 /// Distributes `pulses` evenly across `steps` slots.
 /// Returns a Vec<bool> where `true` = pulse, `false` = rest.
@@ -56,3 +60,61 @@ pub fn bjorklund(pulses: usize, steps: usize) -> Vec<bool> {
     }
     result
 }
+
+/// [`bjorklund`] starting `rotation` steps later, wrapping around the end.
+/// Negative rotations start earlier.
+pub fn bjorklund_rotated(pulses: usize, steps: usize, rotation: i32) -> Vec<bool> {
+    let mut rhythm = bjorklund(pulses, steps);
+    if steps > 0 {
+        rhythm.rotate_left(rotation.rem_euclid(steps as i32) as usize);
+    }
+    rhythm
+}
+
+/// Build a Euclidean rhythm as a pattern of `steps` slots.
+///
+/// `pulses` onsets are spread with [`bjorklund`], then the rhythm starts
+/// `rotation` steps later (negative values start earlier). With `invert`, the
+/// onsets and rests swap places. Onsets take their sound from `onsets` in turn,
+/// left to right, so a list of chords gives each hit its own chord.
+pub fn euclidean(
+    pulses: usize,
+    steps: usize,
+    rotation: i32,
+    onsets: &[PatternStep],
+    invert: bool,
+) -> Result<Pattern> {
+    if steps == 0 {
+        return Err(anyhow!("Euclidean rhythm needs at least one step"));
+    }
+    if pulses > steps {
+        return Err(anyhow!(
+            "Euclidean rhythm can't fit {} pulses in {} steps",
+            pulses,
+            steps
+        ));
+    }
+    if onsets.is_empty() {
+        return Err(anyhow!(
+            "Euclidean rhythm needs a note or chord for its onsets"
+        ));
+    }
+
+    let mut rhythm = bjorklund_rotated(pulses, steps, rotation);
+    if invert {
+        rhythm.iter_mut().for_each(|hit| *hit = !*hit);
+    }
+
+    let mut sounds = onsets.iter().cycle();
+    let pattern_steps = rhythm
+        .into_iter()
+        .map(|hit| {
+            if hit {
+                sounds.next().cloned().unwrap_or(PatternStep::Rest)
+            } else {
+                PatternStep::Rest
+            }
+        })
+        .collect();
+    Ok(Pattern::with_steps(pattern_steps))
+}
@@ -4,6 +4,9 @@ use super::super::drum::DrumSound;
 use super::super::note::Note;
 use super::super::time::{to_f32, Time};
 
+/// MIDI velocity of notes without an explicit velocity
+pub const DEFAULT_VELOCITY: u8 = 100;
+
 /// Information about a single note, preserving full identity for accurate
 /// MIDI output and visualization without floating-point conversion.
 #[derive(Debug, Clone, PartialEq)]
@@ -19,14 +22,21 @@ pub struct NoteInfo {
     pub pitch_class: u8,
     /// Octave in scientific pitch notation (4 = middle C octave)
     pub octave: i8,
-    /// MIDI velocity (0-127), default 100
+    /// MIDI velocity (0-127), default [`DEFAULT_VELOCITY`]
     pub velocity: u8,
+    /// Stereo pan for this note alone (0.0 = left, 1.0 = right), overriding
+    /// the track's pan. Set on the layers of `jux()`
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub pan: Option<f32>,
 }
 
 impl NoteInfo {
     /// Create NoteInfo from a Note with default velocity
     pub fn from_note(note: &Note) -> Self {
-        Self::from_note_with_velocity(note, 100)
+        Self::from_note_with_velocity(note, DEFAULT_VELOCITY)
     }
 
     /// Create NoteInfo from a Note with specific velocity
@@ -38,6 +48,7 @@ impl NoteInfo {
             pitch_class: note.pitch_class(),
             octave: note.octave(),
             velocity,
+            pan: None,
         }
     }
 
@@ -48,6 +59,22 @@ impl NoteInfo {
             ..self.clone()
         }
     }
+
+    /// Create a copy panned to `pan` (0.0 = left, 1.0 = right)
+    pub fn with_pan(&self, pan: f32) -> Self {
+        NoteInfo {
+            pan: Some(pan),
+            ..self.clone()
+        }
+    }
+
+    /// Create a copy sounding at `speed` times the frequency
+    pub fn with_speed(&self, speed: f32) -> Self {
+        NoteInfo {
+            frequency: self.frequency * speed,
+            ..self.clone()
+        }
+    }
 }
 
 /// A single playback event with full note data for visualization and playback.
@@ -60,6 +87,8 @@ pub struct PlaybackEvent {
     pub notes: Vec<NoteInfo>,
     /// Drum sounds in this event (for percussion)
     pub drums: Vec<DrumSound>,
+    /// MIDI velocity (0-127) of the drum hits, default [`DEFAULT_VELOCITY`]
+    pub drum_velocity: u8,
     /// Start time in beats relative to pattern start (exact rational)
     pub start_beat: Time,
     /// Duration in beats (exact rational)
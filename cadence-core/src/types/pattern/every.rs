@@ -10,6 +10,7 @@ use std::fmt;
 /// Unlike lazy evaluation, both patterns are pre-computed at creation time,
 /// making the runtime selection fast and predictable.
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct EveryPattern {
     /// How often to apply the transformation (every N cycles)
     pub interval: usize,
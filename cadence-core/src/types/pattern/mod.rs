@@ -3,19 +3,25 @@
 //! Enables cycle-based patterns like `"C E G _"` where all steps fit into one cycle,
 //! with support for rests, repetition, and grouping.
 
+mod compiled;
 mod core;
 mod euclidean;
 mod event;
 mod every;
+mod morph;
 mod parser;
+mod probability;
 mod step;
 
 #[cfg(test)]
 mod tests;
 
 // Re-export public types
-pub use core::Pattern;
-pub use euclidean::bjorklund;
-pub use event::{NoteInfo, PlaybackEvent};
+pub use compiled::{CompiledPattern, MAX_COMPILED_CYCLES};
+pub use core::{Delay, DelayTime, Pattern, PatternChange, Scatter, Strum, Swing};
+pub use euclidean::{bjorklund, bjorklund_rotated, euclidean};
+pub use event::{NoteInfo, PlaybackEvent, DEFAULT_VELOCITY};
 pub use every::EveryPattern;
+pub use morph::{morph_amount, morph_events};
+pub use probability::ProbabilityPattern;
 pub use step::PatternStep;
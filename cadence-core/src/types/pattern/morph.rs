@@ -0,0 +1,192 @@
+//! Blending between two versions of a pattern
+//!
+//! When a looping pattern is redefined, the dispatcher can morph from the old
+//! version to the new one over a few cycles instead of jumping straight to it.
+//! Each cycle of the morph plays a blend of the two versions' events for that
+//! cycle, moving further towards the new version every cycle.
+
+use super::event::{NoteInfo, PlaybackEvent, DEFAULT_VELOCITY};
+use crate::types::note::Note;
+use crate::types::rng::{SeededRng, DEFAULT_SEED};
+use crate::types::time::Time;
+
+/// Events for one cycle partway through a morph from `from` to `to`.
+///
+/// `amount` runs from 0.0 (all `from`) to 1.0 (all `to`), and `from` is
+/// stretched to the length of `to`'s cycle. If both cycles have the same
+/// rhythm and the same number of notes per event, each note moves in
+/// semitones towards its new pitch. Otherwise the events crossfade: old events
+/// drop out and new ones come in, a few more each cycle.
+pub fn morph_events(
+    from: &[PlaybackEvent],
+    from_beats: Time,
+    to: &[PlaybackEvent],
+    to_beats: Time,
+    amount: f64,
+) -> Vec<PlaybackEvent> {
+    let amount = amount.clamp(0.0, 1.0);
+    let from = stretch(from, from_beats, to_beats);
+
+    if same_shape(&from, to) {
+        from.iter()
+            .zip(to)
+            .map(|(old, new)| glide(old, new, amount))
+            .collect()
+    } else {
+        crossfade(&from, to, to_beats, amount)
+    }
+}
+
+/// Rescale event times from a cycle of `from_beats` to one of `to_beats`
+fn stretch(events: &[PlaybackEvent], from_beats: Time, to_beats: Time) -> Vec<PlaybackEvent> {
+    if from_beats == to_beats || from_beats == Time::from_integer(0) {
+        return events.to_vec();
+    }
+    let scale = to_beats / from_beats;
+    events
+        .iter()
+        .map(|event| PlaybackEvent {
+            start_beat: event.start_beat * scale,
+            duration: event.duration * scale,
+            ..event.clone()
+        })
+        .collect()
+}
+
+/// Whether two cycles line up event for event, so notes can be moved in place
+fn same_shape(from: &[PlaybackEvent], to: &[PlaybackEvent]) -> bool {
+    from.len() == to.len()
+        && from.iter().zip(to).all(|(old, new)| {
+            old.start_beat == new.start_beat
+                && old.duration == new.duration
+                && old.is_rest == new.is_rest
+                && old.notes.len() == new.notes.len()
+                && old.drums == new.drums
+        })
+}
+
+/// Move each note of `old` part of the way towards the matching note of `new`
+fn glide(old: &PlaybackEvent, new: &PlaybackEvent, amount: f64) -> PlaybackEvent {
+    let notes = old
+        .notes
+        .iter()
+        .zip(&new.notes)
+        .map(|(a, b)| {
+            let midi = lerp(a.midi as f64, b.midi as f64, amount) as u8;
+            let velocity = lerp(a.velocity as f64, b.velocity as f64, amount) as u8;
+            match Note::new_with_octave(midi % 12, (midi / 12) as i8 - 1) {
+                Ok(note) => NoteInfo::from_note_with_velocity(&note, velocity),
+                Err(_) => b.clone(),
+            }
+        })
+        .collect();
+    PlaybackEvent {
+        notes,
+        ..new.clone()
+    }
+}
+
+fn lerp(a: f64, b: f64, amount: f64) -> f64 {
+    (a + (b - a) * amount).round()
+}
+
+/// Old events drop out and new events come in once `amount` passes each
+/// event's threshold. Thresholds are spread evenly in a fixed shuffled order,
+/// so the same amount always gives the same blend.
+fn crossfade(
+    from: &[PlaybackEvent],
+    to: &[PlaybackEvent],
+    to_beats: Time,
+    amount: f64,
+) -> Vec<PlaybackEvent> {
+    let sounding = |events: &[PlaybackEvent]| -> Vec<PlaybackEvent> {
+        events.iter().filter(|e| !e.is_rest).cloned().collect()
+    };
+    let old = sounding(from);
+    let new = sounding(to);
+    let old_thresholds = thresholds(old.len());
+    let new_thresholds = thresholds(new.len());
+
+    let mut present: Vec<PlaybackEvent> = old
+        .into_iter()
+        .zip(old_thresholds)
+        .filter(|(_, threshold)| amount < *threshold)
+        .chain(
+            new.into_iter()
+                .zip(new_thresholds)
+                .filter(|(_, threshold)| amount >= *threshold),
+        )
+        .map(|(event, _)| event)
+        .collect();
+    present.sort_by_key(|e| e.start_beat);
+
+    // Events starting together become one chord
+    let mut merged: Vec<PlaybackEvent> = Vec::new();
+    for event in present {
+        match merged.last_mut() {
+            Some(last) if last.start_beat == event.start_beat => {
+                for note in event.notes {
+                    if !last.notes.iter().any(|n| n.midi == note.midi) {
+                        last.notes.push(note);
+                    }
+                }
+                for drum in event.drums {
+                    if !last.drums.contains(&drum) {
+                        last.drums.push(drum);
+                    }
+                }
+                last.duration = last.duration.max(event.duration);
+            }
+            _ => merged.push(event),
+        }
+    }
+
+    // Fill the gaps with rests so silence still stops the previous note
+    let mut events = Vec::with_capacity(merged.len() * 2 + 1);
+    let mut cursor = Time::from_integer(0);
+    for event in merged {
+        if event.start_beat > cursor {
+            events.push(rest(cursor, event.start_beat - cursor));
+        }
+        cursor = cursor.max(event.start_beat + event.duration);
+        events.push(event);
+    }
+    if cursor < to_beats {
+        events.push(rest(cursor, to_beats - cursor));
+    }
+    events
+}
+
+/// Thresholds `1/(n+1) ..= n/(n+1)`, assigned to events in shuffled order
+fn thresholds(count: usize) -> Vec<f64> {
+    let mut ranks: Vec<usize> = (1..=count).collect();
+    let mut rng = SeededRng::new(DEFAULT_SEED);
+    for i in (1..ranks.len()).rev() {
+        let j = (rng.next_u64() % (i as u64 + 1)) as usize;
+        ranks.swap(i, j);
+    }
+    ranks
+        .into_iter()
+        .map(|rank| rank as f64 / (count + 1) as f64)
+        .collect()
+}
+
+fn rest(start_beat: Time, duration: Time) -> PlaybackEvent {
+    PlaybackEvent {
+        notes: vec![],
+        drums: vec![],
+        drum_velocity: DEFAULT_VELOCITY,
+        start_beat,
+        duration,
+        is_rest: true,
+    }
+}
+
+/// Morph amount for the `index`-th cycle (from 0) of a morph lasting `cycles`
+/// cycles. The last cycle plays the new pattern unchanged.
+pub fn morph_amount(index: usize, cycles: usize) -> f64 {
+    if cycles == 0 {
+        return 1.0;
+    }
+    ((index + 1) as f64 / cycles as f64).min(1.0)
+}
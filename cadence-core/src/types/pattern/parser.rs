@@ -10,15 +10,23 @@ pub fn has_non_variable_content(step: &PatternStep) -> bool {
         PatternStep::Note(_) | PatternStep::Chord(_) | PatternStep::Rest | PatternStep::Drum(_) => {
             true
         }
-        PatternStep::Group(steps) => steps.iter().any(has_non_variable_content),
+        PatternStep::Group(steps) | PatternStep::Tuplet(steps) => {
+            steps.iter().any(has_non_variable_content)
+        }
         PatternStep::Repeat(inner, _) => has_non_variable_content(inner),
         PatternStep::Weighted(inner, _) => has_non_variable_content(inner),
         PatternStep::Alternation(steps) => steps.iter().any(has_non_variable_content),
-        PatternStep::Euclidean(inner, _, _) => has_non_variable_content(inner),
+        PatternStep::Euclidean(inner, ..) => has_non_variable_content(inner),
         PatternStep::Polyrhythm(sub_patterns) => sub_patterns
             .iter()
             .any(|sub| sub.iter().any(has_non_variable_content)),
-        PatternStep::Velocity(inner, _) => has_non_variable_content(inner),
+        PatternStep::Polymeter(steps, _) | PatternStep::Choice(steps) => {
+            steps.iter().any(has_non_variable_content)
+        }
+        PatternStep::Velocity(inner, _)
+        | PatternStep::Speed(inner, _)
+        | PatternStep::Pan(inner, _)
+        | PatternStep::Degraded(inner, ..) => has_non_variable_content(inner),
         PatternStep::Variable(_) => false,
     }
 }
@@ -54,9 +62,30 @@ pub fn parse_steps(notation: &str) -> Result<Vec<PatternStep>> {
                 steps.push(step);
             }
             // Polyrhythm: {C D E, F G} plays multiple patterns simultaneously at their own tempos
+            // Polymeter: {C D E}%4 plays four of its steps per cycle
             '{' => {
                 chars.next(); // consume '{'
                 let poly_content = take_until_brace(&mut chars)?;
+                if chars.peek() == Some(&'%') {
+                    chars.next(); // consume '%'
+                    let per_cycle = parse_polymeter_count(&mut chars)?;
+                    if poly_content.contains(',') {
+                        return Err(anyhow!(
+                            "Polymeter {{...}}%{} takes a single sequence, not a polyrhythm",
+                            per_cycle
+                        ));
+                    }
+                    let inner_steps = parse_steps(&poly_content)?;
+                    if inner_steps.is_empty() {
+                        return Err(anyhow!("Polymeter {{}} cannot be empty"));
+                    }
+                    let step = maybe_parse_weight_and_repeat(
+                        &mut chars,
+                        PatternStep::Polymeter(inner_steps, per_cycle),
+                    )?;
+                    steps.push(step);
+                    continue;
+                }
                 // Split by comma to get sub-patterns
                 let sub_pattern_strs: Vec<&str> = poly_content.split(',').collect();
                 if sub_pattern_strs.is_empty() {
@@ -81,6 +110,24 @@ pub fn parse_steps(notation: &str) -> Result<Vec<PatternStep>> {
                 chars.next(); // consume '['
                 let group_content = take_until_bracket(&mut chars)?;
 
+                // Random choice: [C|E|G] picks one option per cycle
+                let options = split_choice_options(&group_content);
+                if options.len() > 1 {
+                    let mut choices = Vec::new();
+                    for option in options {
+                        let mut option_steps = parse_steps(option)?;
+                        choices.push(match option_steps.len() {
+                            0 => return Err(anyhow!("Choice option cannot be empty")),
+                            1 => option_steps.remove(0),
+                            _ => PatternStep::Group(option_steps),
+                        });
+                    }
+                    let step =
+                        maybe_parse_weight_and_repeat(&mut chars, PatternStep::Choice(choices))?;
+                    steps.push(step);
+                    continue;
+                }
+
                 // Check if it's a nested group first (starts with '[' after whitespace)
                 // This handles [[Bb4,D5,F5] [F4,A4,C5]] as a group containing chords
                 let trimmed = group_content.trim_start();
@@ -138,6 +185,22 @@ pub fn parse_steps(notation: &str) -> Result<Vec<PatternStep>> {
             // Identifier starting with h-z (could be drum like 'kick', 'hh', or variable)
             'h'..='z' | 'H'..='Z' => {
                 let ident = take_identifier(&mut chars);
+                // Tuplet: t(E G A) fits its steps into one step, like a group.
+                // `t` is also the tom, so t(100) and t(3,8) stay velocity and Euclidean.
+                if ident == "t" && starts_tuplet(&chars) {
+                    chars.next(); // consume '('
+                    let tuplet_content = take_until_paren(&mut chars)?;
+                    let inner_steps = parse_steps(&tuplet_content)?;
+                    if inner_steps.is_empty() {
+                        return Err(anyhow!("Tuplet t() cannot be empty"));
+                    }
+                    let step = maybe_parse_weight_and_repeat(
+                        &mut chars,
+                        PatternStep::Tuplet(inner_steps),
+                    )?;
+                    steps.push(step);
+                    continue;
+                }
                 // Check if it's a drum name first
                 let step = if let Some(drum) = DrumSound::from_name(&ident) {
                     PatternStep::Drum(drum)
@@ -157,6 +220,27 @@ pub fn parse_steps(notation: &str) -> Result<Vec<PatternStep>> {
     Ok(steps)
 }
 
+/// Split group content at the `|` separators that aren't inside a nested
+/// group, alternation or polyrhythm
+fn split_choice_options(content: &str) -> Vec<&str> {
+    let mut options = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in content.char_indices() {
+        match c {
+            '[' | '{' | '<' => depth += 1,
+            ']' | '}' | '>' => depth -= 1,
+            '|' if depth == 0 => {
+                options.push(&content[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    options.push(&content[start..]);
+    options
+}
+
 /// Take content until matching '>', handling nested angle brackets
 fn take_until_angle_bracket(chars: &mut std::iter::Peekable<std::str::Chars>) -> Result<String> {
     let mut content = String::new();
@@ -232,6 +316,43 @@ fn take_until_brace(chars: &mut std::iter::Peekable<std::str::Chars>) -> Result<
     Err(anyhow!("Unclosed brace in pattern"))
 }
 
+/// Whether the next '(' opens a tuplet's steps rather than a velocity or
+/// Euclidean rhythm, which always start with a number
+fn starts_tuplet(chars: &std::iter::Peekable<std::str::Chars>) -> bool {
+    let mut ahead = chars.clone();
+    if ahead.next() != Some('(') {
+        return false;
+    }
+    ahead
+        .find(|c| !c.is_whitespace())
+        .is_some_and(|c| !(c.is_ascii_digit() || c == '.' || c == '-'))
+}
+
+/// Take content until matching ')', handling nested parentheses
+fn take_until_paren(chars: &mut std::iter::Peekable<std::str::Chars>) -> Result<String> {
+    let mut content = String::new();
+    let mut depth = 1;
+
+    for c in chars.by_ref() {
+        match c {
+            '(' => {
+                depth += 1;
+                content.push(c);
+            }
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Ok(content);
+                }
+                content.push(c);
+            }
+            _ => content.push(c),
+        }
+    }
+
+    Err(anyhow!("Unclosed parenthesis in pattern"))
+}
+
 /// Take a note token OR a longer identifier (for variable names)
 /// Keeps case as-is for variable names, but uppercases for notes
 fn take_note_or_identifier(chars: &mut std::iter::Peekable<std::str::Chars>) -> String {
@@ -298,9 +419,10 @@ fn take_identifier(chars: &mut std::iter::Peekable<std::str::Chars>) -> String {
     ident
 }
 
-/// Parse optional (n,k) Euclidean, (vel) velocity, @N weight, and *N repetition suffixes
-/// Order: parens first (Euclidean or Velocity), then weight, then repeat (e.g., C(3,8)@2*3 or C5(0.5)@2)
-/// Euclidean: (pulses,steps) - two comma-separated integers
+/// Parse optional (n,k) Euclidean, (vel) velocity, :N speed, @N weight, and *N repetition suffixes
+/// Order: parens first (Euclidean or Velocity), then speed, then weight, then repeat
+/// (e.g., C(3,8)@2*3, C5(0.5)@2 or C:2@2)
+/// Euclidean: (pulses,steps) or (pulses,steps,rotation) - comma-separated integers
 /// Velocity: (vel) - single number (0.0-1.0 float or 0-127 integer)
 fn maybe_parse_weight_and_repeat(
     chars: &mut std::iter::Peekable<std::str::Chars>,
@@ -333,9 +455,9 @@ fn maybe_parse_weight_and_repeat(
 
         // Determine if this is Euclidean or Velocity by checking for comma
         if content.contains(',') {
-            // Euclidean: (pulses,steps)
-            let (pulses, steps) = parse_euclidean_params(chars)?;
-            PatternStep::Euclidean(Box::new(step), pulses, steps)
+            // Euclidean: (pulses,steps) or (pulses,steps,rotation)
+            let (pulses, steps, rotation) = parse_euclidean_params(chars)?;
+            PatternStep::Euclidean(Box::new(step), pulses, steps, rotation)
         } else {
             // Velocity: single number
             let velocity = parse_velocity_param(chars)?;
@@ -345,6 +467,17 @@ fn maybe_parse_weight_and_repeat(
         step
     };
 
+    // Check for :N speed (:1 is normal speed)
+    let step = if chars.peek() == Some(&':') {
+        chars.next(); // consume ':'
+        match parse_speed_param(chars)? {
+            1.0 => step,
+            speed => PatternStep::Speed(Box::new(step), speed),
+        }
+    } else {
+        step
+    };
+
     // Check for @N weight
     let step = if chars.peek() == Some(&'@') {
         chars.next(); // consume '@'
@@ -389,6 +522,48 @@ fn maybe_parse_weight_and_repeat(
     }
 }
 
+/// Parse the steps-per-cycle count after a polymeter's `%`
+fn parse_polymeter_count(chars: &mut std::iter::Peekable<std::str::Chars>) -> Result<usize> {
+    let mut count_str = String::new();
+    while let Some(&c) = chars.peek() {
+        if c.is_ascii_digit() {
+            count_str.push(chars.next().unwrap());
+        } else {
+            break;
+        }
+    }
+    if count_str.is_empty() {
+        return Err(anyhow!("Expected number after '%'"));
+    }
+    let count: usize = count_str.parse()?;
+    if count == 0 {
+        return Err(anyhow!("Polymeter %0 is not allowed"));
+    }
+    Ok(count)
+}
+
+/// Parse the playback speed after `:` (a positive integer or decimal)
+fn parse_speed_param(chars: &mut std::iter::Peekable<std::str::Chars>) -> Result<f32> {
+    let mut num_str = String::new();
+    while let Some(&c) = chars.peek() {
+        if c.is_ascii_digit() || c == '.' {
+            num_str.push(chars.next().unwrap());
+        } else {
+            break;
+        }
+    }
+    if num_str.is_empty() {
+        return Err(anyhow!("Expected number after ':'"));
+    }
+    let speed: f32 = num_str
+        .parse()
+        .map_err(|_| anyhow!("Invalid speed: {}", num_str))?;
+    if speed <= 0.0 {
+        return Err(anyhow!("Speed must be greater than 0, got {}", num_str));
+    }
+    Ok(speed)
+}
+
 /// Parse velocity parameter (single number: 0.0-1.0 float or 0-127 integer)
 fn parse_velocity_param(chars: &mut std::iter::Peekable<std::str::Chars>) -> Result<u8> {
     let mut num_str = String::new();
@@ -435,10 +610,11 @@ fn parse_velocity_param(chars: &mut std::iter::Peekable<std::str::Chars>) -> Res
     Ok(velocity)
 }
 
-/// Parse the (pulses,steps) parameters for Euclidean rhythms
+/// Parse the (pulses,steps) or (pulses,steps,rotation) parameters for
+/// Euclidean rhythms. The rotation may be negative.
 fn parse_euclidean_params(
     chars: &mut std::iter::Peekable<std::str::Chars>,
-) -> Result<(usize, usize)> {
+) -> Result<(usize, usize, i32)> {
     // Parse first number (pulses)
     let mut pulses_str = String::new();
     while let Some(&c) = chars.peek() {
@@ -472,6 +648,25 @@ fn parse_euclidean_params(
         return Err(anyhow!("Expected number for Euclidean steps"));
     }
 
+    // Optional third number (rotation)
+    let mut rotation_str = String::new();
+    if chars.peek() == Some(&',') {
+        chars.next();
+        if chars.peek() == Some(&'-') {
+            rotation_str.push(chars.next().unwrap());
+        }
+        while let Some(&c) = chars.peek() {
+            if c.is_ascii_digit() {
+                rotation_str.push(chars.next().unwrap());
+            } else {
+                break;
+            }
+        }
+        if rotation_str.trim_start_matches('-').is_empty() {
+            return Err(anyhow!("Expected number for Euclidean rotation"));
+        }
+    }
+
     // Expect closing paren
     if chars.next() != Some(')') {
         return Err(anyhow!("Expected ')' to close Euclidean pattern"));
@@ -479,10 +674,15 @@ fn parse_euclidean_params(
 
     let pulses: usize = pulses_str.parse()?;
     let steps: usize = steps_str.parse()?;
+    let rotation: i32 = if rotation_str.is_empty() {
+        0
+    } else {
+        rotation_str.parse()?
+    };
 
     if steps == 0 {
         return Err(anyhow!("Euclidean steps must be > 0"));
     }
 
-    Ok((pulses, steps))
+    Ok((pulses, steps, rotation))
 }
@@ -0,0 +1,84 @@
+//! ProbabilityPattern - TidalCycles-style `sometimes` alternation.
+
+use super::core::Pattern;
+use crate::types::{probability_seed, SeededRng};
+use std::fmt;
+
+/// A pattern combinator that applies a transformation on a random share of
+/// cycles. Like `EveryPattern`, both variants are pre-computed at creation
+/// time and the playback engine picks one per cycle.
+///
+/// The pick for a cycle is fixed by `seed`, so re-evaluating the expression
+/// on every tick keeps choosing the same variant within a cycle.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ProbabilityPattern {
+    /// Chance of playing the transformed pattern in a cycle, in percent (0-100)
+    pub probability: u8,
+    /// Seed for the per-cycle choice
+    pub seed: u64,
+    /// The base (untransformed) pattern
+    pub base: Pattern,
+    /// The transformed pattern (pre-computed at creation time)
+    pub transformed: Pattern,
+}
+
+impl ProbabilityPattern {
+    /// Create a new ProbabilityPattern combinator
+    ///
+    /// # Arguments
+    /// * `probability` - Chance in percent that a cycle plays the transformed pattern (clamped to 100)
+    /// * `seed` - Seed for the per-cycle choice
+    /// * `base` - The original, untransformed pattern
+    /// * `transformed` - The pattern with the transformation applied
+    pub fn new(probability: u8, seed: u64, base: Pattern, transformed: Pattern) -> Self {
+        Self {
+            probability: probability.min(100),
+            seed,
+            base,
+            transformed,
+        }
+    }
+
+    /// Copy of this combinator with new base and transformed patterns, keeping
+    /// the probability and seed (for builtins that map over both variants)
+    pub fn with_patterns(&self, base: Pattern, transformed: Pattern) -> Self {
+        Self::new(self.probability, self.seed, base, transformed)
+    }
+
+    /// Whether the transformed pattern plays in the given cycle
+    pub fn is_transformed(&self, cycle: usize) -> bool {
+        match self.probability {
+            0 => false,
+            100 => true,
+            p => {
+                let mut rng = SeededRng::new(probability_seed(self.seed, 0, cycle));
+                rng.next_f64() * 100.0 < p as f64
+            }
+        }
+    }
+
+    /// Get the appropriate pattern for the given absolute cycle number
+    pub fn get_pattern_for_cycle(&self, cycle: usize) -> &Pattern {
+        if self.is_transformed(cycle) {
+            &self.transformed
+        } else {
+            &self.base
+        }
+    }
+
+    /// Get a clone of the pattern for the given cycle
+    pub fn pattern_for_cycle(&self, cycle: usize) -> Pattern {
+        self.get_pattern_for_cycle(cycle).clone()
+    }
+}
+
+impl fmt::Display for ProbabilityPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "sometimes_by({}, transform, {})",
+            self.probability, self.base
+        )
+    }
+}
@@ -1,12 +1,18 @@
 //! PatternStep enum - a single step in a pattern.
 
-use super::euclidean::bjorklund;
-use super::event::NoteInfo;
-use crate::types::{Chord, DrumSound, Note};
+use super::euclidean::bjorklund_rotated;
+use super::event::{NoteInfo, DEFAULT_VELOCITY};
+use crate::types::{Chord, DrumSound, Note, SeededRng};
 use std::fmt;
+use std::hash::{Hash, Hasher};
 
 /// A single step in a pattern
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(tag = "type", content = "value", rename_all = "snake_case")
+)]
 pub enum PatternStep {
     /// Single note: C, D#, etc.
     Note(Note),
@@ -16,6 +22,9 @@ pub enum PatternStep {
     Rest,
     /// Group of steps that share one slot: [C E]
     Group(Vec<PatternStep>),
+    /// Tuplet: t(C E G) plays exactly like the group [C E G], and keeps its
+    /// own spelling when shown
+    Tuplet(Vec<PatternStep>),
     /// Repeat a step N times: C*3
     Repeat(Box<PatternStep>, usize),
     /// Unresolved variable reference (resolved at evaluation time)
@@ -26,16 +35,92 @@ pub enum PatternStep {
     Weighted(Box<PatternStep>, usize),
     /// Cycle-based alternation: <C D E> plays one element per cycle
     Alternation(Vec<PatternStep>),
-    /// Euclidean rhythm: C(3,8) distributes 3 pulses evenly across 8 slots
-    Euclidean(Box<PatternStep>, usize, usize), // (inner, pulses, steps)
+    /// Euclidean rhythm: C(3,8) distributes 3 pulses evenly across 8 slots,
+    /// C(3,8,2) starts the same rhythm two slots later
+    Euclidean(
+        Box<PatternStep>,
+        usize,
+        usize,
+        #[cfg_attr(feature = "serde", serde(default))] i32,
+    ), // (inner, pulses, steps, rotation)
     /// Polyrhythm: {C D E, F G} plays multiple patterns simultaneously,
     /// each at its own tempo (3-step pattern plays 3 notes/cycle, 2-step plays 2 notes/cycle)
     Polyrhythm(Vec<Vec<PatternStep>>), // Each inner Vec is a sub-pattern's steps
+    /// Polymeter: {C E G}%4 plays 4 of its steps per cycle, picking up each
+    /// cycle where the last one stopped (C E G C, then E G C E, ...)
+    Polymeter(Vec<PatternStep>, usize), // (steps, steps per cycle)
     /// Velocity modifier: C5(0.5) or C5(100) sets MIDI velocity (0-127)
     Velocity(Box<PatternStep>, u8),
+    /// Playback speed: C:2 plays C at double rate, an octave up; C:0.5 an
+    /// octave down. Scales the synth frequency only, not the MIDI note
+    Speed(Box<PatternStep>, f32),
+    /// Stereo pan for the inner step's notes (0.0 = left, 1.0 = right),
+    /// overriding the track's pan. Has no mini-notation; `jux()` builds it
+    Pan(Box<PatternStep>, f32),
+    /// Random choice: [C|E|G] plays one of its options, picked again every cycle
+    Choice(Vec<PatternStep>),
+    /// Randomly dropped step: rests with the given chance in percent, picked
+    /// again every cycle, or with the flag set (`undegrade()`) plays with that
+    /// chance instead. Has no mini-notation; `degrade()` builds it
+    Degraded(Box<PatternStep>, u8, bool),
 }
 
 impl PatternStep {
+    /// Feed this step's structure into `state`, for [`Pattern::fingerprint`].
+    /// Floats are hashed by their bits.
+    ///
+    /// [`Pattern::fingerprint`]: super::Pattern::fingerprint
+    pub fn hash_structure<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        let hash_all = |steps: &[PatternStep], state: &mut H| {
+            steps.len().hash(state);
+            for step in steps {
+                step.hash_structure(state);
+            }
+        };
+        match self {
+            PatternStep::Note(note) => note.hash(state),
+            PatternStep::Chord(chord) => chord.hash(state),
+            PatternStep::Rest => {}
+            PatternStep::Variable(name) => name.hash(state),
+            PatternStep::Drum(drum) => drum.hash(state),
+            PatternStep::Group(steps)
+            | PatternStep::Tuplet(steps)
+            | PatternStep::Alternation(steps)
+            | PatternStep::Choice(steps) => hash_all(steps, state),
+            PatternStep::Polymeter(steps, per_cycle) => {
+                hash_all(steps, state);
+                per_cycle.hash(state);
+            }
+            PatternStep::Polyrhythm(layers) => {
+                layers.len().hash(state);
+                for layer in layers {
+                    hash_all(layer, state);
+                }
+            }
+            PatternStep::Repeat(inner, n) | PatternStep::Weighted(inner, n) => {
+                inner.hash_structure(state);
+                n.hash(state);
+            }
+            PatternStep::Euclidean(inner, pulses, steps, rotation) => {
+                inner.hash_structure(state);
+                (pulses, steps, rotation).hash(state);
+            }
+            PatternStep::Velocity(inner, velocity) => {
+                inner.hash_structure(state);
+                velocity.hash(state);
+            }
+            PatternStep::Speed(inner, value) | PatternStep::Pan(inner, value) => {
+                inner.hash_structure(state);
+                value.to_bits().hash(state);
+            }
+            PatternStep::Degraded(inner, percent, keep) => {
+                inner.hash_structure(state);
+                (percent, keep).hash(state);
+            }
+        }
+    }
+
     /// Get the weight of this step for duration calculation.
     /// Weighted steps return their weight, all others return 1.
     pub fn weight(&self) -> usize {
@@ -54,7 +139,9 @@ impl PatternStep {
                 vec![(c.notes_vec().iter().map(|n| n.frequency()).collect(), false)]
             }
             PatternStep::Rest => vec![(vec![], true)],
-            PatternStep::Group(steps) => steps.iter().flat_map(|s| s.to_frequencies()).collect(),
+            PatternStep::Group(steps) | PatternStep::Tuplet(steps) => {
+                steps.iter().flat_map(|s| s.to_frequencies()).collect()
+            }
             PatternStep::Repeat(step, count) => {
                 let inner = step.to_frequencies();
                 (0..*count).flat_map(|_| inner.clone()).collect()
@@ -75,8 +162,8 @@ impl PatternStep {
                 .map(|s| s.to_frequencies())
                 .unwrap_or_default(),
             // Euclidean: expand using Bjorklund algorithm
-            PatternStep::Euclidean(inner, pulses, steps) => {
-                let rhythm = bjorklund(*pulses, *steps);
+            PatternStep::Euclidean(inner, pulses, steps, rotation) => {
+                let rhythm = bjorklund_rotated(*pulses, *steps, *rotation);
                 let inner_freq = inner.to_frequencies();
                 rhythm
                     .into_iter()
@@ -108,8 +195,27 @@ impl PatternStep {
                     vec![(merged_freqs, false)]
                 }
             }
+            // Polymeter returns the first cycle for static contexts
+            PatternStep::Polymeter(steps, per_cycle) => polymeter_slots(steps, *per_cycle, 0)
+                .flat_map(|s| s.to_frequencies())
+                .collect(),
+            // Choice returns the first option for static contexts
+            PatternStep::Choice(options) => options
+                .first()
+                .map(|s| s.to_frequencies())
+                .unwrap_or_default(),
             // Velocity: delegate to inner (velocity is handled in NoteInfo conversion)
             PatternStep::Velocity(inner, _) => inner.to_frequencies(),
+            // Speed: scale the inner frequencies
+            PatternStep::Speed(inner, speed) => inner
+                .to_frequencies()
+                .into_iter()
+                .map(|(freqs, is_rest)| (freqs.into_iter().map(|f| f * speed).collect(), is_rest))
+                .collect(),
+            // Pan: delegate to inner (pan is handled in NoteInfo conversion)
+            PatternStep::Pan(inner, _) => inner.to_frequencies(),
+            // Degraded steps play for static contexts, like a choice's first option
+            PatternStep::Degraded(inner, ..) => inner.to_frequencies(),
         }
     }
 
@@ -123,7 +229,9 @@ impl PatternStep {
                 vec![(notes, false)]
             }
             PatternStep::Rest => vec![(vec![], true)],
-            PatternStep::Group(steps) => steps.iter().flat_map(|s| s.to_note_infos()).collect(),
+            PatternStep::Group(steps) | PatternStep::Tuplet(steps) => {
+                steps.iter().flat_map(|s| s.to_note_infos()).collect()
+            }
             PatternStep::Repeat(step, count) => {
                 let inner = step.to_note_infos();
                 (0..*count).flat_map(|_| inner.clone()).collect()
@@ -144,6 +252,7 @@ impl PatternStep {
                         pitch_class: d.midi_note() % 12,
                         octave: (d.midi_note() / 12) as i8 - 1,
                         velocity: 100,
+                        pan: None,
                     }],
                     false,
                 )]
@@ -155,8 +264,8 @@ impl PatternStep {
                 steps.first().map(|s| s.to_note_infos()).unwrap_or_default()
             }
             // Euclidean: expand using Bjorklund algorithm
-            PatternStep::Euclidean(inner, pulses, steps) => {
-                let rhythm = bjorklund(*pulses, *steps);
+            PatternStep::Euclidean(inner, pulses, steps, rotation) => {
+                let rhythm = bjorklund_rotated(*pulses, *steps, *rotation);
                 let inner_info = inner.to_note_infos();
                 rhythm
                     .into_iter()
@@ -187,6 +296,15 @@ impl PatternStep {
                     vec![(merged_notes, false)]
                 }
             }
+            // Polymeter returns the first cycle for static contexts
+            PatternStep::Polymeter(steps, per_cycle) => polymeter_slots(steps, *per_cycle, 0)
+                .flat_map(|s| s.to_note_infos())
+                .collect(),
+            // Choice returns the first option for static contexts
+            PatternStep::Choice(options) => options
+                .first()
+                .map(|s| s.to_note_infos())
+                .unwrap_or_default(),
             // Velocity: apply velocity to all notes from inner step
             PatternStep::Velocity(inner, vel) => inner
                 .to_note_infos()
@@ -197,20 +315,48 @@ impl PatternStep {
                     (notes_with_vel, is_rest)
                 })
                 .collect(),
+            // Speed: scale the frequency of all notes from inner step
+            PatternStep::Speed(inner, speed) => inner
+                .to_note_infos()
+                .into_iter()
+                .map(|(notes, is_rest)| {
+                    let notes_with_speed: Vec<NoteInfo> =
+                        notes.into_iter().map(|n| n.with_speed(*speed)).collect();
+                    (notes_with_speed, is_rest)
+                })
+                .collect(),
+            // Pan: pan all notes from inner step
+            PatternStep::Pan(inner, pan) => inner
+                .to_note_infos()
+                .into_iter()
+                .map(|(notes, is_rest)| {
+                    let notes_with_pan: Vec<NoteInfo> =
+                        notes.into_iter().map(|n| n.with_pan(*pan)).collect();
+                    (notes_with_pan, is_rest)
+                })
+                .collect(),
+            PatternStep::Degraded(inner, ..) => inner.to_note_infos(),
         }
     }
 
     /// Flatten this step into separate notes and drums for playback
-    /// Returns (Vec<NoteInfo>, Vec<DrumSound>, is_rest) preserving type distinction
-    pub fn to_step_info(&self) -> Vec<(Vec<NoteInfo>, Vec<DrumSound>, bool)> {
+    /// Returns (Vec<NoteInfo>, Vec<DrumSound>, drum velocity, is_rest) preserving type distinction
+    pub fn to_step_info(&self) -> Vec<(Vec<NoteInfo>, Vec<DrumSound>, u8, bool)> {
         match self {
-            PatternStep::Note(n) => vec![(vec![NoteInfo::from_note(n)], vec![], false)],
+            PatternStep::Note(n) => vec![(
+                vec![NoteInfo::from_note(n)],
+                vec![],
+                DEFAULT_VELOCITY,
+                false,
+            )],
             PatternStep::Chord(c) => {
                 let notes: Vec<NoteInfo> = c.notes_vec().iter().map(NoteInfo::from_note).collect();
-                vec![(notes, vec![], false)]
+                vec![(notes, vec![], DEFAULT_VELOCITY, false)]
+            }
+            PatternStep::Rest => vec![(vec![], vec![], DEFAULT_VELOCITY, true)],
+            PatternStep::Group(steps) | PatternStep::Tuplet(steps) => {
+                steps.iter().flat_map(|s| s.to_step_info()).collect()
             }
-            PatternStep::Rest => vec![(vec![], vec![], true)],
-            PatternStep::Group(steps) => steps.iter().flat_map(|s| s.to_step_info()).collect(),
             PatternStep::Repeat(step, count) => {
                 let inner = step.to_step_info();
                 (0..*count).flat_map(|_| inner.clone()).collect()
@@ -221,7 +367,7 @@ impl PatternStep {
                     name
                 )
             }
-            PatternStep::Drum(d) => vec![(vec![], vec![*d], false)],
+            PatternStep::Drum(d) => vec![(vec![], vec![*d], DEFAULT_VELOCITY, false)],
             // Weighted delegates to inner (weight is handled at duration calculation)
             PatternStep::Weighted(inner, _) => inner.to_step_info(),
             // Alternation returns first step for static contexts
@@ -229,19 +375,21 @@ impl PatternStep {
                 steps.first().map(|s| s.to_step_info()).unwrap_or_default()
             }
             // Euclidean: expand using Bjorklund algorithm
-            PatternStep::Euclidean(inner, pulses, steps) => {
-                let rhythm = bjorklund(*pulses, *steps);
+            PatternStep::Euclidean(inner, pulses, steps, rotation) => {
+                let rhythm = bjorklund_rotated(*pulses, *steps, *rotation);
                 let inner_info = inner.to_step_info();
                 rhythm
                     .into_iter()
                     .map(|is_pulse| {
                         if is_pulse {
-                            inner_info
-                                .first()
-                                .cloned()
-                                .unwrap_or((vec![], vec![], true))
+                            inner_info.first().cloned().unwrap_or((
+                                vec![],
+                                vec![],
+                                DEFAULT_VELOCITY,
+                                true,
+                            ))
                         } else {
-                            (vec![], vec![], true) // rest
+                            (vec![], vec![], DEFAULT_VELOCITY, true) // rest
                         }
                     })
                     .collect()
@@ -250,50 +398,89 @@ impl PatternStep {
             PatternStep::Polyrhythm(sub_patterns) => {
                 let mut merged_notes: Vec<NoteInfo> = Vec::new();
                 let mut merged_drums: Vec<DrumSound> = Vec::new();
+                let mut drum_velocity = DEFAULT_VELOCITY;
                 for sub in sub_patterns {
                     for step in sub {
-                        for (notes, drums, is_rest) in step.to_step_info() {
+                        for (notes, drums, velocity, is_rest) in step.to_step_info() {
                             if !is_rest {
                                 merged_notes.extend(notes);
+                                if !drums.is_empty() {
+                                    drum_velocity = velocity;
+                                }
                                 merged_drums.extend(drums);
                             }
                         }
                     }
                 }
                 if merged_notes.is_empty() && merged_drums.is_empty() {
-                    vec![(vec![], vec![], true)]
+                    vec![(vec![], vec![], DEFAULT_VELOCITY, true)]
                 } else {
-                    vec![(merged_notes, merged_drums, false)]
+                    vec![(merged_notes, merged_drums, drum_velocity, false)]
                 }
             }
-            // Velocity: apply velocity to all notes from inner step
+            // Polymeter returns the first cycle for static contexts
+            PatternStep::Polymeter(steps, per_cycle) => polymeter_slots(steps, *per_cycle, 0)
+                .flat_map(|s| s.to_step_info())
+                .collect(),
+            // Choice returns the first option for static contexts
+            PatternStep::Choice(options) => options
+                .first()
+                .map(|s| s.to_step_info())
+                .unwrap_or_default(),
+            // Velocity: apply velocity to all notes and drums from inner step
             PatternStep::Velocity(inner, vel) => inner
                 .to_step_info()
                 .into_iter()
-                .map(|(notes, drums, is_rest)| {
+                .map(|(notes, drums, _, is_rest)| {
                     let notes_with_vel: Vec<NoteInfo> =
                         notes.into_iter().map(|n| n.with_velocity(*vel)).collect();
-                    (notes_with_vel, drums, is_rest)
+                    (notes_with_vel, drums, *vel, is_rest)
                 })
                 .collect(),
+            // Speed: scale the frequency of all notes from inner step
+            PatternStep::Speed(inner, speed) => inner
+                .to_step_info()
+                .into_iter()
+                .map(|(notes, drums, velocity, is_rest)| {
+                    let notes_with_speed: Vec<NoteInfo> =
+                        notes.into_iter().map(|n| n.with_speed(*speed)).collect();
+                    (notes_with_speed, drums, velocity, is_rest)
+                })
+                .collect(),
+            // Pan: pan all notes from inner step (drums keep the track's pan)
+            PatternStep::Pan(inner, pan) => inner
+                .to_step_info()
+                .into_iter()
+                .map(|(notes, drums, velocity, is_rest)| {
+                    let notes_with_pan: Vec<NoteInfo> =
+                        notes.into_iter().map(|n| n.with_pan(*pan)).collect();
+                    (notes_with_pan, drums, velocity, is_rest)
+                })
+                .collect(),
+            PatternStep::Degraded(inner, ..) => inner.to_step_info(),
         }
     }
 
     /// Flatten this step into separate notes and drums for playback, with cycle-awareness.
     /// For Alternation steps, selects the appropriate element based on the current cycle.
-    /// Returns (Vec<NoteInfo>, Vec<DrumSound>, is_rest) preserving type distinction.
+    /// Returns (Vec<NoteInfo>, Vec<DrumSound>, drum velocity, is_rest) preserving type distinction.
     pub fn to_step_info_for_cycle(
         &self,
         cycle: usize,
-    ) -> Vec<(Vec<NoteInfo>, Vec<DrumSound>, bool)> {
+    ) -> Vec<(Vec<NoteInfo>, Vec<DrumSound>, u8, bool)> {
         match self {
-            PatternStep::Note(n) => vec![(vec![NoteInfo::from_note(n)], vec![], false)],
+            PatternStep::Note(n) => vec![(
+                vec![NoteInfo::from_note(n)],
+                vec![],
+                DEFAULT_VELOCITY,
+                false,
+            )],
             PatternStep::Chord(c) => {
                 let notes: Vec<NoteInfo> = c.notes_vec().iter().map(NoteInfo::from_note).collect();
-                vec![(notes, vec![], false)]
+                vec![(notes, vec![], DEFAULT_VELOCITY, false)]
             }
-            PatternStep::Rest => vec![(vec![], vec![], true)],
-            PatternStep::Group(steps) => steps
+            PatternStep::Rest => vec![(vec![], vec![], DEFAULT_VELOCITY, true)],
+            PatternStep::Group(steps) | PatternStep::Tuplet(steps) => steps
                 .iter()
                 .flat_map(|s| s.to_step_info_for_cycle(cycle))
                 .collect(),
@@ -307,7 +494,7 @@ impl PatternStep {
                     name
                 )
             }
-            PatternStep::Drum(d) => vec![(vec![], vec![*d], false)],
+            PatternStep::Drum(d) => vec![(vec![], vec![*d], DEFAULT_VELOCITY, false)],
             PatternStep::Weighted(inner, _) => inner.to_step_info_for_cycle(cycle),
             // Alternation: select element based on cycle
             PatternStep::Alternation(steps) => {
@@ -318,19 +505,21 @@ impl PatternStep {
                 steps[idx].to_step_info_for_cycle(cycle)
             }
             // Euclidean: expand using Bjorklund algorithm
-            PatternStep::Euclidean(inner, pulses, steps) => {
-                let rhythm = bjorklund(*pulses, *steps);
+            PatternStep::Euclidean(inner, pulses, steps, rotation) => {
+                let rhythm = bjorklund_rotated(*pulses, *steps, *rotation);
                 let inner_info = inner.to_step_info_for_cycle(cycle);
                 rhythm
                     .into_iter()
                     .map(|is_pulse| {
                         if is_pulse {
-                            inner_info
-                                .first()
-                                .cloned()
-                                .unwrap_or((vec![], vec![], true))
+                            inner_info.first().cloned().unwrap_or((
+                                vec![],
+                                vec![],
+                                DEFAULT_VELOCITY,
+                                true,
+                            ))
                         } else {
-                            (vec![], vec![], true) // rest
+                            (vec![], vec![], DEFAULT_VELOCITY, true) // rest
                         }
                     })
                     .collect()
@@ -339,32 +528,121 @@ impl PatternStep {
             PatternStep::Polyrhythm(sub_patterns) => {
                 let mut merged_notes: Vec<NoteInfo> = Vec::new();
                 let mut merged_drums: Vec<DrumSound> = Vec::new();
+                let mut drum_velocity = DEFAULT_VELOCITY;
                 for sub in sub_patterns {
                     for step in sub {
-                        for (notes, drums, is_rest) in step.to_step_info_for_cycle(cycle) {
+                        for (notes, drums, velocity, is_rest) in step.to_step_info_for_cycle(cycle)
+                        {
                             if !is_rest {
                                 merged_notes.extend(notes);
+                                if !drums.is_empty() {
+                                    drum_velocity = velocity;
+                                }
                                 merged_drums.extend(drums);
                             }
                         }
                     }
                 }
                 if merged_notes.is_empty() && merged_drums.is_empty() {
-                    vec![(vec![], vec![], true)]
+                    vec![(vec![], vec![], DEFAULT_VELOCITY, true)]
                 } else {
-                    vec![(merged_notes, merged_drums, false)]
+                    vec![(merged_notes, merged_drums, drum_velocity, false)]
                 }
             }
-            // Velocity: apply velocity to all notes from inner step
+            // Polymeter: continue from where the previous cycle stopped
+            PatternStep::Polymeter(steps, per_cycle) => polymeter_slots(steps, *per_cycle, cycle)
+                .flat_map(|s| s.to_step_info_for_cycle(cycle))
+                .collect(),
+            // Choices are rolled by Pattern::to_rich_events_for_cycle before
+            // this is called; any left over play their first option
+            PatternStep::Choice(options) => options
+                .first()
+                .map(|s| s.to_step_info_for_cycle(cycle))
+                .unwrap_or_default(),
+            // Velocity: apply velocity to all notes and drums from inner step
             PatternStep::Velocity(inner, vel) => inner
                 .to_step_info_for_cycle(cycle)
                 .into_iter()
-                .map(|(notes, drums, is_rest)| {
+                .map(|(notes, drums, _, is_rest)| {
                     let notes_with_vel: Vec<NoteInfo> =
                         notes.into_iter().map(|n| n.with_velocity(*vel)).collect();
-                    (notes_with_vel, drums, is_rest)
+                    (notes_with_vel, drums, *vel, is_rest)
                 })
                 .collect(),
+            // Speed: scale the frequency of all notes from inner step
+            PatternStep::Speed(inner, speed) => inner
+                .to_step_info_for_cycle(cycle)
+                .into_iter()
+                .map(|(notes, drums, velocity, is_rest)| {
+                    let notes_with_speed: Vec<NoteInfo> =
+                        notes.into_iter().map(|n| n.with_speed(*speed)).collect();
+                    (notes_with_speed, drums, velocity, is_rest)
+                })
+                .collect(),
+            // Pan: pan all notes from inner step (drums keep the track's pan)
+            PatternStep::Pan(inner, pan) => inner
+                .to_step_info_for_cycle(cycle)
+                .into_iter()
+                .map(|(notes, drums, velocity, is_rest)| {
+                    let notes_with_pan: Vec<NoteInfo> =
+                        notes.into_iter().map(|n| n.with_pan(*pan)).collect();
+                    (notes_with_pan, drums, velocity, is_rest)
+                })
+                .collect(),
+            // Rolled along with choices; any left over play
+            PatternStep::Degraded(inner, ..) => inner.to_step_info_for_cycle(cycle),
+        }
+    }
+
+    /// Replace every note in this step, including chord notes, with
+    /// `f(note)`. Rests, drums and variables are left alone.
+    pub fn map_notes(&self, f: &dyn Fn(Note) -> Note) -> PatternStep {
+        match self {
+            PatternStep::Note(n) => PatternStep::Note(f(*n)),
+            PatternStep::Chord(c) => PatternStep::Chord(c.clone().map_notes(f)),
+            PatternStep::Rest => PatternStep::Rest,
+            PatternStep::Group(steps) => {
+                PatternStep::Group(steps.iter().map(|s| s.map_notes(f)).collect())
+            }
+            PatternStep::Tuplet(steps) => {
+                PatternStep::Tuplet(steps.iter().map(|s| s.map_notes(f)).collect())
+            }
+            PatternStep::Repeat(step, count) => {
+                PatternStep::Repeat(Box::new(step.map_notes(f)), *count)
+            }
+            PatternStep::Variable(name) => PatternStep::Variable(name.clone()),
+            PatternStep::Drum(d) => PatternStep::Drum(*d),
+            PatternStep::Weighted(inner, weight) => {
+                PatternStep::Weighted(Box::new(inner.map_notes(f)), *weight)
+            }
+            PatternStep::Alternation(steps) => {
+                PatternStep::Alternation(steps.iter().map(|s| s.map_notes(f)).collect())
+            }
+            PatternStep::Euclidean(inner, pulses, steps, rotation) => {
+                PatternStep::Euclidean(Box::new(inner.map_notes(f)), *pulses, *steps, *rotation)
+            }
+            PatternStep::Polyrhythm(sub_patterns) => PatternStep::Polyrhythm(
+                sub_patterns
+                    .iter()
+                    .map(|sub| sub.iter().map(|s| s.map_notes(f)).collect())
+                    .collect(),
+            ),
+            PatternStep::Polymeter(steps, per_cycle) => {
+                PatternStep::Polymeter(steps.iter().map(|s| s.map_notes(f)).collect(), *per_cycle)
+            }
+            PatternStep::Velocity(inner, vel) => {
+                PatternStep::Velocity(Box::new(inner.map_notes(f)), *vel)
+            }
+            PatternStep::Speed(inner, speed) => {
+                PatternStep::Speed(Box::new(inner.map_notes(f)), *speed)
+            }
+            PatternStep::Pan(inner, pan) => PatternStep::Pan(Box::new(inner.map_notes(f)), *pan),
+            PatternStep::Degraded(inner, percent, keep) => {
+                PatternStep::Degraded(Box::new(inner.map_notes(f)), *percent, *keep)
+            }
+            PatternStep::Choice(options) => {
+                PatternStep::Choice(options.iter().map(|s| s.map_notes(f)).collect())
+            }
         }
     }
 
@@ -377,6 +655,9 @@ impl PatternStep {
             PatternStep::Group(steps) => {
                 PatternStep::Group(steps.iter().map(|s| s.transpose(semitones)).collect())
             }
+            PatternStep::Tuplet(steps) => {
+                PatternStep::Tuplet(steps.iter().map(|s| s.transpose(semitones)).collect())
+            }
             PatternStep::Repeat(step, count) => {
                 PatternStep::Repeat(Box::new(step.transpose(semitones)), *count)
             }
@@ -388,27 +669,156 @@ impl PatternStep {
             PatternStep::Alternation(steps) => {
                 PatternStep::Alternation(steps.iter().map(|s| s.transpose(semitones)).collect())
             }
-            PatternStep::Euclidean(inner, pulses, steps) => {
-                PatternStep::Euclidean(Box::new(inner.transpose(semitones)), *pulses, *steps)
-            }
+            PatternStep::Euclidean(inner, pulses, steps, rotation) => PatternStep::Euclidean(
+                Box::new(inner.transpose(semitones)),
+                *pulses,
+                *steps,
+                *rotation,
+            ),
             PatternStep::Polyrhythm(sub_patterns) => PatternStep::Polyrhythm(
                 sub_patterns
                     .iter()
                     .map(|sub| sub.iter().map(|s| s.transpose(semitones)).collect())
                     .collect(),
             ),
+            PatternStep::Polymeter(steps, per_cycle) => PatternStep::Polymeter(
+                steps.iter().map(|s| s.transpose(semitones)).collect(),
+                *per_cycle,
+            ),
             PatternStep::Velocity(inner, vel) => {
                 PatternStep::Velocity(Box::new(inner.transpose(semitones)), *vel)
             }
+            PatternStep::Speed(inner, speed) => {
+                PatternStep::Speed(Box::new(inner.transpose(semitones)), *speed)
+            }
+            PatternStep::Pan(inner, pan) => {
+                PatternStep::Pan(Box::new(inner.transpose(semitones)), *pan)
+            }
+            PatternStep::Degraded(inner, percent, keep) => {
+                PatternStep::Degraded(Box::new(inner.transpose(semitones)), *percent, *keep)
+            }
+            PatternStep::Choice(options) => {
+                PatternStep::Choice(options.iter().map(|s| s.transpose(semitones)).collect())
+            }
+        }
+    }
+
+    /// Whether this step contains a random choice or a degraded step
+    /// anywhere inside it
+    pub fn has_choices(&self) -> bool {
+        match self {
+            PatternStep::Choice(_) | PatternStep::Degraded(..) => true,
+            PatternStep::Group(steps)
+            | PatternStep::Tuplet(steps)
+            | PatternStep::Alternation(steps)
+            | PatternStep::Polymeter(steps, _) => steps.iter().any(|s| s.has_choices()),
+            PatternStep::Polyrhythm(sub_patterns) => sub_patterns
+                .iter()
+                .any(|sub| sub.iter().any(|s| s.has_choices())),
+            PatternStep::Repeat(inner, _)
+            | PatternStep::Weighted(inner, _)
+            | PatternStep::Euclidean(inner, ..)
+            | PatternStep::Velocity(inner, _)
+            | PatternStep::Speed(inner, _)
+            | PatternStep::Pan(inner, _) => inner.has_choices(),
+            PatternStep::Note(_)
+            | PatternStep::Chord(_)
+            | PatternStep::Rest
+            | PatternStep::Variable(_)
+            | PatternStep::Drum(_) => false,
         }
     }
+
+    /// Replace every random choice with one of its options, and every
+    /// degraded step with a rest or what it wraps, drawing from `rng` in step
+    /// order so each choice is picked independently
+    pub fn choose(&self, rng: &mut SeededRng) -> PatternStep {
+        match self {
+            PatternStep::Choice(options) if !options.is_empty() => {
+                let index = (rng.next_u64() % options.len() as u64) as usize;
+                options[index].choose(rng)
+            }
+            PatternStep::Choice(_) => PatternStep::Rest,
+            PatternStep::Degraded(inner, percent, keep) => {
+                // Draw before the inner choices so a step's fate doesn't depend on them
+                let dropped = (rng.next_f64() * 100.0 < *percent as f64) != *keep;
+                if dropped {
+                    // A dropped step keeps its share of the cycle
+                    match inner.weight() {
+                        1 => PatternStep::Rest,
+                        weight => PatternStep::Weighted(Box::new(PatternStep::Rest), weight),
+                    }
+                } else {
+                    inner.choose(rng)
+                }
+            }
+            PatternStep::Group(steps) => {
+                PatternStep::Group(steps.iter().map(|s| s.choose(rng)).collect())
+            }
+            PatternStep::Tuplet(steps) => {
+                PatternStep::Tuplet(steps.iter().map(|s| s.choose(rng)).collect())
+            }
+            PatternStep::Alternation(steps) => {
+                PatternStep::Alternation(steps.iter().map(|s| s.choose(rng)).collect())
+            }
+            PatternStep::Polymeter(steps, per_cycle) => {
+                PatternStep::Polymeter(steps.iter().map(|s| s.choose(rng)).collect(), *per_cycle)
+            }
+            PatternStep::Polyrhythm(sub_patterns) => PatternStep::Polyrhythm(
+                sub_patterns
+                    .iter()
+                    .map(|sub| sub.iter().map(|s| s.choose(rng)).collect())
+                    .collect(),
+            ),
+            PatternStep::Repeat(inner, count) => {
+                PatternStep::Repeat(Box::new(inner.choose(rng)), *count)
+            }
+            PatternStep::Weighted(inner, weight) => {
+                PatternStep::Weighted(Box::new(inner.choose(rng)), *weight)
+            }
+            PatternStep::Euclidean(inner, pulses, steps, rotation) => {
+                PatternStep::Euclidean(Box::new(inner.choose(rng)), *pulses, *steps, *rotation)
+            }
+            PatternStep::Velocity(inner, vel) => {
+                PatternStep::Velocity(Box::new(inner.choose(rng)), *vel)
+            }
+            PatternStep::Speed(inner, speed) => {
+                PatternStep::Speed(Box::new(inner.choose(rng)), *speed)
+            }
+            PatternStep::Pan(inner, pan) => PatternStep::Pan(Box::new(inner.choose(rng)), *pan),
+            other => other.clone(),
+        }
+    }
+}
+
+/// The steps a polymeter plays in `cycle`: `per_cycle` of them, continuing
+/// from where the previous cycle stopped
+fn polymeter_slots(
+    steps: &[PatternStep],
+    per_cycle: usize,
+    cycle: usize,
+) -> impl Iterator<Item = &PatternStep> {
+    let start = cycle.wrapping_mul(per_cycle);
+    (0..per_cycle)
+        .filter(|_| !steps.is_empty())
+        .map(move |i| &steps[start.wrapping_add(i) % steps.len()])
 }
 
+/// Chord steps are shown as their symbol, e.g. `Cmaj7` or `Gmaj`, when that
+/// symbol reads back as the same chord. The alternate form (`{:#}`) always
+/// shows the notes, as mini-notation like `[C,E,G]`; so does any chord
+/// without such a symbol.
 impl fmt::Display for PatternStep {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             PatternStep::Note(n) => write!(f, "{}", n),
-            PatternStep::Chord(c) => write!(f, "{}", c),
+            PatternStep::Chord(c) => match c.script_symbol().filter(|_| !f.alternate()) {
+                Some(symbol) => write!(f, "{}", symbol),
+                None => {
+                    let notes: Vec<String> = c.notes_vec().iter().map(|n| n.to_string()).collect();
+                    write!(f, "[{}]", notes.join(","))
+                }
+            },
             PatternStep::Rest => write!(f, "_"),
             PatternStep::Group(steps) => {
                 write!(f, "[")?;
@@ -416,26 +826,46 @@ impl fmt::Display for PatternStep {
                     if i > 0 {
                         write!(f, " ")?;
                     }
-                    write!(f, "{}", s)?;
+                    fmt::Display::fmt(s, f)?;
                 }
                 write!(f, "]")
             }
-            PatternStep::Repeat(step, count) => write!(f, "{}*{}", step, count),
+            PatternStep::Tuplet(steps) => {
+                write!(f, "t(")?;
+                for (i, s) in steps.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " ")?;
+                    }
+                    fmt::Display::fmt(s, f)?;
+                }
+                write!(f, ")")
+            }
+            PatternStep::Repeat(step, count) => {
+                fmt::Display::fmt(step, f)?;
+                write!(f, "*{}", count)
+            }
             PatternStep::Variable(name) => write!(f, "{}", name),
             PatternStep::Drum(d) => write!(f, "{}", d),
-            PatternStep::Weighted(inner, weight) => write!(f, "{}@{}", inner, weight),
+            PatternStep::Weighted(inner, weight) => {
+                fmt::Display::fmt(inner, f)?;
+                write!(f, "@{}", weight)
+            }
             PatternStep::Alternation(steps) => {
                 write!(f, "<")?;
                 for (i, s) in steps.iter().enumerate() {
                     if i > 0 {
                         write!(f, " ")?;
                     }
-                    write!(f, "{}", s)?;
+                    fmt::Display::fmt(s, f)?;
                 }
                 write!(f, ">")
             }
-            PatternStep::Euclidean(inner, pulses, steps) => {
-                write!(f, "{}({},{})", inner, pulses, steps)
+            PatternStep::Euclidean(inner, pulses, steps, rotation) => {
+                fmt::Display::fmt(inner, f)?;
+                match rotation {
+                    0 => write!(f, "({},{})", pulses, steps),
+                    _ => write!(f, "({},{},{})", pulses, steps, rotation),
+                }
             }
             PatternStep::Polyrhythm(sub_patterns) => {
                 write!(f, "{{")?;
@@ -447,13 +877,42 @@ impl fmt::Display for PatternStep {
                         if j > 0 {
                             write!(f, " ")?;
                         }
-                        write!(f, "{}", step)?;
+                        fmt::Display::fmt(step, f)?;
                     }
                 }
                 write!(f, "}}")
             }
+            PatternStep::Polymeter(steps, per_cycle) => {
+                write!(f, "{{")?;
+                for (i, s) in steps.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " ")?;
+                    }
+                    fmt::Display::fmt(s, f)?;
+                }
+                write!(f, "}}%{}", per_cycle)
+            }
             PatternStep::Velocity(inner, vel) => {
-                write!(f, "{}({})", inner, vel)
+                fmt::Display::fmt(inner, f)?;
+                write!(f, "({})", vel)
+            }
+            PatternStep::Speed(inner, speed) => {
+                fmt::Display::fmt(inner, f)?;
+                write!(f, ":{}", speed)
+            }
+            // Pan has no mini-notation, like a pattern's pan
+            PatternStep::Pan(inner, _) => fmt::Display::fmt(inner, f),
+            // Nor does degrading
+            PatternStep::Degraded(inner, ..) => fmt::Display::fmt(inner, f),
+            PatternStep::Choice(options) => {
+                write!(f, "[")?;
+                for (i, s) in options.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, "|")?;
+                    }
+                    fmt::Display::fmt(s, f)?;
+                }
+                write!(f, "]")
             }
         }
     }
@@ -1,11 +1,14 @@
 //! Tests for pattern module.
 
 use super::core::Pattern;
-use super::euclidean::bjorklund;
+use super::euclidean::{bjorklund, bjorklund_rotated, euclidean};
+use super::event::PlaybackEvent;
 use super::every::EveryPattern;
+use super::morph::morph_events;
+use super::probability::ProbabilityPattern;
 use super::step::PatternStep;
 use crate::types::time::beats;
-use crate::types::Chord;
+use crate::types::{Chord, Note};
 use num_rational::Ratio;
 
 #[test]
@@ -59,16 +62,28 @@ fn test_step_beats() {
 
 #[test]
 fn test_fast() {
-    let p = Pattern::parse("C E").unwrap().fast(2);
+    let p = Pattern::parse("C E").unwrap().fast(beats(2));
     assert_eq!(p.beats_per_cycle, beats(2)); // Now plays in 2 beats
 }
 
 #[test]
 fn test_slow() {
-    let p = Pattern::parse("C E").unwrap().slow(2);
+    let p = Pattern::parse("C E").unwrap().slow(beats(2));
     assert_eq!(p.beats_per_cycle, beats(8)); // Now takes 8 beats
 }
 
+#[test]
+fn test_fractional_fast_and_slow() {
+    let p = Pattern::parse("C E").unwrap();
+    assert_eq!(
+        p.clone().fast(Ratio::new(3, 2)).beats_per_cycle,
+        Ratio::new(8, 3)
+    );
+    assert_eq!(p.clone().slow(Ratio::new(5, 2)).beats_per_cycle, beats(10));
+    // Whole-number ratios match the integer factors
+    assert_eq!(p.clone().fast(Ratio::new(4, 2)), p.fast(beats(2)));
+}
+
 #[test]
 fn test_rev() {
     let p = Pattern::parse("C D E").unwrap().rev();
@@ -94,6 +109,13 @@ fn test_display() {
     assert_eq!(format!("{}", p), "\"C E G\"");
 }
 
+#[test]
+fn test_chord_display_parses_back() {
+    let p = Pattern::parse("[C,E,G] C [G,B,D,F]*2 [E,G,C] _").unwrap();
+    let shown = format!("{:#}", p);
+    assert_eq!(Pattern::parse(shown.trim_matches('"')).unwrap(), p);
+}
+
 #[test]
 fn test_parse_flat_notes() {
     let p = Pattern::parse("Bb Eb Ab").unwrap();
@@ -292,6 +314,38 @@ fn test_rotate_left() {
     }
 }
 
+#[test]
+fn test_to_register_moves_by_octaves() {
+    // A melody around C6 moved to a cello center (C3) drops three octaves
+    let melody = Pattern::parse("C6 E6 G6 [C6, E6]").unwrap();
+    let low = melody.clone().to_register("C3".parse().unwrap());
+    let octaves = |p: &Pattern| -> Vec<(u8, i8)> {
+        p.get_all_notes()
+            .iter()
+            .map(|n| (n.pitch_class(), n.octave()))
+            .collect()
+    };
+    assert_eq!(octaves(&low), vec![(0, 3), (4, 3), (7, 3)]);
+    assert_eq!(
+        octaves(&melody)
+            .iter()
+            .map(|&(pc, octave)| (pc, octave - 3))
+            .collect::<Vec<_>>(),
+        octaves(&low)
+    );
+
+    // A wide range is centered on its mean: C2..C6 averages C4, so it stays put
+    let wide = Pattern::parse("C2 C6").unwrap();
+    assert_eq!(
+        wide.clone().to_register("D4".parse().unwrap()).to_string(),
+        wide.to_string()
+    );
+    assert_eq!(
+        wide.to_register("C5".parse().unwrap()).to_string(),
+        "\"C3 C7\""
+    );
+}
+
 #[test]
 fn test_take() {
     let p = Pattern::parse("C D E F").unwrap().take(2);
@@ -358,6 +412,349 @@ fn test_stutter() {
     }
 }
 
+#[test]
+fn test_offbeat() {
+    let p = Pattern::parse("C E").unwrap().offbeat();
+    assert_eq!(p.steps.len(), 4); // _ C _ E
+    assert!(matches!(&p.steps[0], PatternStep::Rest));
+    assert!(matches!(&p.steps[2], PatternStep::Rest));
+    assert_eq!(p.beats_per_cycle, beats(4));
+
+    let onsets = |p: &Pattern| -> Vec<(Ratio<i64>, Ratio<i64>)> {
+        p.to_rich_events()
+            .iter()
+            .filter(|e| !e.is_rest)
+            .map(|e| (e.start_beat, e.duration))
+            .collect()
+    };
+    assert_eq!(onsets(&p), vec![(beats(1), beats(1)), (beats(3), beats(1))]);
+
+    // Still on the off-beats when sped up, either before or after
+    let half = Ratio::new(1, 2);
+    let expected = vec![(half, half), (Ratio::new(3, 2), half)];
+    assert_eq!(onsets(&p.clone().fast(beats(2))), expected);
+    assert_eq!(
+        onsets(&Pattern::parse("C E").unwrap().fast(beats(2)).offbeat()),
+        expected
+    );
+
+    // A weighted step keeps its proportion: the rest matches its weight
+    let p = Pattern::parse("C@3 E").unwrap().offbeat();
+    assert_eq!(
+        onsets(&p),
+        vec![
+            (Ratio::new(3, 2), Ratio::new(3, 2)),
+            (Ratio::new(7, 2), half)
+        ]
+    );
+}
+
+#[test]
+fn test_swing_n() {
+    let starts = |p: &Pattern| -> Vec<Ratio<i64>> {
+        p.to_rich_events().iter().map(|e| e.start_beat).collect()
+    };
+    let sixteenths = Pattern::parse("C*16").unwrap();
+    let straight = starts(&sixteenths);
+
+    // 16th swing delays the 2nd and 4th sixteenth of every beat by half a 16th
+    let swung = starts(&sixteenths.clone().swing_n(16, 50));
+    let delay = Ratio::new(1, 8);
+    for (i, (swung, straight)) in swung.iter().zip(&straight).enumerate() {
+        let expected = if i % 2 == 1 {
+            straight + delay
+        } else {
+            *straight
+        };
+        assert_eq!(*swung, expected, "sixteenth {}", i);
+    }
+
+    // 8th swing delays only the off-beat eighths: the 3rd sixteenth of each beat
+    let swung = starts(&sixteenths.clone().swing_n(8, 50));
+    let delay = Ratio::new(1, 4);
+    for (i, (swung, straight)) in swung.iter().zip(&straight).enumerate() {
+        let expected = if i % 4 == 2 {
+            straight + delay
+        } else {
+            *straight
+        };
+        assert_eq!(*swung, expected, "sixteenth {}", i);
+    }
+
+    // The note before a delayed one stretches to meet it
+    let events = Pattern::parse("C D")
+        .unwrap()
+        .fast(beats(4))
+        .swing_n(8, 50)
+        .to_rich_events();
+    assert_eq!(events[0].duration, Ratio::new(3, 4));
+    assert_eq!(events[1].start_beat, Ratio::new(3, 4));
+    assert_eq!(events[1].duration, Ratio::new(1, 4));
+
+    // A grid finer than the steps has nothing to swing
+    let quarters = Pattern::parse("C D E F").unwrap();
+    assert_eq!(
+        quarters.clone().swing_n(16, 50).to_rich_events(),
+        quarters.to_rich_events()
+    );
+}
+
+#[test]
+fn test_scatter() {
+    let p = Pattern::parse("C D E F G A B C5").unwrap();
+    let straight = p.to_rich_events();
+    let scattered = p.clone().scatter(20, 7).to_rich_events();
+
+    // Every note moves by at most a fifth of its half-beat step, and still ends on the grid
+    let limit = Ratio::new(1, 10);
+    for (moved, grid) in scattered.iter().zip(&straight) {
+        let nudge = moved.start_beat - grid.start_beat;
+        assert!(-limit <= nudge && nudge <= limit);
+        assert!(moved.start_beat >= beats(0));
+        assert_eq!(
+            moved.start_beat + moved.duration,
+            grid.start_beat + grid.duration
+        );
+    }
+    assert_ne!(scattered, straight);
+
+    // The seed fixes the nudges
+    assert_eq!(scattered, p.clone().scatter(20, 7).to_rich_events());
+    assert_ne!(scattered, p.clone().scatter(20, 8).to_rich_events());
+    assert_eq!(p.clone().scatter(0, 7).to_rich_events(), straight);
+}
+
+#[test]
+fn test_strum() {
+    let notes = |p: &Pattern| -> Vec<(String, Ratio<i64>, Ratio<i64>)> {
+        p.to_rich_events()
+            .iter()
+            .filter(|e| !e.is_rest)
+            .map(|e| (e.notes[0].name.clone(), e.start_beat, e.duration))
+            .collect()
+    };
+    let triad = Pattern::parse("[C, E, G] D").unwrap().slow(beats(1));
+
+    // Each note of the chord starts a tenth of a beat after the one below,
+    // and they all end with the step. The single note is left alone.
+    let tenth = Ratio::new(1, 10);
+    assert_eq!(
+        notes(&triad.clone().strum(10, false)),
+        vec![
+            ("C4".to_string(), beats(0), beats(2)),
+            ("E4".to_string(), tenth, beats(2) - tenth),
+            ("G4".to_string(), tenth * 2, beats(2) - tenth * 2),
+            ("D4".to_string(), beats(2), beats(2)),
+        ]
+    );
+
+    // Downstrokes start from the top
+    let down: Vec<String> = notes(&triad.clone().strum(10, true))
+        .into_iter()
+        .map(|(name, _, _)| name)
+        .collect();
+    assert_eq!(down, vec!["G4", "E4", "C4", "D4"]);
+
+    // A spread wider than the step is squeezed so every note starts inside it
+    let starts: Vec<Ratio<i64>> = notes(&triad.strum(100, false))
+        .into_iter()
+        .map(|(_, start, _)| start)
+        .collect();
+    let third = Ratio::new(2, 3);
+    assert_eq!(starts, vec![beats(0), third, third * 2, beats(2)]);
+}
+
+#[test]
+fn test_linger() {
+    let notes = |p: &Pattern| -> Vec<(String, Ratio<i64>, Ratio<i64>)> {
+        p.to_rich_events()
+            .iter()
+            .filter(|e| !e.is_rest)
+            .map(|e| (e.notes[0].name.clone(), e.start_beat, e.duration))
+            .collect()
+    };
+    let half = Ratio::new(1, 2);
+    let lingered = Pattern::parse("C E G D").unwrap().linger(half);
+    assert_eq!(notes(&lingered), notes(&Pattern::parse("C E C E").unwrap()));
+
+    // A cut inside a step shortens it: the first half of "C E G" is C and half of E
+    let lingered = Pattern::parse("C E G").unwrap().linger(half);
+    let third = Ratio::new(4, 3);
+    let sixth = Ratio::new(2, 3);
+    assert_eq!(
+        notes(&lingered),
+        vec![
+            ("C4".to_string(), beats(0), third),
+            ("E4".to_string(), third, sixth),
+            ("C4".to_string(), beats(2), third),
+            ("E4".to_string(), beats(2) + third, sixth),
+        ]
+    );
+
+    // A quarter repeats four times; the whole cycle or out of range leaves it alone
+    let p = Pattern::parse("C E G D").unwrap();
+    assert_eq!(
+        notes(&p.clone().linger(Ratio::new(1, 4))),
+        notes(&Pattern::parse("C C C C").unwrap())
+    );
+    assert_eq!(p.clone().linger(beats(1)), p);
+    assert_eq!(p.clone().linger(beats(0)), p);
+}
+
+#[test]
+fn test_trunc() {
+    let p = Pattern::parse("C E G D").unwrap().trunc(Ratio::new(3, 4));
+    assert_eq!(p.to_string(), "\"C E G _\"");
+    assert_eq!(p.beats_per_cycle, beats(4));
+
+    let p = Pattern::parse("C@3 E").unwrap().trunc(Ratio::new(1, 2));
+    let sounding: Vec<_> = p
+        .to_rich_events()
+        .iter()
+        .filter(|e| !e.is_rest)
+        .map(|e| (e.start_beat, e.duration))
+        .collect();
+    assert_eq!(sounding, vec![(beats(0), beats(2))]);
+}
+
+#[test]
+fn test_zoom() {
+    let notes = |p: &Pattern| -> Vec<(String, Ratio<i64>, Ratio<i64>)> {
+        p.to_rich_events()
+            .iter()
+            .filter(|e| !e.is_rest)
+            .map(|e| (e.notes[0].name.clone(), e.start_beat, e.duration))
+            .collect()
+    };
+    let half = Ratio::new(1, 2);
+
+    // The first half, stretched over the cycle
+    let zoomed = Pattern::parse("C E G D").unwrap().zoom(beats(0), half);
+    assert_eq!(zoomed.to_string(), "\"C E\"");
+    assert_eq!(zoomed.beats_per_cycle, beats(4));
+
+    // Cuts fall inside groups and repeats, and inside a step it is shortened
+    let zoomed = Pattern::parse("C [E G] D*4")
+        .unwrap()
+        .zoom(Ratio::new(1, 2), Ratio::new(19, 24));
+    assert_eq!(zoomed.to_string(), "\"G@4 D@2 D\"");
+    let zoomed = Pattern::parse("C E G")
+        .unwrap()
+        .zoom(Ratio::new(1, 6), half);
+    assert_eq!(
+        notes(&zoomed),
+        vec![
+            ("C4".to_string(), beats(0), beats(2)),
+            ("E4".to_string(), beats(2), beats(2)),
+        ]
+    );
+
+    // The whole cycle, or an empty or out-of-range window, leaves it alone
+    let p = Pattern::parse("C E@2 G").unwrap();
+    assert_eq!(notes(&p.clone().zoom(beats(0), beats(1))), notes(&p));
+    assert_eq!(p.clone().zoom(half, half), p);
+    assert_eq!(p.clone().zoom(half, beats(2)), p);
+}
+
+#[test]
+fn test_iter() {
+    let p = Pattern::parse("C D E F").unwrap();
+    let at = |n: usize, cycle: usize| p.clone().iter(n, cycle).to_string();
+    assert_eq!(at(4, 0), "\"C D E F\"");
+    assert_eq!(at(4, 1), "\"D E F C\"");
+    assert_eq!(at(4, 3), "\"F C D E\"");
+    assert_eq!(at(4, 4), "\"C D E F\"");
+    assert_eq!(at(2, 1), "\"E F C D\"");
+
+    // Groups stay whole when a section starts on a step
+    let grouped = Pattern::parse("[C D] E").unwrap().iter(2, 1);
+    assert_eq!(grouped.to_string(), "\"E [C D]\"");
+
+    // A section boundary inside a step splits it
+    let split = Pattern::parse("C E G").unwrap().iter(2, 1);
+    assert_eq!(split.to_string(), "\"E G@2 C@2 E\"");
+    assert_eq!(p.clone().iter(0, 3), p);
+}
+
+#[test]
+fn test_diff() {
+    use super::core::PatternChange;
+
+    let p = Pattern::parse("C E G").unwrap();
+    let lines =
+        |other: Pattern| -> Vec<String> { p.diff(&other).iter().map(|c| c.to_string()).collect() };
+
+    assert!(p.diff(&p).is_empty());
+    assert_eq!(p.diff(&p.clone().rev()), vec![PatternChange::Reversed]);
+    assert_eq!(
+        lines(p.clone().transpose(2)),
+        vec!["transposed up 2 semitones"]
+    );
+    assert_eq!(
+        lines(Pattern::parse("C F G").unwrap()),
+        vec!["step 1: E -> F (up 1 semitone)"]
+    );
+    assert_eq!(
+        lines(Pattern::parse("C [E,G] G").unwrap()),
+        vec!["step 1: E -> [E,G]"]
+    );
+
+    // An inserted or removed step doesn't shift the ones after it
+    assert_eq!(
+        lines(Pattern::parse("C D E G").unwrap()),
+        vec!["step 1: added D", "length: 3 -> 4 steps"]
+    );
+    assert_eq!(
+        lines(Pattern::parse("E G").unwrap()),
+        vec!["step 0: removed C", "length: 3 -> 2 steps"]
+    );
+
+    assert_eq!(lines(p.clone().fast(beats(2))), vec!["cycle: 4 -> 2 beats"]);
+    assert_eq!(
+        p.diff(&p.clone().env_preset("pluck")),
+        vec![PatternChange::Settings]
+    );
+}
+
+#[test]
+fn test_replace_insert_remove_steps() {
+    let p = Pattern::parse("C E G").unwrap();
+    let d = PatternStep::Note("D".parse().unwrap());
+
+    assert_eq!(
+        p.clone().replace_step(1, d.clone()).unwrap().to_string(),
+        "\"C D G\""
+    );
+    assert_eq!(
+        p.clone().replace_step(-1, d.clone()).unwrap().to_string(),
+        "\"C E D\""
+    );
+
+    let inserted = p.clone().insert_step(1, d.clone()).unwrap();
+    assert_eq!(inserted.to_string(), "\"C D E G\"");
+    assert_eq!(inserted.steps.len(), 4);
+    assert_eq!(
+        p.clone().insert_step(-1, d.clone()).unwrap().to_string(),
+        "\"C E G D\""
+    );
+    assert_eq!(
+        p.clone().insert_step(3, d.clone()).unwrap().to_string(),
+        "\"C E G D\""
+    );
+
+    let removed = p.clone().remove_step(0).unwrap();
+    assert_eq!(removed.to_string(), "\"E G\"");
+    assert_eq!(removed.steps.len(), 2);
+    assert_eq!(p.clone().remove_step(-1).unwrap().to_string(), "\"C E\"");
+
+    // Out of bounds, either way
+    assert!(p.clone().replace_step(3, d.clone()).is_err());
+    assert!(p.clone().replace_step(-4, d.clone()).is_err());
+    assert!(p.clone().insert_step(4, d).is_err());
+    assert!(p.clone().remove_step(3).is_err());
+    assert!(Pattern::new().remove_step(0).is_err());
+}
+
 #[test]
 fn test_concat() {
     let p1 = Pattern::parse("C D").unwrap();
@@ -488,6 +885,109 @@ fn test_alternation_unclosed_fails() {
     assert!(result.is_err());
 }
 
+#[test]
+fn test_polymeter_drifts_across_cycles() {
+    let p = Pattern::parse("{C E G}%4").unwrap();
+    let names = |cycle| -> Vec<String> {
+        p.to_rich_events_for_cycle(cycle)
+            .iter()
+            .map(|e| e.notes[0].name.clone())
+            .collect()
+    };
+    assert_eq!(names(0), vec!["C4", "E4", "G4", "C4"]);
+    assert_eq!(names(1), vec!["E4", "G4", "C4", "E4"]);
+    assert_eq!(names(2), vec!["G4", "C4", "E4", "G4"]);
+    assert_eq!(names(3), names(0));
+
+    // Four equal steps per cycle, next to a plain step
+    let events = Pattern::parse("{C E G}%4 D")
+        .unwrap()
+        .to_rich_events_for_cycle(1);
+    assert_eq!(events.len(), 5);
+    assert_eq!(events[1].start_beat, Ratio::new(1, 2));
+    assert_eq!(events[1].duration, Ratio::new(1, 2));
+    assert_eq!(events[4].start_beat, beats(2));
+}
+
+#[test]
+fn test_polymeter_without_count_is_a_group() {
+    let plain = Pattern::parse("{C E G} D").unwrap();
+    let group = Pattern::parse("[C E G] D").unwrap();
+    for cycle in 0..3 {
+        assert_eq!(
+            plain.to_rich_events_for_cycle(cycle),
+            group.to_rich_events_for_cycle(cycle)
+        );
+    }
+}
+
+#[test]
+fn test_polymeter_display_and_errors() {
+    let p = Pattern::parse("{C E G}%4@2 D").unwrap();
+    assert_eq!(p.to_string(), "\"{C E G}%4@2 D\"");
+    assert_eq!(p.transpose(2).to_string(), "\"{D F# A}%4@2 E\"");
+
+    assert!(Pattern::parse("{C E}%").is_err());
+    assert!(Pattern::parse("{C E}%0").is_err());
+    assert!(Pattern::parse("{}%4").is_err());
+    assert!(Pattern::parse("{C E, G}%4").is_err());
+}
+
+#[test]
+fn test_tuplet_fills_one_step() {
+    // A triplet on beat two
+    let p = Pattern::parse("C2 t(E G A) C2 C2").unwrap();
+    assert_eq!(p.steps.len(), 4);
+    let events = p.to_rich_events();
+    assert_eq!(events.len(), 6);
+    assert_eq!(events[1].start_beat, beats(1));
+    assert_eq!(events[2].start_beat, Ratio::new(4, 3));
+    assert_eq!(events[3].start_beat, Ratio::new(5, 3));
+    assert_eq!(events[3].duration, Ratio::new(1, 3));
+    assert_eq!(events[4].start_beat, beats(2));
+
+    // Inside a repeat, each repetition is its own tuplet
+    let repeated = Pattern::parse("t(E G A)*2 C").unwrap().to_rich_events();
+    assert_eq!(repeated.len(), 7);
+    assert_eq!(repeated[3].start_beat, beats(1));
+    assert_eq!(repeated[1].duration, Ratio::new(1, 3));
+    assert_eq!(repeated[6].start_beat, beats(2));
+}
+
+#[test]
+fn test_tuplet_parsing() {
+    // Plays like a group, but keeps its spelling
+    let tuplet = Pattern::parse("t(E G A)@2 D").unwrap();
+    assert_eq!(
+        tuplet.to_rich_events(),
+        Pattern::parse("[E G A]@2 D").unwrap().to_rich_events()
+    );
+    assert_eq!(tuplet.to_string(), "\"t(E G A)@2 D\"");
+    let nested = Pattern::parse("t([C,E] t(D F) _)").unwrap();
+    assert_eq!(
+        nested.to_rich_events(),
+        Pattern::parse("[[C,E] [D F] _]").unwrap().to_rich_events()
+    );
+    assert_eq!(nested.to_string(), "\"t([C,E] t(D F) _)\"");
+    for notation in [
+        "t(E G A)@2 D",
+        "t([C,E] t(D F) _)",
+        "C t(E G A)*2 [D t(F A)]",
+    ] {
+        let p = Pattern::parse(notation).unwrap();
+        let shown = p.to_string();
+        assert_eq!(Pattern::parse(shown.trim_matches('"')).unwrap(), p);
+    }
+
+    // `t` is also the tom: numbers in brackets are still velocity and Euclidean
+    let tom = Pattern::parse("t(100) t(3,8)").unwrap();
+    assert!(matches!(tom.steps[0], PatternStep::Velocity(_, 100)));
+    assert!(matches!(tom.steps[1], PatternStep::Euclidean(_, 3, 8, 0)));
+
+    assert!(Pattern::parse("t()").is_err());
+    assert!(Pattern::parse("t(E G").is_err());
+}
+
 // ========================================================================
 // EveryPattern Tests
 // ========================================================================
@@ -645,6 +1145,38 @@ fn test_every_pattern_pattern_for_cycle_clone() {
     assert_eq!(cloned_transformed.steps[0], every.transformed.steps[0]);
 }
 
+// ========================================================================
+// ProbabilityPattern Tests
+// ========================================================================
+
+#[test]
+fn test_probability_pattern_cycle_selection() {
+    let base = Pattern::parse("C D E F").unwrap();
+    let transformed = base.clone().rev();
+
+    let never = ProbabilityPattern::new(0, 1, base.clone(), transformed.clone());
+    let always = ProbabilityPattern::new(100, 1, base.clone(), transformed.clone());
+    for cycle in 0..16 {
+        assert_eq!(never.get_pattern_for_cycle(cycle), &base);
+        assert_eq!(always.get_pattern_for_cycle(cycle), &transformed);
+    }
+
+    // The choice for a cycle is fixed by the seed
+    let sometimes = ProbabilityPattern::new(50, 1, base.clone(), transformed.clone());
+    let again = ProbabilityPattern::new(50, 1, base.clone(), transformed.clone());
+    let picks: Vec<bool> = (0..32).map(|c| sometimes.is_transformed(c)).collect();
+    assert_eq!(
+        picks,
+        (0..32).map(|c| again.is_transformed(c)).collect::<Vec<_>>()
+    );
+    assert!(picks.contains(&true) && picks.contains(&false));
+
+    assert_eq!(
+        ProbabilityPattern::new(150, 1, base, transformed).probability,
+        100
+    );
+}
+
 // ========================================================================
 // Weighted Steps Tests
 // ========================================================================
@@ -824,9 +1356,10 @@ fn test_euclidean_parse_simple() {
     let p = Pattern::parse("C(3,8)").unwrap();
     assert_eq!(p.steps.len(), 1);
     match &p.steps[0] {
-        PatternStep::Euclidean(inner, pulses, steps) => {
+        PatternStep::Euclidean(inner, pulses, steps, rotation) => {
             assert_eq!(*pulses, 3);
             assert_eq!(*steps, 8);
+            assert_eq!(*rotation, 0);
             assert!(matches!(inner.as_ref(), PatternStep::Note(_)));
         }
         _ => panic!("Expected Euclidean step"),
@@ -841,7 +1374,7 @@ fn test_euclidean_parse_with_weight() {
         PatternStep::Weighted(inner_weighted, weight) => {
             assert_eq!(*weight, 2);
             match inner_weighted.as_ref() {
-                PatternStep::Euclidean(_, pulses, steps) => {
+                PatternStep::Euclidean(_, pulses, steps, _) => {
                     assert_eq!(*pulses, 3);
                     assert_eq!(*steps, 8);
                 }
@@ -859,12 +1392,44 @@ fn test_euclidean_parse_with_repeat() {
     match &p.steps[0] {
         PatternStep::Repeat(inner, count) => {
             assert_eq!(*count, 2);
-            assert!(matches!(inner.as_ref(), PatternStep::Euclidean(_, 3, 8)));
+            assert!(matches!(inner.as_ref(), PatternStep::Euclidean(_, 3, 8, 0)));
         }
         _ => panic!("Expected Repeat step"),
     }
 }
 
+#[test]
+fn test_euclidean_rotation() {
+    let onsets = |source: &str| -> Vec<usize> {
+        let p = Pattern::parse(source).unwrap();
+        p.steps[0]
+            .to_step_info_for_cycle(0)
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, _, _, is_rest))| !is_rest)
+            .map(|(i, _)| i)
+            .collect()
+    };
+
+    assert_eq!(onsets("C(3,8,0)"), vec![0, 3, 6]);
+    assert_eq!(onsets("C(3,8,0)"), onsets("C(3,8)"));
+    assert_eq!(onsets("C(3,8,2)"), vec![1, 4, 6]);
+    // Rotation wraps, and negative rotations go the other way
+    assert_eq!(onsets("C(3,8,10)"), onsets("C(3,8,2)"));
+    assert_eq!(onsets("C(5,8,-1)"), vec![1, 3, 4, 6, 7]);
+    assert_eq!(bjorklund_rotated(5, 8, -1), {
+        let mut rhythm = bjorklund(5, 8);
+        rhythm.rotate_right(1);
+        rhythm
+    });
+
+    // Rotation round-trips through display and survives transposition
+    let p = Pattern::parse("kick(3,8,2) C(5,8,-1)").unwrap();
+    assert_eq!(p.to_string(), "\"kick(3,8,2) C(5,8,-1)\"");
+    assert_eq!(p.transpose(2).to_string(), "\"kick(3,8,2) D(5,8,-1)\"");
+    assert!(Pattern::parse("C(3,8,)").is_err());
+}
+
 #[test]
 fn test_euclidean_display() {
     let p = Pattern::parse("C(3,8) D").unwrap();
@@ -883,7 +1448,7 @@ fn test_euclidean_transpose() {
     let transposed = p.transpose(2);
 
     match &transposed.steps[0] {
-        PatternStep::Euclidean(inner, pulses, steps) => {
+        PatternStep::Euclidean(inner, pulses, steps, _) => {
             assert_eq!(*pulses, 3);
             assert_eq!(*steps, 8);
             match inner.as_ref() {
@@ -908,11 +1473,11 @@ fn test_euclidean_to_step_info_expansion() {
 
     let pulses: usize = events
         .iter()
-        .filter(|(notes, _, is_rest)| !is_rest && !notes.is_empty())
+        .filter(|(notes, _, _, is_rest)| !is_rest && !notes.is_empty())
         .count();
     let rests: usize = events
         .iter()
-        .filter(|(notes, _, is_rest)| *is_rest || notes.is_empty())
+        .filter(|(notes, _, _, is_rest)| *is_rest || notes.is_empty())
         .count();
 
     assert_eq!(pulses, 3, "Should have 3 pulses");
@@ -935,6 +1500,54 @@ fn test_euclidean_invalid_syntax() {
     assert!(Pattern::parse("C(3,0)").is_err());
 }
 
+/// Onsets as `x` and rests as `.`
+fn rhythm(pattern: &Pattern) -> String {
+    pattern
+        .steps
+        .iter()
+        .map(|step| if *step == PatternStep::Rest { '.' } else { 'x' })
+        .collect()
+}
+
+#[test]
+fn test_euclidean_named_rhythms() {
+    let c = [PatternStep::Note(Note::new(0).unwrap())];
+    let e =
+        |pulses, steps, rotation| rhythm(&euclidean(pulses, steps, rotation, &c, false).unwrap());
+
+    assert_eq!(e(3, 8, 0), "x..x..x."); // Cuban tresillo
+    assert_eq!(e(5, 8, 0), "x.xx.xx."); // Cuban cinquillo
+    assert_eq!(e(2, 5, 0), "x.x.."); // Persian khafif-e-ramal
+    assert_eq!(e(4, 9, 0), "x.x.x.x.."); // Turkish aksak
+    assert_eq!(e(7, 16, 0), "x..x.x.x..x.x.x."); // Brazilian samba
+
+    // Rotation starts the rhythm later, negative earlier
+    assert_eq!(e(3, 8, 3), "x..x.x..");
+    assert_eq!(e(3, 8, -2), "x.x..x..");
+    assert_eq!(e(3, 8, 11), e(3, 8, 3));
+}
+
+#[test]
+fn test_euclidean_onsets_and_inversion() {
+    let chords: Vec<PatternStep> = ["C", "F", "G"]
+        .iter()
+        .map(|root| PatternStep::Chord(Chord::from_note_strings(vec![root]).unwrap()))
+        .collect();
+    let p = euclidean(3, 8, 0, &chords, false).unwrap();
+    // Each onset takes the next chord
+    assert_eq!(p.steps[0], chords[0]);
+    assert_eq!(p.steps[3], chords[1]);
+    assert_eq!(p.steps[6], chords[2]);
+
+    let c = [PatternStep::Note(Note::new(0).unwrap())];
+    let inverted = euclidean(3, 8, 0, &c, true).unwrap();
+    assert_eq!(rhythm(&inverted), ".xx.xx.x");
+
+    assert!(euclidean(9, 8, 0, &c, false).is_err());
+    assert!(euclidean(1, 0, 0, &c, false).is_err());
+    assert!(euclidean(3, 8, 0, &[], false).is_err());
+}
+
 // ============================================================================
 // Velocity Syntax Tests
 // ============================================================================
@@ -1053,18 +1666,136 @@ fn test_velocity_applied_to_noteinfo() {
     let infos = step.to_step_info_for_cycle(0);
 
     assert_eq!(infos.len(), 1);
-    let (notes, _, is_rest) = &infos[0];
+    let (notes, _, _, is_rest) = &infos[0];
     assert!(!is_rest);
     assert_eq!(notes.len(), 1);
     assert_eq!(notes[0].velocity, 64);
 }
 
+#[test]
+fn test_speed_parse_and_frequency() {
+    let p = Pattern::parse("C:2").unwrap();
+    assert_eq!(
+        p.steps[0],
+        PatternStep::Speed(Box::new(PatternStep::Note("C".parse().unwrap())), 2.0)
+    );
+    let c: Note = "C".parse().unwrap();
+    let events = p.to_rich_events();
+    assert!((events[0].notes[0].frequency - c.frequency() * 2.0).abs() < 0.01);
+    // The MIDI note is unchanged
+    assert_eq!(events[0].notes[0].midi, c.midi_note());
+
+    // :1 is normal speed
+    assert_eq!(
+        Pattern::parse("C:1 D").unwrap(),
+        Pattern::parse("C D").unwrap()
+    );
+
+    // Chords, decimals, and the other suffixes
+    let p = Pattern::parse("[C,E](100):0.5@2 D:3*2").unwrap();
+    assert_eq!(p.to_string(), "\"[C,E](100):0.5@2 D:3*2\"");
+    let events = p.to_rich_events();
+    assert!((events[0].notes[1].frequency - 329.63 / 2.0).abs() < 0.01);
+
+    assert!(Pattern::parse("C:").is_err());
+    assert!(Pattern::parse("C:0").is_err());
+}
+
+#[test]
+fn test_choice_parse_and_display() {
+    let note = |name: &str| PatternStep::Note(name.parse().unwrap());
+    let p = Pattern::parse("C [E|G|B] _ C").unwrap();
+    assert_eq!(
+        p.steps[1],
+        PatternStep::Choice(vec![note("E"), note("G"), note("B")])
+    );
+    assert_eq!(p.to_string(), "\"C [E|G|B] _ C\"");
+
+    // Options can be groups, chords and choices of their own
+    let p = Pattern::parse("[C E|[C,E]|[D|F]]@2").unwrap();
+    assert_eq!(p.to_string(), "\"[[C E]|[C,E]|[D|F]]@2\"");
+    assert_eq!(Pattern::parse("[[C E]|[C,E]|[D|F]]@2").unwrap(), p);
+
+    assert!(Pattern::parse("[C|]").is_err());
+    assert!(Pattern::parse("[|C]").is_err());
+}
+
+#[test]
+fn test_choice_rerolls_every_cycle() {
+    let p = Pattern::parse("C [E|G|B] _ C").unwrap();
+    let picked =
+        |p: &Pattern, cycle: usize| p.to_rich_events_for_cycle(cycle)[1].notes[0].name.clone();
+
+    let picks: Vec<String> = (0..32).map(|cycle| picked(&p, cycle)).collect();
+    for name in ["E4", "G4", "B4"] {
+        assert!(
+            picks.iter().any(|n| n == name),
+            "{} never picked: {:?}",
+            name,
+            picks
+        );
+    }
+    // The same cycle always makes the same choice
+    assert_eq!(
+        picks,
+        (0..32).map(|cycle| picked(&p, cycle)).collect::<Vec<_>>()
+    );
+    // Other steps are untouched
+    assert_eq!(p.to_rich_events_for_cycle(5)[0].notes[0].name, "C4");
+
+    // A different seed makes a different set of choices
+    let seeded = p.clone().with_seed(7);
+    let seeded_picks: Vec<String> = (0..32).map(|cycle| picked(&seeded, cycle)).collect();
+    assert_ne!(picks, seeded_picks);
+    assert_eq!(
+        seeded_picks,
+        (0..32)
+            .map(|cycle| picked(&p.clone().with_seed(7), cycle))
+            .collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn test_choice_nested_in_groups_and_euclidean() {
+    // Two choices in one cycle are picked independently
+    let p = Pattern::parse("[[C|D] [E|F]]").unwrap();
+    let pairs: Vec<(String, String)> = (0..32)
+        .map(|cycle| {
+            let events = p.to_rich_events_for_cycle(cycle);
+            (
+                events[0].notes[0].name.clone(),
+                events[1].notes[0].name.clone(),
+            )
+        })
+        .collect();
+    assert!(pairs.iter().any(|(a, b)| a == "C4" && b == "F4"));
+    assert!(pairs.iter().any(|(a, b)| a == "D4" && b == "E4"));
+
+    // A chosen Euclidean pulse keeps its rhythm
+    let p = Pattern::parse("[C|E](3,8)").unwrap();
+    let mut names = Vec::new();
+    for cycle in 0..16 {
+        let events = p.to_rich_events_for_cycle(cycle);
+        let hits: Vec<&PlaybackEvent> = events.iter().filter(|e| !e.is_rest).collect();
+        assert_eq!(hits.len(), 3);
+        names.push(hits[0].notes[0].name.clone());
+    }
+    assert!(names.contains(&"C4".to_string()) && names.contains(&"E4".to_string()));
+
+    // Static contexts see the first option
+    assert_eq!(Pattern::parse("[E|G]").unwrap().get_all_notes().len(), 2);
+    assert_eq!(
+        Pattern::parse("[E|G]").unwrap().to_rich_events()[0].notes[0].name,
+        "E4"
+    );
+}
+
 #[test]
 fn test_euclidean_drum() {
     let p = Pattern::parse("kick(3,8)").unwrap();
     assert_eq!(p.steps.len(), 1);
     match &p.steps[0] {
-        PatternStep::Euclidean(inner, pulses, steps) => {
+        PatternStep::Euclidean(inner, pulses, steps, _) => {
             assert_eq!(*pulses, 3);
             assert_eq!(*steps, 8);
             assert!(matches!(inner.as_ref(), PatternStep::Drum(_)));
@@ -1148,6 +1879,87 @@ fn test_stack_with_rests() {
     }
 }
 
+#[test]
+fn test_shuffle_permutes_steps_per_cycle() {
+    let pattern = Pattern::parse("C D E F G A")
+        .unwrap()
+        .with_seed(3)
+        .shuffle();
+    let order = |cycle: usize| -> Vec<String> {
+        pattern
+            .to_rich_events_for_cycle(cycle)
+            .iter()
+            .map(|e| e.notes[0].name.clone())
+            .collect()
+    };
+
+    let mut sorted = order(0);
+    sorted.sort();
+    assert_eq!(sorted, vec!["A4", "C4", "D4", "E4", "F4", "G4"]);
+    assert_eq!(order(5), order(5));
+    assert!((1..8).any(|cycle| order(cycle) != order(0)));
+    // Timing stays on the grid
+    let starts: Vec<f32> = pattern
+        .to_rich_events_for_cycle(2)
+        .iter()
+        .map(|e| e.start_beat_f32())
+        .collect();
+    assert_eq!(starts.len(), 6);
+    assert!(starts.windows(2).all(|w| w[1] > w[0]));
+}
+
+#[test]
+fn test_scramble_picks_from_pattern() {
+    let pattern = Pattern::parse("C E G").unwrap().scramble();
+    assert_eq!(pattern.steps.len(), 3);
+    let names: Vec<String> = (0..8)
+        .flat_map(|cycle| pattern.to_rich_events_for_cycle(cycle))
+        .map(|e| e.notes[0].name.clone())
+        .collect();
+    assert_eq!(names.len(), 24);
+    assert!(names
+        .iter()
+        .all(|n| ["C4", "E4", "G4"].contains(&n.as_str())));
+}
+
+#[test]
+fn test_jux_pans_layers() {
+    let pattern = Pattern::parse("C E G _").unwrap();
+    let juxed = pattern.clone().jux(pattern.rev());
+    assert_eq!(juxed.beats_per_cycle, beats(4));
+
+    let events = juxed.to_rich_events();
+    let notes: Vec<Vec<(String, Option<f32>)>> = events
+        .iter()
+        .map(|e| e.notes.iter().map(|n| (n.name.clone(), n.pan)).collect())
+        .collect();
+    assert_eq!(
+        notes[0],
+        vec![("C4".to_string(), Some(0.0))],
+        "the reversed layer rests on the first step"
+    );
+    assert_eq!(
+        notes[1],
+        vec![("E4".to_string(), Some(0.0)), ("G4".to_string(), Some(1.0))]
+    );
+    assert_eq!(notes[3], vec![("C4".to_string(), Some(1.0))]);
+}
+
+#[test]
+fn test_jux_fast_layer_repeats() {
+    let pattern = Pattern::parse("C E").unwrap();
+    let juxed = pattern.clone().jux(pattern.fast(Ratio::from_integer(2)));
+    assert_eq!(juxed.beats_per_cycle, beats(4));
+
+    let right: Vec<f32> = juxed
+        .to_rich_events()
+        .iter()
+        .filter(|e| e.notes.iter().any(|n| n.pan == Some(1.0)))
+        .map(|e| e.start_beat_f32())
+        .collect();
+    assert_eq!(right, vec![0.0, 1.0, 2.0, 3.0]);
+}
+
 // ========================================================================
 // Polyrhythm Tests
 // ========================================================================
@@ -1296,3 +2108,215 @@ fn test_polyrhythm_3_over_2() {
     assert!(all_notes.contains(&"F4".to_string()), "Missing F");
     assert!(all_notes.contains(&"G4".to_string()), "Missing G");
 }
+
+#[test]
+fn test_scan_reveals_one_step_per_cycle() {
+    let p = Pattern::parse("C D E F").unwrap();
+    let sounding = |cycle: usize| {
+        p.clone()
+            .scan(cycle)
+            .to_rich_events()
+            .iter()
+            .filter(|e| !e.is_rest)
+            .count()
+    };
+
+    assert_eq!(sounding(0), 1);
+    assert_eq!(sounding(2), 3);
+    // Fully revealed: keeps looping the whole pattern
+    assert_eq!(sounding(3), 4);
+    assert_eq!(sounding(10), 4);
+
+    // Hidden steps are rests, so revealed steps keep their timing
+    assert_eq!(p.clone().scan(0).to_rich_events().len(), 4);
+}
+
+/// Which steps of a pattern sound in a cycle
+fn sounding_in_cycle(pattern: &Pattern, cycle: usize) -> Vec<bool> {
+    pattern
+        .to_rich_events_for_cycle(cycle)
+        .iter()
+        .map(|e| !e.is_rest)
+        .collect()
+}
+
+#[test]
+fn test_degrade_drops_steps_per_cycle() {
+    let p = Pattern::parse("C D E F G A B C5").unwrap();
+    let kept = |percent: u8, seed: u64, cycle: usize| -> Vec<bool> {
+        sounding_in_cycle(&p.clone().with_seed(seed).degrade(percent), cycle)
+    };
+
+    assert_eq!(kept(0, 1, 0), vec![true; 8]);
+    assert_eq!(kept(100, 1, 0), vec![false; 8]);
+
+    // Decided when events are generated: the same cycle always drops the
+    // same steps, but other cycles and seeds drop others
+    assert_eq!(kept(50, 7, 3), kept(50, 7, 3));
+    assert!((1..8).any(|cycle| kept(50, 7, cycle) != kept(50, 7, 0)));
+    assert!((0..8).any(|cycle| kept(50, 7, cycle) != kept(50, 8, cycle)));
+
+    // Static contexts play every step
+    let degraded = p.clone().degrade(100);
+    assert_eq!(degraded.to_rich_events().len(), 8);
+    assert!(degraded.to_rich_events().iter().all(|e| !e.is_rest));
+
+    // Dropped weighted steps keep their share of the cycle
+    let weighted = Pattern::parse("C@3 D").unwrap().degrade(100);
+    assert_eq!(weighted.to_rich_events_for_cycle(0)[1].start_beat, beats(3));
+}
+
+#[test]
+fn test_undegrade_complements_degrade() {
+    let p = Pattern::parse("C D E F G A B C5").unwrap().with_seed(7);
+
+    assert_eq!(
+        sounding_in_cycle(&p.clone().undegrade(100), 0),
+        vec![true; 8]
+    );
+    assert_eq!(
+        sounding_in_cycle(&p.clone().undegrade(0), 0),
+        vec![false; 8]
+    );
+
+    // In every cycle each step plays in exactly one of the two
+    let (degraded, undegraded) = (p.clone().degrade(50), p.clone().undegrade(50));
+    for cycle in 0..4 {
+        let (a, b) = (
+            sounding_in_cycle(&degraded, cycle),
+            sounding_in_cycle(&undegraded, cycle),
+        );
+        assert!(a.iter().zip(&b).all(|(a, b)| a != b));
+    }
+}
+
+#[test]
+fn test_some_steps() {
+    let p = Pattern::with_steps(vec![PatternStep::Note("C".parse().unwrap()); 200]);
+    let up = |step: &PatternStep| Ok(step.transpose(12));
+    let changed = |amount: f64, seed: u64| -> usize {
+        let result = p.clone().some_steps(amount, seed, up).unwrap();
+        result.steps.iter().filter(|s| **s != p.steps[0]).count()
+    };
+
+    assert_eq!(changed(0.0, 3), 0);
+    assert_eq!(changed(1.0, 3), 200);
+    // Roughly the given share of steps, the same ones for the same seed
+    let some = changed(0.3, 3);
+    assert!((40..=80).contains(&some), "{} of 200 steps changed", some);
+    assert_eq!(
+        p.clone().some_steps(0.3, 3, up).unwrap(),
+        p.clone().some_steps(0.3, 3, up).unwrap()
+    );
+
+    // Steps that become several steps play as a group in their slot
+    let doubled = Pattern::parse("C _ E")
+        .unwrap()
+        .some_steps(1.0, 3, |step| {
+            Ok(PatternStep::Group(vec![step.clone(), step.clone()]))
+        })
+        .unwrap();
+    assert_eq!(doubled.to_string(), "\"[C C] _ [E E]\"");
+    assert_eq!(doubled.to_rich_events().len(), 5);
+}
+
+#[test]
+fn test_rand_transpose_by_seed() {
+    let p = Pattern::parse("C E G").unwrap();
+    let shift = |max: u8, seed: u64| -> i8 {
+        let moved = p.clone().rand_transpose(max, seed);
+        (-12..=12)
+            .find(|&n| p.clone().transpose(n) == moved)
+            .expect("should be a plain transposition")
+    };
+
+    for seed in 0..20 {
+        assert!((-5..=5).contains(&shift(5, seed)));
+        assert_eq!(shift(5, seed), shift(5, seed));
+        assert_eq!(shift(0, seed), 0);
+    }
+    let shifts: Vec<i8> = (0..20).map(|seed| shift(5, seed)).collect();
+    assert!(shifts.iter().any(|&n| n != shifts[0]));
+}
+
+#[test]
+fn test_rand_transpose_stays_in_midi_range() {
+    // C4 is MIDI 60 and G4 is 67, so 60 semitones either way is all there is room for
+    let p = Pattern::parse("C E G").unwrap();
+    for seed in 0..50 {
+        let moved = p.clone().rand_transpose(127, seed);
+        assert!(
+            (-60..=60).any(|n| p.clone().transpose(n) == moved),
+            "seed {} left the MIDI range: {}",
+            seed,
+            moved
+        );
+    }
+    assert_eq!(
+        p.clone().rand_transpose(127, 1),
+        p.clone().rand_transpose(127, 1)
+    );
+}
+
+#[test]
+fn test_morph_moves_notes_towards_new_pitches() {
+    let from = Pattern::parse("C E G").unwrap();
+    let to = Pattern::parse("E G C5").unwrap();
+    let blend = |amount: f64| -> Vec<String> {
+        morph_events(
+            &from.to_rich_events(),
+            from.beats_per_cycle,
+            &to.to_rich_events(),
+            to.beats_per_cycle,
+            amount,
+        )
+        .iter()
+        .map(|e| e.notes[0].name.clone())
+        .collect()
+    };
+
+    assert_eq!(blend(0.0), vec!["C4", "E4", "G4"]);
+    // Halfway in semitones: C->E passes D, E->G passes F#, G->C5 passes A#
+    assert_eq!(blend(0.5), vec!["D4", "F#4", "A#4"]);
+    assert_eq!(blend(1.0), vec!["E4", "G4", "C5"]);
+}
+
+#[test]
+fn test_morph_crossfades_different_rhythms() {
+    let from = Pattern::parse("C D E F").unwrap();
+    let to = Pattern::parse("G A B").unwrap();
+    let blend = |amount: f64| {
+        morph_events(
+            &from.to_rich_events(),
+            from.beats_per_cycle,
+            &to.to_rich_events(),
+            to.beats_per_cycle,
+            amount,
+        )
+    };
+    let names = |events: &[PlaybackEvent]| -> Vec<String> {
+        events
+            .iter()
+            .flat_map(|e| e.notes.iter().map(|n| n.name.clone()))
+            .collect()
+    };
+
+    assert_eq!(names(&blend(0.0)), vec!["C4", "D4", "E4", "F4"]);
+    assert_eq!(names(&blend(1.0)), vec!["G4", "A4", "B4"]);
+
+    // Halfway keeps two of the old notes and brings in two of the new ones
+    let half = blend(0.5);
+    let half_names = names(&half);
+    let old = half_names
+        .iter()
+        .filter(|n| ["C4", "D4", "E4", "F4"].contains(&n.as_str()));
+    let new = half_names
+        .iter()
+        .filter(|n| ["G4", "A4", "B4"].contains(&n.as_str()));
+    assert_eq!((old.count(), new.count()), (2, 2));
+
+    // The blend still fills exactly one cycle, in order
+    let end = half.last().map(|e| e.start_beat + e.duration);
+    assert_eq!(end, Some(to.beats_per_cycle));
+    assert!(half.windows(2).all(|w| w[0].start_beat < w[1].start_beat));
+}
@@ -0,0 +1,121 @@
+//! Deterministic pseudo-random numbers for generative builtins
+//!
+//! Looping expressions are re-evaluated on every tick, so generators must
+//! return the same result for the same seed instead of drawing from a global
+//! source. `SeededRng` is a small SplitMix64 generator with no dependencies,
+//! which keeps output identical across native and wasm builds.
+
+/// Seed used by generators when none is given
+pub const DEFAULT_SEED: u64 = 0x5EED;
+
+/// Seed for the random choices one track makes in one cycle.
+///
+/// Every looping track evaluates its expression with the same `seed`, so
+/// mixing in the track keeps two tracks playing the same generator from
+/// making the same choices, and mixing in the cycle lets choices change from
+/// one cycle to the next.
+pub fn probability_seed(seed: u64, track_id: usize, cycle: usize) -> u64 {
+    SeededRng::new(seed ^ (track_id as u64).rotate_left(32) ^ cycle as u64).next_u64()
+}
+
+/// Seedable SplitMix64 generator
+#[derive(Debug, Clone)]
+pub struct SeededRng {
+    state: u64,
+}
+
+impl SeededRng {
+    pub fn new(seed: u64) -> Self {
+        SeededRng { state: seed }
+    }
+
+    /// Next 64 random bits
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform float in [0.0, 1.0)
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Uniform integer in `[-max, max]`, for `max` up to 127
+    pub fn next_offset(&mut self, max: u8) -> i8 {
+        let max = max.min(i8::MAX as u8) as i16;
+        let span = 2 * max as u64 + 1;
+        ((self.next_u64() % span) as i16 - max) as i8
+    }
+
+    /// Pick an index with probability proportional to its weight.
+    /// Negative weights count as zero; returns `None` if no weight is positive.
+    pub fn weighted_index(&mut self, weights: &[f64]) -> Option<usize> {
+        let total: f64 = weights.iter().map(|w| w.max(0.0)).sum();
+        if total <= 0.0 {
+            return None;
+        }
+
+        let mut target = self.next_f64() * total;
+        for (i, weight) in weights.iter().enumerate() {
+            let weight = weight.max(0.0);
+            if target < weight {
+                return Some(i);
+            }
+            target -= weight;
+        }
+        // Float rounding can leave a sliver past the last bucket
+        weights.iter().rposition(|w| *w > 0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_same_seed_same_sequence() {
+        let mut a = SeededRng::new(42);
+        let mut b = SeededRng::new(42);
+        let mut c = SeededRng::new(43);
+        let first: Vec<u64> = (0..8).map(|_| a.next_u64()).collect();
+        let second: Vec<u64> = (0..8).map(|_| b.next_u64()).collect();
+        let other: Vec<u64> = (0..8).map(|_| c.next_u64()).collect();
+        assert_eq!(first, second);
+        assert_ne!(first, other);
+    }
+
+    #[test]
+    fn test_probability_seed_per_track_and_cycle() {
+        let seed = probability_seed(DEFAULT_SEED, 1, 0);
+        assert_eq!(seed, probability_seed(DEFAULT_SEED, 1, 0));
+        assert_ne!(seed, probability_seed(DEFAULT_SEED, 2, 0));
+        assert_ne!(seed, probability_seed(DEFAULT_SEED, 1, 1));
+        assert_ne!(seed, probability_seed(7, 1, 0));
+    }
+
+    #[test]
+    fn test_next_offset() {
+        let mut rng = SeededRng::new(DEFAULT_SEED);
+        let offsets: Vec<i8> = (0..200).map(|_| rng.next_offset(3)).collect();
+        assert!(offsets.iter().all(|n| (-3..=3).contains(n)));
+        assert!(offsets.contains(&-3) && offsets.contains(&3));
+        assert_eq!(rng.next_offset(0), 0);
+    }
+
+    #[test]
+    fn test_weighted_index() {
+        let mut rng = SeededRng::new(DEFAULT_SEED);
+        let mut counts = [0usize; 3];
+        for _ in 0..1000 {
+            counts[rng.weighted_index(&[1.0, 0.0, 3.0]).unwrap()] += 1;
+        }
+        assert_eq!(counts[1], 0);
+        assert!(counts[2] > counts[0] * 2, "counts: {:?}", counts);
+
+        assert_eq!(rng.weighted_index(&[]), None);
+        assert_eq!(rng.weighted_index(&[0.0, -1.0]), None);
+    }
+}
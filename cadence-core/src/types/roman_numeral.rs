@@ -525,6 +525,45 @@ pub fn analyze_progression(
 /// Enhanced common progressions database
 pub struct CommonProgressions;
 
+/// The named progressions `CommonProgressions::identify` can recognize, in
+/// the order `list_progressions` presents them
+const NAMED_PROGRESSIONS: &[&str] = &[
+    "I_V_vi_IV",
+    "vi_IV_I_V",
+    "I_vi_ii_V",
+    "I_IV_V_I",
+    "vi_V_IV_V",
+    "ii_V_I",
+    "ii_V_I_vi",
+    "iii_vi_ii_V_I",
+    "vi_ii_V_I",
+    "I_bVII_IV_I",
+    "vi_bVI_bVII_I",
+    "I_bIII_bVII_IV",
+    "Pachelbel",
+    "I_vi_IV_V",
+    "vi_IV_V_I",
+    "I_V_IV_I",
+    "12_bar_blues",
+];
+
+/// The numeral of a chord's triad: sevenths, extensions and inversions are
+/// dropped so "V7" and "V⁶" both read as "V"
+fn triad_numeral(numeral: &RomanNumeral) -> String {
+    let quality = match numeral.quality {
+        ChordQuality::MajorMinor => ChordQuality::Major,
+        ChordQuality::HalfDiminished => ChordQuality::Diminished,
+        ref quality => quality.clone(),
+    };
+    RomanNumeral {
+        quality,
+        inversion: 0,
+        extensions: Vec::new(),
+        ..numeral.clone()
+    }
+    .as_string()
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum ChordType {
     Major,
@@ -816,6 +855,90 @@ impl CommonProgressions {
         Ok(crate::types::Pattern::from_chords(chords))
     }
 
+    /// The ii-V that leads into a scale degree (1-7) of a major key, followed
+    /// by the degree's own triad. Minor and diminished targets get a
+    /// half-diminished ii (m7b5), major ones a minor seventh; the V is always
+    /// a dominant seventh. Degree 1 gives the key's own ii-V-I.
+    pub fn approach(degree: u8, key: Note) -> Result<crate::types::Pattern> {
+        let (offset, chord_type) = match degree {
+            1 => (0, ChordType::Major),
+            2 => (2, ChordType::Minor),
+            3 => (4, ChordType::Minor),
+            4 => (5, ChordType::Major),
+            5 => (7, ChordType::Major),
+            6 => (9, ChordType::Minor),
+            7 => (11, ChordType::Diminished),
+            _ => return Err(anyhow!("Scale degree must be from 1 to 7, got {}", degree)),
+        };
+        let target = key + offset;
+
+        let (two, triad): (&[u8], &[u8]) = match chord_type {
+            ChordType::Major => (&[3, 7, 10], &[4, 7]),
+            ChordType::Minor => (&[3, 6, 10], &[3, 7]),
+            ChordType::Diminished => (&[3, 6, 10], &[3, 6]),
+        };
+        Ok(crate::types::Pattern::from_chords(vec![
+            crate::types::Chord::from_intervals(target + 2, two),
+            crate::types::Chord::from_intervals(target + 7, &[4, 7, 10]),
+            crate::types::Chord::from_intervals(target, triad),
+        ]))
+    }
+
+    /// Name the progression a chord pattern plays in `key`, the reverse of
+    /// `get_progression`. Chords are compared by Roman numeral and triad
+    /// quality, so sevenths and inversions don't matter, and a pattern that
+    /// repeats a progression still matches it. When no named progression
+    /// matches every chord, the closest one is reported with how many of the
+    /// chords it shares, e.g. "ii_V_I (closest match, 2 of 3 chords)".
+    pub fn identify(pattern: &crate::types::Pattern, key: Note) -> Result<String> {
+        let numerals = |pattern: &crate::types::Pattern| -> Result<Vec<String>> {
+            Ok(analyze_progression(pattern, key)?
+                .iter()
+                .map(triad_numeral)
+                .collect())
+        };
+        let chords = numerals(pattern)?;
+        if chords.is_empty() {
+            return Err(anyhow!("Cannot identify an empty progression"));
+        }
+
+        // (name, matching chords, compared length), best first
+        let mut best: Option<(&str, usize, usize)> = None;
+        for &name in NAMED_PROGRESSIONS {
+            let named = numerals(&Self::get_progression(name, key)?)?;
+            let matches = chords
+                .iter()
+                .enumerate()
+                .filter(|(i, numeral)| **numeral == named[i % named.len()])
+                .count();
+            let length = chords.len().max(named.len());
+            if matches == chords.len() && chords.len() % named.len() == 0 {
+                return Ok(name.to_string());
+            }
+            let better = match best {
+                Some((_, best_matches, best_length)) => {
+                    matches * best_length > best_matches * length
+                }
+                None => matches > 0,
+            };
+            if better {
+                best = Some((name, matches, length));
+            }
+        }
+
+        match best {
+            Some((name, matches, length)) => Ok(format!(
+                "{} (closest match, {} of {} chords)",
+                name, matches, length
+            )),
+            None => Err(anyhow!(
+                "No known progression resembles {} in {}",
+                chords.join("-"),
+                key
+            )),
+        }
+    }
+
     /// Parse numeric progression patterns with proper chord type handling
     pub fn parse_numeric_progression(pattern: &str) -> Result<Vec<(i8, ChordType)>> {
         let mut chord_specs = Vec::new();
@@ -1333,6 +1456,41 @@ mod tests {
         assert_eq!(analysis[3].to_string(), "IV");
     }
 
+    #[test]
+    fn test_identify_progression() {
+        let g: Note = "G".parse().unwrap();
+        let pop = CommonProgressions::get_progression("1564", g).unwrap();
+        assert_eq!(CommonProgressions::identify(&pop, g).unwrap(), "I_V_vi_IV");
+
+        // Sevenths and repeats still match
+        let c: Note = "C".parse().unwrap();
+        let chord = |names: &str| {
+            let notes: Vec<Note> = names.split(' ').map(|n| n.parse().unwrap()).collect();
+            Chord::from_notes(notes)
+        };
+        let two_five_one = crate::types::Pattern::from_chords(vec![
+            chord("D F A C5"),
+            chord("G3 B3 D F"),
+            chord("C E G B"),
+        ]);
+        assert_eq!(
+            CommonProgressions::identify(&two_five_one, c).unwrap(),
+            "ii_V_I"
+        );
+        let once = CommonProgressions::get_progression("I_vi_IV_V", c).unwrap();
+        let twice = once.clone().concat(once);
+        assert_eq!(
+            CommonProgressions::identify(&twice, c).unwrap(),
+            "I_vi_IV_V"
+        );
+
+        let partial = CommonProgressions::get_progression("256", c).unwrap();
+        assert_eq!(
+            CommonProgressions::identify(&partial, c).unwrap(),
+            "ii_V_I (closest match, 2 of 3 chords)"
+        );
+    }
+
     #[test]
     fn test_progression_with_dashes() {
         let progression =
@@ -1372,6 +1530,23 @@ mod tests {
         assert_eq!(analysis[2].to_string(), "I");
     }
 
+    #[test]
+    fn test_approach() {
+        let symbols = |degree: u8| -> Vec<String> {
+            let pattern = CommonProgressions::approach(degree, "C".parse().unwrap()).unwrap();
+            let chords = pattern.as_chords().expect("Should be chord-only pattern");
+            chords.iter().map(|c| c.to_symbol()).collect()
+        };
+
+        // ii-V of vi resolving to A minor
+        assert_eq!(symbols(6), vec!["Bm7b5", "E7", "Am"]);
+        // Targeting the tonic is the key's own ii-V-I
+        assert_eq!(symbols(1), vec!["Dm7", "G7", "C"]);
+        assert_eq!(symbols(4), vec!["Gm7", "C7", "F"]);
+        assert!(CommonProgressions::approach(0, "C".parse().unwrap()).is_err());
+        assert!(CommonProgressions::approach(8, "C".parse().unwrap()).is_err());
+    }
+
     #[test]
     fn test_blues_progression() {
         let pattern =
@@ -1380,8 +1555,8 @@ mod tests {
         assert_eq!(chords.len(), 12);
 
         // First four chords should be I
-        for i in 0..4 {
-            let analysis = RomanNumeral::analyze(&chords[i], "C".parse().unwrap()).unwrap();
+        for chord in chords.iter().take(4) {
+            let analysis = RomanNumeral::analyze(chord, "C".parse().unwrap()).unwrap();
             assert_eq!(analysis.to_string(), "I");
         }
     }
@@ -0,0 +1,359 @@
+//! Scale modes and their interval sets
+
+use crate::types::{Chord, Note};
+
+/// A named scale, defined by its intervals above the root
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScaleMode {
+    Major,
+    Minor,
+    Dorian,
+    Phrygian,
+    Lydian,
+    Mixolydian,
+    Locrian,
+    HarmonicMinor,
+    MelodicMinor,
+    MajorPentatonic,
+    MinorPentatonic,
+    Blues,
+    Chromatic,
+}
+
+impl ScaleMode {
+    /// One name for each mode, in the order they are listed to users
+    pub const NAMES: &'static [&'static str] = &[
+        "major",
+        "minor",
+        "dorian",
+        "phrygian",
+        "lydian",
+        "mixolydian",
+        "locrian",
+        "harmonic_minor",
+        "melodic_minor",
+        "major_pentatonic",
+        "minor_pentatonic",
+        "blues",
+        "chromatic",
+    ];
+
+    /// Seven-note modes in the order they are suggested for a chord, most
+    /// common first
+    const CHORD_SCALES: [ScaleMode; 9] = [
+        ScaleMode::Major,
+        ScaleMode::Dorian,
+        ScaleMode::Mixolydian,
+        ScaleMode::Minor,
+        ScaleMode::Lydian,
+        ScaleMode::Phrygian,
+        ScaleMode::Locrian,
+        ScaleMode::MelodicMinor,
+        ScaleMode::HarmonicMinor,
+    ];
+
+    /// The mode's name as listed in `NAMES`
+    pub fn name(&self) -> &'static str {
+        Self::NAMES[*self as usize]
+    }
+
+    /// Scales to play over `chord` in the key of `key` `mode`, best first,
+    /// each to be played from the chord's root.
+    ///
+    /// When the chord's tones are all in the key, the first pick is the
+    /// key's own scale read from the chord root: D dorian for Dm7 in C
+    /// major. After it come the other seven-note modes that contain every
+    /// chord tone. A chord that fits none of them gets the chromatic scale.
+    pub fn for_chord(chord: &Chord, key: Note, mode: ScaleMode) -> Vec<ScaleMode> {
+        let Some(root) = chord.root() else {
+            return vec![ScaleMode::Chromatic];
+        };
+        let above_root = |pitch_class: u8| (pitch_class + 12 - root.pitch_class()) % 12;
+        let tones: Vec<u8> = chord.notes().map(|n| above_root(n.pitch_class())).collect();
+        let fits = |mode: &ScaleMode| tones.iter().all(|tone| mode.intervals().contains(tone));
+
+        // The key's scale tones measured from the chord root
+        let mut from_root: Vec<u8> = mode
+            .intervals()
+            .iter()
+            .map(|&interval| above_root((key.pitch_class() + interval) % 12))
+            .collect();
+        from_root.sort();
+
+        let mut suggestions: Vec<ScaleMode> = Self::CHORD_SCALES
+            .iter()
+            .copied()
+            .filter(|candidate| candidate.intervals() == from_root.as_slice() && fits(candidate))
+            .collect();
+        for candidate in Self::CHORD_SCALES
+            .iter()
+            .filter(|candidate| fits(candidate))
+        {
+            if !suggestions.contains(candidate) {
+                suggestions.push(*candidate);
+            }
+        }
+        if suggestions.is_empty() {
+            suggestions.push(ScaleMode::Chromatic);
+        }
+        suggestions
+    }
+
+    /// Parse a mode name (case-insensitive, `_` or `-` between words)
+    pub fn from_name(s: &str) -> Option<Self> {
+        match s.to_lowercase().replace('-', "_").as_str() {
+            "major" | "ionian" => Some(ScaleMode::Major),
+            "minor" | "aeolian" | "natural_minor" => Some(ScaleMode::Minor),
+            "dorian" => Some(ScaleMode::Dorian),
+            "phrygian" => Some(ScaleMode::Phrygian),
+            "lydian" => Some(ScaleMode::Lydian),
+            "mixolydian" => Some(ScaleMode::Mixolydian),
+            "locrian" => Some(ScaleMode::Locrian),
+            "harmonic_minor" => Some(ScaleMode::HarmonicMinor),
+            "melodic_minor" => Some(ScaleMode::MelodicMinor),
+            "major_pentatonic" | "pentatonic" => Some(ScaleMode::MajorPentatonic),
+            "minor_pentatonic" => Some(ScaleMode::MinorPentatonic),
+            "blues" => Some(ScaleMode::Blues),
+            "chromatic" => Some(ScaleMode::Chromatic),
+            _ => None,
+        }
+    }
+
+    /// Semitone offsets of each degree above the root
+    pub fn intervals(&self) -> &'static [u8] {
+        match self {
+            ScaleMode::Major => &[0, 2, 4, 5, 7, 9, 11],
+            ScaleMode::Minor => &[0, 2, 3, 5, 7, 8, 10],
+            ScaleMode::Dorian => &[0, 2, 3, 5, 7, 9, 10],
+            ScaleMode::Phrygian => &[0, 1, 3, 5, 7, 8, 10],
+            ScaleMode::Lydian => &[0, 2, 4, 6, 7, 9, 11],
+            ScaleMode::Mixolydian => &[0, 2, 4, 5, 7, 9, 10],
+            ScaleMode::Locrian => &[0, 1, 3, 5, 6, 8, 10],
+            ScaleMode::HarmonicMinor => &[0, 2, 3, 5, 7, 8, 11],
+            ScaleMode::MelodicMinor => &[0, 2, 3, 5, 7, 9, 11],
+            ScaleMode::MajorPentatonic => &[0, 2, 4, 7, 9],
+            ScaleMode::MinorPentatonic => &[0, 3, 5, 7, 10],
+            ScaleMode::Blues => &[0, 3, 5, 6, 7, 10],
+            ScaleMode::Chromatic => &[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11],
+        }
+    }
+
+    /// Scale tones for one octave starting at `root`
+    pub fn notes(&self, root: Note) -> Vec<Note> {
+        self.intervals()
+            .iter()
+            .map(|&interval| root.transpose(interval as i8))
+            .collect()
+    }
+
+    /// The note `degree` steps up the scale from `key`, counting from 0.
+    ///
+    /// Degrees past the top of the scale wrap into the octaves above, and
+    /// negative ones go below the tonic: in C major, 7 is the next C and -1
+    /// is the B below. None if the note is outside the MIDI range.
+    pub fn degree(&self, key: Note, degree: i32) -> Option<Note> {
+        let intervals = self.intervals();
+        let size = intervals.len() as i32;
+        let semitones =
+            degree.div_euclid(size) * 12 + intervals[degree.rem_euclid(size) as usize] as i32;
+        if !(0..=127).contains(&(key.midi_note() as i32 + semitones)) {
+            return None;
+        }
+        Some(key.transpose(semitones as i8).respell(uses_sharps(key)))
+    }
+
+    /// A three-note enclosure leading into `target`: the next scale tone
+    /// above it, the note a semitone below it, then the target.
+    ///
+    /// The upper neighbour is the nearest scale tone above the target, so in
+    /// a pentatonic scale it can be a third away, and at the top of the scale
+    /// it is the root of the next octave. The target doesn't have to be in
+    /// the scale.
+    pub fn enclose(&self, key: Note, target: Note) -> Vec<Note> {
+        let intervals = self.intervals();
+        let above = (1..=12)
+            .find(|&step| {
+                let degree = (target.pitch_class() + step + 12 - key.pitch_class()) % 12;
+                intervals.contains(&degree)
+            })
+            .unwrap_or(12);
+        vec![
+            target.transpose(above as i8).respell(uses_sharps(key)),
+            // Leading up by a semitone reads as a sharp: F# into G
+            target.transpose(-1).respell(true),
+            target,
+        ]
+    }
+
+    /// The scale tone nearest to `note`, in the scale on `key`. A note
+    /// exactly between two scale tones goes to the lower one, so the result
+    /// never depends on anything but the note.
+    pub fn snap(&self, key: Note, note: Note) -> Note {
+        let intervals = self.intervals();
+        let in_scale = |n: Note| {
+            let offset = (n.pitch_class() as i32 - key.pitch_class() as i32).rem_euclid(12);
+            intervals.contains(&(offset as u8))
+        };
+        (0..=6i8)
+            .flat_map(|distance| [note.transpose(-distance), note.transpose(distance)])
+            .find(|&n| in_scale(n))
+            .unwrap_or(note)
+            .respell(uses_sharps(key))
+    }
+}
+
+/// Scale tones follow the key: flats for F and keys written with a flat
+pub(crate) fn uses_sharps(key: Note) -> bool {
+    !(key.pitch_class() == 5 || key.name().ends_with('b'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scale_notes() {
+        let root: Note = "D".parse().unwrap();
+        let names: Vec<String> = ScaleMode::Dorian
+            .notes(root)
+            .iter()
+            .map(|n| n.to_string())
+            .collect();
+        assert_eq!(names, vec!["D", "E", "F", "G", "A", "B", "C5"]);
+        assert_eq!(
+            ScaleMode::from_name("Minor-Pentatonic"),
+            Some(ScaleMode::MinorPentatonic)
+        );
+        assert_eq!(ScaleMode::from_name("bebop"), None);
+    }
+
+    #[test]
+    fn test_names_parse() {
+        for name in ScaleMode::NAMES {
+            assert!(ScaleMode::from_name(name).is_some(), "{}", name);
+        }
+        assert_eq!(
+            ScaleMode::from_name("Natural-Minor"),
+            Some(ScaleMode::Minor)
+        );
+    }
+
+    #[test]
+    fn test_for_chord() {
+        let c: Note = "C".parse().unwrap();
+        let chord =
+            |notes: &[&str]| Chord::from_notes(notes.iter().map(|n| n.parse().unwrap()).collect());
+        let first = |notes: &[&str], key: Note, mode: ScaleMode| {
+            ScaleMode::for_chord(&chord(notes), key, mode)[0]
+        };
+
+        // Diatonic chords get the key's scale read from their root
+        assert_eq!(
+            first(&["D", "F", "A", "C5"], c, ScaleMode::Major),
+            ScaleMode::Dorian
+        );
+        assert_eq!(
+            first(&["G", "B", "D5", "F5"], c, ScaleMode::Major),
+            ScaleMode::Mixolydian
+        );
+        assert_eq!(
+            first(&["F", "A", "C5"], c, ScaleMode::Major),
+            ScaleMode::Lydian
+        );
+        assert_eq!(
+            first(&["E", "G", "B"], c, ScaleMode::Major),
+            ScaleMode::Phrygian
+        );
+
+        // Outside the key, the most common mode holding every chord tone
+        assert_eq!(
+            first(&["D", "F#", "A"], c, ScaleMode::Major),
+            ScaleMode::Major
+        );
+        assert_eq!(
+            ScaleMode::for_chord(&chord(&["D", "F#", "A"]), c, ScaleMode::Major),
+            vec![ScaleMode::Major, ScaleMode::Mixolydian, ScaleMode::Lydian]
+        );
+
+        // Nothing holds a diminished seventh
+        assert_eq!(
+            first(&["B", "D5", "F5", "G#5"], c, ScaleMode::Minor),
+            ScaleMode::Chromatic
+        );
+        assert_eq!(ScaleMode::Dorian.name(), "dorian");
+    }
+
+    #[test]
+    fn test_enclose() {
+        let names =
+            |notes: Vec<Note>| -> Vec<String> { notes.iter().map(|n| n.to_string()).collect() };
+        let c: Note = "C".parse().unwrap();
+        let g: Note = "G".parse().unwrap();
+        assert_eq!(names(ScaleMode::Major.enclose(c, g)), vec!["A", "F#", "G"]);
+
+        // B is the top of C major: the neighbour above is the next C
+        let b: Note = "B".parse().unwrap();
+        assert_eq!(names(ScaleMode::Major.enclose(c, b)), vec!["C5", "A#", "B"]);
+
+        // Pentatonic scales skip notes, so the upper neighbour is further off
+        let e: Note = "E".parse().unwrap();
+        assert_eq!(
+            names(ScaleMode::MajorPentatonic.enclose(c, e)),
+            vec!["G", "D#", "E"]
+        );
+
+        let f: Note = "F".parse().unwrap();
+        let a: Note = "A".parse().unwrap();
+        assert_eq!(names(ScaleMode::Major.enclose(f, a)), vec!["Bb", "G#", "A"]);
+        assert_eq!(names(ScaleMode::Major.enclose(g, e)), vec!["F#", "D#", "E"]);
+
+        // A target outside the scale still gets the scale tone above it
+        let f_sharp: Note = "F#".parse().unwrap();
+        assert_eq!(
+            names(ScaleMode::Major.enclose(c, f_sharp)),
+            vec!["G", "F", "F#"]
+        );
+    }
+
+    #[test]
+    fn test_snap() {
+        let c: Note = "C".parse().unwrap();
+        let snap = |mode: ScaleMode, name: &str| mode.snap(c, name.parse().unwrap()).to_string();
+        // Scale tones stay; C#, F# and Bb are halfway between two and go down
+        assert_eq!(snap(ScaleMode::Major, "E"), "E");
+        assert_eq!(snap(ScaleMode::Major, "C#"), "C");
+        assert_eq!(snap(ScaleMode::Major, "F#"), "F");
+        assert_eq!(snap(ScaleMode::Major, "Bb"), "A");
+        assert_eq!(snap(ScaleMode::Major, "B3"), "B3");
+        // In C minor pentatonic E sits between Eb and F, and B between Bb and C
+        assert_eq!(snap(ScaleMode::MinorPentatonic, "E"), "D#");
+        assert_eq!(snap(ScaleMode::MinorPentatonic, "B"), "A#");
+        // Flat keys spell the result with flats
+        let f: Note = "F".parse().unwrap();
+        assert_eq!(
+            ScaleMode::Major.snap(f, "B".parse().unwrap()).to_string(),
+            "Bb"
+        );
+    }
+
+    #[test]
+    fn test_degree() {
+        let c: Note = "C".parse().unwrap();
+        let names = |mode: ScaleMode, key: Note, degrees: &[i32]| -> Vec<String> {
+            degrees
+                .iter()
+                .map(|&d| mode.degree(key, d).unwrap().to_string())
+                .collect()
+        };
+        assert_eq!(names(ScaleMode::Major, c, &[0, 2, 4]), vec!["C", "E", "G"]);
+        // Past the top of the scale, into the next octave
+        assert_eq!(names(ScaleMode::Major, c, &[7, 9]), vec!["C5", "E5"]);
+        assert_eq!(names(ScaleMode::MinorPentatonic, c, &[5]), vec!["C5"]);
+        // Below the tonic
+        assert_eq!(names(ScaleMode::Major, c, &[-1, -7]), vec!["B3", "C3"]);
+
+        let f: Note = "F".parse().unwrap();
+        assert_eq!(names(ScaleMode::Major, f, &[3]), vec!["Bb"]);
+        assert_eq!(ScaleMode::Major.degree(c, 100), None);
+    }
+}
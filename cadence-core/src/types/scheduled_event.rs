@@ -56,6 +56,8 @@ pub enum ScheduledAction {
         duration_beats: f32,
         /// Optional drum sounds to trigger
         drums: Vec<DrumSound>,
+        /// MIDI velocity (0-127) for each frequency
+        velocities: Vec<u8>,
     },
     /// Set tempo at this moment
     SetTempo(f32),
@@ -86,6 +88,7 @@ mod tests {
                 frequencies: vec![440.0],
                 duration_beats: 1.0,
                 drums: vec![],
+                velocities: vec![100],
             },
             1,
         ));
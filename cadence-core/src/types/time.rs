@@ -80,6 +80,25 @@ pub fn from_f64(f: f64) -> Time {
     Ratio::new(numer, denom)
 }
 
+/// Note values that can be named, with their length in beats (a beat is a
+/// quarter note)
+pub const NOTE_VALUES: [(&str, i64, i64); 6] = [
+    ("whole", 4, 1),
+    ("half", 2, 1),
+    ("quarter", 1, 1),
+    ("8th", 1, 2),
+    ("16th", 1, 4),
+    ("triplet8th", 1, 3),
+];
+
+/// Length in beats of a named note value such as "8th" or "triplet8th"
+pub fn note_value(name: &str) -> Option<Time> {
+    NOTE_VALUES
+        .iter()
+        .find(|(value, ..)| *value == name)
+        .map(|&(_, n, d)| time(n, d))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -127,6 +146,14 @@ mod tests {
         assert_eq!(to_f64(t), 0.5);
     }
 
+    #[test]
+    fn test_note_value() {
+        assert_eq!(note_value("quarter"), Some(beats(1)));
+        assert_eq!(note_value("16th"), Some(time(1, 4)));
+        assert_eq!(note_value("triplet8th"), Some(time(1, 3)));
+        assert_eq!(note_value("eighth"), None);
+    }
+
     #[test]
     fn test_beats_helper() {
         assert_eq!(beats(4), time(4, 1));
@@ -72,6 +72,7 @@ impl HighlightSpan {
             | Token::Fn
             | Token::On
             | Token::For
+            | Token::While
             | Token::In
             | Token::Wait => "keyword".to_string(),
 
@@ -173,6 +174,7 @@ impl HighlightSpan {
             Token::Fn => "fn".to_string(),
             Token::On => "on".to_string(),
             Token::For => "for".to_string(),
+            Token::While => "while".to_string(),
             Token::In => "in".to_string(),
             Token::Wait => "wait".to_string(),
             Token::Tempo => "tempo".to_string(),
@@ -279,44 +281,15 @@ struct ParseResult {
 #[cfg(feature = "wasm")]
 #[wasm_bindgen]
 pub fn parse_and_check(input: &str) -> JsValue {
-    use crate::parser::binder::Binder;
-    use crate::parser::statement_parser::parse_spanned_statements;
-    use crate::parser::validator::Validator;
+    use crate::parser::validator::check_source;
 
-    let spanned_program = match parse_spanned_statements(input) {
-        Ok(p) => p,
-        Err(e) => {
-            let error_js: ParseErrorJS = e.into();
-            return serde_wasm_bindgen::to_value(&ParseResult {
-                success: false,
-                error: Some(error_js.clone()), // Legacy
-                errors: vec![error_js],
-            })
-            .unwrap_or(JsValue::NULL);
-        }
-    };
-
-    // Run static analysis
-    let table = Binder::bind(&spanned_program);
-    let binder = Binder { table };
-    let validation_errors = Validator::validate(&spanned_program, &binder);
-
-    if !validation_errors.is_empty() {
-        let errors_js: Vec<ParseErrorJS> =
-            validation_errors.into_iter().map(|e| e.into()).collect();
-
-        return serde_wasm_bindgen::to_value(&ParseResult {
-            success: false,
-            error: Some(errors_js[0].clone()), // First error
-            errors: errors_js,
-        })
-        .unwrap_or(JsValue::NULL);
-    }
+    // Every parse error plus static analysis of what did parse
+    let errors_js: Vec<ParseErrorJS> = check_source(input).into_iter().map(|e| e.into()).collect();
 
     serde_wasm_bindgen::to_value(&ParseResult {
-        success: true,
-        error: None,
-        errors: vec![],
+        success: errors_js.is_empty(),
+        error: errors_js.first().cloned(), // First error
+        errors: errors_js,
     })
     .unwrap_or(JsValue::NULL)
 }
@@ -425,7 +398,7 @@ pub fn get_use_statements(code: &str) -> JsValue {
                 Statement::Loop { body } | Statement::Repeat { body, .. } => {
                     collect_use_paths(body, paths);
                 }
-                Statement::For { body, .. } => {
+                Statement::For { body, .. } | Statement::While { body, .. } => {
                     collect_use_paths(body, paths);
                 }
                 Statement::If {
@@ -876,6 +849,16 @@ fn convert_action(
         InterpreterAction::Stop { track_id } => Some(ActionJS::Stop {
             track_id: *track_id,
         }),
+        // Capo is applied by the native event dispatcher only
+        InterpreterAction::SetCapo(_) => None,
+        // MIDI CC automation, patch changes and input bindings have no web audio equivalent
+        InterpreterAction::ControlChange { .. }
+        | InterpreterAction::MidiProgram { .. }
+        | InterpreterAction::MidiBank { .. }
+        | InterpreterAction::MidiVelocityCurve { .. }
+        | InterpreterAction::Bind { .. } => None,
+        // Aliases are REPL commands
+        InterpreterAction::DefineAlias { .. } => None,
     }
 }
 
@@ -1028,6 +1011,12 @@ pub fn get_events_at_position(code: &str, position: usize) -> JsValue {
         }
     };
 
+    // `sometimes` patterns are shown by their base pattern, like `every` patterns
+    let value = match value {
+        Value::ProbabilityPattern(sometimes) => Value::Pattern(sometimes.base),
+        other => other,
+    };
+
     // Convert to events with cycle timing
     let result: PatternEventsJS = match value {
         Value::Pattern(ref p) => {
@@ -1141,6 +1130,202 @@ pub fn get_events_at_position(code: &str, position: usize) -> JsValue {
     serde_wasm_bindgen::to_value(&result).unwrap_or(JsValue::NULL)
 }
 
+// ============================================================================
+// Playback Scheduling (for Web Audio)
+// ============================================================================
+
+/// A sounding event on the compiled timeline
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TimedEventJS {
+    pub track: usize,
+    /// Start in beats from the start of the program
+    pub time_beats: f64,
+    pub duration_beats: f64,
+    /// Frequencies to sound (Hz), empty for drum-only events
+    pub frequencies: Vec<f32>,
+    /// Drum short names ("bd", "sn", ...)
+    pub drums: Vec<String>,
+    /// MIDI velocity (0-127) of the loudest note
+    pub velocity: u8,
+    /// The pattern's waveform, or the track's if the pattern has none
+    pub waveform: Option<String>,
+    /// Stereo pan position (0.0 = left, 0.5 = center, 1.0 = right)
+    pub pan: Option<f32>,
+}
+
+/// Every track's events over a number of cycles, in time order
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PlaybackScheduleJS {
+    /// The script's tempo, or the one passed in if it sets none
+    pub bpm: f32,
+    pub events: Vec<TimedEventJS>,
+    /// Optional error message if the program failed to parse or run
+    pub error: Option<String>,
+}
+
+/// Run a program without audio and lay out what each track plays.
+///
+/// Looping plays repeat for `cycles` cycles of their own pattern, so `every`,
+/// alternations and `_cycle` expressions advance. One-shot plays sound once,
+/// at the beat their `wait`s put them on. As in the REPL, a later looping play
+/// replaces the track's earlier one and `stop` drops what was looping.
+pub fn compile_playback_events(
+    source: &str,
+    cycles: u32,
+    bpm: f32,
+) -> Result<PlaybackScheduleJS, String> {
+    use crate::parser::ast::Value;
+    use crate::parser::evaluator::{EnvironmentRef, Evaluator};
+    use crate::parser::interpreter::{Interpreter, InterpreterAction};
+    use crate::parser::parse_statements;
+    use crate::types::{beats, to_f64, ScheduledAction, DEFAULT_VELOCITY};
+    use std::collections::{BTreeMap, HashMap};
+
+    let program = parse_statements(source).map_err(|e| e.to_string())?;
+    let mut interpreter = Interpreter::new();
+    interpreter
+        .run_program(&program)
+        .map_err(|e| e.to_string())?;
+
+    let mut bpm = bpm;
+    let mut loops = BTreeMap::new();
+    let mut track_waveforms = HashMap::new();
+    for action in interpreter.take_actions() {
+        match action {
+            InterpreterAction::PlayExpression {
+                expression,
+                track_id,
+                ..
+            } => {
+                loops.insert(track_id, expression);
+            }
+            InterpreterAction::SetTempo(tempo) => bpm = tempo,
+            InterpreterAction::SetWaveform { waveform, track_id } => {
+                track_waveforms.insert(track_id, waveform);
+            }
+            InterpreterAction::Stop {
+                track_id: Some(track),
+            } => {
+                loops.remove(&track);
+            }
+            InterpreterAction::Stop { track_id: None } => loops.clear(),
+            _ => {}
+        }
+    }
+
+    let mut events = Vec::new();
+
+    // One-shot plays were already flattened into notes by the interpreter
+    for scheduled in interpreter.take_scheduled_events() {
+        if let ScheduledAction::PlayNotes {
+            frequencies,
+            duration_beats,
+            drums,
+            velocities,
+        } = scheduled.action
+        {
+            if frequencies.is_empty() && drums.is_empty() {
+                continue;
+            }
+            events.push(TimedEventJS {
+                track: scheduled.track_id,
+                time_beats: scheduled.scheduled_beat,
+                duration_beats: duration_beats as f64,
+                frequencies,
+                drums: drums.iter().map(|d| d.short_name().to_string()).collect(),
+                velocity: velocities.into_iter().max().unwrap_or(DEFAULT_VELOCITY),
+                waveform: track_waveforms.get(&scheduled.track_id).cloned(),
+                pan: None,
+            });
+        }
+    }
+
+    let env = interpreter.shared_environment();
+    let evaluator = Evaluator::new();
+
+    for (track, expression) in &loops {
+        let mut offset = beats(0);
+        for cycle in 0..cycles as usize {
+            // Expressions see `_beat`/`_cycle` as they would at this point during playback
+            {
+                let mut env_write = env.write().map_err(|e| e.to_string())?;
+                env_write.define("_beat".to_string(), Value::Number(to_f64(offset) as i32));
+                env_write.define("_cycle".to_string(), Value::Number(cycle as i32));
+            }
+            let env_guard = env.read().map_err(|e| e.to_string())?;
+            let pattern = evaluator
+                .eval_with_env(
+                    expression.clone(),
+                    Some(EnvironmentRef::Borrowed(&env_guard)),
+                )
+                .map_err(|e| e.to_string())?
+                .pattern_for_cycle(cycle)?;
+
+            let waveform = pattern
+                .waveform
+                .map(|w| w.name().to_string())
+                .or_else(|| track_waveforms.get(track).cloned());
+            for event in pattern.to_rich_events_for_cycle(cycle) {
+                if event.is_rest {
+                    continue;
+                }
+                events.push(TimedEventJS {
+                    track: *track,
+                    time_beats: to_f64(offset + event.start_beat),
+                    duration_beats: to_f64(event.duration),
+                    frequencies: event.notes.iter().map(|n| n.frequency).collect(),
+                    drums: event
+                        .drums
+                        .iter()
+                        .map(|d| d.short_name().to_string())
+                        .collect(),
+                    velocity: event
+                        .notes
+                        .iter()
+                        .map(|n| n.velocity)
+                        .max()
+                        .unwrap_or(DEFAULT_VELOCITY),
+                    waveform: waveform.clone(),
+                    pan: pattern.pan,
+                });
+            }
+
+            if pattern.beats_per_cycle <= beats(0) {
+                break;
+            }
+            offset += pattern.beats_per_cycle;
+        }
+    }
+
+    events.sort_by(|a, b| {
+        a.time_beats
+            .total_cmp(&b.time_beats)
+            .then(a.track.cmp(&b.track))
+    });
+
+    Ok(PlaybackScheduleJS {
+        bpm,
+        events,
+        error: None,
+    })
+}
+
+/// Compile a program into timed events for a Web Audio scheduler.
+/// Returns PlaybackScheduleJS; editor squiggles come from `parse_and_check`.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn compile_playback(source: &str, cycles: u32, bpm: f32) -> JsValue {
+    let schedule =
+        compile_playback_events(source, cycles, bpm).unwrap_or_else(|e| PlaybackScheduleJS {
+            bpm,
+            events: vec![],
+            error: Some(e),
+        });
+    serde_wasm_bindgen::to_value(&schedule).unwrap_or(JsValue::NULL)
+}
+
 // ============================================================================
 // Cursor Context API (for Properties Panel)
 // ============================================================================
@@ -1224,7 +1409,7 @@ pub fn get_context_at_cursor(code: &str, position: usize) -> JsValue {
         Statement::Let { name, value } => {
             ("let".to_string(), Some(value.clone()), Some(name.clone()))
         }
-        Statement::Assign { name, value } => (
+        Statement::Assign { name, value } | Statement::IndexAssign { name, value, .. } => (
             "assign".to_string(),
             Some(value.clone()),
             Some(name.clone()),
@@ -1235,6 +1420,7 @@ pub fn get_context_at_cursor(code: &str, position: usize) -> JsValue {
             // Extract tempo value from expression if it's a simple number
             let tempo_val = match expr {
                 Expression::Number(n) => Some(*n as f32),
+                Expression::Float(n) => Some(*n),
                 _ => None,
             };
             // Direct tempo statement
@@ -1307,6 +1493,98 @@ pub fn get_context_at_cursor(code: &str, position: usize) -> JsValue {
             };
             return serde_wasm_bindgen::to_value(&context).unwrap_or(JsValue::NULL);
         }
+        Statement::UseStrict => {
+            let context = CursorContextJS {
+                statement_type: "use".to_string(),
+                value_type: None,
+                properties: None,
+                span: SpanInfoJS {
+                    start: spanned_stmt.start,
+                    end: spanned_stmt.end,
+                    utf16_start: spanned_stmt.utf16_start,
+                    utf16_end: spanned_stmt.utf16_end,
+                },
+                variable_name: Some("use strict".to_string()),
+            };
+            return serde_wasm_bindgen::to_value(&context).unwrap_or(JsValue::NULL);
+        }
+        Statement::Alias { .. } => {
+            let context = CursorContextJS {
+                statement_type: "alias".to_string(),
+                value_type: None,
+                properties: None,
+                span: SpanInfoJS {
+                    start: spanned_stmt.start,
+                    end: spanned_stmt.end,
+                    utf16_start: spanned_stmt.utf16_start,
+                    utf16_end: spanned_stmt.utf16_end,
+                },
+                variable_name: None,
+            };
+            return serde_wasm_bindgen::to_value(&context).unwrap_or(JsValue::NULL);
+        }
+        Statement::Bind(_) => {
+            let context = CursorContextJS {
+                statement_type: "bind".to_string(),
+                value_type: None,
+                properties: None,
+                span: SpanInfoJS {
+                    start: spanned_stmt.start,
+                    end: spanned_stmt.end,
+                    utf16_start: spanned_stmt.utf16_start,
+                    utf16_end: spanned_stmt.utf16_end,
+                },
+                variable_name: None,
+            };
+            return serde_wasm_bindgen::to_value(&context).unwrap_or(JsValue::NULL);
+        }
+        Statement::ControlChange { .. } => {
+            let context = CursorContextJS {
+                statement_type: "cc".to_string(),
+                value_type: Some("control".to_string()),
+                properties: None,
+                span: SpanInfoJS {
+                    start: spanned_stmt.start,
+                    end: spanned_stmt.end,
+                    utf16_start: spanned_stmt.utf16_start,
+                    utf16_end: spanned_stmt.utf16_end,
+                },
+                variable_name: None,
+            };
+            return serde_wasm_bindgen::to_value(&context).unwrap_or(JsValue::NULL);
+        }
+        Statement::Capo(_) => {
+            let context = CursorContextJS {
+                statement_type: "capo".to_string(),
+                value_type: Some("number".to_string()),
+                properties: None,
+                span: SpanInfoJS {
+                    start: spanned_stmt.start,
+                    end: spanned_stmt.end,
+                    utf16_start: spanned_stmt.utf16_start,
+                    utf16_end: spanned_stmt.utf16_end,
+                },
+                variable_name: None,
+            };
+            return serde_wasm_bindgen::to_value(&context).unwrap_or(JsValue::NULL);
+        }
+        Statement::MidiProgram(_)
+        | Statement::MidiBank { .. }
+        | Statement::MidiVelocityCurve(_) => {
+            let context = CursorContextJS {
+                statement_type: "midi".to_string(),
+                value_type: None,
+                properties: None,
+                span: SpanInfoJS {
+                    start: spanned_stmt.start,
+                    end: spanned_stmt.end,
+                    utf16_start: spanned_stmt.utf16_start,
+                    utf16_end: spanned_stmt.utf16_end,
+                },
+                variable_name: None,
+            };
+            return serde_wasm_bindgen::to_value(&context).unwrap_or(JsValue::NULL);
+        }
         Statement::Stop => {
             let context = CursorContextJS {
                 statement_type: "stop".to_string(),
@@ -1436,6 +1714,21 @@ pub fn get_context_at_cursor(code: &str, position: usize) -> JsValue {
             };
             return serde_wasm_bindgen::to_value(&context).unwrap_or(JsValue::NULL);
         }
+        Statement::While { condition, body } => {
+            let context = CursorContextJS {
+                statement_type: "while".to_string(),
+                value_type: Some(format!("{} statements", body.len())),
+                properties: None,
+                span: SpanInfoJS {
+                    start: spanned_stmt.start,
+                    end: spanned_stmt.end,
+                    utf16_start: spanned_stmt.utf16_start,
+                    utf16_end: spanned_stmt.utf16_end,
+                },
+                variable_name: Some(condition.to_string()),
+            };
+            return serde_wasm_bindgen::to_value(&context).unwrap_or(JsValue::NULL);
+        }
         Statement::If {
             then_body,
             else_body,
@@ -1596,7 +1889,7 @@ pub fn get_context_at_cursor(code: &str, position: usize) -> JsValue {
                     }
                     Value::Chord(_) => ("chord".to_string(), None),
                     Value::Note(_) => ("note".to_string(), None),
-                    Value::Number(_) => ("number".to_string(), None),
+                    Value::Number(_) | Value::Float(_) => ("number".to_string(), None),
                     Value::String(_) => ("string".to_string(), None),
                     Value::Boolean(_) => ("boolean".to_string(), None),
                     Value::Function { .. } => ("function".to_string(), None),
@@ -1613,6 +1906,20 @@ pub fn get_context_at_cursor(code: &str, position: usize) -> JsValue {
                         };
                         ("every_pattern".to_string(), Some(props))
                     }
+                    Value::ProbabilityPattern(ref sometimes) => {
+                        let props = EditablePropertiesJS {
+                            waveform: sometimes
+                                .base
+                                .waveform
+                                .as_ref()
+                                .map(|w| w.name().to_string()),
+                            envelope: sometimes.base.envelope.map(|(a, d, s, r)| [a, d, s, r]),
+                            tempo: None,
+                            volume: None,
+                            beats_per_cycle: Some(sometimes.base.beats_per_cycle_f32()),
+                        };
+                        ("probability_pattern".to_string(), Some(props))
+                    }
                     Value::Thunk { .. } => ("thunk".to_string(), None),
                 };
                 (Some(vt), props)
@@ -1723,11 +2030,16 @@ impl WasmInterpreter {
                     }
                 }
                 crate::parser::Statement::FunctionDef {
-                    name, params, body, ..
+                    name,
+                    params,
+                    body,
+                    return_type,
+                    ..
                 } => {
-                    exports
-                        .functions
-                        .insert(name.clone(), (params.clone(), body.clone()));
+                    exports.functions.insert(
+                        name.clone(),
+                        (params.clone(), body.clone(), return_type.clone()),
+                    );
                 }
                 _ => {}
             }
@@ -1741,13 +2053,14 @@ impl WasmInterpreter {
             for (name, val) in &exports.values {
                 env.define(name.clone(), val.clone());
             }
-            for (name, (params, body)) in &exports.functions {
+            for (name, (params, body, return_type)) in &exports.functions {
                 env.define(
                     name.clone(),
                     Value::Function {
                         name: name.clone(),
                         params: params.clone(),
                         body: body.clone(),
+                        return_type: return_type.clone(),
                     },
                 );
             }
@@ -1991,6 +2304,12 @@ impl WasmInterpreter {
                         .map(|e| e.duration_f32())
                         .sum()
                 }
+                Value::ProbabilityPattern(ref sometimes) => sometimes
+                    .base
+                    .to_rich_events()
+                    .iter()
+                    .map(|e| e.duration_f32())
+                    .sum(),
                 _ => 1.0,
             };
 
@@ -2017,6 +2336,14 @@ impl WasmInterpreter {
             };
             drop(env_read);
 
+            // A `sometimes` pattern plays the variant picked for this cycle
+            let value = match value {
+                Value::ProbabilityPattern(sometimes) => {
+                    Value::Pattern(sometimes.pattern_for_cycle(pattern_cycle as usize))
+                }
+                other => other,
+            };
+
             // Convert to rich events (with full note identity)
             // Also capture the exact beats_per_cycle to avoid floating-point accumulation errors (e.g., 6 * 0.333... = 3.999... | Sneaky bug)
             let (events, envelope, waveform, pan, beats_per_cycle) = match value {
@@ -2278,6 +2605,12 @@ impl WasmInterpreter {
             }
         };
 
+        // `sometimes` patterns are shown by their base pattern, like `every` patterns
+        let value = match value {
+            Value::ProbabilityPattern(sometimes) => Value::Pattern(sometimes.base),
+            other => other,
+        };
+
         // Convert to events
         let result: PatternEventsJS = match value {
             Value::Pattern(ref p) => {
@@ -2430,7 +2763,7 @@ impl WasmInterpreter {
             Statement::Let { name, value } => {
                 ("let".to_string(), Some(value.clone()), Some(name.clone()))
             }
-            Statement::Assign { name, value } => (
+            Statement::Assign { name, value } | Statement::IndexAssign { name, value, .. } => (
                 "assign".to_string(),
                 Some(value.clone()),
                 Some(name.clone()),
@@ -2515,6 +2848,109 @@ impl WasmInterpreter {
     }
 }
 
+// ============================================================================
+// Keyboard Layout (for the piano UI)
+// ============================================================================
+
+/// Build the piano key layout for an inclusive MIDI range as a JSON array.
+///
+/// Each key has its MIDI number, name, color and `position`: the number of
+/// white keys before it in the range. Black keys sit on the boundary at that
+/// position, between the white keys on either side.
+pub fn keyboard_layout_json(low: u8, high: u8) -> Result<String, String> {
+    if high > 127 {
+        return Err(format!("MIDI range must be within 0-127, got {}", high));
+    }
+    if low > high {
+        return Err(format!("Invalid MIDI range: {} is above {}", low, high));
+    }
+
+    let mut white_keys = 0;
+    let keys: Vec<String> = (low..=high)
+        .map(|midi| {
+            let note = crate::types::Note::new_with_octave(midi % 12, (midi / 12) as i8 - 1)
+                .expect("pitch class is always 0-11");
+            let is_black = note.is_black_key();
+            let key = format!(
+                r#"{{"midi":{},"name":"{}","black":{},"position":{}}}"#,
+                midi,
+                note.full_name(),
+                is_black,
+                white_keys
+            );
+            if !is_black {
+                white_keys += 1;
+            }
+            key
+        })
+        .collect();
+
+    Ok(format!("[{}]", keys.join(",")))
+}
+
+/// Piano key layout for a MIDI range (inclusive), or `{"error": ...}` if invalid
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn keyboard_layout(low: u8, high: u8) -> String {
+    keyboard_layout_json(low, high).unwrap_or_else(|e| format!(r#"{{"error":"{}"}}"#, e))
+}
+
+/// Result of a JSON conversion (JS-serializable)
+#[cfg(feature = "wasm")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct JsonResult {
+    success: bool,
+    output: Option<String>,
+    error: Option<String>,
+}
+
+#[cfg(feature = "wasm")]
+fn json_result(result: Result<String, String>) -> JsValue {
+    let (output, error) = match result {
+        Ok(output) => (Some(output), None),
+        Err(e) => (None, Some(e)),
+    };
+    serde_wasm_bindgen::to_value(&JsonResult {
+        success: error.is_none(),
+        output,
+        error,
+    })
+    .unwrap_or(JsValue::NULL)
+}
+
+/// Evaluate an expression and return its value as JSON (the `to_json` shape)
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn value_to_json(expression: &str) -> JsValue {
+    json_result(
+        crate::parser::eval(expression)
+            .map_err(|e| e.to_string())
+            .and_then(|value| value.to_json()),
+    )
+}
+
+/// Rebuild a value from `to_json` output and return it in Cadence notation
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn value_from_json(json: &str) -> JsValue {
+    use crate::parser::Value;
+
+    json_result(Value::from_json(json).map(|value| value.to_string()))
+}
+
+/// Evaluate an expression and return the frequencies (Hz) of each event in
+/// its first cycle as a JSON array of arrays. Rests give empty arrays.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn frequencies_json(source: &str) -> JsValue {
+    json_result(
+        crate::parser::eval(source)
+            .map_err(|e| e.to_string())
+            .and_then(|value| value.event_frequencies())
+            .and_then(|events| serde_json::to_string(&events).map_err(|e| e.to_string())),
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -2626,4 +3062,27 @@ mod tests {
             assert_eq!(types, expected_types, "Failed for input: {}", input);
         }
     }
+
+    #[test]
+    fn test_keyboard_layout() {
+        // One octave C4-C5: 8 white keys, 5 black keys
+        let json = keyboard_layout_json(60, 72).unwrap();
+        assert_eq!(json.matches("\"midi\"").count(), 13);
+        assert_eq!(json.matches("\"black\":true").count(), 5);
+        assert!(json.starts_with(r#"[{"midi":60,"name":"C4","black":false,"position":0}"#));
+        // C#4 sits between the first two white keys
+        assert!(json.contains(r#"{"midi":61,"name":"C#4","black":true,"position":1}"#));
+        assert!(json.ends_with(r#"{"midi":72,"name":"C5","black":false,"position":7}]"#));
+
+        // Bounds are inclusive
+        assert_eq!(
+            keyboard_layout_json(64, 64)
+                .unwrap()
+                .matches("midi")
+                .count(),
+            1
+        );
+        assert!(keyboard_layout_json(72, 60).is_err());
+        assert!(keyboard_layout_json(0, 128).is_err());
+    }
 }
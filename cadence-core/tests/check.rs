@@ -0,0 +1,68 @@
+//! `check_source` over fixture files with known issues
+
+use cadence_core::parser::validator::check_source;
+
+/// Each problem as "line: message"
+fn check(source: &str) -> Vec<String> {
+    check_source(source)
+        .iter()
+        .map(|error| format!("{}: {}", error.span.line, error.message))
+        .collect()
+}
+
+#[test]
+fn test_clean_file_has_no_issues() {
+    let issues = check(include_str!("fixtures/check/clean.cadence"));
+    assert!(issues.is_empty(), "{:#?}", issues);
+}
+
+#[test]
+fn test_broken_file_reports_every_issue() {
+    let issues = check(include_str!("fixtures/check/broken.cadence"));
+    assert_eq!(
+        issues,
+        vec![
+            "4: Expected identifier after 'let'",
+            "5: Pattern error: Unclosed bracket in pattern",
+            "6: Tempo requires a number, found a string",
+            "7: Function 'fast' expects 2 arguments, got 1",
+            "8: Unknown function: glissando",
+            "9: Pattern error: Unexpected character in pattern: '('",
+            "10: Expected RightParen, found LeftBrace",
+            "11: wait requires a number, found a chord",
+        ]
+    );
+}
+
+#[test]
+fn test_imports_may_define_functions() {
+    // Functions from a whole-module import or a namespace can't be seen
+    assert!(check("use \"lib.cadence\"\nplay groove() loop").is_empty());
+    assert!(check("use \"lib.cadence\" as lib\nplay lib_groove() loop").is_empty());
+    assert_eq!(
+        check("use { groove } from \"lib.cadence\"\nplay outro() loop"),
+        vec!["2: Unknown function: outro"]
+    );
+}
+
+#[test]
+fn test_std_prefix_and_strict_mode() {
+    assert!(check("play std.fast(\"C E\", 2) loop").is_empty());
+    assert_eq!(
+        check("play std.fast(\"C E\") loop"),
+        vec!["1: Function 'std.fast' expects 2 arguments, got 1"]
+    );
+    assert_eq!(
+        check("use strict\nplay std.rev(\"C E\") loop\nplay rev(\"C E\") loop"),
+        vec!["3: Builtin 'rev' needs the std prefix after use strict: std.rev()"]
+    );
+    // Method calls through std., and transforms passed by name, are fine
+    assert!(check(
+        "use strict\nplay \"C E\".std.fast(2).std.rev() loop\nplay std.every(2, \"rev\", \"C E\") loop\nplay std.jux(\"C E\", rev) loop"
+    )
+    .is_empty());
+    assert_eq!(
+        check("use strict\nplay \"C E\".rev() loop"),
+        vec!["2: Builtin 'rev' needs the std prefix after use strict: std.rev()"]
+    );
+}
@@ -0,0 +1,122 @@
+//! Pinned JSON shapes for `Value::to_json`, relied on by the web frontend
+#![cfg(feature = "serde")]
+
+use cadence_core::parser::{eval, Value};
+use cadence_core::types::Pattern;
+
+fn json(source: &str) -> String {
+    eval(source).unwrap().to_json().unwrap()
+}
+
+#[test]
+fn test_scalar_shapes() {
+    assert_eq!(json("C#4"), r#"{"type":"note","value":"C#4"}"#);
+    assert_eq!(json("Db3"), r#"{"type":"note","value":"Db3"}"#);
+    assert_eq!(json("true"), r#"{"type":"boolean","value":true}"#);
+    assert_eq!(json("3"), r#"{"type":"number","value":3}"#);
+    assert_eq!(
+        json("[1, C]"),
+        r#"{"type":"array","value":[{"type":"number","value":1},{"type":"note","value":"C4"}]}"#
+    );
+}
+
+#[test]
+fn test_chord_shape() {
+    assert_eq!(
+        json("[E, G, C5]"),
+        r#"{"type":"chord","value":{"notes":["E4","G4","C5"],"bass":"E4"}}"#
+    );
+}
+
+#[test]
+fn test_pattern_shape() {
+    assert_eq!(
+        json(r#""C [E G] _ kick".wave("saw").env("pluck").pan(25).swing_n(8, 50)"#),
+        concat!(
+            r#"{"type":"pattern","value":{"steps":["#,
+            r#"{"type":"note","value":"C4"},"#,
+            r#"{"type":"group","value":[{"type":"note","value":"E4"},{"type":"note","value":"G4"}]},"#,
+            r#"{"type":"rest"},"#,
+            r#"{"type":"drum","value":"Kick"}],"#,
+            r#""beats_per_cycle":[4,1],"envelope":[0.001,0.15,0.0,0.1],"waveform":"saw","#,
+            r#""pan":0.25,"swing":{"subdivision":8,"amount":50}}}"#
+        )
+    );
+}
+
+#[test]
+fn test_step_shapes() {
+    let pattern = Pattern::parse("C*3 D@2 <E F> G(3,8) {C D, E} A(0.5) foo").unwrap();
+    assert_eq!(
+        Value::Pattern(pattern).to_json().unwrap(),
+        concat!(
+            r#"{"type":"pattern","value":{"steps":["#,
+            r#"{"type":"repeat","value":[{"type":"note","value":"C4"},3]},"#,
+            r#"{"type":"weighted","value":[{"type":"note","value":"D4"},2]},"#,
+            r#"{"type":"alternation","value":[{"type":"note","value":"E4"},{"type":"note","value":"F4"}]},"#,
+            r#"{"type":"euclidean","value":[{"type":"note","value":"G4"},3,8,0]},"#,
+            r#"{"type":"polyrhythm","value":[[{"type":"note","value":"C4"},{"type":"note","value":"D4"}],[{"type":"note","value":"E4"}]]},"#,
+            r#"{"type":"velocity","value":[{"type":"note","value":"A4"},64]},"#,
+            r#"{"type":"variable","value":"foo"}],"#,
+            r#""beats_per_cycle":[4,1],"envelope":null,"waveform":null,"pan":null,"swing":null}}"#
+        )
+    );
+}
+
+#[test]
+fn test_every_pattern_shape() {
+    let pattern = r#"{"steps":[{"type":"note","value":"C4"},{"type":"note","value":"D4"}],"beats_per_cycle":[4,1],"envelope":null,"waveform":null,"pan":null,"swing":null}"#;
+    let reversed = r#"{"steps":[{"type":"note","value":"D4"},{"type":"note","value":"C4"}],"beats_per_cycle":[4,1],"envelope":null,"waveform":null,"pan":null,"swing":null}"#;
+    assert_eq!(
+        json(r#"every(2, rev, "C D")"#),
+        format!(
+            r#"{{"type":"every_pattern","value":{{"interval":2,"base":{},"transformed":{}}}}}"#,
+            pattern, reversed
+        )
+    );
+}
+
+#[test]
+fn test_round_trip() {
+    for source in [
+        "Db3",
+        "[G, C5, E5]",
+        r#""C*3 D@2 <E F> G(3,8) {C D, E} A(0.5) [C,E,G] _ hh B(3,8,2) {C E G}%4 D:2 [E|G]".wave("square").env(10, 20, 70, 300)"#,
+        r#"every(3, rev, "C E G").fast(2)"#,
+        "[C, [E, G], 4]",
+    ] {
+        let value = eval(source).unwrap();
+        let restored = Value::from_json(&value.to_json().unwrap()).unwrap();
+        assert_eq!(restored, value, "{}", source);
+    }
+}
+
+#[test]
+fn test_unknown_fields_are_ignored() {
+    let value = Value::from_json(
+        r#"{"type":"chord","value":{"notes":["C4","E4","G4"],"bass":"C4","voicing":"close"},"source":"repl"}"#,
+    )
+    .unwrap();
+    assert_eq!(value, eval("[C, E, G]").unwrap());
+}
+
+#[test]
+fn test_euclidean_without_rotation_still_loads() {
+    let value = Value::from_json(
+        r#"{"type":"pattern","value":{"steps":[{"type":"euclidean","value":[{"type":"note","value":"G4"},3,8]}],"beats_per_cycle":[4,1]}}"#,
+    )
+    .unwrap();
+    assert_eq!(value, eval(r#""G(3,8)""#).unwrap());
+}
+
+#[test]
+fn test_functions_have_no_json_form() {
+    let function = Value::Function {
+        name: "f".to_string(),
+        params: vec![],
+        body: vec![],
+        return_type: None,
+    };
+    assert!(function.to_json().is_err());
+    assert!(Value::from_json(r#"{"type":"function","value":{}}"#).is_err());
+}
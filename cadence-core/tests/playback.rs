@@ -0,0 +1,93 @@
+//! `compile_playback_events`: the event stream handed to the web scheduler.
+//! Runs natively, and in a browser through wasm-bindgen-test.
+
+use cadence_core::wasm::{compile_playback_events, TimedEventJS};
+
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen_test::wasm_bindgen_test;
+
+const TWO_TRACKS: &str = r#"
+tempo 90
+track 1 play "C E G _" loop
+track 2 play "kick*2" loop
+"#;
+
+/// (time, track) of every event
+fn timeline(events: &[TimedEventJS]) -> Vec<(f64, usize)> {
+    events.iter().map(|e| (e.time_beats, e.track)).collect()
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_two_tracks_over_two_cycles() {
+    let schedule = compile_playback_events(TWO_TRACKS, 2, 120.0).unwrap();
+    assert_eq!(schedule.bpm, 90.0);
+    assert_eq!(
+        timeline(&schedule.events),
+        vec![
+            (0.0, 1),
+            (0.0, 2),
+            (1.0, 1),
+            (2.0, 1),
+            (2.0, 2),
+            (4.0, 1),
+            (4.0, 2),
+            (5.0, 1),
+            (6.0, 1),
+            (6.0, 2),
+        ]
+    );
+
+    let kick = &schedule.events[1];
+    assert_eq!(kick.drums, vec!["kick".to_string()]);
+    assert!(kick.frequencies.is_empty());
+    assert_eq!(kick.duration_beats, 2.0);
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_cycle_combinators_advance() {
+    let schedule = compile_playback_events(r#"play every(2, rev, "C D") loop"#, 2, 120.0).unwrap();
+    let pitches: Vec<f32> = schedule.events.iter().map(|e| e.frequencies[0]).collect();
+    // Cycle 0 plays the pattern as written, cycle 1 reversed
+    assert!(pitches[0] < pitches[1]);
+    assert!(pitches[2] > pitches[3]);
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_one_shots_land_after_waits() {
+    let source = "play \"C*2\".wave(\"saw\").pan(25) loop\nwait 2\nplay \"D E\"";
+    let schedule = compile_playback_events(source, 2, 120.0).unwrap();
+    let times: Vec<f64> = schedule.events.iter().map(|e| e.time_beats).collect();
+    assert_eq!(times, vec![0.0, 2.0, 2.0, 4.0, 4.0, 6.0]);
+
+    let looped = &schedule.events[0];
+    assert_eq!(looped.waveform.as_deref(), Some("saw"));
+    assert_eq!(looped.pan, Some(0.25));
+    assert_eq!(looped.duration_beats, 2.0);
+    let one_shots: Vec<f64> = schedule
+        .events
+        .iter()
+        .filter(|e| e.waveform.is_none())
+        .map(|e| e.time_beats)
+        .collect();
+    assert_eq!(one_shots, vec![2.0, 4.0]);
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_later_loop_replaces_earlier() {
+    let schedule = compile_playback_events("play \"C\" loop\nplay \"D E\" loop", 1, 120.0).unwrap();
+    assert_eq!(schedule.events.len(), 2);
+
+    let schedule = compile_playback_events("play \"C\" loop\nstop", 1, 120.0).unwrap();
+    assert!(schedule.events.is_empty());
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn test_errors_are_reported() {
+    assert!(compile_playback_events("let = 3", 1, 120.0).is_err());
+    assert!(compile_playback_events("play nope loop", 1, 120.0).is_err());
+}
@@ -8,7 +8,7 @@
  * Module imports (`use` statements) are not supported in the browser.
  */
 
-import init, { tokenize, parse_and_check, run_script, get_events_at_position, get_context_at_cursor, get_documentation, get_symbols, get_symbol_at_position, get_definition_by_name, get_use_statements, WasmInterpreter } from './wasm/cadence_core.js';
+import init, { tokenize, parse_and_check, run_script, compile_playback, get_events_at_position, get_context_at_cursor, get_documentation, get_symbols, get_symbol_at_position, get_definition_by_name, get_use_statements, WasmInterpreter } from './wasm/cadence_core.js';
 
 export interface HighlightSpan {
     start_line: number;
@@ -153,6 +153,32 @@ export interface ScriptResult {
     output: string[];
 }
 
+/** A sounding event on the compiled timeline (returned by compile_playback) */
+export interface TimedEvent {
+    track: number;
+    /** Start in beats from the start of the program */
+    time_beats: number;
+    duration_beats: number;
+    /** Frequencies to play (empty for drum-only events) */
+    frequencies: number[];
+    /** Drum sounds to play (for percussion) */
+    drums: string[];
+    /** MIDI velocity (0-127) of the loudest note */
+    velocity: number;
+    /** The pattern's waveform, or the track's if the pattern has none */
+    waveform: string | null;
+    /** Stereo pan position (0.0 = left, 0.5 = center, 1.0 = right) */
+    pan: number | null;
+}
+
+/** Every track's events over a number of cycles, in time order */
+export interface PlaybackSchedule {
+    /** The script's tempo, or the one passed in if it sets none */
+    bpm: number;
+    events: TimedEvent[];
+    error: string | null;
+}
+
 // ============================================================================
 // Cursor Context Types (for Properties Panel)
 // ============================================================================
@@ -287,6 +313,24 @@ export function runScript(input: string): ScriptResult {
     }
 }
 
+/**
+ * Compile a script into timed events for a Web Audio scheduler,
+ * looping tracks unrolled for the given number of cycles
+ */
+export function compilePlayback(input: string, cycles: number, bpm: number): PlaybackSchedule {
+    if (!wasmInitialized) {
+        console.warn('WASM not initialized, call initWasm() first');
+        return { bpm, events: [], error: 'WASM not initialized' };
+    }
+
+    try {
+        return compile_playback(input, cycles, bpm) as PlaybackSchedule;
+    } catch (e) {
+        console.error('Compile playback error:', e);
+        return { bpm, events: [], error: String(e) };
+    }
+}
+
 /**
  * Get play events for the statement at the given cursor position
  * Returns PatternEvents with events and cycle timing for piano roll rendering
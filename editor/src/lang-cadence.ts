@@ -10,7 +10,7 @@ import { tags } from '@lezer/highlight';
 
 // Cadence keywords
 const KEYWORDS = new Set([
-    'let', 'fn', 'loop', 'repeat', 'if', 'else', 'break', 'continue', 'return',
+    'let', 'fn', 'loop', 'repeat', 'while', 'if', 'else', 'break', 'continue', 'return',
     'play', 'stop', 'track', 'on', 'load'
 ]);
 
@@ -379,7 +379,7 @@ mod tests {
         for _ in 0..10000 {
             let sample = env.next_sample();
             assert!(
-                sample >= 0.0 && sample <= 1.0,
+                (0.0..=1.0).contains(&sample),
                 "Sample {} out of range",
                 sample
             );
@@ -390,7 +390,7 @@ mod tests {
         for _ in 0..10000 {
             let sample = env.next_sample();
             assert!(
-                sample >= 0.0 && sample <= 1.0,
+                (0.0..=1.0).contains(&sample),
                 "Sample {} out of range",
                 sample
             );
@@ -7,8 +7,9 @@ use std::sync::mpsc::{channel, Sender};
 use std::sync::{Arc, Mutex};
 use std::thread::{self, JoinHandle};
 
-use super::drum_synth::DrumOscillator;
-use super::oscillator::EnvelopedOscillator;
+use super::delay::DelaySettings;
+use super::headless::HeadlessAudio;
+use super::mixer::Mixer;
 use crate::types::Waveform;
 
 /// State for a single audio track
@@ -16,6 +17,8 @@ use crate::types::Waveform;
 pub struct TrackState {
     /// List of frequencies to play (in Hz)
     pub notes: Vec<f32>,
+    /// Pan for each note, overriding `pan` (empty when the notes follow the track)
+    pub note_pans: Vec<Option<f32>>,
     /// Volume level (0.0 to 1.0)
     pub volume: f32,
     /// Whether this specific track is playing (not currently used for master pause)
@@ -28,18 +31,22 @@ pub struct TrackState {
     pub pan: f32,
     /// Force envelope retrigger on next note (for same-note sequences like [C5 C5])
     pub retrigger: bool,
+    /// Optional feedback delay the track's voices are sent through
+    pub delay: Option<DelaySettings>,
 }
 
 impl Default for TrackState {
     fn default() -> Self {
         TrackState {
             notes: Vec::new(),
+            note_pans: Vec::new(),
             volume: 1.0, // Individual tracks default to full volume (master mixer handles global)
             is_playing: true,
             envelope: None,                // Use default ADSR
             waveform: Waveform::default(), // Sine by default
             pan: 0.5,                      // Center by default
             retrigger: false,
+            delay: None,
         }
     }
 }
@@ -55,6 +62,8 @@ pub struct AudioState {
     pub is_playing: bool,
     /// Pending drum triggers: (track_id, drum_sound)
     pub pending_drums: Vec<(usize, DrumSound)>,
+    /// Hard reset requested: the stream drops every sounding voice on its next callback
+    pub reset_pending: bool,
 }
 
 impl Default for AudioState {
@@ -64,11 +73,86 @@ impl Default for AudioState {
             volume: 0.2,       // Default to 20% master volume
             is_playing: false, // Start paused
             pending_drums: Vec::new(),
+            reset_pending: false,
         }
     }
 }
 
-// EnvelopedOscillator is now in oscillator.rs
+impl AudioState {
+    /// Silence every track and ask the stream to drop all voices immediately,
+    /// skipping release tails (used by `panic`)
+    pub fn reset_voices(&mut self) {
+        for track in self.tracks.values_mut() {
+            track.notes.clear();
+            track.retrigger = false;
+        }
+        self.pending_drums.clear();
+        self.reset_pending = true;
+    }
+
+    /// Apply a command from an [`AudioPlayerHandle`]. `Play` and `Pause` only
+    /// set the master flag here; `Quit` is for the audio thread.
+    pub fn apply(&mut self, command: AudioPlayerCommand) {
+        match command {
+            AudioPlayerCommand::SetTrackNotes(track_id, notes) => {
+                let track = self.tracks.entry(track_id).or_default();
+
+                // Check if we need to retrigger (notes are the same but new event)
+                // This handles sequences like [C5 C5] where the same note is played twice
+                let same_notes = track.notes.len() == notes.len()
+                    && track
+                        .notes
+                        .iter()
+                        .zip(notes.iter())
+                        .all(|(a, b)| (a - b).abs() < 0.01);
+
+                // Same notes - request retrigger. Otherwise reset the flag so
+                // it can be set again next time
+                track.retrigger = same_notes && !notes.is_empty();
+                track.notes = notes;
+                track.note_pans.clear();
+            }
+            AudioPlayerCommand::TriggerNote(track_id, notes) => {
+                let track = self.tracks.entry(track_id).or_default();
+                // Always force retrigger for scheduled notes
+                track.retrigger = true;
+                track.notes = notes;
+                track.note_pans.clear();
+            }
+            AudioPlayerCommand::TriggerPannedNotes(track_id, notes, pans) => {
+                let track = self.tracks.entry(track_id).or_default();
+                track.retrigger = true;
+                track.notes = notes;
+                track.note_pans = pans;
+            }
+            AudioPlayerCommand::SetTrackVolume(track_id, volume) => {
+                self.tracks.entry(track_id).or_default().volume = volume.clamp(0.0, 1.0);
+            }
+            AudioPlayerCommand::SetTrackEnvelope(track_id, envelope) => {
+                self.tracks.entry(track_id).or_default().envelope = envelope;
+            }
+            AudioPlayerCommand::SetTrackWaveform(track_id, waveform) => {
+                self.tracks.entry(track_id).or_default().waveform = waveform;
+            }
+            AudioPlayerCommand::SetTrackPan(track_id, pan) => {
+                self.tracks.entry(track_id).or_default().pan = pan.clamp(0.0, 1.0);
+            }
+            AudioPlayerCommand::SetTrackDelay(track_id, delay) => {
+                self.tracks.entry(track_id).or_default().delay = delay;
+            }
+            AudioPlayerCommand::PlayDrum(track_id, drum) => {
+                self.pending_drums.push((track_id, drum));
+            }
+            AudioPlayerCommand::ResetVoices => self.reset_voices(),
+            AudioPlayerCommand::SetMasterVolume(volume) => {
+                self.volume = volume.clamp(0.0, 1.0);
+            }
+            AudioPlayerCommand::Play => self.is_playing = true,
+            AudioPlayerCommand::Pause => self.is_playing = false,
+            AudioPlayerCommand::Quit => {}
+        }
+    }
+}
 
 /// Commands that can be sent to the audio player thread
 #[derive(Debug, Clone)]
@@ -76,11 +160,16 @@ pub enum AudioPlayerCommand {
     SetTrackNotes(usize, Vec<f32>),
     /// Trigger notes with forced envelope attack (for scheduled playback)
     TriggerNote(usize, Vec<f32>),
+    /// Trigger notes like `TriggerNote`, each with its own pan (`None` follows the track)
+    TriggerPannedNotes(usize, Vec<f32>, Vec<Option<f32>>),
     SetTrackVolume(usize, f32),
     SetTrackEnvelope(usize, Option<(f32, f32, f32, f32)>),
     SetTrackWaveform(usize, Waveform),
     SetTrackPan(usize, f32),
+    SetTrackDelay(usize, Option<DelaySettings>),
     PlayDrum(usize, DrumSound),
+    /// Hard-reset all voices (panic)
+    ResetVoices,
     SetMasterVolume(f32),
     Play,
     Pause,
@@ -124,19 +213,9 @@ impl AudioPlayerInternal {
         T: Sample + SizedSample + Send + 'static + cpal::FromSample<f32>,
     {
         let channels = config.channels as usize;
-        let sample_rate = config.sample_rate.0 as f32;
-
-        let mut oscillators: Vec<EnvelopedOscillator> = Vec::new();
-        let mut drum_oscillators: Vec<DrumOscillator> = Vec::new();
-        // Track current frequencies per track: Map<TrackId, Vec<Freq>>
-        let mut track_frequencies: HashMap<usize, Vec<f32>> = HashMap::new();
-        // Track current waveforms per track to detect changes
-        let mut track_waveforms: HashMap<usize, Waveform> = HashMap::new();
-
-        let mut master_amplitude: f32 = 0.0;
-        // Master fade rate should match or exceed ADSR release time (200ms default)
-        // to allow envelopes to complete their release phase gracefully
-        let master_fade_rate = 1.0 / (0.25 * sample_rate); // 250ms for smooth master fade
+        let mut mixer = Mixer::new(config.sample_rate.0 as f32);
+        // Mixed as f32, then converted to the device's sample format
+        let mut buffer: Vec<f32> = Vec::new();
 
         let err_fn = |err| eprintln!("Audio stream error: {:?}", err);
 
@@ -155,145 +234,11 @@ impl AudioPlayerInternal {
                         }
                     };
 
-                    let master_volume = state.volume;
-                    let is_playing = state.is_playing;
-
-                    // Spawn drum oscillators for pending triggers
-                    for (track_id, drum_sound) in state.pending_drums.drain(..) {
-                        drum_oscillators.push(DrumOscillator::new(
-                            drum_sound,
-                            sample_rate,
-                            track_id,
-                        ));
+                    buffer.resize(data.len(), 0.0);
+                    mixer.fill(&mut state, &mut buffer, channels);
+                    for (sample, &value) in data.iter_mut().zip(&buffer) {
+                        *sample = T::from_sample(value);
                     }
-
-                    // 1. Sync oscillators with state
-                    // Check for changes in each track
-                    for (track_id, track_state) in &mut state.tracks {
-                        let current = track_frequencies.entry(*track_id).or_default();
-                        let current_waveform = track_waveforms
-                            .entry(*track_id)
-                            .or_insert(Waveform::default());
-
-                        // If notes changed OR waveform changed OR retrigger requested for this track
-                        let notes_changed = current.len() != track_state.notes.len()
-                            || current
-                                .iter()
-                                .zip(track_state.notes.iter())
-                                .any(|(a, b)| (a - b).abs() > 0.01);
-                        let waveform_changed = *current_waveform != track_state.waveform;
-
-                        // Check if retrigger is requested
-                        let needs_retrigger = track_state.retrigger;
-
-                        if notes_changed || waveform_changed || needs_retrigger {
-                            // Fade out old oscillators for this track
-                            for osc in oscillators.iter_mut().filter(|o| o.track_id == *track_id) {
-                                osc.start_fade_out();
-                            }
-
-                            // Add new oscillators with track's envelope settings
-                            for &freq in &track_state.notes {
-                                oscillators.push(EnvelopedOscillator::with_envelope(
-                                    freq,
-                                    sample_rate,
-                                    *track_id,
-                                    track_state.envelope,
-                                    track_state.waveform,
-                                ));
-                            }
-
-                            // Update cache
-                            *current = track_state.notes.clone();
-                            *current_waveform = track_state.waveform;
-
-                            // Reset retrigger flag AFTER processing - this is the proper fix!
-                            // Now trigger_note() can set it to true again for the next note.
-                            track_state.retrigger = false;
-                        }
-                    }
-
-                    // 2. Generate audio with stereo panning
-                    for frame in data.chunks_mut(channels) {
-                        if is_playing {
-                            master_amplitude = (master_amplitude + master_fade_rate).min(1.0);
-                        } else {
-                            master_amplitude = (master_amplitude - master_fade_rate).max(0.0);
-                        }
-
-                        let mut left_mix = 0.0f32;
-                        let mut right_mix = 0.0f32;
-                        let mut active_count = 0;
-
-                        // Sum all melodic oscillators with per-track panning
-                        for oscillator in oscillators.iter_mut() {
-                            let (track_vol, track_pan) = state
-                                .tracks
-                                .get(&oscillator.track_id)
-                                .map(|t| (t.volume, t.pan))
-                                .unwrap_or((1.0, 0.5));
-
-                            let sample = oscillator.next_sample();
-                            if sample.abs() > 0.0001 {
-                                // Equal-power panning: use sqrt for smooth stereo field
-                                let left_gain = (1.0 - track_pan).sqrt();
-                                let right_gain = track_pan.sqrt();
-
-                                left_mix += sample * track_vol * left_gain;
-                                right_mix += sample * track_vol * right_gain;
-                                active_count += 1;
-                            }
-                        }
-
-                        // Sum all drum oscillators (one-shot, with panning)
-                        for drum_osc in drum_oscillators.iter_mut() {
-                            let (track_vol, track_pan) = state
-                                .tracks
-                                .get(&drum_osc.track_id)
-                                .map(|t| (t.volume, t.pan))
-                                .unwrap_or((1.0, 0.5));
-
-                            let sample = drum_osc.next_sample();
-                            if sample.abs() > 0.0001 {
-                                let left_gain = (1.0 - track_pan).sqrt();
-                                let right_gain = track_pan.sqrt();
-
-                                left_mix += sample * track_vol * left_gain;
-                                right_mix += sample * track_vol * right_gain;
-                                active_count += 1;
-                            }
-                        }
-
-                        // Apply headroom scaling
-                        if active_count > 0 {
-                            left_mix *= 0.3;
-                            right_mix *= 0.3;
-                        }
-
-                        // Hard limiter
-                        left_mix = left_mix.clamp(-1.0, 1.0);
-                        right_mix = right_mix.clamp(-1.0, 1.0);
-
-                        // Apply master volume and amplitude
-                        left_mix *= master_volume * master_amplitude;
-                        right_mix *= master_volume * master_amplitude;
-
-                        // Write to output channels (stereo or mono)
-                        if channels >= 2 {
-                            frame[0] = T::from_sample(left_mix);
-                            frame[1] = T::from_sample(right_mix);
-                            // Fill remaining channels with center mix for surround
-                            for sample in frame.iter_mut().skip(2) {
-                                *sample = T::from_sample((left_mix + right_mix) * 0.5);
-                            }
-                        } else {
-                            // Mono output: use center mix
-                            frame[0] = T::from_sample((left_mix + right_mix) * 0.5);
-                        }
-                    }
-
-                    oscillators.retain(|osc| !osc.is_finished());
-                    drum_oscillators.retain(|osc| !osc.is_finished());
                 },
                 err_fn,
                 None,
@@ -303,129 +248,18 @@ impl AudioPlayerInternal {
         Ok(stream)
     }
 
-    fn set_track_notes(&mut self, track_id: usize, notes: Vec<f32>) -> Result<()> {
-        let mut state = self
-            .state
-            .lock()
-            .map_err(|e| anyhow!("Lock error: {}", e))?;
-        let track = state.tracks.entry(track_id).or_default();
-
-        // Check if we need to retrigger (notes are the same but new event)
-        // This handles sequences like [C5 C5] where the same note is played twice
-        let same_notes = track.notes.len() == notes.len()
-            && track
-                .notes
-                .iter()
-                .zip(notes.iter())
-                .all(|(a, b)| (a - b).abs() < 0.01);
-
-        if same_notes && !notes.is_empty() {
-            // Same notes - request retrigger
-            track.retrigger = true;
-        } else {
-            // Notes changed - reset retrigger flag so it can be set again next time
-            track.retrigger = false;
+    /// Apply a command to the shared state, starting the stream first for `Play`
+    fn handle(&mut self, command: AudioPlayerCommand) -> Result<()> {
+        if let AudioPlayerCommand::Play = command {
+            self.stream
+                .play()
+                .map_err(|e| anyhow!("Failed to play: {}", e))?;
         }
-
-        track.notes = notes;
-        Ok(())
-    }
-
-    /// Trigger notes with forced envelope attack (for scheduled playback)
-    /// Always sets retrigger=true to ensure new envelope attack
-    fn trigger_note(&mut self, track_id: usize, notes: Vec<f32>) -> Result<()> {
         let mut state = self
             .state
             .lock()
             .map_err(|e| anyhow!("Lock error: {}", e))?;
-        let track = state.tracks.entry(track_id).or_default();
-
-        // Always force retrigger for scheduled notes
-        track.retrigger = true;
-        track.notes = notes;
-        Ok(())
-    }
-
-    fn set_track_volume(&mut self, track_id: usize, volume: f32) -> Result<()> {
-        let mut state = self
-            .state
-            .lock()
-            .map_err(|e| anyhow!("Lock error: {}", e))?;
-        let track = state.tracks.entry(track_id).or_default();
-        track.volume = volume.clamp(0.0, 1.0);
-        Ok(())
-    }
-
-    fn set_track_envelope(
-        &mut self,
-        track_id: usize,
-        envelope: Option<(f32, f32, f32, f32)>,
-    ) -> Result<()> {
-        let mut state = self
-            .state
-            .lock()
-            .map_err(|e| anyhow!("Lock error: {}", e))?;
-        let track = state.tracks.entry(track_id).or_default();
-        track.envelope = envelope;
-        Ok(())
-    }
-
-    fn set_track_waveform(&mut self, track_id: usize, waveform: Waveform) -> Result<()> {
-        let mut state = self
-            .state
-            .lock()
-            .map_err(|e| anyhow!("Lock error: {}", e))?;
-        let track = state.tracks.entry(track_id).or_default();
-        track.waveform = waveform;
-        Ok(())
-    }
-
-    fn set_track_pan(&mut self, track_id: usize, pan: f32) -> Result<()> {
-        let mut state = self
-            .state
-            .lock()
-            .map_err(|e| anyhow!("Lock error: {}", e))?;
-        let track = state.tracks.entry(track_id).or_default();
-        track.pan = pan.clamp(0.0, 1.0);
-        Ok(())
-    }
-
-    fn play_drum(&mut self, track_id: usize, drum: DrumSound) -> Result<()> {
-        let mut state = self
-            .state
-            .lock()
-            .map_err(|e| anyhow!("Lock error: {}", e))?;
-        state.pending_drums.push((track_id, drum));
-        Ok(())
-    }
-
-    fn set_master_volume(&mut self, volume: f32) -> Result<()> {
-        let mut state = self
-            .state
-            .lock()
-            .map_err(|e| anyhow!("Lock error: {}", e))?;
-        state.volume = volume.clamp(0.0, 1.0);
-        Ok(())
-    }
-
-    fn play(&mut self) -> Result<()> {
-        self.stream
-            .play()
-            .map_err(|e| anyhow!("Failed to play: {}", e))?;
-        let mut state = self
-            .state
-            .lock()
-            .map_err(|e| anyhow!("Lock error: {}", e))?;
-        state.is_playing = true;
-        Ok(())
-    }
-
-    fn pause(&mut self) -> Result<()> {
-        let mut state = self
-            .state
-            .lock()
-            .map_err(|e| anyhow!("Lock error: {}", e))?;
-        state.is_playing = false;
+        state.apply(command);
         Ok(())
     }
 }
@@ -434,7 +268,8 @@ impl AudioPlayerInternal {
 /// Uses internal channels to communicate with the audio thread
 pub struct AudioPlayerHandle {
     command_tx: Sender<AudioPlayerCommand>,
-    _thread: JoinHandle<()>,
+    /// Audio thread owning the stream (none for a headless handle)
+    _thread: Option<JoinHandle<()>>,
 }
 
 impl AudioPlayerHandle {
@@ -455,68 +290,32 @@ impl AudioPlayerHandle {
 
             // Process commands until quit
             while let Ok(cmd) = rx.recv() {
-                match cmd {
-                    AudioPlayerCommand::SetTrackNotes(track_id, notes) => {
-                        if let Err(e) = player.set_track_notes(track_id, notes) {
-                            eprintln!("Failed to set track notes: {}", e);
-                        }
-                    }
-                    AudioPlayerCommand::TriggerNote(track_id, notes) => {
-                        if let Err(e) = player.trigger_note(track_id, notes) {
-                            eprintln!("Failed to trigger note: {}", e);
-                        }
-                    }
-                    AudioPlayerCommand::SetTrackVolume(track_id, vol) => {
-                        if let Err(e) = player.set_track_volume(track_id, vol) {
-                            eprintln!("Failed to set track volume: {}", e);
-                        }
-                    }
-                    AudioPlayerCommand::SetTrackEnvelope(track_id, envelope) => {
-                        if let Err(e) = player.set_track_envelope(track_id, envelope) {
-                            eprintln!("Failed to set track envelope: {}", e);
-                        }
-                    }
-                    AudioPlayerCommand::SetTrackWaveform(track_id, waveform) => {
-                        if let Err(e) = player.set_track_waveform(track_id, waveform) {
-                            eprintln!("Failed to set track waveform: {}", e);
-                        }
-                    }
-                    AudioPlayerCommand::SetTrackPan(track_id, pan) => {
-                        if let Err(e) = player.set_track_pan(track_id, pan) {
-                            eprintln!("Failed to set track pan: {}", e);
-                        }
-                    }
-                    AudioPlayerCommand::PlayDrum(track_id, drum) => {
-                        if let Err(e) = player.play_drum(track_id, drum) {
-                            eprintln!("Failed to play drum: {}", e);
-                        }
-                    }
-                    AudioPlayerCommand::SetMasterVolume(vol) => {
-                        if let Err(e) = player.set_master_volume(vol) {
-                            eprintln!("Failed to set master volume: {}", e);
-                        }
-                    }
-                    AudioPlayerCommand::Play => {
-                        if let Err(e) = player.play() {
-                            eprintln!("Failed to play: {}", e);
-                        }
-                    }
-                    AudioPlayerCommand::Pause => {
-                        if let Err(e) = player.pause() {
-                            eprintln!("Failed to pause: {}", e);
-                        }
-                    }
-                    AudioPlayerCommand::Quit => break,
+                if let AudioPlayerCommand::Quit = cmd {
+                    break;
+                }
+                if let Err(e) = player.handle(cmd) {
+                    eprintln!("Audio command failed: {}", e);
                 }
             }
         });
 
         Ok(AudioPlayerHandle {
             command_tx: tx,
-            _thread: thread,
+            _thread: Some(thread),
         })
     }
 
+    /// Create a handle with no sound card behind it. Commands go to the
+    /// returned [`HeadlessAudio`], which mixes frames when asked to.
+    pub fn headless(sample_rate: f32) -> (Self, HeadlessAudio) {
+        let (tx, rx) = channel();
+        let handle = AudioPlayerHandle {
+            command_tx: tx,
+            _thread: None,
+        };
+        (handle, HeadlessAudio::new(rx, sample_rate))
+    }
+
     /// Set the frequencies to play for a specific track
     pub fn set_track_notes(&self, track_id: usize, notes: Vec<f32>) -> Result<()> {
         self.command_tx
@@ -532,6 +331,21 @@ impl AudioPlayerHandle {
             .map_err(|e| anyhow!("Failed to send command: {}", e))
     }
 
+    /// Trigger notes with forced envelope attack, each panned on its own
+    /// (`None` follows the track's pan)
+    pub fn trigger_panned_notes(
+        &self,
+        track_id: usize,
+        notes: Vec<f32>,
+        pans: Vec<Option<f32>>,
+    ) -> Result<()> {
+        self.command_tx
+            .send(AudioPlayerCommand::TriggerPannedNotes(
+                track_id, notes, pans,
+            ))
+            .map_err(|e| anyhow!("Failed to send command: {}", e))
+    }
+
     /// Set the frequencies to play (default track 1)
     pub fn set_notes(&self, notes: Vec<f32>) -> Result<()> {
         self.set_track_notes(1, notes)
@@ -569,6 +383,13 @@ impl AudioPlayerHandle {
             .map_err(|e| anyhow!("Failed to send command: {}", e))
     }
 
+    /// Set or clear the feedback delay for a specific track
+    pub fn set_track_delay(&self, track_id: usize, delay: Option<DelaySettings>) -> Result<()> {
+        self.command_tx
+            .send(AudioPlayerCommand::SetTrackDelay(track_id, delay))
+            .map_err(|e| anyhow!("Failed to send command: {}", e))
+    }
+
     /// Trigger a drum sound on a specific track
     pub fn play_drum(&self, track_id: usize, drum: DrumSound) -> Result<()> {
         self.command_tx
@@ -576,6 +397,13 @@ impl AudioPlayerHandle {
             .map_err(|e| anyhow!("Failed to send command: {}", e))
     }
 
+    /// Hard-reset the engine: stop every track and drop all sounding voices
+    pub fn reset_voices(&self) -> Result<()> {
+        self.command_tx
+            .send(AudioPlayerCommand::ResetVoices)
+            .map_err(|e| anyhow!("Failed to send command: {}", e))
+    }
+
     /// Set master volume
     pub fn set_master_volume(&self, volume: f32) -> Result<()> {
         self.command_tx
@@ -613,13 +441,13 @@ impl Drop for AudioPlayerHandle {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::audio::oscillator::EnvelopedOscillator;
 
     #[test]
     fn test_audio_player_handle_creation() {
         match AudioPlayerHandle::new() {
             Ok(_handle) => {
                 // Successfully created
-                assert!(true);
             }
             Err(_) => {
                 println!("AudioPlayer creation failed - no audio device available");
@@ -627,6 +455,23 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_reset_voices_clears_tracks() {
+        let mut state = AudioState::default();
+        state.tracks.entry(1).or_default().notes = vec![440.0, 554.37];
+        state.tracks.entry(2).or_default().retrigger = true;
+        state.pending_drums.push((3, DrumSound::Kick));
+
+        state.reset_voices();
+
+        assert!(state
+            .tracks
+            .values()
+            .all(|t| t.notes.is_empty() && !t.retrigger));
+        assert!(state.pending_drums.is_empty());
+        assert!(state.reset_pending);
+    }
+
     #[test]
     fn test_oscillator_generation() {
         let sample_rate = 44100.0;
@@ -635,7 +480,7 @@ mod tests {
         for _ in 0..1000 {
             let value = osc.next_sample();
             assert!(
-                value >= -1.0 && value <= 1.0,
+                (-1.0..=1.0).contains(&value),
                 "Oscillator value {} out of expected range",
                 value
             );
@@ -23,6 +23,8 @@ pub struct ClockTick {
     pub beat_number: u64,
     /// Tick within current beat (0-23 for 24 PPQN)
     pub tick_in_beat: u8,
+    /// Tempo when this tick was generated
+    pub bpm: f32,
     /// The instant this tick was generated (for precise timing)
     pub timestamp: Instant,
 }
@@ -35,7 +37,7 @@ impl ClockTick {
 
     /// Returns true if this tick is on a bar boundary (beat 0, 4, 8, 12... in 4/4 time)
     pub fn is_bar_boundary(&self) -> bool {
-        self.tick_in_beat == 0 && self.beat_number % 4 == 0
+        self.tick_in_beat == 0 && self.beat_number.is_multiple_of(4)
     }
 
     /// Returns true if this tick is on a subdivision boundary.
@@ -51,7 +53,7 @@ impl ClockTick {
         if ticks_per_subdivision == 0 {
             return true; // subdivision finer than our resolution, treat every tick as a boundary
         }
-        self.tick_in_beat % ticks_per_subdivision == 0
+        self.tick_in_beat.is_multiple_of(ticks_per_subdivision)
     }
 
     /// Returns true if this tick is on a half-beat (8th note) boundary
@@ -233,13 +235,10 @@ impl ClockThread {
             // Check for commands (non-blocking when running, blocking when stopped)
             if self.running.load(Ordering::Relaxed) {
                 // Non-blocking check for commands while running
-                match self.command_rx.try_recv() {
-                    Ok(cmd) => {
-                        if self.handle_command(cmd) {
-                            break;
-                        }
+                if let Ok(cmd) = self.command_rx.try_recv() {
+                    if self.handle_command(cmd) {
+                        break;
                     }
-                    Err(_) => {} // No command, continue
                 }
 
                 // Generate tick if it's time
@@ -323,6 +322,7 @@ impl ClockThread {
             beat,
             beat_number: self.beat_number,
             tick_in_beat: self.tick_in_beat,
+            bpm: self.get_bpm(),
             timestamp: Instant::now(),
         };
         // Broadcast to all subscribers, removing disconnected ones
@@ -400,6 +400,7 @@ mod tests {
             beat: 4.0,
             beat_number: 4,
             tick_in_beat: 0,
+            bpm: 120.0,
             timestamp: Instant::now(),
         };
         assert!(tick_on_beat.is_beat_boundary());
@@ -408,6 +409,7 @@ mod tests {
             beat: 4.5,
             beat_number: 4,
             tick_in_beat: 12,
+            bpm: 120.0,
             timestamp: Instant::now(),
         };
         assert!(!tick_off_beat.is_beat_boundary());
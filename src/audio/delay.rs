@@ -0,0 +1,184 @@
+//! Feedback delay for the internal synth
+//!
+//! A track whose pattern carries a `delay()` gets its own stereo
+//! [`DelayLine`]. The mixer sends the track's panned voices into the line
+//! instead of straight to the output, and the line keeps producing echoes
+//! after the voices have stopped, so delay tails ring out when a track stops.
+
+use crate::types::Delay;
+
+/// Longest delay time in seconds, which bounds the line's buffer
+const MAX_TIME: f32 = 4.0;
+
+/// Level below which a line with no input counts as silent
+const SILENCE: f32 = 0.0001;
+
+/// Delay settings resolved for the audio thread
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DelaySettings {
+    /// Time between echoes in seconds
+    pub time: f32,
+    /// Level of each echo relative to the one before (0.0 to 0.95)
+    pub feedback: f32,
+    /// Balance of echoes against the dry sound (0.0 = dry only, 1.0 = echoes only)
+    pub mix: f32,
+}
+
+impl DelaySettings {
+    /// Resolve a pattern's delay at the given tempo; beat times follow the tempo
+    pub fn from_delay(delay: &Delay, bpm: f32) -> Self {
+        DelaySettings {
+            time: delay.seconds(bpm).clamp(0.0, MAX_TIME),
+            feedback: delay.feedback.min(Delay::MAX_FEEDBACK) as f32 / 100.0,
+            mix: delay.mix.min(100) as f32 / 100.0,
+        }
+    }
+}
+
+/// Stereo feedback delay line for one track
+pub struct DelayLine {
+    settings: DelaySettings,
+    sample_rate: f32,
+    left: Vec<f32>,
+    right: Vec<f32>,
+    position: usize,
+    /// Input sent by the track's voices for the current frame
+    input: (f32, f32),
+    /// Frames in a row with no input and no audible echo
+    silent_frames: usize,
+}
+
+impl DelayLine {
+    pub fn new(settings: DelaySettings, sample_rate: f32) -> Self {
+        let mut line = DelayLine {
+            settings,
+            sample_rate,
+            left: Vec::new(),
+            right: Vec::new(),
+            position: 0,
+            input: (0.0, 0.0),
+            silent_frames: 0,
+        };
+        line.set_settings(settings);
+        line
+    }
+
+    /// Change the settings. A new time resizes the buffer, keeping the echoes
+    /// that still fit.
+    pub fn set_settings(&mut self, settings: DelaySettings) {
+        let frames =
+            ((settings.time.clamp(0.0, MAX_TIME) * self.sample_rate).round() as usize).max(1);
+        if frames != self.left.len() {
+            self.left.resize(frames, 0.0);
+            self.right.resize(frames, 0.0);
+            self.position %= frames;
+        }
+        self.settings = DelaySettings {
+            feedback: settings
+                .feedback
+                .clamp(0.0, Delay::MAX_FEEDBACK as f32 / 100.0),
+            mix: settings.mix.clamp(0.0, 1.0),
+            ..settings
+        };
+    }
+
+    /// Add a voice's panned sample to this frame's input
+    pub fn send(&mut self, left: f32, right: f32) {
+        self.input.0 += left;
+        self.input.1 += right;
+    }
+
+    /// Output for this frame: the dry input blended with the echoes, by `mix`.
+    /// Consumes the input sent since the last frame.
+    pub fn next_frame(&mut self) -> (f32, f32) {
+        let (dry_left, dry_right) = std::mem::take(&mut self.input);
+        let (wet_left, wet_right) = (self.left[self.position], self.right[self.position]);
+
+        let feedback = self.settings.feedback;
+        self.left[self.position] = dry_left + wet_left * feedback;
+        self.right[self.position] = dry_right + wet_right * feedback;
+        self.position = (self.position + 1) % self.left.len();
+
+        let quiet = |sample: f32| sample.abs() < SILENCE;
+        if quiet(dry_left) && quiet(dry_right) && quiet(wet_left) && quiet(wet_right) {
+            self.silent_frames += 1;
+        } else {
+            self.silent_frames = 0;
+        }
+
+        let mix = self.settings.mix;
+        (
+            dry_left * (1.0 - mix) + wet_left * mix,
+            dry_right * (1.0 - mix) + wet_right * mix,
+        )
+    }
+
+    /// Whether the echoes have died away: nothing audible has come out for
+    /// a whole trip round the buffer
+    pub fn is_silent(&self) -> bool {
+        self.silent_frames >= self.left.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::DelayTime;
+
+    const SAMPLE_RATE: f32 = 1000.0;
+
+    fn settings(time: f32, feedback: f32, mix: f32) -> DelaySettings {
+        DelaySettings {
+            time,
+            feedback,
+            mix,
+        }
+    }
+
+    #[test]
+    fn test_mix_zero_is_dry() {
+        let mut line = DelayLine::new(settings(0.01, 0.5, 0.0), SAMPLE_RATE);
+        for i in 0..100 {
+            let dry = ((i as f32) * 0.3).sin();
+            line.send(dry, -dry);
+            assert_eq!(line.next_frame(), (dry, -dry));
+        }
+    }
+
+    #[test]
+    fn test_echoes_repeat_and_fade() {
+        // 10 frames between echoes, each half as loud as the one before
+        let mut line = DelayLine::new(settings(0.01, 0.5, 1.0), SAMPLE_RATE);
+        line.send(1.0, 1.0);
+        let output: Vec<f32> = (0..31).map(|_| line.next_frame().0).collect();
+
+        assert_eq!(output[0], 0.0);
+        assert_eq!(output[10], 1.0);
+        assert_eq!(output[20], 0.5);
+        assert_eq!(output[30], 0.25);
+        assert_eq!(output.iter().filter(|s| **s != 0.0).count(), 3);
+    }
+
+    #[test]
+    fn test_tail_dies_away() {
+        let mut line = DelayLine::new(settings(0.01, 0.5, 1.0), SAMPLE_RATE);
+        line.send(1.0, 1.0);
+        line.next_frame();
+        assert!(!line.is_silent());
+
+        for _ in 0..1000 {
+            line.next_frame();
+        }
+        assert!(line.is_silent());
+    }
+
+    #[test]
+    fn test_settings_from_delay() {
+        let synced = DelaySettings::from_delay(&Delay::new(DelayTime::Beats(0.5), 40, 30), 120.0);
+        assert_eq!(synced, settings(0.25, 0.4, 0.3));
+
+        // Feedback of 100% or more is clamped so echoes still fade
+        let fixed = DelaySettings::from_delay(&Delay::new(DelayTime::Millis(300), 150, 100), 90.0);
+        assert_eq!(fixed, settings(0.3, 0.95, 1.0));
+    }
+}
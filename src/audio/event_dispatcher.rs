@@ -10,32 +10,173 @@
 
 use crate::audio::audio::AudioPlayerHandle;
 use crate::audio::clock::ClockTick;
-use crate::audio::midi::{frequency_to_midi, MidiOutputHandle};
+use crate::audio::delay::DelaySettings;
+use crate::audio::midi::{frequency_to_midi_cents, MidiOutputHandle};
 use crate::parser::{EnvironmentRef, Evaluator, Expression, SharedEnvironment, Value};
-use crate::types::{DrumSound, QueueMode, Waveform};
+use crate::types::{
+    morph_amount, morph_events, to_f64, CompiledPattern, Delay, DrumSound, NoteInfo, Pattern,
+    PlaybackEvent, QueueMode, Waveform, DEFAULT_VELOCITY,
+};
 use cadence_core::types::{ScheduledAction, ScheduledEvent};
-use crossbeam_channel::{unbounded, Receiver, Sender};
+use crossbeam_channel::{bounded, unbounded, Receiver, Sender};
 use std::collections::{BinaryHeap, HashMap};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::thread;
+use std::time::Duration;
 
 /// Result from evaluating a pattern step - includes audio properties
 #[derive(Clone, Debug)]
 pub struct PlaybackStep {
     pub frequencies: Vec<f32>,
+    /// MIDI velocity (0-127) for each frequency
+    pub velocities: Vec<u8>,
+    /// Pan overriding the track's pan for each frequency (set by `jux()`);
+    /// empty when no note has its own pan
+    pub note_pans: Vec<Option<f32>>,
     pub drums: Vec<DrumSound>,
     pub envelope: Option<(f32, f32, f32, f32)>,
     pub waveform: Option<Waveform>,
     /// Stereo pan position (0.0 = left, 0.5 = center, 1.0 = right)
     pub pan: Option<f32>,
+    /// Feedback delay carried by the pattern
+    pub delay: Option<Delay>,
     /// Duration of this step in beats (for fast/slow support)
     pub duration_beats: f32,
 }
 
+impl PlaybackStep {
+    /// Shift sounding frequencies by a capo offset in semitones (drums are unaffected)
+    pub fn with_capo(mut self, semitones: i32) -> Self {
+        self.frequencies = capo_frequencies(&self.frequencies, semitones);
+        self
+    }
+}
+
+/// Transpose frequencies by `semitones` (0 returns them unchanged)
+fn capo_frequencies(frequencies: &[f32], semitones: i32) -> Vec<f32> {
+    if semitones == 0 {
+        return frequencies.to_vec();
+    }
+    let ratio = 2f32.powf(semitones as f32 / 12.0);
+    frequencies.iter().map(|f| f * ratio).collect()
+}
+
+/// Per-note pans of an event's notes, or none when no note carries its own
+fn note_pans(notes: &[NoteInfo]) -> Vec<Option<f32>> {
+    if notes.iter().any(|n| n.pan.is_some()) {
+        notes.iter().map(|n| n.pan).collect()
+    } else {
+        Vec::new()
+    }
+}
+
+/// Convert a latency in milliseconds to beats at the given tempo
+fn ms_to_beats(ms: i32, bpm: f32) -> f64 {
+    ms as f64 / 1000.0 * bpm as f64 / 60.0
+}
+
+/// What to send when a queued output comes due
+#[derive(Clone, Debug)]
+enum OutputAction {
+    /// Internal synth: envelope/waveform/pan updates, notes and drums
+    Audio(PlaybackStep),
+    /// MIDI note-ons. Looping steps first release the track's previous notes.
+    Midi {
+        step: PlaybackStep,
+        release_previous: bool,
+    },
+    /// A non-note scheduled action (volume, stop, tempo)
+    Scheduled(ScheduledAction),
+}
+
+#[derive(Clone, Debug)]
+struct PendingOutput {
+    due_beat: f64,
+    track_id: usize,
+    action: OutputAction,
+}
+
+/// Output held until its send time, so MIDI can lead or lag the internal
+/// synth to compensate for the latency of external gear
+#[derive(Debug, Default)]
+struct OutputQueue {
+    pending: Vec<PendingOutput>,
+}
+
+impl OutputQueue {
+    fn push(&mut self, due_beat: f64, track_id: usize, action: OutputAction) {
+        self.pending.push(PendingOutput {
+            due_beat,
+            track_id,
+            action,
+        });
+    }
+
+    /// Queue a step sounding at `onset`: audio at the onset, MIDI
+    /// `midi_latency` beats earlier (negative latency sends it later)
+    fn push_step(
+        &mut self,
+        track_id: usize,
+        step: PlaybackStep,
+        onset: f64,
+        midi_latency: f64,
+        release_previous: bool,
+    ) {
+        self.push(onset, track_id, OutputAction::Audio(step.clone()));
+        self.push(
+            onset - midi_latency,
+            track_id,
+            OutputAction::Midi {
+                step,
+                release_previous,
+            },
+        );
+    }
+
+    /// Remove and return everything due at `beat`, earliest first
+    fn take_due(&mut self, beat: f64) -> Vec<PendingOutput> {
+        let (mut due, later): (Vec<_>, Vec<_>) = self
+            .pending
+            .drain(..)
+            .partition(|output| output.due_beat <= beat + 1e-9);
+        self.pending = later;
+        due.sort_by(|a, b| a.due_beat.total_cmp(&b.due_beat));
+        due
+    }
+
+    fn clear_track(&mut self, track_id: usize) {
+        self.pending.retain(|output| output.track_id != track_id);
+    }
+
+    fn clear(&mut self) {
+        self.pending.clear();
+    }
+}
+
 /// Unique identifier for a looping pattern
 pub type PatternId = u64;
 
+/// Snapshot of a track's playback state, reported by [`DispatcherHandle::track_status`]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TrackStatus {
+    pub track_id: usize,
+    /// A looping pattern is playing (otherwise only one-shot events are scheduled)
+    pub looping: bool,
+    /// A looping pattern is waiting for its queue boundary
+    pub queued: bool,
+    /// Last volume set on the track
+    pub volume: Option<f32>,
+    /// Last waveform set on the track or carried by its pattern
+    pub waveform: Option<Waveform>,
+    /// Last pan carried by the track's pattern
+    pub pan: Option<f32>,
+    /// Last ADSR envelope set on the track or carried by its pattern
+    pub envelope: Option<(f32, f32, f32, f32)>,
+    /// Beats until the next sounding step or scheduled event
+    pub next_event_in: Option<f64>,
+}
+
 /// Configuration for a looping pattern (TidalCycles-style cycle tracking)
 #[derive(Clone, Debug)]
 pub struct LoopingPattern {
@@ -50,11 +191,71 @@ pub struct LoopingPattern {
     /// Last step index we triggered (to detect transitions)
     pub last_triggered_step: Option<usize>,
     /// Cached pattern data: (total_steps, beats_per_cycle, envelope, waveform)
+    #[allow(clippy::type_complexity)]
     pub cached_pattern_info: Option<(usize, f32, Option<(f32, f32, f32, f32)>, Option<Waveform>)>,
     /// Current cycle count (for EveryPattern alternation)
     pub current_cycle: usize,
     /// Cached beats per cycle for the current pattern (for Cycle queue mode)
     pub last_known_beats_per_cycle: f32,
+    /// Compiled form of the last evaluated pattern (recompiled on fingerprint change)
+    pub compiled: Option<CompiledPattern>,
+    /// Cycles to morph over when the pattern changes (0 or 1 switches at once)
+    pub morph_cycles: usize,
+    /// Blend from the previous version of the pattern, while one is running
+    pub morph: Option<Morph>,
+    /// The expression's value as last evaluated, reused until its inputs change
+    evaluated: Option<Evaluated>,
+}
+
+/// What a looping pattern's expression was evaluated against: the
+/// environment's generation and the playback variables the loop set
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct EvalInputs {
+    generation: u64,
+    beat: i32,
+    cycle: Option<i32>,
+}
+
+/// A looping pattern's expression as last evaluated
+#[derive(Clone, Debug)]
+struct Evaluated {
+    inputs: EvalInputs,
+    /// The value, with a pattern string already parsed into a pattern
+    value: Value,
+    /// Fingerprint of the value when it's a pattern, computed once per evaluation
+    fingerprint: u64,
+}
+
+impl Evaluated {
+    fn new(inputs: EvalInputs, value: Value) -> Result<Self, anyhow::Error> {
+        let value = match value {
+            Value::String(s) => Value::Pattern(
+                crate::types::Pattern::parse(&s)
+                    .map_err(|_| anyhow::anyhow!("Cannot play string"))?,
+            ),
+            value => value,
+        };
+        let fingerprint = match &value {
+            Value::Pattern(pattern) => pattern.fingerprint(),
+            _ => 0,
+        };
+        Ok(Evaluated {
+            inputs,
+            value,
+            fingerprint,
+        })
+    }
+}
+
+/// A blend from the previous version of a looping pattern to the current one
+#[derive(Clone, Debug)]
+pub struct Morph {
+    /// The pattern as it was before the change
+    pub from: CompiledPattern,
+    /// Cycle the morph started in
+    pub start_cycle: usize,
+    /// Blended events, cached for the cycle they were built for
+    blended: Option<(usize, Vec<PlaybackEvent>)>,
 }
 
 impl LoopingPattern {
@@ -73,6 +274,10 @@ impl LoopingPattern {
             cached_pattern_info: None,
             current_cycle: 0,
             last_known_beats_per_cycle: 0.0,
+            compiled: None,
+            morph_cycles: 0,
+            morph: None,
+            evaluated: None,
         }
     }
 
@@ -82,21 +287,53 @@ impl LoopingPattern {
         &mut self,
         current_beat: f64,
     ) -> Result<Option<PlaybackStep>, anyhow::Error> {
-        let evaluator = Evaluator::new();
+        let cycle = (self.last_known_beats_per_cycle > 0.0).then(|| {
+            ((current_beat - self.start_beat).max(0.0) / self.last_known_beats_per_cycle as f64)
+                .floor() as i32
+        });
 
         // Inject _beat into environment for beat() function
-        {
+        let inputs = {
             let mut env_write = self.env.write().map_err(|e| anyhow::anyhow!("{}", e))?;
             env_write.define("_beat".to_string(), Value::Number(current_beat as i32));
-        }
+            // Inject _track so random choices differ between tracks
+            env_write.define("_track".to_string(), Value::Number(self.track_id as i32));
+            // Inject _cycle for cycle-reactive combinators like scan()
+            if let Some(cycle) = cycle {
+                env_write.define("_cycle".to_string(), Value::Number(cycle));
+            }
+            EvalInputs {
+                generation: env_write.generation(),
+                beat: current_beat as i32,
+                cycle,
+            }
+        };
 
-        let env_guard = self.env.read().map_err(|e| anyhow::anyhow!("{}", e))?;
-        let value = evaluator.eval_with_env(
-            self.expression.clone(),
-            Some(EnvironmentRef::Borrowed(&env_guard)),
-        )?;
+        // The expression can only evaluate differently when a variable or
+        // the beat or cycle it sees changed, so reuse the last value until then
+        let evaluated = match self.evaluated.take() {
+            Some(evaluated) if evaluated.inputs == inputs => evaluated,
+            _ => {
+                let env_guard = self.env.read().map_err(|e| anyhow::anyhow!("{}", e))?;
+                let value = Evaluator::new().eval_with_env(
+                    self.expression.clone(),
+                    Some(EnvironmentRef::Borrowed(&env_guard)),
+                )?;
+                Evaluated::new(inputs, value)?
+            }
+        };
+        let step = self.step_value(&evaluated, current_beat);
+        self.evaluated = Some(evaluated);
+        step
+    }
 
-        match value {
+    /// Step an evaluated expression at `current_beat`
+    fn step_value(
+        &mut self,
+        evaluated: &Evaluated,
+        current_beat: f64,
+    ) -> Result<Option<PlaybackStep>, anyhow::Error> {
+        match &evaluated.value {
             Value::Note(note) => {
                 // Single note: trigger once per beat
                 let beats_elapsed = current_beat - self.start_beat;
@@ -106,10 +343,13 @@ impl LoopingPattern {
                     self.last_triggered_step = Some(current_step);
                     Ok(Some(PlaybackStep {
                         frequencies: vec![note.frequency()],
+                        velocities: vec![DEFAULT_VELOCITY],
+                        note_pans: vec![],
                         drums: vec![],
                         envelope: None,
                         waveform: None,
                         pan: None,
+                        delay: None,
                         duration_beats: 1.0,
                     }))
                 } else {
@@ -125,10 +365,13 @@ impl LoopingPattern {
                     self.last_triggered_step = Some(current_step);
                     Ok(Some(PlaybackStep {
                         frequencies: chord.notes_vec().iter().map(|n| n.frequency()).collect(),
+                        velocities: vec![DEFAULT_VELOCITY; chord.len()],
+                        note_pans: vec![],
                         drums: vec![],
                         envelope: None,
                         waveform: None,
                         pan: None,
+                        delay: None,
                         duration_beats: 1.0,
                     }))
                 } else {
@@ -136,157 +379,295 @@ impl LoopingPattern {
                 }
             }
             Value::Pattern(pattern) => {
-                let events = pattern.to_rich_events();
-                let beats_per_cycle = pattern.beats_per_cycle_f32();
-                self.last_known_beats_per_cycle = beats_per_cycle;
-
-                // Calculate position within the cycle
-                let beats_elapsed = (current_beat - self.start_beat) as f32;
-                let cycle_position = beats_elapsed % beats_per_cycle;
-
-                // Find which step we're currently in
-                let mut accumulated = 0.0f32;
-                let mut current_step = 0;
-                for (i, event) in events.iter().enumerate() {
-                    let event_dur = event.duration_f32();
-                    if cycle_position >= accumulated && cycle_position < accumulated + event_dur {
-                        current_step = i;
-                        break;
-                    }
-                    accumulated += event_dur;
-                    // If we've gone past all events, we're in the last one
-                    if i == events.len() - 1 {
-                        current_step = i;
-                    }
-                }
+                Ok(self.step_pattern(pattern, evaluated.fingerprint, current_beat))
+            }
+            Value::EveryPattern(every) => {
+                Ok(self.step_cycle_variant(&every.base, current_beat, |cycle| {
+                    every.get_pattern_for_cycle(cycle)
+                }))
+            }
+            Value::ProbabilityPattern(sometimes) => Ok(self.step_cycle_variant(
+                &sometimes.base,
+                current_beat,
+                |cycle| sometimes.get_pattern_for_cycle(cycle),
+            )),
+            _ => Err(anyhow::anyhow!("Cannot play this type")),
+        }
+    }
 
-                // Only trigger if this is a new step
-                if self.last_triggered_step != Some(current_step) {
-                    self.last_triggered_step = Some(current_step);
+    /// Step a combinator that picks one of its pre-computed patterns per
+    /// cycle (`every`, `sometimes`), timing cycles by the base pattern
+    fn step_cycle_variant<'a>(
+        &mut self,
+        base: &Pattern,
+        current_beat: f64,
+        pattern_for_cycle: impl Fn(usize) -> &'a Pattern,
+    ) -> Option<PlaybackStep> {
+        // Get beats_per_cycle from the base pattern (both should have same duration)
+        let beats_per_cycle = base.beats_per_cycle_f32();
+        self.last_known_beats_per_cycle = beats_per_cycle;
+
+        // Calculate position within the cycle FIRST
+        let beats_elapsed = (current_beat - self.start_beat) as f32;
+        let cycle_position = beats_elapsed % beats_per_cycle;
 
-                    if current_step < events.len() {
-                        let event = &events[current_step];
-                        Ok(Some(PlaybackStep {
-                            frequencies: event.notes.iter().map(|n| n.frequency).collect(),
-                            drums: event.drums.clone(),
-                            envelope: pattern.envelope,
-                            waveform: pattern.waveform,
-                            pan: pattern.pan,
-                            duration_beats: event.duration_f32(),
-                        }))
-                    } else {
-                        Ok(None)
-                    }
-                } else {
-                    Ok(None) // Same step, don't re-trigger
-                }
-            }
-            Value::String(s) => {
-                if let Ok(pattern) = crate::types::Pattern::parse(&s) {
-                    let events = pattern.to_rich_events();
-                    let beats_per_cycle = pattern.beats_per_cycle_f32();
-                    self.last_known_beats_per_cycle = beats_per_cycle;
-
-                    let beats_elapsed = (current_beat - self.start_beat) as f32;
-                    let cycle_position = beats_elapsed % beats_per_cycle;
-
-                    let mut accumulated = 0.0f32;
-                    let mut current_step = 0;
-                    for (i, event) in events.iter().enumerate() {
-                        let event_dur = event.duration_f32();
-                        if cycle_position >= accumulated && cycle_position < accumulated + event_dur
-                        {
-                            current_step = i;
-                            break;
-                        }
-                        accumulated += event_dur;
-                        if i == events.len() - 1 {
-                            current_step = i;
-                        }
-                    }
+        // Calculate current cycle number BEFORE selecting pattern
+        let new_cycle = (beats_elapsed / beats_per_cycle).floor() as usize;
 
-                    if self.last_triggered_step != Some(current_step) {
-                        self.last_triggered_step = Some(current_step);
-
-                        if current_step < events.len() {
-                            let event = &events[current_step];
-                            Ok(Some(PlaybackStep {
-                                frequencies: event.notes.iter().map(|n| n.frequency).collect(),
-                                drums: event.drums.clone(),
-                                envelope: pattern.envelope,
-                                waveform: pattern.waveform,
-                                pan: pattern.pan,
-                                duration_beats: event.duration_f32(),
-                            }))
-                        } else {
-                            Ok(None)
-                        }
-                    } else {
-                        Ok(None)
-                    }
-                } else {
-                    Err(anyhow::anyhow!("Cannot play string"))
-                }
+        // Track cycle transitions - reset step tracking when cycle changes
+        if new_cycle > self.current_cycle {
+            self.current_cycle = new_cycle;
+            self.last_triggered_step = None; // Reset to trigger first step of new cycle
+        }
+
+        // NOW select the appropriate pattern based on updated cycle
+        let pattern = pattern_for_cycle(self.current_cycle);
+        let events = pattern.to_rich_events();
+
+        // Find which step we're currently in
+        let mut accumulated = 0.0f32;
+        let mut current_step = 0;
+        for (i, event) in events.iter().enumerate() {
+            let event_dur = event.duration_f32();
+            if cycle_position >= accumulated && cycle_position < accumulated + event_dur {
+                current_step = i;
+                break;
             }
-            Value::EveryPattern(every) => {
-                // Get beats_per_cycle from the base pattern (both should have same duration)
-                let beats_per_cycle = every.base.beats_per_cycle_f32();
-                self.last_known_beats_per_cycle = beats_per_cycle;
+            accumulated += event_dur;
+            if i == events.len() - 1 {
+                current_step = i;
+            }
+        }
 
-                // Calculate position within the cycle FIRST
-                let beats_elapsed = (current_beat - self.start_beat) as f32;
-                let cycle_position = beats_elapsed % beats_per_cycle;
+        // Only trigger if this is a new step
+        if self.last_triggered_step != Some(current_step) {
+            self.last_triggered_step = Some(current_step);
+
+            if current_step < events.len() {
+                let event = &events[current_step];
+                Some(PlaybackStep {
+                    frequencies: event.notes.iter().map(|n| n.frequency).collect(),
+                    velocities: event.notes.iter().map(|n| n.velocity).collect(),
+                    note_pans: note_pans(&event.notes),
+                    drums: event.drums.clone(),
+                    envelope: pattern.envelope,
+                    waveform: pattern.waveform,
+                    pan: pattern.pan,
+                    delay: pattern.delay,
+                    duration_beats: event.duration_f32(),
+                })
+            } else {
+                None
+            }
+        } else {
+            None
+        }
+    }
 
-                // Calculate current cycle number BEFORE selecting pattern
-                let new_cycle = (beats_elapsed / beats_per_cycle).floor() as usize;
+    /// Step a pattern through its compiled form, recompiling only when the
+    /// evaluated pattern's fingerprint differs from the cached one
+    fn step_pattern(
+        &mut self,
+        pattern: &Pattern,
+        fingerprint: u64,
+        current_beat: f64,
+    ) -> Option<PlaybackStep> {
+        let beats_per_cycle = pattern.beats_per_cycle_f32();
+        self.last_known_beats_per_cycle = beats_per_cycle;
+        if beats_per_cycle <= 0.0 {
+            return None;
+        }
 
-                // Track cycle transitions - reset step tracking when cycle changes
-                if new_cycle > self.current_cycle {
-                    self.current_cycle = new_cycle;
-                    self.last_triggered_step = None; // Reset to trigger first step of new cycle
-                }
+        let beats_elapsed = (current_beat - self.start_beat).max(0.0);
+        let cycle = (beats_elapsed / beats_per_cycle as f64).floor() as usize;
+        let cycle_position = beats_elapsed - cycle as f64 * beats_per_cycle as f64;
+
+        if self.compiled.as_ref().map(|c| c.fingerprint) != Some(fingerprint) {
+            let previous = self.compiled.take();
+            self.compiled = CompiledPattern::compile(pattern);
+            // Morphing needs both versions compiled; otherwise switch at once
+            self.morph = match previous {
+                Some(from) if self.morph_cycles > 1 && self.compiled.is_some() => Some(Morph {
+                    from,
+                    start_cycle: cycle,
+                    blended: None,
+                }),
+                _ => None,
+            };
+        }
 
-                // NOW select the appropriate pattern based on updated cycle
-                let pattern = every.get_pattern_for_cycle(self.current_cycle);
-                let events = pattern.to_rich_events();
-
-                // Find which step we're currently in
-                let mut accumulated = 0.0f32;
-                let mut current_step = 0;
-                for (i, event) in events.iter().enumerate() {
-                    let event_dur = event.duration_f32();
-                    if cycle_position >= accumulated && cycle_position < accumulated + event_dur {
-                        current_step = i;
-                        break;
-                    }
-                    accumulated += event_dur;
-                    if i == events.len() - 1 {
-                        current_step = i;
-                    }
-                }
+        // Reset step tracking on cycle change so single-event cycles retrigger
+        if cycle != self.current_cycle {
+            self.current_cycle = cycle;
+            self.last_triggered_step = None;
+        }
 
-                // Only trigger if this is a new step
-                if self.last_triggered_step != Some(current_step) {
-                    self.last_triggered_step = Some(current_step);
+        if self
+            .morph
+            .as_ref()
+            .is_some_and(|m| cycle >= m.start_cycle + self.morph_cycles - 1)
+        {
+            self.morph = None;
+        }
 
-                    if current_step < events.len() {
-                        let event = &events[current_step];
-                        Ok(Some(PlaybackStep {
-                            frequencies: event.notes.iter().map(|n| n.frequency).collect(),
-                            drums: event.drums.clone(),
-                            envelope: pattern.envelope,
-                            waveform: pattern.waveform,
-                            pan: pattern.pan,
-                            duration_beats: event.duration_f32(),
-                        }))
-                    } else {
-                        Ok(None)
-                    }
-                } else {
-                    Ok(None)
+        // Patterns with very long alternation periods aren't compiled
+        let uncompiled;
+        let events = match (&self.compiled, &mut self.morph) {
+            (Some(compiled), Some(morph)) => {
+                if morph.blended.as_ref().map(|(c, _)| *c) != Some(cycle) {
+                    let amount = morph_amount(cycle - morph.start_cycle, self.morph_cycles);
+                    let blended = morph_events(
+                        morph.from.events_for_cycle(cycle),
+                        morph.from.beats_per_cycle,
+                        compiled.events_for_cycle(cycle),
+                        compiled.beats_per_cycle,
+                        amount,
+                    );
+                    morph.blended = Some((cycle, blended));
                 }
+                morph
+                    .blended
+                    .as_ref()
+                    .map_or(&[][..], |(_, events)| &events[..])
             }
-            _ => Err(anyhow::anyhow!("Cannot play this type")),
+            (Some(compiled), None) => compiled.events_for_cycle(cycle),
+            (None, _) => {
+                uncompiled = pattern.to_rich_events_for_cycle(cycle);
+                &uncompiled[..]
+            }
+        };
+
+        let current_step = events
+            .partition_point(|e| e.start_beat_f32() as f64 <= cycle_position)
+            .checked_sub(1)?;
+
+        // Only trigger if this is a new step
+        if self.last_triggered_step == Some(current_step) {
+            return None;
+        }
+        let event = &events[current_step];
+        let step = PlaybackStep {
+            frequencies: event.notes.iter().map(|n| n.frequency).collect(),
+            velocities: event.notes.iter().map(|n| n.velocity).collect(),
+            note_pans: note_pans(&event.notes),
+            drums: event.drums.clone(),
+            envelope: pattern.envelope,
+            waveform: pattern.waveform,
+            pan: pattern.pan,
+            delay: pattern.delay,
+            duration_beats: event.duration_f32(),
+        };
+        self.last_triggered_step = Some(current_step);
+        Some(step)
+    }
+
+    /// Beats from `current_beat` until the next sounding step starts.
+    ///
+    /// Uses the last compiled pattern; patterns that could not be compiled
+    /// report the next cycle boundary, and single notes or chords the next beat.
+    pub fn beats_until_next_step(&self, current_beat: f64) -> Option<f64> {
+        let elapsed = (current_beat - self.start_beat).max(0.0);
+
+        let Some(compiled) = &self.compiled else {
+            let period = match self.last_known_beats_per_cycle as f64 {
+                bpc if bpc > 0.0 => bpc,
+                _ => 1.0,
+            };
+            return Some(period - elapsed % period);
+        };
+
+        let beats_per_cycle = to_f64(compiled.beats_per_cycle);
+        if beats_per_cycle <= 0.0 {
+            return None;
+        }
+        let cycle = (elapsed / beats_per_cycle).floor() as usize;
+        let position = elapsed - cycle as f64 * beats_per_cycle;
+
+        // Search through one full variation period for a step that isn't a rest
+        (0..=compiled.period()).find_map(|ahead| {
+            let offset = ahead as f64 * beats_per_cycle;
+            compiled
+                .events_for_cycle(cycle + ahead)
+                .iter()
+                .filter(|e| !e.is_rest)
+                .map(|e| offset + to_f64(e.start_beat) - position)
+                .find(|&beats| beats > 1e-9)
+        })
+    }
+}
+
+/// A MIDI CC automation lane driven by a numeric control pattern
+#[derive(Clone, Debug)]
+pub struct ControlLane {
+    /// Expression to evaluate each tick (for reactive updates)
+    pub expression: Expression,
+    /// Environment for evaluation
+    pub env: SharedEnvironment,
+    /// Track ID (selects the MIDI channel)
+    pub track_id: usize,
+    /// MIDI controller number (0-127)
+    pub controller: u8,
+    /// Interpolate between steps instead of sending once per step
+    pub smooth: bool,
+    /// Beat when this lane started
+    pub start_beat: f64,
+    /// Last step index sampled (for per-step sending)
+    pub last_step: Option<usize>,
+    /// Last value sent (to avoid flooding with duplicates when smoothing)
+    pub last_value: Option<u8>,
+}
+
+impl ControlLane {
+    pub fn new(
+        expression: Expression,
+        env: SharedEnvironment,
+        track_id: usize,
+        controller: u8,
+        smooth: bool,
+        start_beat: f64,
+    ) -> Self {
+        Self {
+            expression,
+            env,
+            track_id,
+            controller,
+            smooth,
+            start_beat,
+            last_step: None,
+            last_value: None,
+        }
+    }
+
+    /// Sample the control pattern at the given beat.
+    /// Returns a value only when a CC message should be sent.
+    pub fn value_at_beat(&mut self, current_beat: f64) -> Result<Option<u8>, anyhow::Error> {
+        let evaluator = Evaluator::new();
+        let env_guard = self.env.read().map_err(|e| anyhow::anyhow!("{}", e))?;
+        let value = evaluator.eval_with_env(
+            self.expression.clone(),
+            Some(EnvironmentRef::Borrowed(&env_guard)),
+        )?;
+        let control = value
+            .to_control_pattern()
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+
+        let Some((step, sampled)) = control.sample(current_beat - self.start_beat, self.smooth)
+        else {
+            return Ok(None);
+        };
+
+        let should_send = if self.smooth {
+            self.last_value != Some(sampled)
+        } else {
+            self.last_step != Some(step)
+        };
+        self.last_step = Some(step);
+
+        if should_send {
+            self.last_value = Some(sampled);
+            Ok(Some(sampled))
+        } else {
+            Ok(None)
         }
     }
 }
@@ -324,6 +705,12 @@ pub enum DispatcherCommand {
     StopTrack(usize),
     /// Stop all playback
     StopAll,
+    /// Put every track's volume, waveform, pan and envelope back to the defaults
+    ResetTrackProperties,
+    /// Set the global capo in semitones
+    SetCapo(i32),
+    /// Set how many cycles a changed looping pattern takes to morph into its new version
+    SetMorph(usize),
     /// Set track volume
     SetTrackVolume(usize, f32),
     /// Set track waveform
@@ -336,6 +723,15 @@ pub enum DispatcherCommand {
         frequencies: Vec<f32>,
         drums: Vec<DrumSound>,
     },
+    /// Start a MIDI CC automation lane (replaces any lane for the same track/controller)
+    StartControl {
+        id: PatternId,
+        expression: Expression,
+        env: SharedEnvironment,
+        track_id: usize,
+        controller: u8,
+        smooth: bool,
+    },
     /// Queue a looping pattern to start at next musical boundary
     QueueLoop {
         id: PatternId,
@@ -344,6 +740,8 @@ pub enum DispatcherCommand {
         track_id: usize,
         queue_mode: QueueMode,
     },
+    /// Report the state of every track with something playing or scheduled
+    QueryStatus(Sender<Vec<TrackStatus>>),
     /// Shutdown
     Shutdown,
 }
@@ -401,6 +799,27 @@ impl DispatcherHandle {
         id
     }
 
+    /// Start a MIDI CC automation lane, returns its ID
+    pub fn start_control(
+        &self,
+        expression: Expression,
+        env: SharedEnvironment,
+        track_id: usize,
+        controller: u8,
+        smooth: bool,
+    ) -> PatternId {
+        let id = self.next_pattern_id.fetch_add(1, Ordering::Relaxed);
+        let _ = self.command_tx.send(DispatcherCommand::StartControl {
+            id,
+            expression,
+            env,
+            track_id,
+            controller,
+            smooth,
+        });
+        id
+    }
+
     /// Stop a specific looping pattern
     pub fn stop_loop(&self, id: PatternId) {
         let _ = self.command_tx.send(DispatcherCommand::StopLoop(id));
@@ -416,6 +835,13 @@ impl DispatcherHandle {
         let _ = self.command_tx.send(DispatcherCommand::StopAll);
     }
 
+    /// Put every track's volume, waveform, pan and envelope back to the defaults
+    pub fn reset_track_properties(&self) {
+        let _ = self
+            .command_tx
+            .send(DispatcherCommand::ResetTrackProperties);
+    }
+
     /// Trigger a note immediately (for simple one-shot plays)
     pub fn trigger_immediate(&self, track_id: usize, frequencies: Vec<f32>, drums: Vec<DrumSound>) {
         let _ = self.command_tx.send(DispatcherCommand::TriggerImmediate {
@@ -425,6 +851,17 @@ impl DispatcherHandle {
         });
     }
 
+    /// Set the global capo (semitones added to all sounding notes)
+    pub fn set_capo(&self, semitones: i32) {
+        let _ = self.command_tx.send(DispatcherCommand::SetCapo(semitones));
+    }
+
+    /// Morph changed looping patterns into their new version over `cycles`
+    /// cycles (0 or 1 switches at once)
+    pub fn set_morph(&self, cycles: usize) {
+        let _ = self.command_tx.send(DispatcherCommand::SetMorph(cycles));
+    }
+
     /// Set track volume
     pub fn set_track_volume(&self, track_id: usize, volume: f32) {
         let _ = self
@@ -446,6 +883,22 @@ impl DispatcherHandle {
             .send(DispatcherCommand::SetTrackEnvelope(track_id, envelope));
     }
 
+    /// State of every track that is playing or has events scheduled, sorted by track.
+    /// Returns an empty list if the dispatcher doesn't answer promptly.
+    pub fn track_status(&self) -> Vec<TrackStatus> {
+        let (reply_tx, reply_rx) = bounded(1);
+        if self
+            .command_tx
+            .send(DispatcherCommand::QueryStatus(reply_tx))
+            .is_err()
+        {
+            return Vec::new();
+        }
+        reply_rx
+            .recv_timeout(Duration::from_millis(200))
+            .unwrap_or_default()
+    }
+
     /// Shutdown the dispatcher
     pub fn shutdown(&self) {
         let _ = self.command_tx.send(DispatcherCommand::Shutdown);
@@ -465,6 +918,8 @@ pub struct EventDispatcher {
     active_loops: HashMap<PatternId, LoopingPattern>,
     /// Patterns waiting to be activated at a musical boundary (track_id -> pending)
     pending_loops: HashMap<usize, PendingLoop>,
+    /// Active MIDI CC automation lanes
+    active_controls: HashMap<PatternId, ControlLane>,
     /// Audio handle
     audio_handle: Arc<AudioPlayerHandle>,
     /// Command receiver
@@ -482,6 +937,14 @@ pub struct EventDispatcher {
     /// Track active MIDI notes per track: track_id -> set of active note numbers
     /// Used to send note_off before note_on to prevent note stacking
     active_midi_notes: HashMap<usize, Vec<u8>>,
+    /// Capo offset in semitones applied to all sounding notes
+    capo: i32,
+    /// Cycles a changed looping pattern takes to morph into its new version
+    morph_cycles: usize,
+    /// Step output waiting for its send time (MIDI latency compensation)
+    output_queue: OutputQueue,
+    /// Last volume/waveform/pan/envelope per track, for status reports
+    track_properties: HashMap<usize, TrackStatus>,
 }
 
 impl EventDispatcher {
@@ -491,6 +954,23 @@ impl EventDispatcher {
         tick_rx: Receiver<ClockTick>,
         midi_handle: Option<Arc<MidiOutputHandle>>,
     ) -> DispatcherHandle {
+        let (dispatcher, handle) = Self::new(audio_handle, tick_rx, midi_handle);
+        thread::spawn(move || dispatcher.run_loop());
+        handle
+    }
+
+    /// Create a dispatcher that runs on the caller's thread, for offline
+    /// rendering. It follows no clock: call [`EventDispatcher::advance`] with
+    /// each tick. Output only goes to the internal synth.
+    pub fn headless(audio_handle: Arc<AudioPlayerHandle>) -> (Self, DispatcherHandle) {
+        Self::new(audio_handle, crossbeam_channel::never(), None)
+    }
+
+    fn new(
+        audio_handle: Arc<AudioPlayerHandle>,
+        tick_rx: Receiver<ClockTick>,
+        midi_handle: Option<Arc<MidiOutputHandle>>,
+    ) -> (Self, DispatcherHandle) {
         let (command_tx, command_rx) = unbounded();
         let is_running = Arc::new(AtomicBool::new(true));
         let is_running_clone = is_running.clone();
@@ -499,6 +979,7 @@ impl EventDispatcher {
             event_queue: BinaryHeap::new(),
             active_loops: HashMap::new(),
             pending_loops: HashMap::new(),
+            active_controls: HashMap::new(),
             audio_handle,
             command_rx,
             tick_rx,
@@ -507,15 +988,29 @@ impl EventDispatcher {
             is_running: is_running_clone,
             midi_handle,
             active_midi_notes: HashMap::new(),
+            capo: 0,
+            morph_cycles: 0,
+            output_queue: OutputQueue::default(),
+            track_properties: HashMap::new(),
         };
 
-        thread::spawn(move || dispatcher.run_loop());
-
-        DispatcherHandle {
+        let handle = DispatcherHandle {
             command_tx,
             next_pattern_id: Arc::new(std::sync::atomic::AtomicU64::new(1)),
             is_running,
+        };
+        (dispatcher, handle)
+    }
+
+    /// Handle the commands sent so far, then process `tick` (headless dispatchers)
+    pub fn advance(&mut self, tick: &ClockTick) {
+        while let Ok(cmd) = self.command_rx.try_recv() {
+            if !self.handle_command(cmd) {
+                self.is_running.store(false, Ordering::Relaxed);
+                return;
+            }
         }
+        self.process_tick(tick);
     }
 
     /// Main dispatcher loop
@@ -573,18 +1068,14 @@ impl EventDispatcher {
                 env,
                 track_id,
             } => {
-                // Stop any existing loops on this track first
-                self.active_loops.retain(|_, p| p.track_id != track_id);
-                // Also cancel any pending loops on this track
+                // Cancel any pending loops on this track
                 self.pending_loops.remove(&track_id);
-                // Start new loop at current beat position
-                self.active_loops.insert(
-                    id,
-                    LoopingPattern::new(expression, env, track_id, self.current_beat),
-                );
+                // Start new loop at current beat position, replacing the track's loop
+                self.replace_loop(id, expression, env, track_id, self.current_beat);
             }
             DispatcherCommand::StopLoop(id) => {
                 if let Some(pattern) = self.active_loops.remove(&id) {
+                    self.output_queue.clear_track(pattern.track_id);
                     // Clear the track's audio notes
                     let _ = self.audio_handle.set_track_notes(pattern.track_id, vec![]);
                     // Send MIDI note_off for any active notes on this track
@@ -600,6 +1091,7 @@ impl EventDispatcher {
             DispatcherCommand::StopTrack(track_id) => {
                 // Remove all loops on this track
                 self.active_loops.retain(|_, p| p.track_id != track_id);
+                self.active_controls.retain(|_, c| c.track_id != track_id);
                 // Remove any pending loops on this track
                 self.pending_loops.remove(&track_id);
                 // Clear scheduled events for this track
@@ -611,6 +1103,7 @@ impl EventDispatcher {
                 for event in remaining {
                     self.event_queue.push(event);
                 }
+                self.output_queue.clear_track(track_id);
                 // Clear the track's audio notes
                 let _ = self.audio_handle.set_track_notes(track_id, vec![]);
                 // Send MIDI note_off for any active notes on this track
@@ -626,6 +1119,8 @@ impl EventDispatcher {
                 self.active_loops.clear();
                 self.pending_loops.clear();
                 self.event_queue.clear();
+                self.active_controls.clear();
+                self.output_queue.clear();
                 // Send MIDI note_off for all active notes
                 if let Some(midi) = &self.midi_handle {
                     for (track_id, notes) in self.active_midi_notes.drain() {
@@ -639,13 +1134,39 @@ impl EventDispatcher {
                     let _ = self.audio_handle.set_track_notes(track_id, vec![]);
                 }
             }
+            DispatcherCommand::ResetTrackProperties => {
+                for track_id in std::mem::take(&mut self.track_properties).into_keys() {
+                    let _ = self.audio_handle.set_track_volume(track_id, 1.0);
+                    let _ = self
+                        .audio_handle
+                        .set_track_waveform(track_id, Waveform::default());
+                    let _ = self.audio_handle.set_track_pan(track_id, 0.5);
+                    let _ = self.audio_handle.set_track_envelope(track_id, None);
+                    let _ = self.audio_handle.set_track_delay(track_id, None);
+                }
+            }
+            DispatcherCommand::SetCapo(semitones) => {
+                self.capo = semitones;
+            }
+            DispatcherCommand::SetMorph(cycles) => {
+                self.morph_cycles = cycles;
+                for pattern in self.active_loops.values_mut() {
+                    pattern.morph_cycles = cycles;
+                    if cycles <= 1 {
+                        pattern.morph = None;
+                    }
+                }
+            }
             DispatcherCommand::SetTrackVolume(track_id, volume) => {
+                self.properties_mut(track_id).volume = Some(volume);
                 let _ = self.audio_handle.set_track_volume(track_id, volume);
             }
             DispatcherCommand::SetTrackWaveform(track_id, waveform) => {
+                self.properties_mut(track_id).waveform = Some(waveform);
                 let _ = self.audio_handle.set_track_waveform(track_id, waveform);
             }
             DispatcherCommand::SetTrackEnvelope(track_id, envelope) => {
+                self.properties_mut(track_id).envelope = envelope;
                 let _ = self.audio_handle.set_track_envelope(track_id, envelope);
             }
             DispatcherCommand::TriggerImmediate {
@@ -653,15 +1174,13 @@ impl EventDispatcher {
                 frequencies,
                 drums,
             } => {
+                let frequencies = capo_frequencies(&frequencies, self.capo);
                 // Check output mode - only play internal audio if enabled
-                let audio_enabled = self
-                    .midi_handle
-                    .as_ref()
-                    .map_or(true, |h| h.audio_enabled());
+                let audio_enabled = self.midi_handle.as_ref().is_none_or(|h| h.audio_enabled());
                 let midi_enabled = self
                     .midi_handle
                     .as_ref()
-                    .map_or(false, |h| h.midi_enabled() && h.is_connected());
+                    .is_some_and(|h| h.midi_enabled() && h.is_connected());
 
                 if audio_enabled {
                     // Trigger internal synth
@@ -680,12 +1199,35 @@ impl EventDispatcher {
                     // Send MIDI notes
                     if let Some(midi) = &self.midi_handle {
                         for freq in &frequencies {
-                            let midi_note = frequency_to_midi(*freq);
-                            let _ = midi.note_on(track_id, midi_note, 100);
+                            let (midi_note, cents) = frequency_to_midi_cents(*freq);
+                            let _ =
+                                midi.note_on_detuned(track_id, midi_note, cents, DEFAULT_VELOCITY);
                         }
                     }
                 }
             }
+            DispatcherCommand::StartControl {
+                id,
+                expression,
+                env,
+                track_id,
+                controller,
+                smooth,
+            } => {
+                self.active_controls
+                    .retain(|_, c| !(c.track_id == track_id && c.controller == controller));
+                self.active_controls.insert(
+                    id,
+                    ControlLane::new(
+                        expression,
+                        env,
+                        track_id,
+                        controller,
+                        smooth,
+                        self.current_beat,
+                    ),
+                );
+            }
             DispatcherCommand::QueueLoop {
                 id,
                 expression,
@@ -705,6 +1247,9 @@ impl EventDispatcher {
                     },
                 );
             }
+            DispatcherCommand::QueryStatus(reply) => {
+                let _ = reply.send(self.track_status());
+            }
             DispatcherCommand::Shutdown => {
                 return false;
             }
@@ -712,6 +1257,92 @@ impl EventDispatcher {
         true
     }
 
+    /// Start a loop in place of any loops on the same track.
+    ///
+    /// With morphing on, the new loop starts from the old loop's compiled
+    /// pattern, so a changed pattern blends in rather than jumping.
+    fn replace_loop(
+        &mut self,
+        id: PatternId,
+        expression: Expression,
+        env: SharedEnvironment,
+        track_id: usize,
+        start_beat: f64,
+    ) {
+        let mut previous = None;
+        self.active_loops.retain(|_, p| {
+            if p.track_id != track_id {
+                return true;
+            }
+            previous = p.compiled.take();
+            false
+        });
+
+        let mut pattern = LoopingPattern::new(expression, env, track_id, start_beat);
+        pattern.morph_cycles = self.morph_cycles;
+        if self.morph_cycles > 1 {
+            pattern.compiled = previous;
+        }
+        self.active_loops.insert(id, pattern);
+    }
+
+    /// Stored properties for a track, created on first use
+    fn properties_mut(&mut self, track_id: usize) -> &mut TrackStatus {
+        self.track_properties
+            .entry(track_id)
+            .or_insert_with(|| TrackStatus {
+                track_id,
+                ..TrackStatus::default()
+            })
+    }
+
+    /// Build a status entry for every track with a loop, a queued loop, or scheduled events
+    fn track_status(&self) -> Vec<TrackStatus> {
+        let track_ids = self
+            .active_loops
+            .values()
+            .map(|p| p.track_id)
+            .chain(self.pending_loops.keys().copied())
+            .chain(self.event_queue.iter().map(|e| e.track_id));
+
+        let mut statuses: HashMap<usize, TrackStatus> = HashMap::new();
+        for track_id in track_ids {
+            statuses.entry(track_id).or_insert_with(|| {
+                self.track_properties
+                    .get(&track_id)
+                    .cloned()
+                    .unwrap_or(TrackStatus {
+                        track_id,
+                        ..TrackStatus::default()
+                    })
+            });
+        }
+
+        for pattern in self.active_loops.values() {
+            if let Some(status) = statuses.get_mut(&pattern.track_id) {
+                status.looping = true;
+                status.next_event_in = pattern.beats_until_next_step(self.current_beat);
+            }
+        }
+        for track_id in self.pending_loops.keys() {
+            if let Some(status) = statuses.get_mut(track_id) {
+                status.queued = true;
+            }
+        }
+        for event in self.event_queue.iter() {
+            if let Some(status) = statuses.get_mut(&event.track_id) {
+                let beats = (event.scheduled_beat - self.current_beat).max(0.0);
+                if !status.looping && status.next_event_in.is_none_or(|next| beats < next) {
+                    status.next_event_in = Some(beats);
+                }
+            }
+        }
+
+        let mut statuses: Vec<TrackStatus> = statuses.into_values().collect();
+        statuses.sort_by_key(|s| s.track_id);
+        statuses
+    }
+
     /// Process a clock tick
     fn process_tick(&mut self, tick: &ClockTick) {
         self.current_beat = tick.beat;
@@ -723,11 +1354,14 @@ impl EventDispatcher {
             self.last_beat_floor = current_beat_floor;
         }
 
-        // 1. Dispatch any due one-shot events
+        // Look ahead far enough that MIDI for the most delayed track can be sent early
+        let lookahead = self.midi_lookahead_beats(tick.bpm);
+
+        // 1. Queue any one-shot events that are due within the lookahead
         while let Some(event) = self.event_queue.peek() {
-            if event.scheduled_beat <= tick.beat {
+            if event.scheduled_beat <= tick.beat + lookahead {
                 let event = self.event_queue.pop().unwrap();
-                self.dispatch_event(&event);
+                self.queue_event(event, tick.bpm);
             } else {
                 break;
             }
@@ -773,17 +1407,13 @@ impl EventDispatcher {
         // Activate the pending patterns
         for track_id in to_activate {
             if let Some(pending) = self.pending_loops.remove(&track_id) {
-                // Stop any existing loops on this track
-                self.active_loops.retain(|_, p| p.track_id != track_id);
-                // Start the new loop
-                self.active_loops.insert(
+                // Start at exactly this beat for precise timing
+                self.replace_loop(
                     pending.id,
-                    LoopingPattern::new(
-                        pending.expression,
-                        pending.env,
-                        track_id,
-                        tick.beat, // Start at exactly this beat for precise timing
-                    ),
+                    pending.expression,
+                    pending.env,
+                    track_id,
+                    tick.beat,
                 );
             }
         }
@@ -793,9 +1423,10 @@ impl EventDispatcher {
         // The pattern tracks which step was last triggered and only fires when
         // the cycle position crosses into a new step.
         let mut updates: Vec<(usize, PlaybackStep)> = Vec::new();
+        let step_beat = tick.beat + lookahead;
 
         for pattern in self.active_loops.values_mut() {
-            match pattern.get_step_at_beat(tick.beat) {
+            match pattern.get_step_at_beat(step_beat) {
                 Ok(Some(step)) => {
                     updates.push((pattern.track_id, step));
                 }
@@ -808,123 +1439,200 @@ impl EventDispatcher {
             }
         }
 
-        // Apply updates
-        for (track_id, step) in updates {
-            // Check output mode - only play internal audio if enabled
-            let audio_enabled = self
-                .midi_handle
-                .as_ref()
-                .map_or(true, |h| h.audio_enabled());
-            let midi_enabled = self
-                .midi_handle
-                .as_ref()
-                .map_or(false, |h| h.midi_enabled() && h.is_connected());
-
-            // Apply envelope if present (enables reactive envelope updates)
-            if let Some(envelope) = step.envelope {
-                let _ = self
-                    .audio_handle
-                    .set_track_envelope(track_id, Some(envelope));
-            }
-            // Apply waveform if present (enables reactive waveform updates)
-            if let Some(waveform) = step.waveform {
-                let _ = self.audio_handle.set_track_waveform(track_id, waveform);
-            }
-            // Apply pan if present (enables reactive pan updates)
-            if let Some(pan) = step.pan {
-                let _ = self.audio_handle.set_track_pan(track_id, pan);
-            }
-
-            if audio_enabled {
-                // Play internal synth
-                let _ = self.audio_handle.play();
-                if !step.frequencies.is_empty() {
-                    let _ = self
-                        .audio_handle
-                        .trigger_note(track_id, step.frequencies.clone());
-                }
-                for drum in &step.drums {
-                    let _ = self.audio_handle.play_drum(track_id, *drum);
+        // 3. Sample CC automation lanes (MIDI only)
+        let midi_ready = self
+            .midi_handle
+            .as_ref()
+            .is_some_and(|h| h.midi_enabled() && h.is_connected());
+        if midi_ready {
+            if let Some(midi) = &self.midi_handle {
+                for lane in self.active_controls.values_mut() {
+                    // Sample ahead (or behind) by the track's latency instead of queueing
+                    let latency = ms_to_beats(midi.latency_ms(lane.track_id), tick.bpm);
+                    match lane.value_at_beat(tick.beat + latency) {
+                        Ok(Some(value)) => {
+                            let _ = midi.cc(lane.track_id, lane.controller, value);
+                        }
+                        Ok(None) => {}
+                        Err(e) => eprintln!("CC automation error: {}", e),
+                    }
                 }
             }
+        }
 
-            if midi_enabled {
-                // Send note_off for previous notes on this track, then note_on for new notes
-                if let Some(midi) = &self.midi_handle {
-                    // First, send note_off for any previously active notes on this track
-                    if let Some(prev_notes) = self.active_midi_notes.get(&track_id) {
-                        for &note in prev_notes {
-                            let _ = midi.note_off(track_id, note);
-                        }
-                    }
+        // Queue step output: audio at the step onset, MIDI offset by the track's latency
+        for (track_id, step) in updates {
+            let properties = self.properties_mut(track_id);
+            properties.envelope = step.envelope.or(properties.envelope);
+            properties.waveform = step.waveform.or(properties.waveform);
+            properties.pan = step.pan.or(properties.pan);
+
+            let step = step.with_capo(self.capo);
+            let latency = self.midi_latency_beats(track_id, tick.bpm);
+            self.output_queue
+                .push_step(track_id, step, step_beat, latency, true);
+        }
 
-                    // Convert new frequencies to MIDI notes
-                    let new_notes: Vec<u8> = step
-                        .frequencies
-                        .iter()
-                        .map(|f| frequency_to_midi(*f))
-                        .collect();
+        // 4. Send everything that has come due
+        for output in self.output_queue.take_due(tick.beat) {
+            self.send_output(output, tick.bpm);
+        }
+    }
 
-                    // Send note_on for new notes
-                    for &note in &new_notes {
-                        let _ = midi.note_on(track_id, note, 100);
-                    }
+    /// MIDI latency compensation for a track in beats (0 when MIDI is not in use)
+    fn midi_latency_beats(&self, track_id: usize, bpm: f32) -> f64 {
+        match &self.midi_handle {
+            Some(midi) if midi.midi_enabled() && midi.is_connected() => {
+                ms_to_beats(midi.latency_ms(track_id), bpm)
+            }
+            _ => 0.0,
+        }
+    }
 
-                    // Store the new active notes
-                    self.active_midi_notes.insert(track_id, new_notes);
-                }
+    /// How far ahead patterns are evaluated so early MIDI can be sent on time
+    fn midi_lookahead_beats(&self, bpm: f32) -> f64 {
+        match &self.midi_handle {
+            Some(midi) if midi.midi_enabled() && midi.is_connected() => {
+                ms_to_beats(midi.max_latency_ms().max(0), bpm)
             }
+            _ => 0.0,
         }
     }
 
-    /// Dispatch a one-shot scheduled event
-    fn dispatch_event(&self, event: &ScheduledEvent) {
-        match &event.action {
+    /// Queue a one-shot scheduled event at its scheduled beat
+    fn queue_event(&mut self, event: ScheduledEvent, bpm: f32) {
+        match event.action {
             ScheduledAction::PlayNotes {
-                frequencies, drums, ..
+                frequencies,
+                duration_beats,
+                drums,
+                velocities,
             } => {
-                // Check output mode
-                let audio_enabled = self
-                    .midi_handle
-                    .as_ref()
-                    .map_or(true, |h| h.audio_enabled());
-                let midi_enabled = self
-                    .midi_handle
-                    .as_ref()
-                    .map_or(false, |h| h.midi_enabled() && h.is_connected());
+                let step = PlaybackStep {
+                    frequencies: capo_frequencies(&frequencies, self.capo),
+                    velocities,
+                    note_pans: vec![],
+                    drums,
+                    envelope: None,
+                    waveform: None,
+                    pan: None,
+                    delay: None,
+                    duration_beats,
+                };
+                let latency = self.midi_latency_beats(event.track_id, bpm);
+                self.output_queue.push_step(
+                    event.track_id,
+                    step,
+                    event.scheduled_beat,
+                    latency,
+                    false,
+                );
+            }
+            action => self.output_queue.push(
+                event.scheduled_beat,
+                event.track_id,
+                OutputAction::Scheduled(action),
+            ),
+        }
+    }
+
+    /// Send a queued output to the internal synth or MIDI, per the output mode.
+    /// Delay times in beats are resolved at `bpm`.
+    fn send_output(&mut self, output: PendingOutput, bpm: f32) {
+        let track_id = output.track_id;
+        let audio_enabled = self.midi_handle.as_ref().is_none_or(|h| h.audio_enabled());
+        let midi_enabled = self
+            .midi_handle
+            .as_ref()
+            .is_some_and(|h| h.midi_enabled() && h.is_connected());
+
+        match output.action {
+            OutputAction::Audio(step) => {
+                // Apply envelope if present (enables reactive envelope updates)
+                if let Some(envelope) = step.envelope {
+                    let _ = self
+                        .audio_handle
+                        .set_track_envelope(track_id, Some(envelope));
+                }
+                // Apply waveform if present (enables reactive waveform updates)
+                if let Some(waveform) = step.waveform {
+                    let _ = self.audio_handle.set_track_waveform(track_id, waveform);
+                }
+                // Apply pan if present (enables reactive pan updates)
+                if let Some(pan) = step.pan {
+                    let _ = self.audio_handle.set_track_pan(track_id, pan);
+                }
+                // Apply delay if present (enables reactive delay updates)
+                if let Some(delay) = &step.delay {
+                    let settings = DelaySettings::from_delay(delay, bpm);
+                    let _ = self.audio_handle.set_track_delay(track_id, Some(settings));
+                }
 
+                // Only play internal audio if enabled
                 if audio_enabled {
                     let _ = self.audio_handle.play();
-                    if !frequencies.is_empty() {
-                        let _ = self
-                            .audio_handle
-                            .trigger_note(event.track_id, frequencies.clone());
+                    if !step.frequencies.is_empty() {
+                        let _ = if step.note_pans.is_empty() {
+                            self.audio_handle.trigger_note(track_id, step.frequencies)
+                        } else {
+                            self.audio_handle.trigger_panned_notes(
+                                track_id,
+                                step.frequencies,
+                                step.note_pans,
+                            )
+                        };
                     }
-                    for drum in drums {
-                        if let Err(e) = self.audio_handle.play_drum(event.track_id, *drum) {
+                    for drum in &step.drums {
+                        if let Err(e) = self.audio_handle.play_drum(track_id, *drum) {
                             eprintln!("Drum error: {}", e);
                         }
                     }
                 }
-
-                if midi_enabled {
-                    if let Some(midi) = &self.midi_handle {
-                        for freq in frequencies {
-                            let midi_note = frequency_to_midi(*freq);
-                            let _ = midi.note_on(event.track_id, midi_note, 100);
+            }
+            OutputAction::Midi {
+                step,
+                release_previous,
+            } => {
+                if !midi_enabled {
+                    return;
+                }
+                if let Some(midi) = &self.midi_handle {
+                    // Send note_off for the previous step's notes to prevent note stacking
+                    if release_previous {
+                        if let Some(prev_notes) = self.active_midi_notes.get(&track_id) {
+                            for &note in prev_notes {
+                                let _ = midi.note_off(track_id, note);
+                            }
                         }
                     }
+
+                    // Send note_on for new notes at their pattern velocities,
+                    // bending any that fall between semitones
+                    let mut new_notes = Vec::with_capacity(step.frequencies.len());
+                    for (freq, &velocity) in step.frequencies.iter().zip(&step.velocities) {
+                        let (note, cents) = frequency_to_midi_cents(*freq);
+                        let _ = midi.note_on_detuned(track_id, note, cents, velocity);
+                        new_notes.push(note);
+                    }
+
+                    // Store the new active notes
+                    if release_previous {
+                        self.active_midi_notes.insert(track_id, new_notes);
+                    }
                 }
             }
-            ScheduledAction::SetTempo(_bpm) => {
-                // TODO: Send tempo change to clock
-            }
-            ScheduledAction::SetVolume(volume) => {
-                let _ = self.audio_handle.set_track_volume(event.track_id, *volume);
-            }
-            ScheduledAction::Stop => {
-                let _ = self.audio_handle.set_track_notes(event.track_id, vec![]);
-            }
+            OutputAction::Scheduled(action) => match action {
+                ScheduledAction::SetTempo(_bpm) => {
+                    // TODO: Send tempo change to clock
+                }
+                ScheduledAction::SetVolume(volume) => {
+                    let _ = self.audio_handle.set_track_volume(track_id, volume);
+                }
+                ScheduledAction::Stop => {
+                    let _ = self.audio_handle.set_track_notes(track_id, vec![]);
+                }
+                ScheduledAction::PlayNotes { .. } => {}
+            },
         }
     }
 }
@@ -1117,4 +1825,357 @@ mod tests {
         let cycle_position = beats_elapsed % beats_per_cycle;
         assert!(cycle_position > 0.1, "At beat 1.8, should be mid-cycle");
     }
+
+    /// Looping patterns step through the compiled form, following alternations
+    #[test]
+    fn test_looping_pattern_steps_compiled_alternation() {
+        use crate::parser::Environment;
+        use std::sync::RwLock;
+
+        let env = Arc::new(RwLock::new(Environment::new()));
+        let mut looping =
+            LoopingPattern::new(Expression::String("<C D> E".to_string()), env, 1, 0.0);
+
+        let mut triggered = Vec::new();
+        for i in 0..16 {
+            let beat = i as f64 * 0.5;
+            if let Some(step) = looping.get_step_at_beat(beat).unwrap() {
+                triggered.push((beat, step.frequencies[0].round() as u32));
+            }
+        }
+        // One trigger per event: C4@0, E4@2, D4@4, E4@6
+        assert_eq!(
+            triggered,
+            vec![(0.0, 262), (2.0, 330), (4.0, 294), (6.0, 330)]
+        );
+
+        let expected = Pattern::parse("<C D> E").unwrap().fingerprint();
+        assert_eq!(looping.compiled.as_ref().unwrap().fingerprint, expected);
+    }
+
+    /// The expression is evaluated again only when a variable, the beat or
+    /// the cycle changed, and a changed variable still reaches playback
+    #[test]
+    fn test_looping_pattern_reevaluates_only_on_change() {
+        use crate::parser::Environment;
+        use std::sync::RwLock;
+
+        let env = Arc::new(RwLock::new(Environment::new()));
+        env.write()
+            .unwrap()
+            .define("riff".to_string(), Value::String("C E".to_string()));
+        let mut looping = LoopingPattern::new(
+            Expression::Variable("riff".to_string()),
+            env.clone(),
+            1,
+            0.0,
+        );
+
+        let inputs = |looping: &LoopingPattern| looping.evaluated.as_ref().unwrap().inputs;
+        // The cycle is known once the first evaluation gave the cycle length
+        looping.get_step_at_beat(0.0).unwrap();
+        looping.get_step_at_beat(0.25).unwrap();
+        let first = inputs(&looping);
+        assert_eq!(first.cycle, Some(0));
+        looping.get_step_at_beat(0.5).unwrap();
+        looping.get_step_at_beat(0.75).unwrap();
+        assert_eq!(inputs(&looping), first);
+
+        looping.get_step_at_beat(1.0).unwrap();
+        assert_eq!(inputs(&looping).beat, 1);
+        assert_eq!(inputs(&looping).generation, first.generation);
+
+        env.write()
+            .unwrap()
+            .set("riff", Value::String("D F".to_string()))
+            .unwrap();
+        let step = looping.get_step_at_beat(2.0).unwrap().unwrap();
+        assert!(inputs(&looping).generation > first.generation);
+        assert_eq!(step.frequencies[0].round() as u32, 349);
+        let expected = Pattern::parse("D F").unwrap().fingerprint();
+        assert_eq!(looping.compiled.as_ref().unwrap().fingerprint, expected);
+    }
+
+    /// Per-step velocities reach the MIDI path instead of a fixed value
+    #[test]
+    fn test_looping_pattern_steps_carry_velocities() {
+        use crate::parser::Environment;
+        use std::sync::RwLock;
+
+        let env = Arc::new(RwLock::new(Environment::new()));
+        let mut looping = LoopingPattern::new(
+            Expression::String("C(127) C C(127) [C, E](0.25)".to_string()),
+            env,
+            1,
+            0.0,
+        );
+
+        let velocities: Vec<Vec<u8>> = (0..4)
+            .filter_map(|beat| looping.get_step_at_beat(beat as f64).unwrap())
+            .map(|step| step.velocities)
+            .collect();
+        assert_eq!(
+            velocities,
+            vec![vec![127], vec![100], vec![127], vec![32, 32]]
+        );
+    }
+
+    /// The same degraded pattern on two tracks drops different steps
+    #[test]
+    fn test_looping_degrade_differs_per_track() {
+        use crate::parser::Environment;
+        use std::sync::RwLock;
+
+        let env = Arc::new(RwLock::new(Environment::new()));
+        let expression = crate::parser::parse("degrade(\"C D E F G A B C5\")").unwrap();
+        let mut first = LoopingPattern::new(expression.clone(), env.clone(), 1, 0.0);
+        let mut second = LoopingPattern::new(expression, env, 2, 0.0);
+
+        // Beats where each track sounds during the first cycle
+        let sounding = |looping: &mut LoopingPattern| -> Vec<u32> {
+            (0..8)
+                .filter(|&i| {
+                    looping
+                        .get_step_at_beat(i as f64 * 0.5)
+                        .unwrap()
+                        .is_some_and(|step| !step.frequencies.is_empty())
+                })
+                .collect()
+        };
+        let (first, second) = (sounding(&mut first), sounding(&mut second));
+        assert!(first.len() < 8 && second.len() < 8);
+        assert_ne!(first, second);
+    }
+
+    /// A redefined pattern blends into its new version over the morph cycles
+    #[test]
+    fn test_looping_pattern_morphs_between_versions() {
+        use crate::parser::Environment;
+        use std::sync::RwLock;
+
+        let env = Arc::new(RwLock::new(Environment::new()));
+        let define = |notation: &str| {
+            env.write()
+                .unwrap()
+                .define("riff".to_string(), Value::String(notation.to_string()));
+        };
+        define("C E G B");
+        let mut looping = LoopingPattern::new(
+            Expression::Variable("riff".to_string()),
+            env.clone(),
+            1,
+            0.0,
+        );
+        looping.morph_cycles = 2;
+
+        // MIDI notes triggered over one 4-beat cycle
+        let mut play_cycle = |cycle: usize| -> Vec<u8> {
+            (0..8)
+                .filter_map(|i| {
+                    let beat = (cycle * 4) as f64 + i as f64 * 0.5;
+                    looping.get_step_at_beat(beat).unwrap()
+                })
+                .map(|step| frequency_to_midi_cents(step.frequencies[0]).0)
+                .collect()
+        };
+
+        assert_eq!(play_cycle(0), vec![60, 64, 67, 71]);
+        define("E G B D5");
+        // Halfway: every note has moved half the distance to its new pitch
+        assert_eq!(play_cycle(1), vec![62, 66, 69, 73]);
+        assert_eq!(play_cycle(2), vec![64, 67, 71, 74]);
+        assert!(looping.morph.is_none());
+    }
+
+    /// A capo set through the dispatcher shifts what looping and immediate
+    /// notes sound, but leaves the written pattern alone
+    #[test]
+    fn test_capo_transposes_dispatched_frequencies() {
+        use crate::audio::audio::AudioPlayerHandle;
+        use crate::audio::clock::ClockTick;
+        use crate::parser::Environment;
+        use crate::types::Note;
+        use std::sync::RwLock;
+        use std::time::Instant;
+
+        let (audio, mut output) = AudioPlayerHandle::headless(44_100.0);
+        let (mut dispatcher, handle) = EventDispatcher::headless(Arc::new(audio));
+        let tick = |beat: f64| ClockTick {
+            beat,
+            beat_number: beat as u64,
+            tick_in_beat: 0,
+            bpm: 120.0,
+            timestamp: Instant::now(),
+        };
+        let env = Arc::new(RwLock::new(Environment::new()));
+        env.write()
+            .unwrap()
+            .define("riff".to_string(), Value::String("C E G B".to_string()));
+        let mut sounding = |dispatcher: &mut EventDispatcher, beat: f64, track_id: usize| {
+            dispatcher.advance(&tick(beat));
+            output.render(0);
+            output.state().tracks[&track_id].notes.clone()
+        };
+        let frequency = |name: &str| name.parse::<Note>().unwrap().frequency();
+        let close = |a: &[f32], b: &[f32]| {
+            a.len() == b.len() && a.iter().zip(b).all(|(x, y)| (x - y).abs() < 0.01)
+        };
+
+        handle.start_loop(Expression::Variable("riff".to_string()), env.clone(), 1);
+        handle.set_capo(2);
+        assert!(close(
+            &sounding(&mut dispatcher, 0.0, 1),
+            &[frequency("D4")]
+        ));
+
+        // Immediate notes take the capo too
+        handle.trigger_immediate(2, vec![frequency("C4")], vec![]);
+        assert!(close(
+            &sounding(&mut dispatcher, 0.5, 2),
+            &[frequency("D4")]
+        ));
+
+        handle.set_capo(-12);
+        assert!(close(
+            &sounding(&mut dispatcher, 1.0, 1),
+            &[frequency("E3")]
+        ));
+        handle.set_capo(0);
+        assert!(close(
+            &sounding(&mut dispatcher, 2.0, 1),
+            &[frequency("G4")]
+        ));
+
+        // The written notes are untouched
+        let written = Pattern::parse("C E G B").unwrap();
+        let looping = dispatcher.active_loops.values().next().unwrap();
+        assert_eq!(
+            looping.compiled.as_ref().unwrap().fingerprint,
+            written.fingerprint()
+        );
+        assert_eq!(
+            env.read().unwrap().get("riff"),
+            Some(&Value::String("C E G B".to_string()))
+        );
+    }
+
+    /// Simulate clock ticks over a CC lane and collect (beat, value) sends
+    fn run_lane(notation: &str, smooth: bool, ticks: &[f64]) -> Vec<(f64, u8)> {
+        use crate::parser::Environment;
+        use std::sync::RwLock;
+
+        let env = Arc::new(RwLock::new(Environment::new()));
+        let mut lane = ControlLane::new(
+            Expression::String(notation.to_string()),
+            env,
+            1,
+            74,
+            smooth,
+            0.0,
+        );
+        ticks
+            .iter()
+            .filter_map(|&beat| lane.value_at_beat(beat).unwrap().map(|v| (beat, v)))
+            .collect()
+    }
+
+    /// CC lanes send once per step, on the step boundary
+    #[test]
+    fn test_control_lane_per_step() {
+        let ticks: Vec<f64> = (0..16).map(|i| i as f64 * 0.5).collect();
+        let sent = run_lane("0 64 127 ~", false, &ticks);
+        assert_eq!(
+            sent,
+            vec![
+                (0.0, 0),
+                (1.0, 64),
+                (2.0, 127),
+                (3.0, 127),
+                (4.0, 0),
+                (5.0, 64),
+                (6.0, 127),
+                (7.0, 127),
+            ]
+        );
+    }
+
+    /// Smooth lanes interpolate between steps and skip duplicate values
+    #[test]
+    fn test_control_lane_smooth() {
+        let ticks: Vec<f64> = (0..5).map(|i| i as f64 * 0.5).collect();
+        let sent = run_lane("0 100", true, &ticks);
+        assert_eq!(
+            sent,
+            vec![(0.0, 0), (0.5, 25), (1.0, 50), (1.5, 75), (2.0, 100)]
+        );
+
+        // A constant lane only sends once
+        let sent = run_lane("90 90", true, &ticks);
+        assert_eq!(sent, vec![(0.0, 90)]);
+    }
+
+    /// Note patterns are rejected by CC lanes
+    #[test]
+    fn test_control_lane_rejects_notes() {
+        use crate::parser::Environment;
+        use std::sync::RwLock;
+
+        let env = Arc::new(RwLock::new(Environment::new()));
+        let mut lane = ControlLane::new(
+            Expression::String("C E G".to_string()),
+            env,
+            1,
+            74,
+            false,
+            0.0,
+        );
+        assert!(lane.value_at_beat(0.0).is_err());
+    }
+
+    /// With 20ms of latency at 120 BPM, MIDI for a step goes out 0.04 beats
+    /// before the internal synth plays it
+    #[test]
+    fn test_midi_latency_leads_internal_audio() {
+        let step = PlaybackStep {
+            frequencies: vec![261.63],
+            velocities: vec![DEFAULT_VELOCITY],
+            note_pans: vec![],
+            drums: vec![],
+            envelope: None,
+            waveform: None,
+            pan: None,
+            delay: None,
+            duration_beats: 1.0,
+        };
+        let send_beats = |latency_ms: i32| {
+            let mut queue = OutputQueue::default();
+            queue.push_step(1, step.clone(), 4.0, ms_to_beats(latency_ms, 120.0), true);
+
+            let mut sent = Vec::new();
+            let mut beat = 3.9;
+            while beat < 4.1 {
+                for output in queue.take_due(beat) {
+                    let kind = match output.action {
+                        OutputAction::Audio(_) => "audio",
+                        OutputAction::Midi { .. } => "midi",
+                        OutputAction::Scheduled(_) => "other",
+                    };
+                    sent.push((kind, (output.due_beat * 100.0).round() / 100.0));
+                }
+                beat += 0.01;
+            }
+            sent
+        };
+
+        assert_eq!(send_beats(20), vec![("midi", 3.96), ("audio", 4.0)]);
+        assert_eq!(send_beats(-20), vec![("audio", 4.0), ("midi", 4.04)]);
+        assert_eq!(send_beats(0), vec![("audio", 4.0), ("midi", 4.0)]);
+
+        // Stopping a track drops its queued output
+        let mut queue = OutputQueue::default();
+        queue.push_step(1, step.clone(), 4.0, 0.04, true);
+        queue.push_step(2, step.clone(), 4.0, 0.04, true);
+        queue.clear_track(1);
+        assert!(queue.take_due(5.0).iter().all(|o| o.track_id == 2));
+    }
 }
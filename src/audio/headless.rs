@@ -0,0 +1,155 @@
+//! Headless audio backend for offline rendering
+//!
+//! A [`HeadlessAudio`] takes the commands an [`AudioPlayerHandle`] sends, like
+//! the live audio thread does, but mixes frames only when asked and keeps them
+//! instead of playing them. Driven tick by tick, it renders a session as fast
+//! as it can be computed.
+//!
+//! [`AudioPlayerHandle`]: super::audio::AudioPlayerHandle
+
+use anyhow::{anyhow, Result};
+use std::path::Path;
+use std::sync::mpsc::Receiver;
+
+use super::audio::{AudioPlayerCommand, AudioState};
+use super::mixer::Mixer;
+
+/// Sample rate of rendered audio
+pub const SAMPLE_RATE: u32 = 44_100;
+
+/// Rendered audio is stereo
+pub const CHANNELS: usize = 2;
+
+/// Offline output: applies audio commands and collects mixed samples
+pub struct HeadlessAudio {
+    commands: Receiver<AudioPlayerCommand>,
+    state: AudioState,
+    mixer: Mixer,
+    sample_rate: f32,
+    /// Interleaved stereo samples rendered so far
+    samples: Vec<f32>,
+}
+
+impl HeadlessAudio {
+    pub(crate) fn new(commands: Receiver<AudioPlayerCommand>, sample_rate: f32) -> Self {
+        HeadlessAudio {
+            commands,
+            state: AudioState::default(),
+            mixer: Mixer::new(sample_rate),
+            sample_rate,
+            samples: Vec::new(),
+        }
+    }
+
+    /// Apply the commands sent so far, then mix `frames` more frames
+    pub fn render(&mut self, frames: usize) {
+        while let Ok(command) = self.commands.try_recv() {
+            self.state.apply(command);
+        }
+        let start = self.samples.len();
+        self.samples.resize(start + frames * CHANNELS, 0.0);
+        self.mixer
+            .fill(&mut self.state, &mut self.samples[start..], CHANNELS);
+    }
+
+    /// Frames rendered so far
+    pub fn frames(&self) -> usize {
+        self.samples.len() / CHANNELS
+    }
+
+    /// Interleaved stereo samples rendered so far
+    pub fn samples(&self) -> &[f32] {
+        &self.samples
+    }
+
+    /// Track and master state as of the last render
+    pub fn state(&self) -> &AudioState {
+        &self.state
+    }
+
+    /// Write everything rendered so far as a 16-bit stereo WAV file
+    pub fn write_wav(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let bytes = wav_bytes(&self.samples, self.sample_rate as u32, CHANNELS as u16);
+        std::fs::write(path, bytes)
+            .map_err(|e| anyhow!("Failed to write '{}': {}", path.display(), e))
+    }
+}
+
+/// Encode interleaved samples in -1.0..=1.0 as a 16-bit PCM WAV file
+pub fn wav_bytes(samples: &[f32], sample_rate: u32, channels: u16) -> Vec<u8> {
+    let data_len = (samples.len() * 2) as u32;
+    let block_align = channels * 2;
+
+    let mut bytes = Vec::with_capacity(44 + samples.len() * 2);
+    bytes.extend_from_slice(b"RIFF");
+    bytes.extend_from_slice(&(36 + data_len).to_le_bytes());
+    bytes.extend_from_slice(b"WAVE");
+
+    bytes.extend_from_slice(b"fmt ");
+    bytes.extend_from_slice(&16u32.to_le_bytes());
+    bytes.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    bytes.extend_from_slice(&channels.to_le_bytes());
+    bytes.extend_from_slice(&sample_rate.to_le_bytes());
+    bytes.extend_from_slice(&(sample_rate * block_align as u32).to_le_bytes());
+    bytes.extend_from_slice(&block_align.to_le_bytes());
+    bytes.extend_from_slice(&16u16.to_le_bytes()); // bits per sample
+
+    bytes.extend_from_slice(b"data");
+    bytes.extend_from_slice(&data_len.to_le_bytes());
+    for sample in samples {
+        let value = (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+        bytes.extend_from_slice(&value.to_le_bytes());
+    }
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::audio::AudioPlayerHandle;
+
+    #[test]
+    fn test_headless_renders_commands() {
+        let (handle, mut output) = AudioPlayerHandle::headless(SAMPLE_RATE as f32);
+        output.render(100);
+        assert!(output.samples().iter().all(|&s| s == 0.0));
+
+        handle.play().unwrap();
+        handle.trigger_note(1, vec![440.0]).unwrap();
+        output.render(SAMPLE_RATE as usize / 2);
+        assert_eq!(output.frames(), 100 + SAMPLE_RATE as usize / 2);
+        assert!(output.samples().iter().any(|&s| s.abs() > 0.01));
+    }
+
+    #[test]
+    fn test_headless_note_pans() {
+        let (handle, mut output) = AudioPlayerHandle::headless(SAMPLE_RATE as f32);
+        handle.play().unwrap();
+        handle
+            .trigger_panned_notes(1, vec![440.0], vec![Some(0.0)])
+            .unwrap();
+        output.render(SAMPLE_RATE as usize / 2);
+
+        // Hard left: only the left channel sounds
+        let (left, right): (Vec<f32>, Vec<f32>) = output
+            .samples()
+            .chunks(CHANNELS)
+            .map(|frame| (frame[0], frame[1]))
+            .unzip();
+        assert!(left.iter().any(|s| s.abs() > 0.01));
+        assert!(right.iter().all(|s| s.abs() < 1e-6));
+    }
+
+    #[test]
+    fn test_wav_header() {
+        let bytes = wav_bytes(&[0.0, 1.0, -1.0, 0.5], 44_100, 2);
+        assert_eq!(&bytes[0..4], b"RIFF");
+        assert_eq!(u32::from_le_bytes(bytes[4..8].try_into().unwrap()), 36 + 8);
+        assert_eq!(&bytes[8..16], b"WAVEfmt ");
+        assert_eq!(u16::from_le_bytes([bytes[22], bytes[23]]), 2);
+        assert_eq!(&bytes[36..40], b"data");
+        assert_eq!(bytes.len(), 44 + 8);
+        assert_eq!(i16::from_le_bytes([bytes[46], bytes[47]]), i16::MAX);
+    }
+}
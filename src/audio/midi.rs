@@ -5,10 +5,18 @@
 
 use anyhow::{anyhow, Result};
 use midir::{MidiOutput, MidiOutputConnection};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicBool, AtomicI32, AtomicU8, Ordering};
 use std::sync::mpsc::{channel, Sender};
 use std::sync::{Mutex, RwLock};
 use std::thread::{self, JoinHandle};
 
+/// Default name for `midi out virtual` without an argument
+pub const DEFAULT_VIRTUAL_PORT_NAME: &str = "Cadence Out";
+
+/// Whether this platform can create virtual MIDI ports (CoreMIDI and ALSA/JACK can; WinMM cannot)
+pub const VIRTUAL_PORTS_SUPPORTED: bool = cfg!(unix);
+
 /// Convert a Note (pitch_class + octave) to MIDI note number
 /// MIDI note 60 = Middle C (C4 in scientific pitch notation)
 /// Formula: midi_note = (octave + 1) * 12 + pitch_class
@@ -27,21 +35,44 @@ pub fn frequency_to_midi(freq: f32) -> u8 {
     (midi_note.round() as i32).clamp(0, 127) as u8
 }
 
+/// Convert frequency to the nearest MIDI note and its offset from that note in cents.
+/// Offsets are rounded to whole cents so equal-tempered frequencies report 0.
+pub fn frequency_to_midi_cents(freq: f32) -> (u8, f32) {
+    if freq <= 0.0 {
+        return (0, 0.0);
+    }
+    let exact = 69.0 + 12.0 * (freq / 440.0).log2();
+    let note = exact.round();
+    if !(0.0..=127.0).contains(&note) {
+        return (frequency_to_midi(freq), 0.0);
+    }
+    (note as u8, ((exact - note) * 100.0).round())
+}
+
+/// Default pitch bend range in semitones (the General MIDI default)
+pub const DEFAULT_BEND_RANGE: u8 = 2;
+
+/// Pitch bend value for a note at its center (no bend)
+pub const PITCH_BEND_CENTER: u16 = 8192;
+
+/// 14-bit pitch bend value that shifts a note by `cents`, given the synth's
+/// bend range in semitones. Offsets beyond the range are clamped.
+pub fn pitch_bend_value(cents: f32, range_semitones: u8) -> u16 {
+    let range_cents = range_semitones.max(1) as f32 * 100.0;
+    let offset = (cents / range_cents * PITCH_BEND_CENTER as f32).round();
+    (PITCH_BEND_CENTER as f32 + offset).clamp(0.0, 16383.0) as u16
+}
+
 /// MIDI channel mode configuration
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
 pub enum MidiChannelMode {
     /// Each track maps to its own MIDI channel (Track 0 → Ch 1, Track 1 → Ch 2, etc.)
+    #[default]
     PerTrack,
     /// All tracks output to a single MIDI channel
     Mono(u8),
 }
 
-impl Default for MidiChannelMode {
-    fn default() -> Self {
-        MidiChannelMode::PerTrack
-    }
-}
-
 /// Output mode: audio only, MIDI only, or both
 #[derive(Clone, Copy, Debug, PartialEq, Default)]
 pub enum OutputMode {
@@ -59,6 +90,8 @@ pub enum OutputMode {
 pub enum MidiCommand {
     /// Connect to a MIDI port by name
     Connect { port_name: String },
+    /// Create a virtual output port that other applications can connect to
+    ConnectVirtual { name: String },
     /// Send Note On: channel (0-15), note (0-127), velocity (0-127)
     NoteOn { channel: u8, note: u8, velocity: u8 },
     /// Send Note Off: channel (0-15), note (0-127)
@@ -69,14 +102,269 @@ pub enum MidiCommand {
         controller: u8,
         value: u8,
     },
+    /// Send Program Change: channel, program number
+    ProgramChange { channel: u8, program: u8 },
+    /// Send Pitch Bend: channel, 14-bit value (8192 = center)
+    PitchBend { channel: u8, value: u16 },
     /// Send All Notes Off on specified channel
     AllNotesOff { channel: u8 },
+    /// Send All Sound Off on specified channel (cuts release tails too)
+    AllSoundOff { channel: u8 },
+    /// MIDI Clock pulse (24 per quarter note)
+    ClockPulse,
+    /// Transport Start (play from the beginning)
+    TransportStart,
+    /// Transport Continue (resume from the current position)
+    TransportContinue,
+    /// Transport Stop
+    TransportStop,
     /// Disconnect from MIDI port
     Disconnect,
     /// Shutdown the MIDI thread
     Shutdown,
 }
 
+impl MidiCommand {
+    /// Raw MIDI bytes for channel messages (None for connection control commands)
+    pub fn bytes(&self) -> Option<Vec<u8>> {
+        match *self {
+            // MIDI Note On: 0x90 + channel, note, velocity
+            MidiCommand::NoteOn {
+                channel,
+                note,
+                velocity,
+            } => Some(vec![0x90 | (channel & 0x0F), note & 0x7F, velocity & 0x7F]),
+            // MIDI Note Off: 0x80 + channel, note, velocity 0
+            MidiCommand::NoteOff { channel, note } => {
+                Some(vec![0x80 | (channel & 0x0F), note & 0x7F, 0])
+            }
+            // MIDI CC: 0xB0 + channel, controller, value
+            MidiCommand::ControlChange {
+                channel,
+                controller,
+                value,
+            } => Some(vec![
+                0xB0 | (channel & 0x0F),
+                controller & 0x7F,
+                value & 0x7F,
+            ]),
+            // Program Change: 0xC0 + channel, program
+            MidiCommand::ProgramChange { channel, program } => {
+                Some(vec![0xC0 | (channel & 0x0F), program & 0x7F])
+            }
+            // Pitch Bend: 0xE0 + channel, LSB, MSB
+            MidiCommand::PitchBend { channel, value } => Some(vec![
+                0xE0 | (channel & 0x0F),
+                (value & 0x7F) as u8,
+                ((value >> 7) & 0x7F) as u8,
+            ]),
+            // All Notes Off: CC 123, value 0
+            MidiCommand::AllNotesOff { channel } => Some(vec![0xB0 | (channel & 0x0F), 123, 0]),
+            // All Sound Off: CC 120, value 0
+            MidiCommand::AllSoundOff { channel } => Some(vec![0xB0 | (channel & 0x0F), 120, 0]),
+            // System real-time messages
+            MidiCommand::ClockPulse => Some(vec![0xF8]),
+            MidiCommand::TransportStart => Some(vec![0xFA]),
+            MidiCommand::TransportContinue => Some(vec![0xFB]),
+            MidiCommand::TransportStop => Some(vec![0xFC]),
+            MidiCommand::Connect { .. }
+            | MidiCommand::ConnectVirtual { .. }
+            | MidiCommand::Disconnect
+            | MidiCommand::Shutdown => None,
+        }
+    }
+}
+
+/// Transport state reported to external gear through MIDI clock messages
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub enum Transport {
+    /// Nothing has played yet; the next play sends Start
+    #[default]
+    Idle,
+    /// Playing; clock pulses are flowing
+    Playing,
+    /// Stopped after playing; the next play sends Continue
+    Stopped,
+}
+
+/// Receiver of MIDI clock pulses driven by the master clock
+pub trait MidiClockSink: Send + Sync {
+    /// Called once per master clock tick (24 PPQN)
+    fn clock_pulse(&self);
+}
+
+/// Response curve mapping pattern velocities to outgoing note-on velocities
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub enum VelocityCurve {
+    /// Velocities are sent unchanged
+    #[default]
+    Linear,
+    /// Quiet notes are lifted (square-root curve)
+    Soft,
+    /// Quiet notes are pushed down (square curve)
+    Hard,
+}
+
+impl VelocityCurve {
+    /// Map a velocity through the curve, clamped to 1-127 (0 would be a note off)
+    pub fn apply(&self, velocity: u8) -> u8 {
+        let x = velocity.min(127) as f32 / 127.0;
+        let y = match self {
+            VelocityCurve::Linear => x,
+            VelocityCurve::Soft => x.sqrt(),
+            VelocityCurve::Hard => x * x,
+        };
+        ((y * 127.0).round() as u8).clamp(1, 127)
+    }
+}
+
+impl std::str::FromStr for VelocityCurve {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "linear" => Ok(VelocityCurve::Linear),
+            "soft" => Ok(VelocityCurve::Soft),
+            "hard" => Ok(VelocityCurve::Hard),
+            _ => Err(anyhow!(
+                "Unknown velocity curve: {} (use linear, soft or hard)",
+                s
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for VelocityCurve {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VelocityCurve::Linear => write!(f, "linear"),
+            VelocityCurve::Soft => write!(f, "soft"),
+            VelocityCurve::Hard => write!(f, "hard"),
+        }
+    }
+}
+
+/// Per-track MIDI settings from `midi program` / `midi bank` / `midi vel_curve` / `midi latency`
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub struct TrackPatch {
+    /// Program number (0-127)
+    pub program: Option<u8>,
+    /// Bank select as (MSB, LSB)
+    pub bank: Option<(u8, u8)>,
+    /// Curve applied to note-on velocities
+    pub velocity_curve: VelocityCurve,
+    /// Latency override in milliseconds (the global latency applies when unset)
+    pub latency_ms: Option<i32>,
+}
+
+impl TrackPatch {
+    /// Messages that select this patch: Bank Select (CC 0, CC 32) then Program Change
+    pub fn commands(&self, channel: u8) -> Vec<MidiCommand> {
+        let mut commands = Vec::new();
+        if let Some((msb, lsb)) = self.bank {
+            commands.push(MidiCommand::ControlChange {
+                channel,
+                controller: 0,
+                value: msb,
+            });
+            commands.push(MidiCommand::ControlChange {
+                channel,
+                controller: 32,
+                value: lsb,
+            });
+        }
+        if let Some(program) = self.program {
+            commands.push(MidiCommand::ProgramChange { channel, program });
+        }
+        commands
+    }
+}
+
+/// Assigns each sounding note its own channel for MPE-lite output, so notes
+/// in a chord can be bent independently.
+///
+/// Channels rotate through the pool (MIDI channels 2-16; channel 1 is left
+/// as the MPE master channel). When every channel is busy the oldest note
+/// is stolen.
+#[derive(Debug, Clone)]
+pub struct ChannelAllocator {
+    /// Member channels (0-indexed) handed out to notes
+    pool: Vec<u8>,
+    /// Sounding notes as (channel, track, note), oldest first
+    voices: Vec<(u8, usize, u8)>,
+    /// Position in `pool` where the next search starts
+    next: usize,
+}
+
+impl ChannelAllocator {
+    /// Allocator over the MPE lower zone member channels (2-16)
+    pub fn new() -> Self {
+        ChannelAllocator {
+            pool: (1..16).collect(),
+            voices: Vec::new(),
+            next: 0,
+        }
+    }
+
+    /// Pick a channel for a new note.
+    /// Returns the channel and, if a note had to be stolen, that note's (channel, note).
+    pub fn allocate(&mut self, track_id: usize, note: u8) -> (u8, Option<(u8, u8)>) {
+        let free = (0..self.pool.len())
+            .map(|i| (self.next + i) % self.pool.len())
+            .find(|&i| !self.voices.iter().any(|(ch, ..)| *ch == self.pool[i]));
+
+        let (channel, stolen) = match free {
+            Some(i) => {
+                self.next = (i + 1) % self.pool.len();
+                (self.pool[i], None)
+            }
+            None => {
+                let (channel, _, stolen_note) = self.voices.remove(0);
+                (channel, Some((channel, stolen_note)))
+            }
+        };
+
+        self.voices.push((channel, track_id, note));
+        (channel, stolen)
+    }
+
+    /// Release a note, returning the channel it was sounding on
+    pub fn release(&mut self, track_id: usize, note: u8) -> Option<u8> {
+        let index = self
+            .voices
+            .iter()
+            .position(|&(_, t, n)| t == track_id && n == note)?;
+        Some(self.voices.remove(index).0)
+    }
+
+    /// Release every note of a track, returning their (channel, note) pairs
+    pub fn release_track(&mut self, track_id: usize) -> Vec<(u8, u8)> {
+        let mut released = Vec::new();
+        self.voices.retain(|&(channel, t, note)| {
+            if t == track_id {
+                released.push((channel, note));
+            }
+            t != track_id
+        });
+        released
+    }
+
+    /// Forget all sounding notes
+    pub fn clear(&mut self) {
+        self.voices.clear();
+    }
+}
+
+impl Default for ChannelAllocator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn virtual_ports_unsupported() -> anyhow::Error {
+    anyhow!("Virtual MIDI ports are not supported on this platform (use a loopback driver such as loopMIDI)")
+}
+
 /// Internal MIDI output handler that owns the connection
 struct MidiOutputInternal {
     connection: Option<MidiOutputConnection>,
@@ -105,11 +393,31 @@ impl MidiOutputInternal {
             })
             .ok_or_else(|| anyhow!("MIDI port '{}' not found", port_name))?;
 
-        let connection = midi_out.connect(port, "cadence-out")?;
+        let connection = midi_out
+            .connect(port, "cadence-out")
+            .map_err(|e| anyhow!("Failed to connect to MIDI port: {}", e))?;
+        self.connection = Some(connection);
+        Ok(())
+    }
+
+    /// Publish a virtual port; it stays open until disconnect or shutdown
+    #[cfg(unix)]
+    fn connect_virtual(&mut self, name: &str) -> Result<()> {
+        use midir::os::unix::VirtualOutput;
+
+        let midi_out = MidiOutput::new("Cadence")?;
+        let connection = midi_out
+            .create_virtual(name)
+            .map_err(|e| anyhow!("Failed to create virtual MIDI port: {}", e))?;
         self.connection = Some(connection);
         Ok(())
     }
 
+    #[cfg(not(unix))]
+    fn connect_virtual(&mut self, _name: &str) -> Result<()> {
+        Err(virtual_ports_unsupported())
+    }
+
     fn run(&mut self) {
         while let Ok(cmd) = self.command_rx.recv() {
             match cmd {
@@ -118,37 +426,24 @@ impl MidiOutputInternal {
                         eprintln!("MIDI connect error: {}", e);
                     }
                 }
-                MidiCommand::NoteOn {
-                    channel,
-                    note,
-                    velocity,
-                } => {
-                    if let Some(conn) = &mut self.connection {
-                        // MIDI Note On: 0x90 + channel, note, velocity
-                        let _ = conn.send(&[0x90 | (channel & 0x0F), note & 0x7F, velocity & 0x7F]);
-                    }
-                }
-                MidiCommand::NoteOff { channel, note } => {
-                    if let Some(conn) = &mut self.connection {
-                        // MIDI Note Off: 0x80 + channel, note, velocity 0
-                        let _ = conn.send(&[0x80 | (channel & 0x0F), note & 0x7F, 0]);
+                MidiCommand::ConnectVirtual { name } => {
+                    if let Err(e) = self.connect_virtual(&name) {
+                        eprintln!("MIDI virtual port error: {}", e);
                     }
                 }
-                MidiCommand::ControlChange {
-                    channel,
-                    controller,
-                    value,
-                } => {
-                    if let Some(conn) = &mut self.connection {
-                        // MIDI CC: 0xB0 + channel, controller, value
-                        let _ =
-                            conn.send(&[0xB0 | (channel & 0x0F), controller & 0x7F, value & 0x7F]);
-                    }
-                }
-                MidiCommand::AllNotesOff { channel } => {
-                    if let Some(conn) = &mut self.connection {
-                        // All Notes Off: CC 123, value 0
-                        let _ = conn.send(&[0xB0 | (channel & 0x0F), 123, 0]);
+                MidiCommand::NoteOn { .. }
+                | MidiCommand::NoteOff { .. }
+                | MidiCommand::ControlChange { .. }
+                | MidiCommand::ProgramChange { .. }
+                | MidiCommand::PitchBend { .. }
+                | MidiCommand::AllNotesOff { .. }
+                | MidiCommand::AllSoundOff { .. }
+                | MidiCommand::ClockPulse
+                | MidiCommand::TransportStart
+                | MidiCommand::TransportContinue
+                | MidiCommand::TransportStop => {
+                    if let (Some(conn), Some(bytes)) = (&mut self.connection, cmd.bytes()) {
+                        let _ = conn.send(&bytes);
                     }
                 }
                 MidiCommand::Disconnect => {
@@ -193,6 +488,24 @@ pub struct MidiOutputHandle {
     connected: RwLock<bool>,
     /// Name of the connected port
     port_name: RwLock<Option<String>>,
+    /// Whether the connected port is a virtual port we created
+    virtual_port: AtomicBool,
+    /// Program/bank per track, re-sent whenever a port is connected
+    patches: RwLock<BTreeMap<usize, TrackPatch>>,
+    /// Whether MIDI clock and transport messages are sent
+    clock_enabled: AtomicBool,
+    /// Global latency compensation in milliseconds (positive sends MIDI early)
+    latency_ms: AtomicI32,
+    /// Pitch bend range of the receiving synth in semitones
+    bend_range: AtomicU8,
+    /// Whether notes are spread over member channels (MPE-lite)
+    mpe_enabled: AtomicBool,
+    /// Channel assignment for sounding notes in MPE-lite mode
+    allocator: Mutex<ChannelAllocator>,
+    /// Current pitch bend per channel (channels not listed are centered)
+    bends: Mutex<HashMap<u8, u16>>,
+    /// Transport state for Start/Continue/Stop messages
+    transport: Mutex<Transport>,
     /// Cached MidiOutput for port enumeration (avoids creating new CoreMIDI client each time)
     port_enumerator: Mutex<Option<MidiOutput>>,
 }
@@ -221,6 +534,15 @@ impl MidiOutputHandle {
             active_notes: Mutex::new(std::collections::HashSet::new()),
             connected: RwLock::new(false),
             port_name: RwLock::new(None),
+            virtual_port: AtomicBool::new(false),
+            patches: RwLock::new(BTreeMap::new()),
+            clock_enabled: AtomicBool::new(false),
+            latency_ms: AtomicI32::new(0),
+            bend_range: AtomicU8::new(DEFAULT_BEND_RANGE),
+            mpe_enabled: AtomicBool::new(false),
+            allocator: Mutex::new(ChannelAllocator::new()),
+            bends: Mutex::new(HashMap::new()),
+            transport: Mutex::new(Transport::default()),
             port_enumerator: Mutex::new(port_enumerator),
         })
     }
@@ -285,11 +607,42 @@ impl MidiOutputHandle {
             midi_out.port_name(port)?
         };
 
+        self.open_port(
+            MidiCommand::Connect {
+                port_name: port_name.to_string(),
+            },
+            actual_name,
+        )
+    }
+
+    /// Create a virtual output port named `name` and route all MIDI output to it.
+    ///
+    /// DAWs see the port as a MIDI source, so they can record Cadence directly.
+    /// The port stays open until `disconnect()` or the handle is dropped.
+    pub fn open_virtual(&self, name: &str) -> Result<()> {
+        if !VIRTUAL_PORTS_SUPPORTED {
+            return Err(virtual_ports_unsupported());
+        }
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(anyhow!("Virtual MIDI port name cannot be empty"));
+        }
+
+        self.open_port(
+            MidiCommand::ConnectVirtual {
+                name: name.to_string(),
+            },
+            name.to_string(),
+        )?;
+        self.virtual_port.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Connect the MIDI thread to a validated port and restore track patches
+    fn open_port(&self, connect: MidiCommand, actual_name: String) -> Result<()> {
         // Send connect command to the MIDI thread (which will create its own connection)
         self.command_tx
-            .send(MidiCommand::Connect {
-                port_name: port_name.to_string(),
-            })
+            .send(connect)
             .map_err(|e| anyhow!("Failed to send connect command: {}", e))?;
 
         // Update connection state
@@ -299,6 +652,19 @@ impl MidiOutputHandle {
             *connected = true;
             *stored_name = Some(actual_name);
         }
+        self.virtual_port.store(false, Ordering::Relaxed);
+
+        // The device on the other end starts with its default patches
+        let patches: Vec<(usize, TrackPatch)> = self
+            .patches
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(&track_id, &patch)| (track_id, patch))
+            .collect();
+        for (track_id, patch) in patches {
+            self.send_patch(track_id, &patch)?;
+        }
 
         Ok(())
     }
@@ -315,6 +681,7 @@ impl MidiOutputHandle {
             *connected = false;
             *stored_name = None;
         }
+        self.virtual_port.store(false, Ordering::Relaxed);
 
         // Clear active notes
         if let Ok(mut notes) = self.active_notes.lock() {
@@ -334,6 +701,11 @@ impl MidiOutputHandle {
         self.port_name.read().unwrap().clone()
     }
 
+    /// Whether the connected port is a virtual port created by `open_virtual`
+    pub fn is_virtual(&self) -> bool {
+        self.virtual_port.load(Ordering::Relaxed)
+    }
+
     /// Set the channel mode
     pub fn set_channel_mode(&self, mode: MidiChannelMode) {
         if let Ok(mut m) = self.channel_mode.write() {
@@ -376,9 +748,43 @@ impl MidiOutputHandle {
         }
     }
 
-    /// Send Note On for a track
+    /// Send Note On for a track. The velocity is mapped through the track's velocity curve.
     pub fn note_on(&self, track_id: usize, note: u8, velocity: u8) -> Result<()> {
-        let channel = self.channel_for_track(track_id);
+        self.note_on_detuned(track_id, note, 0.0, velocity)
+    }
+
+    /// Send Note On for a note offset by `cents`, approximated with pitch bend.
+    ///
+    /// The bend is sent before the note-on on the note's channel. In MPE-lite
+    /// mode each note gets its own channel so chord notes bend independently;
+    /// otherwise all notes of a track share its channel and the latest bend wins.
+    pub fn note_on_detuned(
+        &self,
+        track_id: usize,
+        note: u8,
+        cents: f32,
+        velocity: u8,
+    ) -> Result<()> {
+        let velocity = self.velocity_curve(track_id).apply(velocity);
+        let channel = if self.mpe_enabled() {
+            let (channel, stolen) = self.allocator.lock().unwrap().allocate(track_id, note);
+            if let Some((stolen_channel, stolen_note)) = stolen {
+                if let Ok(mut notes) = self.active_notes.lock() {
+                    notes.remove(&(stolen_channel, stolen_note));
+                }
+                self.command_tx
+                    .send(MidiCommand::NoteOff {
+                        channel: stolen_channel,
+                        note: stolen_note,
+                    })
+                    .map_err(|e| anyhow!("Failed to send note off: {}", e))?;
+            }
+            channel
+        } else {
+            self.channel_for_track(track_id)
+        };
+
+        self.set_pitch_bend(channel, pitch_bend_value(cents, self.bend_range()))?;
 
         // Track active note
         if let Ok(mut notes) = self.active_notes.lock() {
@@ -394,18 +800,16 @@ impl MidiOutputHandle {
             .map_err(|e| anyhow!("Failed to send note on: {}", e))
     }
 
-    /// Send Note Off for a track
+    /// Send Note Off for a track, re-centering pitch bend once its channel is silent
     pub fn note_off(&self, track_id: usize, note: u8) -> Result<()> {
-        let channel = self.channel_for_track(track_id);
-
-        // Remove from active notes
-        if let Ok(mut notes) = self.active_notes.lock() {
-            notes.remove(&(channel, note));
+        let channel = if self.mpe_enabled() {
+            self.allocator.lock().unwrap().release(track_id, note)
+        } else {
+            None
         }
+        .unwrap_or_else(|| self.channel_for_track(track_id));
 
-        self.command_tx
-            .send(MidiCommand::NoteOff { channel, note })
-            .map_err(|e| anyhow!("Failed to send note off: {}", e))
+        self.release_notes(&[(channel, note)])
     }
 
     /// Send Note On for multiple notes (chord)
@@ -426,34 +830,108 @@ impl MidiOutputHandle {
 
     /// Turn off all active notes for a track
     pub fn all_notes_off_for_track(&self, track_id: usize) -> Result<()> {
-        let channel = self.channel_for_track(track_id);
+        let notes_to_off: Vec<(u8, u8)> = if self.mpe_enabled() {
+            self.allocator.lock().unwrap().release_track(track_id)
+        } else {
+            let channel = self.channel_for_track(track_id);
+            match self.active_notes.lock() {
+                Ok(notes) => notes
+                    .iter()
+                    .filter(|(ch, _)| *ch == channel)
+                    .copied()
+                    .collect(),
+                Err(_) => vec![],
+            }
+        };
 
-        // Get and clear active notes for this channel
-        let notes_to_off: Vec<u8> = if let Ok(mut notes) = self.active_notes.lock() {
-            let channel_notes: Vec<u8> = notes
-                .iter()
-                .filter(|(ch, _)| *ch == channel)
-                .map(|(_, note)| *note)
-                .collect();
+        self.release_notes(&notes_to_off)
+    }
 
-            for note in &channel_notes {
-                notes.remove(&(channel, *note));
+    /// Send Note Off for each (channel, note), then re-center the bend on
+    /// any channel left with no sounding notes
+    fn release_notes(&self, to_release: &[(u8, u8)]) -> Result<()> {
+        if let Ok(mut notes) = self.active_notes.lock() {
+            for key in to_release {
+                notes.remove(key);
             }
-            channel_notes
-        } else {
-            vec![]
-        };
+        }
 
-        // Send Note Off for each
-        for note in notes_to_off {
+        for &(channel, note) in to_release {
             self.command_tx
                 .send(MidiCommand::NoteOff { channel, note })
                 .map_err(|e| anyhow!("Failed to send note off: {}", e))?;
         }
 
+        let mut channels: Vec<u8> = to_release.iter().map(|&(ch, _)| ch).collect();
+        channels.sort_unstable();
+        channels.dedup();
+        for channel in channels {
+            let silent = self
+                .active_notes
+                .lock()
+                .map(|notes| !notes.iter().any(|(ch, _)| *ch == channel))
+                .unwrap_or(true);
+            if silent {
+                self.set_pitch_bend(channel, PITCH_BEND_CENTER)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Send a pitch bend if it differs from the channel's current bend
+    fn set_pitch_bend(&self, channel: u8, value: u16) -> Result<()> {
+        {
+            let mut bends = self.bends.lock().unwrap();
+            let current = bends.get(&channel).copied().unwrap_or(PITCH_BEND_CENTER);
+            if current == value {
+                return Ok(());
+            }
+            if value == PITCH_BEND_CENTER {
+                bends.remove(&channel);
+            } else {
+                bends.insert(channel, value);
+            }
+        }
+
+        self.command_tx
+            .send(MidiCommand::PitchBend { channel, value })
+            .map_err(|e| anyhow!("Failed to send pitch bend: {}", e))
+    }
+
+    /// Set the receiving synth's pitch bend range in semitones (1-48)
+    pub fn set_bend_range(&self, semitones: u8) {
+        self.bend_range
+            .store(semitones.clamp(1, 48), Ordering::Relaxed);
+    }
+
+    /// Pitch bend range used to scale microtonal offsets
+    pub fn bend_range(&self) -> u8 {
+        self.bend_range.load(Ordering::Relaxed)
+    }
+
+    /// Enable or disable MPE-lite channel rotation.
+    /// Held notes are released first, since their note-offs would go to the wrong channel.
+    pub fn set_mpe_enabled(&self, enabled: bool) -> Result<()> {
+        if self.mpe_enabled() == enabled {
+            return Ok(());
+        }
+
+        let held: Vec<(u8, u8)> = match self.active_notes.lock() {
+            Ok(notes) => notes.iter().copied().collect(),
+            Err(_) => vec![],
+        };
+        self.release_notes(&held)?;
+        self.allocator.lock().unwrap().clear();
+        self.mpe_enabled.store(enabled, Ordering::Relaxed);
         Ok(())
     }
 
+    /// Whether notes are spread over member channels
+    pub fn mpe_enabled(&self) -> bool {
+        self.mpe_enabled.load(Ordering::Relaxed)
+    }
+
     /// Send All Notes Off message for a track's channel
     pub fn panic(&self, track_id: usize) -> Result<()> {
         let channel = self.channel_for_track(track_id);
@@ -462,17 +940,46 @@ impl MidiOutputHandle {
             .map_err(|e| anyhow!("Failed to send all notes off: {}", e))
     }
 
-    /// Send All Notes Off on all channels (MIDI panic)
+    /// MIDI panic: release every held note, send All Notes Off and All Sound
+    /// Off on all 16 channels, then re-center any bent channel.
+    ///
+    /// Messages go straight to the MIDI thread, so this works even when the
+    /// event dispatcher is stuck and will never send its scheduled note-offs.
     pub fn panic_all(&self) -> Result<()> {
+        let mut held: Vec<(u8, u8)> = match self.active_notes.lock() {
+            Ok(mut notes) => notes.drain().collect(),
+            Err(_) => vec![],
+        };
+        held.sort_unstable();
+        self.allocator.lock().unwrap().clear();
+
+        for (channel, note) in held {
+            self.command_tx
+                .send(MidiCommand::NoteOff { channel, note })
+                .map_err(|e| anyhow!("Failed to send note off: {}", e))?;
+        }
+
         for ch in 0..16u8 {
             self.command_tx
                 .send(MidiCommand::AllNotesOff { channel: ch })
                 .map_err(|e| anyhow!("Failed to send all notes off: {}", e))?;
+            self.command_tx
+                .send(MidiCommand::AllSoundOff { channel: ch })
+                .map_err(|e| anyhow!("Failed to send all sound off: {}", e))?;
         }
 
-        // Clear active notes tracking
-        if let Ok(mut notes) = self.active_notes.lock() {
-            notes.clear();
+        let mut bent: Vec<u8> = match self.bends.lock() {
+            Ok(mut bends) => bends.drain().map(|(channel, _)| channel).collect(),
+            Err(_) => vec![],
+        };
+        bent.sort_unstable();
+        for channel in bent {
+            self.command_tx
+                .send(MidiCommand::PitchBend {
+                    channel,
+                    value: PITCH_BEND_CENTER,
+                })
+                .map_err(|e| anyhow!("Failed to send pitch bend: {}", e))?;
         }
 
         Ok(())
@@ -492,6 +999,173 @@ impl MidiOutputHandle {
             .map_err(|e| anyhow!("Failed to send CC: {}", e))
     }
 
+    /// Select a program for a track, sending Program Change on its channel
+    pub fn set_track_program(&self, track_id: usize, program: u8) -> Result<()> {
+        let program = program & 0x7F;
+        self.patches
+            .write()
+            .unwrap()
+            .entry(track_id)
+            .or_default()
+            .program = Some(program);
+
+        let channel = self.channel_for_track(track_id);
+        self.command_tx
+            .send(MidiCommand::ProgramChange { channel, program })
+            .map_err(|e| anyhow!("Failed to send program change: {}", e))
+    }
+
+    /// Select a bank for a track, sending Bank Select (CC 0 / CC 32) on its channel.
+    /// The track's program is re-sent afterwards so the new bank takes effect.
+    pub fn set_track_bank(&self, track_id: usize, msb: u8, lsb: u8) -> Result<()> {
+        let patch = {
+            let mut patches = self.patches.write().unwrap();
+            let patch = patches.entry(track_id).or_default();
+            patch.bank = Some((msb & 0x7F, lsb & 0x7F));
+            *patch
+        };
+        self.send_patch(track_id, &patch)
+    }
+
+    /// Set the velocity curve used for a track's note-ons
+    pub fn set_track_velocity_curve(&self, track_id: usize, curve: VelocityCurve) {
+        self.patches
+            .write()
+            .unwrap()
+            .entry(track_id)
+            .or_default()
+            .velocity_curve = curve;
+    }
+
+    /// Velocity curve for a track (linear unless set)
+    pub fn velocity_curve(&self, track_id: usize) -> VelocityCurve {
+        self.patches
+            .read()
+            .unwrap()
+            .get(&track_id)
+            .map(|patch| patch.velocity_curve)
+            .unwrap_or_default()
+    }
+
+    /// Set the global latency compensation. Positive values send MIDI earlier
+    /// than the internal synth, negative values later.
+    pub fn set_latency_ms(&self, ms: i32) {
+        self.latency_ms.store(ms, Ordering::Relaxed);
+    }
+
+    /// Override the latency for one track (`None` falls back to the global value)
+    pub fn set_track_latency_ms(&self, track_id: usize, ms: Option<i32>) {
+        self.patches
+            .write()
+            .unwrap()
+            .entry(track_id)
+            .or_default()
+            .latency_ms = ms;
+    }
+
+    /// Global latency compensation in milliseconds
+    pub fn global_latency_ms(&self) -> i32 {
+        self.latency_ms.load(Ordering::Relaxed)
+    }
+
+    /// Latency compensation for a track: its override, or the global value
+    pub fn latency_ms(&self, track_id: usize) -> i32 {
+        self.patches
+            .read()
+            .unwrap()
+            .get(&track_id)
+            .and_then(|patch| patch.latency_ms)
+            .unwrap_or_else(|| self.global_latency_ms())
+    }
+
+    /// Largest latency of any track, i.e. how far ahead the dispatcher must look
+    pub fn max_latency_ms(&self) -> i32 {
+        let overrides = self.patches.read().unwrap();
+        overrides
+            .values()
+            .filter_map(|patch| patch.latency_ms)
+            .fold(self.global_latency_ms(), i32::max)
+    }
+
+    /// Clear the global latency and every per-track override
+    pub fn reset_latency(&self) {
+        self.set_latency_ms(0);
+        for patch in self.patches.write().unwrap().values_mut() {
+            patch.latency_ms = None;
+        }
+    }
+
+    /// Program/bank selected for each track, ordered by track ID
+    pub fn track_patches(&self) -> Vec<(usize, TrackPatch)> {
+        self.patches
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(&track_id, &patch)| (track_id, patch))
+            .collect()
+    }
+
+    fn send_patch(&self, track_id: usize, patch: &TrackPatch) -> Result<()> {
+        let channel = self.channel_for_track(track_id);
+        for command in patch.commands(channel) {
+            self.command_tx
+                .send(command)
+                .map_err(|e| anyhow!("Failed to send patch change: {}", e))?;
+        }
+        Ok(())
+    }
+
+    /// Enable or disable MIDI clock output (`midi clock on|off`)
+    pub fn set_clock_enabled(&self, enabled: bool) {
+        self.clock_enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Check if MIDI clock output is enabled
+    pub fn clock_enabled(&self) -> bool {
+        self.clock_enabled.load(Ordering::Relaxed)
+    }
+
+    /// Current transport state
+    pub fn transport(&self) -> Transport {
+        *self.transport.lock().unwrap()
+    }
+
+    /// Playback started: send Start the first time, Continue after a stop
+    pub fn transport_play(&self) -> Result<()> {
+        let message = {
+            let mut transport = self.transport.lock().unwrap();
+            let message = match *transport {
+                Transport::Idle => MidiCommand::TransportStart,
+                Transport::Stopped => MidiCommand::TransportContinue,
+                Transport::Playing => return Ok(()),
+            };
+            *transport = Transport::Playing;
+            message
+        };
+        self.send_realtime(message)
+    }
+
+    /// Playback stopped: send Stop
+    pub fn transport_stop(&self) -> Result<()> {
+        {
+            let mut transport = self.transport.lock().unwrap();
+            if *transport != Transport::Playing {
+                return Ok(());
+            }
+            *transport = Transport::Stopped;
+        }
+        self.send_realtime(MidiCommand::TransportStop)
+    }
+
+    fn send_realtime(&self, message: MidiCommand) -> Result<()> {
+        if !self.clock_enabled() {
+            return Ok(());
+        }
+        self.command_tx
+            .send(message)
+            .map_err(|e| anyhow!("Failed to send MIDI clock message: {}", e))
+    }
+
     /// Send Control Change on a specific channel (bypasses track mapping)
     pub fn cc_on_channel(&self, channel: u8, controller: u8, value: u8) -> Result<()> {
         self.command_tx
@@ -504,6 +1178,12 @@ impl MidiOutputHandle {
     }
 }
 
+impl MidiClockSink for MidiOutputHandle {
+    fn clock_pulse(&self) {
+        let _ = self.send_realtime(MidiCommand::ClockPulse);
+    }
+}
+
 impl Drop for MidiOutputHandle {
     fn drop(&mut self) {
         // Send shutdown command to cleanly close the MIDI connection
@@ -528,6 +1208,36 @@ impl Drop for MidiOutputHandle {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::mpsc::Receiver;
+
+    /// Handle with no MIDI thread; commands are captured on the returned receiver
+    fn mock_handle() -> (MidiOutputHandle, Receiver<MidiCommand>) {
+        let (tx, rx) = channel();
+        let handle = MidiOutputHandle {
+            command_tx: tx,
+            thread: None,
+            channel_mode: RwLock::new(MidiChannelMode::default()),
+            output_mode: RwLock::new(OutputMode::default()),
+            active_notes: Mutex::new(std::collections::HashSet::new()),
+            connected: RwLock::new(false),
+            port_name: RwLock::new(None),
+            virtual_port: AtomicBool::new(false),
+            patches: RwLock::new(BTreeMap::new()),
+            clock_enabled: AtomicBool::new(false),
+            latency_ms: AtomicI32::new(0),
+            bend_range: AtomicU8::new(DEFAULT_BEND_RANGE),
+            mpe_enabled: AtomicBool::new(false),
+            allocator: Mutex::new(ChannelAllocator::new()),
+            bends: Mutex::new(HashMap::new()),
+            transport: Mutex::new(Transport::default()),
+            port_enumerator: Mutex::new(None),
+        };
+        (handle, rx)
+    }
+
+    fn sent_bytes(rx: &Receiver<MidiCommand>) -> Vec<Vec<u8>> {
+        rx.try_iter().filter_map(|cmd| cmd.bytes()).collect()
+    }
 
     #[test]
     fn test_note_to_midi() {
@@ -586,4 +1296,279 @@ mod tests {
         let result = handle.list_ports();
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_program_and_bank_bytes() {
+        let (handle, rx) = mock_handle();
+
+        handle.set_track_program(2, 12).unwrap();
+        assert_eq!(sent_bytes(&rx), vec![vec![0xC2, 12]]);
+
+        // Bank select is followed by the stored program so it takes effect
+        handle.set_track_bank(2, 1, 0).unwrap();
+        assert_eq!(
+            sent_bytes(&rx),
+            vec![vec![0xB2, 0, 1], vec![0xB2, 32, 0], vec![0xC2, 12]]
+        );
+
+        assert_eq!(
+            handle.track_patches(),
+            vec![(
+                2,
+                TrackPatch {
+                    program: Some(12),
+                    bank: Some((1, 0)),
+                    velocity_curve: VelocityCurve::Linear,
+                    latency_ms: None,
+                }
+            )]
+        );
+    }
+
+    #[test]
+    fn test_patches_resent_on_reconnect() {
+        let (handle, rx) = mock_handle();
+        handle.set_track_bank(1, 0, 3).unwrap();
+        handle.set_track_program(3, 40).unwrap();
+        rx.try_iter().for_each(drop);
+
+        handle
+            .open_port(
+                MidiCommand::Connect {
+                    port_name: "Synth".to_string(),
+                },
+                "Synth Port 1".to_string(),
+            )
+            .unwrap();
+
+        let commands: Vec<MidiCommand> = rx.try_iter().collect();
+        assert!(matches!(&commands[0], MidiCommand::Connect { port_name } if port_name == "Synth"));
+        let bytes: Vec<Vec<u8>> = commands.iter().filter_map(|c| c.bytes()).collect();
+        assert_eq!(
+            bytes,
+            vec![vec![0xB1, 0, 0], vec![0xB1, 32, 3], vec![0xC3, 40]]
+        );
+        assert_eq!(handle.connected_port().as_deref(), Some("Synth Port 1"));
+    }
+
+    #[test]
+    fn test_transport_messages() {
+        let (handle, rx) = mock_handle();
+
+        // Nothing is sent while clock output is off, but state is tracked
+        handle.transport_play().unwrap();
+        handle.clock_pulse();
+        assert!(sent_bytes(&rx).is_empty());
+        assert_eq!(handle.transport(), Transport::Playing);
+
+        handle.set_clock_enabled(true);
+        handle.clock_pulse();
+        handle.transport_stop().unwrap();
+        handle.transport_stop().unwrap();
+        handle.transport_play().unwrap();
+        handle.transport_play().unwrap();
+        assert_eq!(sent_bytes(&rx), vec![vec![0xF8], vec![0xFC], vec![0xFB]]);
+    }
+
+    #[test]
+    fn test_velocity_curves_clamp() {
+        for curve in [
+            VelocityCurve::Linear,
+            VelocityCurve::Soft,
+            VelocityCurve::Hard,
+        ] {
+            assert_eq!(curve.apply(0), 1, "{} floor", curve);
+            assert_eq!(curve.apply(127), 127, "{} ceiling", curve);
+            assert_eq!(curve.apply(255), 127, "{} overflow", curve);
+        }
+        assert_eq!(VelocityCurve::Linear.apply(64), 64);
+        assert!(VelocityCurve::Soft.apply(64) > 64);
+        assert!(VelocityCurve::Hard.apply(64) < 64);
+        assert_eq!(
+            "Soft".parse::<VelocityCurve>().unwrap(),
+            VelocityCurve::Soft
+        );
+        assert!("loud".parse::<VelocityCurve>().is_err());
+    }
+
+    #[test]
+    fn test_note_on_applies_track_velocity_curve() {
+        let (handle, rx) = mock_handle();
+        handle.set_track_velocity_curve(2, VelocityCurve::Hard);
+
+        handle.note_on(1, 60, 64).unwrap();
+        handle.note_on(2, 60, 64).unwrap();
+        handle.note_on(1, 60, 0).unwrap();
+        assert_eq!(
+            sent_bytes(&rx),
+            vec![vec![0x91, 60, 64], vec![0x92, 60, 32], vec![0x91, 60, 1]]
+        );
+    }
+
+    #[test]
+    fn test_panic_all_message_set() {
+        let (handle, rx) = mock_handle();
+        handle.note_on(3, 64, 100).unwrap();
+        handle.note_on(1, 60, 100).unwrap();
+        rx.try_iter().for_each(drop);
+
+        handle.panic_all().unwrap();
+        let sent = sent_bytes(&rx);
+
+        // Held notes are released first, in channel/note order
+        assert_eq!(&sent[..2], &[vec![0x81, 60, 0], vec![0x83, 64, 0]]);
+
+        let mut expected = Vec::new();
+        for ch in 0..16u8 {
+            expected.push(vec![0xB0 | ch, 123, 0]);
+            expected.push(vec![0xB0 | ch, 120, 0]);
+        }
+        assert_eq!(&sent[2..], expected.as_slice());
+        assert!(handle.active_notes.lock().unwrap().is_empty());
+
+        // Nothing left to release on a second panic
+        handle.panic_all().unwrap();
+        assert_eq!(sent_bytes(&rx).len(), 32);
+    }
+
+    #[test]
+    fn test_open_virtual() {
+        let (handle, rx) = mock_handle();
+        handle.set_track_program(1, 5).unwrap();
+        rx.try_iter().for_each(drop);
+
+        if !VIRTUAL_PORTS_SUPPORTED {
+            let err = handle.open_virtual(DEFAULT_VIRTUAL_PORT_NAME).unwrap_err();
+            assert!(err.to_string().contains("not supported"));
+            assert!(!handle.is_connected());
+            return;
+        }
+
+        assert!(handle.open_virtual("  ").is_err());
+        handle.open_virtual(DEFAULT_VIRTUAL_PORT_NAME).unwrap();
+        assert!(handle.is_connected() && handle.is_virtual());
+        assert_eq!(handle.connected_port().as_deref(), Some("Cadence Out"));
+
+        let commands: Vec<MidiCommand> = rx.try_iter().collect();
+        assert!(
+            matches!(&commands[0], MidiCommand::ConnectVirtual { name } if name == "Cadence Out")
+        );
+        // Track patches are restored on the new port like any other
+        assert_eq!(commands[1].bytes(), Some(vec![0xC1, 5]));
+
+        handle.disconnect().unwrap();
+        assert!(!handle.is_connected() && !handle.is_virtual());
+    }
+
+    #[test]
+    fn test_pitch_bend_values() {
+        // +50 cents is a quarter of a ±2 semitone range, 1/24 of a ±12 range
+        assert_eq!(pitch_bend_value(50.0, 2), 10240);
+        assert_eq!(pitch_bend_value(50.0, 12), 8533);
+        assert_eq!(pitch_bend_value(-50.0, 2), 6144);
+        assert_eq!(pitch_bend_value(0.0, 2), PITCH_BEND_CENTER);
+        // Offsets past the range clamp to the 14-bit limits
+        assert_eq!(pitch_bend_value(300.0, 2), 16383);
+        assert_eq!(pitch_bend_value(-300.0, 2), 0);
+
+        let bend = MidiCommand::PitchBend {
+            channel: 1,
+            value: 10240,
+        };
+        assert_eq!(bend.bytes(), Some(vec![0xE1, 0x00, 0x50]));
+    }
+
+    #[test]
+    fn test_frequency_to_midi_cents() {
+        assert_eq!(frequency_to_midi_cents(440.0), (69, 0.0));
+        // Equal-tempered table frequencies report no offset
+        assert_eq!(frequency_to_midi_cents(261.63), (60, 0.0));
+        // A quarter tone above A4
+        let quarter_sharp = 440.0 * 2f32.powf(0.5 / 12.0) * 0.9999;
+        assert_eq!(frequency_to_midi_cents(quarter_sharp), (69, 50.0));
+    }
+
+    #[test]
+    fn test_detuned_note_bends_and_resets() {
+        let (handle, rx) = mock_handle();
+
+        handle.note_on_detuned(1, 60, 50.0, 100).unwrap();
+        handle.note_off(1, 60).unwrap();
+        // An in-tune note on a centered channel sends no bend at all
+        handle.note_on(1, 62, 100).unwrap();
+        assert_eq!(
+            sent_bytes(&rx),
+            vec![
+                vec![0xE1, 0x00, 0x50],
+                vec![0x91, 60, 100],
+                vec![0x81, 60, 0],
+                vec![0xE1, 0x00, 0x40],
+                vec![0x91, 62, 100],
+            ]
+        );
+
+        handle.set_bend_range(12);
+        handle.note_on_detuned(1, 64, 50.0, 100).unwrap();
+        // 8533 = 0x2155: LSB 0x55, MSB 0x42
+        assert_eq!(sent_bytes(&rx)[0], vec![0xE1, 0x55, 0x42]);
+    }
+
+    #[test]
+    fn test_mpe_rotates_channels() {
+        let (handle, rx) = mock_handle();
+        handle.set_mpe_enabled(true).unwrap();
+
+        // A chord with different offsets lands on separate member channels
+        handle.note_on_detuned(1, 60, 50.0, 100).unwrap();
+        handle.note_on_detuned(1, 64, -14.0, 100).unwrap();
+        handle.note_on(1, 67, 100).unwrap();
+        let sent = sent_bytes(&rx);
+        assert_eq!(sent[0][0], 0xE1);
+        assert_eq!(sent[1], vec![0x91, 60, 100]);
+        assert_eq!(sent[2][0], 0xE2);
+        assert_eq!(sent[3], vec![0x92, 64, 100]);
+        assert_eq!(sent[4], vec![0x93, 67, 100]);
+
+        // Note-offs find the note's channel; the bent channel is re-centered
+        handle.note_off(1, 60).unwrap();
+        assert_eq!(
+            sent_bytes(&rx),
+            vec![vec![0x81, 60, 0], vec![0xE1, 0x00, 0x40]]
+        );
+
+        handle.all_notes_off_for_track(1).unwrap();
+        let sent = sent_bytes(&rx);
+        assert!(sent.contains(&vec![0x82, 64, 0]) && sent.contains(&vec![0x83, 67, 0]));
+        assert!(handle.active_notes.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_channel_allocator_steals_oldest() {
+        let mut allocator = ChannelAllocator::new();
+        let channels: Vec<u8> = (0..15).map(|n| allocator.allocate(1, 40 + n).0).collect();
+        assert_eq!(channels, (1..16).collect::<Vec<u8>>());
+
+        // Pool exhausted: the oldest note gives up its channel
+        assert_eq!(allocator.allocate(1, 80), (1, Some((1, 40))));
+        assert_eq!(allocator.release(1, 41), Some(2));
+        assert_eq!(allocator.allocate(2, 81), (2, None));
+        assert_eq!(allocator.release(1, 40), None);
+    }
+
+    #[test]
+    fn test_track_latency_overrides_global() {
+        let (handle, _rx) = mock_handle();
+        handle.set_latency_ms(20);
+        handle.set_track_latency_ms(2, Some(-5));
+        assert_eq!(handle.latency_ms(1), 20);
+        assert_eq!(handle.latency_ms(2), -5);
+        assert_eq!(handle.max_latency_ms(), 20);
+
+        handle.set_track_latency_ms(3, Some(35));
+        assert_eq!(handle.max_latency_ms(), 35);
+
+        handle.reset_latency();
+        assert_eq!(handle.latency_ms(2), 0);
+        assert_eq!(handle.max_latency_ms(), 0);
+    }
 }
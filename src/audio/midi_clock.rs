@@ -0,0 +1,90 @@
+//! MIDI clock output
+//!
+//! Forwards master clock ticks to MIDI as clock pulses. The master clock
+//! already runs at 24 PPQN, so every tick becomes exactly one pulse; tempo
+//! changes take effect on the next tick without any resynchronisation.
+
+use crate::audio::clock::ClockTick;
+use crate::audio::midi::MidiClockSink;
+use crossbeam_channel::Receiver;
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+/// Spawn a thread sending one clock pulse to `sink` per master clock tick.
+/// The thread exits when the clock is dropped.
+pub fn spawn_midi_clock(
+    ticks: Receiver<ClockTick>,
+    sink: Arc<dyn MidiClockSink>,
+) -> JoinHandle<()> {
+    thread::spawn(move || {
+        while ticks.recv().is_ok() {
+            sink.clock_pulse();
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::clock::TICKS_PER_BEAT;
+    use crossbeam_channel::{unbounded, Sender};
+    use std::time::{Duration, Instant};
+
+    /// Passes each pulse on, so a test can match pulses to the ticks that sent them
+    struct ChannelSink(Sender<()>);
+
+    impl MidiClockSink for ChannelSink {
+        fn clock_pulse(&self) {
+            let _ = self.0.send(());
+        }
+    }
+
+    /// The ticks a master clock at `bpm` sends in its first `seconds`
+    fn clock_ticks(bpm: f32, seconds: f64) -> Vec<ClockTick> {
+        let start = Instant::now();
+        let tick_secs = 60.0 / bpm as f64 / TICKS_PER_BEAT as f64;
+        let ticks_per_beat = TICKS_PER_BEAT as usize;
+        (0..)
+            .map(|i| (i, i as f64 * tick_secs))
+            .take_while(|&(_, at)| at < seconds - 1e-9)
+            .map(|(i, at)| ClockTick {
+                beat: i as f64 / ticks_per_beat as f64,
+                beat_number: (i / ticks_per_beat) as u64,
+                tick_in_beat: (i % ticks_per_beat) as u8,
+                bpm,
+                timestamp: start + Duration::from_secs_f64(at),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_clock_pulses_follow_ticks() {
+        let (tick_tx, tick_rx) = unbounded();
+        let (pulse_tx, pulse_rx) = unbounded();
+        let thread = spawn_midi_clock(tick_rx, Arc::new(ChannelSink(pulse_tx)));
+
+        // Sends the ticks one at a time and counts the pulses, checking each
+        // tick sends exactly one pulse before the next tick arrives
+        let pulses = |ticks: Vec<ClockTick>| {
+            let mut pulses = 0;
+            for tick in ticks {
+                tick_tx.send(tick).unwrap();
+                pulse_rx.recv_timeout(Duration::from_secs(1)).unwrap();
+                assert!(pulse_rx.try_recv().is_err());
+                pulses += 1;
+            }
+            pulses
+        };
+
+        // 120 BPM * 24 PPQN / 60 = 48 pulses per second
+        assert_eq!(pulses(clock_ticks(120.0, 1.0)), 48);
+        // and the same 24 per beat at any tempo
+        assert_eq!(pulses(clock_ticks(60.0, 1.0)), 24);
+        assert_eq!(pulses(clock_ticks(90.0, 2.0)), 72);
+
+        // The thread exits once the clock is gone
+        drop(tick_tx);
+        thread.join().unwrap();
+        assert!(pulse_rx.try_recv().is_err());
+    }
+}
@@ -0,0 +1,379 @@
+//! MIDI input module for Cadence
+//!
+//! Receives messages from a controller on midir's callback thread and hands
+//! them to a sink (the REPL's event channel), so bindings always execute on
+//! the interpreter thread.
+
+use crate::parser::{InterpreterAction, MidiBinding, Statement};
+use anyhow::{anyhow, Result};
+use midir::{MidiInput, MidiInputConnection};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+
+/// A decoded channel message from a MIDI controller
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MidiInputMessage {
+    /// Note On: channel (0-15), note, velocity (> 0)
+    NoteOn { channel: u8, note: u8, velocity: u8 },
+    /// Note Off (or Note On with velocity 0)
+    NoteOff { channel: u8, note: u8 },
+    /// Control Change: channel, controller number, value
+    ControlChange {
+        channel: u8,
+        controller: u8,
+        value: u8,
+    },
+}
+
+impl MidiInputMessage {
+    /// Decode raw MIDI bytes. Returns None for messages Cadence doesn't handle.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let (&status, data) = bytes.split_first()?;
+        let channel = status & 0x0F;
+        match (status & 0xF0, data) {
+            (0x90, [note, 0, ..]) | (0x80, [note, _, ..]) => Some(MidiInputMessage::NoteOff {
+                channel,
+                note: note & 0x7F,
+            }),
+            (0x90, [note, velocity, ..]) => Some(MidiInputMessage::NoteOn {
+                channel,
+                note: note & 0x7F,
+                velocity: velocity & 0x7F,
+            }),
+            (0xB0, [controller, value, ..]) => Some(MidiInputMessage::ControlChange {
+                channel,
+                controller: controller & 0x7F,
+                value: value & 0x7F,
+            }),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for MidiInputMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MidiInputMessage::NoteOn {
+                channel,
+                note,
+                velocity,
+            } => write!(f, "note on {} vel {} (ch {})", note, velocity, channel + 1),
+            MidiInputMessage::NoteOff { channel, note } => {
+                write!(f, "note off {} (ch {})", note, channel + 1)
+            }
+            MidiInputMessage::ControlChange {
+                channel,
+                controller,
+                value,
+            } => write!(f, "cc {} = {} (ch {})", controller, value, channel + 1),
+        }
+    }
+}
+
+/// What a bound message should do on the interpreter thread
+#[derive(Debug, Clone)]
+pub enum BindingEffect {
+    /// Run the statements of a `bind note` block
+    Run(Vec<Statement>),
+    /// Apply a parameter change (tempo/volume) from a `bind cc` mapping
+    Action(Box<InterpreterAction>),
+}
+
+/// Registered MIDI input bindings
+#[derive(Debug, Default)]
+pub struct MidiBindings {
+    notes: HashMap<u8, Vec<Statement>>,
+    /// Parameter, range and the track it was bound on, by controller number
+    controls: HashMap<u8, (String, i32, i32, usize)>,
+}
+
+impl MidiBindings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a binding made on `track_id`, replacing any existing binding
+    /// for the same note/CC
+    pub fn bind(&mut self, binding: MidiBinding, track_id: usize) {
+        match binding {
+            MidiBinding::Note { note, body } => {
+                self.notes.insert(note, body);
+            }
+            MidiBinding::Control {
+                controller,
+                parameter,
+                min,
+                max,
+            } => {
+                self.controls
+                    .insert(controller, (parameter, min, max, track_id));
+            }
+        }
+    }
+
+    /// Remove all bindings
+    pub fn clear(&mut self) {
+        self.notes.clear();
+        self.controls.clear();
+    }
+
+    /// Number of registered bindings
+    pub fn len(&self) -> usize {
+        self.notes.len() + self.controls.len()
+    }
+
+    /// Check if there are no bindings
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Translate an incoming message into its bound effect (None if unbound)
+    pub fn handle(&self, message: &MidiInputMessage) -> Option<BindingEffect> {
+        match message {
+            MidiInputMessage::NoteOn { note, .. } => {
+                self.notes.get(note).cloned().map(BindingEffect::Run)
+            }
+            MidiInputMessage::NoteOff { .. } => None,
+            MidiInputMessage::ControlChange {
+                controller, value, ..
+            } => {
+                let (parameter, min, max, track_id) = self.controls.get(controller)?;
+                let scaled = *min as f32 + (*max - *min) as f32 * (*value as f32 / 127.0);
+                let action = match parameter.as_str() {
+                    "tempo" => InterpreterAction::SetTempo(scaled.round()),
+                    "volume" => InterpreterAction::SetVolume {
+                        volume: (scaled / 100.0).clamp(0.0, 1.0),
+                        track_id: *track_id,
+                    },
+                    _ => return None,
+                };
+                Some(BindingEffect::Action(Box::new(action)))
+            }
+        }
+    }
+}
+
+/// Callback that receives decoded messages (called on the MIDI input thread)
+pub type MidiInputSink = Arc<dyn Fn(MidiInputMessage) + Send + Sync>;
+
+/// Handle to an optional MIDI input connection
+pub struct MidiInputHandle {
+    connection: Mutex<Option<MidiInputConnection<()>>>,
+    port_name: RwLock<Option<String>>,
+    /// Log unbound messages instead of ignoring them
+    monitor: AtomicBool,
+    sink: MidiInputSink,
+}
+
+impl MidiInputHandle {
+    /// Create a new (unconnected) input handle delivering messages to `sink`
+    pub fn new(sink: MidiInputSink) -> Self {
+        Self {
+            connection: Mutex::new(None),
+            port_name: RwLock::new(None),
+            monitor: AtomicBool::new(false),
+            sink,
+        }
+    }
+
+    /// List available MIDI input ports
+    pub fn list_ports(&self) -> Result<Vec<String>> {
+        let midi_in = MidiInput::new("Cadence-Input-Enumerator")
+            .map_err(|e| anyhow!("Failed to initialize MIDI input: {}", e))?;
+        Ok(midi_in
+            .ports()
+            .iter()
+            .filter_map(|p| midi_in.port_name(p).ok())
+            .collect())
+    }
+
+    /// Open an input port by index (as shown by `list_ports`, 0-based)
+    pub fn open(&self, index: usize) -> Result<String> {
+        let midi_in = MidiInput::new("Cadence-Input")
+            .map_err(|e| anyhow!("Failed to initialize MIDI input: {}", e))?;
+        let ports = midi_in.ports();
+        let port = ports
+            .get(index)
+            .ok_or_else(|| anyhow!("MIDI input port {} not found", index + 1))?;
+        let name = midi_in.port_name(port)?;
+
+        // Drop any existing connection first
+        self.close();
+
+        let sink = self.sink.clone();
+        let connection = midi_in
+            .connect(
+                port,
+                "cadence-in",
+                move |_stamp, bytes, _| {
+                    if let Some(message) = MidiInputMessage::from_bytes(bytes) {
+                        sink(message);
+                    }
+                },
+                (),
+            )
+            .map_err(|e| anyhow!("Failed to open MIDI input: {}", e))?;
+
+        *self.connection.lock().unwrap() = Some(connection);
+        *self.port_name.write().unwrap() = Some(name.clone());
+        Ok(name)
+    }
+
+    /// Close the input connection (if any)
+    pub fn close(&self) {
+        if let Some(connection) = self.connection.lock().unwrap().take() {
+            connection.close();
+        }
+        *self.port_name.write().unwrap() = None;
+    }
+
+    /// Name of the open input port
+    pub fn connected_port(&self) -> Option<String> {
+        self.port_name.read().unwrap().clone()
+    }
+
+    /// Enable or disable logging of unbound messages
+    pub fn set_monitor(&self, enabled: bool) {
+        self.monitor.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Whether unbound messages are logged
+    pub fn monitor(&self) -> bool {
+        self.monitor.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{parse_statements, Interpreter};
+
+    fn bindings_from(source: &str) -> MidiBindings {
+        let program = parse_statements(source).unwrap();
+        let mut interpreter = Interpreter::new();
+        interpreter.run_program(&program).unwrap();
+        let mut bindings = MidiBindings::new();
+        for action in interpreter.take_actions() {
+            if let InterpreterAction::Bind { binding, track_id } = action {
+                bindings.bind(binding, track_id);
+            }
+        }
+        bindings
+    }
+
+    #[test]
+    fn test_decode_messages() {
+        assert_eq!(
+            MidiInputMessage::from_bytes(&[0x91, 36, 100]),
+            Some(MidiInputMessage::NoteOn {
+                channel: 1,
+                note: 36,
+                velocity: 100
+            })
+        );
+        // Note On with velocity 0 is a Note Off
+        assert_eq!(
+            MidiInputMessage::from_bytes(&[0x90, 36, 0]),
+            Some(MidiInputMessage::NoteOff {
+                channel: 0,
+                note: 36
+            })
+        );
+        assert_eq!(
+            MidiInputMessage::from_bytes(&[0xB0, 1, 64]),
+            Some(MidiInputMessage::ControlChange {
+                channel: 0,
+                controller: 1,
+                value: 64
+            })
+        );
+        // Clock and other system messages are ignored
+        assert_eq!(MidiInputMessage::from_bytes(&[0xF8]), None);
+    }
+
+    #[test]
+    fn test_note_binding_runs_statements() {
+        let bindings = bindings_from("bind note 36 { tempo 140 }");
+        let effect = bindings.handle(&MidiInputMessage::NoteOn {
+            channel: 0,
+            note: 36,
+            velocity: 90,
+        });
+        match effect {
+            Some(BindingEffect::Run(body)) => {
+                assert_eq!(body.len(), 1);
+                assert!(matches!(body[0], Statement::Tempo(_)));
+            }
+            other => panic!("Expected Run effect, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_cc_binding_scales_tempo() {
+        let bindings = bindings_from("bind cc 1 to tempo range 60 180");
+
+        let at = |value| match bindings.handle(&MidiInputMessage::ControlChange {
+            channel: 0,
+            controller: 1,
+            value,
+        }) {
+            Some(BindingEffect::Action(action)) => match *action {
+                InterpreterAction::SetTempo(bpm) => bpm,
+                other => panic!("Expected SetTempo, got {:?}", other),
+            },
+            other => panic!("Expected SetTempo, got {:?}", other),
+        };
+
+        assert_eq!(at(0), 60.0);
+        assert_eq!(at(127), 180.0);
+        assert_eq!(at(64), 120.0);
+    }
+
+    #[test]
+    fn test_cc_volume_binding_targets_its_track() {
+        let bindings =
+            bindings_from("bind cc 7 to volume range 0 100\non 3 bind cc 8 to volume range 0 100");
+
+        let volume = |controller| match bindings.handle(&MidiInputMessage::ControlChange {
+            channel: 0,
+            controller,
+            value: 127,
+        }) {
+            Some(BindingEffect::Action(action)) => match *action {
+                InterpreterAction::SetVolume { volume, track_id } => (volume, track_id),
+                other => panic!("Expected SetVolume, got {:?}", other),
+            },
+            other => panic!("Expected SetVolume, got {:?}", other),
+        };
+
+        assert_eq!(volume(7), (1.0, 1));
+        assert_eq!(volume(8), (1.0, 3));
+    }
+
+    #[test]
+    fn test_unbound_input_is_ignored() {
+        let bindings = bindings_from("bind note 36 { stop }");
+        assert!(bindings
+            .handle(&MidiInputMessage::NoteOn {
+                channel: 0,
+                note: 37,
+                velocity: 100
+            })
+            .is_none());
+        assert!(bindings
+            .handle(&MidiInputMessage::NoteOff {
+                channel: 0,
+                note: 36
+            })
+            .is_none());
+        assert!(bindings
+            .handle(&MidiInputMessage::ControlChange {
+                channel: 0,
+                controller: 7,
+                value: 10
+            })
+            .is_none());
+    }
+}
@@ -0,0 +1,253 @@
+//! Voice mixing for the internal synth
+//!
+//! Turns the shared [`AudioState`] into samples: starts and fades oscillators
+//! as track notes change, sums melodic and drum voices with per-track volume
+//! and panning, runs tracks with a delay through their delay lines, and
+//! applies master volume. The cpal output stream and the
+//! headless renderer both mix through a [`Mixer`], so a render sounds like
+//! live playback.
+
+use std::collections::HashMap;
+
+use super::audio::AudioState;
+use super::delay::DelayLine;
+use super::drum_synth::DrumOscillator;
+use super::oscillator::EnvelopedOscillator;
+use crate::types::Waveform;
+
+/// Sounding voices and the per-track state they were started from
+pub struct Mixer {
+    sample_rate: f32,
+    oscillators: Vec<EnvelopedOscillator>,
+    drum_oscillators: Vec<DrumOscillator>,
+    /// Current frequencies per track, to detect note changes
+    track_frequencies: HashMap<usize, Vec<f32>>,
+    /// Current waveform per track, to detect changes
+    track_waveforms: HashMap<usize, Waveform>,
+    /// Delay line per track, kept until its echoes die away after the
+    /// track's delay is removed
+    delay_lines: HashMap<usize, DelayLine>,
+    master_amplitude: f32,
+    master_fade_rate: f32,
+}
+
+impl Mixer {
+    pub fn new(sample_rate: f32) -> Self {
+        Mixer {
+            sample_rate,
+            oscillators: Vec::new(),
+            drum_oscillators: Vec::new(),
+            track_frequencies: HashMap::new(),
+            track_waveforms: HashMap::new(),
+            delay_lines: HashMap::new(),
+            master_amplitude: 0.0,
+            // Master fade rate should match or exceed ADSR release time (200ms default)
+            // to allow envelopes to complete their release phase gracefully
+            master_fade_rate: 1.0 / (0.25 * sample_rate), // 250ms for smooth master fade
+        }
+    }
+
+    /// Fill `data`, interleaved with `channels` samples per frame, with the
+    /// next frames of audio for `state`
+    pub fn fill(&mut self, state: &mut AudioState, data: &mut [f32], channels: usize) {
+        let master_volume = state.volume;
+        let is_playing = state.is_playing;
+
+        // Panic: drop every voice without a release
+        if state.reset_pending {
+            self.oscillators.clear();
+            self.drum_oscillators.clear();
+            self.track_frequencies.clear();
+            self.delay_lines.clear();
+            state.reset_pending = false;
+        }
+
+        // Spawn drum oscillators for pending triggers
+        for (track_id, drum_sound) in state.pending_drums.drain(..) {
+            self.drum_oscillators
+                .push(DrumOscillator::new(drum_sound, self.sample_rate, track_id));
+        }
+
+        // 1. Sync oscillators and delay lines with state
+        // Check for changes in each track
+        for (track_id, track_state) in &mut state.tracks {
+            if let Some(settings) = track_state.delay {
+                match self.delay_lines.get_mut(track_id) {
+                    Some(line) => line.set_settings(settings),
+                    None => {
+                        self.delay_lines
+                            .insert(*track_id, DelayLine::new(settings, self.sample_rate));
+                    }
+                }
+            }
+
+            let current = self.track_frequencies.entry(*track_id).or_default();
+            let current_waveform = self.track_waveforms.entry(*track_id).or_default();
+
+            // If notes changed OR waveform changed OR retrigger requested for this track
+            let notes_changed = current.len() != track_state.notes.len()
+                || current
+                    .iter()
+                    .zip(track_state.notes.iter())
+                    .any(|(a, b)| (a - b).abs() > 0.01);
+            let waveform_changed = *current_waveform != track_state.waveform;
+
+            // Check if retrigger is requested
+            let needs_retrigger = track_state.retrigger;
+
+            if notes_changed || waveform_changed || needs_retrigger {
+                // Fade out old oscillators for this track
+                for osc in self
+                    .oscillators
+                    .iter_mut()
+                    .filter(|o| o.track_id == *track_id)
+                {
+                    osc.start_fade_out();
+                }
+
+                // Add new oscillators with track's envelope settings and
+                // any per-note pan
+                for (i, &freq) in track_state.notes.iter().enumerate() {
+                    let mut oscillator = EnvelopedOscillator::with_envelope(
+                        freq,
+                        self.sample_rate,
+                        *track_id,
+                        track_state.envelope,
+                        track_state.waveform,
+                    );
+                    oscillator.pan = track_state.note_pans.get(i).copied().flatten();
+                    self.oscillators.push(oscillator);
+                }
+
+                // Update cache
+                *current = track_state.notes.clone();
+                *current_waveform = track_state.waveform;
+
+                // Reset retrigger flag AFTER processing - this is the proper fix!
+                // Now trigger_note() can set it to true again for the next note.
+                track_state.retrigger = false;
+            }
+        }
+
+        // 2. Generate audio with stereo panning
+        for frame in data.chunks_mut(channels) {
+            if is_playing {
+                self.master_amplitude = (self.master_amplitude + self.master_fade_rate).min(1.0);
+            } else {
+                self.master_amplitude = (self.master_amplitude - self.master_fade_rate).max(0.0);
+            }
+
+            let mut left_mix = 0.0f32;
+            let mut right_mix = 0.0f32;
+            let mut active_count = 0;
+
+            // Sum all melodic oscillators with per-track panning. Tracks with
+            // a delay are sent to their delay line instead of the mix.
+            for oscillator in self.oscillators.iter_mut() {
+                let (track_vol, track_pan, delayed) = state
+                    .tracks
+                    .get(&oscillator.track_id)
+                    .map(|t| (t.volume, t.pan, t.delay.is_some()))
+                    .unwrap_or((1.0, 0.5, false));
+
+                let sample = oscillator.next_sample();
+                if sample.abs() > 0.0001 {
+                    // Equal-power panning: use sqrt for smooth stereo field
+                    let pan = oscillator.pan.unwrap_or(track_pan).clamp(0.0, 1.0);
+                    let left_gain = (1.0 - pan).sqrt();
+                    let right_gain = pan.sqrt();
+                    let (left, right) = (
+                        sample * track_vol * left_gain,
+                        sample * track_vol * right_gain,
+                    );
+
+                    match self.delay_lines.get_mut(&oscillator.track_id) {
+                        Some(line) if delayed => line.send(left, right),
+                        _ => {
+                            left_mix += left;
+                            right_mix += right;
+                        }
+                    }
+                    active_count += 1;
+                }
+            }
+
+            // Sum all drum oscillators (one-shot, with panning)
+            for drum_osc in self.drum_oscillators.iter_mut() {
+                let (track_vol, track_pan, delayed) = state
+                    .tracks
+                    .get(&drum_osc.track_id)
+                    .map(|t| (t.volume, t.pan, t.delay.is_some()))
+                    .unwrap_or((1.0, 0.5, false));
+
+                let sample = drum_osc.next_sample();
+                if sample.abs() > 0.0001 {
+                    let left_gain = (1.0 - track_pan).sqrt();
+                    let right_gain = track_pan.sqrt();
+                    let (left, right) = (
+                        sample * track_vol * left_gain,
+                        sample * track_vol * right_gain,
+                    );
+
+                    match self.delay_lines.get_mut(&drum_osc.track_id) {
+                        Some(line) if delayed => line.send(left, right),
+                        _ => {
+                            left_mix += left;
+                            right_mix += right;
+                        }
+                    }
+                    active_count += 1;
+                }
+            }
+
+            // Add each delay line's dry and echoed sound; lines keep ringing
+            // after their track's voices stop
+            for line in self.delay_lines.values_mut() {
+                let (left, right) = line.next_frame();
+                if left.abs() > 0.0001 || right.abs() > 0.0001 {
+                    left_mix += left;
+                    right_mix += right;
+                    active_count += 1;
+                }
+            }
+
+            // Apply headroom scaling
+            if active_count > 0 {
+                left_mix *= 0.3;
+                right_mix *= 0.3;
+            }
+
+            // Hard limiter
+            left_mix = left_mix.clamp(-1.0, 1.0);
+            right_mix = right_mix.clamp(-1.0, 1.0);
+
+            // Apply master volume and amplitude
+            left_mix *= master_volume * self.master_amplitude;
+            right_mix *= master_volume * self.master_amplitude;
+
+            // Write to output channels (stereo or mono)
+            if channels >= 2 {
+                frame[0] = left_mix;
+                frame[1] = right_mix;
+                // Fill remaining channels with center mix for surround
+                for sample in frame.iter_mut().skip(2) {
+                    *sample = (left_mix + right_mix) * 0.5;
+                }
+            } else {
+                // Mono output: use center mix
+                frame[0] = (left_mix + right_mix) * 0.5;
+            }
+        }
+
+        self.oscillators.retain(|osc| !osc.is_finished());
+        self.drum_oscillators.retain(|osc| !osc.is_finished());
+        // Drop a line once its track no longer has a delay and the tail has died away
+        self.delay_lines.retain(|track_id, line| {
+            state
+                .tracks
+                .get(track_id)
+                .is_some_and(|t| t.delay.is_some())
+                || !line.is_silent()
+        });
+    }
+}
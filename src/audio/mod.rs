@@ -1,10 +1,17 @@
 pub mod adsr;
+#[allow(clippy::module_inception)]
 pub mod audio;
 pub mod clock;
+pub mod delay;
 pub mod drum_synth;
 pub mod event_dispatcher;
+pub mod headless;
 pub mod midi;
+pub mod midi_clock;
+pub mod midi_input;
+pub mod mixer;
 pub mod oscillator;
+pub mod render;
 
 // Deprecated modules moved to _deprecated/ directory:
 // - playback_engine.rs (replaced by event_dispatcher)
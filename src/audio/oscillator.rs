@@ -16,6 +16,8 @@ pub struct EnvelopedOscillator {
     waveform: Waveform,
     /// Which track this oscillator belongs to
     pub track_id: usize,
+    /// Pan of this voice's note, overriding the track's pan
+    pub pan: Option<f32>,
 }
 
 impl EnvelopedOscillator {
@@ -47,6 +49,7 @@ impl EnvelopedOscillator {
             envelope,
             waveform,
             track_id,
+            pan: None,
         }
     }
 
@@ -154,7 +157,7 @@ mod tests {
         for _ in 0..1000 {
             let sample = osc.next_sample();
             assert!(
-                sample >= -1.0 && sample <= 1.0,
+                (-1.0..=1.0).contains(&sample),
                 "Sine out of range: {}",
                 sample
             );
@@ -167,7 +170,7 @@ mod tests {
         for _ in 0..1000 {
             let sample = osc.next_sample();
             assert!(
-                sample >= -1.0 && sample <= 1.0,
+                (-1.0..=1.0).contains(&sample),
                 "Saw out of range: {}",
                 sample
             );
@@ -181,7 +184,7 @@ mod tests {
         for _ in 0..1000 {
             let sample = osc.next_sample();
             assert!(
-                sample >= -1.0 && sample <= 1.0,
+                (-1.0..=1.0).contains(&sample),
                 "Square out of range: {}",
                 sample
             );
@@ -195,7 +198,7 @@ mod tests {
         for _ in 0..1000 {
             let sample = osc.next_sample();
             assert!(
-                sample >= -1.0 && sample <= 1.0,
+                (-1.0..=1.0).contains(&sample),
                 "Triangle out of range: {}",
                 sample
             );
@@ -0,0 +1,238 @@
+//! Offline rendering of patterns to WAV
+//!
+//! A [`PatternRender`] turns each track's playback events into the commands
+//! the dispatcher would send at the same beats, then applies them to an
+//! [`AudioState`] between [`Mixer`] fills, at the frame each falls on. There
+//! is no audio thread, clock or [`AudioPlayerHandle`], so a render runs in
+//! tests and on machines without a sound card, and sounds like live playback:
+//! notes ring on until the track's next note, as they do when looping.
+//!
+//! [`AudioPlayerHandle`]: super::audio::AudioPlayerHandle
+
+use anyhow::{anyhow, Result};
+use std::path::Path;
+
+use super::audio::{AudioPlayerCommand, AudioState};
+use super::delay::DelaySettings;
+use super::headless::{wav_bytes, CHANNELS, SAMPLE_RATE};
+use super::mixer::Mixer;
+use crate::types::{beats, to_f64, Pattern, Time};
+
+/// Bars are 4/4, as in MIDI export
+pub const BEATS_PER_BAR: i64 = 4;
+
+/// Patterns for a fixed number of beats, rendered offline at one tempo
+pub struct PatternRender {
+    bpm: f32,
+    beats: Time,
+    /// Commands with the frame they apply at, in the order they were added
+    commands: Vec<(usize, AudioPlayerCommand)>,
+}
+
+impl PatternRender {
+    /// Create an empty render of `bars` bars at `bpm`
+    pub fn new(bpm: f32, bars: usize) -> Self {
+        PatternRender {
+            bpm: bpm.max(1.0),
+            beats: beats(bars as i64 * BEATS_PER_BAR),
+            commands: vec![(0, AudioPlayerCommand::Play)],
+        }
+    }
+
+    /// Length of the render in frames
+    pub fn frames(&self) -> usize {
+        self.beat_to_frame(self.beats)
+    }
+
+    /// Add a track, one cycle at a time.
+    ///
+    /// `pattern_for_cycle` is called with each cycle number and its start beat,
+    /// so reactive or `every` patterns can produce a different pattern per cycle.
+    /// The pattern's waveform, envelope, pan and delay apply from the start
+    /// of its cycle.
+    pub fn add_track<F>(&mut self, track_id: usize, mut pattern_for_cycle: F) -> Result<()>
+    where
+        F: FnMut(usize, Time) -> Result<Pattern>,
+    {
+        let mut offset = beats(0);
+        let mut cycle = 0;
+
+        while offset < self.beats {
+            let pattern = pattern_for_cycle(cycle, offset)?;
+            if pattern.beats_per_cycle <= beats(0) {
+                break;
+            }
+
+            let frame = self.beat_to_frame(offset);
+            if let Some(envelope) = pattern.envelope {
+                self.push(
+                    frame,
+                    AudioPlayerCommand::SetTrackEnvelope(track_id, Some(envelope)),
+                );
+            }
+            if let Some(waveform) = pattern.waveform {
+                self.push(
+                    frame,
+                    AudioPlayerCommand::SetTrackWaveform(track_id, waveform),
+                );
+            }
+            if let Some(pan) = pattern.pan {
+                self.push(frame, AudioPlayerCommand::SetTrackPan(track_id, pan));
+            }
+            if let Some(delay) = &pattern.delay {
+                let settings = DelaySettings::from_delay(delay, self.bpm);
+                self.push(
+                    frame,
+                    AudioPlayerCommand::SetTrackDelay(track_id, Some(settings)),
+                );
+            }
+
+            for event in pattern.to_rich_events_for_cycle(cycle) {
+                let start = offset + event.start_beat;
+                if event.is_rest || start >= self.beats {
+                    continue;
+                }
+                let frame = self.beat_to_frame(start);
+
+                if !event.notes.is_empty() {
+                    let frequencies = event.notes.iter().map(|n| n.frequency).collect();
+                    // Notes only carry their own pan after jux()
+                    let command = if event.notes.iter().any(|n| n.pan.is_some()) {
+                        let pans = event.notes.iter().map(|n| n.pan).collect();
+                        AudioPlayerCommand::TriggerPannedNotes(track_id, frequencies, pans)
+                    } else {
+                        AudioPlayerCommand::TriggerNote(track_id, frequencies)
+                    };
+                    self.push(frame, command);
+                }
+                for drum in &event.drums {
+                    self.push(frame, AudioPlayerCommand::PlayDrum(track_id, *drum));
+                }
+            }
+
+            offset += pattern.beats_per_cycle;
+            cycle += 1;
+        }
+
+        Ok(())
+    }
+
+    /// Mix every track into interleaved stereo samples
+    pub fn render(mut self) -> Vec<f32> {
+        let frames = self.frames();
+        let mut state = AudioState::default();
+        let mut mixer = Mixer::new(SAMPLE_RATE as f32);
+        let mut samples = vec![0.0; frames * CHANNELS];
+
+        // Stable, so commands at the same frame keep the order they were added in
+        self.commands.sort_by_key(|(frame, _)| *frame);
+        let mut commands = self.commands.into_iter().peekable();
+        let mut rendered = 0;
+        while rendered < frames {
+            while let Some((_, command)) = commands.next_if(|(frame, _)| *frame <= rendered) {
+                state.apply(command);
+            }
+            let next = commands
+                .peek()
+                .map_or(frames, |(frame, _)| (*frame).min(frames));
+            mixer.fill(
+                &mut state,
+                &mut samples[rendered * CHANNELS..next * CHANNELS],
+                CHANNELS,
+            );
+            rendered = next;
+        }
+        samples
+    }
+
+    /// Render and write a 16-bit stereo WAV file
+    pub fn write_wav(self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let bytes = wav_bytes(&self.render(), SAMPLE_RATE, CHANNELS as u16);
+        std::fs::write(path, bytes)
+            .map_err(|e| anyhow!("Failed to write '{}': {}", path.display(), e))
+    }
+
+    fn push(&mut self, frame: usize, command: AudioPlayerCommand) {
+        self.commands.push((frame, command));
+    }
+
+    fn beat_to_frame(&self, beat: Time) -> usize {
+        (to_f64(beat) * 60.0 / self.bpm as f64 * SAMPLE_RATE as f64).round() as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(notation: &str, bpm: f32, bars: usize) -> Vec<f32> {
+        let pattern = Pattern::parse(notation).unwrap();
+        let mut render = PatternRender::new(bpm, bars);
+        render.add_track(1, |_, _| Ok(pattern.clone())).unwrap();
+        render.render()
+    }
+
+    #[test]
+    fn test_render_two_bars_to_wav() {
+        // Two bars at 120 BPM are 8 beats, or 4 seconds
+        let samples = render("C E G _", 120.0, 2);
+        let frames = 4 * SAMPLE_RATE as usize;
+        assert_eq!(samples.len(), frames * CHANNELS);
+        assert!(samples.iter().any(|s| s.abs() > 0.01));
+
+        let bytes = wav_bytes(&samples, SAMPLE_RATE, CHANNELS as u16);
+        assert_eq!(&bytes[0..4], b"RIFF");
+        assert_eq!(&bytes[8..16], b"WAVEfmt ");
+        assert_eq!(u16::from_le_bytes([bytes[20], bytes[21]]), 1); // PCM
+        assert_eq!(u16::from_le_bytes([bytes[22], bytes[23]]), 2);
+        assert_eq!(
+            u32::from_le_bytes(bytes[24..28].try_into().unwrap()),
+            SAMPLE_RATE
+        );
+        assert_eq!(u16::from_le_bytes([bytes[34], bytes[35]]), 16);
+        assert_eq!(&bytes[36..40], b"data");
+        let data_len = u32::from_le_bytes(bytes[40..44].try_into().unwrap()) as usize;
+        assert_eq!(data_len, frames * CHANNELS * 2);
+        assert_eq!(bytes.len(), 44 + data_len);
+    }
+
+    #[test]
+    fn test_render_follows_tempo() {
+        assert_eq!(
+            render("C E", 60.0, 1).len(),
+            4 * SAMPLE_RATE as usize * CHANNELS
+        );
+        assert_eq!(
+            render("C E", 240.0, 1).len(),
+            SAMPLE_RATE as usize * CHANNELS
+        );
+    }
+
+    #[test]
+    fn test_render_pattern_pan() {
+        let samples = render("C E G _", 120.0, 1);
+        let mut left = Pattern::parse("C E G _").unwrap();
+        left.pan = Some(0.0);
+        let mut render = PatternRender::new(120.0, 1);
+        render.add_track(1, |_, _| Ok(left.clone())).unwrap();
+        let panned = render.render();
+
+        // Centered notes sound in both channels; hard left only in the left
+        let right = |samples: &[f32]| samples.chunks(CHANNELS).any(|f| f[1].abs() > 0.01);
+        assert!(right(&samples));
+        assert!(!right(&panned));
+        assert!(panned.chunks(CHANNELS).any(|f| f[0].abs() > 0.01));
+    }
+
+    #[test]
+    fn test_render_is_silent_before_first_note() {
+        // Nothing sounds during the opening rest of "_ C"
+        let samples = render("_ C", 120.0, 1);
+        let half_beat = SAMPLE_RATE as usize / 4;
+        assert!(samples[..half_beat * CHANNELS].iter().all(|&s| s == 0.0));
+        assert!(samples[half_beat * CHANNELS..]
+            .iter()
+            .any(|s| s.abs() > 0.01));
+    }
+}
@@ -126,7 +126,7 @@ fn get_frequencies_from_value(value: &Value) -> anyhow::Result<Vec<f32>> {
             ));
         }
         Value::Boolean(_) => return Err(anyhow::anyhow!("Cannot play a boolean")),
-        Value::Number(_) => return Err(anyhow::anyhow!("Cannot play a number")),
+        Value::Number(_) | Value::Float(_) => return Err(anyhow::anyhow!("Cannot play a number")),
         Value::String(_) => return Err(anyhow::anyhow!("Cannot play a string")),
         Value::Function { name, .. } => {
             return Err(anyhow::anyhow!(
@@ -141,6 +141,11 @@ fn get_frequencies_from_value(value: &Value) -> anyhow::Result<Vec<f32>> {
                 "Cannot play an EveryPattern directly - use 'play X loop' for cycle-based alternation"
             ));
         }
+        Value::ProbabilityPattern(_) => {
+            return Err(anyhow::anyhow!(
+                "Cannot play a ProbabilityPattern directly - use 'play X loop' for cycle-based alternation"
+            ));
+        }
         Value::Thunk { .. } => {
             return Err(anyhow::anyhow!(
                 "Cannot play a thunk directly - it should have been evaluated"
@@ -1,6 +1,7 @@
 //! General REPL commands (help, quit, tempo)
 
-use crate::commands::{CommandContext, CommandResult};
+use crate::commands::{CommandContext, CommandResult, ResetScope};
+use cadence_core::parser::validator::check_source;
 use colored::*;
 
 /// Handle `help` command
@@ -41,6 +42,85 @@ pub fn cmd_watch(args: &str, _ctx: &mut CommandContext) -> CommandResult {
     CommandResult::Watch(args.to_string())
 }
 
+/// Handle `unwatch [file]` command
+pub fn cmd_unwatch(args: &str, _ctx: &mut CommandContext) -> CommandResult {
+    if args.is_empty() {
+        return CommandResult::Error("Usage: unwatch <file>".to_string());
+    }
+    CommandResult::Unwatch(args.to_string())
+}
+
+/// Handle `watches` command
+pub fn cmd_watches(_args: &str, _ctx: &mut CommandContext) -> CommandResult {
+    CommandResult::ListWatches
+}
+
+/// Handle `aliases` command
+pub fn cmd_aliases(_args: &str, _ctx: &mut CommandContext) -> CommandResult {
+    CommandResult::ListAliases
+}
+
+/// Handle `unalias <name>` command
+pub fn cmd_unalias(args: &str, _ctx: &mut CommandContext) -> CommandResult {
+    if args.is_empty() {
+        return CommandResult::Error("Usage: unalias <name>".to_string());
+    }
+    CommandResult::Unalias(args.to_string())
+}
+
+/// Handle `clear` command
+pub fn cmd_clear(_args: &str, _ctx: &mut CommandContext) -> CommandResult {
+    CommandResult::Clear
+}
+
+/// Handle `reset [tracks|vars]`: say what a reset would drop
+pub fn cmd_reset(args: &str, _ctx: &mut CommandContext) -> CommandResult {
+    reset(args, false)
+}
+
+/// Handle `reset! [tracks|vars]`: reset without asking
+pub fn cmd_force_reset(args: &str, _ctx: &mut CommandContext) -> CommandResult {
+    reset(args, true)
+}
+
+fn reset(args: &str, force: bool) -> CommandResult {
+    let scope = match args {
+        "" => ResetScope::All,
+        "tracks" => ResetScope::Tracks,
+        "vars" => ResetScope::Vars,
+        _ => return CommandResult::Error("Usage: reset[!] [tracks|vars]".to_string()),
+    };
+    CommandResult::Reset { scope, force }
+}
+
+/// Handle `check <file>`: report problems in a file without running it
+pub fn cmd_check(args: &str, _ctx: &mut CommandContext) -> CommandResult {
+    let path = args.trim_matches('"');
+    if path.is_empty() {
+        return CommandResult::Error("Usage: check <file>".to_string());
+    }
+    let source = match std::fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(e) => return CommandResult::Error(format!("Failed to read '{}': {}", path, e)),
+    };
+
+    let issues = check_source(&source);
+    if issues.is_empty() {
+        return CommandResult::Message(format!("✅ {} has no problems", path.bright_green()));
+    }
+    let mut output = format!(
+        "🔍 {}: {} problem{}",
+        path.bright_green(),
+        issues.len(),
+        if issues.len() == 1 { "" } else { "s" }
+    );
+    for issue in issues {
+        let location = format!("{}:{}", issue.span.line, issue.span.column);
+        output.push_str(&format!("\n  {:>6}  {}", location.dimmed(), issue.message));
+    }
+    CommandResult::Message(output)
+}
+
 /// Print help information
 fn print_help() {
     println!("{}", "🎵 Cadence Language Help".bold());
@@ -87,6 +167,10 @@ fn print_help() {
     println!();
     println!("{}", "Functions:".green());
     println!("  {}         - First inversion", "invert([C, E, G])".cyan());
+    println!(
+        "  {}    - Downward inversion (G drops below C)",
+        "invert_down([C, E, G])".cyan()
+    );
     println!(
         "  {}    - Nth inversion (D=2, so 2nd inversion)",
         "invert_n([C, E, G], D)".cyan()
@@ -189,6 +273,10 @@ fn print_help() {
     println!("  {}  - Set volume (0-100)", "audio volume <level>".cyan());
     println!("  {}        - Show current tempo", "tempo".cyan());
     println!("  {}    - Set tempo", "tempo <bpm>".cyan());
+    println!(
+        "  {}       - Transpose playback by n semitones",
+        "capo <n>".cyan()
+    );
     println!();
     println!("{}", "Queue Sync Modes:".green());
     println!(
@@ -207,22 +295,158 @@ fn print_help() {
     println!("  {}       - List MIDI output ports", "midi devices".cyan());
     println!("  {} - Connect to MIDI port", "midi connect <port>".cyan());
     println!("  {}    - Disconnect MIDI", "midi disconnect".cyan());
+    println!(
+        "  {} - Open a virtual port (default \"Cadence Out\")",
+        "midi out virtual [name]".cyan()
+    );
+    println!("  {}     - Close the virtual port", "midi out close".cyan());
     println!(
         "  {}     - Set channel (1-16 or 'auto')",
         "midi channel".cyan()
     );
-    println!("  {}        - Show MIDI status", "midi status".cyan());
-    println!("  {}         - All notes off (panic)", "midi panic".cyan());
+    println!(
+        "  {}        - Show MIDI status and track patches",
+        "midi status".cyan()
+    );
+    println!(
+        "  {}   - All notes/sound off everywhere",
+        "panic, midi panic".cyan()
+    );
+    println!(
+        "  {} - Send MIDI clock and start/stop",
+        "midi clock on|off".cyan()
+    );
+    println!(
+        "  {} - Send MIDI early to match audio",
+        "midi latency <ms> [track]".cyan()
+    );
+    println!(
+        "  {} - Synth pitch bend range for microtones",
+        "midi bend_range <n>".cyan()
+    );
+    println!(
+        "  {}  - One channel per note (MPE-lite)",
+        "midi mpe on|off".cyan()
+    );
     println!(
         "  {} - Set output (midi/audio/both)",
         "output <mode>".cyan()
     );
+    println!(
+        "  {}  - Program change on track 2",
+        "on 2 midi program 12".cyan()
+    );
+    println!(
+        "  {}   - Bank select (MSB LSB) on track 2",
+        "on 2 midi bank 1 0".cyan()
+    );
+    println!(
+        "  {} - Velocity curve (linear/soft/hard)",
+        "on 2 midi vel_curve soft".cyan()
+    );
+    println!("  {}       - List MIDI input ports", "midi in list".cyan());
+    println!(
+        "  {}   - Listen to a MIDI input port",
+        "midi in open <n>".cyan()
+    );
+    println!(
+        "  {}  - Log unbound MIDI input",
+        "midi monitor on|off".cyan()
+    );
+    println!(
+        "  {}  - Run statements on pad press",
+        "bind note 36 { ... }".cyan()
+    );
+    println!(
+        "  {} - Map a knob to tempo/volume",
+        "bind cc 1 to tempo range 60 180".cyan()
+    );
     println!();
     println!("{}", "Other Commands:".green());
     println!(
-        "  {}            - List active tracks",
+        "  {}            - List active tracks with their settings",
         "tracks".bright_green()
     );
+    println!(
+        "  {}     - List variables, optionally those starting with <prefix>",
+        "vars [prefix]".bright_green()
+    );
+    println!(
+        "  {}          - Remove a variable",
+        "unset <name>".bright_green()
+    );
+    println!(
+        "  {} - List function categories, or the functions in one",
+        "functions [category]".bright_green()
+    );
+    println!(
+        "  {}           - Show a function's signature and docs",
+        "help <name>".bright_green()
+    );
+    println!(
+        "  {}                  - Show every user function with its docs",
+        "docs".bright_green()
+    );
+    println!(
+        "  {} - Write looping tracks to a MIDI file",
+        "export midi <file> [bars]".bright_green()
+    );
+    println!(
+        "  {}  - Render looping tracks to a WAV file",
+        "export wav <file> [bars]".bright_green()
+    );
+    println!(
+        "  {} - Write looping tracks to a MusicXML score",
+        "export musicxml <file> [key]".bright_green()
+    );
+    println!(
+        "  {}           - Write the session as a runnable .cadence file",
+        "save <file>".bright_green()
+    );
+    println!(
+        "  {}          - Report problems in a file without playing it",
+        "check <file>".bright_green()
+    );
+    println!(
+        "  {}        - Blend changed loops into their new version",
+        "morph <cycles>".bright_green()
+    );
+    println!(
+        "  {} - Print chords as symbols (Cmaj7) or notes",
+        "chords symbols|notes".bright_green()
+    );
+    println!(
+        "  {}   - Stop tracks, clear variables and tempo (reset! to confirm)",
+        "reset [tracks|vars]".bright_green()
+    );
+    println!(
+        "  {}                 - Clear the screen",
+        "clear".bright_green()
+    );
+    println!(
+        "  {} - Make a command that runs others",
+        "alias \"name\" = \"cmds\"".bright_green()
+    );
+    println!(
+        "  {}               - List aliases",
+        "aliases".bright_green()
+    );
+    println!(
+        "  {}        - Remove an alias",
+        "unalias <name>".bright_green()
+    );
+    println!(
+        "  {}          - Reload a file whenever it is saved",
+        "watch <file>".bright_green()
+    );
+    println!(
+        "  {}        - Stop reloading a file",
+        "unwatch <file>".bright_green()
+    );
+    println!(
+        "  {}           - List watched files",
+        "watches".bright_green()
+    );
     println!("  {}              - Show this help", "help".bright_green());
     println!("  {}              - Exit the REPL", "quit".bright_red());
 }
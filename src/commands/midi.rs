@@ -1,6 +1,6 @@
 //! MIDI REPL commands
 
-use crate::audio::midi::{MidiChannelMode, OutputMode};
+use crate::audio::midi::{MidiChannelMode, OutputMode, DEFAULT_VIRTUAL_PORT_NAME};
 use crate::commands::{CommandContext, CommandResult};
 use colored::*;
 
@@ -65,6 +65,26 @@ pub fn cmd_midi_disconnect(_args: &str, ctx: &mut CommandContext) -> CommandResu
     }
 }
 
+/// Handle `midi out virtual [name]` command - publish a virtual MIDI source
+pub fn cmd_midi_out_virtual(args: &str, ctx: &mut CommandContext) -> CommandResult {
+    let name = if args.is_empty() {
+        DEFAULT_VIRTUAL_PORT_NAME
+    } else {
+        args
+    };
+
+    match &ctx.midi_handle {
+        Some(handle) => match handle.open_virtual(name) {
+            Ok(()) => CommandResult::Message(format!(
+                "🎹 Opened virtual MIDI port: {} (close with 'midi out close')",
+                name.green()
+            )),
+            Err(e) => CommandResult::Error(format!("Failed to open virtual port: {}", e)),
+        },
+        None => CommandResult::Error("MIDI output not initialized".to_string()),
+    }
+}
+
 /// Handle `midi channel <n>` command - set channel mode
 pub fn cmd_midi_channel(args: &str, ctx: &mut CommandContext) -> CommandResult {
     if args.is_empty() {
@@ -99,7 +119,7 @@ pub fn cmd_midi_channel(args: &str, ctx: &mut CommandContext) -> CommandResult {
                         .to_string(),
                 )
             } else if let Ok(ch) = channel_arg.parse::<u8>() {
-                if ch >= 1 && ch <= 16 {
+                if (1..=16).contains(&ch) {
                     handle.set_channel_mode(MidiChannelMode::Mono(ch - 1)); // Convert to 0-indexed
                     CommandResult::Message(
                         format!("🎹 MIDI channel mode: Mono (all tracks→Channel {})", ch)
@@ -135,7 +155,12 @@ pub fn cmd_midi_status(_args: &str, ctx: &mut CommandContext) -> CommandResult {
             if connected {
                 output.push_str(&format!("  Status: {}\n", "Connected".green().bold()));
                 if let Some(name) = port_name {
-                    output.push_str(&format!("  Port: {}\n", name.cyan()));
+                    let kind = if handle.is_virtual() {
+                        " (virtual)"
+                    } else {
+                        ""
+                    };
+                    output.push_str(&format!("  Port: {}{}\n", name.cyan(), kind));
                 }
             } else {
                 output.push_str(&format!("  Status: {}\n", "Not connected".yellow()));
@@ -146,6 +171,39 @@ pub fn cmd_midi_status(_args: &str, ctx: &mut CommandContext) -> CommandResult {
                 MidiChannelMode::Mono(ch) => format!("Mono (all → Channel {})", ch + 1),
             };
             output.push_str(&format!("  Channel mode: {}\n", mode_desc));
+            output.push_str(&format!(
+                "  Clock: {}\n",
+                if handle.clock_enabled() { "on" } else { "off" }
+            ));
+            output.push_str(&format!("  Latency: {} ms\n", handle.global_latency_ms()));
+            output.push_str(&format!(
+                "  Pitch bend: ±{} semitones, MPE {}\n",
+                handle.bend_range(),
+                if handle.mpe_enabled() { "on" } else { "off" }
+            ));
+
+            let patches = handle.track_patches();
+            if !patches.is_empty() {
+                output.push_str("  Tracks:\n");
+                for (track_id, patch) in patches {
+                    let program = patch.program.map_or("-".to_string(), |p| p.to_string());
+                    let bank = patch
+                        .bank
+                        .map_or("-".to_string(), |(msb, lsb)| format!("{} {}", msb, lsb));
+                    let latency = patch
+                        .latency_ms
+                        .map_or(String::new(), |ms| format!(", latency {} ms", ms));
+                    output.push_str(&format!(
+                        "    Track {}: channel {}, program {}, bank {}, velocity {}{}\n",
+                        track_id,
+                        handle.channel_for_track(track_id) + 1,
+                        program,
+                        bank,
+                        patch.velocity_curve,
+                        latency
+                    ));
+                }
+            }
 
             CommandResult::Message(output)
         }
@@ -153,18 +211,31 @@ pub fn cmd_midi_status(_args: &str, ctx: &mut CommandContext) -> CommandResult {
     }
 }
 
-/// Handle `midi panic` command - send All Notes Off to all channels
-pub fn cmd_midi_panic(_args: &str, ctx: &mut CommandContext) -> CommandResult {
-    match &ctx.midi_handle {
-        Some(handle) => match handle.panic_all() {
-            Ok(()) => CommandResult::Message(
-                "🎹 MIDI Panic: All Notes Off sent to all channels"
-                    .yellow()
-                    .to_string(),
-            ),
-            Err(e) => CommandResult::Error(format!("Failed to send MIDI panic: {}", e)),
-        },
-        None => CommandResult::Error("MIDI output not initialized".to_string()),
+/// Handle `panic` / `midi panic` - silence everything, MIDI and internal audio.
+///
+/// Releases held notes, sends All Notes Off and All Sound Off on all 16 MIDI
+/// channels, and hard-resets the audio engine's voices. Sent directly from
+/// here rather than through the dispatcher, so it works even if that is stuck.
+pub fn cmd_panic(_args: &str, ctx: &mut CommandContext) -> CommandResult {
+    let mut errors = Vec::new();
+
+    if let Err(e) = ctx.audio_handle.reset_voices() {
+        errors.push(format!("audio: {}", e));
+    }
+    if let Some(handle) = &ctx.midi_handle {
+        if let Err(e) = handle.panic_all() {
+            errors.push(format!("MIDI: {}", e));
+        }
+    }
+
+    if errors.is_empty() {
+        CommandResult::Message(
+            "🎹 Panic: all notes and sound off on every MIDI channel, audio voices reset"
+                .yellow()
+                .to_string(),
+        )
+    } else {
+        CommandResult::Error(format!("Panic incomplete ({})", errors.join(", ")))
     }
 }
 
@@ -193,7 +264,7 @@ pub fn cmd_midi_cc(args: &str, ctx: &mut CommandContext) -> CommandResult {
 
     let channel: u8 = if parts.len() >= 3 {
         match parts[2].parse::<u8>() {
-            Ok(ch) if ch >= 1 && ch <= 16 => ch - 1, // Convert to 0-indexed
+            Ok(ch) if (1..=16).contains(&ch) => ch - 1, // Convert to 0-indexed
             _ => return CommandResult::Error("Channel must be 1-16".to_string()),
         }
     } else {
@@ -276,6 +347,224 @@ pub fn cmd_midi_test(args: &str, ctx: &mut CommandContext) -> CommandResult {
     }
 }
 
+/// Handle `midi in list` command - list available MIDI input ports
+pub fn cmd_midi_in_list(_args: &str, ctx: &mut CommandContext) -> CommandResult {
+    match &ctx.midi_input {
+        Some(input) => match input.list_ports() {
+            Ok(ports) => {
+                if ports.is_empty() {
+                    CommandResult::Message("No MIDI input ports found.".yellow().to_string())
+                } else {
+                    let mut output = format!("{}\n", "🎛️  Available MIDI Input Ports:".bold());
+                    for (i, port) in ports.iter().enumerate() {
+                        output.push_str(&format!("  {}. {}\n", i + 1, port.cyan()));
+                    }
+                    output.push_str(&format!(
+                        "\n{} {}",
+                        "Use".dimmed(),
+                        "midi in open <n>".green()
+                    ));
+                    CommandResult::Message(output)
+                }
+            }
+            Err(e) => CommandResult::Error(format!("Failed to list MIDI inputs: {}", e)),
+        },
+        None => CommandResult::Error("MIDI input not initialized".to_string()),
+    }
+}
+
+/// Handle `midi in open <n>` command - open a MIDI input port by its list number
+pub fn cmd_midi_in_open(args: &str, ctx: &mut CommandContext) -> CommandResult {
+    let index = match args.parse::<usize>() {
+        Ok(n) if n >= 1 => n - 1,
+        _ => {
+            return CommandResult::Error(
+                "Usage: midi in open <n>\nUse 'midi in list' to see available ports".to_string(),
+            )
+        }
+    };
+
+    match &ctx.midi_input {
+        Some(input) => match input.open(index) {
+            Ok(name) => {
+                CommandResult::Message(format!("🎛️  Listening on MIDI input: {}", name.green()))
+            }
+            Err(e) => CommandResult::Error(format!("Failed to open MIDI input: {}", e)),
+        },
+        None => CommandResult::Error("MIDI input not initialized".to_string()),
+    }
+}
+
+/// Handle `midi in close` command
+pub fn cmd_midi_in_close(_args: &str, ctx: &mut CommandContext) -> CommandResult {
+    match &ctx.midi_input {
+        Some(input) => {
+            input.close();
+            CommandResult::Message("🎛️  Closed MIDI input".to_string())
+        }
+        None => CommandResult::Error("MIDI input not initialized".to_string()),
+    }
+}
+
+/// Handle `midi monitor <on|off>` command - log unbound MIDI input
+pub fn cmd_midi_monitor(args: &str, ctx: &mut CommandContext) -> CommandResult {
+    match &ctx.midi_input {
+        Some(input) => match args.to_lowercase().as_str() {
+            "on" => {
+                input.set_monitor(true);
+                CommandResult::Message("🎛️  MIDI monitor on".green().to_string())
+            }
+            "off" => {
+                input.set_monitor(false);
+                CommandResult::Message("🎛️  MIDI monitor off".to_string())
+            }
+            "" => CommandResult::Message(format!(
+                "🎛️  MIDI monitor is {}",
+                if input.monitor() { "on" } else { "off" }
+            )),
+            _ => CommandResult::Error("Usage: midi monitor <on|off>".to_string()),
+        },
+        None => CommandResult::Error("MIDI input not initialized".to_string()),
+    }
+}
+
+/// Handle `midi clock <on|off>` command - toggle MIDI clock and transport output
+pub fn cmd_midi_clock(args: &str, ctx: &mut CommandContext) -> CommandResult {
+    match &ctx.midi_handle {
+        Some(handle) => match args.to_lowercase().as_str() {
+            "on" => {
+                handle.set_clock_enabled(true);
+                CommandResult::Message(
+                    "🎹 MIDI clock on (24 PPQN, start/stop/continue)"
+                        .green()
+                        .to_string(),
+                )
+            }
+            "off" => {
+                handle.set_clock_enabled(false);
+                CommandResult::Message("🎹 MIDI clock off".to_string())
+            }
+            "" => CommandResult::Message(format!(
+                "🎹 MIDI clock is {}",
+                if handle.clock_enabled() { "on" } else { "off" }
+            )),
+            _ => CommandResult::Error("Usage: midi clock <on|off>".to_string()),
+        },
+        None => CommandResult::Error("MIDI output not initialized".to_string()),
+    }
+}
+
+/// Handle `midi bend_range <semitones>` command - set the synth's pitch bend range
+pub fn cmd_midi_bend_range(args: &str, ctx: &mut CommandContext) -> CommandResult {
+    match &ctx.midi_handle {
+        Some(handle) => {
+            if args.is_empty() {
+                return CommandResult::Message(format!(
+                    "🎹 MIDI pitch bend range: ±{} semitones",
+                    handle.bend_range()
+                ));
+            }
+            match args.parse::<u8>() {
+                Ok(semitones) if (1..=48).contains(&semitones) => {
+                    handle.set_bend_range(semitones);
+                    CommandResult::Message(
+                        format!("🎹 MIDI pitch bend range: ±{} semitones", semitones)
+                            .green()
+                            .to_string(),
+                    )
+                }
+                _ => CommandResult::Error(
+                    "Usage: midi bend_range <1-48> (must match the synth's bend range)".to_string(),
+                ),
+            }
+        }
+        None => CommandResult::Error("MIDI output not initialized".to_string()),
+    }
+}
+
+/// Handle `midi mpe <on|off>` command - toggle per-note channel rotation
+pub fn cmd_midi_mpe(args: &str, ctx: &mut CommandContext) -> CommandResult {
+    match &ctx.midi_handle {
+        Some(handle) => match args.to_lowercase().as_str() {
+            "on" => match handle.set_mpe_enabled(true) {
+                Ok(()) => CommandResult::Message(
+                    "🎹 MPE-lite on (each note on its own channel, 2-16)"
+                        .green()
+                        .to_string(),
+                ),
+                Err(e) => CommandResult::Error(format!("Failed to enable MPE: {}", e)),
+            },
+            "off" => match handle.set_mpe_enabled(false) {
+                Ok(()) => CommandResult::Message("🎹 MPE-lite off".to_string()),
+                Err(e) => CommandResult::Error(format!("Failed to disable MPE: {}", e)),
+            },
+            "" => CommandResult::Message(format!(
+                "🎹 MPE-lite is {}",
+                if handle.mpe_enabled() { "on" } else { "off" }
+            )),
+            _ => CommandResult::Error("Usage: midi mpe <on|off>".to_string()),
+        },
+        None => CommandResult::Error("MIDI output not initialized".to_string()),
+    }
+}
+
+/// Largest latency offset accepted by `midi latency`, in milliseconds
+const MAX_LATENCY_MS: i32 = 1000;
+
+/// Handle `midi latency [<ms> [track]|reset]` command - compensate for output latency.
+/// A positive offset sends MIDI that many milliseconds earlier than the internal audio;
+/// a track number makes it an override for that track only.
+pub fn cmd_midi_latency(args: &str, ctx: &mut CommandContext) -> CommandResult {
+    let handle = match &ctx.midi_handle {
+        Some(handle) => handle,
+        None => return CommandResult::Error("MIDI output not initialized".to_string()),
+    };
+
+    let parts: Vec<&str> = args.split_whitespace().collect();
+    match parts.as_slice() {
+        [] => CommandResult::Message(format!(
+            "🎹 MIDI latency: {} ms",
+            handle.global_latency_ms()
+        )),
+        ["reset"] => {
+            handle.reset_latency();
+            CommandResult::Message("🎹 MIDI latency reset to 0 ms".to_string())
+        }
+        [ms] | [ms, _] => {
+            let ms = match ms.trim_end_matches("ms").parse::<i32>() {
+                Ok(ms) if ms.abs() <= MAX_LATENCY_MS => ms,
+                _ => {
+                    return CommandResult::Error(format!(
+                        "Latency must be a whole number of milliseconds between -{} and {}",
+                        MAX_LATENCY_MS, MAX_LATENCY_MS
+                    ))
+                }
+            };
+
+            if let [_, track] = parts.as_slice() {
+                match track.parse::<usize>() {
+                    Ok(track_id) if track_id >= 1 => {
+                        handle.set_track_latency_ms(track_id, Some(ms));
+                        CommandResult::Message(
+                            format!("🎹 Track {} MIDI latency: {} ms", track_id, ms)
+                                .green()
+                                .to_string(),
+                        )
+                    }
+                    _ => CommandResult::Error("Track must be a number (1 or higher)".to_string()),
+                }
+            } else {
+                handle.set_latency_ms(ms);
+                CommandResult::Message(format!("🎹 MIDI latency: {} ms", ms).green().to_string())
+            }
+        }
+        _ => CommandResult::Error(
+            "Usage: midi latency [<ms> [track]|reset]\n  <ms>: Send MIDI this many milliseconds early\n  <ms> <track>: Override for one track"
+                .to_string(),
+        ),
+    }
+}
+
 /// Handle `output <mode>` command - set output mode (midi, audio, both)
 pub fn cmd_output_mode(args: &str, ctx: &mut CommandContext) -> CommandResult {
     let mode_arg = args.to_lowercase().trim().to_string();
@@ -9,7 +9,9 @@ pub mod midi;
 use crate::audio::audio::AudioPlayerHandle;
 use crate::audio::clock::MasterClock;
 use crate::audio::midi::MidiOutputHandle;
+use crate::audio::midi_input::MidiInputHandle;
 use crate::parser::{eval, Value};
+use std::collections::BTreeMap;
 use std::sync::Arc;
 
 /// Result of executing a command
@@ -27,6 +29,31 @@ pub enum CommandResult {
     Error(String),
     /// Watch a file for changes
     Watch(String),
+    /// Stop watching a file
+    Unwatch(String),
+    /// List the watched files
+    ListWatches,
+    /// Clear the terminal and show the banner again
+    Clear,
+    /// Reset the session; without `force`, only say what would be lost
+    Reset { scope: ResetScope, force: bool },
+    /// An alias was typed: run these lines as if they had been entered
+    Alias(Vec<String>),
+    /// List the aliases
+    ListAliases,
+    /// Remove an alias
+    Unalias(String),
+}
+
+/// What `reset` drops
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResetScope {
+    /// Tracks, variables and tempo
+    All,
+    /// Playback and track settings only
+    Tracks,
+    /// Variables and functions only
+    Vars,
 }
 
 /// Context passed to command handlers
@@ -34,6 +61,7 @@ pub struct CommandContext {
     pub audio_handle: Arc<AudioPlayerHandle>,
     pub clock: Arc<MasterClock>,
     pub midi_handle: Option<Arc<MidiOutputHandle>>,
+    pub midi_input: Option<Arc<MidiInputHandle>>,
 }
 
 impl CommandContext {
@@ -42,6 +70,7 @@ impl CommandContext {
             audio_handle,
             clock,
             midi_handle: None,
+            midi_input: None,
         }
     }
 
@@ -55,6 +84,7 @@ impl CommandContext {
             audio_handle,
             clock,
             midi_handle: Some(midi_handle),
+            midi_input: None,
         }
     }
 
@@ -72,6 +102,8 @@ pub struct CommandRegistry {
     /// Commands indexed by their prefix (e.g., "audio play progression")
     /// Sorted by prefix length descending for longest-match-first lookup
     commands: Vec<(String, CommandHandler)>,
+    /// User-defined aliases: name -> the commands it runs, separated by `;`
+    aliases: BTreeMap<String, String>,
 }
 
 impl CommandRegistry {
@@ -79,6 +111,7 @@ impl CommandRegistry {
     pub fn new() -> Self {
         Self {
             commands: Vec::new(),
+            aliases: BTreeMap::new(),
         }
     }
 
@@ -86,7 +119,8 @@ impl CommandRegistry {
     pub fn register(&mut self, prefix: &str, handler: CommandHandler) {
         self.commands.push((prefix.to_string(), handler));
         // Sort by prefix length descending for longest-match-first
-        self.commands.sort_by(|a, b| b.0.len().cmp(&a.0.len()));
+        self.commands
+            .sort_by_key(|(prefix, _)| std::cmp::Reverse(prefix.len()));
     }
 
     /// Execute a command, returning NotACommand if no match found
@@ -101,13 +135,91 @@ impl CommandRegistry {
                 return handler(args, ctx);
             }
         }
-        CommandResult::NotACommand
+        match self.aliases.get(input) {
+            Some(source) => CommandResult::Alias(split_commands(source)),
+            None => CommandResult::NotACommand,
+        }
     }
 
     /// Get all registered command prefixes
     pub fn list_commands(&self) -> Vec<&str> {
         self.commands.iter().map(|(p, _)| p.as_str()).collect()
     }
+
+    /// Add or replace an alias that runs `source` when `name` is typed.
+    /// Fails if `name` isn't a single word, is taken by a command, or if the
+    /// alias would end up running itself.
+    pub fn define_alias(&mut self, name: &str, source: &str) -> Result<(), String> {
+        if name.is_empty() || name.contains(char::is_whitespace) {
+            return Err(format!("Alias name '{}' must be a single word", name));
+        }
+        if self
+            .commands
+            .iter()
+            .any(|(prefix, _)| prefix.split(' ').next() == Some(name))
+        {
+            return Err(format!("'{}' is already a command", name));
+        }
+
+        // Follow the aliases it runs, looking for a way back to `name`
+        let mut pending = split_commands(source);
+        let mut seen = Vec::new();
+        while let Some(line) = pending.pop() {
+            if line == name {
+                return Err(format!("Alias '{}' would run itself", name));
+            }
+            if let Some(inner) = self.aliases.get(&line) {
+                if !seen.contains(&line) {
+                    pending.extend(split_commands(inner));
+                    seen.push(line);
+                }
+            }
+        }
+
+        self.aliases.insert(name.to_string(), source.to_string());
+        Ok(())
+    }
+
+    /// Remove an alias. Returns false if there was none by that name.
+    pub fn remove_alias(&mut self, name: &str) -> bool {
+        self.aliases.remove(name).is_some()
+    }
+
+    /// Aliases and their source, sorted by name
+    pub fn aliases(&self) -> &BTreeMap<String, String> {
+        &self.aliases
+    }
+}
+
+/// Split an alias into the lines it runs, at each `;` that isn't inside a
+/// string or a block
+pub fn split_commands(source: &str) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut current = String::new();
+    let mut depth = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+    for ch in source.chars() {
+        match ch {
+            _ if escaped => escaped = false,
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            '{' | '[' | '(' if !in_string => depth += 1,
+            '}' | ']' | ')' if !in_string => depth -= 1,
+            ';' if !in_string && depth == 0 => {
+                lines.push(std::mem::take(&mut current));
+                continue;
+            }
+            _ => {}
+        }
+        current.push(ch);
+    }
+    lines.push(current);
+    lines
+        .into_iter()
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty())
+        .collect()
 }
 
 impl Default for CommandRegistry {
@@ -130,19 +242,135 @@ pub fn create_registry() -> CommandRegistry {
     registry.register("midi devices", midi::cmd_midi_devices);
     registry.register("midi connect", midi::cmd_midi_connect);
     registry.register("midi disconnect", midi::cmd_midi_disconnect);
+    registry.register("midi out virtual", midi::cmd_midi_out_virtual);
+    registry.register("midi out close", midi::cmd_midi_disconnect);
     registry.register("midi channel", midi::cmd_midi_channel);
     registry.register("midi status", midi::cmd_midi_status);
-    registry.register("midi panic", midi::cmd_midi_panic);
+    registry.register("midi panic", midi::cmd_panic);
     registry.register("midi cc", midi::cmd_midi_cc);
+    registry.register("midi clock", midi::cmd_midi_clock);
+    registry.register("midi latency", midi::cmd_midi_latency);
+    registry.register("midi bend_range", midi::cmd_midi_bend_range);
+    registry.register("midi mpe", midi::cmd_midi_mpe);
     registry.register("midi test", midi::cmd_midi_test);
+    registry.register("midi in list", midi::cmd_midi_in_list);
+    registry.register("midi in open", midi::cmd_midi_in_open);
+    registry.register("midi in close", midi::cmd_midi_in_close);
+    registry.register("midi monitor", midi::cmd_midi_monitor);
     registry.register("output", midi::cmd_output_mode);
 
     // General commands
+    registry.register("panic", midi::cmd_panic);
     registry.register("tempo", general::cmd_tempo);
     registry.register("help", general::cmd_help);
     registry.register("quit", general::cmd_quit);
     registry.register("exit", general::cmd_quit);
     registry.register("watch", general::cmd_watch);
+    registry.register("unwatch", general::cmd_unwatch);
+    registry.register("watches", general::cmd_watches);
+    registry.register("check", general::cmd_check);
+    registry.register("aliases", general::cmd_aliases);
+    registry.register("unalias", general::cmd_unalias);
+    registry.register("clear", general::cmd_clear);
+    registry.register("reset", general::cmd_reset);
+    registry.register("reset!", general::cmd_force_reset);
 
     registry
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::audio::AudioPlayerHandle;
+
+    fn context() -> CommandContext {
+        let (audio, _) = AudioPlayerHandle::headless(44100.0);
+        CommandContext::new(Arc::new(audio), Arc::new(MasterClock::new(120.0)))
+    }
+
+    #[test]
+    fn test_alias() {
+        let mut registry = create_registry();
+        let mut ctx = context();
+        registry
+            .define_alias("drop", "stop 1; stop 2; on 3 play \"kick;snare\" loop")
+            .unwrap();
+
+        match registry.execute("drop", &mut ctx) {
+            CommandResult::Alias(lines) => assert_eq!(
+                lines,
+                vec!["stop 1", "stop 2", "on 3 play \"kick;snare\" loop"]
+            ),
+            other => panic!("Expected the alias to expand, got {:?}", other),
+        }
+        // Only the exact name runs it
+        assert!(matches!(
+            registry.execute("drop 2", &mut ctx),
+            CommandResult::NotACommand
+        ));
+
+        assert!(registry.remove_alias("drop"));
+        assert!(!registry.remove_alias("drop"));
+        assert!(matches!(
+            registry.execute("drop", &mut ctx),
+            CommandResult::NotACommand
+        ));
+    }
+
+    #[test]
+    fn test_alias_name_checks() {
+        let mut registry = create_registry();
+        assert!(registry.define_alias("tempo", "tempo 90").is_err());
+        assert!(registry.define_alias("midi", "midi panic").is_err());
+        assert!(registry.define_alias("two words", "stop").is_err());
+
+        // Aliases can run other aliases, but not end up back where they started
+        registry.define_alias("a", "stop 1; b").unwrap();
+        registry.define_alias("b", "stop 2").unwrap();
+        assert!(registry.define_alias("b", "a").is_err());
+        assert!(registry.define_alias("c", "c").is_err());
+        assert_eq!(registry.aliases().get("b").unwrap(), "stop 2");
+    }
+
+    #[test]
+    fn test_check() {
+        let registry = create_registry();
+        let mut ctx = context();
+        let dir = std::env::temp_dir().join(format!("cadence_check_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("set.cadence");
+        std::fs::write(&path, "tempo 120\nplay \"C [E\" loop\nplay nope() loop\n").unwrap();
+
+        let line = format!("check \"{}\"", path.display());
+        match registry.execute(&line, &mut ctx) {
+            CommandResult::Message(report) => {
+                assert!(report.contains("2 problems"), "{}", report);
+                assert!(report.contains("Unclosed bracket"), "{}", report);
+                assert!(report.contains("Unknown function: nope"), "{}", report);
+            }
+            other => panic!("Expected a report, got {:?}", other),
+        }
+        // Checking doesn't run anything
+        assert_eq!(ctx.clock.get_bpm(), 120.0);
+
+        std::fs::write(&path, "play \"C E G\" loop\n").unwrap();
+        match registry.execute(&line, &mut ctx) {
+            CommandResult::Message(report) => assert!(report.contains("no problems")),
+            other => panic!("Expected a report, got {:?}", other),
+        }
+        std::fs::remove_dir_all(&dir).ok();
+
+        assert!(matches!(
+            registry.execute("check", &mut ctx),
+            CommandResult::Error(_)
+        ));
+    }
+
+    #[test]
+    fn test_split_commands() {
+        assert_eq!(
+            split_commands("stop; repeat 2 { play C; play D };; tempo 90"),
+            vec!["stop", "repeat 2 { play C; play D }", "tempo 90"]
+        );
+    }
+}
@@ -0,0 +1,356 @@
+//! Playback engine shared by the REPL and `cadence run`
+//!
+//! Owns the audio and MIDI outputs, the master clock and the event
+//! dispatcher, and carries out the actions the interpreter collects while it
+//! runs a program.
+
+use crate::audio::audio::AudioPlayerHandle;
+use crate::audio::clock::MasterClock;
+use crate::audio::event_dispatcher::{DispatcherHandle, EventDispatcher, PatternId};
+use crate::audio::headless::{HeadlessAudio, SAMPLE_RATE};
+use crate::audio::midi::{MidiOutputHandle, VelocityCurve};
+use crate::audio::midi_clock::spawn_midi_clock;
+use crate::audio::midi_input::MidiBindings;
+use crate::parser::{Expression, InterpreterAction, SharedEnvironment, Value};
+use crate::types::{QueueMode, ScheduledEvent};
+use colored::*;
+use std::collections::HashMap;
+use std::sync::atomic::AtomicU64;
+use std::sync::Arc;
+
+/// Tempo of a fresh session
+pub const DEFAULT_BPM: f32 = 90.0;
+
+/// Outputs, clock and dispatcher, plus what each track is playing
+pub struct Engine {
+    pub audio_handle: Arc<AudioPlayerHandle>,
+    pub midi_handle: Arc<MidiOutputHandle>,
+    pub clock: Arc<MasterClock>,
+    /// Shared BPM as atomic for playback engines
+    pub bpm: Arc<AtomicU64>,
+    /// Unified event dispatcher (handles both one-shot and looping playback)
+    pub dispatcher_handle: DispatcherHandle,
+    /// Track which pattern IDs are active per track (for stopping)
+    pub active_patterns: HashMap<usize, PatternId>,
+    /// Looping expression per track (for offline rendering with `export midi`)
+    pub track_expressions: HashMap<usize, Expression>,
+    /// Input line that started each track (shown by `tracks`)
+    pub track_sources: HashMap<usize, String>,
+    /// Bindings from `bind` statements
+    pub midi_bindings: MidiBindings,
+    /// Rendering offline: the dispatcher is driven by the caller, so the
+    /// master clock is never started
+    headless: bool,
+}
+
+impl Engine {
+    /// Engine for live playback: the internal synth plays through the sound
+    /// card and the master clock drives the dispatcher and MIDI clock
+    pub fn new(midi_handle: Arc<MidiOutputHandle>) -> Self {
+        let audio_handle =
+            Arc::new(AudioPlayerHandle::new().expect("Failed to create audio player"));
+        let clock = Arc::new(MasterClock::new(DEFAULT_BPM));
+
+        // Spawn the unified event dispatcher (replaces Scheduler + PlaybackEngines)
+        let dispatcher_tick_rx = clock.subscribe();
+        let dispatcher_handle = EventDispatcher::spawn(
+            audio_handle.clone(),
+            dispatcher_tick_rx,
+            Some(midi_handle.clone()),
+        );
+
+        // MIDI clock output (sent only while `midi clock on`)
+        spawn_midi_clock(clock.subscribe(), midi_handle.clone());
+
+        Self::with_parts(audio_handle, midi_handle, clock, dispatcher_handle, false)
+    }
+
+    /// Engine for offline rendering. The caller feeds ticks to the returned
+    /// dispatcher and pulls samples from the returned audio output.
+    pub fn headless(midi_handle: Arc<MidiOutputHandle>) -> (Self, EventDispatcher, HeadlessAudio) {
+        let (audio_handle, output) = AudioPlayerHandle::headless(SAMPLE_RATE as f32);
+        let audio_handle = Arc::new(audio_handle);
+        let clock = Arc::new(MasterClock::new(DEFAULT_BPM));
+        let (dispatcher, dispatcher_handle) = EventDispatcher::headless(audio_handle.clone());
+        let engine = Self::with_parts(audio_handle, midi_handle, clock, dispatcher_handle, true);
+        (engine, dispatcher, output)
+    }
+
+    fn with_parts(
+        audio_handle: Arc<AudioPlayerHandle>,
+        midi_handle: Arc<MidiOutputHandle>,
+        clock: Arc<MasterClock>,
+        dispatcher_handle: DispatcherHandle,
+        headless: bool,
+    ) -> Self {
+        Engine {
+            audio_handle,
+            midi_handle,
+            bpm: Arc::new(AtomicU64::new(clock.get_bpm().to_bits() as u64)),
+            clock,
+            dispatcher_handle,
+            active_patterns: HashMap::new(),
+            track_expressions: HashMap::new(),
+            track_sources: HashMap::new(),
+            midi_bindings: MidiBindings::new(),
+            headless,
+        }
+    }
+
+    /// Convert a Value to frequencies for one-shot playback
+    fn value_to_frequencies(value: &Value) -> Option<(Vec<f32>, Vec<crate::types::DrumSound>)> {
+        match value {
+            Value::Note(note) => Some((vec![note.frequency()], vec![])),
+            Value::Chord(chord) => {
+                let freqs: Vec<f32> = chord.notes_vec().iter().map(|n| n.frequency()).collect();
+                Some((freqs, vec![]))
+            }
+            Value::Pattern(pattern) => {
+                // For immediate play, get the first event
+                let events = pattern.to_rich_events();
+                if let Some(first) = events.first() {
+                    let freqs: Vec<f32> = first.notes.iter().map(|n| n.frequency).collect();
+                    Some((freqs, first.drums.clone()))
+                } else {
+                    Some((vec![], vec![]))
+                }
+            }
+            Value::String(s) => {
+                if let Ok(pattern) = crate::types::Pattern::parse(s) {
+                    let events = pattern.to_rich_events();
+                    if let Some(first) = events.first() {
+                        let freqs: Vec<f32> = first.notes.iter().map(|n| n.frequency).collect();
+                        Some((freqs, first.drums.clone()))
+                    } else {
+                        Some((vec![], vec![]))
+                    }
+                } else {
+                    None
+                }
+            }
+            Value::EveryPattern(every) => {
+                // For immediate play, use base pattern's first event
+                let events = every.base.to_rich_events();
+                if let Some(first) = events.first() {
+                    let freqs: Vec<f32> = first.notes.iter().map(|n| n.frequency).collect();
+                    Some((freqs, first.drums.clone()))
+                } else {
+                    Some((vec![], vec![]))
+                }
+            }
+            Value::ProbabilityPattern(sometimes) => {
+                let events = sometimes.base.to_rich_events();
+                if let Some(first) = events.first() {
+                    let freqs: Vec<f32> = first.notes.iter().map(|n| n.frequency).collect();
+                    Some((freqs, first.drums.clone()))
+                } else {
+                    Some((vec![], vec![]))
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Execute an interpreter action (triggers actual audio/state changes).
+    /// Looping patterns are evaluated in `env` on every step.
+    pub fn execute_action(&mut self, action: InterpreterAction, env: SharedEnvironment) {
+        match action {
+            InterpreterAction::PlayExpression {
+                expression,
+                looping,
+                queue_mode,
+                track_id,
+                display_value,
+                source,
+                scheduled_beat,
+            } => {
+                // Ensure the clock is running before starting playback
+                self.start_clock();
+
+                // A one-shot doesn't replace the description of a loop on the same track
+                if looping || !self.track_expressions.contains_key(&track_id) {
+                    self.track_sources.insert(track_id, source);
+                }
+
+                // Extract envelope and waveform from the pattern if present
+                #[allow(clippy::type_complexity)]
+                let pattern_props: Option<(
+                    Option<(f32, f32, f32, f32)>,
+                    Option<crate::types::Waveform>,
+                )> = match &display_value {
+                    Value::Pattern(pattern) => Some((pattern.envelope, pattern.waveform)),
+                    Value::EveryPattern(every) => Some((every.base.envelope, every.base.waveform)),
+                    Value::ProbabilityPattern(sometimes) => {
+                        Some((sometimes.base.envelope, sometimes.base.waveform))
+                    }
+                    _ => None,
+                };
+
+                if let Some((envelope, waveform)) = pattern_props {
+                    if let Some(env) = envelope {
+                        self.dispatcher_handle
+                            .set_track_envelope(track_id, Some(env));
+                    }
+                    if let Some(wf) = waveform {
+                        self.dispatcher_handle.set_track_waveform(track_id, wf);
+                    }
+                }
+
+                if looping {
+                    self.track_expressions.insert(track_id, expression.clone());
+
+                    // A loop placed after a `wait` starts that many beats from now
+                    let queue_mode = queue_mode.or_else(|| {
+                        scheduled_beat.map(|beat| QueueMode::Beats(beat.round() as u32))
+                    });
+                    if let Some(mode) = queue_mode {
+                        // Queue the pattern for activation at the next musical boundary
+                        let pattern_id = self
+                            .dispatcher_handle
+                            .queue_loop(expression, env, track_id, mode);
+                        // Note: Don't add to active_patterns yet - will be added when activated
+                        println!(
+                            "🎵 Queued {} (Track {}) - will start on {:?}",
+                            display_value, track_id, mode
+                        );
+                        // Still track it for stopping purposes
+                        self.active_patterns.insert(track_id, pattern_id);
+                    } else {
+                        // Immediate start (no queue mode)
+                        let pattern_id =
+                            self.dispatcher_handle.start_loop(expression, env, track_id);
+                        self.active_patterns.insert(track_id, pattern_id);
+                        println!(
+                            "🔊 Playing {} (Track {}) - live reactive!",
+                            display_value, track_id
+                        );
+                    }
+                } else {
+                    // For one-shot plays, trigger immediately
+                    if let Some((freqs, drums)) = Self::value_to_frequencies(&display_value) {
+                        self.dispatcher_handle
+                            .trigger_immediate(track_id, freqs, drums);
+                    } else {
+                        println!("{} Cannot play this value", "Playback error:".red());
+                    }
+                }
+            }
+            InterpreterAction::SetTempo(bpm) => {
+                self.set_tempo(bpm);
+                // Also start the clock if not already running
+                self.start_clock();
+                // Already printed by interpreter
+            }
+            InterpreterAction::SetCapo(semitones) => {
+                self.dispatcher_handle.set_capo(semitones);
+            }
+            InterpreterAction::SetVolume { volume, track_id } => {
+                self.dispatcher_handle.set_track_volume(track_id, volume);
+            }
+            InterpreterAction::SetWaveform { waveform, track_id } => {
+                // Parse waveform name and set it on the audio handle
+                use crate::types::Waveform;
+                if let Some(wf) = Waveform::from_name(&waveform) {
+                    self.dispatcher_handle.set_track_waveform(track_id, wf);
+                } else {
+                    println!(
+                        "{} Unknown waveform: {} (Track {})",
+                        "Waveform error:".red(),
+                        waveform,
+                        track_id
+                    );
+                }
+            }
+            InterpreterAction::ControlChange {
+                controller,
+                expression,
+                smooth,
+                track_id,
+            } => {
+                if !self.midi_handle.is_connected() {
+                    println!(
+                        "{} CC {} will only be sent once a MIDI port is connected",
+                        "Note:".yellow(),
+                        controller
+                    );
+                }
+                self.start_clock();
+                self.dispatcher_handle
+                    .start_control(expression, env, track_id, controller, smooth);
+            }
+            InterpreterAction::MidiProgram { program, track_id } => {
+                if let Err(e) = self.midi_handle.set_track_program(track_id, program) {
+                    println!("{} {}", "MIDI error:".red(), e);
+                }
+            }
+            InterpreterAction::MidiBank { msb, lsb, track_id } => {
+                if let Err(e) = self.midi_handle.set_track_bank(track_id, msb, lsb) {
+                    println!("{} {}", "MIDI error:".red(), e);
+                }
+            }
+            InterpreterAction::MidiVelocityCurve { curve, track_id } => {
+                match curve.parse::<VelocityCurve>() {
+                    Ok(curve) => self.midi_handle.set_track_velocity_curve(track_id, curve),
+                    Err(e) => println!("{} {}", "MIDI error:".red(), e),
+                }
+            }
+            InterpreterAction::Bind { binding, track_id } => {
+                self.midi_bindings.bind(binding, track_id);
+            }
+            // Registered by the REPL; a script run on its own has no commands
+            InterpreterAction::DefineAlias { .. } => {}
+            InterpreterAction::Stop { track_id } => match track_id {
+                Some(id) => {
+                    self.dispatcher_handle.stop_track(id);
+                    self.active_patterns.remove(&id);
+                    self.track_expressions.remove(&id);
+                    self.track_sources.remove(&id);
+                }
+                None => self.stop_all(),
+            },
+        }
+    }
+
+    /// Stop all playback and forget what every track was playing
+    pub fn stop_all(&mut self) {
+        self.dispatcher_handle.stop_all();
+        let _ = self.midi_handle.transport_stop();
+        self.active_patterns.clear();
+        self.track_expressions.clear();
+        self.track_sources.clear();
+    }
+
+    /// Stop all playback and put every track's settings back to the defaults
+    pub fn clear_tracks(&mut self) {
+        self.stop_all();
+        self.dispatcher_handle.reset_track_properties();
+    }
+
+    /// Set the tempo of the master clock, without starting it
+    pub fn set_tempo(&self, bpm: f32) {
+        self.clock.set_bpm(bpm);
+        self.bpm
+            .store(bpm.to_bits() as u64, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    /// Send scheduled events (from `wait` and friends) to the dispatcher,
+    /// relative to the current beat
+    pub fn schedule(&self, events: Vec<ScheduledEvent>) {
+        if events.is_empty() {
+            return;
+        }
+        let base_beat = self.clock.current_beat();
+        self.dispatcher_handle.schedule(events, base_beat);
+        // Start the clock if not already running
+        self.start_clock();
+    }
+
+    /// Start the master clock and tell external MIDI gear that playback is running
+    pub fn start_clock(&self) {
+        if self.headless {
+            return;
+        }
+        self.clock.start();
+        let _ = self.midi_handle.transport_play();
+    }
+}
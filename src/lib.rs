@@ -15,16 +15,21 @@
 //!   the Cadence expression language. It also includes the evaluator responsible
 //!   for interpreting expressions.
 //! - `repl`: Provides the Read-Eval-Print Loop for interactive use of the Cadence language.
+//! - `engine`: Audio and MIDI outputs, clock and dispatcher, driven by the
+//!   actions a program collects. Shared by the REPL and `cadence run`.
+//! - `run`: The non-interactive `cadence run <file>` command.
 //! - `types`: Defines the core data structures for musical concepts like notes,
 //!   chords, progressions, and Roman numerals, along with their associated
 //!   logic and operations.
 
 pub mod audio;
 pub mod commands;
+pub mod engine;
 pub mod parser;
 pub mod repl;
+pub mod run;
 pub mod types;
 
 // Re-export commonly used types and functions for convenience
-pub use crate::parser::{Expression, Value, eval};
+pub use crate::parser::{eval, Expression, Value};
 pub use crate::types::{Chord, CommonProgressions, Note, Pattern, RomanNumeral, VoiceLeading};
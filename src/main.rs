@@ -1,7 +1,10 @@
 use anyhow::Result;
-use cadence::repl;
+use cadence::{repl, run};
 
 fn main() -> Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("run") {
+        std::process::exit(run::main(&args[1..]));
+    }
     repl::start()
-    // Ok(())
 }
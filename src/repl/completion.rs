@@ -0,0 +1,346 @@
+//! Tab completion for the REPL editor
+//!
+//! Words complete from builtins, keywords, and the variables defined so far.
+//! File paths complete inside the string after `load`, `use ... from` or
+//! `save`, and after the `watch`, `unwatch` and `save` commands.
+//!
+//! The editor runs on the input thread, so the main loop shares the
+//! interpreter's variable names through a [`SharedNames`] snapshot. The same
+//! helper colors the line as it's typed, see [`super::highlight`].
+
+use crate::repl::highlight::LineHighlighter;
+use cadence_core::parser::builtins::get_registry;
+use colored::control::SHOULD_COLORIZE;
+use rustyline::completion::Completer;
+use rustyline::highlight::{CmdKind, Highlighter};
+use rustyline::hint::Hinter;
+use rustyline::validate::Validator;
+use rustyline::{Context, Helper};
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+
+/// Language keywords offered outside of strings
+pub const KEYWORDS: &[&str] = &[
+    "as", "break", "continue", "else", "false", "fn", "for", "from", "if", "in", "let", "load",
+    "loop", "on", "play", "queue", "repeat", "return", "stop", "tempo", "track", "true", "use",
+    "volume", "wait", "waveform", "while",
+];
+
+/// Words whose string argument is a file path
+const PATH_KEYWORDS: &[&str] = &["load", "use", "from", "watch", "save", "check"];
+
+/// REPL commands that take an unquoted file path
+const PATH_COMMANDS: &[&str] = &["watch ", "unwatch ", "save ", "check "];
+
+/// Variable names from the interpreter, refreshed by the main loop after each input
+pub type SharedNames = Arc<RwLock<Vec<String>>>;
+
+/// Editor helper that completes and highlights Cadence input
+pub struct CadenceHelper {
+    variables: SharedNames,
+    /// Names of the user's functions, which can also be called as methods
+    functions: SharedNames,
+    highlighter: LineHighlighter,
+    /// Whether to emphasize the bracket at the cursor; off for the final redraw
+    show_brackets: Cell<bool>,
+}
+
+impl CadenceHelper {
+    pub fn new(variables: SharedNames, functions: SharedNames) -> Self {
+        Self {
+            variables,
+            functions,
+            highlighter: LineHighlighter::new(),
+            show_brackets: Cell::new(false),
+        }
+    }
+}
+
+impl Completer for CadenceHelper {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        let variables = self.variables.read().map(|v| v.clone()).unwrap_or_default();
+        let functions = self.functions.read().map(|f| f.clone()).unwrap_or_default();
+        Ok(candidates(line, pos, &variables, &functions))
+    }
+}
+
+impl Hinter for CadenceHelper {
+    type Hint = String;
+}
+
+impl Highlighter for CadenceHelper {
+    fn highlight<'l>(&self, line: &'l str, pos: usize) -> Cow<'l, str> {
+        if line.is_empty() || !SHOULD_COLORIZE.should_colorize() {
+            return Cow::Borrowed(line);
+        }
+        let variables = self.variables.read().map(|v| v.clone()).unwrap_or_default();
+        let cursor = self.show_brackets.get().then_some(pos);
+        Cow::Owned(self.highlighter.highlight(line, cursor, &variables))
+    }
+
+    fn highlight_char(&self, _line: &str, _pos: usize, kind: CmdKind) -> bool {
+        // Colors change as words are typed, so redraw on every edit
+        let redraw = kind != CmdKind::ForcedRefresh;
+        self.show_brackets.set(redraw);
+        redraw
+    }
+}
+
+impl Validator for CadenceHelper {}
+
+impl Helper for CadenceHelper {}
+
+/// Completions for the word ending at `pos`.
+///
+/// Returns where the replaced text starts and the sorted candidates. After a
+/// `.` only builtins and the user's `functions` are offered, since a method
+/// call is a function called on a value.
+pub fn candidates(
+    line: &str,
+    pos: usize,
+    variables: &[String],
+    functions: &[String],
+) -> (usize, Vec<String>) {
+    let before = &line[..pos];
+    let indent = before.len() - before.trim_start().len();
+    for command in PATH_COMMANDS {
+        if let Some(arg) = before[indent..].strip_prefix(command) {
+            if !arg.starts_with('"') {
+                return complete_path(before, indent + command.len());
+            }
+        }
+    }
+
+    match cursor_context(before) {
+        CursorContext::Comment => (pos, Vec::new()),
+        CursorContext::String(quote) => {
+            let preceding = before[..quote].split_whitespace().next_back();
+            if preceding.is_some_and(|word| PATH_KEYWORDS.contains(&word)) {
+                complete_path(before, quote + 1)
+            } else {
+                (pos, Vec::new())
+            }
+        }
+        CursorContext::Code => {
+            let start = before
+                .rfind(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .map_or(0, |i| i + 1);
+            let prefix = &before[start..];
+            if prefix.is_empty() {
+                return (pos, Vec::new());
+            }
+
+            let docs = get_registry().get_documentation();
+            let mut words: Vec<String> = if before[..start].ends_with('.') {
+                // Keywords are documented alongside builtins but can't be called
+                docs.into_iter()
+                    .filter(|d| d.category != "Keyword")
+                    .map(|d| d.name)
+                    .chain(functions.iter().cloned())
+                    .collect()
+            } else {
+                docs.into_iter()
+                    .map(|d| d.name)
+                    .chain(KEYWORDS.iter().map(|k| k.to_string()))
+                    .chain(variables.iter().filter(|v| !v.starts_with('_')).cloned())
+                    .collect()
+            };
+            words.retain(|word| word.starts_with(prefix));
+            words.sort();
+            words.dedup();
+            (start, words)
+        }
+    }
+}
+
+enum CursorContext {
+    Code,
+    /// Inside a string opened at this byte offset
+    String(usize),
+    Comment,
+}
+
+/// Whether the end of `text` is in code, inside a string, or in a comment
+fn cursor_context(text: &str) -> CursorContext {
+    let mut chars = text.char_indices().peekable();
+    let mut in_comment = false;
+    while let Some((i, ch)) = chars.next() {
+        if in_comment {
+            if ch == '*' && chars.peek().map(|&(_, c)| c) == Some('/') {
+                chars.next();
+                in_comment = false;
+            }
+            continue;
+        }
+        match ch {
+            '"' => {
+                let mut closed = false;
+                while let Some((_, c)) = chars.next() {
+                    match c {
+                        '\\' => {
+                            chars.next();
+                        }
+                        '"' => {
+                            closed = true;
+                            break;
+                        }
+                        _ => {}
+                    }
+                }
+                if !closed {
+                    return CursorContext::String(i);
+                }
+            }
+            '/' => match chars.peek().map(|&(_, c)| c) {
+                Some('/') => return CursorContext::Comment,
+                Some('*') => {
+                    chars.next();
+                    in_comment = true;
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+    if in_comment {
+        CursorContext::Comment
+    } else {
+        CursorContext::Code
+    }
+}
+
+/// Complete the path typed from `start` to the end of `before`. Directories
+/// end in `/` so completion can continue into them.
+fn complete_path(before: &str, start: usize) -> (usize, Vec<String>) {
+    let typed = &before[start..];
+    let (dir, file_prefix) = match typed.rfind('/') {
+        Some(i) => (&typed[..=i], &typed[i + 1..]),
+        None => ("", typed),
+    };
+    let search_dir = if dir.is_empty() {
+        Path::new(".")
+    } else {
+        Path::new(dir)
+    };
+
+    let Ok(entries) = std::fs::read_dir(search_dir) else {
+        return (start, Vec::new());
+    };
+    let mut paths: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            // Hidden files only when asked for
+            if !name.starts_with(file_prefix)
+                || (name.starts_with('.') && !file_prefix.starts_with('.'))
+            {
+                return None;
+            }
+            let is_dir = entry.file_type().is_ok_and(|t| t.is_dir());
+            Some(format!("{}{}{}", dir, name, if is_dir { "/" } else { "" }))
+        })
+        .collect();
+    paths.sort();
+    (start, paths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn complete(line: &str) -> Vec<String> {
+        candidates(
+            line,
+            line.len(),
+            &["riff".to_string(), "_beat".to_string()],
+            &["octave_up".to_string()],
+        )
+        .1
+    }
+
+    #[test]
+    fn test_completes_builtins_keywords_and_variables() {
+        assert_eq!(
+            candidates("play smooth_voice_le", 20, &[], &[]),
+            (5, vec!["smooth_voice_leading".to_string()])
+        );
+        assert!(complete("re").contains(&"repeat".to_string()));
+        assert!(complete("re").contains(&"return".to_string()));
+        assert_eq!(complete("play ri"), vec!["riff"]);
+        // Internal variables stay hidden
+        assert!(complete("_b").is_empty());
+    }
+
+    #[test]
+    fn test_completes_word_at_cursor() {
+        // Only the text before the cursor counts
+        let line = "let x = inv([C, E, G])";
+        let (start, words) = candidates(line, 11, &[], &[]);
+        assert_eq!(start, 8);
+        assert!(words.contains(&"invert".to_string()));
+        assert_eq!(candidates(line, 4, &[], &[]).1, Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_methods_complete_functions_only() {
+        let words = complete("riff.re");
+        assert!(words.contains(&"rev".to_string()));
+        assert!(!words.contains(&"repeat".to_string()));
+        assert_eq!(complete("riff.octave_"), vec!["octave_up"]);
+    }
+
+    #[test]
+    fn test_no_words_inside_strings_or_comments() {
+        assert!(complete("play \"C E G re").is_empty());
+        assert!(complete("play C // re").is_empty());
+        assert!(complete("play C /* re").is_empty());
+        // A closed string doesn't count
+        assert_eq!(complete("play \"C E\" + ri"), vec!["riff"]);
+    }
+
+    #[test]
+    fn test_completes_paths_after_file_keywords() {
+        let dir = std::env::temp_dir().join(format!("cadence_complete_{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("songs")).unwrap();
+        std::fs::write(dir.join("verse.cadence"), "").unwrap();
+        std::fs::write(dir.join(".hidden"), "").unwrap();
+        let base = format!("{}/", dir.display());
+
+        let line = format!("load \"{}", base);
+        let (start, paths) = candidates(&line, line.len(), &[], &[]);
+        let in_use = complete(&format!("use lead from \"{}v", base));
+        let in_play = complete(&format!("play \"{}v", base));
+        std::fs::remove_dir_all(&dir).ok();
+
+        assert_eq!(start, 6);
+        assert_eq!(
+            paths,
+            vec![format!("{}songs/", base), format!("{}verse.cadence", base)]
+        );
+        assert_eq!(in_use, vec![format!("{}verse.cadence", base)]);
+        assert!(in_play.is_empty());
+    }
+
+    #[test]
+    fn test_completes_unquoted_paths_after_commands() {
+        let dir = std::env::temp_dir().join(format!("cadence_watch_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("live.cadence"), "").unwrap();
+
+        let line = format!("watch {}/li", dir.display());
+        let (start, paths) = candidates(&line, line.len(), &[], &[]);
+        std::fs::remove_dir_all(&dir).ok();
+
+        assert_eq!(start, 6);
+        assert_eq!(paths, vec![format!("{}/live.cadence", dir.display())]);
+    }
+}
@@ -0,0 +1,198 @@
+//! Text for the `help <name>`, `functions` and `docs` commands
+
+use crate::repl::tracks::truncate;
+use cadence_core::parser::builtins::DocItem;
+use cadence_core::parser::symbols::FunctionSymbol;
+use std::collections::BTreeMap;
+
+/// Category user functions are listed under
+pub const USER_CATEGORY: &str = "User";
+
+/// Longest description shown in a `functions <category>` listing
+const MAX_SUMMARY_WIDTH: usize = 60;
+
+/// Shown for a user function with no `///` lines above it
+const NO_DOCUMENTATION: &str = "(no documentation)";
+
+/// A user function's doc comment, indented, one line per doc line
+fn push_doc_lines(output: &mut String, func: &FunctionSymbol) {
+    let doc = func.doc_comment.as_deref().unwrap_or(NO_DOCUMENTATION);
+    for line in doc.lines() {
+        output.push_str(format!("  {}", line).trim_end());
+        output.push('\n');
+    }
+}
+
+/// Help for the user function or builtin called `name`. User functions come
+/// first, since defining one hides the builtin of the same name.
+pub fn format_help(name: &str, builtins: &[DocItem], user: &[FunctionSymbol]) -> Option<String> {
+    if let Some(func) = user.iter().find(|f| f.name == name) {
+        let mut output = format!("{}  ({})\n", func.signature(), USER_CATEGORY);
+        push_doc_lines(&mut output, func);
+        return Some(output);
+    }
+
+    let doc = builtins.iter().find(|d| d.name == name)?;
+    let mut output = format!("{}  ({})\n", doc.signature, doc.category);
+    output.push_str(&format!("  {}\n", doc.description));
+    Some(output)
+}
+
+/// Every user function with its signature and whole doc comment
+pub fn format_docs(user: &[FunctionSymbol]) -> String {
+    if user.is_empty() {
+        return "No user functions. Document one with /// lines above its fn.".to_string();
+    }
+    let mut output = format!("📚 User functions ({}):\n", user.len());
+    for func in user {
+        output.push_str(&format!("{}\n", func.signature()));
+        push_doc_lines(&mut output, func);
+    }
+    output
+}
+
+/// With no category, the categories and how many functions each has.
+/// With one (any case), each of its functions with a one-line summary.
+pub fn format_functions(category: &str, builtins: &[DocItem], user: &[FunctionSymbol]) -> String {
+    // (signature, summary) for each function, by category
+    let mut categories: BTreeMap<&str, Vec<(String, String)>> = BTreeMap::new();
+    for doc in builtins {
+        categories
+            .entry(doc.category.as_str())
+            .or_default()
+            .push((doc.signature.clone(), doc.description.clone()));
+    }
+    for func in user {
+        let summary = func
+            .doc_comment
+            .as_deref()
+            .and_then(|doc| doc.lines().next())
+            .unwrap_or_default();
+        categories
+            .entry(USER_CATEGORY)
+            .or_default()
+            .push((func.signature(), summary.to_string()));
+    }
+
+    if category.is_empty() {
+        let width = categories
+            .keys()
+            .map(|c| c.chars().count())
+            .max()
+            .unwrap_or(0);
+        let mut output = String::from("📚 Function categories:\n");
+        for (name, functions) in &categories {
+            output.push_str(&format!("  {:width$}  {}\n", name, functions.len()));
+        }
+        output.push_str("Use `functions <category>` to list them and `help <name>` for details.");
+        return output;
+    }
+
+    let Some((name, functions)) = categories
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(category))
+    else {
+        let names: Vec<&str> = categories.keys().copied().collect();
+        return format!(
+            "No category '{}'. Categories: {}",
+            category,
+            names.join(", ")
+        );
+    };
+
+    let width = functions
+        .iter()
+        .map(|(signature, _)| signature.chars().count())
+        .max()
+        .unwrap_or(0);
+    let mut output = format!("📚 {} functions ({}):\n", name, functions.len());
+    for (signature, summary) in functions {
+        let line = format!(
+            "  {:width$}  {}",
+            signature,
+            truncate(summary, MAX_SUMMARY_WIDTH)
+        );
+        output.push_str(line.trim_end());
+        output.push('\n');
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{parse_statements, Interpreter};
+    use cadence_core::parser::builtins::get_registry;
+
+    fn documented_session() -> Interpreter {
+        let mut interpreter = Interpreter::new();
+        let program = parse_statements(
+            "/// Raises a pattern by an octave.\n/// Handy for doubling a bass line.\nfn up(p) -> Pattern {\n  return p + 12\n}\nfn plain(a, b) {\n  return a\n}",
+        )
+        .unwrap();
+        interpreter.run_program(&program).unwrap();
+        interpreter
+    }
+
+    #[test]
+    fn test_help_for_user_function() {
+        let user = documented_session().user_functions();
+        let builtins = get_registry().get_documentation();
+
+        assert_eq!(
+            format_help("up", &builtins, &user).unwrap(),
+            "fn up(p) -> Pattern  (User)\n  Raises a pattern by an octave.\n  Handy for doubling a bass line.\n"
+        );
+        assert_eq!(
+            format_help("plain", &builtins, &user).unwrap(),
+            "fn plain(a, b)  (User)\n  (no documentation)\n"
+        );
+        assert!(format_help("nothing", &builtins, &user).is_none());
+    }
+
+    #[test]
+    fn test_docs_listing() {
+        let user = documented_session().user_functions();
+        assert_eq!(
+            user[1].doc_comment.as_deref(),
+            Some("Raises a pattern by an octave.\nHandy for doubling a bass line.")
+        );
+        assert_eq!(
+            format_docs(&user),
+            "📚 User functions (2):\nfn plain(a, b)\n  (no documentation)\nfn up(p) -> Pattern\n  Raises a pattern by an octave.\n  Handy for doubling a bass line.\n"
+        );
+        assert!(format_docs(&[]).starts_with("No user functions"));
+    }
+
+    #[test]
+    fn test_help_for_builtin() {
+        let builtins = get_registry().get_documentation();
+        let help = format_help("fast", &builtins, &[]).unwrap();
+        assert!(help.starts_with(
+            "fast(pattern: Pattern, factor: Number | Note | String) -> Pattern  (Pattern)\n"
+        ));
+        assert!(help.contains("Speeds up a pattern"));
+    }
+
+    #[test]
+    fn test_functions_listing() {
+        let user = documented_session().user_functions();
+        let builtins = get_registry().get_documentation();
+
+        let overview = format_functions("", &builtins, &user);
+        assert!(overview
+            .lines()
+            .any(|line| line.starts_with("  User") && line.ends_with(" 2")));
+
+        let listing = format_functions("user", &builtins, &user);
+        let lines: Vec<&str> = listing.lines().collect();
+        assert_eq!(lines[0], "📚 User functions (2):");
+        assert_eq!(lines[1], "  fn plain(a, b)");
+        assert_eq!(
+            lines[2],
+            "  fn up(p) -> Pattern  Raises a pattern by an octave."
+        );
+
+        assert!(format_functions("nope", &builtins, &user).starts_with("No category 'nope'"));
+    }
+}
@@ -0,0 +1,422 @@
+//! Syntax highlighting for the REPL editor
+//!
+//! Lines are split by [`tokenize_for_highlighting`], the same tokenizer the web
+//! editor colors with, and each token is colored by kind. Identifiers that
+//! aren't builtins, keywords or defined variables get their own color so typos
+//! show before the line runs, and the bracket matching the one at the cursor is
+//! emphasized.
+//!
+//! Highlighting runs on every keystroke, so the classified tokens of the last
+//! line are cached by its hash. Moving the cursor only redoes the brackets.
+
+use crate::commands::create_registry;
+use crate::repl::completion::KEYWORDS;
+use cadence_core::parser::builtins::get_registry;
+use cadence_core::tokenize_for_highlighting;
+use colored::Color;
+use std::cell::RefCell;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::ops::Range;
+
+/// REPL commands handled by the main loop rather than the command registry
+pub(crate) const LOOP_COMMANDS: &[&str] = &[
+    "tracks",
+    "vars",
+    "unset",
+    "functions",
+    "save",
+    "morph",
+    "chords",
+    "export midi",
+    "export wav",
+    "export musicxml",
+];
+
+/// Identifiers the statement parser reads as part of a statement, like the
+/// `midi program` in `track 1 midi program 33`
+const CONTEXT_WORDS: &[&str] = &[
+    "bank",
+    "bar",
+    "beat",
+    "bind",
+    "capo",
+    "cc",
+    "cycle",
+    "duration",
+    "hard",
+    "linear",
+    "midi",
+    "note",
+    "program",
+    "range",
+    "smooth",
+    "soft",
+    "to",
+    "vel_curve",
+];
+
+/// What a piece of the line is, which decides its color
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Keyword,
+    Note,
+    String,
+    Number,
+    Comment,
+    /// A builtin or a variable
+    Name,
+    /// An identifier nothing defines
+    Unknown,
+    /// Operators and punctuation
+    Plain,
+}
+
+impl Kind {
+    fn color(self) -> Option<Color> {
+        match self {
+            Kind::Keyword => Some(Color::Magenta),
+            Kind::Note => Some(Color::Cyan),
+            Kind::String => Some(Color::Green),
+            Kind::Number => Some(Color::Yellow),
+            Kind::Comment => Some(Color::BrightBlack),
+            Kind::Name => Some(Color::Blue),
+            Kind::Unknown => Some(Color::Red),
+            Kind::Plain => None,
+        }
+    }
+}
+
+/// A classified piece of the line, by byte range
+#[derive(Debug, Clone)]
+struct Token {
+    range: Range<usize>,
+    kind: Kind,
+}
+
+/// Colors REPL input, remembering the tokens of the last line it saw
+pub struct LineHighlighter {
+    commands: Vec<String>,
+    cache: RefCell<Option<(u64, Vec<Token>)>>,
+}
+
+impl LineHighlighter {
+    pub fn new() -> Self {
+        let mut commands: Vec<String> = create_registry()
+            .list_commands()
+            .into_iter()
+            .chain(LOOP_COMMANDS.iter().copied())
+            // `tempo 120` reads the same either way; leave it to the tokenizer
+            .filter(|command| !KEYWORDS.contains(&command.split(' ').next().unwrap_or("")))
+            .map(String::from)
+            .collect();
+        commands.sort_by_key(|command| std::cmp::Reverse(command.len()));
+        Self {
+            commands,
+            cache: RefCell::new(None),
+        }
+    }
+
+    /// `line` with ANSI colors. When `cursor` is next to a bracket, it and
+    /// its partner are emphasized.
+    pub fn highlight(&self, line: &str, cursor: Option<usize>, variables: &[String]) -> String {
+        let mut hasher = DefaultHasher::new();
+        line.hash(&mut hasher);
+        variables.hash(&mut hasher);
+        let key = hasher.finish();
+
+        let mut cache = self.cache.borrow_mut();
+        let tokens = match cache.as_ref() {
+            Some((cached, tokens)) if *cached == key => tokens,
+            _ => {
+                let tokens = self.classify(line, variables);
+                &cache.insert((key, tokens)).1
+            }
+        };
+        let brackets = cursor
+            .and_then(|pos| matching_brackets(line, tokens, pos))
+            .unwrap_or_default();
+        render(line, tokens, &brackets)
+    }
+
+    fn classify(&self, line: &str, variables: &[String]) -> Vec<Token> {
+        let indent = line.len() - line.trim_start().len();
+        let body = &line[indent..];
+        for command in &self.commands {
+            let Some(rest) = body.strip_prefix(command.as_str()) else {
+                continue;
+            };
+            // `save = 1` assigns to a variable called save
+            if rest.is_empty() || (rest.starts_with(' ') && !rest.trim_start().starts_with('=')) {
+                let end = indent + command.len();
+                return vec![
+                    Token {
+                        range: indent..end,
+                        kind: Kind::Keyword,
+                    },
+                    Token {
+                        range: end..line.len(),
+                        kind: Kind::Plain,
+                    },
+                ];
+            }
+        }
+        classify_code(line, variables)
+    }
+}
+
+impl Default for LineHighlighter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Classify each token of a line of Cadence code
+fn classify_code(line: &str, variables: &[String]) -> Vec<Token> {
+    let mut spans = tokenize_for_highlighting(line);
+    if spans.is_empty() && !line.trim().is_empty() {
+        // Most likely a string that hasn't been closed yet
+        spans = tokenize_for_highlighting(&format!("{}\"", line));
+    }
+
+    let offsets = utf16_to_byte(line);
+    let byte = |utf16: usize| offsets.get(utf16).copied().unwrap_or(line.len());
+    let declared = declared_names(&spans);
+    let registry = get_registry();
+
+    let mut tokens = Vec::with_capacity(spans.len());
+    let mut after_dot = false;
+    for span in &spans {
+        let kind = match span.token_type.as_str() {
+            "keyword" | "keyword.control" => Kind::Keyword,
+            "constant.note" => Kind::Note,
+            "string" => Kind::String,
+            "constant.numeric" | "constant.boolean" => Kind::Number,
+            "comment" => Kind::Comment,
+            "variable" => {
+                let name = span.text.as_str();
+                let known = if after_dot {
+                    registry.get(name).is_some()
+                } else {
+                    registry.get(name).is_some()
+                        || variables.iter().any(|v| v == name)
+                        || declared.contains(&name)
+                };
+                if known {
+                    Kind::Name
+                } else if !after_dot && CONTEXT_WORDS.contains(&name) {
+                    Kind::Keyword
+                } else {
+                    Kind::Unknown
+                }
+            }
+            _ => Kind::Plain,
+        };
+        after_dot = span.text == ".";
+
+        let start = byte(span.utf16_start);
+        let end = byte(span.utf16_start + span.utf16_len).min(line.len());
+        if start < end {
+            tokens.push(Token {
+                range: start..end,
+                kind,
+            });
+        }
+    }
+    tokens
+}
+
+/// Names the line itself defines: after `let`, `for`, `as` and `fn`, function
+/// parameters, and the names imported by `use`
+fn declared_names(spans: &[cadence_core::HighlightSpan]) -> Vec<&str> {
+    let mut names = Vec::new();
+    let mut previous = "";
+    let mut in_params = false;
+    let mut in_use = false;
+    for (i, span) in spans.iter().enumerate() {
+        let text = span.text.as_str();
+        match text {
+            "use" => in_use = true,
+            "from" => in_use = false,
+            "(" if i >= 2 && spans[i - 2].text == "fn" => in_params = true,
+            ")" => in_params = false,
+            _ => {}
+        }
+        if span.token_type == "variable"
+            && (in_params || in_use || matches!(previous, "let" | "for" | "as" | "fn"))
+        {
+            names.push(text);
+        }
+        previous = text;
+    }
+    names
+}
+
+/// Byte offset of each UTF-16 offset in `line`
+fn utf16_to_byte(line: &str) -> Vec<usize> {
+    let mut offsets = Vec::with_capacity(line.len() + 1);
+    for (i, ch) in line.char_indices() {
+        for _ in 0..ch.len_utf16() {
+            offsets.push(i);
+        }
+    }
+    offsets.push(line.len());
+    offsets
+}
+
+/// The bracket at or just before `cursor` and its partner, if both are there
+fn matching_brackets(line: &str, tokens: &[Token], cursor: usize) -> Option<Vec<usize>> {
+    let brackets: Vec<(usize, u8)> = tokens
+        .iter()
+        .filter(|t| t.kind == Kind::Plain)
+        .flat_map(|t| {
+            line.as_bytes()[t.range.clone()]
+                .iter()
+                .enumerate()
+                .map(move |(i, b)| (t.range.start + i, *b))
+        })
+        .filter(|(_, b)| b"()[]{}".contains(b))
+        .collect();
+
+    let index = brackets
+        .iter()
+        .position(|&(at, _)| at == cursor)
+        .or_else(|| brackets.iter().position(|&(at, _)| at + 1 == cursor))?;
+    let (at, bracket) = brackets[index];
+
+    let (partner, forward) = match bracket {
+        b'(' => (b')', true),
+        b'[' => (b']', true),
+        b'{' => (b'}', true),
+        b')' => (b'(', false),
+        b']' => (b'[', false),
+        _ => (b'{', false),
+    };
+    let mut depth = 0;
+    let candidates: Box<dyn Iterator<Item = &(usize, u8)>> = if forward {
+        Box::new(brackets[index + 1..].iter())
+    } else {
+        Box::new(brackets[..index].iter().rev())
+    };
+    for &(other, b) in candidates {
+        if b == bracket {
+            depth += 1;
+        } else if b == partner {
+            if depth == 0 {
+                return Some(vec![at, other]);
+            }
+            depth -= 1;
+        }
+    }
+    None
+}
+
+/// Write out the line with each token wrapped in its color
+fn render(line: &str, tokens: &[Token], brackets: &[usize]) -> String {
+    let mut output = String::with_capacity(line.len() * 2);
+    let mut cursor = 0;
+    for token in tokens {
+        if token.range.start < cursor {
+            continue;
+        }
+        gap(&mut output, &line[cursor..token.range.start]);
+        let text = &line[token.range.clone()];
+        if token.kind == Kind::Plain && brackets.iter().any(|b| token.range.contains(b)) {
+            // `[[` is a single token, so emphasize just the bracket that matches
+            let mut rest = token.range.start;
+            for &at in brackets.iter().filter(|b| token.range.contains(b)) {
+                output.push_str(&line[rest..at]);
+                output.push_str(&paint(&line[at..at + 1], "1;4"));
+                rest = at + 1;
+            }
+            output.push_str(&line[rest..token.range.end]);
+        } else {
+            match token.kind.color() {
+                Some(color) => output.push_str(&paint(text, &color.to_fg_str())),
+                None => output.push_str(text),
+            }
+        }
+        cursor = token.range.end;
+    }
+    gap(&mut output, &line[cursor..]);
+    output
+}
+
+/// Text between tokens is whitespace, except for `/* */` comments
+fn gap(output: &mut String, text: &str) {
+    if text.trim().is_empty() {
+        output.push_str(text);
+    } else {
+        output.push_str(&paint(text, &Color::BrightBlack.to_fg_str()));
+    }
+}
+
+fn paint(text: &str, codes: &str) -> String {
+    format!("\x1b[{}m{}\x1b[0m", codes, text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn highlight(line: &str, cursor: Option<usize>) -> String {
+        LineHighlighter::new().highlight(line, cursor, &["riff".to_string()])
+    }
+
+    #[test]
+    fn test_colors_each_kind_of_token() {
+        assert_eq!(
+            highlight("let x = [C, E, G] + 2 // up", None),
+            "\x1b[35mlet\x1b[0m \x1b[34mx\x1b[0m = [\x1b[36mC\x1b[0m, \x1b[36mE\x1b[0m, \
+             \x1b[36mG\x1b[0m] + \x1b[33m2\x1b[0m \x1b[90m// up\x1b[0m"
+        );
+        assert_eq!(
+            highlight("play \"C E G\" loop", None),
+            "\x1b[35mplay\x1b[0m \x1b[32m\"C E G\"\x1b[0m \x1b[35mloop\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn test_unknown_identifiers_stand_out() {
+        assert_eq!(
+            highlight("fast(riff, 2).rev()", None),
+            "\x1b[34mfast\x1b[0m(\x1b[34mriff\x1b[0m, \x1b[33m2\x1b[0m).\x1b[34mrev\x1b[0m()"
+        );
+        assert_eq!(highlight("rif", None), "\x1b[31mrif\x1b[0m");
+        // Parameters are defined by the line itself
+        assert_eq!(
+            highlight("fn up(p) { p }", None),
+            "\x1b[35mfn\x1b[0m \x1b[34mup\x1b[0m(\x1b[34mp\x1b[0m) { \x1b[34mp\x1b[0m }"
+        );
+    }
+
+    #[test]
+    fn test_matching_bracket_is_emphasized() {
+        let line = "rev([C, E])";
+        // Cursor on the closing `]`
+        assert_eq!(
+            highlight(line, Some(9)),
+            "\x1b[34mrev\x1b[0m(\x1b[1;4m[\x1b[0m\x1b[36mC\x1b[0m, \x1b[36mE\x1b[0m\x1b[1;4m]\x1b[0m)"
+        );
+        // No partner yet
+        assert!(!highlight("rev([C", Some(4)).contains("1;4"));
+    }
+
+    #[test]
+    fn test_unfinished_and_command_lines() {
+        // An open string is colored as it's typed
+        assert_eq!(
+            highlight("play \"C E", None),
+            "\x1b[35mplay\x1b[0m \x1b[32m\"C E\x1b[0m"
+        );
+        assert_eq!(
+            highlight("watch songs/live.cadence", None),
+            "\x1b[35mwatch\x1b[0m songs/live.cadence"
+        );
+        // An assignment, not the save command
+        assert_eq!(
+            highlight("save = 1", None),
+            "\x1b[31msave\x1b[0m = \x1b[33m1\x1b[0m"
+        );
+    }
+}
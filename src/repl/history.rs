@@ -0,0 +1,135 @@
+//! Persistent line history for the REPL editor
+//!
+//! History is written after every entry rather than on exit: the editor lives
+//! on the input thread, which is still blocked in `readline` when the main
+//! loop quits, so an exit hook would never run there.
+
+use rustyline::config::Config;
+use rustyline::history::DefaultHistory;
+use rustyline::{Editor, Helper};
+use std::path::{Path, PathBuf};
+
+/// History file name in the home directory
+pub const HISTORY_FILE: &str = ".cadence_history";
+
+/// Most entries kept, in memory and on disk
+pub const MAX_HISTORY: usize = 1000;
+
+/// Where history is stored: `$XDG_STATE_HOME/cadence/history` on Linux when
+/// that is set, otherwise `~/.cadence_history`
+pub fn history_path() -> Option<PathBuf> {
+    let xdg_state = if cfg!(target_os = "linux") {
+        std::env::var_os("XDG_STATE_HOME")
+    } else {
+        None
+    };
+    let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"));
+    resolve_history_path(home.map(PathBuf::from), xdg_state.map(PathBuf::from))
+}
+
+fn resolve_history_path(home: Option<PathBuf>, xdg_state: Option<PathBuf>) -> Option<PathBuf> {
+    // The XDG spec says relative paths are invalid and should be ignored
+    match xdg_state.filter(|dir| dir.is_absolute()) {
+        Some(dir) => Some(dir.join("cadence").join("history")),
+        None => home.map(|home| home.join(HISTORY_FILE)),
+    }
+}
+
+/// Create the line editor, loading saved history from `path` if it exists.
+/// Consecutive duplicate entries are skipped and Ctrl-R searches history.
+pub fn create_editor<H: Helper>(
+    path: Option<&Path>,
+) -> rustyline::Result<Editor<H, DefaultHistory>> {
+    let config = Config::builder()
+        .max_history_size(MAX_HISTORY)?
+        .history_ignore_dups(true)?
+        .build();
+    let mut editor = Editor::with_config(config)?;
+
+    if let Some(path) = path.filter(|p| p.exists()) {
+        if let Err(e) = editor.load_history(path) {
+            eprintln!("⚠️  Could not load history from {}: {}", path.display(), e);
+        }
+    }
+    Ok(editor)
+}
+
+/// Add a line to history and write the history file
+pub fn record<H: Helper>(editor: &mut Editor<H, DefaultHistory>, line: &str, path: Option<&Path>) {
+    if !editor.add_history_entry(line).unwrap_or(false) {
+        return;
+    }
+    let Some(path) = path else {
+        return;
+    };
+    if let Some(dir) = path.parent() {
+        let _ = std::fs::create_dir_all(dir);
+    }
+    // A failed save shouldn't interrupt the session; the next entry tries again
+    let _ = editor.save_history(path);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustyline::DefaultEditor;
+
+    /// Entries held by the editor, oldest first
+    fn entries(editor: &DefaultEditor) -> Vec<String> {
+        editor.history().iter().cloned().collect()
+    }
+
+    #[test]
+    fn test_history_path() {
+        let home = PathBuf::from("/home/user");
+        assert_eq!(
+            resolve_history_path(Some(home.clone()), None),
+            Some(PathBuf::from("/home/user/.cadence_history"))
+        );
+        assert_eq!(
+            resolve_history_path(Some(home.clone()), Some(PathBuf::from("/state"))),
+            Some(PathBuf::from("/state/cadence/history"))
+        );
+        assert_eq!(
+            resolve_history_path(Some(home), Some(PathBuf::from("relative"))),
+            Some(PathBuf::from("/home/user/.cadence_history"))
+        );
+        assert_eq!(resolve_history_path(None, None), None);
+    }
+
+    #[test]
+    fn test_history_round_trip() {
+        let home = std::env::temp_dir().join(format!("cadence_history_{}", std::process::id()));
+        let path = resolve_history_path(Some(home.clone()), None).unwrap();
+        std::fs::create_dir_all(&home).unwrap();
+
+        let mut editor = create_editor::<()>(Some(&path)).unwrap();
+        record(&mut editor, "play \"C E G\" loop", Some(&path));
+        record(&mut editor, "play \"C E G\" loop", Some(&path));
+        record(&mut editor, "tempo 120", Some(&path));
+        drop(editor);
+
+        // A new session sees the saved lines, with the repeat collapsed
+        let editor = create_editor::<()>(Some(&path)).unwrap();
+        let saved = entries(&editor);
+        std::fs::remove_dir_all(&home).ok();
+        assert_eq!(saved, vec!["play \"C E G\" loop", "tempo 120"]);
+    }
+
+    #[test]
+    fn test_history_is_capped() {
+        let dir = std::env::temp_dir().join(format!("cadence_history_cap_{}", std::process::id()));
+        let path = dir.join("cadence").join("history");
+
+        let mut editor = create_editor::<()>(Some(&path)).unwrap();
+        for i in 0..MAX_HISTORY + 5 {
+            editor.add_history_entry(format!("tempo {}", i)).unwrap();
+        }
+        record(&mut editor, "stop", Some(&path));
+
+        let saved = entries(&create_editor::<()>(Some(&path)).unwrap());
+        std::fs::remove_dir_all(&dir).ok();
+        assert_eq!(saved.len(), MAX_HISTORY);
+        assert_eq!(saved.last().map(String::as_str), Some("stop"));
+    }
+}
@@ -1,44 +1,67 @@
 //! REPL (Read-Eval-Print Loop) for the Cadence language
 
-use crate::audio::audio::AudioPlayerHandle;
-use crate::audio::clock::MasterClock;
-use crate::audio::event_dispatcher::{DispatcherHandle, EventDispatcher, PatternId};
+use crate::audio::event_dispatcher::TrackStatus;
+use crate::audio::headless::SAMPLE_RATE;
 use crate::audio::midi::MidiOutputHandle;
-use crate::commands::{create_registry, CommandContext, CommandResult};
-use crate::parser::{parse_statements, Interpreter, InterpreterAction, Value};
-use crate::repl::watcher::FileWatcher;
-use anyhow::Result;
+use crate::audio::midi_input::{BindingEffect, MidiInputHandle, MidiInputMessage};
+use crate::audio::render::PatternRender;
+use crate::commands::{
+    create_registry, CommandContext, CommandRegistry, CommandResult, ResetScope,
+};
+use crate::engine::{Engine, DEFAULT_BPM};
+use crate::parser::{
+    parse_spanned_statements, EnvironmentRef, Evaluator, Interpreter, InterpreterAction, Value,
+};
+use crate::repl::completion::{CadenceHelper, SharedNames, KEYWORDS};
+use crate::repl::session::{Session, TrackState};
+use crate::repl::watcher::{Debouncer, FileWatcher};
+use crate::types::{musicxml_score, to_f64, MidiFile, MidiFileTrack, Note, Pattern, Time};
+use anyhow::{anyhow, Result};
+use cadence_core::parser::builtins::get_registry;
 use colored::*;
 use crossbeam_channel::{unbounded, Receiver, Sender};
 use notify::Event;
 use rustyline::error::ReadlineError;
-use rustyline::{DefaultEditor, Result as RustylineResult};
+use rustyline::history::DefaultHistory;
+use rustyline::{Editor, Result as RustylineResult};
 use std::collections::HashMap;
-use std::sync::atomic::AtomicU64;
+use std::path::Path;
 use std::sync::Arc;
 use std::thread;
+use std::time::Instant;
 
+pub mod completion;
+pub mod docs;
+pub mod highlight;
+pub mod history;
+pub mod multiline;
+pub mod session;
+pub mod tracks;
+pub mod vars;
 pub mod watcher;
 
 /// Types of events the REPL loop handles
 enum ReplEvent {
     Input(Result<String, ReadlineError>),
+    /// Message from the MIDI input thread
+    MidiInput(MidiInputMessage),
 }
 
 /// Interactive REPL for the Cadence language
 pub struct Repl {
-    editor: Option<DefaultEditor>,
-    audio_handle: Arc<AudioPlayerHandle>,
-    midi_handle: Arc<MidiOutputHandle>,
-    clock: Arc<MasterClock>,
-    /// Shared BPM as atomic for playback engines
-    bpm: Arc<AtomicU64>,
-    /// Unified event dispatcher (handles both one-shot and looping playback)
-    dispatcher_handle: DispatcherHandle,
-    /// Track which pattern IDs are active per track (for stopping)
-    active_patterns: HashMap<usize, PatternId>,
+    editor: Option<Editor<CadenceHelper, DefaultHistory>>,
+    /// Variable names offered by tab completion (the editor lives on another thread)
+    completion_names: SharedNames,
+    /// User function names, also offered after a `.`
+    completion_functions: SharedNames,
+    /// Outputs, clock and per-track playback state
+    engine: Engine,
+    /// MIDI input (controller messages are forwarded into the input channel)
+    midi_input: Arc<MidiInputHandle>,
     /// Interpreter for scripting constructs
     interpreter: Interpreter,
+    /// Built-in commands and the user's aliases
+    registry: CommandRegistry,
 
     // Event channels
     tx_input: Sender<ReplEvent>,
@@ -48,15 +71,22 @@ pub struct Repl {
 
     // File watcher
     watcher: Option<FileWatcher>,
+    /// Changed files waiting for their events to settle before reloading
+    reload_debouncer: Debouncer,
+    /// Print chords in values as their notes rather than their symbols
+    raw_chords: bool,
 }
 
 impl Repl {
     /// Create a new REPL instance
     pub fn new() -> RustylineResult<Self> {
-        let editor = DefaultEditor::new()?;
-        let audio_handle =
-            Arc::new(AudioPlayerHandle::new().expect("Failed to create audio player"));
-
+        let completion_names = SharedNames::default();
+        let completion_functions = SharedNames::default();
+        let mut editor = history::create_editor(history::history_path().as_deref())?;
+        editor.set_helper(Some(CadenceHelper::new(
+            completion_names.clone(),
+            completion_functions.clone(),
+        )));
         // Initialize MIDI output (non-fatal if it fails - MIDI server might be deadlocked)
         let midi_handle = match MidiOutputHandle::new() {
             Ok(handle) => Arc::new(handle),
@@ -74,34 +104,29 @@ impl Repl {
             }
         };
 
-        let clock = Arc::new(MasterClock::new(90.0)); // Default 90 BPM
-        let bpm = Arc::new(AtomicU64::new(90.0_f32.to_bits() as u64));
-
         let (tx_input, rx_input) = unbounded();
         let (tx_watcher, rx_watcher) = unbounded();
 
-        // Spawn the unified event dispatcher (replaces Scheduler + PlaybackEngines)
-        let dispatcher_tick_rx = clock.subscribe();
-        let dispatcher_handle = EventDispatcher::spawn(
-            audio_handle.clone(),
-            dispatcher_tick_rx,
-            Some(midi_handle.clone()),
-        );
+        let midi_tx = tx_input.clone();
+        let midi_input = Arc::new(MidiInputHandle::new(Arc::new(move |message| {
+            let _ = midi_tx.send(ReplEvent::MidiInput(message));
+        })));
 
         Ok(Repl {
             editor: Some(editor),
-            audio_handle,
-            midi_handle,
-            clock,
-            bpm,
-            dispatcher_handle,
-            active_patterns: HashMap::new(),
+            completion_names,
+            completion_functions,
+            engine: Engine::new(midi_handle),
+            midi_input,
             interpreter: Interpreter::new(),
+            registry: create_registry(),
             tx_input,
             rx_input,
             tx_watcher,
             rx_watcher,
             watcher: None,
+            reload_debouncer: Debouncer::new(),
+            raw_chords: false,
         })
     }
 
@@ -110,177 +135,396 @@ impl Repl {
 
     /// List all active tracks and their status
     pub fn list_tracks(&self) -> String {
-        if self.active_patterns.is_empty() {
-            return "No active tracks".to_string();
-        }
-        let mut track_ids: Vec<_> = self.active_patterns.keys().cloned().collect();
-        track_ids.sort();
+        let statuses = self.track_statuses();
+        tracks::format_tracks(&statuses, &self.engine.track_sources, Self::MAX_TRACKS)
+    }
 
-        let mut output = format!(
-            "🎛️  Active Tracks ({}/{}):\n",
-            track_ids.len(),
-            Self::MAX_TRACKS
-        );
-        for id in track_ids {
-            output.push_str(&format!("  Track {}: ▶ playing\n", id));
+    /// Status of every active track, sorted by track
+    fn track_statuses(&self) -> Vec<TrackStatus> {
+        let mut statuses = self.engine.dispatcher_handle.track_status();
+        if statuses.is_empty() {
+            // Dispatcher didn't answer: fall back to what the REPL started
+            statuses = self
+                .engine
+                .active_patterns
+                .keys()
+                .map(|&track_id| TrackStatus {
+                    track_id,
+                    looping: true,
+                    ..TrackStatus::default()
+                })
+                .collect();
+            statuses.sort_by_key(|s| s.track_id);
         }
-        output
+        statuses
     }
 
-    /// Convert a Value to frequencies for one-shot playback
-    fn value_to_frequencies(value: &Value) -> Option<(Vec<f32>, Vec<crate::types::DrumSound>)> {
-        match value {
-            Value::Note(note) => Some((vec![note.frequency()], vec![])),
-            Value::Chord(chord) => {
-                let freqs: Vec<f32> = chord.notes_vec().iter().map(|n| n.frequency()).collect();
-                Some((freqs, vec![]))
+    /// Handle `reset [tracks|vars]` and `reset!`. Without `force` nothing is
+    /// dropped; the message says what would be and how to go ahead.
+    fn reset(&mut self, scope: ResetScope, force: bool) -> String {
+        let tracks = scope != ResetScope::Vars;
+        let vars = scope != ResetScope::Tracks;
+
+        if !force {
+            let mut lost = Vec::new();
+            if tracks {
+                let count = self.track_statuses().len();
+                let noun = if count == 1 { "track" } else { "tracks" };
+                lost.push(format!("stops {} {}", count, noun));
             }
-            Value::Pattern(pattern) => {
-                // For immediate play, get the first event
-                let events = pattern.to_rich_events();
-                if let Some(first) = events.first() {
-                    let freqs: Vec<f32> = first.notes.iter().map(|n| n.frequency).collect();
-                    Some((freqs, first.drums.clone()))
-                } else {
-                    Some((vec![], vec![]))
-                }
+            if vars {
+                let count = self.interpreter.bindings().len();
+                let noun = if count == 1 { "variable" } else { "variables" };
+                lost.push(format!("removes {} {}", count, noun));
             }
-            Value::String(s) => {
-                if let Ok(pattern) = crate::types::Pattern::parse(s) {
-                    let events = pattern.to_rich_events();
-                    if let Some(first) = events.first() {
-                        let freqs: Vec<f32> = first.notes.iter().map(|n| n.frequency).collect();
-                        Some((freqs, first.drums.clone()))
-                    } else {
-                        Some((vec![], vec![]))
-                    }
-                } else {
-                    None
-                }
-            }
-            Value::EveryPattern(every) => {
-                // For immediate play, use base pattern's first event
-                let events = every.base.to_rich_events();
-                if let Some(first) = events.first() {
-                    let freqs: Vec<f32> = first.notes.iter().map(|n| n.frequency).collect();
-                    Some((freqs, first.drums.clone()))
-                } else {
-                    Some((vec![], vec![]))
-                }
+            if scope == ResetScope::All {
+                lost.push(format!("sets the tempo to {} BPM", DEFAULT_BPM));
             }
-            _ => None,
+            let command = match scope {
+                ResetScope::All => "reset!",
+                ResetScope::Tracks => "reset! tracks",
+                ResetScope::Vars => "reset! vars",
+            };
+            return format!(
+                "⚠️  This {}. Type '{}' to go ahead.",
+                lost.join(", "),
+                command.bright_green()
+            );
+        }
+
+        if tracks {
+            self.engine.clear_tracks();
+        }
+        if vars {
+            self.interpreter.clear_environment();
+            self.refresh_completions();
+        }
+        if scope == ResetScope::All {
+            self.engine.set_tempo(DEFAULT_BPM);
+            self.interpreter.tempo = DEFAULT_BPM;
+        }
+        match scope {
+            ResetScope::All => "🧹 Session reset".to_string(),
+            ResetScope::Tracks => "🧹 All tracks stopped and cleared".to_string(),
+            ResetScope::Vars => "🧹 All variables removed".to_string(),
         }
     }
 
-    /// Execute an interpreter action (triggers actual audio/state changes)
-    fn execute_action(&mut self, action: InterpreterAction, _ctx: &mut CommandContext) {
-        match action {
-            InterpreterAction::PlayExpression {
-                expression,
-                looping,
-                queue_mode,
-                track_id,
-                display_value,
-                scheduled_beat: _,
-            } => {
-                // Ensure the clock is running before starting playback
-                self.clock.start();
-
-                // Extract envelope and waveform from the pattern if present
-                let pattern_props: Option<(
-                    Option<(f32, f32, f32, f32)>,
-                    Option<crate::types::Waveform>,
-                )> = match &display_value {
-                    Value::Pattern(pattern) => Some((pattern.envelope, pattern.waveform)),
-                    Value::EveryPattern(every) => Some((every.base.envelope, every.base.waveform)),
-                    _ => None,
-                };
+    /// Share the interpreter's variable and function names with the editor
+    /// for tab completion
+    fn refresh_completions(&self) {
+        let functions = self
+            .interpreter
+            .user_functions()
+            .into_iter()
+            .map(|f| f.name)
+            .collect();
+        if let Ok(mut shared) = self.completion_functions.write() {
+            *shared = functions;
+        }
 
-                if let Some((envelope, waveform)) = pattern_props {
-                    if let Some(env) = envelope {
-                        self.dispatcher_handle
-                            .set_track_envelope(track_id, Some(env));
-                    }
-                    if let Some(wf) = waveform {
-                        self.dispatcher_handle.set_track_waveform(track_id, wf);
-                    }
-                }
+        let env = self.interpreter.shared_environment();
+        let Ok(env) = env.read() else {
+            return;
+        };
+        let names = env.all_names().into_iter().cloned().collect();
+        if let Ok(mut shared) = self.completion_names.write() {
+            *shared = names;
+        }
+    }
 
-                if looping {
-                    let shared_env = self.interpreter.shared_environment();
-
-                    if let Some(mode) = queue_mode {
-                        // Queue the pattern for activation at the next musical boundary
-                        let pattern_id = self
-                            .dispatcher_handle
-                            .queue_loop(expression, shared_env, track_id, mode);
-                        // Note: Don't add to active_patterns yet - will be added when activated
-                        println!(
-                            "🎵 Queued {} (Track {}) - will start on {:?}",
-                            display_value, track_id, mode
-                        );
-                        // Still track it for stopping purposes
-                        self.active_patterns.insert(track_id, pattern_id);
-                    } else {
-                        // Immediate start (no queue mode)
-                        let pattern_id = self
-                            .dispatcher_handle
-                            .start_loop(expression, shared_env, track_id);
-                        self.active_patterns.insert(track_id, pattern_id);
-                        println!(
-                            "🔊 Playing {} (Track {}) - live reactive!",
-                            display_value, track_id
-                        );
-                    }
-                } else {
-                    // For one-shot plays, trigger immediately
-                    if let Some((freqs, drums)) = Self::value_to_frequencies(&display_value) {
-                        self.dispatcher_handle
-                            .trigger_immediate(track_id, freqs, drums);
-                    } else {
-                        println!("{} Cannot play this value", "Playback error:".red());
-                    }
-                }
-            }
-            InterpreterAction::SetTempo(bpm) => {
-                self.clock.set_bpm(bpm);
-                self.bpm
-                    .store(bpm.to_bits() as u64, std::sync::atomic::Ordering::Relaxed);
-                // Also start the clock if not already running
-                self.clock.start();
-                // Already printed by interpreter
-            }
-            InterpreterAction::SetVolume { volume, track_id } => {
-                self.dispatcher_handle.set_track_volume(track_id, volume);
-            }
-            InterpreterAction::SetWaveform { waveform, track_id } => {
-                // Parse waveform name and set it on the audio handle
-                use crate::types::Waveform;
-                if let Some(wf) = Waveform::from_name(&waveform) {
-                    self.dispatcher_handle.set_track_waveform(track_id, wf);
-                } else {
-                    println!(
-                        "{} Unknown waveform: {} (Track {})",
-                        "Waveform error:".red(),
-                        waveform,
-                        track_id
-                    );
-                }
-            }
-            InterpreterAction::Stop { track_id } => {
-                match track_id {
-                    Some(id) => {
-                        self.dispatcher_handle.stop_track(id);
-                        self.active_patterns.remove(&id);
-                    }
-                    None => {
-                        // Stop all playback
-                        self.dispatcher_handle.stop_all();
-                        self.active_patterns.clear();
-                    }
-                }
+    /// Handle `unset <name>`: remove a binding from the session
+    fn unset_variable(&mut self, name: &str) -> Result<String> {
+        if name.is_empty() {
+            return Err(anyhow!("Usage: unset <name>"));
+        }
+        if !self.interpreter.unset(name) {
+            return Err(anyhow!("'{}' is not defined", name));
+        }
+        self.refresh_completions();
+        Ok(format!("Removed {}", name.bright_green()))
+    }
+
+    /// Handle `morph <cycles>` / `morph off`: how many cycles a changed
+    /// looping pattern takes to blend into its new version
+    fn set_morph(&self, args: &str) -> Result<String> {
+        let cycles = match args.trim() {
+            "off" => 0,
+            n => n
+                .parse::<usize>()
+                .map_err(|_| anyhow!("Usage: morph <cycles> | morph off"))?,
+        };
+        self.engine.dispatcher_handle.set_morph(cycles);
+        Ok(if cycles > 1 {
+            format!("🌊 Changed patterns morph over {} cycles", cycles)
+        } else {
+            "🌊 Morph off: changed patterns switch at once".to_string()
+        })
+    }
+
+    /// Handle `chords symbols` / `chords notes`: how printed values show chords
+    fn set_chord_display(&mut self, args: &str) -> Result<String> {
+        self.raw_chords = match args.trim() {
+            "symbols" => false,
+            "notes" => true,
+            _ => return Err(anyhow!("Usage: chords symbols | chords notes")),
+        };
+        Ok(if self.raw_chords {
+            "🎹 Chords print as their notes".to_string()
+        } else {
+            "🎹 Chords print as symbols, e.g. \"Cmaj7 Dm7 Gdom7\"".to_string()
+        })
+    }
+
+    /// Parse the `<file> [bars]` arguments of `export midi` and `export wav`
+    fn export_file_and_bars<'a>(args: &'a str, usage: &str) -> Result<(&'a str, usize)> {
+        /// Length of the export when no bar count is given
+        const DEFAULT_BARS: usize = 4;
+
+        let parts: Vec<&str> = args.split_whitespace().collect();
+        match parts.as_slice() {
+            [path] => Ok((*path, DEFAULT_BARS)),
+            [path, bars] => match bars.parse::<usize>() {
+                Ok(bars) if bars > 0 => Ok((*path, bars)),
+                _ => Err(anyhow!("Bars must be a positive number")),
+            },
+            _ => Err(anyhow!("Usage: {}", usage)),
+        }
+    }
+
+    /// Looping track ids in order, or an error when nothing is looping
+    fn export_track_ids(&self) -> Result<Vec<usize>> {
+        if self.engine.track_expressions.is_empty() {
+            return Err(anyhow!("No looping tracks to export"));
+        }
+        let mut track_ids: Vec<_> = self.engine.track_expressions.keys().copied().collect();
+        track_ids.sort();
+        Ok(track_ids)
+    }
+
+    /// Evaluate a looping track's expression for one cycle of an offline
+    /// render. It sees `_beat`/`_cycle` as it would at this point during playback.
+    fn track_pattern_for_cycle(
+        &self,
+        evaluator: &Evaluator,
+        track_id: usize,
+        cycle: usize,
+        offset: Time,
+    ) -> Result<Pattern> {
+        let env = self.interpreter.shared_environment();
+        {
+            let mut env_write = env.write().map_err(|e| anyhow!("{}", e))?;
+            env_write.define("_beat".to_string(), Value::Number(to_f64(offset) as i32));
+            env_write.define("_cycle".to_string(), Value::Number(cycle as i32));
+        }
+
+        let env_guard = env.read().map_err(|e| anyhow!("{}", e))?;
+        let value = evaluator.eval_with_env(
+            self.engine.track_expressions[&track_id].clone(),
+            Some(EnvironmentRef::Borrowed(&env_guard)),
+        )?;
+        value.pattern_for_cycle(cycle).map_err(|e| anyhow!("{}", e))
+    }
+
+    /// Handle `export midi <file> [bars]`: render the looping tracks offline
+    /// (one cycle at a time on a virtual clock) and write a Standard MIDI File
+    fn export_midi(&self, args: &str) -> Result<String> {
+        let (path, bars) = Self::export_file_and_bars(args, "export midi <file> [bars]")?;
+        let track_ids = self.export_track_ids()?;
+
+        let mut file = MidiFile::new(self.engine.clock.get_bpm());
+        let beats = file.bars_to_beats(bars);
+        let evaluator = Evaluator::new();
+
+        for track_id in track_ids {
+            // Same channel the track uses for live MIDI output
+            let channel = self.engine.midi_handle.channel_for_track(track_id);
+            let mut track = MidiFileTrack::new(format!("Track {}", track_id), channel);
+
+            track.render(beats, |cycle, offset| {
+                self.track_pattern_for_cycle(&evaluator, track_id, cycle, offset)
+            })?;
+            file.tracks.push(track);
+        }
+
+        file.write(path)?;
+        Ok(format!(
+            "💾 Exported {} track(s), {} bar(s) at {:.0} BPM to {}",
+            file.tracks.len(),
+            bars,
+            file.tempo,
+            path.green()
+        ))
+    }
+
+    /// Handle `export wav <file> [bars]`: synthesize the looping tracks offline
+    /// with the internal synth, at the current tempo, and write a WAV file
+    fn export_wav(&self, args: &str) -> Result<String> {
+        let (path, bars) = Self::export_file_and_bars(args, "export wav <file> [bars]")?;
+        let track_ids = self.export_track_ids()?;
+
+        let bpm = self.engine.clock.get_bpm();
+        let mut render = PatternRender::new(bpm, bars);
+        let evaluator = Evaluator::new();
+        for &track_id in &track_ids {
+            render.add_track(track_id, |cycle, offset| {
+                self.track_pattern_for_cycle(&evaluator, track_id, cycle, offset)
+            })?;
+        }
+
+        let seconds = render.frames() as f64 / SAMPLE_RATE as f64;
+        render.write_wav(path)?;
+        Ok(format!(
+            "💾 Rendered {} track(s), {} bar(s) ({:.1}s) at {:.0} BPM to {}",
+            track_ids.len(),
+            bars,
+            seconds,
+            bpm,
+            path.green()
+        ))
+    }
+
+    /// Handle `export musicxml <file> [key]`: write the first cycle of each
+    /// looping track as a part of a MusicXML score
+    fn export_musicxml(&self, args: &str) -> Result<String> {
+        let parts: Vec<&str> = args.split_whitespace().collect();
+        let (path, key) = match parts.as_slice() {
+            [path] => (*path, Note::new(0).unwrap()),
+            [path, key] => match key.parse::<Note>() {
+                Ok(key) => (*path, key),
+                Err(_) => return Err(anyhow!("Invalid key: {}", key)),
+            },
+            _ => return Err(anyhow!("Usage: export musicxml <file> [key]")),
+        };
+
+        if self.engine.track_expressions.is_empty() {
+            return Err(anyhow!("No looping tracks to export"));
+        }
+
+        let evaluator = Evaluator::new();
+        let env = self.interpreter.shared_environment();
+        {
+            let mut env_write = env.write().map_err(|e| anyhow!("{}", e))?;
+            env_write.define("_beat".to_string(), Value::Number(0));
+            env_write.define("_cycle".to_string(), Value::Number(0));
+        }
+
+        let mut track_ids: Vec<_> = self.engine.track_expressions.keys().copied().collect();
+        track_ids.sort();
+
+        let mut score = Vec::new();
+        for track_id in track_ids {
+            let expression = &self.engine.track_expressions[&track_id];
+            let env_guard = env.read().map_err(|e| anyhow!("{}", e))?;
+            let value = evaluator.eval_with_env(
+                expression.clone(),
+                Some(EnvironmentRef::Borrowed(&env_guard)),
+            )?;
+            let pattern = value.pattern_for_cycle(0).map_err(|e| anyhow!("{}", e))?;
+            score.push((format!("Track {}", track_id), pattern));
+        }
+
+        std::fs::write(path, musicxml_score(&score, key))?;
+        Ok(format!(
+            "💾 Exported {} track(s) as MusicXML to {}",
+            score.len(),
+            path.green()
+        ))
+    }
+
+    /// Handle `save "file"`: write tempo, definitions, track settings and
+    /// looping plays as a `.cadence` file that recreates the session
+    fn save_session(&self, args: &str) -> Result<String> {
+        let path = args.trim().trim_matches('"');
+        if path.is_empty() {
+            return Err(anyhow!("Usage: save \"file.cadence\""));
+        }
+
+        let (definitions, mut warnings) = self.interpreter.definitions_source();
+
+        let mut tracks: HashMap<usize, TrackState> = HashMap::new();
+        for status in self.engine.dispatcher_handle.track_status() {
+            let state = tracks.entry(status.track_id).or_default();
+            state.track_id = status.track_id;
+            state.volume = status.volume;
+            state.waveform = status.waveform;
+        }
+        for (track_id, patch) in self.engine.midi_handle.track_patches() {
+            let state = tracks.entry(track_id).or_default();
+            state.track_id = track_id;
+            state.patch = Some(patch);
+        }
+        for (&track_id, expression) in &self.engine.track_expressions {
+            let state = tracks.entry(track_id).or_default();
+            state.track_id = track_id;
+            state.looping = Some(expression.clone());
+            state.source = self.engine.track_sources.get(&track_id).cloned();
+        }
+
+        let session = Session {
+            tempo: self.engine.clock.get_bpm(),
+            capo: self.interpreter.capo,
+            definitions,
+            aliases: self
+                .registry
+                .aliases()
+                .iter()
+                .map(|(name, source)| (name.clone(), source.clone()))
+                .collect(),
+            tracks: tracks.into_values().collect(),
+        };
+        let (text, track_warnings) = session.render();
+        warnings.extend(track_warnings);
+
+        std::fs::write(path, text).map_err(|e| anyhow!("Failed to write '{}': {}", path, e))?;
+
+        let mut message = format!("💾 Saved session to {}", path.green());
+        for warning in warnings {
+            message.push_str(&format!("\n{} {}", "Warning:".yellow(), warning));
+        }
+        Ok(message)
+    }
+
+    /// Execute an interpreter action; looping patterns see the interpreter's variables
+    fn execute_action(&mut self, action: InterpreterAction) {
+        if let InterpreterAction::DefineAlias { name, source } = action {
+            match self.define_alias(&name, &source) {
+                Ok(msg) => println!("{}", msg),
+                Err(e) => println!("{} {}", "Error:".bright_red().bold(), e.to_string().red()),
             }
+            return;
+        }
+        let env = self.interpreter.shared_environment();
+        self.engine.execute_action(action, env);
+    }
+
+    /// Register an alias from an `alias "name" = "..."` statement
+    fn define_alias(&mut self, name: &str, source: &str) -> Result<String> {
+        if KEYWORDS.contains(&name) || highlight::LOOP_COMMANDS.contains(&name) {
+            return Err(anyhow!("'{}' is already a keyword or command", name));
         }
+        self.registry
+            .define_alias(name, source)
+            .map_err(|e| anyhow!(e))?;
+        Ok(format!("🔗 {} runs: {}", name.bright_green(), source))
+    }
+
+    /// Handle `aliases`: one line per alias with the commands it runs
+    fn list_aliases(&self) -> String {
+        let aliases = self.registry.aliases();
+        if aliases.is_empty() {
+            return "No aliases defined".to_string();
+        }
+        let width = aliases
+            .keys()
+            .map(|name| name.chars().count())
+            .max()
+            .unwrap_or(0);
+        let mut output = format!("🔗 Aliases ({}):", aliases.len());
+        for (name, source) in aliases {
+            output.push_str(&format!("\n  {:width$}  {}", name, source));
+        }
+        output
     }
 
     /// Execute an action but skip looped play expressions if track is already playing.
@@ -290,7 +534,7 @@ impl Repl {
     /// so if you change `let bass = "C2 G1"` to `let bass = "C2 _ C2 G1"`,
     /// the track playing `bass` will automatically pick up the new value
     /// WITHOUT needing to restart the progression!
-    fn execute_action_queued(&mut self, action: InterpreterAction, ctx: &mut CommandContext) {
+    fn execute_action_queued(&mut self, action: InterpreterAction) {
         match action {
             InterpreterAction::PlayExpression {
                 expression,
@@ -298,92 +542,184 @@ impl Repl {
                 queue_mode: _,
                 track_id,
                 display_value,
+                source,
                 scheduled_beat,
             } => {
                 // KEY FIX: If this track is already playing, SKIP the play command!
                 // The reactive expression will automatically pick up variable changes
                 // on the next beat. This is what makes hot-reload feel like the REPL.
-                if self.active_patterns.contains_key(&track_id) {
+                if self.engine.active_patterns.contains_key(&track_id) {
                     // Use the pre-evaluated display_value from when the action was created
                     println!(
                         "🔄 Track {} updated: {} (reactive, no restart needed)",
                         track_id, display_value
                     );
+                    self.engine.track_sources.insert(track_id, source);
                     return;
                 }
 
                 // Track is not playing - start it normally
-                self.execute_action(
-                    InterpreterAction::PlayExpression {
-                        expression,
-                        looping: true,
-                        queue_mode: None, // Immediate play since track isn't running
-                        track_id,
-                        display_value,
-                        scheduled_beat,
-                    },
-                    ctx,
-                );
+                self.execute_action(InterpreterAction::PlayExpression {
+                    expression,
+                    looping: true,
+                    queue_mode: None, // Immediate play since track isn't running
+                    track_id,
+                    display_value,
+                    source,
+                    scheduled_beat,
+                });
             }
             // For all other actions, use normal execution
-            other => self.execute_action(other, ctx),
+            other => self.execute_action(other),
+        }
+    }
+
+    /// Run the binding for an incoming MIDI message (on the interpreter thread)
+    fn handle_midi_input(&mut self, message: MidiInputMessage) {
+        match self.engine.midi_bindings.handle(&message) {
+            Some(BindingEffect::Run(body)) => {
+                if let Err(e) = self
+                    .interpreter
+                    .run_statement(&crate::parser::Statement::Block(body))
+                {
+                    println!("{} {}", "MIDI binding error:".bright_red().bold(), e);
+                }
+                for action in self.interpreter.take_actions() {
+                    self.execute_action(action);
+                }
+                self.engine
+                    .schedule(self.interpreter.take_scheduled_events());
+                self.interpreter.reset_virtual_time();
+            }
+            Some(BindingEffect::Action(action)) => self.execute_action(*action),
+            None => {
+                if self.midi_input.monitor() {
+                    println!("{} {}", "🎛️  MIDI in:".dimmed(), message);
+                }
+            }
+        }
+    }
+
+    /// Rerun a watched file after it changed
+    fn reload_file(&mut self, path: &Path) {
+        println!("{} File changed: {}", "⚡".bright_yellow(), path.display());
+
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) => {
+                println!("{} Failed to read file: {}", "Error:".red(), e);
+                return;
+            }
+        };
+        match parse_spanned_statements(&contents) {
+            Ok(program) => {
+                // Only statements that changed since the last reload run again
+                let key = loaded_key(path);
+                match self
+                    .interpreter
+                    .reload_spanned_program(&key, &program, &contents)
+                {
+                    Ok(summary) => println!("{} Reloaded: {}", "✓".bright_green(), summary),
+                    Err(e) => println!("{} Runtime error: {}", "Error:".red(), e),
+                }
+
+                // Execute actions using queued execution for smoother hot-reload
+                // Looped patterns will queue instead of immediate restart
+                for action in self.interpreter.take_actions() {
+                    self.execute_action_queued(action);
+                }
+                // One-shot plays (spaced out by `wait`) start from now, and the
+                // next run starts again at beat 0
+                self.engine
+                    .schedule(self.interpreter.take_scheduled_events());
+                self.interpreter.reset_virtual_time();
+                self.refresh_completions();
+            }
+            Err(e) => println!("{} Parse error: {}", "Error:".red(), e),
+        }
+    }
+
+    /// List the files being watched
+    fn list_watches(&self) -> String {
+        let paths: Vec<String> = self
+            .watcher
+            .iter()
+            .flat_map(|w| w.paths())
+            .map(|path| format!("  {}", path.display().to_string().bright_green()))
+            .collect();
+        if paths.is_empty() {
+            "No files are being watched".to_string()
+        } else {
+            format!("Watching:\n{}", paths.join("\n"))
         }
     }
 
     /// Start the REPL loop
     pub fn run(&mut self) -> Result<()> {
-        println!(
-            "{} {}",
-            "🎵".bright_yellow(),
-            "Cadence Music Programming Language".bright_cyan().bold()
-        );
-        println!(
-            "Type expressions like: {}, {}, {}",
-            "[C, E, G]".cyan(),
-            "[C, E, G] + 2".cyan(),
-            "invert([C, E, G])".cyan()
-        );
-        println!(
-            "Type '{}' for more information, '{}' or {} to exit.\n",
-            "help".bright_green(),
-            "quit".bright_red(),
-            "Ctrl+C".bright_red()
-        );
+        print_banner();
 
         // Move editor to thread
         let mut editor = self.editor.take().expect("Repl editor missing");
         let tx_input = self.tx_input.clone();
+        let history_path = history::history_path();
 
-        thread::spawn(move || loop {
-            let prompt = format!("{} ", "cadence>".bright_magenta().bold());
-            let readline = editor.readline(&prompt);
+        thread::spawn(move || {
+            // Lines of a block that isn't closed yet
+            let mut pending = String::new();
+            loop {
+                let prompt = if pending.is_empty() {
+                    format!("{} ", "cadence>".bright_magenta().bold())
+                } else {
+                    format!("{} ", "     ...".bright_black())
+                };
+                let readline = editor.readline(&prompt);
 
-            match readline {
-                Ok(line) => {
-                    let line = line.trim().to_string();
-                    if !line.is_empty() {
-                        let _ = editor.add_history_entry(&line);
+                match readline {
+                    Ok(line) => {
+                        if !pending.is_empty() {
+                            pending.push('\n');
+                        }
+                        pending.push_str(&line);
+                        if multiline::is_incomplete(&pending) {
+                            continue;
+                        }
+
+                        let input = std::mem::take(&mut pending).trim().to_string();
+                        if !input.is_empty() {
+                            history::record(&mut editor, &input, history_path.as_deref());
+                        }
+                        if tx_input.send(ReplEvent::Input(Ok(input))).is_err() {
+                            break;
+                        }
+                    }
+                    // Ctrl+C inside a block throws the block away instead of quitting
+                    Err(ReadlineError::Interrupted) if !pending.is_empty() => {
+                        pending.clear();
+                        println!("{}", "(block cancelled)".bright_black());
                     }
-                    if tx_input.send(ReplEvent::Input(Ok(line))).is_err() {
+                    Err(err) => {
+                        let _ = tx_input.send(ReplEvent::Input(Err(err)));
                         break;
                     }
                 }
-                Err(err) => {
-                    let _ = tx_input.send(ReplEvent::Input(Err(err)));
-                    break;
-                }
             }
         });
 
-        // Create command registry and context
-        let registry = create_registry();
+        // Create command context
         let mut ctx = CommandContext::new_with_midi(
-            self.audio_handle.clone(),
-            self.clock.clone(),
-            self.midi_handle.clone(),
+            self.engine.audio_handle.clone(),
+            self.engine.clock.clone(),
+            self.engine.midi_handle.clone(),
         );
+        ctx.midi_input = Some(self.midi_input.clone());
 
         loop {
+            // Fires when a changed file has been quiet long enough to reload
+            let reload_timer = match self.reload_debouncer.deadline() {
+                Some(deadline) => crossbeam_channel::at(deadline),
+                None => crossbeam_channel::never(),
+            };
+
             crossbeam_channel::select! {
                 recv(self.rx_input) -> msg => match msg {
                     Ok(ReplEvent::Input(res)) => {
@@ -398,9 +734,78 @@ impl Repl {
                                     println!("{}", self.list_tracks());
                                     continue;
                                 }
+                                if let Some(prefix) = line.strip_prefix("vars").filter(|a| a.is_empty() || (a.starts_with(' ') && !a.trim_start().starts_with('='))) {
+                                    println!("{}", vars::format_vars(&self.interpreter.bindings(), prefix.trim()));
+                                    continue;
+                                }
+                                if let Some(name) = line.strip_prefix("help ").filter(|a| !a.trim_start().starts_with('=')) {
+                                    let name = name.trim();
+                                    match docs::format_help(name, &get_registry().get_documentation(), &self.interpreter.user_functions()) {
+                                        Some(help) => print!("{}", help),
+                                        None => println!("{} {}", "Error:".bright_red().bold(), format!("No function named '{}'", name).red()),
+                                    }
+                                    continue;
+                                }
+                                if line == "docs" {
+                                    print!("{}", docs::format_docs(&self.interpreter.user_functions()));
+                                    continue;
+                                }
+                                if let Some(category) = line.strip_prefix("functions").filter(|a| a.is_empty() || (a.starts_with(' ') && !a.trim_start().starts_with('='))) {
+                                    println!("{}", docs::format_functions(category.trim(), &get_registry().get_documentation(), &self.interpreter.user_functions()));
+                                    continue;
+                                }
+                                if let Some(name) = line.strip_prefix("unset ").filter(|a| !a.trim_start().starts_with('=')) {
+                                    match self.unset_variable(name.trim()) {
+                                        Ok(msg) => println!("{}", msg),
+                                        Err(e) => println!("{} {}", "Error:".bright_red().bold(), e.to_string().red()),
+                                    }
+                                    continue;
+                                }
+                                if let Some(args) = line.strip_prefix("save ").filter(|a| !a.trim_start().starts_with('=')) {
+                                    match self.save_session(args) {
+                                        Ok(msg) => println!("{}", msg),
+                                        Err(e) => println!("{} {}", "Error:".bright_red().bold(), e.to_string().red()),
+                                    }
+                                    continue;
+                                }
+                                if let Some(args) = line.strip_prefix("chords ").filter(|a| !a.trim_start().starts_with('=')) {
+                                    match self.set_chord_display(args) {
+                                        Ok(msg) => println!("{}", msg),
+                                        Err(e) => println!("{} {}", "Error:".bright_red().bold(), e.to_string().red()),
+                                    }
+                                    continue;
+                                }
+                                if let Some(args) = line.strip_prefix("morph ").filter(|a| !a.trim_start().starts_with('=')) {
+                                    match self.set_morph(args) {
+                                        Ok(msg) => println!("{}", msg),
+                                        Err(e) => println!("{} {}", "Error:".bright_red().bold(), e.to_string().red()),
+                                    }
+                                    continue;
+                                }
+                                if let Some(args) = line.strip_prefix("export musicxml") {
+                                    match self.export_musicxml(args) {
+                                        Ok(msg) => println!("{}", msg),
+                                        Err(e) => println!("{} {}", "Error:".bright_red().bold(), e.to_string().red()),
+                                    }
+                                    continue;
+                                }
+                                if let Some(args) = line.strip_prefix("export wav") {
+                                    match self.export_wav(args) {
+                                        Ok(msg) => println!("{}", msg),
+                                        Err(e) => println!("{} {}", "Error:".bright_red().bold(), e.to_string().red()),
+                                    }
+                                    continue;
+                                }
+                                if let Some(args) = line.strip_prefix("export midi") {
+                                    match self.export_midi(args) {
+                                        Ok(msg) => println!("{}", msg),
+                                        Err(e) => println!("{} {}", "Error:".bright_red().bold(), e.to_string().red()),
+                                    }
+                                    continue;
+                                }
 
                                 // Try to execute as a command
-                                match registry.execute(&line, &mut ctx) {
+                                match self.registry.execute(&line, &mut ctx) {
                                     CommandResult::Success => {
                                         // Command executed, no output needed
                                     }
@@ -424,22 +829,60 @@ impl Repl {
                                          }
 
                                          if let Some(w) = &mut self.watcher {
-                                             if let Err(e) = w.watch(&path) {
-                                                  println!("{} Failed to watch {}: {}", "Error:".red(), path, e);
-                                             } else {
-                                                  println!("{} Watching {} for changes...", "eyes".bright_cyan(), path.bright_green());
+                                             match w.watch(&path) {
+                                                 Ok(true) => println!("{} Watching {} for changes...", "eyes".bright_cyan(), path.bright_green()),
+                                                 Ok(false) => println!("Already watching {}", path.bright_green()),
+                                                 Err(e) => println!("{} Failed to watch {}: {}", "Error:".red(), path, e),
                                              }
                                          }
                                     }
+                                    CommandResult::Unwatch(path) => {
+                                        match self.watcher.as_mut().map(|w| w.unwatch(&path)) {
+                                            Some(Ok(removed)) => {
+                                                self.reload_debouncer.forget(&removed);
+                                                self.interpreter.forget_loaded(&loaded_key(&removed));
+                                                println!("Stopped watching {}", path.bright_green());
+                                            }
+                                            _ => println!("{} Not watching {}", "Error:".red(), path),
+                                        }
+                                    }
+                                    CommandResult::ListWatches => {
+                                        println!("{}", self.list_watches());
+                                    }
+                                    CommandResult::Clear => {
+                                        // Clear the screen and move the cursor home
+                                        print!("\x1B[2J\x1B[1;1H");
+                                        print_banner();
+                                    }
+                                    CommandResult::Reset { scope, force } => {
+                                        println!("{}", self.reset(scope, force));
+                                    }
+                                    CommandResult::Alias(lines) => {
+                                        // Run each line as if it had been typed
+                                        for line in lines {
+                                            let _ = self.tx_input.send(ReplEvent::Input(Ok(line)));
+                                        }
+                                    }
+                                    CommandResult::ListAliases => {
+                                        println!("{}", self.list_aliases());
+                                    }
+                                    CommandResult::Unalias(name) => {
+                                        if self.registry.remove_alias(&name) {
+                                            println!("Removed alias {}", name.bright_green());
+                                        } else {
+                                            println!("{} No alias named '{}'", "Error:".red(), name);
+                                        }
+                                    }
                                     CommandResult::NotACommand => {
                                         // Parse and execute as statement(s)
-                                        match parse_statements(&line) {
+                                        match parse_spanned_statements(&line) {
                                             Ok(program) => {
                                                 // Inject _beat for beat() function
-                                                let current_beat = self.clock.current_beat() as i32;
+                                                let current_beat = self.engine.clock.current_beat() as i32;
                                                 self.interpreter.set_variable("_beat", Value::Number(current_beat));
 
-                                                match self.interpreter.run_program(&program) {
+                                                match self.interpreter.run_spanned_program(&program, &line) {
+                                                    Ok(Some(value)) if self.raw_chords => println!("{:#}", value),
                                                     Ok(Some(value)) => println!("{}", value),
                                                     Ok(None) => {} // Statement with no value
                                                     Err(e) => println!(
@@ -451,21 +894,15 @@ impl Repl {
 
                                                 // Execute collected actions (immediate plays)
                                                 for action in self.interpreter.take_actions() {
-                                                    self.execute_action(action, &mut ctx);
+                                                    self.execute_action(action);
                                                 }
 
-                                                // Send scheduled events to the dispatcher
-                                                let scheduled_events = self.interpreter.take_scheduled_events();
-                                                if !scheduled_events.is_empty() {
-                                                    // Get current beat for scheduling relative to now
-                                                    let base_beat = self.clock.current_beat();
-                                                    self.dispatcher_handle.schedule(scheduled_events, base_beat);
-                                                    // Start the clock if not already running
-                                                    self.clock.start();
-                                                }
+                                                // Send scheduled events to the dispatcher, relative to now
+                                                self.engine.schedule(self.interpreter.take_scheduled_events());
 
                                                 // Reset virtual time for next interaction
                                                 self.interpreter.reset_virtual_time();
+                                                self.refresh_completions();
                                             }
                                             Err(e) => println!(
                                                 "{} {}",
@@ -493,6 +930,9 @@ impl Repl {
                             }
                         }
                     },
+                    Ok(ReplEvent::MidiInput(message)) => {
+                        self.handle_midi_input(message);
+                    }
                     Err(_) => break, // Channel closed
                 },
 
@@ -502,40 +942,24 @@ impl Repl {
                         // notify 5.0+ events are granular
                         // We generally reload on any write-close or modify
                         use notify::EventKind;
-                        match event.kind {
-                            EventKind::Modify(_) | EventKind::Create(_) => {
-                                for path in event.paths {
-                                    println!("{} File changed: {}", "⚡".bright_yellow(), path.display());
-
-                                    // Reload the file content
-                                    match std::fs::read_to_string(&path) {
-                                        Ok(contents) => {
-                                            println!("Reloading...");
-                                            match parse_statements(&contents) {
-                                                Ok(program) => {
-                                                    match self.interpreter.run_program(&program) {
-                                                        Ok(_) => println!("{} Reloaded successfully", "✓".bright_green()),
-                                                        Err(e) => println!("{} Runtime error: {}", "Error:".red(), e),
-                                                    }
-
-                                                    // Execute actions using queued execution for smoother hot-reload
-                                                    // Looped patterns will queue instead of immediate restart
-                                                    for action in self.interpreter.take_actions() {
-                                                        self.execute_action_queued(action, &mut ctx);
-                                                    }
-                                                },
-                                                Err(e) => println!("{} Parse error: {}", "Error:".red(), e),
-                                            }
-                                        },
-                                        Err(e) => println!("{} Failed to read file: {}", "Error:".red(), e),
-                                    }
+                        if let EventKind::Modify(_) | EventKind::Create(_) = event.kind {
+                            // A save fires several events; reload once they settle
+                            let now = Instant::now();
+                            for path in event.paths {
+                                if self.watcher.as_ref().is_some_and(|w| w.is_watched(&path)) {
+                                    self.reload_debouncer.record(path, now);
                                 }
-                            },
-                            _ => {}
+                            }
                         }
                     },
                     Ok(Err(e)) => println!("{} Watch error: {}", "Error:".red(), e),
                     Err(_) => break, // Channel closed
+                },
+
+                recv(reload_timer) -> _ => {
+                    for path in self.reload_debouncer.take_due(Instant::now()) {
+                        self.reload_file(&path);
+                    }
                 }
             }
         }
@@ -550,15 +974,45 @@ impl Default for Repl {
     }
 }
 
+/// The key a watched file's statements are remembered under between reloads
+fn loaded_key(path: &Path) -> String {
+    path.canonicalize()
+        .unwrap_or_else(|_| path.to_path_buf())
+        .display()
+        .to_string()
+}
+
 /// Convenience function to start the REPL
 pub fn start() -> Result<()> {
     let mut repl = Repl::new().map_err(|e| anyhow::anyhow!("Failed to initialize REPL: {}", e))?;
     repl.run()
 }
 
+/// Title and a few hints, shown at startup and after `clear`
+fn print_banner() {
+    println!(
+        "{} {}",
+        "🎵".bright_yellow(),
+        "Cadence Music Programming Language".bright_cyan().bold()
+    );
+    println!(
+        "Type expressions like: {}, {}, {}",
+        "[C, E, G]".cyan(),
+        "[C, E, G] + 2".cyan(),
+        "invert([C, E, G])".cyan()
+    );
+    println!(
+        "Type '{}' for more information, '{}' or {} to exit.\n",
+        "help".bright_green(),
+        "quit".bright_red(),
+        "Ctrl+C".bright_red()
+    );
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::parser::parse_statements;
 
     #[test]
     fn test_repl_creation() {
@@ -567,6 +1021,58 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    /// Run a line the way the REPL loop does
+    fn enter(repl: &mut Repl, line: &str) {
+        let program = parse_spanned_statements(line).unwrap();
+        repl.interpreter
+            .run_spanned_program(&program, line)
+            .unwrap();
+        for action in repl.interpreter.take_actions() {
+            repl.execute_action(action);
+        }
+    }
+
+    #[test]
+    fn test_reset() {
+        let mut repl = Repl::new().unwrap();
+        enter(&mut repl, "tempo 140");
+        enter(&mut repl, "let bass = \"C2 G1\"\nlet lead = \"E G\"");
+        enter(&mut repl, "track 2 play bass loop");
+        assert_ne!(repl.list_tracks(), "No active tracks");
+
+        // Without the `!` nothing is dropped
+        let warning = repl.reset(ResetScope::All, false);
+        assert!(
+            warning.contains("stops 1 track, removes 2 variables"),
+            "{}",
+            warning
+        );
+        assert_eq!(repl.interpreter.bindings().len(), 2);
+
+        repl.reset(ResetScope::All, true);
+        assert!(repl.interpreter.bindings().is_empty());
+        assert_eq!(repl.list_tracks(), "No active tracks");
+        assert_eq!(repl.engine.clock.get_bpm(), DEFAULT_BPM);
+    }
+
+    #[test]
+    fn test_scoped_reset() {
+        let mut repl = Repl::new().unwrap();
+        enter(&mut repl, "let bass = \"C2 G1\"");
+        enter(&mut repl, "track 2 play bass loop");
+
+        repl.reset(ResetScope::Tracks, true);
+        assert_eq!(repl.list_tracks(), "No active tracks");
+        assert_eq!(repl.interpreter.bindings().len(), 1);
+
+        enter(&mut repl, "track 2 play bass loop");
+        repl.reset(ResetScope::Vars, true);
+        assert!(repl.interpreter.bindings().is_empty());
+        assert_ne!(repl.list_tracks(), "No active tracks");
+        let (source, _) = repl.interpreter.definitions_source();
+        assert!(source.is_empty());
+    }
+
     fn run_statement(input: &str) -> bool {
         let program = parse_statements(input);
         if program.is_err() {
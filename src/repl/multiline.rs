@@ -0,0 +1,98 @@
+//! Detecting input that continues on the next line
+//!
+//! A line that opens a block (`fn f() {`, `loop {`) can't be parsed on its
+//! own, so the REPL keeps reading until every bracket is closed.
+
+/// Returns true if `input` has an unclosed `{`, `(`, `[` or `/*` comment.
+///
+/// Brackets inside strings and comments are ignored. Input with too many
+/// closing brackets counts as complete so the parser can report the error.
+pub fn is_incomplete(input: &str) -> bool {
+    let mut depth: i32 = 0;
+    let mut chars = input.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        match ch {
+            '"' => {
+                // Strings end at the closing quote or the end of the line
+                while let Some(c) = chars.next() {
+                    match c {
+                        '\\' => {
+                            chars.next();
+                        }
+                        '"' | '\n' => break,
+                        _ => {}
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'/') => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut closed = false;
+                while let Some(c) = chars.next() {
+                    if c == '*' && chars.peek() == Some(&'/') {
+                        chars.next();
+                        closed = true;
+                        break;
+                    }
+                }
+                if !closed {
+                    return true;
+                }
+            }
+            '{' | '(' | '[' => depth += 1,
+            '}' | ')' | ']' => {
+                depth -= 1;
+                if depth < 0 {
+                    return false;
+                }
+            }
+            _ => {}
+        }
+    }
+    depth > 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_complete_input() {
+        assert!(!is_incomplete("play \"C E G\" loop"));
+        assert!(!is_incomplete("fn up(p) { return p + 12 }"));
+        assert!(!is_incomplete("loop {\n  play C\n  wait 1\n}"));
+        assert!(!is_incomplete("let chords = [[C, E, G], [F, A, C]]"));
+        assert!(!is_incomplete(""));
+    }
+
+    #[test]
+    fn test_incomplete_input() {
+        assert!(is_incomplete("fn up(p) {"));
+        assert!(is_incomplete("loop {\n  play C"));
+        assert!(is_incomplete("track 1 {\n  if true {\n  }"));
+        assert!(is_incomplete("play stack(\"C E\","));
+        assert!(is_incomplete("/* notes for\nlater"));
+    }
+
+    #[test]
+    fn test_brackets_in_strings_and_comments_are_ignored() {
+        assert!(!is_incomplete("play \"[C E] {G B}\" loop"));
+        assert!(!is_incomplete("play \"C \\\" {\""));
+        assert!(!is_incomplete("play C // opens a {"));
+        assert!(!is_incomplete("play C /* { */"));
+        assert!(is_incomplete("loop { // close with }"));
+    }
+
+    #[test]
+    fn test_extra_closers_are_complete() {
+        assert!(!is_incomplete("play C }"));
+        assert!(!is_incomplete("} {"));
+    }
+}
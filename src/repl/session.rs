@@ -0,0 +1,248 @@
+//! Writing the live session back out as a runnable `.cadence` file
+
+use crate::audio::midi::TrackPatch;
+use crate::parser::{parse_statements, Expression, Statement};
+use crate::types::Waveform;
+
+/// Settings and looping expression of one track
+#[derive(Debug, Clone, Default)]
+pub struct TrackState {
+    pub track_id: usize,
+    /// Volume from 0.0 to 1.0
+    pub volume: Option<f32>,
+    pub waveform: Option<Waveform>,
+    pub patch: Option<TrackPatch>,
+    /// Expression the track is looping
+    pub looping: Option<Expression>,
+    /// Statement that started the loop, as entered
+    pub source: Option<String>,
+}
+
+/// Everything needed to reproduce a session
+#[derive(Debug, Clone, Default)]
+pub struct Session {
+    pub tempo: f32,
+    pub capo: i32,
+    /// Top-level definitions, already written as source
+    pub definitions: String,
+    /// Command aliases: (name, the commands it runs)
+    pub aliases: Vec<(String, String)>,
+    pub tracks: Vec<TrackState>,
+}
+
+impl Session {
+    /// Render the session as source. Returns the text and a warning for each
+    /// part that could only be written as a comment.
+    pub fn render(&self) -> (String, Vec<String>) {
+        let mut output = String::from("// Saved Cadence session\n\n");
+        let mut warnings = Vec::new();
+
+        output.push_str(&format!("tempo {}\n", self.tempo.round() as i32));
+        if self.capo != 0 {
+            output.push_str(&format!("capo {}\n", self.capo));
+        }
+
+        if !self.definitions.is_empty() {
+            output.push('\n');
+            output.push_str(&self.definitions);
+        }
+
+        if !self.aliases.is_empty() {
+            output.push('\n');
+            for (name, source) in &self.aliases {
+                let alias = Statement::Alias {
+                    name: name.clone(),
+                    source: source.clone(),
+                };
+                output.push_str(&format!("{}\n", alias));
+            }
+        }
+
+        let mut tracks = self.tracks.clone();
+        tracks.sort_by_key(|t| t.track_id);
+        for track in &tracks {
+            let lines = track_lines(track, &mut warnings);
+            if !lines.is_empty() {
+                output.push('\n');
+                for line in lines {
+                    output.push_str(&line);
+                    output.push('\n');
+                }
+            }
+        }
+
+        (output, warnings)
+    }
+}
+
+fn track_lines(track: &TrackState, warnings: &mut Vec<String>) -> Vec<String> {
+    let id = track.track_id;
+    let mut lines = Vec::new();
+
+    if let Some(volume) = track.volume {
+        lines.push(format!(
+            "track {} volume {}",
+            id,
+            (volume * 100.0).round() as i32
+        ));
+    }
+    if let Some(waveform) = track.waveform {
+        lines.push(format!("track {} waveform \"{}\"", id, waveform.name()));
+    }
+    if let Some(patch) = &track.patch {
+        if let Some((msb, lsb)) = patch.bank {
+            lines.push(format!("track {} midi bank {} {}", id, msb, lsb));
+        }
+        if let Some(program) = patch.program {
+            lines.push(format!("track {} midi program {}", id, program));
+        }
+        if patch.velocity_curve != Default::default() {
+            lines.push(format!(
+                "track {} midi vel_curve {}",
+                id, patch.velocity_curve
+            ));
+        }
+    }
+
+    if let Some(expression) = &track.looping {
+        let play = format!("track {} play {} loop", id, expression);
+        let expected = Statement::Track {
+            id,
+            body: Box::new(Statement::Play {
+                target: expression.clone(),
+                looping: true,
+                queue_mode: None,
+                duration: None,
+            }),
+        };
+        let round_trips =
+            parse_statements(&play).is_ok_and(|program| program.statements == [expected]);
+        if round_trips {
+            lines.push(play);
+        } else {
+            // Keep what was typed so it can be fixed up by hand
+            let text = track.source.as_deref().unwrap_or(&play);
+            for line in text.lines() {
+                lines.push(format!("// {}", line));
+            }
+            warnings.push(format!(
+                "Track {} plays an expression that can't be written as source; saved as a comment",
+                id
+            ));
+        }
+    }
+
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::midi::VelocityCurve;
+    use crate::parser::{parse, parse_spanned_statements, Interpreter, InterpreterAction};
+
+    #[test]
+    fn test_render_session() {
+        let session = Session {
+            tempo: 96.0,
+            capo: 2,
+            definitions: "let bass = \"C2 G1\"\n".to_string(),
+            aliases: vec![("drop".to_string(), "stop 1; play \"C\"".to_string())],
+            tracks: vec![
+                TrackState {
+                    track_id: 2,
+                    volume: Some(0.4),
+                    waveform: Some(Waveform::Saw),
+                    patch: Some(TrackPatch {
+                        program: Some(33),
+                        velocity_curve: VelocityCurve::Soft,
+                        ..TrackPatch::default()
+                    }),
+                    looping: Some(parse("bass").unwrap()),
+                    source: None,
+                },
+                TrackState {
+                    track_id: 1,
+                    looping: Some(parse("fast(\"C E G\", 2)").unwrap()),
+                    ..TrackState::default()
+                },
+            ],
+        };
+
+        let (text, warnings) = session.render();
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        assert_eq!(
+            text,
+            "// Saved Cadence session\n\n\
+             tempo 96\ncapo 2\n\n\
+             let bass = \"C2 G1\"\n\n\
+             alias \"drop\" = \"stop 1; play \\\"C\\\"\"\n\n\
+             track 1 play fast(\"C E G\", 2) loop\n\n\
+             track 2 volume 40\n\
+             track 2 waveform \"saw\"\n\
+             track 2 midi program 33\n\
+             track 2 midi vel_curve soft\n\
+             track 2 play bass loop\n"
+        );
+    }
+
+    #[test]
+    fn test_saved_session_replays_the_same_actions() {
+        let session = Session {
+            tempo: 120.0,
+            definitions: "let lead = \"C E G\"\n".to_string(),
+            aliases: vec![("hush".to_string(), "stop".to_string())],
+            tracks: vec![TrackState {
+                track_id: 3,
+                volume: Some(0.5),
+                looping: Some(parse("rev(lead)").unwrap()),
+                ..TrackState::default()
+            }],
+            ..Session::default()
+        };
+        let (text, _) = session.render();
+
+        let mut interpreter = Interpreter::new();
+        let program = parse_spanned_statements(&text).unwrap();
+        interpreter.run_spanned_program(&program, &text).unwrap();
+
+        let actions = interpreter.take_actions();
+        assert!(matches!(actions[0], InterpreterAction::SetTempo(bpm) if bpm == 120.0));
+        assert!(matches!(
+            &actions[1],
+            InterpreterAction::DefineAlias { name, source } if name == "hush" && source == "stop"
+        ));
+        assert!(matches!(
+            actions[2],
+            InterpreterAction::SetVolume { volume, track_id: 3 } if volume == 0.5
+        ));
+        match &actions[3] {
+            InterpreterAction::PlayExpression {
+                expression,
+                looping: true,
+                track_id: 3,
+                ..
+            } => assert_eq!(expression, &parse("rev(lead)").unwrap()),
+            other => panic!("Expected a looping play, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_unprintable_play_is_commented() {
+        // Chords inside patterns don't print back as mini-notation
+        let session = Session {
+            tempo: 120.0,
+            tracks: vec![TrackState {
+                track_id: 1,
+                looping: Some(parse("\"[C,E,G] D\"").unwrap()),
+                source: Some("play \"[C,E,G] D\" loop".to_string()),
+                ..TrackState::default()
+            }],
+            ..Session::default()
+        };
+
+        let (text, warnings) = session.render();
+        assert!(text.ends_with("\n// play \"[C,E,G] D\" loop\n"), "{}", text);
+        assert_eq!(warnings.len(), 1);
+    }
+}
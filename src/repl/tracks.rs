@@ -0,0 +1,203 @@
+//! Table rendering for the `tracks` command
+
+use crate::audio::event_dispatcher::TrackStatus;
+use crate::types::AdsrParams;
+use std::collections::HashMap;
+
+/// Longest expression shown before it is cut off with an ellipsis
+const MAX_EXPRESSION_WIDTH: usize = 32;
+
+/// Render one row per track: mode, source expression, and derived properties
+pub fn format_tracks(
+    statuses: &[TrackStatus],
+    sources: &HashMap<usize, String>,
+    max_tracks: usize,
+) -> String {
+    if statuses.is_empty() {
+        return "No active tracks".to_string();
+    }
+
+    let header = [
+        "Track",
+        "Mode",
+        "Expression",
+        "Vol",
+        "Wave",
+        "Pan",
+        "Env",
+        "Next",
+    ]
+    .map(String::from);
+    let rows: Vec<[String; 8]> = statuses
+        .iter()
+        .map(|status| format_row(status, sources.get(&status.track_id)))
+        .collect();
+
+    let mut widths = header.clone().map(|h| h.chars().count());
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let mut output = format!("🎛️  Active Tracks ({}/{}):\n", statuses.len(), max_tracks);
+    for row in std::iter::once(&header).chain(&rows) {
+        let cells: Vec<String> = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, &width)| pad(cell, width))
+            .collect();
+        output.push_str(&format!("  {}\n", cells.join("  ").trim_end()));
+    }
+    output
+}
+
+fn format_row(status: &TrackStatus, source: Option<&String>) -> [String; 8] {
+    let mode = if status.queued {
+        "queued"
+    } else if status.looping {
+        "loop"
+    } else {
+        "one-shot"
+    };
+    let envelope = status.envelope.map(|env| {
+        AdsrParams::preset_name(env)
+            .map(String::from)
+            .unwrap_or_else(|| "custom".to_string())
+    });
+
+    [
+        status.track_id.to_string(),
+        mode.to_string(),
+        source.map_or_else(|| "-".to_string(), |s| truncate(s, MAX_EXPRESSION_WIDTH)),
+        or_dash(status.volume.map(|v| format!("{:.2}", v))),
+        or_dash(status.waveform.map(|w| format!("{:?}", w).to_lowercase())),
+        or_dash(status.pan.map(|p| format!("{:+.2}", p))),
+        or_dash(envelope),
+        or_dash(
+            status
+                .next_event_in
+                .map(|b| format!("in {} beats", trim_beats(b))),
+        ),
+    ]
+}
+
+/// Put `text` on one line and cut it to at most `max` characters,
+/// ending in "…" if anything was dropped
+pub(crate) fn truncate(text: &str, max: usize) -> String {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if text.chars().count() <= max {
+        return text;
+    }
+    let kept: String = text.chars().take(max.saturating_sub(1)).collect();
+    format!("{}…", kept.trim_end())
+}
+
+/// Beats with at most two decimals and no trailing zeros (0.5, 1, 0.33)
+fn trim_beats(beats: f64) -> String {
+    let text = format!("{:.2}", beats);
+    text.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+fn or_dash(cell: Option<String>) -> String {
+    cell.unwrap_or_else(|| "-".to_string())
+}
+
+fn pad(cell: &str, width: usize) -> String {
+    let padding = width.saturating_sub(cell.chars().count());
+    format!("{}{}", cell, " ".repeat(padding))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Waveform;
+
+    fn looping_track(track_id: usize) -> TrackStatus {
+        TrackStatus {
+            track_id,
+            looping: true,
+            ..TrackStatus::default()
+        }
+    }
+
+    #[test]
+    fn test_format_tracks_row() {
+        let status = TrackStatus {
+            volume: Some(0.8),
+            waveform: Some(Waveform::Saw),
+            pan: Some(-0.5),
+            envelope: Some((0.001, 0.15, 0.0, 0.1)),
+            next_event_in: Some(0.5),
+            ..looping_track(2)
+        };
+        let sources = HashMap::from([(2, "play \"C E G\" loop".to_string())]);
+
+        let output = format_tracks(&[status], &sources, 16);
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[0], "🎛️  Active Tracks (1/16):");
+        assert!(lines[1].starts_with("  Track  Mode  Expression"));
+        let row = lines[2];
+        for cell in [
+            "2 ",
+            "loop",
+            "play \"C E G\" loop",
+            "0.80",
+            "saw",
+            "-0.50",
+            "pluck",
+            "in 0.5 beats",
+        ] {
+            assert!(row.contains(cell), "missing {:?} in {:?}", cell, row);
+        }
+    }
+
+    #[test]
+    fn test_format_tracks_modes_and_missing_properties() {
+        let queued = TrackStatus {
+            queued: true,
+            ..looping_track(1)
+        };
+        let one_shot = TrackStatus {
+            track_id: 3,
+            envelope: Some((0.5, 0.1, 0.5, 0.5)),
+            next_event_in: Some(2.0),
+            ..TrackStatus::default()
+        };
+
+        let output = format_tracks(&[queued, one_shot], &HashMap::new(), 16);
+        let lines: Vec<&str> = output.lines().collect();
+        assert!(lines[2].contains("queued"));
+        assert!(lines[3].contains("one-shot"));
+        assert!(lines[3].contains("custom"));
+        assert!(lines[3].contains("in 2 beats"));
+        // Columns line up: every row starts its Mode cell at the same offset
+        let mode_column = lines[1].find("Mode").unwrap();
+        assert_eq!(lines[2].find("queued"), Some(mode_column));
+        assert_eq!(lines[3].find("one-shot"), Some(mode_column));
+    }
+
+    #[test]
+    fn test_format_tracks_truncates_long_expressions() {
+        let source = format!("play \"{}\" loop", "C E G B ".repeat(10));
+        let sources = HashMap::from([(1, source)]);
+
+        let output = format_tracks(&[looping_track(1)], &sources, 16);
+        let row = output.lines().nth(2).unwrap();
+        assert!(row.contains("play \"C E G B C E G B C E G B C…"));
+    }
+
+    #[test]
+    fn test_format_tracks_empty() {
+        assert_eq!(format_tracks(&[], &HashMap::new(), 16), "No active tracks");
+    }
+
+    #[test]
+    fn test_truncate() {
+        assert_eq!(truncate("C E G", 10), "C E G");
+        assert_eq!(truncate("C E G B D", 6), "C E G…");
+        assert_eq!(truncate("ééééé", 3), "éé…");
+        // Blocks entered over several lines show on one
+        assert_eq!(truncate("loop {\n  play C\n}", 20), "loop { play C }");
+    }
+}
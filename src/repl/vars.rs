@@ -0,0 +1,128 @@
+//! Listing for the `vars` command
+
+use crate::parser::Value;
+use crate::repl::tracks::truncate;
+
+/// Longest value preview shown before it is cut off with an ellipsis
+const MAX_PREVIEW_WIDTH: usize = 40;
+
+/// Render one line per binding whose name starts with `prefix`: name, type
+/// and a short preview of the value. `bindings` should already be sorted.
+pub fn format_vars(bindings: &[(String, Value)], prefix: &str) -> String {
+    let rows: Vec<[String; 3]> = bindings
+        .iter()
+        .filter(|(name, _)| name.starts_with(prefix))
+        .map(|(name, value)| {
+            [
+                name.clone(),
+                value.type_name().to_string(),
+                truncate(&preview(value), MAX_PREVIEW_WIDTH),
+            ]
+        })
+        .collect();
+
+    if rows.is_empty() {
+        return if prefix.is_empty() {
+            "No variables defined".to_string()
+        } else {
+            format!("No variables starting with '{}'", prefix)
+        };
+    }
+
+    let mut widths = [0; 2];
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let mut output = format!("📦 Variables ({}):\n", rows.len());
+    for [name, kind, value] in &rows {
+        output.push_str(&format!(
+            "  {:name_width$}  {:kind_width$}  {}\n",
+            name,
+            kind,
+            value,
+            name_width = widths[0],
+            kind_width = widths[1]
+        ));
+    }
+    output
+}
+
+/// Patterns as mini-notation, chords as symbols and functions as signatures
+fn preview(value: &Value) -> String {
+    match value {
+        Value::Chord(chord) => chord.to_symbol(),
+        Value::Function { name, params, .. } => format!("fn {}({})", name, params.join(", ")),
+        // Its expression couldn't be evaluated; show what was written
+        Value::Thunk { expression, .. } => expression.to_string(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{parse_statements, Interpreter};
+
+    fn bindings(source: &str) -> Vec<(String, Value)> {
+        let mut interpreter = Interpreter::new();
+        interpreter.set_variable("_beat", Value::Number(0));
+        interpreter
+            .run_program(&parse_statements(source).unwrap())
+            .unwrap();
+        interpreter.bindings()
+    }
+
+    #[test]
+    fn test_format_vars() {
+        let bindings = bindings(
+            "let bass = \"C2 _ G1\"\nlet chords = [C, E, G, B]\nfn up(p, n) {\n  return p + n\n}\nlet bars = 4\nlet root = D",
+        );
+
+        let output = format_vars(&bindings, "");
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[0], "📦 Variables (5):");
+        // Sorted by name, with the host's `_beat` left out
+        assert_eq!(lines[1], "  bars    number    4");
+        assert_eq!(lines[2], "  bass    pattern   \"C2 _ G1\"");
+        assert_eq!(lines[3], "  chords  chord     Cmaj7");
+        assert_eq!(lines[4], "  root    note      D");
+        assert_eq!(lines[5], "  up      function  fn up(p, n)");
+    }
+
+    #[test]
+    fn test_format_vars_prefix_and_truncation() {
+        let long = format!("let melody = \"{}\"\nlet bars = 4", "C E G B ".repeat(10));
+        let bindings = bindings(&long);
+
+        let output = format_vars(&bindings, "me");
+        assert_eq!(output.lines().count(), 2);
+        assert!(output.contains("melody"));
+        assert!(!output.contains("bars"));
+        assert!(output.trim_end().ends_with('…'));
+
+        assert_eq!(
+            format_vars(&bindings, "x"),
+            "No variables starting with 'x'"
+        );
+        assert_eq!(format_vars(&[], ""), "No variables defined");
+    }
+
+    #[test]
+    fn test_unset_removes_binding() {
+        let mut interpreter = Interpreter::new();
+        let program = parse_statements("let a = C\nlet b = 2").unwrap();
+        interpreter.run_program(&program).unwrap();
+
+        assert!(interpreter.unset("a"));
+        assert!(!interpreter.unset("a"));
+        let output = format_vars(&interpreter.bindings(), "");
+        assert!(!output.contains("  a "));
+        assert!(output.contains("  b "));
+        // A saved session doesn't bring it back
+        let (source, _) = interpreter.definitions_source();
+        assert_eq!(source, "let b = 2\n");
+    }
+}
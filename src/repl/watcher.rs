@@ -1,11 +1,19 @@
 use crossbeam_channel::Sender;
 use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
-use std::path::Path;
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// How long a file must be quiet after a change before it is reloaded.
+/// Editors often write a file in several steps, each firing its own event.
+pub const DEBOUNCE: Duration = Duration::from_millis(150);
 
 /// A simple file watcher that runs on a background thread (via notify's internal threads)
 /// and sends events to a channel.
 pub struct FileWatcher {
     watcher: RecommendedWatcher,
+    /// Watched files, as absolute paths
+    paths: BTreeSet<PathBuf>,
 }
 
 impl FileWatcher {
@@ -16,17 +24,163 @@ impl FileWatcher {
             let _ = tx.send(res);
         })?;
 
-        Ok(Self { watcher })
+        Ok(Self {
+            watcher,
+            paths: BTreeSet::new(),
+        })
+    }
+
+    /// Add a path to be watched. Returns false if it was already watched.
+    pub fn watch<P: AsRef<Path>>(&mut self, path: P) -> notify::Result<bool> {
+        let path = absolute(path.as_ref());
+        if self.paths.contains(&path) {
+            return Ok(false);
+        }
+        self.watcher.watch(&path, RecursiveMode::NonRecursive)?;
+        self.paths.insert(path);
+        Ok(true)
+    }
+
+    /// Remove a path from being watched. Returns the path as it was watched.
+    pub fn unwatch<P: AsRef<Path>>(&mut self, path: P) -> notify::Result<PathBuf> {
+        let path = absolute(path.as_ref());
+        if !self.paths.contains(&path) {
+            return Err(notify::Error::watch_not_found().add_path(path));
+        }
+        // The file may be gone already, in which case notify has dropped it too
+        let _ = self.watcher.unwatch(&path);
+        self.paths.remove(&path);
+        Ok(path)
     }
 
-    /// Add a path to be watched
-    pub fn watch<P: AsRef<Path>>(&mut self, path: P) -> notify::Result<()> {
-        self.watcher
-            .watch(path.as_ref(), RecursiveMode::NonRecursive)
+    /// Whether `path` is being watched
+    pub fn is_watched<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.paths.contains(&absolute(path.as_ref()))
     }
 
-    /// Remove a path from being watched
-    pub fn unwatch<P: AsRef<Path>>(&mut self, path: P) -> notify::Result<()> {
-        self.watcher.unwatch(path.as_ref())
+    /// Watched paths, sorted
+    pub fn paths(&self) -> impl Iterator<Item = &Path> {
+        self.paths.iter().map(PathBuf::as_path)
+    }
+}
+
+/// The canonical form of `path`, or the absolute one if it doesn't exist
+fn absolute(path: &Path) -> PathBuf {
+    path.canonicalize()
+        .or_else(|_| std::path::absolute(path))
+        .unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Collapses bursts of change events into one reload per file.
+///
+/// Each event pushes the file's reload back by [`DEBOUNCE`]; the file is due
+/// once no event has arrived for that long.
+#[derive(Debug, Default)]
+pub struct Debouncer {
+    pending: HashMap<PathBuf, Instant>,
+}
+
+impl Debouncer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Note that `path` changed at `now`
+    pub fn record(&mut self, path: PathBuf, now: Instant) {
+        self.pending.insert(path, now + DEBOUNCE);
+    }
+
+    /// Drop a pending reload, e.g. after the file is unwatched
+    pub fn forget(&mut self, path: &Path) {
+        self.pending.remove(path);
+    }
+
+    /// When the next file becomes due, if any change is pending
+    pub fn deadline(&self) -> Option<Instant> {
+        self.pending.values().min().copied()
+    }
+
+    /// Remove and return the files that have been quiet long enough, sorted
+    pub fn take_due(&mut self, now: Instant) -> Vec<PathBuf> {
+        let mut due: Vec<PathBuf> = self
+            .pending
+            .iter()
+            .filter(|(_, &deadline)| deadline <= now)
+            .map(|(path, _)| path.clone())
+            .collect();
+        for path in &due {
+            self.pending.remove(path);
+        }
+        due.sort();
+        due
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossbeam_channel::unbounded;
+
+    #[test]
+    fn test_debounce_collapses_bursts() {
+        let start = Instant::now();
+        let song = PathBuf::from("/songs/live.cadence");
+        let mut debouncer = Debouncer::new();
+
+        // An editor save: several events within a few milliseconds
+        for ms in [0, 2, 5, 40] {
+            debouncer.record(song.clone(), start + Duration::from_millis(ms));
+        }
+        assert_eq!(
+            debouncer.deadline(),
+            Some(start + Duration::from_millis(40) + DEBOUNCE)
+        );
+        assert!(debouncer.take_due(start + DEBOUNCE).is_empty());
+
+        let quiet = start + Duration::from_millis(40) + DEBOUNCE;
+        assert_eq!(debouncer.take_due(quiet), vec![song]);
+        assert!(debouncer.take_due(quiet + DEBOUNCE).is_empty());
+        assert_eq!(debouncer.deadline(), None);
+    }
+
+    #[test]
+    fn test_debounce_per_file_and_forget() {
+        let start = Instant::now();
+        let mut debouncer = Debouncer::new();
+        debouncer.record(PathBuf::from("/a.cadence"), start);
+        debouncer.record(PathBuf::from("/b.cadence"), start);
+        debouncer.record(PathBuf::from("/c.cadence"), start);
+        debouncer.forget(Path::new("/b.cadence"));
+
+        assert_eq!(
+            debouncer.take_due(start + DEBOUNCE),
+            vec![PathBuf::from("/a.cadence"), PathBuf::from("/c.cadence")]
+        );
+    }
+
+    #[test]
+    fn test_watch_list_and_removal() {
+        let dir = std::env::temp_dir().join(format!("cadence_watcher_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let song = dir.join("live.cadence");
+        std::fs::write(&song, "play C").unwrap();
+        let canonical = song.canonicalize().unwrap();
+
+        let (tx, _rx) = unbounded();
+        let mut watcher = FileWatcher::new(tx).unwrap();
+        let first = watcher.watch(&song).unwrap();
+        // The same file by another spelling is already watched
+        let again = watcher.watch(dir.join(".").join("live.cadence")).unwrap();
+        let listed: Vec<PathBuf> = watcher.paths().map(Path::to_path_buf).collect();
+        let removed = watcher.unwatch(&song);
+        let removed_again = watcher.unwatch(&song);
+        std::fs::remove_dir_all(&dir).ok();
+
+        assert!(first);
+        assert!(!again);
+        assert_eq!(listed, vec![canonical.clone()]);
+        assert_eq!(removed.unwrap(), canonical);
+        assert!(removed_again.is_err());
+        assert!(!watcher.is_watched(&song));
     }
 }
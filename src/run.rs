@@ -0,0 +1,258 @@
+//! Non-interactive runner: `cadence run <file>`
+//!
+//! Runs a script the way `load` would in the REPL, then either plays it in
+//! real time for a number of beats, renders those beats to a WAV file through
+//! the headless backend, or stops after running it (`--no-audio`), which is
+//! enough to check a script in CI. The exit code tells the caller whether the
+//! script parsed and ran.
+
+use crate::audio::clock::{ClockTick, TICKS_PER_BEAT};
+use crate::audio::headless::SAMPLE_RATE;
+use crate::audio::midi::MidiOutputHandle;
+use crate::engine::Engine;
+use crate::parser::{parse_spanned_statements, Interpreter, Value};
+use anyhow::{anyhow, Result};
+use colored::*;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// The script ran and playback finished
+pub const EXIT_OK: i32 = 0;
+/// Bad arguments, or a file couldn't be read or written
+pub const EXIT_USAGE: i32 = 1;
+/// The script has a syntax error
+pub const EXIT_PARSE_ERROR: i32 = 2;
+/// The script failed while running
+pub const EXIT_RUNTIME_ERROR: i32 = 3;
+
+/// Beats played or rendered when `--beats` isn't given
+pub const DEFAULT_BEATS: u32 = 16;
+
+pub const USAGE: &str = "Usage: cadence run <file> [--beats N] [--render out.wav] [--no-audio]";
+
+/// What `cadence run` should do with a script
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunOptions {
+    pub path: PathBuf,
+    /// How long to play or render
+    pub beats: u32,
+    /// Render offline to this WAV file instead of playing
+    pub render: Option<PathBuf>,
+    /// Only run the script; don't produce any sound
+    pub no_audio: bool,
+}
+
+impl RunOptions {
+    /// Parse the arguments that follow `run`
+    pub fn parse(args: &[String]) -> Result<Self> {
+        let mut path = None;
+        let mut beats = DEFAULT_BEATS;
+        let mut render = None;
+        let mut no_audio = false;
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--beats" => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| anyhow!("--beats needs a value"))?;
+                    beats = value
+                        .parse()
+                        .ok()
+                        .filter(|&beats| beats > 0)
+                        .ok_or_else(|| anyhow!("--beats must be a positive whole number"))?;
+                }
+                "--render" => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| anyhow!("--render needs a file"))?;
+                    render = Some(PathBuf::from(value));
+                }
+                "--no-audio" => no_audio = true,
+                flag if flag.starts_with("--") => return Err(anyhow!("Unknown option '{}'", flag)),
+                file if path.is_none() => path = Some(PathBuf::from(file)),
+                extra => return Err(anyhow!("Unexpected argument '{}'", extra)),
+            }
+        }
+
+        if no_audio && render.is_some() {
+            return Err(anyhow!("--render and --no-audio can't be used together"));
+        }
+        Ok(RunOptions {
+            path: path.ok_or_else(|| anyhow!("No file given"))?,
+            beats,
+            render,
+            no_audio,
+        })
+    }
+}
+
+/// Entry point for `cadence run`: parse `args` and run. Returns the exit code.
+pub fn main(args: &[String]) -> i32 {
+    match RunOptions::parse(args) {
+        Ok(options) => run(&options),
+        Err(e) => {
+            eprintln!("{} {}\n{}", "Error:".bright_red().bold(), e, USAGE);
+            EXIT_USAGE
+        }
+    }
+}
+
+/// Run a script as described by `options`. Returns the exit code.
+pub fn run(options: &RunOptions) -> i32 {
+    let source = match std::fs::read_to_string(&options.path) {
+        Ok(source) => source,
+        Err(e) => {
+            eprintln!(
+                "{} Failed to read '{}': {}",
+                "Error:".bright_red().bold(),
+                options.path.display(),
+                e
+            );
+            return EXIT_USAGE;
+        }
+    };
+
+    let program = match parse_spanned_statements(&source) {
+        Ok(program) => program,
+        Err(e) => {
+            eprintln!("{} {}", "Parse error:".bright_red().bold(), e);
+            return EXIT_PARSE_ERROR;
+        }
+    };
+
+    let mut interpreter = Interpreter::new();
+    interpreter.set_variable("_beat", Value::Number(0));
+    if let Err(e) = interpreter.run_spanned_program(&program, &source) {
+        eprintln!("{} {}", "Error:".bright_red().bold(), e);
+        return EXIT_RUNTIME_ERROR;
+    }
+    if options.no_audio {
+        return EXIT_OK;
+    }
+
+    let midi_handle = match MidiOutputHandle::new() {
+        Ok(handle) => Arc::new(handle),
+        Err(e) => {
+            eprintln!(
+                "{} MIDI initialization failed: {}",
+                "Error:".bright_red().bold(),
+                e
+            );
+            return EXIT_USAGE;
+        }
+    };
+
+    let result = match &options.render {
+        Some(path) => render(&mut interpreter, midi_handle, options.beats, path),
+        None => {
+            play(&mut interpreter, midi_handle, options.beats);
+            Ok(())
+        }
+    };
+    match result {
+        Ok(()) => EXIT_OK,
+        Err(e) => {
+            eprintln!("{} {}", "Error:".bright_red().bold(), e);
+            EXIT_USAGE
+        }
+    }
+}
+
+/// Hand what the script asked for to `engine`
+fn start(interpreter: &mut Interpreter, engine: &mut Engine) {
+    let env = interpreter.shared_environment();
+    for action in interpreter.take_actions() {
+        engine.execute_action(action, env.clone());
+    }
+    engine.schedule(interpreter.take_scheduled_events());
+}
+
+/// Play through the sound card for `beats` beats
+fn play(interpreter: &mut Interpreter, midi_handle: Arc<MidiOutputHandle>, beats: u32) {
+    let mut engine = Engine::new(midi_handle);
+    start(interpreter, &mut engine);
+    engine.start_clock();
+
+    let seconds = beats as f64 * 60.0 / engine.clock.get_bpm() as f64;
+    std::thread::sleep(Duration::from_secs_f64(seconds));
+
+    engine.dispatcher_handle.stop_all();
+    let _ = engine.midi_handle.transport_stop();
+    // Let the last notes release
+    std::thread::sleep(Duration::from_millis(300));
+}
+
+/// Render `beats` beats offline and write them to a WAV file at `path`
+fn render(
+    interpreter: &mut Interpreter,
+    midi_handle: Arc<MidiOutputHandle>,
+    beats: u32,
+    path: &Path,
+) -> Result<()> {
+    let (mut engine, mut dispatcher, mut audio) = Engine::headless(midi_handle);
+    start(interpreter, &mut engine);
+
+    // Frames per tick aren't whole, so carry the remainder to the next tick
+    let mut frames_due = 0.0;
+    for tick in 0..beats as u64 * TICKS_PER_BEAT as u64 {
+        let bpm = engine.clock.get_bpm();
+        let tick_in_beat = (tick % TICKS_PER_BEAT as u64) as u8;
+        let beat_number = tick / TICKS_PER_BEAT as u64;
+        dispatcher.advance(&ClockTick {
+            beat: beat_number as f64 + tick_in_beat as f64 / TICKS_PER_BEAT as f64,
+            beat_number,
+            tick_in_beat,
+            bpm,
+            timestamp: Instant::now(),
+        });
+
+        frames_due += SAMPLE_RATE as f64 * 60.0 / (bpm as f64 * TICKS_PER_BEAT as f64);
+        let frames = frames_due as usize;
+        frames_due -= frames as f64;
+        audio.render(frames);
+    }
+
+    audio.write_wav(path)?;
+    println!(
+        "💾 Rendered {} beats ({:.1}s) to {}",
+        beats,
+        audio.frames() as f64 / SAMPLE_RATE as f64,
+        path.display().to_string().green()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn test_parse_run_options() {
+        let options = RunOptions::parse(&args("set.cadence --beats 64 --render out.wav")).unwrap();
+        assert_eq!(options.path, PathBuf::from("set.cadence"));
+        assert_eq!(options.beats, 64);
+        assert_eq!(options.render, Some(PathBuf::from("out.wav")));
+        assert!(!options.no_audio);
+
+        let options = RunOptions::parse(&args("--no-audio set.cadence")).unwrap();
+        assert_eq!(options.beats, DEFAULT_BEATS);
+        assert!(options.no_audio);
+    }
+
+    #[test]
+    fn test_parse_run_options_errors() {
+        assert!(RunOptions::parse(&args("")).is_err());
+        assert!(RunOptions::parse(&args("set.cadence --beats")).is_err());
+        assert!(RunOptions::parse(&args("set.cadence --beats 0")).is_err());
+        assert!(RunOptions::parse(&args("set.cadence --loud")).is_err());
+        assert!(RunOptions::parse(&args("a.cadence b.cadence")).is_err());
+        assert!(RunOptions::parse(&args("a.cadence --no-audio --render x.wav")).is_err());
+    }
+}
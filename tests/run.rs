@@ -0,0 +1,67 @@
+//! `cadence run` end to end, rendering through the headless backend
+
+use cadence::run::{run, RunOptions, EXIT_OK, EXIT_PARSE_ERROR, EXIT_RUNTIME_ERROR, EXIT_USAGE};
+use std::path::PathBuf;
+
+fn script(name: &str, source: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("cadence_run_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(name);
+    std::fs::write(&path, source).unwrap();
+    path
+}
+
+fn options(path: PathBuf) -> RunOptions {
+    RunOptions {
+        path,
+        beats: 4,
+        render: None,
+        no_audio: true,
+    }
+}
+
+#[test]
+fn test_render_writes_wav() {
+    let path = script("render.cadence", "tempo 120\nplay \"C E G\" loop\n");
+    let wav = path.with_extension("wav");
+    let code = run(&RunOptions {
+        render: Some(wav.clone()),
+        no_audio: false,
+        ..options(path)
+    });
+    assert_eq!(code, EXIT_OK);
+
+    let bytes = std::fs::read(&wav).unwrap();
+    std::fs::remove_file(&wav).ok();
+    assert_eq!(&bytes[..4], b"RIFF");
+    assert_eq!(&bytes[8..12], b"WAVE");
+
+    // 4 beats at 120 BPM is two seconds of 16-bit stereo
+    let data = &bytes[44..];
+    let frames = data.len() / 4;
+    assert!((frames as i64 - 88_200).abs() <= 1, "{} frames", frames);
+    let loudest = data
+        .chunks_exact(2)
+        .map(|s| i16::from_le_bytes([s[0], s[1]]).unsigned_abs())
+        .max()
+        .unwrap();
+    assert!(loudest > 1000, "render is silent");
+}
+
+#[test]
+fn test_no_audio_checks_script() {
+    let path = script("check.cadence", "let riff = \"C E G\"\nplay riff loop\n");
+    assert_eq!(run(&options(path)), EXIT_OK);
+}
+
+#[test]
+fn test_exit_codes_for_errors() {
+    let parse_error = script("parse.cadence", "let x = [C, E\n");
+    assert_eq!(run(&options(parse_error)), EXIT_PARSE_ERROR);
+
+    let runtime_error = script("runtime.cadence", "play missing_variable\n");
+    assert_eq!(run(&options(runtime_error)), EXIT_RUNTIME_ERROR);
+
+    let missing = options(PathBuf::from("/nonexistent/set.cadence"));
+    assert_eq!(run(&missing), EXIT_USAGE);
+}